@@ -0,0 +1,664 @@
+//! Detailed plan statistics for `drafter stats`, beyond [`crate::sim::stats`].
+//!
+//! Plans only hold key events and waits, so paragraphs, corrections and navigation are
+//! reconstructed by replaying the keys into a simple editor model, like
+//! [`crate::sim::simulate_typed_text`] does.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::editor::CursorPosition;
+use crate::keyboard::{
+    KeyStroke, KEY_BACKSPACE, KEY_CAPSLOCK, KEY_DELETE, KEY_END, KEY_HOME, KEY_LEFT, KEY_LEFTCTRL,
+    KEY_LEFTSHIFT, KEY_RIGHT, KEY_RIGHTCTRL, KEY_RIGHTSHIFT,
+};
+use crate::layout::{Decoded, KeyDecoder, KeyboardLayout};
+use crate::model::{Action, KeyState, ParagraphBreaks, Plan};
+
+/// Upper bounds (exclusive) of the inter-key delay histogram buckets; a last, open bucket holds
+/// longer delays.
+pub const DELAY_BUCKET_BOUNDS_MS: [u64; 8] = [50, 100, 150, 200, 300, 500, 1000, 2000];
+
+/// Characters shown before the cursor for each pause.
+const PAUSE_CONTEXT_CHARS: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnalysisOptions {
+    /// Length of the windows typing speed is reported for.
+    pub wpm_window_ms: u64,
+    /// How many of the longest pauses to report.
+    pub longest_pauses: usize,
+    /// Interval of the rolling typing speed samples, each over the last `wpm_window_ms`; no
+    /// samples if `None`.
+    pub rolling_wpm_step_ms: Option<u64>,
+    /// Report every pause at least this long, in order.
+    pub pause_threshold_ms: Option<u64>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            wpm_window_ms: 30_000,
+            longest_pauses: 5,
+            rolling_wpm_step_ms: None,
+            pause_threshold_ms: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlanAnalysis {
+    /// Total wait time.
+    pub total_ms: u64,
+    /// Characters typed, including ones deleted later.
+    pub typed_chars: usize,
+    /// Characters deleted with Backspace or Delete.
+    pub deleted_chars: usize,
+    pub paragraphs: Vec<ParagraphTiming>,
+    pub wpm_windows: Vec<WpmWindow>,
+    /// Typing speed over the last `wpm_window_ms`, every `rolling_wpm_step_ms`.
+    pub rolling_wpm: Vec<WpmSample>,
+    pub corrections: CorrectionCounts,
+    /// Longest gaps between key presses, longest first.
+    pub longest_pauses: Vec<Pause>,
+    /// Gaps between key presses of at least `pause_threshold_ms`, in order.
+    pub pauses: Vec<Pause>,
+    pub navigation: NavigationTotals,
+    /// Gaps between key presses (modifiers aside), bucketed by [`DELAY_BUCKET_BOUNDS_MS`].
+    pub delay_histogram: Vec<DelayBucket>,
+}
+
+/// Time spent with the cursor in one paragraph of the final text (see
+/// [`crate::planner::split_paragraphs`]), including revisits to correct it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParagraphTiming {
+    /// Characters in the final paragraph.
+    pub chars: usize,
+    pub ms: u64,
+    /// `chars` at five characters per word, over `ms`.
+    pub wpm: f64,
+}
+
+/// Typing speed in one window of time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WpmWindow {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Characters typed in the window, including ones deleted later.
+    pub typed_chars: usize,
+    pub wpm: f64,
+}
+
+/// Typing speed over the window ending at `at_ms` (shorter at the start of the plan).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WpmSample {
+    pub at_ms: u64,
+    /// Characters typed in the window, including ones deleted later.
+    pub typed_chars: usize,
+    pub wpm: f64,
+}
+
+/// Corrections, i.e. text deleted and retyped in one go, by when they were made and by kind.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CorrectionCounts {
+    /// Made at the end of the text, right after the mistake.
+    pub immediate: usize,
+    /// Made after moving the cursor back.
+    pub delayed: usize,
+    /// One character replaced, such as an adjacent-key typo.
+    pub substitution: usize,
+    /// Two neighbouring characters swapped.
+    pub transposition: usize,
+    /// Whitespace only, such as a double space.
+    pub spacing: usize,
+    /// A different word, such as a word variant or an LLM-proposed mistake.
+    pub word: usize,
+    /// Text spanning several words, such as a phrase alternative or a draft edit.
+    pub phrase: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Pause {
+    /// When the pause started.
+    pub at_ms: u64,
+    pub ms: u64,
+    /// The text just before the cursor.
+    pub context: String,
+    /// Where the cursor was in the text typed so far.
+    pub cursor: CursorPosition,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NavigationTotals {
+    /// Left/Right presses moving by one character.
+    pub arrow_presses: usize,
+    /// Ctrl+Left/Right presses.
+    pub word_jumps: usize,
+    /// Home/End presses (to the edge of the line, or of the text with Ctrl).
+    pub line_jumps: usize,
+    /// Characters the cursor moved over.
+    pub chars_moved: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DelayBucket {
+    pub min_ms: u64,
+    /// Exclusive; `None` for the last bucket.
+    pub max_ms: Option<u64>,
+    pub count: usize,
+}
+
+/// Replays `plan`'s key events and gathers its statistics.
+pub fn analyze(plan: &Plan, options: &AnalysisOptions) -> Result<PlanAnalysis> {
+    let layout = KeyboardLayout::for_plan_config(&plan.config)?;
+    let mut replay = Replay::new(&layout, plan.config.paragraph_breaks, options);
+    for action in plan.actions.iter().flat_map(Action::expand) {
+        match action {
+            Action::Wait { ms } => replay.wait(ms),
+            Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            } => replay.press(keycode)?,
+            Action::Key {
+                keycode,
+                state: KeyState::Released,
+            } => replay.release(keycode),
+            Action::Modifiers { .. } | Action::Checkpoint { .. } | Action::KeyRepeat { .. } => {}
+        }
+    }
+    Ok(replay.finish())
+}
+
+/// Text deleted and retyped at one place.
+#[derive(Debug, Default)]
+struct Correction {
+    deleted_before: Vec<char>,
+    deleted_after: Vec<char>,
+    inserted: String,
+    at_end: bool,
+}
+
+impl Correction {
+    fn deleted(&self) -> String {
+        self.deleted_before
+            .iter()
+            .rev()
+            .chain(&self.deleted_after)
+            .collect()
+    }
+}
+
+struct Replay<'a> {
+    options: &'a AnalysisOptions,
+    decoder: KeyDecoder,
+    altgr_keycode: Option<u32>,
+    shift_down: bool,
+    ctrl_down: bool,
+    altgr_down: bool,
+
+    buf: Vec<char>,
+    cursor: usize,
+    /// Newlines before the cursor.
+    cursor_line: usize,
+    paragraph_breaks: ParagraphBreaks,
+    /// Where each paragraph but the first starts in `buf`, in order.
+    paragraph_starts: Vec<usize>,
+    correction: Option<Correction>,
+
+    now_ms: u64,
+    last_press_ms: Option<u64>,
+    paragraph_ms: Vec<u64>,
+    typed_at_ms: Vec<u64>,
+    deleted_chars: usize,
+    corrections: CorrectionCounts,
+    navigation: NavigationTotals,
+    longest_pauses: Vec<Pause>,
+    pauses: Vec<Pause>,
+    delay_counts: [usize; DELAY_BUCKET_BOUNDS_MS.len() + 1],
+}
+
+impl<'a> Replay<'a> {
+    fn new(
+        layout: &KeyboardLayout,
+        paragraph_breaks: ParagraphBreaks,
+        options: &'a AnalysisOptions,
+    ) -> Self {
+        let decoder = layout.decoder();
+        Self {
+            options,
+            paragraph_breaks,
+            altgr_keycode: decoder.altgr_keycode(),
+            decoder,
+            shift_down: false,
+            ctrl_down: false,
+            altgr_down: false,
+            buf: Vec::new(),
+            cursor: 0,
+            cursor_line: 0,
+            paragraph_starts: Vec::new(),
+            correction: None,
+            now_ms: 0,
+            last_press_ms: None,
+            paragraph_ms: Vec::new(),
+            typed_at_ms: Vec::new(),
+            deleted_chars: 0,
+            corrections: CorrectionCounts::default(),
+            navigation: NavigationTotals::default(),
+            longest_pauses: Vec::new(),
+            pauses: Vec::new(),
+            delay_counts: [0; DELAY_BUCKET_BOUNDS_MS.len() + 1],
+        }
+    }
+
+    fn wait(&mut self, ms: u64) {
+        self.now_ms = self.now_ms.saturating_add(ms);
+        let paragraph = self.paragraph_starts.partition_point(|&s| s <= self.cursor);
+        if self.paragraph_ms.len() <= paragraph {
+            self.paragraph_ms.resize(paragraph + 1, 0);
+        }
+        self.paragraph_ms[paragraph] += ms;
+    }
+
+    fn release(&mut self, keycode: u32) {
+        match keycode {
+            KEY_LEFTSHIFT | KEY_RIGHTSHIFT => self.shift_down = false,
+            KEY_LEFTCTRL | KEY_RIGHTCTRL => self.ctrl_down = false,
+            keycode if Some(keycode) == self.altgr_keycode => self.altgr_down = false,
+            _ => {}
+        }
+    }
+
+    fn press(&mut self, keycode: u32) -> Result<()> {
+        match keycode {
+            KEY_LEFTSHIFT | KEY_RIGHTSHIFT => self.shift_down = true,
+            KEY_LEFTCTRL | KEY_RIGHTCTRL => self.ctrl_down = true,
+            KEY_CAPSLOCK => self.decoder.toggle_caps_lock(),
+            keycode if Some(keycode) == self.altgr_keycode => self.altgr_down = true,
+            keycode => {
+                self.record_gap();
+                self.press_key(keycode)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the time since the previous (non-modifier) key press.
+    fn record_gap(&mut self) {
+        let Some(last) = self.last_press_ms.replace(self.now_ms) else {
+            return;
+        };
+        let ms = self.now_ms - last;
+        let bucket = DELAY_BUCKET_BOUNDS_MS.partition_point(|&bound| bound <= ms);
+        self.delay_counts[bucket] += 1;
+
+        let limit = self.options.longest_pauses;
+        let longest = limit > 0
+            && (self.longest_pauses.len() < limit || self.longest_pauses[limit - 1].ms < ms);
+        let over_threshold = self
+            .options
+            .pause_threshold_ms
+            .is_some_and(|threshold| ms >= threshold);
+        if !longest && !over_threshold {
+            return;
+        }
+        let context_start = self.cursor.saturating_sub(PAUSE_CONTEXT_CHARS);
+        let line_start = self.buf[..self.cursor]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map_or(0, |newline| newline + 1);
+        let pause = Pause {
+            at_ms: last,
+            ms,
+            context: self.buf[context_start..self.cursor].iter().collect(),
+            cursor: CursorPosition {
+                line: self.cursor_line + 1,
+                column: self.cursor - line_start + 1,
+            },
+        };
+        if over_threshold {
+            self.pauses.push(pause.clone());
+        }
+        if longest {
+            let at = self.longest_pauses.partition_point(|other| other.ms >= ms);
+            self.longest_pauses.insert(at, pause);
+            self.longest_pauses.truncate(limit);
+        }
+    }
+
+    fn press_key(&mut self, keycode: u32) -> Result<()> {
+        if matches!(
+            keycode,
+            KEY_LEFT | KEY_RIGHT | KEY_HOME | KEY_END | KEY_BACKSPACE | KEY_DELETE
+        ) {
+            self.decoder.reset();
+        }
+        match keycode {
+            KEY_LEFT | KEY_RIGHT | KEY_HOME | KEY_END => {
+                self.finish_correction();
+                let line_start = || {
+                    self.buf[..self.cursor]
+                        .iter()
+                        .rposition(|c| *c == '\n')
+                        .map_or(0, |newline| newline + 1)
+                };
+                let line_end = || {
+                    self.buf[self.cursor..]
+                        .iter()
+                        .position(|c| *c == '\n')
+                        .map_or(self.buf.len(), |newline| self.cursor + newline)
+                };
+                let target = match (keycode, self.ctrl_down) {
+                    (KEY_LEFT, true) => {
+                        crate::word_nav::ctrl_left(&self.buf, self.cursor, is_word_char)
+                    }
+                    (KEY_LEFT, false) => self.cursor.saturating_sub(1),
+                    (KEY_RIGHT, true) => {
+                        crate::word_nav::ctrl_right(&self.buf, self.cursor, is_word_char)
+                    }
+                    (KEY_RIGHT, false) => (self.cursor + 1).min(self.buf.len()),
+                    (KEY_HOME, true) => 0,
+                    (KEY_HOME, false) => line_start(),
+                    (_, true) => self.buf.len(),
+                    (_, false) => line_end(),
+                };
+                if matches!(keycode, KEY_HOME | KEY_END) {
+                    self.navigation.line_jumps += 1;
+                } else if self.ctrl_down {
+                    self.navigation.word_jumps += 1;
+                } else {
+                    self.navigation.arrow_presses += 1;
+                }
+                self.navigation.chars_moved += self.cursor.abs_diff(target);
+                let newlines = |chars: &[char]| chars.iter().filter(|c| **c == '\n').count();
+                if target < self.cursor {
+                    self.cursor_line -= newlines(&self.buf[target..self.cursor]);
+                } else {
+                    self.cursor_line += newlines(&self.buf[self.cursor..target]);
+                }
+                self.cursor = target;
+            }
+            KEY_BACKSPACE | KEY_DELETE => {
+                let deleting_before = keycode == KEY_BACKSPACE;
+                let pos = match deleting_before {
+                    true if self.cursor > 0 => self.cursor - 1,
+                    false if self.cursor < self.buf.len() => self.cursor,
+                    _ => return Ok(()),
+                };
+                if self
+                    .correction
+                    .as_ref()
+                    .is_some_and(|correction| !correction.inserted.is_empty())
+                {
+                    self.finish_correction();
+                }
+                let at_end = self.cursor == self.buf.len();
+                let correction = self.correction.get_or_insert_with(|| Correction {
+                    at_end,
+                    ..Default::default()
+                });
+
+                let c = self.buf.remove(pos);
+                if deleting_before {
+                    self.cursor_line -= usize::from(c == '\n');
+                    correction.deleted_before.push(c);
+                } else {
+                    correction.deleted_after.push(c);
+                }
+                self.cursor = pos;
+                self.deleted_chars += 1;
+                self.update_paragraph_starts(pos, c, false);
+            }
+            _ => {
+                if self.ctrl_down {
+                    return Err(anyhow!("stats do not support Ctrl+keycode {keycode}"));
+                }
+                let stroke = KeyStroke {
+                    keycode,
+                    shift: self.shift_down,
+                    altgr: self.altgr_down,
+                };
+                match self.decoder.feed(stroke) {
+                    Decoded::Char(c) => self.insert(c),
+                    Decoded::Pending => {}
+                    Decoded::Unknown => {
+                        return Err(anyhow!(
+                            "stats do not support keycode {keycode} (shift={}, altgr={})",
+                            self.shift_down,
+                            self.altgr_down
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, c: char) {
+        if let Some(correction) = &mut self.correction {
+            // At the end of the text, typing simply goes on after the corrected word (or run of
+            // spaces and punctuation).
+            let ends_token = correction.at_end
+                && correction
+                    .inserted
+                    .chars()
+                    .last()
+                    .is_some_and(|last| is_word_char(last) != is_word_char(c));
+            if ends_token {
+                self.finish_correction();
+            } else {
+                correction.inserted.push(c);
+            }
+        }
+        self.buf.insert(self.cursor, c);
+        self.update_paragraph_starts(self.cursor, c, true);
+        self.cursor += 1;
+        self.cursor_line += usize::from(c == '\n');
+        self.typed_at_ms.push(self.now_ms);
+    }
+
+    /// Keeps `paragraph_starts` in step after `c` was inserted or deleted at `pos`.
+    fn update_paragraph_starts(&mut self, pos: usize, c: char, inserted: bool) {
+        if c == '\n'
+            || pos
+                .checked_sub(1)
+                .is_some_and(|prev| self.buf[prev] == '\n')
+        {
+            self.paragraph_starts = paragraph_starts(&self.buf, self.paragraph_breaks);
+            return;
+        }
+        for start in self
+            .paragraph_starts
+            .iter_mut()
+            .filter(|start| **start > pos)
+        {
+            if inserted {
+                *start += 1;
+            } else {
+                *start -= 1;
+            }
+        }
+    }
+
+    fn finish_correction(&mut self) {
+        let Some(correction) = self.correction.take() else {
+            return;
+        };
+        if correction.at_end {
+            self.corrections.immediate += 1;
+        } else {
+            self.corrections.delayed += 1;
+        }
+        let deleted = correction.deleted();
+        let kind = match classify(&deleted, &correction.inserted) {
+            CorrectionKind::Substitution => &mut self.corrections.substitution,
+            CorrectionKind::Transposition => &mut self.corrections.transposition,
+            CorrectionKind::Spacing => &mut self.corrections.spacing,
+            CorrectionKind::Word => &mut self.corrections.word,
+            CorrectionKind::Phrase => &mut self.corrections.phrase,
+        };
+        *kind += 1;
+    }
+
+    fn finish(mut self) -> PlanAnalysis {
+        self.finish_correction();
+
+        let text: String = self.buf.iter().collect();
+        let paragraphs = crate::planner::split_paragraphs_with(&text, self.paragraph_breaks)
+            .iter()
+            .enumerate()
+            .map(|(idx, paragraph)| {
+                let chars = paragraph.chars().count();
+                let ms = self.paragraph_ms.get(idx).copied().unwrap_or(0);
+                ParagraphTiming {
+                    chars,
+                    ms,
+                    wpm: wpm(chars, ms),
+                }
+            })
+            .collect();
+
+        let typed_between = |start_ms: u64, end_ms: u64| {
+            // Characters typed at the very end of the plan count in the last window or sample.
+            let typed_before_end = if end_ms == self.now_ms {
+                self.typed_at_ms.len()
+            } else {
+                self.typed_at_ms.partition_point(|&at| at < end_ms)
+            };
+            typed_before_end - self.typed_at_ms.partition_point(|&at| at < start_ms)
+        };
+        let window = self.options.wpm_window_ms.max(1);
+        let wpm_windows = (0..self.now_ms.div_ceil(window))
+            .map(|idx| {
+                let start_ms = idx * window;
+                let end_ms = (start_ms + window).min(self.now_ms);
+                let typed_chars = typed_between(start_ms, end_ms);
+                WpmWindow {
+                    start_ms,
+                    end_ms,
+                    typed_chars,
+                    wpm: wpm(typed_chars, end_ms - start_ms),
+                }
+            })
+            .collect();
+
+        let rolling_wpm = self
+            .options
+            .rolling_wpm_step_ms
+            .map(|step| {
+                let step = step.max(1) as usize;
+                let mut times: Vec<u64> = (step as u64..self.now_ms).step_by(step).collect();
+                times.extend((self.now_ms > 0).then_some(self.now_ms));
+                times
+                    .into_iter()
+                    .map(|at_ms| {
+                        let start_ms = at_ms.saturating_sub(window);
+                        let typed_chars = typed_between(start_ms, at_ms);
+                        WpmSample {
+                            at_ms,
+                            typed_chars,
+                            wpm: wpm(typed_chars, at_ms - start_ms),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let delay_histogram = self
+            .delay_counts
+            .iter()
+            .enumerate()
+            .map(|(idx, &count)| DelayBucket {
+                min_ms: idx
+                    .checked_sub(1)
+                    .map_or(0, |prev| DELAY_BUCKET_BOUNDS_MS[prev]),
+                max_ms: DELAY_BUCKET_BOUNDS_MS.get(idx).copied(),
+                count,
+            })
+            .collect();
+
+        PlanAnalysis {
+            total_ms: self.now_ms,
+            typed_chars: self.typed_at_ms.len(),
+            deleted_chars: self.deleted_chars,
+            paragraphs,
+            wpm_windows,
+            rolling_wpm,
+            corrections: self.corrections,
+            longest_pauses: self.longest_pauses,
+            pauses: self.pauses,
+            navigation: self.navigation,
+            delay_histogram,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CorrectionKind {
+    Substitution,
+    Transposition,
+    Spacing,
+    Word,
+    Phrase,
+}
+
+fn classify(wrong: &str, correct: &str) -> CorrectionKind {
+    let is_blank = |text: &str| text.chars().all(char::is_whitespace);
+    let has_space = |text: &str| text.trim().contains(char::is_whitespace);
+    if is_blank(wrong) && is_blank(correct) {
+        return CorrectionKind::Spacing;
+    }
+    if has_space(wrong) || has_space(correct) {
+        return CorrectionKind::Phrase;
+    }
+
+    let wrong: Vec<char> = wrong.chars().collect();
+    let correct: Vec<char> = correct.chars().collect();
+    if wrong.len() != correct.len() {
+        return CorrectionKind::Word;
+    }
+    let differing: Vec<usize> = (0..wrong.len())
+        .filter(|&idx| wrong[idx] != correct[idx])
+        .collect();
+    match differing[..] {
+        [_] => CorrectionKind::Substitution,
+        [a, b] if b == a + 1 && wrong[a] == correct[b] && wrong[b] == correct[a] => {
+            CorrectionKind::Transposition
+        }
+        _ => CorrectionKind::Word,
+    }
+}
+
+/// Where each paragraph but the first starts, as in [`crate::planner::split_paragraphs_with`].
+fn paragraph_starts(buf: &[char], breaks: ParagraphBreaks) -> Vec<usize> {
+    let separator = if breaks == ParagraphBreaks::Single {
+        1
+    } else {
+        2
+    };
+    let mut starts = Vec::new();
+    let mut seen_text = false;
+    let mut newlines = 0usize;
+    for (idx, &c) in buf.iter().enumerate() {
+        if c == '\n' {
+            newlines += 1;
+            continue;
+        }
+        if seen_text && newlines >= separator {
+            starts.push(idx);
+        }
+        seen_text = true;
+        newlines = 0;
+    }
+    starts
+}
+
+fn wpm(chars: usize, ms: u64) -> f64 {
+    if ms == 0 {
+        return 0.0;
+    }
+    (chars as f64 / 5.0) / (ms as f64 / 60_000.0)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '\''
+}
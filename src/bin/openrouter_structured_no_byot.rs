@@ -36,6 +36,7 @@ mod llm_main {
         let options = ParagraphRephraseOptions {
             max_suggestions: 4,
             strength: RewriteStrength::Subtle,
+            ..Default::default()
         };
 
         let schema: Value = serde_json::from_str(PARAGRAPH_REPHRASE_JSON_SCHEMA)
@@ -102,7 +103,7 @@ mod llm_main {
 
         let content = response
             .choices
-            .get(0)
+            .first()
             .and_then(|c| c.message.content.as_deref())
             .context("missing choices[0].message.content")?;
 
@@ -0,0 +1,149 @@
+//! Finding the tokens of a text the planner types carefully: URLs, email addresses and
+//! code-like identifiers.
+//!
+//! People slow down for these and rarely mistype them, and a half-typed URL being fixed later
+//! is both unusual and confusing for editors that turn URLs into links as they are typed. The
+//! planner types each token in one go, so no correction lands inside it.
+
+use std::ops::Range;
+
+/// What kind of token a [`CarefulToken`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CarefulKind {
+    /// Starts with a scheme such as `https://` or `mailto:`, or with `www.`.
+    Url,
+    /// `name@domain.tld`.
+    Email,
+    /// `snake_case`, `camelCase` (not PascalCase), `path::to`, `call()`, `dotted.names` or `file/paths.rs`.
+    Identifier,
+}
+
+/// A token to type carefully, as a range of character (not byte) indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CarefulToken {
+    pub range: Range<usize>,
+    pub kind: CarefulKind,
+}
+
+const URL_PREFIXES: &[&str] = &[
+    "http://", "https://", "ftp://", "file://", "mailto:", "www.",
+];
+
+/// Characters before a token that belong to the sentence instead.
+const LEADING_PUNCTUATION: &[char] = &['(', '[', '{', '<', '"', '\'', '“', '‘'];
+
+/// Characters after a token that belong to the sentence instead; closing brackets only when the
+/// token does not open them.
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', '"', '\'', '”', '’', '>'];
+
+/// The careful tokens of `chars`, in order. Tokens are whitespace-separated words with the
+/// sentence punctuation around them left out.
+pub fn careful_tokens(chars: &[char]) -> Vec<CarefulToken> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let mut start = i;
+        while i < chars.len() && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        let mut end = i;
+        while start < end && LEADING_PUNCTUATION.contains(&chars[start]) {
+            start += 1;
+        }
+        while start < end && is_trailing_punctuation(&chars[start..end]) {
+            end -= 1;
+        }
+        if let Some(kind) = classify(&chars[start..end]) {
+            tokens.push(CarefulToken {
+                range: start..end,
+                kind,
+            });
+        }
+    }
+    tokens
+}
+
+/// Whether the last character of `token` belongs to the sentence around it.
+fn is_trailing_punctuation(token: &[char]) -> bool {
+    let last = token[token.len() - 1];
+    let open = match last {
+        ')' => '(',
+        ']' => '[',
+        '}' => '{',
+        _ => return TRAILING_PUNCTUATION.contains(&last),
+    };
+    let opened = token.iter().filter(|&&c| c == open).count();
+    let closed = token.iter().filter(|&&c| c == last).count();
+    closed > opened
+}
+
+fn classify(token: &[char]) -> Option<CarefulKind> {
+    if !token.iter().any(char::is_ascii_alphabetic) {
+        return None;
+    }
+    let text: String = token.iter().collect();
+    let lower = text.to_lowercase();
+    if URL_PREFIXES
+        .iter()
+        .any(|prefix| lower.len() > prefix.len() && lower.starts_with(prefix))
+    {
+        return Some(CarefulKind::Url);
+    }
+    if is_email(&text) {
+        return Some(CarefulKind::Email);
+    }
+    is_identifier(token, &text).then_some(CarefulKind::Identifier)
+}
+
+fn is_email(text: &str) -> bool {
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+    let labels: Vec<&str> = domain.split('.').collect();
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._%+-".contains(c))
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && labels
+            .last()
+            .is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+fn is_identifier(token: &[char], text: &str) -> bool {
+    // Not PascalCase, which names like YouTube or PowerPoint have too.
+    let camel = token[0].is_lowercase() && token.iter().any(|c| c.is_uppercase());
+    // Not `and/or`.
+    let path = text.starts_with('/')
+        || text.starts_with("~/")
+        || (text.contains('/') && text.contains('.'));
+    text.contains('_')
+        || camel
+        || path
+        || text.contains("::")
+        || text.ends_with("()")
+        || is_dotted(text)
+}
+
+/// `config.toml`, `example.com` or `self.value`: two or more names of at least two characters
+/// joined by dots, unlike abbreviations (`e.g`) and numbers (`3.14`).
+fn is_dotted(text: &str) -> bool {
+    let segments: Vec<&str> = text.split('.').collect();
+    segments.len() >= 2
+        && segments.iter().all(|segment| {
+            segment.chars().count() >= 2
+                && segment
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        })
+        && segments
+            .last()
+            .is_some_and(|last| last.starts_with(|c: char| c.is_alphabetic()))
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use serde::Deserialize;
+
+use crate::keyboard::KeyStroke;
+
+/// User-supplied overrides for how specific draft characters are produced.
+///
+/// Loaded from JSON (see [`CharMap::from_json_str`]) and applied by the planner before it checks
+/// which characters the layout can type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CharMap {
+    entries: BTreeMap<char, CharMapping>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CharMapping {
+    /// Replace the character in the draft with this text (e.g. `—` → `--`).
+    Replace(String),
+    /// Keep the character in the draft but type it with these keystrokes, relying on the editor
+    /// to produce it (like smart quotes).
+    Keys(Vec<KeyStroke>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawMapping {
+    Replace(String),
+    Keys { keys: Vec<RawKey> },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawKey {
+    keycode: u32,
+    #[serde(default)]
+    shift: bool,
+    #[serde(default)]
+    altgr: bool,
+}
+
+impl CharMap {
+    /// Parse a JSON object whose keys are single characters and whose values are either a
+    /// replacement string or `{"keys": [{"keycode": <evdev>, "shift": bool, "altgr": bool}]}`.
+    ///
+    /// ```json
+    /// { "—": "--", "→": "->", "€": { "keys": [{ "keycode": 18, "altgr": true }] } }
+    /// ```
+    pub fn from_json_str(json: &str) -> Result<Self> {
+        let raw: BTreeMap<String, RawMapping> =
+            serde_json::from_str(json).context("failed to parse charmap JSON")?;
+
+        let mut entries = BTreeMap::new();
+        for (key, mapping) in raw {
+            let mut chars = key.chars();
+            let c = match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(anyhow!("charmap key {key:?} must be a single character")),
+            };
+
+            let mapping = match mapping {
+                RawMapping::Replace(text) => {
+                    ensure!(
+                        !text.contains(c),
+                        "charmap replacement for {c:?} must not contain {c:?}"
+                    );
+                    CharMapping::Replace(text)
+                }
+                RawMapping::Keys { keys } => {
+                    ensure!(!keys.is_empty(), "charmap keys for {c:?} must not be empty");
+                    CharMapping::Keys(
+                        keys.into_iter()
+                            .map(|key| KeyStroke {
+                                keycode: key.keycode,
+                                shift: key.shift,
+                                altgr: key.altgr,
+                            })
+                            .collect(),
+                    )
+                }
+            };
+            entries.insert(c, mapping);
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.entries.contains_key(&c)
+    }
+
+    /// Apply the `Replace` entries to `text`.
+    pub fn apply(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for c in text.chars() {
+            match self.entries.get(&c) {
+                Some(CharMapping::Replace(replacement)) => out.push_str(replacement),
+                _ => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// The `Keys` entries, in character order.
+    pub fn key_overrides(&self) -> impl Iterator<Item = (char, &[KeyStroke])> {
+        self.entries
+            .iter()
+            .filter_map(|(c, mapping)| match mapping {
+                CharMapping::Keys(keys) => Some((*c, keys.as_slice())),
+                CharMapping::Replace(_) => None,
+            })
+    }
+}
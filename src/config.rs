@@ -0,0 +1,302 @@
+//! Named profiles of command-line settings, read from a TOML config file.
+//!
+//! ```toml
+//! [profiles.thesis]
+//! wpm = 70
+//! profile = "compatible"
+//! countdown = 3
+//! llm = true
+//! llm-provider = "anthropic"
+//! llm-exclude = ["code", "quote"]
+//! ```
+//!
+//! Keys are long flag names without the leading `--` (`llm_provider` works too). The CLI turns
+//! the profile selected with `--profile-name` into flags placed before the ones on the command
+//! line, so those still win.
+//!
+//! `[word-nav.NAME]` tables define word navigation profiles for `--profile NAME`:
+//!
+//! ```toml
+//! [word-nav.notes]
+//! word-chars = ["apostrophes", "hyphens"]
+//! punctuation-runs = false
+//! cross-newlines = true
+//! min-jump = 6
+//! ```
+//!
+//! `[word-nav-apps]` maps applications, by X11 `WM_CLASS` or Wayland app id, to the profile
+//! `--profile auto` picks for them and `play` expects, on top of [`DEFAULT_APP_PROFILES`]:
+//!
+//! ```toml
+//! [word-nav-apps]
+//! obsidian = "notes"
+//! "org.gnome.TextEditor" = "compatible"
+//! ```
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::word_nav::WordChars;
+use crate::word_nav_profile::{CustomWordNav, WordNavProfile, DEFAULT_APP_PROFILES};
+
+/// The config file used without `--config`: `$XDG_CONFIG_HOME/drafter/config.toml`, falling back
+/// to `~/.config/drafter/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let non_empty = |var: &str| std::env::var_os(var).filter(|value| !value.is_empty());
+    let base = non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("drafter").join("config.toml"))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub profiles: BTreeMap<String, Profile>,
+    /// User-defined word navigation profiles, from `[word-nav.NAME]` tables.
+    pub word_nav: BTreeMap<String, CustomWordNav>,
+    /// Word navigation profile names keyed by lowercase application id, from `[word-nav-apps]`.
+    pub word_nav_apps: BTreeMap<String, String>,
+}
+
+/// One profile: flag values keyed by flag name, without the leading `--`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub settings: BTreeMap<String, Setting>,
+}
+
+/// A flag's value in a profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Setting {
+    /// Turns a switch such as `--llm` on or off, or is the value of a boolean flag.
+    Bool(bool),
+    /// One value, as it would be written on the command line.
+    Value(String),
+    /// A flag given once per value, such as `--llm-redact-term`.
+    List(Vec<String>),
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let table: toml::Table = text.parse()?;
+        let mut profiles = BTreeMap::new();
+        let mut word_nav = BTreeMap::new();
+        let mut apps = toml::Table::new();
+        for (key, value) in table {
+            let toml::Value::Table(tables) = value else {
+                bail!("{key} must be a table");
+            };
+            if key == "word-nav-apps" {
+                apps = tables;
+                continue;
+            }
+            if key != "profiles" && key != "word-nav" {
+                bail!(
+                    "unknown table [{key}] (settings go in [profiles.NAME], word navigation \
+                     profiles in [word-nav.NAME] and their applications in [word-nav-apps])"
+                );
+            }
+            for (name, value) in tables {
+                let toml::Value::Table(settings) = value else {
+                    bail!("{key}.{name} must be a table");
+                };
+                if key == "word-nav" {
+                    let custom = custom_word_nav(&name, settings)
+                        .with_context(|| format!("invalid word-nav.{name}"))?;
+                    word_nav.insert(name, custom);
+                } else {
+                    let profile = Profile::from_table(&name, settings)?;
+                    profiles.insert(name, profile);
+                }
+            }
+        }
+
+        let mut word_nav_apps = BTreeMap::new();
+        for (app, value) in apps {
+            let toml::Value::String(name) = value else {
+                bail!("word-nav-apps.{app} must be a profile name");
+            };
+            let name = match WordNavProfile::builtin(&name) {
+                Some(profile) => profile.name().to_string(),
+                None if word_nav.contains_key(&name) => name,
+                None => bail!(
+                    "word-nav-apps.{app}: {name:?} is neither a built-in profile nor a \
+                     [word-nav.NAME] table"
+                ),
+            };
+            word_nav_apps.insert(app.to_lowercase(), name);
+        }
+
+        Ok(Self {
+            profiles,
+            word_nav,
+            word_nav_apps,
+        })
+    }
+
+    /// Sets `key` to `value` in profile `name` of the config file at `path`, creating the file
+    /// and the profile if needed. The rest of the file, comments included, is kept as it is.
+    pub fn write_setting(path: &Path, name: &str, key: &str, value: &str) -> Result<()> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to read config file {}", path.display()))
+            }
+        };
+        Self::parse(&text).with_context(|| format!("invalid config file {}", path.display()))?;
+
+        let mut document: toml_edit::DocumentMut = text.parse()?;
+        let profiles = document
+            .entry("profiles")
+            .or_insert_with(|| {
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                table.into()
+            })
+            .as_table_mut()
+            .context("profiles must be a table")?;
+        let profile = profiles
+            .entry(name)
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .with_context(|| format!("profiles.{name} must be a table"))?;
+        profile[key] = toml_edit::value(value);
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        std::fs::write(path, document.to_string())
+            .with_context(|| format!("failed to write config file {}", path.display()))
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile> {
+        self.profiles.get(name).ok_or_else(|| {
+            if self.profiles.is_empty() {
+                anyhow!("no profile {name:?}: the config file defines none")
+            } else {
+                let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                anyhow!("no profile {name:?} (defined: {})", known.join(", "))
+            }
+        })
+    }
+
+    /// The user-defined word navigation profile `[word-nav.NAME]`.
+    pub fn word_nav(&self, name: &str) -> Result<WordNavProfile> {
+        let custom = self.word_nav.get(name).ok_or_else(|| {
+            if self.word_nav.is_empty() {
+                anyhow!("no word navigation profile {name:?}: the config file defines none")
+            } else {
+                let known: Vec<&str> = self.word_nav.keys().map(String::as_str).collect();
+                anyhow!(
+                    "no word navigation profile {name:?} (defined: {})",
+                    known.join(", ")
+                )
+            }
+        })?;
+        Ok(WordNavProfile::Custom(custom.clone()))
+    }
+
+    /// The word navigation profile for an application known by `app_ids`, from
+    /// `[word-nav-apps]` or else [`DEFAULT_APP_PROFILES`], with the app id that matched.
+    /// App ids match ignoring case.
+    pub fn app_word_nav(&self, app_ids: &[String]) -> Option<(&str, &str)> {
+        let user = app_ids.iter().find_map(|id| {
+            let (id, name) = self.word_nav_apps.get_key_value(&id.to_lowercase())?;
+            Some((id.as_str(), name.as_str()))
+        });
+        user.or_else(|| {
+            app_ids.iter().find_map(|id| {
+                DEFAULT_APP_PROFILES
+                    .iter()
+                    .find(|(app, _)| app.eq_ignore_ascii_case(id))
+                    .map(|&(app, name)| (app, name))
+            })
+        })
+    }
+}
+
+impl Profile {
+    fn from_table(name: &str, table: toml::Table) -> Result<Self> {
+        let settings = table
+            .into_iter()
+            .map(|(key, value)| {
+                let setting = Setting::from_value(value)
+                    .with_context(|| format!("invalid profiles.{name}.{key}"))?;
+                Ok((key.replace('_', "-"), setting))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { settings })
+    }
+}
+
+impl Setting {
+    fn from_value(value: toml::Value) -> Result<Self> {
+        Ok(match value {
+            toml::Value::Boolean(on) => Setting::Bool(on),
+            toml::Value::Array(items) => {
+                Setting::List(items.into_iter().map(scalar).collect::<Result<_>>()?)
+            }
+            other => Setting::Value(scalar(other)?),
+        })
+    }
+}
+
+/// The `[word-nav.NAME]` table `name`; missing keys keep `CustomWordNav::default()`.
+fn custom_word_nav(name: &str, table: toml::Table) -> Result<CustomWordNav> {
+    let mut custom = CustomWordNav {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    for (key, value) in table {
+        let key = key.replace('_', "-");
+        match (key.as_str(), value) {
+            ("word-chars", toml::Value::Array(names)) => {
+                for name in names {
+                    let toml::Value::String(name) = name else {
+                        bail!("word-chars must list class names");
+                    };
+                    if !custom.rules.word_chars.insert(&name) {
+                        bail!(
+                            "unknown word-chars class {name:?} (expected {})",
+                            WordChars::NAMES.join(", ")
+                        );
+                    }
+                }
+            }
+            ("punctuation-runs", toml::Value::Boolean(on)) => custom.rules.punctuation_runs = on,
+            ("cross-newlines", toml::Value::Boolean(on)) => custom.crosses_newlines = on,
+            ("min-jump", toml::Value::Integer(chars)) if chars >= 1 => {
+                custom.min_jump = usize::try_from(chars)?;
+            }
+            ("word-chars", _) => bail!("word-chars must be an array of class names"),
+            ("punctuation-runs" | "cross-newlines", _) => bail!("{key} must be true or false"),
+            ("min-jump", _) => bail!("min-jump must be a positive integer"),
+            _ => bail!(
+                "unknown key {key} (expected word-chars, punctuation-runs, cross-newlines or min-jump)"
+            ),
+        }
+    }
+    Ok(custom)
+}
+
+fn scalar(value: toml::Value) -> Result<String> {
+    Ok(match value {
+        toml::Value::String(text) => text,
+        toml::Value::Integer(number) => number.to_string(),
+        toml::Value::Float(number) => number.to_string(),
+        toml::Value::Boolean(on) => on.to_string(),
+        other => bail!(
+            "expected a string, number or boolean, got {}",
+            other.type_str()
+        ),
+    })
+}
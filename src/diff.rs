@@ -0,0 +1,165 @@
+//! Word-level differences between two texts, as the edits that turn one into the other.
+
+/// Equal runs of at most this many tokens between two changes are folded into one edit.
+const MERGE_GAP_TOKENS: usize = 2;
+
+/// One replacement in `target`: the text at `start` reads `draft` in the draft and `target` in
+/// the target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordEdit {
+    /// Char index of the edit in the target text.
+    pub start: usize,
+    pub target: String,
+    pub draft: String,
+}
+
+/// The edits that turn `draft` into `target`, ordered by `start` and non-overlapping.
+///
+/// Both sides of every edit are non-empty: pure insertions and deletions take in a neighboring
+/// word, so each edit is a replacement the planner can type and later correct. Replacing every
+/// `target` span with its `draft` text yields `draft`.
+pub fn word_edits(draft: &str, target: &str) -> Vec<WordEdit> {
+    let draft_tokens = tokenize(draft);
+    let target_tokens = tokenize(target);
+    if draft_tokens.is_empty() || target_tokens.is_empty() || draft == target {
+        return Vec::new();
+    }
+
+    let mut hunks = merge_close_hunks(changed_hunks(&draft_tokens, &target_tokens));
+    let len_t = target_tokens.len();
+    for idx in 0..hunks.len() {
+        let lower = if idx == 0 { 0 } else { hunks[idx - 1].t.end };
+        let upper = hunks.get(idx + 1).map_or(len_t, |next| next.t.start);
+        widen_to_replacement(&mut hunks[idx], &target_tokens, lower, upper);
+    }
+
+    let mut char_starts = Vec::with_capacity(len_t + 1);
+    let mut offset = 0usize;
+    for token in &target_tokens {
+        char_starts.push(offset);
+        offset += token.chars().count();
+    }
+    char_starts.push(offset);
+
+    hunks
+        .into_iter()
+        .map(|hunk| WordEdit {
+            start: char_starts[hunk.t.start],
+            target: target_tokens[hunk.t.clone()].concat(),
+            draft: draft_tokens[hunk.d.clone()].concat(),
+        })
+        .collect()
+}
+
+/// Token ranges that differ between the target (`t`) and the draft (`d`).
+#[derive(Debug, Clone)]
+struct Hunk {
+    t: std::ops::Range<usize>,
+    d: std::ops::Range<usize>,
+}
+
+/// Words, whitespace runs, and single punctuation characters.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0usize;
+    let mut prev_class: Option<u8> = None;
+
+    for (idx, c) in text.char_indices() {
+        let class = if is_word_char(c) {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        };
+        if idx > start && (prev_class != Some(class) || class == 2) {
+            tokens.push(&text[start..idx]);
+            start = idx;
+        }
+        prev_class = Some(class);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '\'' || c == '’'
+}
+
+/// Maximal runs of tokens outside a longest common subsequence of the two token lists.
+fn changed_hunks(draft: &[&str], target: &[&str]) -> Vec<Hunk> {
+    let (n, m) = (target.len(), draft.len());
+    // lcs[i][j]: LCS length of target[i..] and draft[j..].
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if target[i] == draft[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0usize, 0usize);
+    let mut open: Option<Hunk> = None;
+    while i < n || j < m {
+        if i < n && j < m && target[i] == draft[j] {
+            hunks.extend(open.take());
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        let hunk = open.get_or_insert(Hunk { t: i..i, d: j..j });
+        if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            j += 1;
+            hunk.d.end = j;
+        } else {
+            i += 1;
+            hunk.t.end = i;
+        }
+    }
+    hunks.extend(open);
+    hunks
+}
+
+fn merge_close_hunks(hunks: Vec<Hunk>) -> Vec<Hunk> {
+    let mut merged: Vec<Hunk> = Vec::with_capacity(hunks.len());
+    for hunk in hunks {
+        match merged.last_mut() {
+            Some(prev) if hunk.t.start - prev.t.end <= MERGE_GAP_TOKENS => {
+                prev.t.end = hunk.t.end;
+                prev.d.end = hunk.d.end;
+            }
+            _ => merged.push(hunk),
+        }
+    }
+    merged
+}
+
+/// Grow an insertion or deletion over neighboring equal tokens (within `lower..upper`) until
+/// both sides are non-empty and the edit does not start with whitespace where a word precedes it.
+fn widen_to_replacement(hunk: &mut Hunk, target: &[&str], lower: usize, upper: usize) {
+    while hunk.t.is_empty() || hunk.d.is_empty() || starts_with_space(hunk, target, lower) {
+        if hunk.t.start > lower {
+            hunk.t.start -= 1;
+            hunk.d.start -= 1;
+        } else if hunk.t.end < upper {
+            hunk.t.end += 1;
+            hunk.d.end += 1;
+        } else {
+            break;
+        }
+    }
+}
+
+fn starts_with_space(hunk: &Hunk, target: &[&str], lower: usize) -> bool {
+    hunk.t.start > lower
+        && target
+            .get(hunk.t.start)
+            .is_some_and(|token| token.starts_with(char::is_whitespace))
+}
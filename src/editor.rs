@@ -0,0 +1,573 @@
+//! The editor model shared by the planner, the simulator and the console trace.
+//!
+//! [`EditorModel`] is a text buffer with a cursor and a selection, changed by keystrokes the
+//! way common editors change a plain text field: Shift extends the selection from an anchor,
+//! plain arrows collapse it, and typing, Backspace or Delete replace it. Ctrl moves and
+//! deletes by word ([`crate::word_nav`]). Lines are the buffer's `\n`-separated lines; soft
+//! wrapping is not modeled.
+//!
+//! [`EditorModel::with_undo`] also keeps an undo history. Edits are grouped the way common
+//! editors group them: typing is undone a word at a time (a group ends after the whitespace
+//! following a word), consecutive Backspaces or Deletes are undone together, typing over a
+//! selection is undone together with the selection's deletion, and moving the cursor starts a
+//! new group.
+//!
+//! [`EditorModel::autocorrect`] fixes the classic typos of [`AUTOCORRECTIONS`] the way editors
+//! with autocorrect do once the word is followed by whitespace, and [`EditorModel::type_char`]
+//! makes the edits of a [`CodeEditor`]: indenting new lines and closing brackets.
+
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::keyboard::{
+    KEY_BACKSPACE, KEY_DELETE, KEY_DOWN, KEY_END, KEY_HOME, KEY_LEFT, KEY_RIGHT, KEY_UP,
+};
+
+/// A line and column in the editor, both counted from 1 (columns in characters).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CursorPosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Edits a code editor makes as code is typed, besides inserting the typed characters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CodeEditor {
+    /// Enter indents the new line with the spaces and tabs that start the line it was pressed
+    /// on.
+    pub auto_indent: bool,
+    /// Typing `(`, `[` or `{` before whitespace, a closing bracket or the end of the text adds
+    /// the closing bracket after the cursor, and typing a closing bracket right before the same
+    /// one moves over it instead.
+    pub auto_close_brackets: bool,
+}
+
+impl CodeEditor {
+    /// Whether typing `c` may make the editor change more than inserting it.
+    pub fn edits_after(&self, c: char) -> bool {
+        (self.auto_indent && c == '\n') || (self.auto_close_brackets && is_bracket(c))
+    }
+}
+
+/// Opening brackets and the closing brackets that match them.
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// The closing bracket matching `c`, if it is an opening bracket.
+pub fn closing_bracket(c: char) -> Option<char> {
+    BRACKETS
+        .iter()
+        .find(|(open, _)| *open == c)
+        .map(|(_, close)| *close)
+}
+
+fn is_closing_bracket(c: char) -> bool {
+    BRACKETS.iter().any(|(_, close)| *close == c)
+}
+
+fn is_bracket(c: char) -> bool {
+    closing_bracket(c).is_some() || is_closing_bracket(c)
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct EditorModel {
+    buf: Vec<char>,
+    /// Which insertion typed each character of `buf`, counting from 0.
+    ids: Vec<usize>,
+    inserted: usize,
+    cursor: usize,
+    /// Other end of the selection, set by the first Shift+movement.
+    anchor: Option<usize>,
+    history: Option<UndoHistory>,
+}
+
+/// What made an edit, for grouping edits into undo steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Typing,
+    Backspace,
+    Delete,
+    Selection,
+}
+
+/// Characters inserted into or removed from the text at `at`.
+#[derive(Debug, Clone)]
+struct Edit {
+    at: usize,
+    chars: Vec<char>,
+    ids: Vec<usize>,
+    inserted: bool,
+}
+
+/// Edits undone and redone together.
+#[derive(Debug, Clone)]
+struct UndoGroup {
+    kind: EditKind,
+    cursor_before: usize,
+    edits: Vec<Edit>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct UndoHistory {
+    undo: Vec<UndoGroup>,
+    redo: Vec<UndoGroup>,
+    /// Whether the next edit may join the last undo group.
+    open: bool,
+}
+
+impl UndoHistory {
+    fn record(&mut self, kind: EditKind, cursor_before: usize, edit: Edit) {
+        self.redo.clear();
+        let open = self.open;
+        self.open = true;
+        if let Some(group) = self.undo.last_mut().filter(|_| open) {
+            let joins = match (group.kind, kind) {
+                (EditKind::Selection, EditKind::Typing) => true,
+                (EditKind::Typing, EditKind::Typing) => {
+                    let after_space = group
+                        .edits
+                        .last()
+                        .and_then(|last| last.chars.last())
+                        .is_some_and(|c| c.is_whitespace());
+                    !after_space || edit.chars.iter().all(|c| c.is_whitespace())
+                }
+                (before, kind) => before == kind,
+            };
+            if joins {
+                group.kind = kind;
+                group.edits.push(edit);
+                return;
+            }
+        }
+        self.undo.push(UndoGroup {
+            kind,
+            cursor_before,
+            edits: vec![edit],
+        });
+    }
+}
+
+impl EditorModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty editor that keeps an undo history for [`EditorModel::undo`] and
+    /// [`EditorModel::redo`].
+    pub fn with_undo() -> Self {
+        Self {
+            history: Some(UndoHistory::default()),
+            ..Self::default()
+        }
+    }
+
+    pub fn text(&self) -> &[char] {
+        &self.buf
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.cursor == self.buf.len()
+    }
+
+    /// Insertion ids of the characters of [`EditorModel::text`], as returned by
+    /// [`EditorModel::insert_char`].
+    pub fn char_ids(&self) -> &[usize] {
+        &self.ids
+    }
+
+    pub fn as_string(&self) -> String {
+        self.buf.iter().collect()
+    }
+
+    /// The selected range of the text, if any.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        (anchor != self.cursor).then(|| anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.selection().is_some()
+    }
+
+    pub fn cursor_position(&self) -> CursorPosition {
+        CursorPosition {
+            line: self.buf[..self.cursor]
+                .iter()
+                .filter(|c| **c == '\n')
+                .count()
+                + 1,
+            column: self.cursor - self.line_start(self.cursor) + 1,
+        }
+    }
+
+    /// Types `c` at the cursor, replacing the selection, and returns its insertion id.
+    pub fn insert_char(&mut self, c: char) -> usize {
+        self.delete_selection();
+        let id = self.inserted;
+        let at = self.cursor;
+        self.buf.insert(at, c);
+        self.ids.insert(at, id);
+        self.cursor += 1;
+        self.inserted += 1;
+        if let Some(history) = &mut self.history {
+            let edit = Edit {
+                at,
+                chars: vec![c],
+                ids: vec![id],
+                inserted: true,
+            };
+            history.record(EditKind::Typing, at, edit);
+        }
+        id
+    }
+
+    /// Types `c` like [`EditorModel::insert_char`], with the edits `code` makes as it is typed.
+    /// Returns how many characters the editor added besides `c` (a closing bracket or the
+    /// indentation of a new line).
+    pub fn type_char(&mut self, c: char, code: CodeEditor) -> usize {
+        if code.auto_close_brackets
+            && is_closing_bracket(c)
+            && !self.has_selection()
+            && self.buf.get(self.cursor) == Some(&c)
+        {
+            self.move_to(self.cursor + 1, false);
+            return 0;
+        }
+        self.insert_char(c);
+        let mut added = 0;
+        let closes = self
+            .buf
+            .get(self.cursor)
+            .is_none_or(|&next| next.is_whitespace() || is_closing_bracket(next));
+        if let Some(close) = closing_bracket(c).filter(|_| code.auto_close_brackets && closes) {
+            self.insert_char(close);
+            self.cursor -= 1;
+            added += 1;
+        }
+        if code.auto_indent && c == '\n' {
+            let line = self.line_start(self.cursor - 1);
+            let indent: Vec<char> = self.buf[line..self.cursor - 1]
+                .iter()
+                .copied()
+                .take_while(|&c| c == ' ' || c == '\t')
+                .collect();
+            for &c in &indent {
+                self.insert_char(c);
+            }
+            added += indent.len();
+        }
+        added
+    }
+
+    /// Deletes the selected text, if any, clears the selection and returns the deleted text.
+    pub fn delete_selection(&mut self) -> Vec<char> {
+        let selection = self.selection();
+        self.anchor = None;
+        selection.map_or_else(Vec::new, |selection| {
+            self.remove(selection, EditKind::Selection)
+        })
+    }
+
+    /// Backspace, or Ctrl+Backspace with `word`. Returns the deleted text (the selection, if
+    /// any).
+    pub fn backspace(&mut self, word: bool) -> Vec<char> {
+        if self.has_selection() {
+            return self.delete_selection();
+        }
+        self.anchor = None;
+        let start = if word {
+            crate::word_nav::ctrl_left(&self.buf, self.cursor, is_word_char)
+        } else {
+            self.cursor.saturating_sub(1)
+        };
+        self.remove(start..self.cursor, EditKind::Backspace)
+    }
+
+    /// Delete, or Ctrl+Delete with `word`. Returns the deleted text (the selection, if any).
+    pub fn delete(&mut self, word: bool) -> Vec<char> {
+        if self.has_selection() {
+            return self.delete_selection();
+        }
+        self.anchor = None;
+        let cursor = self.cursor;
+        let end = if word {
+            crate::word_nav::ctrl_right(&self.buf, cursor, is_word_char)
+        } else {
+            (cursor + 1).min(self.buf.len())
+        };
+        self.remove(cursor..end, EditKind::Delete)
+    }
+
+    pub fn move_left(&mut self, select: bool) {
+        // A plain arrow collapses a selection to its start (or end).
+        let target = match self.selection() {
+            Some(selection) if !select => selection.start,
+            _ => self.cursor.saturating_sub(1),
+        };
+        self.move_to(target, select);
+    }
+
+    pub fn move_right(&mut self, select: bool) {
+        let target = match self.selection() {
+            Some(selection) if !select => selection.end,
+            _ => (self.cursor + 1).min(self.buf.len()),
+        };
+        self.move_to(target, select);
+    }
+
+    pub fn move_word_left(&mut self, select: bool) {
+        let target = crate::word_nav::ctrl_left(&self.buf, self.cursor, is_word_char);
+        self.move_to(target, select);
+    }
+
+    pub fn move_word_right(&mut self, select: bool) {
+        let target = crate::word_nav::ctrl_right(&self.buf, self.cursor, is_word_char);
+        self.move_to(target, select);
+    }
+
+    /// Up: the same column on the previous line (or its end if shorter), or the start of the
+    /// text from the first line.
+    pub fn move_up(&mut self, select: bool) {
+        let line_start = self.line_start(self.cursor);
+        let target = match line_start.checked_sub(1) {
+            Some(previous_end) => {
+                let previous_start = self.line_start(previous_end);
+                (previous_start + self.cursor - line_start).min(previous_end)
+            }
+            None => 0,
+        };
+        self.move_to(target, select);
+    }
+
+    /// Down: the same column on the next line (or its end if shorter), or the end of the text
+    /// from the last line.
+    pub fn move_down(&mut self, select: bool) {
+        let line_end = self.line_end(self.cursor);
+        let target = if line_end < self.buf.len() {
+            let next_start = line_end + 1;
+            (next_start + self.cursor - self.line_start(self.cursor)).min(self.line_end(next_start))
+        } else {
+            self.buf.len()
+        };
+        self.move_to(target, select);
+    }
+
+    /// Home: the start of the line, or of the text with `document` (Ctrl+Home).
+    pub fn home(&mut self, select: bool, document: bool) {
+        let target = if document {
+            0
+        } else {
+            self.line_start(self.cursor)
+        };
+        self.move_to(target, select);
+    }
+
+    /// End: the end of the line, or of the text with `document` (Ctrl+End).
+    pub fn end(&mut self, select: bool, document: bool) {
+        let target = if document {
+            self.buf.len()
+        } else {
+            self.line_end(self.cursor)
+        };
+        self.move_to(target, select);
+    }
+
+    /// Undoes the last group of edits, if any, putting the cursor where it was before them.
+    /// Returns whether there was anything to undo (always false without
+    /// [`EditorModel::with_undo`]).
+    pub fn undo(&mut self) -> bool {
+        let Some(group) = self.history.as_mut().and_then(|history| history.undo.pop()) else {
+            return false;
+        };
+        for edit in group.edits.iter().rev() {
+            self.apply(edit, !edit.inserted);
+        }
+        self.cursor = group.cursor_before;
+        self.anchor = None;
+        if let Some(history) = &mut self.history {
+            history.redo.push(group);
+            history.open = false;
+        }
+        true
+    }
+
+    /// Redoes the last undone group of edits, if any, putting the cursor after them. Returns
+    /// whether there was anything to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(group) = self.history.as_mut().and_then(|history| history.redo.pop()) else {
+            return false;
+        };
+        for edit in &group.edits {
+            self.apply(edit, edit.inserted);
+        }
+        self.anchor = None;
+        if let Some(history) = &mut self.history {
+            history.undo.push(group);
+            history.open = false;
+        }
+        true
+    }
+
+    /// Replaces the word before the whitespace just typed with its [`autocorrection`], if it
+    /// has one, the way editors with autocorrect do. The cursor stays after the whitespace, and
+    /// the correction is undone on its own. Returns whether the word was corrected.
+    pub fn autocorrect(&mut self) -> bool {
+        let Some(end) = self
+            .cursor
+            .checked_sub(1)
+            .filter(|&end| self.buf[end].is_whitespace())
+        else {
+            return false;
+        };
+        let start = self.buf[..end]
+            .iter()
+            .rposition(|c| !is_word_char(*c))
+            .map_or(0, |before| before + 1);
+        let word: String = self.buf[start..end].iter().collect();
+        let Some(corrected) = autocorrection(&word) else {
+            return false;
+        };
+
+        let after = self.cursor - end;
+        self.anchor = None;
+        self.remove(start..end, EditKind::Typing);
+        for c in corrected.chars() {
+            self.insert_char(c);
+        }
+        self.cursor += after;
+        if let Some(history) = &mut self.history {
+            history.open = false;
+        }
+        true
+    }
+
+    /// Applies an editing or navigation key (arrows, Home/End, Backspace/Delete) pressed with
+    /// Shift and Ctrl as given. Returns the deleted text, or `None` for other keys.
+    pub fn press_edit_key(&mut self, keycode: u32, shift: bool, ctrl: bool) -> Option<Vec<char>> {
+        match keycode {
+            KEY_LEFT if ctrl => self.move_word_left(shift),
+            KEY_LEFT => self.move_left(shift),
+            KEY_RIGHT if ctrl => self.move_word_right(shift),
+            KEY_RIGHT => self.move_right(shift),
+            KEY_UP => self.move_up(shift),
+            KEY_DOWN => self.move_down(shift),
+            KEY_HOME => self.home(shift, ctrl),
+            KEY_END => self.end(shift, ctrl),
+            KEY_BACKSPACE => return Some(self.backspace(ctrl)),
+            KEY_DELETE => return Some(self.delete(ctrl)),
+            _ => return None,
+        }
+        Some(Vec::new())
+    }
+
+    /// Moves the cursor to `cursor`, selecting from where it was with `select` (Shift held).
+    fn move_to(&mut self, cursor: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = cursor;
+        if let Some(history) = &mut self.history {
+            history.open = false;
+        }
+    }
+
+    /// Deletes `range` of the text, moves the cursor to its start and returns its characters.
+    fn remove(&mut self, range: Range<usize>, kind: EditKind) -> Vec<char> {
+        let cursor_before = self.cursor;
+        self.cursor = range.start;
+        let ids: Vec<usize> = self.ids.drain(range.clone()).collect();
+        let chars: Vec<char> = self.buf.drain(range.clone()).collect();
+        if let Some(history) = self.history.as_mut().filter(|_| !chars.is_empty()) {
+            let edit = Edit {
+                at: range.start,
+                chars: chars.clone(),
+                ids,
+                inserted: false,
+            };
+            history.record(kind, cursor_before, edit);
+        }
+        chars
+    }
+
+    /// Inserts the characters of `edit` (`insert`) or removes them, without recording it, and
+    /// leaves the cursor after the change.
+    fn apply(&mut self, edit: &Edit, insert: bool) {
+        let range = edit.at..edit.at + edit.chars.len();
+        if insert {
+            self.buf
+                .splice(edit.at..edit.at, edit.chars.iter().copied());
+            self.ids.splice(edit.at..edit.at, edit.ids.iter().copied());
+            self.cursor = range.end;
+        } else {
+            self.buf.drain(range.clone());
+            self.ids.drain(range.clone());
+            self.cursor = range.start;
+        }
+    }
+
+    fn line_start(&self, idx: usize) -> usize {
+        self.buf[..idx]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map_or(0, |newline| newline + 1)
+    }
+
+    fn line_end(&self, idx: usize) -> usize {
+        self.buf[idx..]
+            .iter()
+            .position(|c| *c == '\n')
+            .map_or(self.buf.len(), |newline| idx + newline)
+    }
+}
+
+/// Classic typos that editors with autocorrect (like Google Docs) fix once whitespace follows
+/// them, in lower case, with their corrections. Each is a swap of two letters, so correcting
+/// it keeps the length of the text.
+pub const AUTOCORRECTIONS: &[(&str, &str)] = &[
+    ("teh", "the"),
+    ("hte", "the"),
+    ("adn", "and"),
+    ("taht", "that"),
+    ("waht", "what"),
+    ("wiht", "with"),
+    ("jsut", "just"),
+    ("thier", "their"),
+    ("wierd", "weird"),
+    ("freind", "friend"),
+    ("becuase", "because"),
+    ("recieve", "receive"),
+    ("beleive", "believe"),
+];
+
+/// The correction of `word` in [`AUTOCORRECTIONS`], in the same case (lower case, capitalized
+/// or all caps), or `None` if it is not a typo there.
+pub fn autocorrection(word: &str) -> Option<String> {
+    let lower = word.to_lowercase();
+    let (_typo, corrected) = AUTOCORRECTIONS
+        .iter()
+        .find(|(typo, _corrected)| *typo == lower)?;
+    let mut chars = word.chars();
+    let first_upper = chars.next().is_some_and(char::is_uppercase);
+    if word == lower {
+        Some(corrected.to_string())
+    } else if word == lower.to_uppercase() {
+        Some(corrected.to_uppercase())
+    } else if first_upper && chars.all(char::is_lowercase) {
+        let mut out: String = corrected[..1].to_uppercase();
+        out.push_str(&corrected[1..]);
+        Some(out)
+    } else {
+        None
+    }
+}
+
+/// Characters Ctrl+arrows and Ctrl+Backspace/Delete treat as part of a word.
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '\'' || c == '’'
+}
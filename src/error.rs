@@ -0,0 +1,99 @@
+//! [`DrafterError`], the error type of the library's planning, playback and LLM entry points, so
+//! callers can tell failures apart without matching on messages.
+//!
+//! Internally the library uses `anyhow` and raises a `DrafterError` where the failure happens;
+//! the entry points find it again under any added context, and wrap every other failure in
+//! [`DrafterError::Other`] (or [`DrafterError::Llm`]) with its full message.
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DrafterError {
+    /// A planner setting is out of range, e.g. a rate outside 0–1.
+    #[error("{0}")]
+    InvalidConfig(String),
+    /// The text has a character the keyboard layout cannot type.
+    #[error(
+        "unsupported character {ch:?} (U+{code:04X}) at line {line}, column {column} for keyboard layout {layout}. Supported: characters the layout can type (directly or via dead keys/Compose), newline, and smart quotes (’ ‘ ” “); other characters need normalization, a charmap, or a custom keymap.",
+        code = *ch as u32
+    )]
+    UnsupportedCharacter {
+        ch: char,
+        /// 1-based, like `column`.
+        line: usize,
+        column: usize,
+        layout: String,
+    },
+    /// The text has a tab and the tab policy rejects tabs.
+    #[error(
+        "tab at line {line}, column {column}. Tabs are rejected by default; choose a tab policy (literal Tab key or spaces)."
+    )]
+    TabRejected { line: usize, column: usize },
+    /// The text has whitespace at the end of a line and the trailing whitespace policy rejects
+    /// it.
+    #[error(
+        "trailing whitespace at line {line}, column {column}. Trailing whitespace is rejected; remove it from the draft or choose a policy that strips or keeps it."
+    )]
+    TrailingWhitespace { line: usize, column: usize },
+    /// The text ends with a newline and the final newline policy rejects it.
+    #[error(
+        "the text ends with a newline. A final newline is rejected; remove it from the draft or choose a policy that strips or keeps it."
+    )]
+    FinalNewline,
+    /// No playback backend supports this session, or the one asked for is disabled in this
+    /// build.
+    #[error("{0}")]
+    BackendUnavailable(String),
+    /// The compositor has no Wayland seat with the requested name.
+    #[error("requested seat {requested:?} not found; available seats: {}", available.join(", "))]
+    SeatNotFound {
+        requested: String,
+        available: Vec<String>,
+    },
+    /// The session keyboard layout does not match the plan's, and playback was not forced.
+    #[error(
+        "{0}. Switch the session layout or re-plan with a matching `--layout`; pass --force to play anyway."
+    )]
+    LayoutMismatch(String),
+    /// The playback was cancelled.
+    #[error("aborted")]
+    Aborted,
+    /// The library was built without the `llm` feature.
+    #[error("LLM support is disabled (build with --features llm)")]
+    LlmDisabled,
+    /// The environment variable holding an LLM provider's API key is not set.
+    #[error("{0} is not set")]
+    MissingApiKey(String),
+    /// An LLM provider's API answered a request with an error status.
+    #[error("{provider} API returned {status}: {message}")]
+    LlmApi {
+        provider: String,
+        status: u16,
+        message: String,
+    },
+    /// Any other failure of an LLM request, e.g. a network error, an invalid reply or the
+    /// deadline passing.
+    #[error(transparent)]
+    Llm(anyhow::Error),
+    /// Any other failure.
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl DrafterError {
+    /// `err` as the LLM request failure it is.
+    #[cfg(feature = "llm")]
+    pub(crate) fn llm(err: anyhow::Error) -> Self {
+        match Self::from(err) {
+            Self::Other(err) => Self::Llm(err),
+            typed => typed,
+        }
+    }
+}
+
+impl From<anyhow::Error> for DrafterError {
+    fn from(err: anyhow::Error) -> Self {
+        err.downcast::<DrafterError>().unwrap_or_else(Self::Other)
+    }
+}
@@ -0,0 +1,331 @@
+//! Estimating how long a plan for a text will take, without generating it (`drafter bench`).
+//!
+//! The estimate adds up the expected value of every wait the planner makes: key holds,
+//! inter-key delays at the plan's speed, punctuation and thinking pauses, modifier changes,
+//! and the extra typing, deleting and cursor movement of corrections. It mirrors the timing in
+//! `planner.rs`; `tests/duration_estimate.rs` checks it against generated plans.
+
+use anyhow::Result;
+
+use crate::careful::careful_tokens;
+use crate::layout::KeyboardLayout;
+use crate::planner::{
+    apply_error_mix, confusion_options, split_paragraphs, split_paragraphs_with, strip_whitespace,
+    unclosed_brackets, PlannerConfig, TypingMode, CAREFUL_SPEED,
+};
+
+/// Expected waits around a Shift or AltGr press or release (5..=20 ms, then 0..=12 ms).
+const MODIFIER_CHANGE_MS: f64 = 18.5;
+/// Expected wait before the first key (250..=600 ms).
+const START_PAUSE_MS: f64 = 425.0;
+/// Expected wait after each Backspace of a correction (15..=55 ms).
+const BACKSPACE_GAP_MS: f64 = 35.0;
+/// Expected pause before deleting a mistake (60..=260 ms right away, 50..=220 ms later).
+const NOTICE_PAUSE_MS: f64 = 150.0;
+/// Expected pause after a later correction (80..=420 ms).
+const AFTER_FIX_PAUSE_MS: f64 = 250.0;
+/// Expected wait after an arrow key while moving to a mistake and back (6..=22 ms, with an
+/// occasional longer pause), besides the key hold.
+const NAV_GAP_MS: f64 = 17.0;
+/// Typical characters crossed per cursor movement step, mixing word jumps and single arrows.
+const NAV_CHARS_PER_STEP: f64 = 4.0;
+/// Typical distance, in characters, from a mistake back to the cursor when it is fixed later.
+const FIX_DISTANCE_CHARS: f64 = 90.0;
+/// Chance of a doubled space, per space, when errors are enabled.
+const DOUBLE_SPACE_RATE: f64 = 0.015;
+/// Expected waits of a re-read after a session break: reorienting (1500..=4000 ms), three Up
+/// and three Down presses (250..=900 ms and 60..=200 ms after each), reading (1500..=5000 ms)
+/// and getting back to typing (300..=900 ms); key holds come on top.
+const REREAD_MS: f64 = 2750.0 + 3.0 * 575.0 + 3.0 * 130.0 + 3250.0 + 600.0;
+/// Up, Home and Down presses of a re-read.
+const REREAD_KEYS: f64 = 7.0;
+/// Expected waits around deleting the indentation the editor added that a line does not have
+/// (60..=260 ms before, then each Backspace's).
+const UNINDENT_PAUSE_MS: f64 = 160.0;
+/// Expected waits around deleting a closing bracket the editor added (80..=300 ms before,
+/// 40..=160 ms after).
+const UNCLOSE_PAUSE_MS: f64 = 290.0;
+
+/// Expected and extreme durations of a plan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurationEstimate {
+    /// Expected duration, with the typing speed drawn from the WPM range.
+    pub expected_ms: f64,
+    /// Expected duration at the top of the WPM range.
+    pub fastest_ms: f64,
+    /// Expected duration at the bottom of the WPM range.
+    pub slowest_ms: f64,
+}
+
+/// What the estimate needs to know about a text, gathered once for any number of configs.
+#[derive(Debug, Clone)]
+pub struct DurationEstimator {
+    /// Keystrokes of the text (more than its characters with dead keys or Compose).
+    strokes: usize,
+    /// Shift and AltGr presses and releases while typing the text.
+    modifier_changes: usize,
+    /// Pause after characters, independent of speed (punctuation and thinking pauses).
+    pause_ms: f64,
+    words: usize,
+    word_chars: usize,
+    spaces: usize,
+    /// Punctuation marks with another one on an adjacent key to mistype them as.
+    mistypable_punctuation: usize,
+    /// Periods ending a paragraph, which the planner may leave out.
+    final_periods: usize,
+    /// Words of the planner's table of confusions, and their characters.
+    confusable_words: usize,
+    confusable_chars: usize,
+    /// Paragraphs after the first, where the planner may take a session break.
+    later_paragraphs: usize,
+    /// Characters of careful tokens, typed slower.
+    careful_chars: usize,
+    /// Lines with less indentation than the one before, whose extra indentation the editor adds
+    /// and the planner deletes, and the Backspaces that takes.
+    unindented_lines: usize,
+    unindent_backspaces: usize,
+    /// Opening brackets the text never closes, whose closing bracket the editor adds and the
+    /// planner deletes.
+    unclosed_brackets: usize,
+}
+
+impl DurationEstimator {
+    /// Gathers the text's keystrokes on `cfg.layout`. Characters the layout cannot type count
+    /// as one plain keystroke.
+    pub fn new(text: &str, cfg: &PlannerConfig) -> Result<Self> {
+        let layout = KeyboardLayout::from_spec(&cfg.layout)?;
+        let text = &cfg.paragraph_breaks.apply(&strip_whitespace(text, cfg));
+        let mut estimator = Self {
+            strokes: 0,
+            modifier_changes: 0,
+            pause_ms: 0.0,
+            words: 0,
+            word_chars: 0,
+            spaces: 0,
+            mistypable_punctuation: 0,
+            final_periods: 0,
+            confusable_words: 0,
+            confusable_chars: 0,
+            later_paragraphs: split_paragraphs_with(text, cfg.paragraph_breaks)
+                .len()
+                .saturating_sub(1),
+            careful_chars: 0,
+            unindented_lines: 0,
+            unindent_backspaces: 0,
+            unclosed_brackets: 0,
+        };
+        let chars: Vec<char> = text.chars().collect();
+        let mut careful = vec![false; chars.len()];
+        if cfg.careful_tokens && cfg.mode == TypingMode::Prose {
+            for token in careful_tokens(&chars) {
+                careful[token.range].fill(true);
+            }
+        }
+        estimator.careful_chars = careful.iter().filter(|&&careful| careful).count();
+        if cfg.code_editor.auto_close_brackets {
+            estimator.unclosed_brackets = unclosed_brackets(&chars)
+                .into_iter()
+                .filter(|&unclosed| unclosed)
+                .count();
+        }
+        let auto_indented = if cfg.code_editor.auto_indent {
+            estimator.auto_indent(&chars)
+        } else {
+            vec![false; chars.len()]
+        };
+
+        let (mut shift, mut altgr) = (false, false);
+        for (i, (c, careful)) in chars.iter().copied().zip(careful).enumerate() {
+            if auto_indented[i] {
+                continue;
+            }
+            match layout.strokes_for_output_char(c) {
+                Some(strokes) => {
+                    for stroke in strokes {
+                        estimator.modifier_changes +=
+                            usize::from(stroke.shift != shift) + usize::from(stroke.altgr != altgr);
+                        (shift, altgr) = (stroke.shift, stroke.altgr);
+                    }
+                    estimator.strokes += strokes.len();
+                }
+                None => estimator.strokes += 1,
+            }
+            if cfg.mode == TypingMode::Code {
+                estimator.pause_ms += code_pause_ms(c);
+            } else if !careful {
+                estimator.pause_ms += punctuation_pause_ms(c) + think_pause_ms(c);
+            }
+            estimator.spaces += usize::from(c == ' ');
+            estimator.mistypable_punctuation += usize::from(
+                c.is_ascii_punctuation()
+                    && layout
+                        .adjacent_chars(c)
+                        .iter()
+                        .any(char::is_ascii_punctuation),
+            );
+        }
+        estimator.final_periods = (1..chars.len())
+            .filter(|&i| {
+                chars[i] == '.'
+                    && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '\'')
+                    && chars.get(i + 1).is_none_or(|&next| next == '\n')
+            })
+            .count();
+
+        for paragraph in split_paragraphs(text) {
+            for word in paragraph.split(|c: char| !c.is_alphanumeric() && c != '\'') {
+                if !word.is_empty() {
+                    estimator.words += 1;
+                    estimator.word_chars += word.chars().count();
+                }
+                if !confusion_options(&word.to_lowercase()).is_empty() {
+                    estimator.confusable_words += 1;
+                    estimator.confusable_chars += word.chars().count();
+                }
+            }
+        }
+        Ok(estimator)
+    }
+
+    /// Which of `chars` an auto-indenting editor types: the indentation each line shares with
+    /// the one before. Counts the lines that have less.
+    fn auto_indent(&mut self, chars: &[char]) -> Vec<bool> {
+        let indent = |start: usize| {
+            chars[start..]
+                .iter()
+                .take_while(|&&c| c == ' ' || c == '\t')
+                .count()
+        };
+        let mut typed = vec![false; chars.len()];
+        let mut line_start = 0;
+        for newline in (0..chars.len()).filter(|&i| chars[i] == '\n') {
+            // The editor copies the indentation of the line Enter is pressed on.
+            let added = indent(line_start);
+            let next = newline + 1;
+            let kept = (0..added)
+                .take_while(|&k| chars.get(next + k) == Some(&chars[line_start + k]))
+                .count();
+            typed[next..next + kept].fill(true);
+            if added > kept {
+                self.unindented_lines += 1;
+                self.unindent_backspaces += added - kept;
+            }
+            line_start = next;
+        }
+        typed
+    }
+
+    /// Estimated duration of a plan for the text with `cfg`'s speed, error and fix rates and
+    /// review pauses.
+    pub fn estimate(&self, cfg: &PlannerConfig) -> DurationEstimate {
+        let delay_ms = |wpm: f64| 12_000.0 / wpm;
+        // Delays are proportional to 1/WPM, drawn uniformly from the range.
+        let expected_delay_ms = if cfg.wpm_max > cfg.wpm_min {
+            12_000.0 * (cfg.wpm_max / cfg.wpm_min).ln() / (cfg.wpm_max - cfg.wpm_min)
+        } else {
+            delay_ms(cfg.wpm_min)
+        };
+        DurationEstimate {
+            expected_ms: self.duration_ms(cfg, expected_delay_ms),
+            fastest_ms: self.duration_ms(cfg, delay_ms(cfg.wpm_max)),
+            slowest_ms: self.duration_ms(cfg, delay_ms(cfg.wpm_min)),
+        }
+    }
+
+    fn duration_ms(&self, cfg: &PlannerConfig, delay_ms: f64) -> f64 {
+        let cfg = &apply_error_mix(cfg.clone());
+        let hold_ms = cfg.rhythm.mean_hold_ms();
+        let char_ms = hold_ms + delay_ms;
+        let typing_ms = self.strokes as f64 * char_ms
+            + self.careful_chars as f64 * delay_ms * (1.0 / CAREFUL_SPEED - 1.0)
+            + self.modifier_changes as f64 * MODIFIER_CHANGE_MS
+            + self.pause_ms;
+        let review_ms = (cfg.review_pause_ms_min + cfg.review_pause_ms_max) as f64 / 2.0;
+
+        let nav_ms = 2.0 * FIX_DISTANCE_CHARS / NAV_CHARS_PER_STEP * (hold_ms + NAV_GAP_MS);
+        // A mistyped punctuation mark is deleted and typed again like a one-letter word; a
+        // left out period is only typed later, at the cost of moving there and back.
+        let retype_ms = hold_ms + BACKSPACE_GAP_MS + char_ms + NOTICE_PAUSE_MS;
+        let punctuation_fix_ms = cfg.immediate_fix_rate * retype_ms
+            + (1.0 - cfg.immediate_fix_rate) * (retype_ms + nav_ms + AFTER_FIX_PAUSE_MS);
+        let mut corrections_ms = self.mistypable_punctuation as f64 * cfg.punctuation_error_rate * punctuation_fix_ms
+                + self.final_periods as f64
+                    * cfg.missing_period_rate
+                    * (NOTICE_PAUSE_MS + nav_ms + AFTER_FIX_PAUSE_MS)
+            // A confused word is always fixed later.
+            + cfg.confusion_rate
+                * (self.confusable_chars as f64 * (hold_ms + BACKSPACE_GAP_MS + char_ms)
+                    + self.confusable_words as f64
+                        * (NOTICE_PAUSE_MS + nav_ms + AFTER_FIX_PAUSE_MS));
+        corrections_ms += self.unindented_lines as f64 * UNINDENT_PAUSE_MS
+            + self.unindent_backspaces as f64 * (hold_ms + BACKSPACE_GAP_MS)
+            + self.unclosed_brackets as f64 * (UNCLOSE_PAUSE_MS + hold_ms);
+        if cfg.error_rate_per_word > 0.0 {
+            let word_len = self.word_chars as f64 / self.words.max(1) as f64;
+            // The mistake is deleted and the word typed again.
+            let redo_ms = word_len * (hold_ms + BACKSPACE_GAP_MS + char_ms) + NOTICE_PAUSE_MS;
+            let later_ms = redo_ms + nav_ms + AFTER_FIX_PAUSE_MS;
+            let fix_ms =
+                cfg.immediate_fix_rate * redo_ms + (1.0 - cfg.immediate_fix_rate) * later_ms;
+            let mix = cfg.error_mix;
+            let word_mistakes = mix.adjacent_substitution
+                || mix.transposition
+                || (mix.word_variants && cfg.mode == TypingMode::Prose);
+            let double_spaces = mix.double_spaces && cfg.mode == TypingMode::Prose;
+            let double_space_ms =
+                hold_ms + BACKSPACE_GAP_MS + char_ms + nav_ms + AFTER_FIX_PAUSE_MS;
+
+            if word_mistakes {
+                corrections_ms += self.words as f64 * cfg.error_rate_per_word * fix_ms;
+            }
+            if double_spaces {
+                corrections_ms += self.spaces as f64 * DOUBLE_SPACE_RATE * double_space_ms;
+            }
+        }
+
+        START_PAUSE_MS + typing_ms + corrections_ms + review_ms + self.breaks_ms(cfg, hold_ms)
+    }
+
+    /// Expected session breaks, with the re-reads after the long ones.
+    fn breaks_ms(&self, cfg: &PlannerConfig, hold_ms: f64) -> f64 {
+        let (min, max) = (cfg.session_break_ms_min, cfg.session_break_ms_max);
+        // Break lengths are drawn uniformly from `min..=max`.
+        let reread_share = if cfg.reread_after_ms == 0 || cfg.reread_after_ms > max {
+            0.0
+        } else {
+            (max - cfg.reread_after_ms.max(min) + 1) as f64 / (max - min + 1) as f64
+        };
+        let break_ms =
+            (min + max) as f64 / 2.0 + reread_share * (REREAD_MS + REREAD_KEYS * hold_ms);
+        self.later_paragraphs as f64 * cfg.session_break_rate * break_ms
+    }
+}
+
+/// Expected pause after typing `c` (see the planner's `punctuation_pause_ms`).
+fn punctuation_pause_ms(c: char) -> f64 {
+    match c {
+        ',' | ';' | ':' => 140.0,
+        '.' | '!' | '?' => 320.0,
+        '\n' => 550.0,
+        _ => 0.0,
+    }
+}
+
+/// Expected pause after typing `c` in code (see the planner's `code_pause_ms` and
+/// `maybe_code_think_pause_ms`).
+fn code_pause_ms(c: char) -> f64 {
+    match c {
+        '\n' => 425.0 + 0.15 * 1900.0,
+        c if c.is_ascii_punctuation() => 90.0,
+        _ => 0.0,
+    }
+}
+
+/// Expected thinking pause after typing `c` (see the planner's `maybe_think_pause_ms`).
+fn think_pause_ms(c: char) -> f64 {
+    match c {
+        '.' | '!' | '?' => 0.12 * 1550.0,
+        '\n' => 0.10 * 1300.0,
+        _ => 0.0,
+    }
+}
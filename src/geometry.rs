@@ -0,0 +1,150 @@
+use crate::keyboard::{
+    KEY_0, KEY_1, KEY_102ND, KEY_A, KEY_APOSTROPHE, KEY_BACKSLASH, KEY_EQUAL, KEY_GRAVE, KEY_L,
+    KEY_LEFTBRACE, KEY_MINUS, KEY_P, KEY_Q, KEY_RIGHTBRACE, KEY_SEMICOLON, KEY_SLASH, KEY_Z,
+};
+
+/// Physical keyboard form factor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Geometry {
+    /// ANSI (104 keys): `\` sits above Enter and there is no key between left Shift and `Z`.
+    Ansi,
+    /// ISO (105 keys): the extra `<LSGT>` key (`KEY_102ND`) sits between left Shift and `Z`,
+    /// and the key above Enter on ANSI moves to the end of the home row.
+    #[default]
+    Iso,
+}
+
+impl Geometry {
+    /// XKB keyboard model used when compiling keymaps for this geometry.
+    pub fn xkb_model(self) -> &'static str {
+        match self {
+            Geometry::Ansi => "pc104",
+            Geometry::Iso => "pc105",
+        }
+    }
+
+    /// Geometry of an XKB keyboard model (as reported by the X server); unknown models are
+    /// assumed to be ISO.
+    pub fn from_xkb_model(model: &str) -> Self {
+        match model {
+            "pc101" | "pc104" | "pc104alt" => Geometry::Ansi,
+            _ => Geometry::Iso,
+        }
+    }
+
+    /// Whether keyboards of this geometry have the key with evdev `keycode`.
+    pub fn has_key(self, keycode: u32) -> bool {
+        self == Geometry::Iso || keycode != KEY_102ND
+    }
+}
+
+/// Physical position of a key: its row (0 = number row) and the horizontal offset of its left
+/// edge, in key widths.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyPosition {
+    pub row: u8,
+    pub x: f64,
+}
+
+/// Physical layout of the main typing block, keyed by evdev keycode.
+///
+/// Keycodes name physical keys regardless of the XKB layout, so typos generated from this
+/// geometry stay physically plausible on any layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardGeometry {
+    geometry: Geometry,
+    keys: Vec<(u32, KeyPosition)>,
+}
+
+impl KeyboardGeometry {
+    pub fn new(geometry: Geometry) -> Self {
+        let mut keys = Vec::new();
+        let mut row = |row: u8, start_x: f64, keycodes: &[u32]| {
+            for (idx, keycode) in keycodes.iter().enumerate() {
+                let x = start_x + idx as f64;
+                keys.push((*keycode, KeyPosition { row, x }));
+            }
+        };
+
+        let digits: Vec<u32> = (KEY_1..=KEY_0).collect();
+        row(0, 0.0, &[KEY_GRAVE]);
+        row(0, 1.0, &digits);
+        row(0, 11.0, &[KEY_MINUS, KEY_EQUAL]);
+
+        let top: Vec<u32> = (KEY_Q..=KEY_P).collect();
+        row(1, 1.5, &top);
+        row(1, 11.5, &[KEY_LEFTBRACE, KEY_RIGHTBRACE]);
+
+        let home: Vec<u32> = (KEY_A..=KEY_L).collect();
+        row(2, 1.75, &home);
+        row(2, 10.75, &[KEY_SEMICOLON, KEY_APOSTROPHE]);
+
+        let bottom: Vec<u32> = (KEY_Z..=KEY_SLASH).collect();
+        row(3, 2.25, &bottom);
+
+        match geometry {
+            Geometry::Ansi => row(1, 13.5, &[KEY_BACKSLASH]),
+            Geometry::Iso => {
+                row(2, 12.75, &[KEY_BACKSLASH]);
+                row(3, 1.25, &[KEY_102ND]);
+            }
+        }
+
+        Self { geometry, keys }
+    }
+
+    /// Standard ANSI row-staggered keyboard.
+    pub fn ansi() -> Self {
+        Self::new(Geometry::Ansi)
+    }
+
+    /// Standard ISO row-staggered keyboard.
+    pub fn iso() -> Self {
+        Self::new(Geometry::Iso)
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    /// Keys of the typing block and their positions, row by row.
+    pub fn keys(&self) -> impl Iterator<Item = (u32, KeyPosition)> + '_ {
+        self.keys.iter().copied()
+    }
+
+    pub fn position(&self, keycode: u32) -> Option<KeyPosition> {
+        self.keys
+            .iter()
+            .find(|(k, _)| *k == keycode)
+            .map(|(_, position)| *position)
+    }
+
+    /// Keys touching `keycode`: its left/right neighbors and the overlapping keys in the rows
+    /// above and below, in keycode order.
+    ///
+    /// Letter-row keys do not count the number row as adjacent; a finger slipping off a letter
+    /// rarely reaches it.
+    pub fn neighbors(&self, keycode: u32) -> Vec<u32> {
+        let Some(pos) = self.position(keycode) else {
+            return Vec::new();
+        };
+
+        let mut out: Vec<u32> = self
+            .keys
+            .iter()
+            .filter(|(other, _)| *other != keycode)
+            .filter(|(_, other)| pos.row == 0 || other.row != 0)
+            .filter(|(_, other)| {
+                let dx = (other.x - pos.x).abs();
+                match other.row.abs_diff(pos.row) {
+                    0 => dx <= 1.0,
+                    1 => dx < 1.0,
+                    _ => false,
+                }
+            })
+            .map(|(other, _)| *other)
+            .collect();
+        out.sort_unstable();
+        out
+    }
+}
@@ -0,0 +1,250 @@
+//! Per-key press counts and hold times of a plan, and an SVG keyboard heatmap of them
+//! (`drafter stats --key-usage`, `--heatmap`).
+//!
+//! Natural typing presses keys in proportion to the letter frequencies of the text, with
+//! Space, Shift and Backspace close behind; a heatmap that looks mechanical (every key the
+//! same, or holds all alike) is easy to spot.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::geometry::{Geometry, KeyboardGeometry};
+use crate::keyboard::{
+    KeyStroke, KEY_BACKSLASH, KEY_BACKSPACE, KEY_CAPSLOCK, KEY_DELETE, KEY_DOWN, KEY_END,
+    KEY_ENTER, KEY_ESC, KEY_HOME, KEY_LEFT, KEY_LEFTALT, KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_RIGHT,
+    KEY_RIGHTALT, KEY_RIGHTCTRL, KEY_RIGHTSHIFT, KEY_SPACE, KEY_TAB, KEY_UP,
+};
+use crate::layout::{Decoded, KeyboardLayout};
+use crate::model::{Action, KeyState, Plan};
+
+/// Width and height of a one-unit key in the SVG, in pixels.
+const KEY_UNIT_PX: f64 = 44.0;
+/// Gap around each key in the SVG.
+const KEY_GAP_PX: f64 = 3.0;
+
+/// Labels of keys that do not type a character.
+const KEY_NAMES: &[(u32, &str)] = &[
+    (KEY_ESC, "Esc"),
+    (KEY_BACKSPACE, "Bksp"),
+    (KEY_TAB, "Tab"),
+    (KEY_ENTER, "Enter"),
+    (KEY_CAPSLOCK, "Caps"),
+    (KEY_LEFTSHIFT, "Shift"),
+    (KEY_RIGHTSHIFT, "Shift"),
+    (KEY_LEFTCTRL, "Ctrl"),
+    (KEY_RIGHTCTRL, "Ctrl"),
+    (KEY_LEFTALT, "Alt"),
+    (KEY_SPACE, "Space"),
+    (KEY_DELETE, "Del"),
+    (KEY_HOME, "Home"),
+    (KEY_END, "End"),
+    (KEY_LEFT, "←"),
+    (KEY_RIGHT, "→"),
+    (KEY_UP, "↑"),
+    (KEY_DOWN, "↓"),
+];
+
+/// Keys outside the typing block of [`KeyboardGeometry`], drawn around it: keycode, row, left
+/// edge and width in key units, on both geometries.
+const OUTER_KEYS: &[(u32, f64, f64, f64)] = &[
+    (KEY_BACKSPACE, 0.0, 13.0, 2.0),
+    (KEY_TAB, 1.0, 0.0, 1.5),
+    (KEY_CAPSLOCK, 2.0, 0.0, 1.75),
+    (KEY_RIGHTSHIFT, 3.0, 12.25, 2.75),
+    (KEY_LEFTCTRL, 4.0, 0.0, 1.25),
+    (KEY_LEFTALT, 4.0, 2.5, 1.25),
+    (KEY_SPACE, 4.0, 3.75, 6.25),
+    (KEY_RIGHTALT, 4.0, 10.0, 1.25),
+    (KEY_RIGHTCTRL, 4.0, 13.75, 1.25),
+    (KEY_HOME, 0.0, 15.5, 1.0),
+    (KEY_END, 0.0, 16.5, 1.0),
+    (KEY_DELETE, 1.0, 15.5, 1.0),
+    (KEY_UP, 3.0, 16.5, 1.0),
+    (KEY_LEFT, 4.0, 15.5, 1.0),
+    (KEY_DOWN, 4.0, 16.5, 1.0),
+    (KEY_RIGHT, 4.0, 17.5, 1.0),
+];
+
+/// Width of the drawing, in key units.
+const KEYBOARD_WIDTH_UNITS: f64 = 18.5;
+/// Rows of keys in the drawing.
+const KEYBOARD_ROWS: f64 = 5.0;
+
+/// How often one key is pressed in a plan, and for how long.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KeyUsage {
+    pub keycode: u32,
+    /// What the key types on the plan's layout without modifiers, or its name.
+    pub label: String,
+    pub presses: usize,
+    /// Fraction of all key presses.
+    pub share: f64,
+    /// Time held over all presses released in the plan.
+    pub total_hold_ms: u64,
+    pub mean_hold_ms: f64,
+}
+
+/// Press counts and hold times of every key `plan` presses, most pressed first (by keycode
+/// among equals).
+pub fn key_usage(plan: &Plan) -> Result<Vec<KeyUsage>> {
+    let layout = KeyboardLayout::for_plan_config(&plan.config)?;
+    // keycode -> (presses, released presses, total hold).
+    let mut counts: HashMap<u32, (usize, usize, u64)> = HashMap::new();
+    let mut pressed_at: HashMap<u32, u64> = HashMap::new();
+    let mut now_ms = 0u64;
+    for action in plan.actions.iter().flat_map(Action::expand) {
+        match action {
+            Action::Wait { ms } => now_ms = now_ms.saturating_add(ms),
+            Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            } => {
+                if pressed_at.insert(keycode, now_ms).is_none() {
+                    counts.entry(keycode).or_default().0 += 1;
+                }
+            }
+            Action::Key {
+                keycode,
+                state: KeyState::Released,
+            } => {
+                if let Some(at) = pressed_at.remove(&keycode) {
+                    let entry = counts.entry(keycode).or_default();
+                    entry.1 += 1;
+                    entry.2 += now_ms - at;
+                }
+            }
+            Action::Modifiers { .. } | Action::Checkpoint { .. } | Action::KeyRepeat { .. } => {}
+        }
+    }
+
+    let total: usize = counts.values().map(|(presses, _, _)| presses).sum();
+    let mut usage: Vec<KeyUsage> = counts
+        .into_iter()
+        .map(|(keycode, (presses, released, total_hold_ms))| KeyUsage {
+            keycode,
+            label: key_label(&layout, keycode),
+            presses,
+            share: presses as f64 / total as f64,
+            total_hold_ms,
+            mean_hold_ms: if released == 0 {
+                0.0
+            } else {
+                total_hold_ms as f64 / released as f64
+            },
+        })
+        .collect();
+    usage.sort_by(|a, b| b.presses.cmp(&a.presses).then(a.keycode.cmp(&b.keycode)));
+    Ok(usage)
+}
+
+/// What `keycode` types on `layout` without modifiers, its name for keys that type no
+/// character, or its number.
+pub fn key_label(layout: &KeyboardLayout, keycode: u32) -> String {
+    if Some(keycode) == layout.altgr_keycode() {
+        return "AltGr".to_string();
+    }
+    if let Some((_, name)) = KEY_NAMES.iter().find(|(named, _)| *named == keycode) {
+        return name.to_string();
+    }
+    let stroke = KeyStroke {
+        keycode,
+        shift: false,
+        altgr: false,
+    };
+    match layout.decoder().feed(stroke) {
+        Decoded::Char(c) if !c.is_control() => c.to_string(),
+        _ => format!("#{keycode}"),
+    }
+}
+
+/// An SVG drawing of a keyboard with `geometry`, each key shaded by its share of `usage`'s
+/// presses (darkest for the most pressed key) and labelled with its press count.
+pub fn heatmap_svg(usage: &[KeyUsage], layout: &KeyboardLayout, geometry: Geometry) -> String {
+    let typing_block = KeyboardGeometry::new(geometry);
+    let mut keys: Vec<(u32, f64, f64, f64, f64)> = typing_block
+        .keys()
+        .map(|(keycode, position)| {
+            let width = if geometry == Geometry::Ansi && keycode == KEY_BACKSLASH {
+                1.5
+            } else {
+                1.0
+            };
+            (keycode, f64::from(position.row), position.x, width, 1.0)
+        })
+        .collect();
+    keys.extend(
+        OUTER_KEYS
+            .iter()
+            .map(|&(keycode, row, x, width)| (keycode, row, x, width, 1.0)),
+    );
+    keys.extend(match geometry {
+        Geometry::Ansi => [
+            (KEY_ENTER, 2.0, 12.75, 2.25, 1.0),
+            (KEY_LEFTSHIFT, 3.0, 0.0, 2.25, 1.0),
+        ],
+        Geometry::Iso => [
+            (KEY_ENTER, 1.0, 13.75, 1.25, 2.0),
+            (KEY_LEFTSHIFT, 3.0, 0.0, 1.25, 1.0),
+        ],
+    });
+
+    let most = usage
+        .iter()
+        .map(|key| key.presses)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let width = KEYBOARD_WIDTH_UNITS * KEY_UNIT_PX;
+    let height = KEYBOARD_ROWS * KEY_UNIT_PX;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\" font-family=\"sans-serif\">\n"
+    );
+    for (keycode, row, x, w, h) in keys {
+        let presses = usage
+            .iter()
+            .find(|key| key.keycode == keycode)
+            .map_or(0, |key| key.presses);
+        let (r, g, b) = heat_color(presses as f64 / most as f64);
+        let (left, top) = (x * KEY_UNIT_PX + KEY_GAP_PX, row * KEY_UNIT_PX + KEY_GAP_PX);
+        let (key_w, key_h) = (
+            w * KEY_UNIT_PX - 2.0 * KEY_GAP_PX,
+            h * KEY_UNIT_PX - 2.0 * KEY_GAP_PX,
+        );
+        let text_x = left + key_w / 2.0;
+        let label = xml_escape(&key_label(layout, keycode));
+        let _ = writeln!(
+            svg,
+            "  <g><title>{label} ({keycode}): {presses}</title>\
+             <rect x=\"{left}\" y=\"{top}\" width=\"{key_w}\" height=\"{key_h}\" rx=\"4\" \
+             fill=\"#{r:02x}{g:02x}{b:02x}\" stroke=\"#999\"/>\
+             <text x=\"{text_x}\" y=\"{}\" font-size=\"13\" text-anchor=\"middle\">{label}</text>\
+             <text x=\"{text_x}\" y=\"{}\" font-size=\"9\" text-anchor=\"middle\" \
+             fill=\"#444\">{presses}</text></g>",
+            top + 17.0,
+            top + key_h - 6.0,
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Light grey for unused keys through to dark red for the most used one.
+fn heat_color(heat: f64) -> (u8, u8, u8) {
+    let (cold, hot) = ([0xf4, 0xf4, 0xf4], [0xd7, 0x30, 0x1f]);
+    let mix = |idx: usize| {
+        let (cold, hot) = (f64::from(cold[idx]), f64::from(hot[idx]));
+        (cold + (hot - cold) * heat.clamp(0.0, 1.0)).round() as u8
+    };
+    (mix(0), mix(1), mix(2))
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
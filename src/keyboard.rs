@@ -1,9 +1,9 @@
-use rand::Rng;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct KeyStroke {
     pub keycode: u32,
     pub shift: bool,
+    /// Whether the layout's AltGr (level 3) modifier must be held.
+    pub altgr: bool,
 }
 
 // Linux evdev keycodes (see linux/input-event-codes.h)
@@ -78,6 +78,10 @@ pub const KEY_RIGHTSHIFT: u32 = 54;
 
 pub const KEY_LEFTALT: u32 = 56;
 pub const KEY_SPACE: u32 = 57;
+pub const KEY_CAPSLOCK: u32 = 58;
+
+/// Extra key next to left Shift on ISO keyboards (`<LSGT>`).
+pub const KEY_102ND: u32 = 86;
 
 pub const KEY_DELETE: u32 = 111;
 
@@ -89,6 +93,23 @@ pub const KEY_UP: u32 = 103;
 pub const KEY_HOME: u32 = 102;
 pub const KEY_END: u32 = 107;
 
+/// Whether a touch typist presses `keycode` with the left hand.
+///
+/// Uses the usual split between `5`/`6`, `T`/`Y`, `G`/`H` and `B`/`N`; keys outside the main
+/// typing block count as right-hand keys.
+pub fn is_left_hand_keycode(keycode: u32) -> bool {
+    matches!(
+        keycode,
+        KEY_GRAVE
+            | KEY_1..=KEY_5
+            | KEY_TAB..=KEY_T
+            | KEY_A..=KEY_G
+            | KEY_LEFTSHIFT
+            | KEY_102ND
+            | KEY_Z..=KEY_B
+    )
+}
+
 pub fn typed_char_for_output_char(c: char) -> Option<char> {
     match c {
         '\n' => Some('\n'),
@@ -124,443 +145,484 @@ pub fn char_to_keystroke(c: char) -> Option<KeyStroke> {
         'a' => KeyStroke {
             keycode: KEY_A,
             shift: false,
+            altgr: false,
         },
         'b' => KeyStroke {
             keycode: KEY_B,
             shift: false,
+            altgr: false,
         },
         'c' => KeyStroke {
             keycode: KEY_C,
             shift: false,
+            altgr: false,
         },
         'd' => KeyStroke {
             keycode: KEY_D,
             shift: false,
+            altgr: false,
         },
         'e' => KeyStroke {
             keycode: KEY_E,
             shift: false,
+            altgr: false,
         },
         'f' => KeyStroke {
             keycode: KEY_F,
             shift: false,
+            altgr: false,
         },
         'g' => KeyStroke {
             keycode: KEY_G,
             shift: false,
+            altgr: false,
         },
         'h' => KeyStroke {
             keycode: KEY_H,
             shift: false,
+            altgr: false,
         },
         'i' => KeyStroke {
             keycode: KEY_I,
             shift: false,
+            altgr: false,
         },
         'j' => KeyStroke {
             keycode: KEY_J,
             shift: false,
+            altgr: false,
         },
         'k' => KeyStroke {
             keycode: KEY_K,
             shift: false,
+            altgr: false,
         },
         'l' => KeyStroke {
             keycode: KEY_L,
             shift: false,
+            altgr: false,
         },
         'm' => KeyStroke {
             keycode: KEY_M,
             shift: false,
+            altgr: false,
         },
         'n' => KeyStroke {
             keycode: KEY_N,
             shift: false,
+            altgr: false,
         },
         'o' => KeyStroke {
             keycode: KEY_O,
             shift: false,
+            altgr: false,
         },
         'p' => KeyStroke {
             keycode: KEY_P,
             shift: false,
+            altgr: false,
         },
         'q' => KeyStroke {
             keycode: KEY_Q,
             shift: false,
+            altgr: false,
         },
         'r' => KeyStroke {
             keycode: KEY_R,
             shift: false,
+            altgr: false,
         },
         's' => KeyStroke {
             keycode: KEY_S,
             shift: false,
+            altgr: false,
         },
         't' => KeyStroke {
             keycode: KEY_T,
             shift: false,
+            altgr: false,
         },
         'u' => KeyStroke {
             keycode: KEY_U,
             shift: false,
+            altgr: false,
         },
         'v' => KeyStroke {
             keycode: KEY_V,
             shift: false,
+            altgr: false,
         },
         'w' => KeyStroke {
             keycode: KEY_W,
             shift: false,
+            altgr: false,
         },
         'x' => KeyStroke {
             keycode: KEY_X,
             shift: false,
+            altgr: false,
         },
         'y' => KeyStroke {
             keycode: KEY_Y,
             shift: false,
+            altgr: false,
         },
         'z' => KeyStroke {
             keycode: KEY_Z,
             shift: false,
+            altgr: false,
         },
         'A' => KeyStroke {
             keycode: KEY_A,
             shift: true,
+            altgr: false,
         },
         'B' => KeyStroke {
             keycode: KEY_B,
             shift: true,
+            altgr: false,
         },
         'C' => KeyStroke {
             keycode: KEY_C,
             shift: true,
+            altgr: false,
         },
         'D' => KeyStroke {
             keycode: KEY_D,
             shift: true,
+            altgr: false,
         },
         'E' => KeyStroke {
             keycode: KEY_E,
             shift: true,
+            altgr: false,
         },
         'F' => KeyStroke {
             keycode: KEY_F,
             shift: true,
+            altgr: false,
         },
         'G' => KeyStroke {
             keycode: KEY_G,
             shift: true,
+            altgr: false,
         },
         'H' => KeyStroke {
             keycode: KEY_H,
             shift: true,
+            altgr: false,
         },
         'I' => KeyStroke {
             keycode: KEY_I,
             shift: true,
+            altgr: false,
         },
         'J' => KeyStroke {
             keycode: KEY_J,
             shift: true,
+            altgr: false,
         },
         'K' => KeyStroke {
             keycode: KEY_K,
             shift: true,
+            altgr: false,
         },
         'L' => KeyStroke {
             keycode: KEY_L,
             shift: true,
+            altgr: false,
         },
         'M' => KeyStroke {
             keycode: KEY_M,
             shift: true,
+            altgr: false,
         },
         'N' => KeyStroke {
             keycode: KEY_N,
             shift: true,
+            altgr: false,
         },
         'O' => KeyStroke {
             keycode: KEY_O,
             shift: true,
+            altgr: false,
         },
         'P' => KeyStroke {
             keycode: KEY_P,
             shift: true,
+            altgr: false,
         },
         'Q' => KeyStroke {
             keycode: KEY_Q,
             shift: true,
+            altgr: false,
         },
         'R' => KeyStroke {
             keycode: KEY_R,
             shift: true,
+            altgr: false,
         },
         'S' => KeyStroke {
             keycode: KEY_S,
             shift: true,
+            altgr: false,
         },
         'T' => KeyStroke {
             keycode: KEY_T,
             shift: true,
+            altgr: false,
         },
         'U' => KeyStroke {
             keycode: KEY_U,
             shift: true,
+            altgr: false,
         },
         'V' => KeyStroke {
             keycode: KEY_V,
             shift: true,
+            altgr: false,
         },
         'W' => KeyStroke {
             keycode: KEY_W,
             shift: true,
+            altgr: false,
         },
         'X' => KeyStroke {
             keycode: KEY_X,
             shift: true,
+            altgr: false,
         },
         'Y' => KeyStroke {
             keycode: KEY_Y,
             shift: true,
+            altgr: false,
         },
         'Z' => KeyStroke {
             keycode: KEY_Z,
             shift: true,
+            altgr: false,
         },
         '1' => KeyStroke {
             keycode: KEY_1,
             shift: false,
+            altgr: false,
         },
         '2' => KeyStroke {
             keycode: KEY_2,
             shift: false,
+            altgr: false,
         },
         '3' => KeyStroke {
             keycode: KEY_3,
             shift: false,
+            altgr: false,
         },
         '4' => KeyStroke {
             keycode: KEY_4,
             shift: false,
+            altgr: false,
         },
         '5' => KeyStroke {
             keycode: KEY_5,
             shift: false,
+            altgr: false,
         },
         '6' => KeyStroke {
             keycode: KEY_6,
             shift: false,
+            altgr: false,
         },
         '7' => KeyStroke {
             keycode: KEY_7,
             shift: false,
+            altgr: false,
         },
         '8' => KeyStroke {
             keycode: KEY_8,
             shift: false,
+            altgr: false,
         },
         '9' => KeyStroke {
             keycode: KEY_9,
             shift: false,
+            altgr: false,
         },
         '0' => KeyStroke {
             keycode: KEY_0,
             shift: false,
+            altgr: false,
         },
         '!' => KeyStroke {
             keycode: KEY_1,
             shift: true,
+            altgr: false,
         },
         '@' => KeyStroke {
             keycode: KEY_2,
             shift: true,
+            altgr: false,
         },
         '#' => KeyStroke {
             keycode: KEY_3,
             shift: true,
+            altgr: false,
         },
         '$' => KeyStroke {
             keycode: KEY_4,
             shift: true,
+            altgr: false,
         },
         '%' => KeyStroke {
             keycode: KEY_5,
             shift: true,
+            altgr: false,
         },
         '^' => KeyStroke {
             keycode: KEY_6,
             shift: true,
+            altgr: false,
         },
         '&' => KeyStroke {
             keycode: KEY_7,
             shift: true,
+            altgr: false,
         },
         '*' => KeyStroke {
             keycode: KEY_8,
             shift: true,
+            altgr: false,
         },
         '(' => KeyStroke {
             keycode: KEY_9,
             shift: true,
+            altgr: false,
         },
         ')' => KeyStroke {
             keycode: KEY_0,
             shift: true,
+            altgr: false,
         },
         '-' => KeyStroke {
             keycode: KEY_MINUS,
             shift: false,
+            altgr: false,
         },
         '_' => KeyStroke {
             keycode: KEY_MINUS,
             shift: true,
+            altgr: false,
         },
         '=' => KeyStroke {
             keycode: KEY_EQUAL,
             shift: false,
+            altgr: false,
         },
         '+' => KeyStroke {
             keycode: KEY_EQUAL,
             shift: true,
+            altgr: false,
         },
         '[' => KeyStroke {
             keycode: KEY_LEFTBRACE,
             shift: false,
+            altgr: false,
         },
         '{' => KeyStroke {
             keycode: KEY_LEFTBRACE,
             shift: true,
+            altgr: false,
         },
         ']' => KeyStroke {
             keycode: KEY_RIGHTBRACE,
             shift: false,
+            altgr: false,
         },
         '}' => KeyStroke {
             keycode: KEY_RIGHTBRACE,
             shift: true,
+            altgr: false,
         },
         '\\' => KeyStroke {
             keycode: KEY_BACKSLASH,
             shift: false,
+            altgr: false,
         },
         '|' => KeyStroke {
             keycode: KEY_BACKSLASH,
             shift: true,
+            altgr: false,
         },
         ';' => KeyStroke {
             keycode: KEY_SEMICOLON,
             shift: false,
+            altgr: false,
         },
         ':' => KeyStroke {
             keycode: KEY_SEMICOLON,
             shift: true,
+            altgr: false,
         },
         '\'' => KeyStroke {
             keycode: KEY_APOSTROPHE,
             shift: false,
+            altgr: false,
         },
         '"' => KeyStroke {
             keycode: KEY_APOSTROPHE,
             shift: true,
+            altgr: false,
         },
         '`' => KeyStroke {
             keycode: KEY_GRAVE,
             shift: false,
+            altgr: false,
         },
         '~' => KeyStroke {
             keycode: KEY_GRAVE,
             shift: true,
+            altgr: false,
         },
         ',' => KeyStroke {
             keycode: KEY_COMMA,
             shift: false,
+            altgr: false,
         },
         '<' => KeyStroke {
             keycode: KEY_COMMA,
             shift: true,
+            altgr: false,
         },
         '.' => KeyStroke {
             keycode: KEY_DOT,
             shift: false,
+            altgr: false,
         },
         '>' => KeyStroke {
             keycode: KEY_DOT,
             shift: true,
+            altgr: false,
         },
         '/' => KeyStroke {
             keycode: KEY_SLASH,
             shift: false,
+            altgr: false,
         },
         '?' => KeyStroke {
             keycode: KEY_SLASH,
             shift: true,
+            altgr: false,
         },
         ' ' => KeyStroke {
             keycode: KEY_SPACE,
             shift: false,
+            altgr: false,
         },
         '\n' => KeyStroke {
             keycode: KEY_ENTER,
             shift: false,
+            altgr: false,
         },
         _ => return None,
     };
     Some(stroke)
 }
-
-pub fn qwerty_adjacent_char(c: char, rng: &mut impl Rng) -> Option<char> {
-    let (base, make_upper) = if c.is_ascii_uppercase() {
-        (c.to_ascii_lowercase(), true)
-    } else {
-        (c, false)
-    };
-
-    let neighbors: &[char] = match base {
-        'a' => &['q', 'w', 's', 'z', 'x'],
-        'b' => &['v', 'g', 'h', 'n'],
-        'c' => &['x', 'd', 'f', 'v'],
-        'd' => &['s', 'e', 'r', 'f', 'c', 'x'],
-        'e' => &['w', 's', 'd', 'r'],
-        'f' => &['d', 'r', 't', 'g', 'v', 'c'],
-        'g' => &['f', 't', 'y', 'h', 'b', 'v'],
-        'h' => &['g', 'y', 'u', 'j', 'n', 'b'],
-        'i' => &['u', 'j', 'k', 'o'],
-        'j' => &['h', 'u', 'i', 'k', 'm', 'n'],
-        'k' => &['j', 'i', 'o', 'l', ',', 'm'],
-        'l' => &['k', 'o', 'p', ';', '.'],
-        'm' => &['n', 'j', 'k', ','],
-        'n' => &['b', 'h', 'j', 'm'],
-        'o' => &['i', 'k', 'l', 'p'],
-        'p' => &['o', 'l', '['],
-        'q' => &['w', 'a'],
-        'r' => &['e', 'd', 'f', 't'],
-        's' => &['a', 'w', 'e', 'd', 'x', 'z'],
-        't' => &['r', 'f', 'g', 'y'],
-        'u' => &['y', 'h', 'j', 'i'],
-        'v' => &['c', 'f', 'g', 'b'],
-        'w' => &['q', 'a', 's', 'e'],
-        'x' => &['z', 's', 'd', 'c'],
-        'y' => &['t', 'g', 'h', 'u'],
-        'z' => &['a', 's', 'x'],
-        '1' => &['2', 'q'],
-        '2' => &['1', '3', 'q', 'w'],
-        '3' => &['2', '4', 'w', 'e'],
-        '4' => &['3', '5', 'e', 'r'],
-        '5' => &['4', '6', 'r', 't'],
-        '6' => &['5', '7', 't', 'y'],
-        '7' => &['6', '8', 'y', 'u'],
-        '8' => &['7', '9', 'u', 'i'],
-        '9' => &['8', '0', 'i', 'o'],
-        '0' => &['9', 'o', 'p'],
-        _ => return None,
-    };
-
-    let chosen = neighbors[rng.gen_range(0..neighbors.len())];
-    Some(if make_upper {
-        chosen.to_ascii_uppercase()
-    } else {
-        chosen
-    })
-}
@@ -1,8 +1,72 @@
-use anyhow::{anyhow, Result};
+use std::fmt::Write as _;
+
+use anyhow::{anyhow, ensure, Result};
 use xkbcommon::xkb;
 
+use crate::geometry::Geometry;
+
 pub const KEYMAP_FORMAT_XKB_V1: u32 = 1;
 
+/// Evdev keycodes with generic `<I…>` names that standard layouts leave unbound.
+///
+/// Custom keymaps bind characters the layout cannot type to these keys (plain and Shift level).
+pub(crate) const SPARE_KEYCODES: [u32; 12] =
+    [112, 141, 146, 160, 170, 175, 176, 209, 211, 214, 222, 240];
+
+/// XKB rule names (RMLVO minus rules/model) selecting the layout to plan for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutSpec {
+    /// XKB layout name (e.g. `us`, `de`, `fr`).
+    pub layout: String,
+    /// XKB layout variant (e.g. `nodeadkeys`, `intl`); empty for the default variant.
+    pub variant: String,
+    /// Optional XKB options (e.g. `compose:ralt` to enable a Compose key).
+    pub options: Option<String>,
+    /// Physical keyboard geometry; decides whether the ISO `<LSGT>` key may be typed.
+    pub geometry: Geometry,
+}
+
+impl Default for LayoutSpec {
+    fn default() -> Self {
+        Self {
+            layout: "us".to_string(),
+            variant: String::new(),
+            options: None,
+            geometry: Geometry::default(),
+        }
+    }
+}
+
+impl LayoutSpec {
+    pub fn new(layout: impl Into<String>) -> Self {
+        Self {
+            layout: layout.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Layout label in the usual XKB `layout(variant)` notation, as stored in `PlanConfig.layout`.
+    pub fn label(&self) -> String {
+        if self.variant.is_empty() {
+            self.layout.clone()
+        } else {
+            format!("{}({})", self.layout, self.variant)
+        }
+    }
+
+    /// Parse a `layout(variant)` label back into a spec (options are not part of the label).
+    pub fn from_label(label: &str) -> Self {
+        match label.split_once('(') {
+            Some((layout, rest)) => Self {
+                layout: layout.to_string(),
+                variant: rest.trim_end_matches(')').to_string(),
+                ..Self::default()
+            },
+            None => Self::new(label),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct KeymapInfo {
     pub layout: String,
@@ -10,22 +74,38 @@ pub struct KeymapInfo {
     pub keymap: String,
     pub shift_mask: u32,
     pub ctrl_mask: u32,
+    /// Modifier mask produced by the layout's AltGr (ISO_Level3_Shift) key, or 0 if none.
+    pub altgr_mask: u32,
+    /// Modifier mask of the CapsLock lock (`Lock`), or 0 if the keymap has none.
+    pub caps_mask: u32,
 }
 
 pub fn us_qwerty_keymap() -> Result<KeymapInfo> {
+    keymap_for_layout(&LayoutSpec::default())
+}
+
+pub fn keymap_for_layout(spec: &LayoutSpec) -> Result<KeymapInfo> {
+    let keymap = compile_keymap(spec)?;
+    keymap_info(&keymap, spec.label())
+}
+
+pub(crate) fn compile_keymap(spec: &LayoutSpec) -> Result<xkb::Keymap> {
     let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
 
-    let keymap = xkb::Keymap::new_from_names(
+    let model = spec.geometry.xkb_model();
+    xkb::Keymap::new_from_names(
         &context,
         "evdev",
-        "pc105",
-        "us",
-        "",
-        None,
+        model,
+        spec.layout.as_str(),
+        spec.variant.as_str(),
+        spec.options.clone(),
         xkb::KEYMAP_COMPILE_NO_FLAGS,
     )
-    .ok_or_else(|| anyhow!("failed to build xkb keymap for us/pc105"))?;
+    .ok_or_else(|| anyhow!("failed to build xkb keymap for {}/{model}", spec.label()))
+}
 
+pub(crate) fn keymap_info(keymap: &xkb::Keymap, layout: String) -> Result<KeymapInfo> {
     let keymap_str = keymap.get_as_string(xkb::KEYMAP_FORMAT_TEXT_V1);
 
     let shift_index = keymap.mod_get_index(xkb::MOD_NAME_SHIFT);
@@ -45,11 +125,93 @@ pub fn us_qwerty_keymap() -> Result<KeymapInfo> {
         .checked_shl(ctrl_index)
         .ok_or_else(|| anyhow!("Control modifier index out of range"))?;
 
+    let altgr_mask = level3_keycode(keymap)
+        .map(|evdev| {
+            let mut state = xkb::State::new(keymap);
+            state.update_key(xkb::Keycode::new(evdev + 8), xkb::KeyDirection::Down);
+            state.serialize_mods(xkb::STATE_MODS_DEPRESSED)
+        })
+        .unwrap_or(0);
+
+    let caps_index = keymap.mod_get_index(xkb::MOD_NAME_CAPS);
+    let caps_mask = if caps_index == xkb::MOD_INVALID {
+        0
+    } else {
+        1u32.checked_shl(caps_index).unwrap_or(0)
+    };
+
     Ok(KeymapInfo {
-        layout: "us".to_string(),
+        layout,
         keymap_format: KEYMAP_FORMAT_XKB_V1,
         keymap: keymap_str,
         shift_mask,
         ctrl_mask,
+        altgr_mask,
+        caps_mask,
+    })
+}
+
+/// Extend `keymap` by binding `chars` to unused [`SPARE_KEYCODES`], two characters per key.
+pub(crate) fn extend_keymap_with_chars(
+    keymap: &xkb::Keymap,
+    chars: &[char],
+) -> Result<xkb::Keymap> {
+    let spare_names: Vec<String> = SPARE_KEYCODES
+        .iter()
+        .map(|evdev| xkb::Keycode::new(evdev + 8))
+        .filter(|keycode| keymap.num_layouts_for_key(*keycode) == 0)
+        .filter_map(|keycode| keymap.key_get_name(keycode).map(str::to_string))
+        .collect();
+
+    ensure!(
+        chars.len() <= spare_names.len() * 2,
+        "custom keymap can bind at most {} extra characters, but the text needs {}",
+        spare_names.len() * 2,
+        chars.len()
+    );
+
+    let mut symbols = String::new();
+    for (name, pair) in spare_names.iter().zip(chars.chunks(2)) {
+        let mut keysyms = Vec::with_capacity(pair.len());
+        for c in pair {
+            let sym = xkb::utf32_to_keysym(*c as u32);
+            ensure!(
+                sym.raw() != xkb::keysyms::KEY_NoSymbol,
+                "no XKB keysym for {c:?} (U+{:04X})",
+                *c as u32
+            );
+            keysyms.push(xkb::keysym_get_name(sym));
+        }
+        let _ = writeln!(symbols, "\tkey <{name}> {{ [ {} ] }};", keysyms.join(", "));
+    }
+
+    let mut source = keymap.get_as_string(xkb::KEYMAP_FORMAT_TEXT_V1);
+    let section = source
+        .find("xkb_symbols")
+        .ok_or_else(|| anyhow!("xkb keymap has no xkb_symbols section"))?;
+    let section_end = source[section..]
+        .find("\n};")
+        .map(|offset| section + offset + 1)
+        .ok_or_else(|| anyhow!("unterminated xkb_symbols section"))?;
+    source.insert_str(section_end, &symbols);
+
+    let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+    xkb::Keymap::new_from_string(
+        &context,
+        source,
+        xkb::KEYMAP_FORMAT_TEXT_V1,
+        xkb::KEYMAP_COMPILE_NO_FLAGS,
+    )
+    .ok_or_else(|| anyhow!("failed to compile custom xkb keymap"))
+}
+
+/// Find the evdev keycode of the key acting as AltGr (ISO_Level3_Shift), preferring Right Alt.
+pub(crate) fn level3_keycode(keymap: &xkb::Keymap) -> Option<u32> {
+    let candidates = [crate::keyboard::KEY_RIGHTALT, crate::keyboard::KEY_LEFTALT];
+    candidates.into_iter().find(|evdev| {
+        keymap
+            .key_get_syms_by_level(xkb::Keycode::new(evdev + 8), 0, 0)
+            .iter()
+            .any(|sym| sym.raw() == xkb::keysyms::KEY_ISO_Level3_Shift)
     })
 }
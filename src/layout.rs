@@ -0,0 +1,626 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+
+use anyhow::{anyhow, ensure, Result};
+use xkbcommon::xkb;
+
+use crate::geometry::{Geometry, KeyboardGeometry};
+use crate::keyboard::{char_to_keystroke, KeyStroke, KEY_102ND, KEY_ENTER, KEY_RIGHTALT, KEY_TAB};
+use crate::keymap::{
+    compile_keymap, extend_keymap_with_chars, keymap_info, level3_keycode, KeymapInfo, LayoutSpec,
+    KEYMAP_FORMAT_XKB_V1, SPARE_KEYCODES,
+};
+use crate::model::PlanConfig;
+
+/// Locale whose Compose table drives dead-key and Compose-key sequences.
+///
+/// This is fixed rather than read from the environment so that the keystrokes in a plan do not
+/// depend on the locale of the machine that generated it.
+const COMPOSE_LOCALE: &str = "en_US.UTF-8";
+
+const TAB_STROKE: KeyStroke = KeyStroke {
+    keycode: KEY_TAB,
+    shift: false,
+    altgr: false,
+};
+
+/// Evdev keycodes of the main typing block that may be used for text entry.
+///
+/// Keypad keys are excluded on purpose: they duplicate characters (`*`, `-`, `+`, digits) at a
+/// lower shift level, and NumLock state would make them unreliable.
+fn is_text_keycode(keycode: u32) -> bool {
+    matches!(keycode, 2..=13 | 16..=27 | 30..=41 | 43..=53 | 57 | KEY_102ND)
+}
+
+/// How smart quotes (`’ ‘ ” “`) in the final text are typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmartQuotes {
+    /// Type ASCII `'` and `"` and rely on the editor's smart-quote substitution.
+    #[default]
+    Substitute,
+    /// Type the quote characters themselves, like any other character. Layouts without keys for
+    /// them need [`KeyboardLayout::from_spec_covering`] to bind them to spare keys.
+    Literal,
+}
+
+/// Characters and keystroke sequences available on a specific XKB layout.
+///
+/// Characters are typed either with a single keystroke (optionally with Shift and/or AltGr) or,
+/// when the layout has no direct key for them, with a dead-key sequence (e.g. `´` then `e` for
+/// `é`) or a Compose-key sequence (when the layout maps Right Alt to `Multi_key`).
+#[derive(Debug, Clone)]
+pub struct KeyboardLayout {
+    keymap: KeymapInfo,
+    geometry: KeyboardGeometry,
+    altgr_keycode: Option<u32>,
+    strokes_by_char: HashMap<char, Vec<KeyStroke>>,
+    /// Like `strokes_by_char`, for typing while CapsLock is on.
+    caps_strokes_by_char: HashMap<char, Vec<KeyStroke>>,
+    /// User-supplied strokes (see `CharMap`) that take precedence over the layout's own.
+    overrides: HashMap<char, Vec<KeyStroke>>,
+    decode_sequences: HashMap<Vec<KeyStroke>, char>,
+    caps_decode_sequences: HashMap<Vec<KeyStroke>, char>,
+    smart_quotes: SmartQuotes,
+}
+
+impl KeyboardLayout {
+    pub fn us_qwerty() -> Result<Self> {
+        Self::from_spec(&LayoutSpec::default())
+    }
+
+    pub fn from_spec(spec: &LayoutSpec) -> Result<Self> {
+        let keymap = compile_keymap(spec)?;
+        Self::from_keymap(&keymap, spec.label(), spec.geometry)
+    }
+
+    /// Like [`KeyboardLayout::from_spec`], but binds every character of `text` the layout cannot
+    /// type (with `smart_quotes` applied) to a spare keycode in a generated keymap.
+    ///
+    /// The generated keymap is embedded in the plan and uploaded by the Wayland backend; its
+    /// label gets a `+custom` suffix.
+    pub fn from_spec_covering(
+        spec: &LayoutSpec,
+        text: &str,
+        smart_quotes: SmartQuotes,
+    ) -> Result<Self> {
+        let keymap = compile_keymap(spec)?;
+        let mut base = Self::from_keymap(&keymap, spec.label(), spec.geometry)?;
+        base.set_smart_quotes(smart_quotes);
+
+        let mut missing: Vec<char> = text
+            .chars()
+            .filter(|c| !c.is_control() && !base.is_supported_char(*c))
+            .collect();
+        if missing.is_empty() {
+            return Ok(base);
+        }
+        missing.sort_unstable();
+        missing.dedup();
+
+        let extended = extend_keymap_with_chars(&keymap, &missing)?;
+        let mut layout =
+            Self::from_keymap(&extended, format!("{}+custom", spec.label()), spec.geometry)?;
+        layout.set_smart_quotes(smart_quotes);
+        Ok(layout)
+    }
+
+    /// Rebuild the layout a plan was generated for, using the plan's embedded keymap.
+    ///
+    /// Plans without an embedded keymap fall back to compiling the layout by name.
+    pub fn for_plan_config(config: &PlanConfig) -> Result<Self> {
+        if config.keymap.is_empty() {
+            return Self::from_spec(&LayoutSpec::from_label(&config.layout));
+        }
+
+        ensure!(
+            config.keymap_format == KEYMAP_FORMAT_XKB_V1,
+            "unsupported plan keymap format {}",
+            config.keymap_format
+        );
+
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = xkb::Keymap::new_from_string(
+            &context,
+            config.keymap.clone(),
+            xkb::KEYMAP_FORMAT_TEXT_V1,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )
+        .ok_or_else(|| anyhow!("failed to compile the plan's embedded xkb keymap"))?;
+
+        // Plans do not record the geometry. ISO is a superset of ANSI, so it decodes both.
+        Self::from_keymap(&keymap, config.layout.clone(), Geometry::Iso)
+    }
+
+    fn from_keymap(keymap: &xkb::Keymap, label: String, geometry: Geometry) -> Result<Self> {
+        let info = keymap_info(keymap, label)?;
+        let altgr_keycode = level3_keycode(keymap);
+
+        let mut keys = KeyScan::default();
+        for evdev in scanned_keycodes(geometry) {
+            let keycode = xkb::Keycode::new(evdev + 8);
+            for level in 0..keymap.num_levels_for_key(keycode, 0) {
+                let syms = keymap.key_get_syms_by_level(keycode, 0, level);
+                let [sym] = syms else {
+                    continue;
+                };
+                let Some((shift, altgr)) = level_modifiers(keymap, keycode, level, &info) else {
+                    continue;
+                };
+                let stroke = KeyStroke {
+                    keycode: evdev,
+                    shift,
+                    altgr,
+                };
+                keys.push(stroke, *sym);
+            }
+        }
+
+        // `compose:ralt` turns Right Alt into a Compose (Multi_key) key.
+        let ralt = xkb::Keycode::new(KEY_RIGHTALT + 8);
+        let mut compose_keys: Vec<(KeyStroke, xkb::Keysym)> = Vec::new();
+        if keymap
+            .key_get_syms_by_level(ralt, 0, 0)
+            .iter()
+            .any(|sym| sym.raw() == xkb::keysyms::KEY_Multi_key)
+        {
+            compose_keys.push((
+                KeyStroke {
+                    keycode: KEY_RIGHTALT,
+                    shift: false,
+                    altgr: false,
+                },
+                xkb::Keysym::new(xkb::keysyms::KEY_Multi_key),
+            ));
+        }
+
+        let mut compose = if !keys.dead_keys.is_empty() || !compose_keys.is_empty() {
+            compose_state()
+        } else {
+            None
+        };
+
+        let (strokes_by_char, decode_sequences) =
+            build_sequences(keys, &compose_keys, compose.as_mut());
+
+        // With CapsLock on, evaluate every key under each Shift/AltGr combination so xkb applies
+        // the key types' Lock handling and its capitalization transformation.
+        let mut caps_keys = KeyScan::default();
+        if info.caps_mask != 0 {
+            let mut combos = vec![(0, (false, false)), (info.shift_mask, (true, false))];
+            if info.altgr_mask != 0 {
+                combos.push((info.altgr_mask, (false, true)));
+                combos.push((info.shift_mask | info.altgr_mask, (true, true)));
+            }
+
+            let mut state = xkb::State::new(keymap);
+            for evdev in scanned_keycodes(geometry) {
+                let keycode = xkb::Keycode::new(evdev + 8);
+                if keymap.num_layouts_for_key(keycode) == 0 {
+                    continue;
+                }
+                for (mask, (shift, altgr)) in &combos {
+                    state.update_mask(*mask, 0, info.caps_mask, 0, 0, 0);
+                    let stroke = KeyStroke {
+                        keycode: evdev,
+                        shift: *shift,
+                        altgr: *altgr,
+                    };
+                    caps_keys.push(stroke, state.key_get_one_sym(keycode));
+                }
+            }
+        }
+        let (caps_strokes_by_char, caps_decode_sequences) =
+            build_sequences(caps_keys, &compose_keys, compose.as_mut());
+
+        Ok(Self {
+            keymap: info,
+            geometry: KeyboardGeometry::new(geometry),
+            altgr_keycode,
+            strokes_by_char,
+            caps_strokes_by_char,
+            overrides: HashMap::new(),
+            decode_sequences,
+            caps_decode_sequences,
+            smart_quotes: SmartQuotes::default(),
+        })
+    }
+
+    pub fn keymap(&self) -> &KeymapInfo {
+        &self.keymap
+    }
+
+    /// Layout label (`layout(variant)`), as stored in `PlanConfig.layout`.
+    pub fn name(&self) -> &str {
+        &self.keymap.layout
+    }
+
+    /// Evdev keycode of the key acting as AltGr on this layout, if any.
+    pub fn altgr_keycode(&self) -> Option<u32> {
+        self.altgr_keycode
+    }
+
+    pub fn geometry(&self) -> &KeyboardGeometry {
+        &self.geometry
+    }
+
+    pub fn smart_quotes(&self) -> SmartQuotes {
+        self.smart_quotes
+    }
+
+    pub fn set_smart_quotes(&mut self, smart_quotes: SmartQuotes) {
+        self.smart_quotes = smart_quotes;
+    }
+
+    /// Characters on the keys physically adjacent to the key that types `c`, at the same
+    /// Shift/AltGr level, in keycode order.
+    ///
+    /// Empty for characters typed with several keystrokes (dead keys, Compose).
+    pub fn adjacent_chars(&self, c: char) -> Vec<char> {
+        let Some([stroke]) = self.strokes_for_output_char(c) else {
+            return Vec::new();
+        };
+
+        self.geometry
+            .neighbors(stroke.keycode)
+            .into_iter()
+            .filter_map(|keycode| {
+                let neighbor = KeyStroke { keycode, ..*stroke };
+                self.decode_sequences.get(&vec![neighbor]).copied()
+            })
+            .filter(|adjacent| !adjacent.is_whitespace() && *adjacent != c)
+            .collect()
+    }
+
+    /// Keystroke sequence that produces `c` in the final text, if the layout can type it.
+    ///
+    /// With [`SmartQuotes::Substitute`], smart quotes are typed as their ASCII equivalents (see
+    /// `keyboard::typed_char_for_output_char`), relying on editor auto-substitution.
+    pub fn strokes_for_output_char(&self, c: char) -> Option<&[KeyStroke]> {
+        if let Some(strokes) = self.overrides.get(&c) {
+            return Some(strokes);
+        }
+
+        self.strokes_by_char
+            .get(&self.typed_char(c)?)
+            .map(Vec::as_slice)
+    }
+
+    /// Like [`KeyboardLayout::strokes_for_output_char`], for typing while CapsLock is on.
+    ///
+    /// Characters with user-supplied strokes are not available this way, since CapsLock may
+    /// change what those strokes produce.
+    pub fn caps_lock_strokes_for_output_char(&self, c: char) -> Option<&[KeyStroke]> {
+        if self.overrides.contains_key(&c) {
+            return None;
+        }
+        self.caps_strokes_by_char
+            .get(&self.typed_char(c)?)
+            .map(Vec::as_slice)
+    }
+
+    /// Type `c` with `strokes` instead of the layout's own mapping.
+    ///
+    /// The strokes may only use typing keys, and AltGr only when the layout has it. The editor is
+    /// trusted to turn them into `c`, so the simulator and trace see the strokes' own characters.
+    pub fn override_strokes(&mut self, c: char, strokes: &[KeyStroke]) -> Result<()> {
+        for stroke in strokes {
+            ensure!(
+                is_text_keycode(stroke.keycode) || SPARE_KEYCODES.contains(&stroke.keycode),
+                "keystroke for {c:?} uses keycode {}, which is not a typing key",
+                stroke.keycode
+            );
+            ensure!(
+                self.geometry.geometry().has_key(stroke.keycode),
+                "keystroke for {c:?} uses keycode {}, which {:?} keyboards do not have",
+                stroke.keycode,
+                self.geometry.geometry()
+            );
+            ensure!(
+                !stroke.altgr || self.altgr_keycode.is_some(),
+                "keystroke for {c:?} uses AltGr, but layout {} has no AltGr key",
+                self.name()
+            );
+        }
+        self.overrides.insert(c, strokes.to_vec());
+        Ok(())
+    }
+
+    pub fn is_supported_char(&self, c: char) -> bool {
+        self.strokes_for_output_char(c).is_some()
+    }
+
+    pub fn is_supported_text(&self, text: &str) -> bool {
+        text.chars().all(|c| self.is_supported_char(c))
+    }
+
+    pub fn find_first_unsupported_char(&self, text: &str) -> Option<(usize, char)> {
+        text.char_indices()
+            .find(|&(_idx, c)| !self.is_supported_char(c))
+    }
+
+    pub fn decoder(&self) -> KeyDecoder {
+        KeyDecoder::new(
+            self.decode_sequences.clone(),
+            self.caps_decode_sequences.clone(),
+            self.altgr_keycode,
+        )
+    }
+
+    /// Character whose keystrokes produce `c` in the final text (see `strokes_for_output_char`).
+    fn typed_char(&self, c: char) -> Option<char> {
+        match (c, self.smart_quotes) {
+            ('\t' | '\r', _) => None,
+            ('’' | '‘', SmartQuotes::Substitute) => Some('\''),
+            ('”' | '“', SmartQuotes::Substitute) => Some('"'),
+            (c, _) => Some(c),
+        }
+    }
+}
+
+fn scanned_keycodes(geometry: Geometry) -> impl Iterator<Item = u32> {
+    (1..=255u32)
+        .filter(move |k| is_text_keycode(*k) && geometry.has_key(*k))
+        .chain(SPARE_KEYCODES)
+}
+
+/// Keysyms found on the typing keys of a keymap, split into characters and dead keys.
+#[derive(Default)]
+struct KeyScan {
+    direct: Vec<(char, KeyStroke, xkb::Keysym)>,
+    dead_keys: Vec<(KeyStroke, xkb::Keysym)>,
+}
+
+impl KeyScan {
+    fn push(&mut self, stroke: KeyStroke, sym: xkb::Keysym) {
+        if xkb::keysym_get_name(sym).starts_with("dead_") {
+            self.dead_keys.push((stroke, sym));
+        } else if let Some(c) = char::from_u32(xkb::keysym_to_utf32(sym)) {
+            if c != '\0' && !c.is_control() {
+                self.direct.push((c, stroke, sym));
+            }
+        }
+    }
+}
+
+/// Build the per-character keystrokes and the decoding table for one modifier lock state.
+fn build_sequences(
+    mut keys: KeyScan,
+    compose_keys: &[(KeyStroke, xkb::Keysym)],
+    compose: Option<&mut xkb::compose::State>,
+) -> (HashMap<char, Vec<KeyStroke>>, HashMap<Vec<KeyStroke>, char>) {
+    // Prefer the simplest stroke for each character: no modifiers, then Shift, then AltGr,
+    // then Shift+AltGr; ties go to the lowest keycode. The ISO key (missing on ANSI
+    // keyboards) and spare keys are only used for characters the main block lacks.
+    keys.direct.sort_by_key(|(_c, stroke, _sym)| {
+        let secondary = stroke.keycode == KEY_102ND || SPARE_KEYCODES.contains(&stroke.keycode);
+        (secondary, stroke.altgr, stroke.shift, stroke.keycode)
+    });
+
+    let mut strokes_by_char: HashMap<char, Vec<KeyStroke>> = HashMap::new();
+    let mut decode_sequences: HashMap<Vec<KeyStroke>, char> = HashMap::new();
+    let mut bases: Vec<(KeyStroke, xkb::Keysym)> = Vec::new();
+
+    for (c, stroke, sym) in &keys.direct {
+        decode_sequences.insert(vec![*stroke], *c);
+        if !strokes_by_char.contains_key(c) {
+            strokes_by_char.insert(*c, vec![*stroke]);
+            bases.push((*stroke, *sym));
+        }
+    }
+
+    let enter = KeyStroke {
+        keycode: KEY_ENTER,
+        shift: false,
+        altgr: false,
+    };
+    strokes_by_char.insert('\n', vec![enter]);
+    decode_sequences.insert(vec![enter], '\n');
+    // Tab is only typed under `TabPolicy::Literal`, but always decodes.
+    decode_sequences.insert(vec![TAB_STROKE], '\t');
+
+    if let Some(compose) = compose {
+        let mut add_sequence = |c: char, sequence: Vec<KeyStroke>| {
+            decode_sequences.entry(sequence.clone()).or_insert(c);
+            strokes_by_char.entry(c).or_insert(sequence);
+        };
+
+        for (dead, dead_sym) in &keys.dead_keys {
+            for (base, base_sym) in &bases {
+                if let Some(c) = compose_chars(compose, &[*dead_sym, *base_sym]) {
+                    add_sequence(c, vec![*dead, *base]);
+                }
+            }
+        }
+
+        for (multi, multi_sym) in compose_keys {
+            for (first, first_sym) in &bases {
+                if !is_composing(compose, &[*multi_sym, *first_sym]) {
+                    continue;
+                }
+                for (second, second_sym) in &bases {
+                    let syms = [*multi_sym, *first_sym, *second_sym];
+                    if let Some(c) = compose_chars(compose, &syms) {
+                        add_sequence(c, vec![*multi, *first, *second]);
+                    }
+                }
+            }
+        }
+    }
+
+    (strokes_by_char, decode_sequences)
+}
+
+fn level_modifiers(
+    keymap: &xkb::Keymap,
+    keycode: xkb::Keycode,
+    level: xkb::LevelIndex,
+    info: &KeymapInfo,
+) -> Option<(bool, bool)> {
+    let mut masks = [xkb::ModMask::default(); 16];
+    let count = keymap.key_get_mods_for_level(keycode, 0, level, &mut masks);
+    let masks = &masks[..count.min(masks.len())];
+
+    let mut options = vec![(0, (false, false)), (info.shift_mask, (true, false))];
+    if info.altgr_mask != 0 {
+        options.push((info.altgr_mask, (false, true)));
+        options.push((info.shift_mask | info.altgr_mask, (true, true)));
+    }
+
+    options
+        .into_iter()
+        .find(|(mask, _)| masks.contains(mask))
+        .map(|(_mask, mods)| mods)
+}
+
+fn compose_state() -> Option<xkb::compose::State> {
+    let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+    let table = xkb::compose::Table::new_from_locale(
+        &context,
+        OsStr::new(COMPOSE_LOCALE),
+        xkb::compose::COMPILE_NO_FLAGS,
+    )
+    .ok()?;
+    Some(xkb::compose::State::new(
+        &table,
+        xkb::compose::STATE_NO_FLAGS,
+    ))
+}
+
+fn feed_all(state: &mut xkb::compose::State, syms: &[xkb::Keysym]) -> xkb::compose::Status {
+    state.reset();
+    for sym in syms {
+        state.feed(*sym);
+    }
+    state.status()
+}
+
+fn is_composing(state: &mut xkb::compose::State, syms: &[xkb::Keysym]) -> bool {
+    feed_all(state, syms) == xkb::compose::Status::Composing
+}
+
+fn compose_chars(state: &mut xkb::compose::State, syms: &[xkb::Keysym]) -> Option<char> {
+    if feed_all(state, syms) != xkb::compose::Status::Composed {
+        return None;
+    }
+    let out = state.utf8()?;
+    let mut chars = out.chars();
+    let c = chars.next()?;
+    (chars.next().is_none() && !c.is_control()).then_some(c)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoded {
+    /// The keystroke completed a character.
+    Char(char),
+    /// The keystroke started or continued a dead-key/Compose sequence.
+    Pending,
+    /// The keystroke (or the sequence it ended) does not produce a known character.
+    Unknown,
+}
+
+/// Stateful decoder that turns pressed keystrokes back into characters.
+///
+/// Used by the simulator and the console trace so that multi-keystroke characters (dead keys,
+/// Compose sequences) are attributed to a single character. CapsLock state is tracked with
+/// [`KeyDecoder::toggle_caps_lock`].
+#[derive(Debug, Clone, Default)]
+pub struct KeyDecoder {
+    sequences: HashMap<Vec<KeyStroke>, char>,
+    caps_sequences: HashMap<Vec<KeyStroke>, char>,
+    prefixes: HashSet<Vec<KeyStroke>>,
+    altgr_keycode: Option<u32>,
+    caps_lock: bool,
+    pending: Vec<KeyStroke>,
+}
+
+impl KeyDecoder {
+    fn new(
+        sequences: HashMap<Vec<KeyStroke>, char>,
+        caps_sequences: HashMap<Vec<KeyStroke>, char>,
+        altgr_keycode: Option<u32>,
+    ) -> Self {
+        let mut prefixes = HashSet::new();
+        for sequence in sequences.keys().chain(caps_sequences.keys()) {
+            for len in 1..sequence.len() {
+                prefixes.insert(sequence[..len].to_vec());
+            }
+        }
+
+        Self {
+            sequences,
+            caps_sequences,
+            prefixes,
+            altgr_keycode,
+            caps_lock: false,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Decoder for the built-in US-QWERTY mapping (`keyboard::char_to_keystroke`).
+    ///
+    /// This does not require xkbcommon and matches what the planner emits for the `us` layout.
+    pub fn us_qwerty() -> Self {
+        let mut candidates = vec!['\n', ' '];
+        candidates.extend((33u8..=126u8).map(char::from));
+
+        let mut sequences: HashMap<Vec<KeyStroke>, char> = candidates
+            .into_iter()
+            .filter_map(|c| char_to_keystroke(c).map(|stroke| (vec![stroke], c)))
+            .collect();
+        sequences.insert(vec![TAB_STROKE], '\t');
+
+        let caps_sequences = sequences
+            .iter()
+            .map(|(sequence, c)| {
+                let c = if c.is_ascii_lowercase() {
+                    c.to_ascii_uppercase()
+                } else {
+                    c.to_ascii_lowercase()
+                };
+                (sequence.clone(), c)
+            })
+            .collect();
+
+        Self::new(sequences, caps_sequences, None)
+    }
+
+    /// Evdev keycode acting as AltGr for this layout, if any.
+    pub fn altgr_keycode(&self) -> Option<u32> {
+        self.altgr_keycode
+    }
+
+    pub fn caps_lock(&self) -> bool {
+        self.caps_lock
+    }
+
+    /// Record a CapsLock press.
+    pub fn toggle_caps_lock(&mut self) {
+        self.caps_lock = !self.caps_lock;
+    }
+
+    pub fn feed(&mut self, stroke: KeyStroke) -> Decoded {
+        self.pending.push(stroke);
+
+        let sequences = if self.caps_lock {
+            &self.caps_sequences
+        } else {
+            &self.sequences
+        };
+        if let Some(c) = sequences.get(&self.pending) {
+            self.pending.clear();
+            return Decoded::Char(*c);
+        }
+
+        if self.prefixes.contains(&self.pending) {
+            return Decoded::Pending;
+        }
+
+        self.pending.clear();
+        Decoded::Unknown
+    }
+
+    /// Abandon any partially typed dead-key/Compose sequence.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+}
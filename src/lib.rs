@@ -1,13 +1,33 @@
+pub mod analysis;
+pub mod careful;
+pub mod charmap;
+pub mod config;
+pub mod diff;
+pub mod editor;
+pub mod error;
+pub mod estimate;
+pub mod geometry;
+pub mod heatmap;
 pub mod keyboard;
 pub mod keymap;
+pub mod layout;
 pub mod llm;
+pub mod logging;
 pub mod model;
+pub mod normalize;
+pub mod plan;
+pub mod plan_edit;
 pub mod planner;
 pub mod playback;
 
 #[cfg(feature = "wayland")]
 pub mod protocols;
+pub mod render;
+pub mod run_state;
 pub mod sim;
+pub mod testing;
+pub mod timing_calibration;
 pub mod trace;
 pub mod word_nav;
+pub mod word_nav_calibration;
 pub mod word_nav_profile;
@@ -0,0 +1,239 @@
+use super::pipeline::{self, api_key_from_env, ParagraphJob, RephraseRequester, RequestPolicy};
+use super::*;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::time::Instant;
+
+pub const DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+
+const ANTHROPIC_API_KEY_ENV: &str = "ANTHROPIC_API_KEY";
+const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// Beta that constrains the reply to the task's JSON schema (`output_format`).
+const ANTHROPIC_STRUCTURED_OUTPUTS_BETA: &str = "structured-outputs-2025-11-13";
+const MAX_OUTPUT_TOKENS: u32 = 4096;
+
+/// Client for the Anthropic Messages API, using structured outputs for the JSON reply.
+#[derive(Debug, Clone)]
+pub struct AnthropicParagraphRephraseClient {
+    http: reqwest::Client,
+    api_key: String,
+    model: String,
+    policy: RequestPolicy,
+}
+
+impl AnthropicParagraphRephraseClient {
+    pub fn from_env() -> Result<Self, DrafterError> {
+        Self::new(api_key_from_env(ANTHROPIC_API_KEY_ENV)?)
+    }
+
+    pub fn new(api_key: impl Into<String>) -> Result<Self, DrafterError> {
+        Ok(Self {
+            http: reqwest::Client::new(),
+            api_key: api_key.into(),
+            model: DEFAULT_MODEL.to_string(),
+            policy: RequestPolicy::default(),
+        })
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Ask these models in turn for a paragraph the model keeps failing on.
+    pub fn with_fallback_models(mut self, models: Vec<String>) -> Self {
+        self.policy.set_fallback_models(models);
+        self
+    }
+
+    /// Send requests through a proxy and with extra headers.
+    pub fn with_http_options(mut self, options: &HttpOptions) -> Result<Self, DrafterError> {
+        self.http = pipeline::http_client(options)?;
+        Ok(self)
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.policy.set_max_concurrency(max_concurrency);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.policy.set_retry(retry);
+        self
+    }
+
+    /// Mask sensitive text before paragraphs are sent (see [`Redactor`]).
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.policy.set_redactor(redactor);
+        self
+    }
+
+    /// Give up on a call's paragraph requests still running at `deadline`.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.policy.set_deadline(deadline);
+        self
+    }
+
+    /// Retry stats of every paragraph request made so far.
+    pub fn retry_stats(&self) -> Vec<RetryStats> {
+        self.policy.retry_stats()
+    }
+
+    pub async fn rephrase_paragraph(
+        &self,
+        paragraph: &str,
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<PhraseAlternative>, DrafterError> {
+        pipeline::run_paragraph(self, &self.policy, paragraph, options).await
+    }
+
+    pub async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        pipeline::run_paragraphs(self, &self.policy, paragraphs, options).await
+    }
+
+    pub async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        pipeline::run_paragraphs(self, &self.policy, paragraphs, options).await
+    }
+
+    pub async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        pipeline::run_paragraphs(self, &self.policy, paragraphs, options).await
+    }
+}
+
+impl ParagraphRephraseProvider for AnthropicParagraphRephraseClient {
+    async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        AnthropicParagraphRephraseClient::rephrase_paragraphs(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphMistakeProvider for AnthropicParagraphRephraseClient {
+    async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        AnthropicParagraphRephraseClient::propose_mistakes(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphDraftProvider for AnthropicParagraphRephraseClient {
+    async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        AnthropicParagraphRephraseClient::draft_paragraphs(self, paragraphs, options).await
+    }
+}
+
+impl RephraseRequester for AnthropicParagraphRephraseClient {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn request<J: ParagraphJob>(
+        &self,
+        model: &str,
+        job: &J,
+        user_prompt: &str,
+    ) -> Result<Value> {
+        let body = json!({
+            "model": model,
+            "max_tokens": MAX_OUTPUT_TOKENS,
+            "temperature": 0.0,
+            "system": job.system_prompt(),
+            "messages": [{ "role": "user", "content": user_prompt }],
+            "output_format": { "type": "json_schema", "schema": job.schema()? },
+        });
+
+        let response = self
+            .http
+            .post(format!("{ANTHROPIC_API_BASE}/messages"))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("anthropic-beta", ANTHROPIC_STRUCTURED_OUTPUTS_BETA)
+            .json(&body)
+            .send()
+            .await
+            .context("Anthropic messages request failed")?;
+        pipeline::check_rate_limit("Anthropic", &response)?;
+
+        let status = response.status();
+        let reply: Value = response
+            .json()
+            .await
+            .context("Anthropic response is not valid JSON")?;
+        if !status.is_success() {
+            let message = reply
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or("no error message");
+            return Err(DrafterError::LlmApi {
+                provider: "Anthropic".to_string(),
+                status: status.as_u16(),
+                message: message.to_string(),
+            }
+            .into());
+        }
+
+        parse_message_json(&reply).context("failed to parse structured output")
+    }
+}
+
+fn parse_message_json(reply: &Value) -> Result<Value> {
+    let text = reply
+        .get("content")
+        .and_then(Value::as_array)
+        .and_then(|blocks| {
+            blocks
+                .iter()
+                .find(|block| block.get("type").and_then(Value::as_str) == Some("text"))
+        })
+        .and_then(|block| block.get("text"))
+        .and_then(Value::as_str)
+        .context("missing text content block")?;
+
+    serde_json::from_str(text.trim()).context("assistant content is not valid JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_message_json;
+    use serde_json::json;
+
+    #[test]
+    fn parses_the_first_text_block() {
+        let reply = json!({
+            "content": [
+                { "type": "thinking", "thinking": "..." },
+                { "type": "text", "text": " [{\"original\": \"quick\", \"alternative\": \"fast\"}]\n" }
+            ],
+            "stop_reason": "end_turn"
+        });
+        let items = parse_message_json(&reply).expect("valid reply");
+        assert_eq!(
+            items,
+            json!([{ "original": "quick", "alternative": "fast" }])
+        );
+
+        assert!(parse_message_json(&json!({ "content": [] })).is_err());
+    }
+}
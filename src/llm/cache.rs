@@ -0,0 +1,345 @@
+//! The `--llm-cache` file: LLM output keyed by a hash of each paragraph, so only new or edited
+//! paragraphs are fetched when the rest of the document stays the same.
+//!
+//! Entries hold draft text. [`LlmCache::prune`] drops those of paragraphs the current draft no
+//! longer has before the file is written, so old wordings do not pile up in it, and
+//! [`CacheSecret`] encrypts the file for `--llm-cache-keyfile` and `DRAFTER_LLM_CACHE_KEY`.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{anyhow, Context, Result};
+#[cfg(feature = "llm")]
+use argon2::Argon2;
+#[cfg(feature = "llm")]
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+#[cfg(feature = "llm")]
+use chacha20poly1305::ChaCha20Poly1305;
+#[cfg(feature = "llm")]
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[cfg(not(feature = "llm"))]
+use crate::error::DrafterError;
+
+use super::{HumanMistake, PhraseAlternative, Redactor, RephrasePrompts};
+
+/// Version of the cache format written by this build.
+pub const LLM_CACHE_VERSION: u32 = 2;
+
+/// `--llm-cache` contents: LLM output keyed by [`cache_key`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmCache {
+    pub version: u32,
+    pub entries: BTreeMap<String, LlmCacheEntry>,
+}
+
+impl Default for LlmCache {
+    fn default() -> Self {
+        Self {
+            version: LLM_CACHE_VERSION,
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+/// Cached LLM output for one paragraph; each task is fetched on first use.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LlmCacheEntry {
+    /// The paragraph the entry was fetched for, to make the file reviewable.
+    pub paragraph: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alternatives: Option<CachedItems<PhraseAlternative>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mistakes: Option<CachedItems<HumanMistake>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draft: Option<String>,
+    /// The model that produced each task's output (`rephrase`, `mistakes`, `draft`), where
+    /// known.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub models: BTreeMap<String, String>,
+}
+
+/// Items fetched with a per-paragraph limit; reused only for the same limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedItems<T> {
+    pub max_items: usize,
+    pub items: Vec<T>,
+    /// Set once `--llm-review` has approved `items`, so they are not reviewed again.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reviewed: bool,
+}
+
+impl<T: Clone> CachedItems<T> {
+    /// `items`, if they were fetched with a limit of `max_items`.
+    pub fn get(items: Option<&Self>, max_items: usize) -> Option<Vec<T>> {
+        items
+            .filter(|cached| cached.max_items == max_items)
+            .map(|cached| cached.items.clone())
+    }
+}
+
+impl LlmCache {
+    /// Parses a cache file, upgrading a version 1 file. Version 1 did not record
+    /// `--llm-max-mistakes` and reused mistakes regardless, so they are taken to match
+    /// `max_mistakes`.
+    pub fn from_json(json: &str, max_mistakes: usize) -> Result<Self> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).context("failed to parse LLM cache JSON")?;
+        match value.get("version").and_then(serde_json::Value::as_u64) {
+            Some(1) => {
+                let cache: LlmCacheV1 =
+                    serde_json::from_value(value).context("failed to parse LLM cache JSON")?;
+                Ok(cache.upgrade(max_mistakes))
+            }
+            Some(version) if version == u64::from(LLM_CACHE_VERSION) => {
+                serde_json::from_value(value).context("failed to parse LLM cache JSON")
+            }
+            version => Err(anyhow!(
+                "unsupported LLM cache version {}; expected 1 or {LLM_CACHE_VERSION}",
+                version.map_or_else(|| "(missing)".to_string(), |v| v.to_string())
+            )),
+        }
+    }
+
+    /// The cache as the JSON written to the file, at [`LLM_CACHE_VERSION`].
+    pub fn to_json(&self) -> Result<String> {
+        let cache = Self {
+            version: LLM_CACHE_VERSION,
+            entries: self.entries.clone(),
+        };
+        serde_json::to_string_pretty(&cache).context("failed to serialize LLM cache")
+    }
+
+    /// The entry for `key`, created for `paragraph` if missing.
+    pub fn entry(&mut self, key: String, paragraph: &str) -> &mut LlmCacheEntry {
+        self.entries.entry(key).or_insert_with(|| LlmCacheEntry {
+            paragraph: paragraph.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// The cached alternatives of the paragraph `key`, if fetched with `max_items`.
+    pub fn alternatives(&self, key: &str, max_items: usize) -> Option<Vec<PhraseAlternative>> {
+        let entry = self.entries.get(key)?;
+        CachedItems::get(entry.alternatives.as_ref(), max_items)
+    }
+
+    /// The cached mistakes of the paragraph `key`, if fetched with `max_items`.
+    pub fn mistakes(&self, key: &str, max_items: usize) -> Option<Vec<HumanMistake>> {
+        let entry = self.entries.get(key)?;
+        CachedItems::get(entry.mistakes.as_ref(), max_items)
+    }
+
+    /// The cached first draft of the paragraph `key`.
+    pub fn draft(&self, key: &str) -> Option<&str> {
+        self.entries.get(key)?.draft.as_deref()
+    }
+
+    /// Removes the entries whose key is not in `keep`, the keys of the current draft's
+    /// paragraphs; returns how many were removed.
+    pub fn prune(&mut self, keep: &HashSet<&str>) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|key, _| keep.contains(key.as_str()));
+        before - self.entries.len()
+    }
+}
+
+/// Version 1 cache file: one set of suggestions for the whole document.
+#[derive(Debug, Clone, Deserialize)]
+struct LlmCacheV1 {
+    model: String,
+    max_suggestions: usize,
+    /// `--llm-rewrite-strength` value, e.g. `subtle`.
+    rewrite_strength: String,
+    paragraphs: Vec<String>,
+    alternatives_by_paragraph: Vec<Vec<PhraseAlternative>>,
+    #[serde(default)]
+    mistakes_by_paragraph: Option<Vec<Vec<HumanMistake>>>,
+    #[serde(default)]
+    drafts_by_paragraph: Option<Vec<String>>,
+}
+
+impl LlmCacheV1 {
+    /// Rekey by paragraph.
+    fn upgrade(self, max_mistakes: usize) -> LlmCache {
+        let mut cache = LlmCache::default();
+        for (idx, paragraph) in self.paragraphs.iter().enumerate() {
+            let key = cache_key(
+                &self.model,
+                &self.rewrite_strength,
+                &RephrasePrompts::default(),
+                &Redactor::default(),
+                false,
+                paragraph,
+            );
+            let entry = cache.entry(key, paragraph);
+            entry.alternatives = self
+                .alternatives_by_paragraph
+                .get(idx)
+                .map(|items| CachedItems {
+                    max_items: self.max_suggestions,
+                    items: items.clone(),
+                    reviewed: false,
+                });
+            entry.mistakes = self
+                .mistakes_by_paragraph
+                .as_ref()
+                .and_then(|mistakes| mistakes.get(idx))
+                .map(|items| CachedItems {
+                    max_items: max_mistakes,
+                    items: items.clone(),
+                    reviewed: false,
+                });
+            entry.draft = self
+                .drafts_by_paragraph
+                .as_ref()
+                .and_then(|drafts| drafts.get(idx).cloned());
+        }
+        cache
+    }
+}
+
+/// SHA-256 of the model, rewrite strength (its `--llm-rewrite-strength` value), custom prompts
+/// (if any), redaction (if any), whether revision hints are requested and paragraph text, in hex.
+pub fn cache_key(
+    model: &str,
+    strength: &str,
+    prompts: &RephrasePrompts,
+    redactor: &Redactor,
+    revision_hints: bool,
+    paragraph: &str,
+) -> String {
+    let mut hasher = Sha256::new();
+    for part in [model, strength, paragraph] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    // Tagged and only hashed when set, so keys without custom prompts stay the same.
+    let custom = [
+        ("system", &prompts.system),
+        ("user", &prompts.user_template),
+    ];
+    for (tag, prompt) in custom {
+        if let Some(prompt) = prompt {
+            hasher.update(tag.as_bytes());
+            hasher.update((prompt.len() as u64).to_le_bytes());
+            hasher.update(prompt.as_bytes());
+        }
+    }
+    // Masking changes what the model sees and which suggestions survive, so it is part of the
+    // key too. Terms are matched independently, so their order and repeats do not matter.
+    if !redactor.is_empty() {
+        hasher.update(b"redact");
+        hasher.update([u8::from(redactor.emails), u8::from(redactor.numbers)]);
+        let mut terms: Vec<&str> = redactor
+            .terms
+            .iter()
+            .map(String::as_str)
+            .filter(|term| !term.is_empty())
+            .collect();
+        terms.sort_unstable();
+        terms.dedup();
+        for term in terms {
+            hasher.update((term.len() as u64).to_le_bytes());
+            hasher.update(term.as_bytes());
+        }
+    }
+    if revision_hints {
+        hasher.update(b"revision_hints");
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Start of an encrypted cache file. A 16-byte salt, a 12-byte nonce and the ChaCha20-Poly1305
+/// ciphertext of the JSON follow; the magic and salt are authenticated with it.
+pub const ENCRYPTED_MAGIC: &[u8] = b"drafter-llm-cache-argon2id-chacha20poly1305\n";
+
+#[cfg(feature = "llm")]
+const SALT_LEN: usize = 16;
+#[cfg(feature = "llm")]
+const NONCE_LEN: usize = 12;
+
+/// Whether `file` is a cache encrypted by [`CacheSecret::encrypt`].
+pub fn is_encrypted(file: &[u8]) -> bool {
+    file.starts_with(ENCRYPTED_MAGIC)
+}
+
+/// The secret of an encrypted cache. Each file gets its own random salt, and the key is derived
+/// from the secret and salt with Argon2id (default parameters: 19 MiB, 2 passes), so guessing a
+/// weak secret offline is slow and has to start over for every file.
+///
+/// The ciphers come with the `llm` feature; without it, encrypting and decrypting fail.
+pub struct CacheSecret(#[cfg_attr(not(feature = "llm"), allow(dead_code))] Vec<u8>);
+
+impl CacheSecret {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self(secret.into())
+    }
+}
+
+#[cfg(feature = "llm")]
+impl CacheSecret {
+    fn cipher(&self, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(&self.0, salt, &mut key)
+            .map_err(|err| anyhow!("failed to derive the LLM cache key: {err}"))?;
+        Ok(ChaCha20Poly1305::new(&key.into()))
+    }
+
+    /// The encrypted file for `json`, with a fresh salt and nonce.
+    pub fn encrypt(&self, json: &str) -> Result<Vec<u8>> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let header = [ENCRYPTED_MAGIC, &salt].concat();
+        let payload = Payload {
+            msg: json.as_bytes(),
+            aad: &header,
+        };
+        let ciphertext = self
+            .cipher(&salt)?
+            .encrypt(&nonce.into(), payload)
+            .map_err(|_| anyhow!("failed to encrypt LLM cache"))?;
+        Ok([&header, &nonce[..], &ciphertext].concat())
+    }
+
+    /// The JSON of a file [`CacheSecret::encrypt`] wrote with the same secret.
+    pub fn decrypt(&self, file: &[u8]) -> Result<String> {
+        let body = file
+            .strip_prefix(ENCRYPTED_MAGIC)
+            .ok_or_else(|| anyhow!("not an encrypted LLM cache"))?;
+        if body.len() < SALT_LEN + NONCE_LEN {
+            return Err(anyhow!("encrypted LLM cache is truncated"));
+        }
+        let (salt, rest) = body.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let header = &file[..ENCRYPTED_MAGIC.len() + SALT_LEN];
+        let payload = Payload {
+            msg: ciphertext,
+            aad: header,
+        };
+        let json = self
+            .cipher(salt)?
+            .decrypt(nonce.into(), payload)
+            .map_err(|_| anyhow!("wrong key, or the file is corrupted"))?;
+        String::from_utf8(json).context("decrypted LLM cache is not UTF-8")
+    }
+}
+
+#[cfg(not(feature = "llm"))]
+impl CacheSecret {
+    pub fn encrypt(&self, _json: &str) -> Result<Vec<u8>> {
+        Err(DrafterError::LlmDisabled.into())
+    }
+
+    pub fn decrypt(&self, _file: &[u8]) -> Result<String> {
+        Err(DrafterError::LlmDisabled.into())
+    }
+}
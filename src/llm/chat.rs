@@ -0,0 +1,292 @@
+//! Requests to an OpenAI-compatible chat completions API.
+//!
+//! The `openrouter`, `openai` and `ollama` clients only differ in how they configure the HTTP
+//! client and how strictly they parse replies.
+
+use super::pipeline::{self, ParagraphJob, RephraseRequester, RequestPolicy};
+use super::*;
+
+use anyhow::{Context, Result};
+use async_openai::{
+    config::{Config, OpenAIConfig},
+    types::chat::{
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        CreateChatCompletionRequestArgs, CreateChatCompletionResponse, ResponseFormat,
+        ResponseFormatJsonSchema,
+    },
+};
+use serde_json::Value;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+pub(crate) struct ChatRephraseClient {
+    /// Provider name used in error messages.
+    provider: &'static str,
+    // Requests are sent with reqwest rather than `async_openai::Client`, which retries
+    // internally and hides the status and `Retry-After` of rate-limited replies.
+    http: reqwest::Client,
+    config: OpenAIConfig,
+    model: String,
+    policy: RequestPolicy,
+    lenient_json: bool,
+    /// Extra top-level request fields, overriding the defaults (such as `temperature`).
+    body_fields: serde_json::Map<String, Value>,
+}
+
+impl ChatRephraseClient {
+    pub(crate) fn new(provider: &'static str, config: OpenAIConfig, model: &str) -> Self {
+        Self {
+            provider,
+            http: reqwest::Client::new(),
+            config,
+            model: model.to_string(),
+            policy: RequestPolicy::default(),
+            lenient_json: false,
+            body_fields: serde_json::Map::new(),
+        }
+    }
+
+    /// Accept replies wrapped in code fences or prose, or an object holding the array, as
+    /// smaller models often produce. Validation of the items is unchanged.
+    pub(crate) fn with_lenient_json(mut self) -> Self {
+        self.lenient_json = true;
+        self
+    }
+
+    pub(crate) fn with_http_options(mut self, options: &HttpOptions) -> Result<Self> {
+        self.http = pipeline::http_client(options)?;
+        Ok(self)
+    }
+
+    pub(crate) fn with_body_fields(mut self, fields: serde_json::Map<String, Value>) -> Self {
+        self.body_fields = fields;
+        self
+    }
+
+    pub(crate) fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub(crate) fn with_fallback_models(mut self, models: Vec<String>) -> Self {
+        self.policy.set_fallback_models(models);
+        self
+    }
+
+    pub(crate) fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.policy.set_max_concurrency(max_concurrency);
+        self
+    }
+
+    pub(crate) fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.policy.set_retry(retry);
+        self
+    }
+
+    pub(crate) fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.policy.set_redactor(redactor);
+        self
+    }
+
+    pub(crate) fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.policy.set_deadline(deadline);
+        self
+    }
+
+    pub(crate) fn retry_stats(&self) -> Vec<RetryStats> {
+        self.policy.retry_stats()
+    }
+
+    pub(crate) async fn rephrase_paragraph(
+        &self,
+        paragraph: &str,
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<PhraseAlternative>, DrafterError> {
+        pipeline::run_paragraph(self, &self.policy, paragraph, options).await
+    }
+
+    pub(crate) async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        pipeline::run_paragraphs(self, &self.policy, paragraphs, options).await
+    }
+
+    pub(crate) async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        pipeline::run_paragraphs(self, &self.policy, paragraphs, options).await
+    }
+
+    pub(crate) async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        pipeline::run_paragraphs(self, &self.policy, paragraphs, options).await
+    }
+}
+
+impl RephraseRequester for ChatRephraseClient {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn request<J: ParagraphJob>(
+        &self,
+        model: &str,
+        job: &J,
+        user_prompt: &str,
+    ) -> Result<Value> {
+        let response_format = ResponseFormat::JsonSchema {
+            json_schema: ResponseFormatJsonSchema {
+                name: J::SCHEMA_NAME.to_string(),
+                description: None,
+                schema: Some(job.schema()?),
+                strict: Some(true),
+            },
+        };
+
+        let request = CreateChatCompletionRequestArgs::default()
+            .model(model)
+            .messages([
+                ChatCompletionRequestSystemMessageArgs::default()
+                    .content(job.system_prompt().into_owned())
+                    .build()?
+                    .into(),
+                ChatCompletionRequestUserMessageArgs::default()
+                    .content(user_prompt)
+                    .build()?
+                    .into(),
+            ])
+            .response_format(response_format)
+            .temperature(0.0)
+            .build()
+            .with_context(|| format!("failed to build {} request", self.provider))?;
+        let mut body = serde_json::to_value(request)
+            .with_context(|| format!("failed to serialize {} request", self.provider))?;
+        if let Value::Object(fields) = &mut body {
+            fields.extend(self.body_fields.clone());
+        }
+
+        let response = self
+            .http
+            .post(self.config.url("/chat/completions"))
+            .headers(self.config.headers())
+            .query(&self.config.query())
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("{} chat completion request failed", self.provider))?;
+        pipeline::check_rate_limit(self.provider, &response)?;
+
+        let status = response.status();
+        let reply: Value = response
+            .json()
+            .await
+            .with_context(|| format!("{} response is not valid JSON", self.provider))?;
+        if !status.is_success() {
+            let message = reply
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or("no error message");
+            return Err(DrafterError::LlmApi {
+                provider: self.provider.to_string(),
+                status: status.as_u16(),
+                message: message.to_string(),
+            }
+            .into());
+        }
+        let response: CreateChatCompletionResponse = serde_json::from_value(reply)
+            .with_context(|| format!("unexpected {} chat completion response", self.provider))?;
+
+        let content = chat_completion_content(&response)?;
+        if self.lenient_json {
+            lenient_json_value(content).context("assistant content contains no JSON")
+        } else {
+            serde_json::from_str(content.trim()).context("assistant content is not valid JSON")
+        }
+    }
+}
+
+fn chat_completion_content(response: &CreateChatCompletionResponse) -> Result<&str> {
+    response
+        .choices
+        .first()
+        .and_then(|c| c.message.content.as_deref())
+        .context("missing choices[0].message.content")
+}
+
+/// The reply itself if it is JSON, otherwise the outermost `[...]` or `{...}` inside it (see
+/// [`ChatRephraseClient::with_lenient_json`]).
+fn lenient_json_value(content: &str) -> Option<Value> {
+    let content = content.trim();
+    if let Ok(value) = serde_json::from_str(content) {
+        return Some(value);
+    }
+
+    [('[', ']'), ('{', '}')]
+        .into_iter()
+        .find_map(|(open, close)| {
+            let start = content.find(open)?;
+            let end = content.rfind(close)?;
+            (start < end)
+                .then(|| serde_json::from_str(&content[start..=end]).ok())
+                .flatten()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lenient_json_value;
+    use crate::llm::pipeline::items_from_reply;
+    use crate::llm::{OpenRouterOptions, PhraseAlternative, ReasoningEffort};
+    use anyhow::{Context, Result};
+    use serde_json::json;
+
+    fn parse_lenient_json(content: &str) -> Result<Vec<PhraseAlternative>> {
+        let value = lenient_json_value(content).context("no JSON")?;
+        items_from_reply(value, usize::MAX)
+    }
+
+    #[test]
+    fn lenient_json_tolerates_fences_prose_and_wrappers() {
+        let fenced = "Sure! Here you go:\n```json\n[{\"original\": \"big\", \"alternative\": \"large\"}]\n```";
+        let items = parse_lenient_json(fenced).unwrap();
+        assert_eq!(items[0].original, "big");
+        assert_eq!(items[0].alternative, "large");
+
+        let wrapped = r#"{"suggestions": [{"original": "a", "alternative": "b", "reason": "x"}]}"#;
+        assert_eq!(parse_lenient_json(wrapped).unwrap().len(), 1);
+
+        let single = r#"{"original": "a", "alternative": "b"}"#;
+        assert_eq!(parse_lenient_json(single).unwrap().len(), 1);
+
+        assert!(parse_lenient_json("I cannot help with that.").is_err());
+    }
+
+    #[test]
+    fn openrouter_options_become_request_fields() {
+        assert!(OpenRouterOptions::default().body_fields().is_empty());
+
+        let options = OpenRouterOptions {
+            temperature: Some(0.7),
+            top_p: Some(0.9),
+            reasoning_effort: Some(ReasoningEffort::Low),
+            only_providers: vec!["openai".to_string()],
+            ignore_providers: vec!["deepinfra".to_string()],
+        };
+        assert_eq!(
+            serde_json::Value::Object(options.body_fields()),
+            json!({
+                "temperature": 0.7,
+                "top_p": 0.9,
+                "reasoning": { "effort": "low" },
+                "provider": { "only": ["openai"], "ignore": ["deepinfra"] },
+            })
+        );
+    }
+}
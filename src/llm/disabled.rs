@@ -0,0 +1,141 @@
+//! Stand-ins for the provider clients when the `llm` feature is off; every call fails.
+
+use super::*;
+
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct DisabledParagraphRephraseClient;
+
+impl DisabledParagraphRephraseClient {
+    pub fn from_env() -> Result<Self, DrafterError> {
+        Err(DrafterError::LlmDisabled)
+    }
+
+    pub fn new(_api_key: impl Into<String>) -> Result<Self, DrafterError> {
+        Err(DrafterError::LlmDisabled)
+    }
+
+    pub fn with_model(self, _model: impl Into<String>) -> Self {
+        self
+    }
+
+    pub fn with_fallback_models(self, _models: Vec<String>) -> Self {
+        self
+    }
+
+    pub fn with_options(self, _options: &OpenRouterOptions) -> Self {
+        self
+    }
+
+    pub fn with_http_options(self, _options: &HttpOptions) -> Result<Self, DrafterError> {
+        Ok(self)
+    }
+
+    pub fn with_max_concurrency(self, _max_concurrency: usize) -> Self {
+        self
+    }
+
+    pub fn with_retry_policy(self, _retry: RetryPolicy) -> Self {
+        self
+    }
+
+    pub fn with_redactor(self, _redactor: Redactor) -> Self {
+        self
+    }
+
+    pub fn with_deadline(self, _deadline: Instant) -> Self {
+        self
+    }
+
+    pub fn retry_stats(&self) -> Vec<RetryStats> {
+        Vec::new()
+    }
+
+    pub async fn rephrase_paragraph(
+        &self,
+        _paragraph: &str,
+        _options: ParagraphRephraseOptions,
+    ) -> Result<Vec<PhraseAlternative>, DrafterError> {
+        Err(DrafterError::LlmDisabled)
+    }
+
+    pub async fn rephrase_paragraphs(
+        &self,
+        _paragraphs: &[String],
+        _options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        Err(DrafterError::LlmDisabled)
+    }
+
+    pub async fn propose_mistakes(
+        &self,
+        _paragraphs: &[String],
+        _options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        Err(DrafterError::LlmDisabled)
+    }
+
+    pub async fn draft_paragraphs(
+        &self,
+        _paragraphs: &[String],
+        _options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        Err(DrafterError::LlmDisabled)
+    }
+}
+
+impl ParagraphRephraseProvider for DisabledParagraphRephraseClient {
+    async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        DisabledParagraphRephraseClient::rephrase_paragraphs(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphMistakeProvider for DisabledParagraphRephraseClient {
+    async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        DisabledParagraphRephraseClient::propose_mistakes(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphDraftProvider for DisabledParagraphRephraseClient {
+    async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        DisabledParagraphRephraseClient::draft_paragraphs(self, paragraphs, options).await
+    }
+}
+
+pub mod openrouter {
+    pub const DEFAULT_MODEL: &str = "google/gemini-3-flash-preview";
+
+    pub type OpenRouterParagraphRephraseClient = super::DisabledParagraphRephraseClient;
+}
+
+pub mod anthropic {
+    pub const DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+
+    pub type AnthropicParagraphRephraseClient = super::DisabledParagraphRephraseClient;
+}
+
+pub mod ollama {
+    pub const DEFAULT_MODEL: &str = "llama3.2";
+    pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+    pub type OllamaParagraphRephraseClient = super::DisabledParagraphRephraseClient;
+}
+
+pub mod openai {
+    pub const DEFAULT_MODEL: &str = "gpt-4.1-mini";
+
+    pub type OpenAiParagraphRephraseClient = super::DisabledParagraphRephraseClient;
+}
@@ -0,0 +1,99 @@
+use anyhow::{ensure, Result};
+
+use super::RewriteStrength;
+
+/// System prompt for an LLM that writes a rough first draft of a paragraph.
+///
+/// The typing simulator types the draft, then edits it into the input paragraph.
+pub const PARAGRAPH_DRAFT_SYSTEM_PROMPT: &str = r#"You are a helper for a human-like typing simulator.
+
+Goal
+- Given a single paragraph of final-draft text, write the rough first draft its author might have typed before revising.
+- The simulator will type your draft, then edit it into the input paragraph. The more plausible the draft, the more convincing the revision.
+
+Output format (STRICT)
+- Output ONLY valid JSON. No markdown, no surrounding prose, no code fences.
+- Output MUST be a JSON object with exactly one key:
+  - "draft": string
+- No additional keys are allowed.
+
+Hard constraints
+- The draft MUST cover the same ideas as the input paragraph, in roughly the same order.
+- The draft MUST be a single paragraph: no blank lines.
+- The draft MUST differ from the input paragraph.
+- The draft MUST NOT start or end with whitespace.
+- The draft MUST NOT be more than twice as long as the input paragraph.
+
+Character set (typing safety)
+- ONLY use characters that are typeable by a US-QWERTY keyboard with ASCII input:
+  - Allowed: ASCII printable characters, space, newline, and smart quotes ’ ‘ ” “.
+  - Disallowed: tabs, carriage returns, and any other Unicode characters.
+
+Quality guidance
+- Write like a person getting their thoughts down: plainer words, looser sentences, the occasional clumsy phrase.
+- Keep the parts that a first draft would plausibly already have right.
+"#;
+
+/// JSON Schema for `PARAGRAPH_DRAFT_SYSTEM_PROMPT` output.
+pub const PARAGRAPH_DRAFT_JSON_SCHEMA: &str = r#"{
+  "type": "object",
+  "additionalProperties": false,
+  "required": ["draft"],
+  "properties": {
+    "draft": { "type": "string" }
+  }
+}"#;
+
+#[derive(Debug, Clone)]
+pub struct ParagraphDraftOptions {
+    pub strength: RewriteStrength,
+}
+
+impl Default for ParagraphDraftOptions {
+    fn default() -> Self {
+        Self {
+            strength: RewriteStrength::Moderate,
+        }
+    }
+}
+
+pub fn validate_paragraph_draft(paragraph: &str, draft: &str) -> Result<()> {
+    validate_paragraph_draft_with(paragraph, draft, super::is_supported_text)
+}
+
+/// Like [`validate_paragraph_draft`], but checks characters against a specific keyboard layout
+/// instead of US-QWERTY.
+pub fn validate_paragraph_draft_for_layout(
+    paragraph: &str,
+    draft: &str,
+    layout: &crate::layout::KeyboardLayout,
+) -> Result<()> {
+    validate_paragraph_draft_with(paragraph, draft, |text| layout.is_supported_text(text))
+}
+
+fn validate_paragraph_draft_with(
+    paragraph: &str,
+    draft: &str,
+    is_supported_text: impl Fn(&str) -> bool,
+) -> Result<()> {
+    ensure!(
+        is_supported_text(paragraph),
+        "paragraph contains unsupported characters"
+    );
+    ensure!(!draft.is_empty(), "draft must not be empty");
+    ensure!(
+        draft.trim() == draft,
+        "draft must not start or end with whitespace"
+    );
+    ensure!(draft != paragraph, "draft and paragraph must differ");
+    ensure!(!draft.contains("\n\n"), "draft must be a single paragraph");
+    ensure!(
+        is_supported_text(draft),
+        "draft contains unsupported characters"
+    );
+    ensure!(
+        draft.chars().count() <= 2 * paragraph.chars().count(),
+        "draft must be at most twice as long as the paragraph"
+    );
+    Ok(())
+}
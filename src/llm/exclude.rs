@@ -0,0 +1,83 @@
+//! Selection of paragraphs that are never sent to an LLM.
+
+use regex::Regex;
+
+/// Opening marks of a paragraph that is one quotation.
+const OPENING_QUOTES: &[char] = &['"', '“', '„', '«'];
+/// Closing marks of a paragraph that is one quotation.
+const CLOSING_QUOTES: &[char] = &['"', '”', '“', '»'];
+
+/// Which paragraphs to keep away from the LLM.
+///
+/// Excluded paragraphs get no suggestions and are typed as written, so their text never leaves
+/// the machine and quoted or code text is never rewritten.
+#[derive(Debug, Clone, Default)]
+pub struct ParagraphExclusions {
+    /// Fenced (` ``` ` or `~~~`) and indented code blocks.
+    pub code_blocks: bool,
+    /// Block quotes (`>` lines) and paragraphs that are a single quotation.
+    pub quotations: bool,
+    /// Paragraph indices (0-based, see [`crate::planner::split_paragraphs`]).
+    pub indices: Vec<usize>,
+    /// Paragraphs in which any of these match.
+    pub patterns: Vec<Regex>,
+}
+
+impl ParagraphExclusions {
+    /// Whether nothing is excluded.
+    pub fn is_empty(&self) -> bool {
+        !self.code_blocks && !self.quotations && self.indices.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Whether each of `paragraphs` is excluded.
+    ///
+    /// Code fences are tracked across paragraphs, so a fenced block with blank lines in it is
+    /// excluded as a whole.
+    pub fn excluded(&self, paragraphs: &[String]) -> Vec<bool> {
+        let mut in_fence = false;
+        paragraphs
+            .iter()
+            .enumerate()
+            .map(|(idx, paragraph)| {
+                let fenced = in_fence || paragraph.lines().any(is_fence);
+                in_fence ^= paragraph.lines().filter(|line| is_fence(line)).count() % 2 == 1;
+
+                self.indices.contains(&idx)
+                    || self
+                        .patterns
+                        .iter()
+                        .any(|pattern| pattern.is_match(paragraph))
+                    || (self.code_blocks && (fenced || is_indented_code(paragraph)))
+                    || (self.quotations && is_quotation(paragraph))
+            })
+            .collect()
+    }
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// Every non-blank line is indented by a tab or at least four spaces.
+fn is_indented_code(paragraph: &str) -> bool {
+    let mut lines = paragraph.lines().filter(|line| !line.trim().is_empty());
+    lines.clone().next().is_some()
+        && lines.all(|line| line.starts_with('\t') || line.starts_with("    "))
+}
+
+/// A block quote, or text wrapped in quotation marks (closing punctuation may follow).
+fn is_quotation(paragraph: &str) -> bool {
+    let mut lines = paragraph.lines().filter(|line| !line.trim().is_empty());
+    if lines.clone().next().is_some() && lines.all(|line| line.trim_start().starts_with('>')) {
+        return true;
+    }
+
+    let text = paragraph.trim();
+    let body = text.trim_end_matches(['.', ',', ';', ':', '!', '?']);
+    text.starts_with(OPENING_QUOTES)
+        && body.ends_with(CLOSING_QUOTES)
+        && body.chars().count() > 1
+        // One opening and one closing mark, so `"Yes," she said, "go."` is not one quotation.
+        && body.chars().filter(|c| OPENING_QUOTES.contains(c) || CLOSING_QUOTES.contains(c)).count() == 2
+}
@@ -0,0 +1,180 @@
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use super::quality::char_edit_distance;
+use super::{validate_phrase_alternatives_with, PhraseAlternative};
+
+/// System prompt for an LLM that proposes plausible human mistakes in a paragraph.
+///
+/// The typing simulator types `mistake` in place of `original` and corrects it later, like its
+/// own mechanical typos.
+pub const PARAGRAPH_MISTAKES_SYSTEM_PROMPT: &str = r#"You are a helper for a human-like typing simulator.
+
+Goal
+- Given a single paragraph of final-draft text, propose a few mistakes a real person might make while typing a first pass of it.
+- The simulator will temporarily type `mistake` in place of `original`, then later correct it back to `original`.
+- The final text after all edits must match the input paragraph exactly.
+
+Output format (STRICT)
+- Output ONLY valid JSON. No markdown, no surrounding prose, no code fences.
+- Output MUST be a JSON array (possibly empty).
+- Each array element MUST be an object with exactly these keys:
+  - "original": string
+  - "mistake": string
+  - "kind": one of "misspelling", "homophone", "informal"
+- No additional keys are allowed.
+
+Kinds
+- "misspelling": a plausible misspelling of a single word (e.g. "definately" for "definitely", "recieve" for "receive"). At most 2 letters may differ.
+- "homophone": a single word replaced by a word that sounds alike (e.g. "their" for "there", "its" for "it's").
+- "informal": a short span written the way someone would phrase it in a quick first pass (e.g. "a lot of" for "numerous").
+
+Hard constraints
+- `original` MUST be a contiguous substring copied verbatim from the input paragraph, starting and ending at word boundaries.
+- `original` MUST occur exactly once in the input paragraph (unique match). If not, expand the span to make it unique, or omit it.
+- For "misspelling" and "homophone", `original` and `mistake` MUST each be a single word (no spaces).
+- `original` and `mistake` MUST NOT start or end with whitespace.
+- All `original` spans MUST be non-overlapping.
+- `mistake` MUST be different from `original`.
+
+Character set (typing safety)
+- ONLY use characters that are typeable by a US-QWERTY keyboard with ASCII input:
+  - Allowed: ASCII printable characters, space, newline, and smart quotes ’ ‘ ” “.
+  - Disallowed: tabs, carriage returns, and any other Unicode characters.
+
+Quality guidance
+- Prefer mistakes people actually make; avoid random keyboard noise.
+- Return fewer items rather than violating constraints.
+"#;
+
+/// JSON Schema for `PARAGRAPH_MISTAKES_SYSTEM_PROMPT` output.
+pub const PARAGRAPH_MISTAKES_JSON_SCHEMA: &str = r#"{
+  "type": "array",
+  "items": {
+    "type": "object",
+    "additionalProperties": false,
+    "required": ["original", "mistake", "kind"],
+    "properties": {
+      "original": { "type": "string" },
+      "mistake": { "type": "string" },
+      "kind": { "type": "string", "enum": ["misspelling", "homophone", "informal"] }
+    }
+  }
+}"#;
+
+/// Most letters a misspelling may change (Levenshtein distance).
+const MAX_MISSPELLING_DISTANCE: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MistakeKind {
+    Misspelling,
+    Homophone,
+    /// Informal first-pass phrasing; may span several words.
+    Informal,
+}
+
+impl MistakeKind {
+    /// Whether mistakes of this kind replace exactly one word.
+    pub fn is_single_word(self) -> bool {
+        matches!(self, MistakeKind::Misspelling | MistakeKind::Homophone)
+    }
+}
+
+/// A human mistake to type in place of `original` and correct later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HumanMistake {
+    pub original: String,
+    pub mistake: String,
+    pub kind: MistakeKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParagraphMistakeOptions {
+    pub max_mistakes: usize,
+}
+
+impl Default for ParagraphMistakeOptions {
+    fn default() -> Self {
+        Self { max_mistakes: 4 }
+    }
+}
+
+pub fn validate_human_mistakes(paragraph: &str, items: &[HumanMistake]) -> Result<()> {
+    validate_human_mistakes_with(paragraph, items, super::is_supported_text)
+}
+
+/// Like [`validate_human_mistakes`], but checks characters against a specific keyboard layout
+/// instead of US-QWERTY.
+pub fn validate_human_mistakes_for_layout(
+    paragraph: &str,
+    items: &[HumanMistake],
+    layout: &crate::layout::KeyboardLayout,
+) -> Result<()> {
+    validate_human_mistakes_with(paragraph, items, |text| layout.is_supported_text(text))
+}
+
+fn validate_human_mistakes_with(
+    paragraph: &str,
+    items: &[HumanMistake],
+    is_supported_text: impl Fn(&str) -> bool,
+) -> Result<()> {
+    // Spans follow the phrase-alternative rules (verbatim, unique, non-overlapping, typeable).
+    let spans: Vec<PhraseAlternative> = items
+        .iter()
+        .map(|item| PhraseAlternative {
+            original: item.original.clone(),
+            alternative: item.mistake.clone(),
+            revise: None,
+        })
+        .collect();
+    validate_phrase_alternatives_with(paragraph, &spans, is_supported_text)?;
+
+    for item in items {
+        let start = paragraph.find(&item.original).unwrap_or_default();
+        let end = start + item.original.len();
+        let before = paragraph[..start].chars().next_back();
+        let after = paragraph[end..].chars().next();
+        ensure!(
+            !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char),
+            "original {:?} must start and end at word boundaries",
+            item.original
+        );
+
+        if item.kind.is_single_word() {
+            ensure!(
+                item.original.chars().all(is_word_char) && item.mistake.chars().all(is_word_char),
+                "{:?} mistake {:?} for {:?} must be a single word",
+                item.kind,
+                item.mistake,
+                item.original
+            );
+        }
+
+        match item.kind {
+            MistakeKind::Misspelling => {
+                let distance = char_edit_distance(&item.original, &item.mistake);
+                ensure!(
+                    distance <= MAX_MISSPELLING_DISTANCE,
+                    "misspelling {:?} differs from {:?} in {distance} letters (at most {MAX_MISSPELLING_DISTANCE})",
+                    item.mistake,
+                    item.original
+                );
+            }
+            MistakeKind::Homophone => ensure!(
+                item.original.to_lowercase() != item.mistake.to_lowercase(),
+                "homophone {:?} only changes the case of {:?}",
+                item.mistake,
+                item.original
+            ),
+            MistakeKind::Informal => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Word characters as the planner segments words.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '\'' || c == '’'
+}
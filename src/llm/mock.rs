@@ -0,0 +1,246 @@
+//! A deterministic, offline stand-in for the LLM providers.
+//!
+//! Useful for exercising the `--llm` pipeline (fetching, caching, planning) in tests and CI
+//! without API keys. Output depends only on the paragraph and options, and always validates.
+
+use anyhow::Result;
+
+use super::*;
+
+/// Word swaps used for alternatives and drafts, as (final text, first-pass wording).
+const SYNONYMS: &[(&str, &str)] = &[
+    ("about", "around"),
+    ("assist", "help"),
+    ("attempt", "try"),
+    ("begin", "start"),
+    ("big", "large"),
+    ("buy", "get"),
+    ("demonstrate", "show"),
+    ("difficult", "hard"),
+    ("enough", "plenty"),
+    ("fast", "quick"),
+    ("frequently", "often"),
+    ("large", "big"),
+    ("many", "lots of"),
+    ("maybe", "perhaps"),
+    ("numerous", "many"),
+    ("obtain", "get"),
+    ("often", "frequently"),
+    ("perhaps", "maybe"),
+    ("quick", "fast"),
+    ("require", "need"),
+    ("show", "demonstrate"),
+    ("start", "begin"),
+    ("use", "utilize"),
+    ("very", "really"),
+];
+
+/// Homophone pairs used for mistakes.
+const HOMOPHONES: &[(&str, &str)] = &[
+    ("their", "there"),
+    ("there", "their"),
+    ("it's", "its"),
+    ("its", "it's"),
+    ("you're", "your"),
+    ("your", "you're"),
+    ("than", "then"),
+    ("then", "than"),
+    ("too", "to"),
+];
+
+/// Revision hints, assigned to alternatives in turn.
+const REVISION_TIMINGS: [RevisionTiming; 3] = [
+    RevisionTiming::Immediately,
+    RevisionTiming::EndOfParagraph,
+    RevisionTiming::FinalReview,
+];
+
+/// Shortest word that gets a transposition misspelling.
+const MIN_MISSPELLING_LEN: usize = 6;
+
+/// Rule-based provider: alternatives and drafts swap common words for synonyms, mistakes are
+/// homophone swaps and letter transpositions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockRephraseProvider;
+
+impl MockRephraseProvider {
+    pub fn rephrase_paragraph(
+        &self,
+        paragraph: &str,
+        options: &ParagraphRephraseOptions,
+    ) -> Vec<PhraseAlternative> {
+        let mut items: Vec<PhraseAlternative> = Vec::new();
+        for (word, replacement) in unique_words(paragraph)
+            .filter_map(|word| Some((word, synonym_for(word)?)))
+            .take(options.max_suggestions)
+        {
+            let item = PhraseAlternative {
+                original: word.to_string(),
+                alternative: replacement,
+                revise: options
+                    .revision_hints
+                    .then(|| REVISION_TIMINGS[items.len() % REVISION_TIMINGS.len()]),
+            };
+            if validate_phrase_alternatives(paragraph, std::slice::from_ref(&item)).is_ok() {
+                items.push(item);
+            }
+        }
+        items
+    }
+
+    pub fn paragraph_mistakes(
+        &self,
+        paragraph: &str,
+        options: &ParagraphMistakeOptions,
+    ) -> Vec<HumanMistake> {
+        let mut items: Vec<HumanMistake> = Vec::new();
+        for word in unique_words(paragraph) {
+            if items.len() >= options.max_mistakes {
+                break;
+            }
+            let item = if let Some(mistake) = homophone_for(word) {
+                HumanMistake {
+                    original: word.to_string(),
+                    mistake,
+                    kind: MistakeKind::Homophone,
+                }
+            } else if let Some(mistake) = transposed(word) {
+                HumanMistake {
+                    original: word.to_string(),
+                    mistake,
+                    kind: MistakeKind::Misspelling,
+                }
+            } else {
+                continue;
+            };
+            if validate_human_mistakes(paragraph, std::slice::from_ref(&item)).is_ok() {
+                items.push(item);
+            }
+        }
+        items
+    }
+
+    /// The paragraph with every synonym swapped in; without any, the final period is dropped
+    /// (or one added).
+    pub fn draft_paragraph(&self, paragraph: &str) -> String {
+        let body = paragraph.trim();
+        let mut draft = String::with_capacity(body.len());
+        let mut word_start: Option<usize> = None;
+        for (idx, c) in body.char_indices().chain([(body.len(), ' ')]) {
+            if is_word_char(c) {
+                word_start.get_or_insert(idx);
+                continue;
+            }
+            if let Some(start) = word_start.take() {
+                let word = &body[start..idx];
+                draft.push_str(synonym_for(word).as_deref().unwrap_or(word));
+            }
+            if idx < body.len() {
+                draft.push(c);
+            }
+        }
+
+        if draft == body {
+            draft = match body.strip_suffix('.') {
+                Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+                _ => format!("{body}."),
+            };
+        }
+        draft
+    }
+}
+
+impl ParagraphRephraseProvider for MockRephraseProvider {
+    async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        Ok(paragraphs
+            .iter()
+            .map(|paragraph| self.rephrase_paragraph(paragraph, &options))
+            .collect())
+    }
+}
+
+impl ParagraphMistakeProvider for MockRephraseProvider {
+    async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        Ok(paragraphs
+            .iter()
+            .map(|paragraph| self.paragraph_mistakes(paragraph, &options))
+            .collect())
+    }
+}
+
+impl ParagraphDraftProvider for MockRephraseProvider {
+    async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        _options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        Ok(paragraphs
+            .iter()
+            .map(|paragraph| self.draft_paragraph(paragraph))
+            .collect())
+    }
+}
+
+/// Words that occur exactly once in `paragraph`, in order.
+fn unique_words(paragraph: &str) -> impl Iterator<Item = &str> {
+    let words: Vec<&str> = paragraph
+        .split(|c: char| !is_word_char(c))
+        .filter(|word| !word.is_empty())
+        .collect();
+    let unique: Vec<&str> = words
+        .iter()
+        .copied()
+        .filter(|word| words.iter().filter(|w| *w == word).count() == 1)
+        .collect();
+    unique.into_iter()
+}
+
+/// `word`'s synonym, with the first letter's case carried over.
+fn synonym_for(word: &str) -> Option<String> {
+    lookup(SYNONYMS, word)
+}
+
+fn homophone_for(word: &str) -> Option<String> {
+    lookup(HOMOPHONES, word)
+}
+
+fn lookup(table: &[(&str, &str)], word: &str) -> Option<String> {
+    let lower = word.to_lowercase();
+    let (_, replacement) = table.iter().find(|(from, _)| *from == lower)?;
+    let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+    Some(if capitalized {
+        let mut chars = replacement.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    } else {
+        replacement.to_string()
+    })
+}
+
+/// `word` with two letters in the middle swapped, if it is long and all lowercase letters.
+fn transposed(word: &str) -> Option<String> {
+    let mut chars: Vec<char> = word.chars().collect();
+    if chars.len() < MIN_MISSPELLING_LEN || !chars.iter().all(char::is_ascii_lowercase) {
+        return None;
+    }
+    let mid = chars.len() / 2;
+    if chars[mid - 1] == chars[mid] {
+        return None;
+    }
+    chars.swap(mid - 1, mid);
+    Some(chars.into_iter().collect())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '\''
+}
@@ -0,0 +1,162 @@
+use super::chat::ChatRephraseClient;
+use super::*;
+
+use anyhow::Result;
+use async_openai::config::OpenAIConfig;
+use std::time::Instant;
+
+pub const DEFAULT_MODEL: &str = "llama3.2";
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+const OLLAMA_HOST_ENV: &str = "OLLAMA_HOST";
+
+/// Client for a local Ollama server, through its OpenAI-compatible endpoint (`<base>/v1`).
+///
+/// Nothing leaves the machine unless the server is remote. Replies are parsed leniently, since
+/// small local models often wrap the JSON in prose or code fences.
+#[derive(Debug, Clone)]
+pub struct OllamaParagraphRephraseClient {
+    inner: ChatRephraseClient,
+}
+
+impl OllamaParagraphRephraseClient {
+    /// Connect to `OLLAMA_HOST` (`host:port` or a URL), or to [`DEFAULT_BASE_URL`].
+    pub fn from_env() -> Result<Self, DrafterError> {
+        dotenvy::dotenv().ok();
+        let base_url = std::env::var(OLLAMA_HOST_ENV)
+            .ok()
+            .filter(|host| !host.trim().is_empty())
+            .map(|host| normalize_host(&host))
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        Self::new(base_url)
+    }
+
+    /// `base_url` is the server root (e.g. `http://localhost:11434`); `/v1` is appended.
+    pub fn new(base_url: impl Into<String>) -> Result<Self, DrafterError> {
+        let base_url = base_url.into();
+        let api_base = format!("{}/v1", base_url.trim_end_matches('/'));
+        // Ollama ignores the key, but the OpenAI client always sends one.
+        let config = OpenAIConfig::new()
+            .with_api_key("ollama")
+            .with_api_base(api_base);
+        Ok(Self {
+            inner: ChatRephraseClient::new("Ollama", config, DEFAULT_MODEL).with_lenient_json(),
+        })
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner = self.inner.with_model(model);
+        self
+    }
+
+    /// Ask these models in turn for a paragraph the model keeps failing on.
+    pub fn with_fallback_models(mut self, models: Vec<String>) -> Self {
+        self.inner = self.inner.with_fallback_models(models);
+        self
+    }
+
+    /// Send requests through a proxy and with extra headers.
+    pub fn with_http_options(mut self, options: &HttpOptions) -> Result<Self, DrafterError> {
+        self.inner = self.inner.with_http_options(options)?;
+        Ok(self)
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.inner = self.inner.with_max_concurrency(max_concurrency);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.inner = self.inner.with_retry_policy(retry);
+        self
+    }
+
+    /// Mask sensitive text before paragraphs are sent (see [`Redactor`]).
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.inner = self.inner.with_redactor(redactor);
+        self
+    }
+
+    /// Give up on a call's paragraph requests still running at `deadline`.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.inner = self.inner.with_deadline(deadline);
+        self
+    }
+
+    /// Retry stats of every paragraph request made so far.
+    pub fn retry_stats(&self) -> Vec<RetryStats> {
+        self.inner.retry_stats()
+    }
+
+    pub async fn rephrase_paragraph(
+        &self,
+        paragraph: &str,
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<PhraseAlternative>, DrafterError> {
+        self.inner.rephrase_paragraph(paragraph, options).await
+    }
+
+    pub async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        self.inner.rephrase_paragraphs(paragraphs, options).await
+    }
+
+    pub async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        self.inner.propose_mistakes(paragraphs, options).await
+    }
+
+    pub async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        self.inner.draft_paragraphs(paragraphs, options).await
+    }
+}
+
+impl ParagraphRephraseProvider for OllamaParagraphRephraseClient {
+    async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        OllamaParagraphRephraseClient::rephrase_paragraphs(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphMistakeProvider for OllamaParagraphRephraseClient {
+    async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        OllamaParagraphRephraseClient::propose_mistakes(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphDraftProvider for OllamaParagraphRephraseClient {
+    async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        OllamaParagraphRephraseClient::draft_paragraphs(self, paragraphs, options).await
+    }
+}
+
+/// `OLLAMA_HOST` may omit the scheme (`127.0.0.1:11434`).
+fn normalize_host(host: &str) -> String {
+    let host = host.trim();
+    if host.contains("://") {
+        host.to_string()
+    } else {
+        format!("http://{host}")
+    }
+}
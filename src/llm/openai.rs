@@ -0,0 +1,139 @@
+use super::chat::ChatRephraseClient;
+use super::pipeline::api_key_from_env;
+use super::*;
+
+use anyhow::Result;
+use async_openai::config::OpenAIConfig;
+use std::time::Instant;
+
+pub const DEFAULT_MODEL: &str = "gpt-4.1-mini";
+
+const OPENAI_API_KEY_ENV: &str = "OPENAI_API_KEY";
+
+/// Client for the OpenAI API (`api.openai.com`).
+///
+/// Besides `OPENAI_API_KEY`, the standard `OPENAI_BASE_URL`, `OPENAI_ORG_ID` and
+/// `OPENAI_PROJECT_ID` environment variables are honored.
+#[derive(Debug, Clone)]
+pub struct OpenAiParagraphRephraseClient {
+    inner: ChatRephraseClient,
+}
+
+impl OpenAiParagraphRephraseClient {
+    pub fn from_env() -> Result<Self, DrafterError> {
+        Self::new(api_key_from_env(OPENAI_API_KEY_ENV)?)
+    }
+
+    pub fn new(api_key: impl Into<String>) -> Result<Self, DrafterError> {
+        let config = OpenAIConfig::new().with_api_key(api_key.into());
+        Ok(Self {
+            inner: ChatRephraseClient::new("OpenAI", config, DEFAULT_MODEL),
+        })
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner = self.inner.with_model(model);
+        self
+    }
+
+    /// Ask these models in turn for a paragraph the model keeps failing on.
+    pub fn with_fallback_models(mut self, models: Vec<String>) -> Self {
+        self.inner = self.inner.with_fallback_models(models);
+        self
+    }
+
+    /// Send requests through a proxy and with extra headers.
+    pub fn with_http_options(mut self, options: &HttpOptions) -> Result<Self, DrafterError> {
+        self.inner = self.inner.with_http_options(options)?;
+        Ok(self)
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.inner = self.inner.with_max_concurrency(max_concurrency);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.inner = self.inner.with_retry_policy(retry);
+        self
+    }
+
+    /// Mask sensitive text before paragraphs are sent (see [`Redactor`]).
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.inner = self.inner.with_redactor(redactor);
+        self
+    }
+
+    /// Give up on a call's paragraph requests still running at `deadline`.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.inner = self.inner.with_deadline(deadline);
+        self
+    }
+
+    /// Retry stats of every paragraph request made so far.
+    pub fn retry_stats(&self) -> Vec<RetryStats> {
+        self.inner.retry_stats()
+    }
+
+    pub async fn rephrase_paragraph(
+        &self,
+        paragraph: &str,
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<PhraseAlternative>, DrafterError> {
+        self.inner.rephrase_paragraph(paragraph, options).await
+    }
+
+    pub async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        self.inner.rephrase_paragraphs(paragraphs, options).await
+    }
+
+    pub async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        self.inner.propose_mistakes(paragraphs, options).await
+    }
+
+    pub async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        self.inner.draft_paragraphs(paragraphs, options).await
+    }
+}
+
+impl ParagraphRephraseProvider for OpenAiParagraphRephraseClient {
+    async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        OpenAiParagraphRephraseClient::rephrase_paragraphs(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphMistakeProvider for OpenAiParagraphRephraseClient {
+    async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        OpenAiParagraphRephraseClient::propose_mistakes(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphDraftProvider for OpenAiParagraphRephraseClient {
+    async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        OpenAiParagraphRephraseClient::draft_paragraphs(self, paragraphs, options).await
+    }
+}
@@ -0,0 +1,156 @@
+use super::chat::ChatRephraseClient;
+use super::pipeline::api_key_from_env;
+use super::*;
+
+use anyhow::Result;
+use async_openai::config::OpenAIConfig;
+use std::time::Instant;
+
+pub const DEFAULT_MODEL: &str = "google/gemini-3-flash-preview";
+
+const OPENROUTER_API_KEY_ENV: &str = "OPENROUTER_API_KEY";
+const OPENROUTER_API_BASE: &str = "https://openrouter.ai/api/v1";
+/// App attribution OpenRouter asks for; [`HttpOptions::headers`] replace it.
+const DEFAULT_HEADERS: &[(&str, &str)] = &[("X-Title", "drafter")];
+
+#[derive(Debug, Clone)]
+pub struct OpenRouterParagraphRephraseClient {
+    inner: ChatRephraseClient,
+}
+
+impl OpenRouterParagraphRephraseClient {
+    pub fn from_env() -> Result<Self, DrafterError> {
+        Self::new(api_key_from_env(OPENROUTER_API_KEY_ENV)?)
+    }
+
+    pub fn new(api_key: impl Into<String>) -> Result<Self, DrafterError> {
+        let config = OpenAIConfig::new()
+            .with_api_key(api_key.into())
+            .with_api_base(OPENROUTER_API_BASE);
+        Self {
+            inner: ChatRephraseClient::new("OpenRouter", config, DEFAULT_MODEL),
+        }
+        .with_http_options(&HttpOptions::default())
+    }
+
+    /// Send requests through a proxy and with extra headers, such as `HTTP-Referer` and
+    /// `X-Title` to attribute them to your app.
+    pub fn with_http_options(mut self, options: &HttpOptions) -> Result<Self, DrafterError> {
+        let headers = DEFAULT_HEADERS
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .chain(options.headers.iter().cloned())
+            .collect();
+        self.inner = self.inner.with_http_options(&HttpOptions {
+            proxy: options.proxy.clone(),
+            headers,
+        })?;
+        Ok(self)
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner = self.inner.with_model(model);
+        self
+    }
+
+    /// Sampling, reasoning and provider routing for every request.
+    pub fn with_options(mut self, options: &OpenRouterOptions) -> Self {
+        self.inner = self.inner.with_body_fields(options.body_fields());
+        self
+    }
+
+    /// Ask these models in turn for a paragraph the model keeps failing on.
+    pub fn with_fallback_models(mut self, models: Vec<String>) -> Self {
+        self.inner = self.inner.with_fallback_models(models);
+        self
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.inner = self.inner.with_max_concurrency(max_concurrency);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.inner = self.inner.with_retry_policy(retry);
+        self
+    }
+
+    /// Mask sensitive text before paragraphs are sent (see [`Redactor`]).
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.inner = self.inner.with_redactor(redactor);
+        self
+    }
+
+    /// Give up on a call's paragraph requests still running at `deadline`.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.inner = self.inner.with_deadline(deadline);
+        self
+    }
+
+    /// Retry stats of every paragraph request made so far.
+    pub fn retry_stats(&self) -> Vec<RetryStats> {
+        self.inner.retry_stats()
+    }
+
+    pub async fn rephrase_paragraph(
+        &self,
+        paragraph: &str,
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<PhraseAlternative>, DrafterError> {
+        self.inner.rephrase_paragraph(paragraph, options).await
+    }
+
+    pub async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        self.inner.rephrase_paragraphs(paragraphs, options).await
+    }
+
+    pub async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        self.inner.propose_mistakes(paragraphs, options).await
+    }
+
+    pub async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        self.inner.draft_paragraphs(paragraphs, options).await
+    }
+}
+
+impl ParagraphRephraseProvider for OpenRouterParagraphRephraseClient {
+    async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        OpenRouterParagraphRephraseClient::rephrase_paragraphs(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphMistakeProvider for OpenRouterParagraphRephraseClient {
+    async fn propose_mistakes(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphMistakeOptions,
+    ) -> Result<Vec<Vec<HumanMistake>>, DrafterError> {
+        OpenRouterParagraphRephraseClient::propose_mistakes(self, paragraphs, options).await
+    }
+}
+
+impl ParagraphDraftProvider for OpenRouterParagraphRephraseClient {
+    async fn draft_paragraphs(
+        &self,
+        paragraphs: &[String],
+        options: ParagraphDraftOptions,
+    ) -> Result<Vec<String>, DrafterError> {
+        OpenRouterParagraphRephraseClient::draft_paragraphs(self, paragraphs, options).await
+    }
+}
@@ -0,0 +1,71 @@
+//! Filtering of trivial phrase alternatives.
+
+use super::PhraseAlternative;
+
+/// How much a phrase alternative must change to be used; smaller changes (an added comma, a
+/// different case) are discarded before planning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuggestionQuality {
+    /// Minimum Levenshtein distance in characters between `original` and `alternative`.
+    pub min_edit_distance: usize,
+    /// Minimum number of words added, removed or replaced, ignoring case and punctuation.
+    pub min_changed_words: usize,
+}
+
+impl Default for SuggestionQuality {
+    fn default() -> Self {
+        Self {
+            min_edit_distance: 0,
+            min_changed_words: 1,
+        }
+    }
+}
+
+impl SuggestionQuality {
+    pub fn accepts(&self, item: &PhraseAlternative) -> bool {
+        char_edit_distance(&item.original, &item.alternative) >= self.min_edit_distance
+            && changed_words(&item.original, &item.alternative) >= self.min_changed_words
+    }
+
+    /// Drop the alternatives that [`SuggestionQuality::accepts`] rejects; returns how many were
+    /// dropped.
+    pub fn retain(&self, items: &mut Vec<PhraseAlternative>) -> usize {
+        let before = items.len();
+        items.retain(|item| self.accepts(item));
+        before - items.len()
+    }
+}
+
+/// Words added, removed or replaced between `original` and `alternative`, compared
+/// case-insensitively with punctuation ignored.
+pub fn changed_words(original: &str, alternative: &str) -> usize {
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '’'))
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    edit_distance(&words(original), &words(alternative))
+}
+
+/// Levenshtein distance in characters.
+pub(super) fn char_edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    edit_distance(&a, &b)
+}
+
+/// Levenshtein distance between two sequences.
+fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut row = Vec::with_capacity(b.len() + 1);
+        row.push(i + 1);
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            row.push(substitution.min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
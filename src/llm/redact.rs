@@ -0,0 +1,204 @@
+//! Masking of sensitive text in paragraphs before they are sent to an LLM.
+
+use std::ops::Range;
+
+/// Characters allowed in the local part of an email address.
+const EMAIL_LOCAL_CHARS: &[u8] = b"._%+-";
+/// Characters allowed between the digits of one number (`555-0100`, `12/05/2024`, `1,000.50`).
+const NUMBER_SEPARATORS: &[u8] = b"-.,/: ";
+
+/// What to mask in a paragraph before sending it to an LLM.
+///
+/// Masked text is replaced by placeholders such as `[EMAIL_1]`. Suggestions that touch a
+/// placeholder are dropped, and placeholders in a first draft are restored, so masked text never
+/// leaves the machine and is always typed as written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redactor {
+    /// Email addresses.
+    pub emails: bool,
+    /// Numbers, including phone numbers, dates and amounts.
+    pub numbers: bool,
+    /// Literal terms such as names, matched case-sensitively as whole words.
+    pub terms: Vec<String>,
+}
+
+impl Redactor {
+    /// Whether nothing is masked.
+    pub fn is_empty(&self) -> bool {
+        !self.emails && !self.numbers && self.terms.iter().all(String::is_empty)
+    }
+
+    pub fn redact(&self, paragraph: &str) -> Redaction {
+        let mut spans: Vec<(Range<usize>, &str)> = Vec::new();
+        if self.emails {
+            spans.extend(email_spans(paragraph).into_iter().map(|r| (r, "EMAIL")));
+        }
+        if self.numbers {
+            spans.extend(number_spans(paragraph).into_iter().map(|r| (r, "NUMBER")));
+        }
+        for term in self.terms.iter().filter(|term| !term.is_empty()) {
+            spans.extend(term_spans(paragraph, term).into_iter().map(|r| (r, "NAME")));
+        }
+        // Earliest first, longest first at the same start; overlapping later spans are dropped.
+        spans.sort_by_key(|(range, _)| (range.start, usize::MAX - range.end));
+
+        let mut text = String::with_capacity(paragraph.len());
+        let mut masked: Vec<MaskedSpan> = Vec::new();
+        // Each distinct masked text and its placeholder; repeats share the placeholder.
+        let mut distinct: Vec<(&str, String)> = Vec::new();
+        let mut copied = 0usize;
+        for (range, label) in spans {
+            if range.start < copied {
+                continue;
+            }
+            let original = &paragraph[range.clone()];
+            let placeholder = match distinct.iter().find(|(text, _)| *text == original) {
+                Some((_, placeholder)) => placeholder.clone(),
+                None => {
+                    let prefix = format!("[{label}_");
+                    let count = distinct
+                        .iter()
+                        .filter(|(_, placeholder)| placeholder.starts_with(&prefix))
+                        .count();
+                    let placeholder = format!("{prefix}{}]", count + 1);
+                    distinct.push((original, placeholder.clone()));
+                    placeholder
+                }
+            };
+
+            text.push_str(&paragraph[copied..range.start]);
+            let start = text.len();
+            text.push_str(&placeholder);
+            masked.push(MaskedSpan {
+                range: start..text.len(),
+                placeholder,
+                original: original.to_string(),
+            });
+            copied = range.end;
+        }
+        text.push_str(&paragraph[copied..]);
+
+        Redaction { text, masked }
+    }
+}
+
+/// A paragraph with its sensitive text replaced by placeholders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    text: String,
+    masked: Vec<MaskedSpan>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MaskedSpan {
+    /// Byte range of the placeholder in the redacted text.
+    range: Range<usize>,
+    placeholder: String,
+    original: String,
+}
+
+impl Redaction {
+    /// The text to send.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether anything was masked.
+    pub fn is_masked(&self) -> bool {
+        !self.masked.is_empty()
+    }
+
+    /// Whether a suggestion to replace `original` (quoted from [`Redaction::text`]) with
+    /// `replacement` touches masked text: `original` overlaps a placeholder, or `replacement`
+    /// contains one.
+    pub fn touches_masked(&self, original: &str, replacement: &str) -> bool {
+        let overlaps = self.text.match_indices(original).any(|(start, found)| {
+            let end = start + found.len();
+            self.masked
+                .iter()
+                .any(|span| start < span.range.end && span.range.start < end)
+        });
+        overlaps
+            || self
+                .masked
+                .iter()
+                .any(|span| replacement.contains(&span.placeholder))
+    }
+
+    /// `text` with every placeholder replaced by the text it masks.
+    pub fn unmask(&self, text: &str) -> String {
+        self.masked.iter().fold(text.to_string(), |text, span| {
+            text.replace(&span.placeholder, &span.original)
+        })
+    }
+}
+
+fn email_spans(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let is_local = |b: u8| b.is_ascii_alphanumeric() || EMAIL_LOCAL_CHARS.contains(&b);
+    let is_domain = |b: u8| b.is_ascii_alphanumeric() || b == b'.' || b == b'-';
+
+    let mut spans = Vec::new();
+    for (at, _) in text.match_indices('@') {
+        let mut start = at;
+        while start > 0 && is_local(bytes[start - 1]) {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while end < bytes.len() && is_domain(bytes[end]) {
+            end += 1;
+        }
+        // A sentence-ending period is not part of the domain.
+        while end > at + 1 && bytes[end - 1] == b'.' {
+            end -= 1;
+        }
+
+        let domain = &text[at + 1..end];
+        if start < at && domain.contains('.') && !domain.starts_with('.') {
+            spans.push(start..end);
+        }
+    }
+    spans
+}
+
+fn number_spans(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let digit_at = |idx: usize| bytes.get(idx).is_some_and(u8::is_ascii_digit);
+
+    let mut spans = Vec::new();
+    let mut idx = 0usize;
+    while idx < bytes.len() {
+        let start = idx;
+        if bytes[idx] == b'+' && digit_at(idx + 1) {
+            idx += 1;
+        } else if !digit_at(idx) {
+            idx += 1;
+            continue;
+        }
+
+        loop {
+            if digit_at(idx) {
+                idx += 1;
+            } else if NUMBER_SEPARATORS.contains(&bytes.get(idx).copied().unwrap_or(0))
+                && digit_at(idx + 1)
+            {
+                idx += 2;
+            } else {
+                break;
+            }
+        }
+        spans.push(start..idx);
+    }
+    spans
+}
+
+fn term_spans(text: &str, term: &str) -> Vec<Range<usize>> {
+    text.match_indices(term)
+        .map(|(start, _)| start..start + term.len())
+        .filter(|range| {
+            let before = text[..range.start].chars().next_back();
+            let after = text[range.end..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+        .collect()
+}
@@ -0,0 +1,182 @@
+//! `--llm-review`: accept, reject or edit each cached suggestion before planning.
+
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, Result};
+
+use super::cache::LlmCacheEntry;
+use super::{
+    validate_human_mistakes, validate_phrase_alternatives, HumanMistake, PhraseAlternative,
+};
+
+/// Longest stretch of the paragraph shown on each side of a suggestion.
+const REVIEW_CONTEXT_CHARS: usize = 40;
+
+/// Interactive review prompts: answers are read from `input`, prompts go to `output`.
+pub struct Reviewer<R, W> {
+    input: R,
+    output: W,
+    /// Set by the "accept all" answer.
+    accept_rest: bool,
+}
+
+impl<R: BufRead, W: Write> Reviewer<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            accept_rest: false,
+        }
+    }
+
+    /// Reviews the entry's alternatives fetched with a limit of `max_alternatives` and mistakes
+    /// fetched with `max_mistakes` (`None` for tasks not in use), unless already reviewed, and
+    /// marks them `reviewed`. Returns whether anything changed.
+    pub fn review_entry(
+        &mut self,
+        entry: &mut LlmCacheEntry,
+        max_alternatives: Option<usize>,
+        max_mistakes: Option<usize>,
+    ) -> Result<bool> {
+        let paragraph = entry.paragraph.as_str();
+        let mut changed = false;
+
+        let alternatives = entry
+            .alternatives
+            .as_mut()
+            .filter(|cached| Some(cached.max_items) == max_alternatives && !cached.reviewed);
+        if let Some(cached) = alternatives {
+            let items = std::mem::take(&mut cached.items);
+            cached.items = self.review(
+                paragraph,
+                items,
+                |item| ("alternative".to_string(), &item.original, &item.alternative),
+                |item, text| PhraseAlternative {
+                    alternative: text,
+                    ..item.clone()
+                },
+                |item| validate_phrase_alternatives(paragraph, std::slice::from_ref(item)),
+            )?;
+            cached.reviewed = true;
+            changed = true;
+        }
+
+        let mistakes = entry
+            .mistakes
+            .as_mut()
+            .filter(|cached| Some(cached.max_items) == max_mistakes && !cached.reviewed);
+        if let Some(cached) = mistakes {
+            let items = std::mem::take(&mut cached.items);
+            cached.items = self.review(
+                paragraph,
+                items,
+                |item| {
+                    (
+                        format!("{:?} mistake", item.kind),
+                        &item.original,
+                        &item.mistake,
+                    )
+                },
+                |item, text| HumanMistake {
+                    mistake: text,
+                    ..item.clone()
+                },
+                |item| validate_human_mistakes(paragraph, std::slice::from_ref(item)),
+            )?;
+            cached.reviewed = true;
+            changed = true;
+        }
+
+        Ok(changed)
+    }
+
+    /// Ask about each item: accept, reject, or edit its replacement text. Edited items must pass
+    /// `validate`.
+    pub fn review<T>(
+        &mut self,
+        paragraph: &str,
+        items: Vec<T>,
+        describe: impl Fn(&T) -> (String, &str, &str),
+        edit: impl Fn(&T, String) -> T,
+        validate: impl Fn(&T) -> Result<()>,
+    ) -> Result<Vec<T>> {
+        let mut kept = Vec::with_capacity(items.len());
+        for item in items {
+            if self.accept_rest {
+                kept.push(item);
+                continue;
+            }
+
+            let (label, original, replacement) = describe(&item);
+            writeln!(self.output, "\n{}", review_context(paragraph, original))?;
+            writeln!(self.output, "  {label}: {original:?} -> {replacement:?}")?;
+            loop {
+                match self
+                    .ask("Accept? [y]es, [n]o, [e]dit, [a]ccept all: ")?
+                    .as_str()
+                {
+                    "y" | "yes" | "" => {
+                        kept.push(item);
+                        break;
+                    }
+                    "n" | "no" => break,
+                    "a" | "all" => {
+                        self.accept_rest = true;
+                        kept.push(item);
+                        break;
+                    }
+                    "e" | "edit" => {
+                        let text = self.ask(&format!("Replacement for {original:?}: "))?;
+                        let edited = edit(&item, text);
+                        match validate(&edited) {
+                            Ok(()) => {
+                                kept.push(edited);
+                                break;
+                            }
+                            Err(err) => writeln!(self.output, "  Rejected edit: {err:#}")?,
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(kept)
+    }
+
+    fn ask(&mut self, prompt: &str) -> Result<String> {
+        write!(self.output, "{prompt}")?;
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Err(anyhow!("--llm-review input ended"));
+        }
+        Ok(line.trim_end_matches(['\n', '\r']).to_string())
+    }
+}
+
+/// `original` in brackets, with up to [`REVIEW_CONTEXT_CHARS`] of the paragraph on each side.
+fn review_context(paragraph: &str, original: &str) -> String {
+    let Some(start) = paragraph.find(original) else {
+        return format!("[{original}]");
+    };
+    let end = start + original.len();
+    let before: String = paragraph[..start]
+        .chars()
+        .rev()
+        .take(REVIEW_CONTEXT_CHARS)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let after: String = paragraph[end..]
+        .chars()
+        .take(REVIEW_CONTEXT_CHARS)
+        .collect();
+    let ellipsis = |cut: bool| if cut { "..." } else { "" };
+    format!(
+        "  {}{before}[{original}]{after}{}",
+        ellipsis(before.len() < start),
+        ellipsis(after.len() < paragraph.len() - end)
+    )
+    .replace('\n', " ")
+}
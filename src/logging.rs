@@ -0,0 +1,42 @@
+//! Structured logging of planning and playback for unattended runs (`--log`).
+//!
+//! The planner and the playback backends emit [`tracing`] events alongside their console output:
+//! status lines, warnings and errors, every console trace line with its action index, planned
+//! time and cursor position as fields, and at the `trace` level every action played. Nothing
+//! subscribes to them unless [`init`] is called, so the console output is the same either way.
+
+use anyhow::{anyhow, Context, Result};
+use tracing::Level;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Syslog identifier of drafter's journal entries (`journalctl -t drafter`).
+pub const SYSLOG_IDENTIFIER: &str = "drafter";
+
+/// Prefix of the journal fields holding event fields, e.g. `DRAFTER_ACTION_INDEX`.
+pub const JOURNAL_FIELD_PREFIX: &str = "DRAFTER";
+
+/// Where [`init`] sends events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogTarget {
+    /// The systemd journal, with each event field as a journal field.
+    Journald,
+}
+
+/// Sends the events at `level` and above to `target` for the rest of the process.
+pub fn init(target: LogTarget, level: Level) -> Result<()> {
+    match target {
+        LogTarget::Journald => {
+            let layer = tracing_journald::layer()
+                .context("failed to connect to the systemd journal")?
+                .with_syslog_identifier(SYSLOG_IDENTIFIER.to_string())
+                .with_field_prefix(Some(JOURNAL_FIELD_PREFIX.to_string()));
+            tracing_subscriber::registry()
+                .with(layer.with_filter(LevelFilter::from_level(level)))
+                .try_init()
+                .map_err(|err| anyhow!("failed to install the journald logger: {err}"))
+        }
+    }
+}
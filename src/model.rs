@@ -1,21 +1,140 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Plan {
     pub version: u32,
     pub config: PlanConfig,
     pub actions: Vec<Action>,
+    /// Spans of `actions` and the characters of the text they type, in the order they start;
+    /// empty for plans that do not record them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Plan {
+    /// The annotations whose actions include `action_index`.
+    pub fn annotations_at(&self, action_index: usize) -> impl Iterator<Item = &Annotation> {
+        self.annotations
+            .iter()
+            .filter(move |annotation| annotation.actions().contains(&action_index))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlanConfig {
     pub layout: String,
     pub keymap_format: u32,
     pub keymap: String,
     pub wpm_target: f64,
+    /// Name of the word navigation profile the plan's Ctrl+Left/Right presses were generated
+    /// for; `None` for plans that were generated without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_nav_profile: Option<String>,
+    /// How the plan types the draft's paragraph breaks; the text it types is the draft with
+    /// [`ParagraphBreaks::apply`].
+    #[serde(default, skip_serializing_if = "ParagraphBreaks::is_as_written")]
+    pub paragraph_breaks: ParagraphBreaks,
+    /// Whether the plan leaves out the spaces and tabs at the ends of the draft's lines.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_trailing_whitespace: bool,
+    /// Whether the plan leaves out the newlines that end the draft.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_final_newline: bool,
+}
+
+impl PlanConfig {
+    /// The text the plan types for `draft`: with its whitespace stripped as configured, then
+    /// its paragraph breaks as the plan types them.
+    pub fn typed_text(&self, draft: &str) -> String {
+        let mut text = if self.strip_trailing_whitespace {
+            strip_trailing_whitespace(draft)
+        } else {
+            draft.to_string()
+        };
+        if self.strip_final_newline {
+            text.truncate(text.trim_end_matches('\n').len());
+        }
+        self.paragraph_breaks.apply(&text)
+    }
+}
+
+/// Whether `c` is whitespace that is invisible at the end of a line: any whitespace but a
+/// newline, including the carriage return of a Windows line ending.
+fn is_trailing_whitespace(c: char) -> bool {
+    c.is_whitespace() && c != '\n'
+}
+
+/// `text` without the whitespace at the ends of its lines.
+pub fn strip_trailing_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (idx, line) in text.split('\n').enumerate() {
+        if idx > 0 {
+            out.push('\n');
+        }
+        out.push_str(line.trim_end_matches(is_trailing_whitespace));
+    }
+    out
+}
+
+/// The 1-based line and column of the first whitespace at the end of each line of `text` that
+/// ends with whitespace, in order.
+pub fn find_trailing_whitespace(text: &str) -> Vec<(usize, usize)> {
+    text.split('\n')
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            let kept = line.trim_end_matches(is_trailing_whitespace);
+            (kept.len() < line.len()).then(|| (idx + 1, kept.chars().count() + 1))
+        })
+        .collect()
+}
+
+/// How the paragraph breaks of a draft (the runs of blank lines between its paragraphs) are
+/// typed, for editors that space paragraphs differently.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParagraphBreaks {
+    /// One Enter per newline of the draft.
+    #[default]
+    AsWritten,
+    /// A single Enter, for editors that add the space between paragraphs themselves (where
+    /// every line is a paragraph).
+    Single,
+    /// Two Enters (one blank line), however many blank lines the draft has.
+    Double,
+}
+
+impl ParagraphBreaks {
+    pub fn is_as_written(&self) -> bool {
+        *self == ParagraphBreaks::AsWritten
+    }
+
+    /// `text` with its paragraph breaks as typed. Single newlines (line breaks within a
+    /// paragraph) and the newlines before the first and after the last paragraph are kept.
+    pub fn apply(self, text: &str) -> String {
+        let separator = match self {
+            ParagraphBreaks::AsWritten => return text.to_string(),
+            ParagraphBreaks::Single => "\n",
+            ParagraphBreaks::Double => "\n\n",
+        };
+        let body = text.trim_matches('\n');
+        let (start, end) = (
+            text.len() - text.trim_start_matches('\n').len(),
+            text.trim_end_matches('\n').len(),
+        );
+        let mut out = text[..start].to_string();
+        let mut rest = body;
+        while let Some(newline) = rest.find("\n\n") {
+            out.push_str(&rest[..newline]);
+            out.push_str(separator);
+            rest = rest[newline..].trim_start_matches('\n');
+        }
+        out.push_str(rest);
+        out.push_str(&text[end.max(start)..]);
+        out
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Action {
     Wait {
@@ -31,6 +150,95 @@ pub enum Action {
         keycode: u32,
         state: KeyState,
     },
+    /// A safe point to cut or resume the plan: the editor holds exactly the first `chars`
+    /// chars of the text (the cursor at its end), no key is held, CapsLock is off and the
+    /// modifiers are neutral. Playing it does nothing.
+    Checkpoint {
+        chars: usize,
+    },
+    /// `keycode` held down to auto-repeat: it types `count` times, `interval_ms` apart, then is
+    /// released. Playback and the simulator expand it into the discrete taps of
+    /// [`Action::expand`], since how fast a held key really repeats is up to each client.
+    KeyRepeat {
+        keycode: u32,
+        count: u32,
+        interval_ms: u64,
+    },
+}
+
+impl Action {
+    /// Milliseconds playing the action takes: a wait's, or the time between the first and
+    /// the last repeat of a [`Action::KeyRepeat`].
+    pub fn duration_ms(&self) -> u64 {
+        match *self {
+            Action::Wait { ms } => ms,
+            Action::KeyRepeat {
+                count, interval_ms, ..
+            } => u64::from(count.saturating_sub(1)).saturating_mul(interval_ms),
+            Action::Modifiers { .. } | Action::Key { .. } | Action::Checkpoint { .. } => 0,
+        }
+    }
+
+    /// The discrete actions the action plays as: a [`Action::KeyRepeat`] is `count` taps (a
+    /// press and a release) with a wait of `interval_ms` between them, any other action is
+    /// itself.
+    pub fn expand(&self) -> Vec<Action> {
+        let Action::KeyRepeat {
+            keycode,
+            count,
+            interval_ms,
+        } = *self
+        else {
+            return vec![self.clone()];
+        };
+        let mut actions = Vec::with_capacity(count as usize * 3);
+        for tap in 0..count {
+            if tap > 0 && interval_ms > 0 {
+                actions.push(Action::Wait { ms: interval_ms });
+            }
+            actions.push(Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            });
+            actions.push(Action::Key {
+                keycode,
+                state: KeyState::Released,
+            });
+        }
+        actions
+    }
+}
+
+/// Links the actions `action_start..action_end` of a plan to the characters
+/// `source_start..source_end` of the text it types (after charmap and tab expansion).
+///
+/// The ranges are half-open; source positions count chars, not bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub kind: AnnotationKind,
+    pub action_start: usize,
+    pub action_end: usize,
+    pub source_start: usize,
+    pub source_end: usize,
+}
+
+impl Annotation {
+    pub fn actions(&self) -> std::ops::Range<usize> {
+        self.action_start..self.action_end
+    }
+
+    pub fn source(&self) -> std::ops::Range<usize> {
+        self.source_start..self.source_end
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AnnotationKind {
+    /// A paragraph (text between blank lines): the actions typing it and the corrections made
+    /// as it ends. Typos fixed later, e.g. in the final review, are outside it.
+    Paragraph,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
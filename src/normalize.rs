@@ -0,0 +1,135 @@
+use std::fmt;
+
+/// ASCII stand-ins for common characters that keyboard layouts usually cannot type.
+///
+/// Zero-width characters map to the empty string and are removed.
+const REPLACEMENTS: &[(char, &str)] = &[
+    // Dashes and minus signs.
+    ('\u{2010}', "-"),
+    ('\u{2011}', "-"),
+    ('\u{2012}', "-"),
+    ('\u{2013}', "-"),
+    ('\u{2014}', "--"),
+    ('\u{2015}', "--"),
+    ('\u{2212}', "-"),
+    // Ellipsis.
+    ('\u{2026}', "..."),
+    // Spaces.
+    ('\u{00A0}', " "),
+    ('\u{2000}', " "),
+    ('\u{2001}', " "),
+    ('\u{2002}', " "),
+    ('\u{2003}', " "),
+    ('\u{2004}', " "),
+    ('\u{2005}', " "),
+    ('\u{2006}', " "),
+    ('\u{2007}', " "),
+    ('\u{2008}', " "),
+    ('\u{2009}', " "),
+    ('\u{200A}', " "),
+    ('\u{202F}', " "),
+    ('\u{205F}', " "),
+    ('\u{3000}', " "),
+    // Quotes beyond the smart quotes typed via editor substitution (’ ‘ ” “).
+    ('\u{201A}', "'"),
+    ('\u{201B}', "'"),
+    ('\u{201E}', "\""),
+    ('\u{201F}', "\""),
+    ('\u{2032}', "'"),
+    ('\u{2033}', "\""),
+    ('\u{00AB}', "\""),
+    ('\u{00BB}', "\""),
+    ('\u{2039}', "'"),
+    ('\u{203A}', "'"),
+    // Zero-width and invisible formatting characters.
+    ('\u{00AD}', ""),
+    ('\u{200B}', ""),
+    ('\u{200C}', ""),
+    ('\u{200D}', ""),
+    ('\u{2060}', ""),
+    ('\u{FEFF}', ""),
+];
+
+/// The ASCII replacement for `c`, if normalization knows one.
+pub fn replacement_for(c: char) -> Option<&'static str> {
+    REPLACEMENTS
+        .iter()
+        .find(|(from, _to)| *from == c)
+        .map(|(_from, to)| *to)
+}
+
+/// One kind of substitution made by [`normalize_text`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitution {
+    pub from: char,
+    pub to: &'static str,
+    pub count: usize,
+    /// 1-based line of the first occurrence in the input.
+    pub first_line: usize,
+    /// 1-based column (in characters) of the first occurrence in the input.
+    pub first_column: usize,
+}
+
+impl fmt::Display for Substitution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let to = if self.to.is_empty() {
+            "nothing (removed)".to_string()
+        } else {
+            format!("{:?}", self.to)
+        };
+        write!(
+            f,
+            "replaced {}x U+{:04X} {:?} with {to} (first at line {}, column {})",
+            self.count, self.from as u32, self.from, self.first_line, self.first_column
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalized {
+    pub text: String,
+    /// Substitutions in order of first occurrence.
+    pub substitutions: Vec<Substitution>,
+}
+
+/// Replace common untypeable characters (see [`replacement_for`]) with ASCII equivalents.
+///
+/// Characters for which `keep` returns true are left alone, so that layouts (or charmaps) that
+/// can type them still do.
+pub fn normalize_text(text: &str, keep: impl Fn(char) -> bool) -> Normalized {
+    let mut out = String::with_capacity(text.len());
+    let mut substitutions: Vec<Substitution> = Vec::new();
+    let mut line = 1usize;
+    let mut column = 1usize;
+
+    for c in text.chars() {
+        match replacement_for(c).filter(|_| !keep(c)) {
+            Some(to) => {
+                out.push_str(to);
+                match substitutions.iter_mut().find(|s| s.from == c) {
+                    Some(existing) => existing.count += 1,
+                    None => substitutions.push(Substitution {
+                        from: c,
+                        to,
+                        count: 1,
+                        first_line: line,
+                        first_column: column,
+                    }),
+                }
+            }
+            None => out.push(c),
+        }
+
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+
+    Normalized {
+        text: out,
+        substitutions,
+    }
+}
@@ -0,0 +1,459 @@
+//! Reading plan files of every format version.
+//!
+//! [`load`] reads the `version` field first, migrates older formats forward one version at a
+//! time, and rejects plans written by a newer drafter, so changes to [`Plan`] do not break
+//! plans saved by older builds.
+//!
+//! Format history:
+//!
+//! - 1: the first format. `config.keymap` may be empty, meaning the layout is compiled by name.
+//! - 2: `config.keymap` always holds the XKB keymap the plan was generated for.
+//! - 3: actions may include [`crate::model::Action::Checkpoint`] markers.
+//! - 4: actions may include [`crate::model::Action::KeyRepeat`] held keys.
+//!
+//! Plans may also be written as a stream ([`write_stream`]): JSON Lines with a [`StreamHeader`]
+//! first and then one action per line, which [`PlanStream`] reads one action at a time so
+//! playback needs constant memory however long the plan is. Streams exist since version 3 and
+//! are only read in the current version.
+
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::keymap::{keymap_for_layout, LayoutSpec};
+use crate::model::{Action, Annotation, Plan, PlanConfig};
+
+/// Plan format version written by this build.
+pub const PLAN_VERSION: u32 = 4;
+
+/// Oldest plan format version [`load`] still reads.
+pub const OLDEST_PLAN_VERSION: u32 = 1;
+
+/// JSON Schema (draft 2020-12) of plans in the current format ([`PLAN_VERSION`]), as written by
+/// `drafter plan` and printed by `drafter schema`.
+pub const PLAN_JSON_SCHEMA: &str = r##"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "drafter plan",
+  "description": "Keyboard actions that type a document, played back in order.",
+  "type": "object",
+  "additionalProperties": false,
+  "required": ["version", "config", "actions"],
+  "properties": {
+    "version": {
+      "description": "Plan format version. drafter migrates older versions when loading.",
+      "const": 4
+    },
+    "config": { "$ref": "#/$defs/PlanConfig" },
+    "actions": {
+      "type": "array",
+      "items": { "$ref": "#/$defs/Action" }
+    },
+    "annotations": {
+      "description": "Spans of `actions` and the characters of the text they type.",
+      "type": "array",
+      "items": { "$ref": "#/$defs/Annotation" }
+    }
+  },
+  "$defs": {
+    "u32": { "type": "integer", "minimum": 0, "maximum": 4294967295 },
+    "PlanConfig": {
+      "type": "object",
+      "additionalProperties": false,
+      "required": ["layout", "keymap_format", "keymap", "wpm_target"],
+      "properties": {
+        "layout": {
+          "description": "XKB layout label, `layout` or `layout(variant)`.",
+          "type": "string"
+        },
+        "keymap_format": {
+          "description": "Format of `keymap`; 1 is the XKB text format (v1).",
+          "const": 1
+        },
+        "keymap": {
+          "description": "XKB keymap the keycodes refer to.",
+          "type": "string"
+        },
+        "wpm_target": {
+          "description": "Typing speed the plan was generated for, in words per minute.",
+          "type": "number"
+        },
+        "word_nav_profile": {
+          "description": "Word navigation profile the plan's Ctrl+Left/Right presses were generated for, e.g. `gtk` or the name of a user-defined profile.",
+          "type": "string"
+        },
+        "paragraph_breaks": {
+          "description": "How the plan types the draft's paragraph breaks: one Enter per newline (`as_written`, the default), a single Enter (`single`) or one blank line (`double`).",
+          "enum": ["as_written", "single", "double"]
+        },
+        "strip_trailing_whitespace": {
+          "description": "Whether the plan leaves out the spaces and tabs at the ends of the draft's lines; false when absent.",
+          "type": "boolean"
+        },
+        "strip_final_newline": {
+          "description": "Whether the plan leaves out the newlines that end the draft; false when absent.",
+          "type": "boolean"
+        }
+      }
+    },
+    "Action": {
+      "oneOf": [
+        { "$ref": "#/$defs/Wait" },
+        { "$ref": "#/$defs/Modifiers" },
+        { "$ref": "#/$defs/Key" },
+        { "$ref": "#/$defs/KeyRepeat" },
+        { "$ref": "#/$defs/Checkpoint" }
+      ]
+    },
+    "Wait": {
+      "description": "Pause before the next action.",
+      "type": "object",
+      "additionalProperties": false,
+      "required": ["type", "ms"],
+      "properties": {
+        "type": { "const": "wait" },
+        "ms": { "type": "integer", "minimum": 0 }
+      }
+    },
+    "Modifiers": {
+      "description": "XKB modifier and group state to send to the virtual keyboard.",
+      "type": "object",
+      "additionalProperties": false,
+      "required": ["type", "mods_depressed", "mods_latched", "mods_locked", "group"],
+      "properties": {
+        "type": { "const": "modifiers" },
+        "mods_depressed": { "$ref": "#/$defs/u32" },
+        "mods_latched": { "$ref": "#/$defs/u32" },
+        "mods_locked": { "$ref": "#/$defs/u32" },
+        "group": { "$ref": "#/$defs/u32" }
+      }
+    },
+    "Key": {
+      "description": "Press or release of a key, by Linux evdev keycode.",
+      "type": "object",
+      "additionalProperties": false,
+      "required": ["type", "keycode", "state"],
+      "properties": {
+        "type": { "const": "key" },
+        "keycode": { "$ref": "#/$defs/u32" },
+        "state": { "enum": ["pressed", "released"] }
+      }
+    },
+    "KeyRepeat": {
+      "description": "Key held down to auto-repeat: it types `count` times, `interval_ms` apart, then is released. Played as that many press/release taps.",
+      "type": "object",
+      "additionalProperties": false,
+      "required": ["type", "keycode", "count", "interval_ms"],
+      "properties": {
+        "type": { "const": "key_repeat" },
+        "keycode": { "$ref": "#/$defs/u32" },
+        "count": { "$ref": "#/$defs/u32" },
+        "interval_ms": { "type": "integer", "minimum": 0 }
+      }
+    },
+    "Checkpoint": {
+      "description": "Safe point to cut or resume the plan: the editor holds exactly the first `chars` chars of the text, no key is held and the modifiers are neutral.",
+      "type": "object",
+      "additionalProperties": false,
+      "required": ["type", "chars"],
+      "properties": {
+        "type": { "const": "checkpoint" },
+        "chars": { "type": "integer", "minimum": 0 }
+      }
+    },
+    "Annotation": {
+      "description": "Links the actions `action_start..action_end` to the characters `source_start..source_end` of the typed text (half-open ranges, counting chars).",
+      "type": "object",
+      "additionalProperties": false,
+      "required": ["kind", "action_start", "action_end", "source_start", "source_end"],
+      "properties": {
+        "kind": { "enum": ["paragraph"] },
+        "action_start": { "type": "integer", "minimum": 0 },
+        "action_end": { "type": "integer", "minimum": 0 },
+        "source_start": { "type": "integer", "minimum": 0 },
+        "source_end": { "type": "integer", "minimum": 0 }
+      }
+    }
+  }
+}"##;
+
+/// Parses plan JSON of any supported version into the current format.
+pub fn load(json: &str) -> Result<Plan> {
+    let mut value: Value = serde_json::from_str(json).context("failed to parse plan JSON")?;
+    let version = value
+        .get("version")
+        .ok_or_else(|| anyhow!("plan JSON has no \"version\" field"))?
+        .as_u64()
+        .ok_or_else(|| anyhow!("plan \"version\" must be a non-negative integer"))?;
+    if version > u64::from(PLAN_VERSION) {
+        return Err(anyhow!(
+            "plan format version {version} is newer than this drafter supports (up to \
+             {PLAN_VERSION}); upgrade drafter to read it"
+        ));
+    }
+    if version < u64::from(OLDEST_PLAN_VERSION) {
+        return Err(anyhow!("unsupported plan format version {version}"));
+    }
+
+    for from in version..u64::from(PLAN_VERSION) {
+        value = migrate(from, value)
+            .with_context(|| format!("failed to migrate plan from version {from}"))?;
+    }
+    serde_json::from_value(value).context("failed to parse plan JSON")
+}
+
+/// The first line of a plan stream: the plan without its actions, and how many follow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamHeader {
+    pub version: u32,
+    pub config: PlanConfig,
+    /// Number of action lines after the header.
+    pub action_count: usize,
+    /// Sum of the plan's waits, for progress without reading ahead.
+    pub total_wait_ms: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+}
+
+/// Writes `plan` as a stream: its [`StreamHeader`], then each action as compact JSON on a line.
+pub fn write_stream(plan: &Plan, mut out: impl Write) -> Result<()> {
+    let header = StreamHeader {
+        version: plan.version,
+        config: plan.config.clone(),
+        action_count: plan.actions.len(),
+        total_wait_ms: crate::sim::stats(plan).total_wait_ms,
+        annotations: plan.annotations.clone(),
+    };
+    serde_json::to_writer(&mut out, &header).context("failed to serialize plan")?;
+    out.write_all(b"\n").context("failed to write plan")?;
+    for action in &plan.actions {
+        serde_json::to_writer(&mut out, action).context("failed to serialize plan")?;
+        out.write_all(b"\n").context("failed to write plan")?;
+    }
+    out.flush().context("failed to write plan")
+}
+
+/// A plan file as read by [`read`]: a whole plan, or a stream still to be read.
+pub enum PlanInput<R> {
+    Plan(Plan),
+    Stream(PlanStream<R>),
+}
+
+/// Reads a plan file of either form from `reader`. A stream is told apart by its first line, a
+/// [`StreamHeader`]; anything else is read to the end and parsed with [`load`].
+pub fn read<R: BufRead>(mut reader: R) -> Result<PlanInput<R>> {
+    let mut first = String::new();
+    reader
+        .read_line(&mut first)
+        .context("failed to read plan")?;
+    let header = serde_json::from_str::<Value>(&first)
+        .ok()
+        .filter(|value| value.get("action_count").is_some());
+    if let Some(header) = header {
+        return PlanStream::from_header(header, reader).map(PlanInput::Stream);
+    }
+    let mut json = first;
+    reader
+        .read_to_string(&mut json)
+        .context("failed to read plan")?;
+    load(&json).map(PlanInput::Plan)
+}
+
+/// A plan stream being read: its header, and the actions yet to be read.
+pub struct PlanStream<R> {
+    pub header: StreamHeader,
+    pub actions: StreamActions<R>,
+}
+
+impl<R: BufRead> PlanStream<R> {
+    fn from_header(header: Value, reader: R) -> Result<Self> {
+        let version = header
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow!("plan stream header has no \"version\""))?;
+        if version > u64::from(PLAN_VERSION) {
+            return Err(anyhow!(
+                "plan format version {version} is newer than this drafter supports (up to \
+                 {PLAN_VERSION}); upgrade drafter to read it"
+            ));
+        }
+        if version != u64::from(PLAN_VERSION) {
+            return Err(anyhow!(
+                "plan streams are only read in format version {PLAN_VERSION}, not {version}; \
+                 re-plan with `drafter plan --stream`"
+            ));
+        }
+        let header: StreamHeader =
+            serde_json::from_value(header).context("failed to parse plan stream header")?;
+        let actions = StreamActions {
+            reader,
+            remaining: header.action_count,
+            line: 1,
+            done: false,
+            buf: String::new(),
+        };
+        Ok(Self { header, actions })
+    }
+}
+
+/// The actions of a [`PlanStream`], parsed one line at a time. Fails if the stream holds fewer
+/// or more actions than its header announced.
+pub struct StreamActions<R> {
+    reader: R,
+    remaining: usize,
+    /// Number of the last line read, from 1.
+    line: usize,
+    done: bool,
+    buf: String,
+}
+
+impl<R: BufRead> StreamActions<R> {
+    fn read_line(&mut self) -> Result<bool> {
+        self.buf.clear();
+        self.line += 1;
+        let read = self
+            .reader
+            .read_line(&mut self.buf)
+            .with_context(|| format!("failed to read plan stream line {}", self.line))?;
+        Ok(read > 0)
+    }
+
+    fn next_action(&mut self) -> Result<Option<Action>> {
+        if self.remaining == 0 {
+            if self.read_line()? && !self.buf.trim().is_empty() {
+                return Err(anyhow!(
+                    "plan stream line {} is past the actions its header announced",
+                    self.line
+                ));
+            }
+            return Ok(None);
+        }
+        if !self.read_line()? {
+            return Err(anyhow!(
+                "plan stream ended after line {} with {} actions missing",
+                self.line - 1,
+                self.remaining
+            ));
+        }
+        self.remaining -= 1;
+        serde_json::from_str(&self.buf)
+            .with_context(|| format!("failed to parse plan stream line {}", self.line))
+            .map(Some)
+    }
+}
+
+impl<R: BufRead> Iterator for StreamActions<R> {
+    type Item = Result<Action>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_action();
+        // Stop at the end or the first error rather than reading past it.
+        self.done = !matches!(next, Ok(Some(_)));
+        next.transpose()
+    }
+}
+
+/// SHA-256 of `plan` as compact JSON, in hex. Plans with the same contents have the same hash
+/// however their files are formatted.
+pub fn canonical_hash(plan: &Plan) -> Result<String> {
+    let json = serde_json::to_vec(plan).context("failed to serialize plan")?;
+    Ok(Sha256::digest(&json)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Where `actual` first differs from `expected`, or `None` if they are the same plan.
+pub fn first_difference(expected: &Plan, actual: &Plan) -> Option<String> {
+    if expected.version != actual.version {
+        return Some(format!(
+            "format version {} instead of {}",
+            actual.version, expected.version
+        ));
+    }
+    if expected.config != actual.config {
+        let (expected_config, actual_config) = (
+            serde_json::to_value(&expected.config).ok()?,
+            serde_json::to_value(&actual.config).ok()?,
+        );
+        return Some(
+            expected_config
+                .as_object()?
+                .iter()
+                .find(|(key, value)| actual_config.get(key.as_str()) != Some(value))
+                .map_or_else(
+                    || "different config".to_string(),
+                    |(key, _)| format!("different config.{key}"),
+                ),
+        );
+    }
+    let different = expected
+        .actions
+        .iter()
+        .zip(&actual.actions)
+        .position(|(expected, actual)| expected != actual);
+    if let Some(idx) = different {
+        return Some(format!(
+            "action {idx} is {:?} instead of {:?}",
+            actual.actions[idx], expected.actions[idx]
+        ));
+    }
+    if expected.actions.len() != actual.actions.len() {
+        return Some(format!(
+            "{} actions instead of {}",
+            actual.actions.len(),
+            expected.actions.len()
+        ));
+    }
+    (expected.annotations != actual.annotations).then(|| "different annotations".to_string())
+}
+
+/// Migrates plan JSON from version `from` to `from + 1`.
+fn migrate(from: u64, value: Value) -> Result<Value> {
+    match from {
+        1 => migrate_v1(value),
+        2 => migrate_v2(value),
+        3 => migrate_v3(value),
+        _ => unreachable!("no migration from plan version {from}"),
+    }
+}
+
+/// Embeds the keymap of plans that only name their layout.
+fn migrate_v1(mut value: Value) -> Result<Value> {
+    let config = value
+        .get_mut("config")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| anyhow!("plan has no \"config\" object"))?;
+    let keymap_missing = config
+        .get("keymap")
+        .and_then(Value::as_str)
+        .is_none_or(str::is_empty);
+    if keymap_missing {
+        let layout = config
+            .get("layout")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("plan config has no \"layout\""))?;
+        let keymap = keymap_for_layout(&LayoutSpec::from_label(layout))?;
+        config.insert("keymap_format".to_string(), keymap.keymap_format.into());
+        config.insert("keymap".to_string(), keymap.keymap.into());
+    }
+    value["version"] = 2.into();
+    Ok(value)
+}
+
+/// Version 2 plans are version 3 plans without checkpoints.
+fn migrate_v2(mut value: Value) -> Result<Value> {
+    value["version"] = 3.into();
+    Ok(value)
+}
+
+/// Version 3 plans are version 4 plans without held keys.
+fn migrate_v3(mut value: Value) -> Result<Value> {
+    value["version"] = 4.into();
+    Ok(value)
+}
@@ -0,0 +1,692 @@
+//! Editing existing plans: trimming, scaling waits, concatenation and shrinking redundant
+//! actions (`drafter edit-plan`), removing their text (`drafter anonymize`), and separating
+//! plans typed one after the other.
+//!
+//! Every edit returns a plan that starts and ends with no key held, CapsLock off and neutral
+//! modifiers. Keys held where a plan is cut are pressed again after the cut (and released
+//! before it), so the kept actions still type the same characters.
+
+use anyhow::{anyhow, Result};
+
+use crate::keyboard::{
+    KEY_BACKSPACE, KEY_CAPSLOCK, KEY_DELETE, KEY_DOWN, KEY_END, KEY_ENTER, KEY_ESC, KEY_HOME,
+    KEY_J, KEY_LEFT, KEY_LEFTALT, KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_Q, KEY_RIGHT, KEY_RIGHTALT,
+    KEY_RIGHTCTRL, KEY_RIGHTSHIFT, KEY_SPACE, KEY_TAB, KEY_UP, KEY_X, KEY_Z,
+};
+use crate::keymap::{keymap_for_layout, LayoutSpec};
+use crate::layout::KeyboardLayout;
+use crate::model::{Action, Annotation, KeyState, Plan, PlanConfig};
+use crate::playback::backends::COMMON_MODIFIER_KEYCODES;
+use crate::sim::simulate_typed_text;
+
+/// Wait between the key events added at a cut.
+const SEAM_KEY_GAP_MS: u64 = 10;
+
+/// Hold time of the Enter presses of a [`separator`].
+const SEPARATOR_KEY_HOLD_MS: u64 = 60;
+
+/// Pause after each Enter press of a [`separator`].
+const SEPARATOR_ENTER_GAP_MS: u64 = 350;
+
+/// Keys [`anonymize`] presses instead of the keys that type text: `x` (`X` with Shift), or the
+/// next rarely typed letter while an earlier placeholder is still held.
+pub const PLACEHOLDER_KEYCODES: [u32; 4] = [KEY_X, KEY_Z, KEY_Q, KEY_J];
+
+/// Keys [`anonymize`] keeps: modifiers, editing and navigation.
+const STRUCTURE_KEYCODES: &[u32] = &[
+    KEY_LEFTSHIFT,
+    KEY_RIGHTSHIFT,
+    KEY_LEFTCTRL,
+    KEY_RIGHTCTRL,
+    KEY_LEFTALT,
+    KEY_RIGHTALT,
+    KEY_CAPSLOCK,
+    KEY_ESC,
+    KEY_BACKSPACE,
+    KEY_DELETE,
+    KEY_LEFT,
+    KEY_RIGHT,
+    KEY_UP,
+    KEY_DOWN,
+    KEY_HOME,
+    KEY_END,
+];
+
+/// Keys `anonymize(.., true)` keeps as well.
+const WHITESPACE_KEYCODES: &[u32] = &[KEY_SPACE, KEY_ENTER, KEY_TAB];
+
+/// A position in a plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanCut {
+    /// Before the action with this index.
+    Action(usize),
+    /// Before the first action that starts at or after this time.
+    Ms(u64),
+    /// At the last [`Action::Checkpoint`] with at most this many chars typed, or the start of
+    /// the plan if there is none. Cuts there need no keys pressed again or released.
+    Checkpoint(usize),
+}
+
+/// Keys held and CapsLock state after some of a plan's actions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyboardState {
+    pub(crate) held: Vec<u32>,
+    pub(crate) caps_lock: bool,
+    /// The last modifier state sent.
+    modifiers: Option<Action>,
+}
+
+impl KeyboardState {
+    pub(crate) fn after(actions: &[Action]) -> Result<Self> {
+        let mut state = Self::default();
+        for (idx, action) in actions.iter().enumerate() {
+            state
+                .observe(action)
+                .map_err(|err| anyhow!("action {idx}: {err}"))?;
+        }
+        Ok(state)
+    }
+
+    fn observe(&mut self, action: &Action) -> Result<()> {
+        match *action {
+            Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            } => {
+                if self.held.contains(&keycode) {
+                    return Err(anyhow!("keycode {keycode} pressed while held"));
+                }
+                self.held.push(keycode);
+                if keycode == KEY_CAPSLOCK {
+                    self.caps_lock = !self.caps_lock;
+                }
+            }
+            Action::Key {
+                keycode,
+                state: KeyState::Released,
+            } => {
+                let Some(pos) = self.held.iter().position(|&held| held == keycode) else {
+                    return Err(anyhow!("keycode {keycode} released while not held"));
+                };
+                self.held.remove(pos);
+            }
+            Action::KeyRepeat { keycode, count, .. } => {
+                if self.held.contains(&keycode) {
+                    return Err(anyhow!("keycode {keycode} repeated while held"));
+                }
+                if keycode == KEY_CAPSLOCK && count % 2 == 1 {
+                    self.caps_lock = !self.caps_lock;
+                }
+            }
+            Action::Modifiers { .. } => self.modifiers = Some(action.clone()),
+            Action::Wait { .. } | Action::Checkpoint { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Actions taking a neutral keyboard to this state.
+    fn restore(&self) -> Vec<Action> {
+        let mut actions = vec![neutral_modifiers()];
+        // Pressing a held CapsLock again toggles it too.
+        if self.caps_lock != self.held.contains(&KEY_CAPSLOCK) {
+            actions.extend(tap(KEY_CAPSLOCK));
+        }
+        for &keycode in &self.held {
+            actions.push(Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            });
+            actions.push(Action::Wait {
+                ms: SEAM_KEY_GAP_MS,
+            });
+        }
+        actions.extend(self.modifiers.clone());
+        actions
+    }
+
+    /// [`KeyboardState::restore`] for a playback that stopped here and released every key: keys
+    /// other than modifiers already typed their character, so only the modifiers are pressed
+    /// again (and CapsLock turned back on without pressing it).
+    fn restore_modifiers(&self) -> Vec<Action> {
+        let held = self
+            .held
+            .iter()
+            .copied()
+            .filter(|keycode| COMMON_MODIFIER_KEYCODES.contains(keycode))
+            .collect();
+        Self {
+            held,
+            ..self.clone()
+        }
+        .restore()
+    }
+
+    /// Actions taking the keyboard from this state back to neutral.
+    fn release(&self) -> Vec<Action> {
+        let mut actions = Vec::new();
+        for &keycode in self.held.iter().rev() {
+            actions.push(Action::Key {
+                keycode,
+                state: KeyState::Released,
+            });
+            actions.push(Action::Wait {
+                ms: SEAM_KEY_GAP_MS,
+            });
+        }
+        if self.caps_lock {
+            actions.extend(tap(KEY_CAPSLOCK));
+        }
+        actions.push(neutral_modifiers());
+        actions
+    }
+
+    fn is_neutral(&self) -> bool {
+        self.held.is_empty() && !self.caps_lock && self.modifiers_neutral()
+    }
+
+    /// Whether the last modifier state sent, if any, has no modifiers set.
+    pub(crate) fn modifiers_neutral(&self) -> bool {
+        self.modifiers.as_ref().is_none_or(is_neutral_modifiers)
+    }
+}
+
+fn tap(keycode: u32) -> [Action; 4] {
+    [
+        Action::Key {
+            keycode,
+            state: KeyState::Pressed,
+        },
+        Action::Wait {
+            ms: SEAM_KEY_GAP_MS,
+        },
+        Action::Key {
+            keycode,
+            state: KeyState::Released,
+        },
+        Action::Wait {
+            ms: SEAM_KEY_GAP_MS,
+        },
+    ]
+}
+
+fn neutral_modifiers() -> Action {
+    Action::Modifiers {
+        mods_depressed: 0,
+        mods_latched: 0,
+        mods_locked: 0,
+        group: 0,
+    }
+}
+
+fn is_neutral_modifiers(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Modifiers {
+            mods_depressed: 0,
+            mods_latched: 0,
+            mods_locked: 0,
+            group: 0,
+        }
+    )
+}
+
+/// Checks that `plan` releases every key it presses, only releases held keys, and ends with
+/// CapsLock off and neutral modifiers.
+pub fn verify_neutral(plan: &Plan) -> Result<()> {
+    let state = KeyboardState::after(&plan.actions)?;
+    if !state.held.is_empty() {
+        return Err(anyhow!("plan ends with keycodes {:?} held", state.held));
+    }
+    if state.caps_lock {
+        return Err(anyhow!("plan ends with CapsLock on"));
+    }
+    if !state.is_neutral() {
+        return Err(anyhow!("plan ends with modifiers set"));
+    }
+    Ok(())
+}
+
+/// Index of the action at `cut`.
+pub fn cut_index(plan: &Plan, cut: PlanCut) -> usize {
+    match cut {
+        PlanCut::Action(idx) => idx.min(plan.actions.len()),
+        PlanCut::Ms(at_ms) => {
+            let mut elapsed_ms = 0u64;
+            for (idx, action) in plan.actions.iter().enumerate() {
+                if elapsed_ms >= at_ms {
+                    return idx;
+                }
+                elapsed_ms = elapsed_ms.saturating_add(action.duration_ms());
+            }
+            plan.actions.len()
+        }
+        PlanCut::Checkpoint(max_chars) => plan
+            .actions
+            .iter()
+            .rposition(
+                |action| matches!(action, Action::Checkpoint { chars } if *chars <= max_chars),
+            )
+            .unwrap_or(0),
+    }
+}
+
+/// The actions of `plan` from `start` up to `end` (the whole plan without them), re-pressing
+/// keys held at `start` and releasing the ones held at `end`. Annotations of actions cut off
+/// are dropped.
+pub fn trim(plan: &Plan, start: Option<PlanCut>, end: Option<PlanCut>) -> Result<Plan> {
+    let start_idx = start.map_or(0, |cut| cut_index(plan, cut));
+    let end_idx = end.map_or(plan.actions.len(), |cut| cut_index(plan, cut));
+    if start_idx >= end_idx {
+        return Err(anyhow!(
+            "trimming keeps no actions (start {start_idx}, end {end_idx})"
+        ));
+    }
+
+    let at_start = KeyboardState::after(&plan.actions[..start_idx])?;
+    let at_end = KeyboardState::after(&plan.actions[..end_idx])?;
+
+    let mut actions = Vec::new();
+    if start_idx > 0 {
+        actions.extend(at_start.restore());
+    }
+    let offset = actions.len();
+    actions.extend_from_slice(&plan.actions[start_idx..end_idx]);
+    let annotations = plan
+        .annotations
+        .iter()
+        .filter(|annotation| {
+            start_idx <= annotation.action_start && annotation.action_end <= end_idx
+        })
+        .map(|annotation| Annotation {
+            action_start: annotation.action_start - start_idx + offset,
+            action_end: annotation.action_end - start_idx + offset,
+            ..annotation.clone()
+        })
+        .collect();
+    if end_idx < plan.actions.len() || !at_end.is_neutral() {
+        actions.extend(at_end.release());
+    }
+
+    let trimmed = Plan {
+        actions,
+        annotations,
+        ..plan.clone()
+    };
+    verify_neutral(&trimmed)?;
+    Ok(trimmed)
+}
+
+/// The rest of `plan` for a playback stopped before the action at `action_index`, which
+/// released the keys it held.
+///
+/// Resumes at the last [`Action::Checkpoint`] at or before `action_index` when no key was
+/// pressed since, which needs no keys pressed again. Otherwise it cuts at `action_index`,
+/// pressing only the held modifiers again: the other keys held there already typed their
+/// character, so their releases are dropped instead of pressing them a second time.
+pub fn resume_at(plan: &Plan, action_index: usize) -> Result<Plan> {
+    let action_index = action_index.min(plan.actions.len());
+    let checkpoint = plan.actions[..action_index]
+        .iter()
+        .rposition(|action| {
+            matches!(
+                action,
+                Action::Checkpoint { .. }
+                    | Action::KeyRepeat { .. }
+                    | Action::Key {
+                        state: KeyState::Pressed,
+                        ..
+                    }
+            )
+        })
+        .filter(|&idx| matches!(plan.actions[idx], Action::Checkpoint { .. }));
+    if let Some(idx) = checkpoint {
+        return trim(plan, Some(PlanCut::Action(idx)), None);
+    }
+    if action_index == 0 {
+        return Ok(plan.clone());
+    }
+
+    let state = KeyboardState::after(&plan.actions[..action_index])?;
+    let mut released: Vec<u32> = state
+        .held
+        .iter()
+        .copied()
+        .filter(|keycode| !COMMON_MODIFIER_KEYCODES.contains(keycode))
+        .collect();
+    let mut actions = state.restore_modifiers();
+    // Index in the resumed plan of each action from `action_index` on (and of the end).
+    let mut new_index = Vec::with_capacity(plan.actions.len() - action_index + 1);
+    for action in &plan.actions[action_index..] {
+        new_index.push(actions.len());
+        if let Action::Key {
+            keycode,
+            state: KeyState::Released,
+        } = action
+        {
+            if let Some(pos) = released.iter().position(|held| held == keycode) {
+                released.remove(pos);
+                continue;
+            }
+        }
+        actions.push(action.clone());
+    }
+    new_index.push(actions.len());
+    let annotations = plan
+        .annotations
+        .iter()
+        .filter(|annotation| action_index <= annotation.action_start)
+        .map(|annotation| Annotation {
+            action_start: new_index[annotation.action_start - action_index],
+            action_end: new_index[annotation.action_end - action_index],
+            ..annotation.clone()
+        })
+        .collect();
+
+    let resumed = Plan {
+        actions,
+        annotations,
+        ..plan.clone()
+    };
+    verify_neutral(&resumed)?;
+    Ok(resumed)
+}
+
+/// `plan` with every wait, and the interval of every [`Action::KeyRepeat`], multiplied by
+/// `factor` (rounded to whole milliseconds).
+pub fn scale_waits(plan: &Plan, factor: f64) -> Result<Plan> {
+    if !(factor.is_finite() && factor > 0.0) {
+        return Err(anyhow!(
+            "wait scale must be a positive number, got {factor}"
+        ));
+    }
+    // Index in the scaled plan of each action (and of the end), as waits scaled to 0 ms go.
+    let mut new_index = Vec::with_capacity(plan.actions.len() + 1);
+    let mut actions = Vec::with_capacity(plan.actions.len());
+    for action in &plan.actions {
+        new_index.push(actions.len());
+        match action {
+            Action::Wait { ms } => {
+                let ms = (*ms as f64 * factor).round() as u64;
+                if ms > 0 {
+                    actions.push(Action::Wait { ms });
+                }
+            }
+            Action::KeyRepeat {
+                keycode,
+                count,
+                interval_ms,
+            } => actions.push(Action::KeyRepeat {
+                keycode: *keycode,
+                count: *count,
+                interval_ms: (*interval_ms as f64 * factor).round() as u64,
+            }),
+            other => actions.push(other.clone()),
+        }
+    }
+    new_index.push(actions.len());
+    let annotations = plan
+        .annotations
+        .iter()
+        .map(|annotation| Annotation {
+            action_start: new_index[annotation.action_start],
+            action_end: new_index[annotation.action_end],
+            ..annotation.clone()
+        })
+        .collect();
+    Ok(Plan {
+        actions,
+        annotations,
+        config: PlanConfig {
+            wpm_target: plan.config.wpm_target / factor,
+            ..plan.config.clone()
+        },
+        ..plan.clone()
+    })
+}
+
+/// `plan` without redundant actions, playing the same keys with the same modifiers at the same
+/// times: consecutive waits are merged and zero waits dropped, and modifier updates that set
+/// the state already set, or are replaced by the next action before any key, are dropped.
+/// Checkpoints are kept where they are, and annotations follow the actions they cover.
+pub fn optimize(plan: &Plan) -> Plan {
+    type ModifierState = (u32, u32, u32, u32);
+    // Index in the optimized plan of each action (and of the end), as in `scale_waits`.
+    let mut new_index = Vec::with_capacity(plan.actions.len() + 1);
+    let mut actions: Vec<Action> = Vec::with_capacity(plan.actions.len());
+    // The state the last kept modifier update sets, and the one before it.
+    let mut sent: Option<ModifierState> = None;
+    let mut sent_before: Option<ModifierState> = None;
+    for action in &plan.actions {
+        new_index.push(actions.len());
+        match *action {
+            Action::Wait { ms: 0 } => {}
+            Action::Wait { ms } => {
+                if let Some(Action::Wait { ms: previous }) = actions.last_mut() {
+                    *previous += ms;
+                } else {
+                    actions.push(action.clone());
+                }
+            }
+            Action::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+            } => {
+                if matches!(actions.last(), Some(Action::Modifiers { .. })) {
+                    actions.pop();
+                    sent = sent_before;
+                }
+                let state = (mods_depressed, mods_latched, mods_locked, group);
+                if sent != Some(state) {
+                    actions.push(action.clone());
+                    sent_before = sent;
+                    sent = Some(state);
+                }
+            }
+            Action::Key { .. } | Action::KeyRepeat { .. } | Action::Checkpoint { .. } => {
+                actions.push(action.clone())
+            }
+        }
+    }
+    new_index.push(actions.len());
+    let annotations = plan
+        .annotations
+        .iter()
+        .map(|annotation| Annotation {
+            action_start: new_index[annotation.action_start].min(actions.len()),
+            action_end: new_index[annotation.action_end].min(actions.len()),
+            ..annotation.clone()
+        })
+        .collect();
+    Plan {
+        actions,
+        annotations,
+        ..plan.clone()
+    }
+}
+
+/// `plans` played one after the other. They must share a keymap, and each must end neutral;
+/// the result keeps the first plan's WPM target, and their annotations and checkpoints refer to
+/// the texts they type put together.
+pub fn concat(plans: &[Plan]) -> Result<Plan> {
+    let Some((first, rest)) = plans.split_first() else {
+        return Err(anyhow!("no plans to concatenate"));
+    };
+    let mut actions = first.actions.clone();
+    let mut annotations = first.annotations.clone();
+    for (idx, plan) in plans.iter().enumerate() {
+        verify_neutral(plan).map_err(|err| anyhow!("plan {}: {err}", idx + 1))?;
+    }
+    // Only simulated when a later plan has annotations to shift.
+    let shift_sources = rest.iter().any(|plan| {
+        !plan.annotations.is_empty()
+            || plan
+                .actions
+                .iter()
+                .any(|action| matches!(action, Action::Checkpoint { .. }))
+    });
+    let mut source_offset = 0;
+    for (idx, plan) in rest.iter().enumerate() {
+        let same_keymap = plan.config.layout == first.config.layout
+            && plan.config.keymap_format == first.config.keymap_format
+            && plan.config.keymap == first.config.keymap;
+        if !same_keymap {
+            return Err(anyhow!(
+                "plan {} uses layout {:?} with a different keymap than plan 1 ({:?})",
+                idx + 2,
+                plan.config.layout,
+                first.config.layout
+            ));
+        }
+        if shift_sources {
+            source_offset += simulate_typed_text(&plans[idx])?.chars().count();
+        }
+        annotations.extend(plan.annotations.iter().map(|annotation| Annotation {
+            action_start: annotation.action_start + actions.len(),
+            action_end: annotation.action_end + actions.len(),
+            source_start: annotation.source_start + source_offset,
+            source_end: annotation.source_end + source_offset,
+            ..annotation.clone()
+        }));
+        actions.extend(plan.actions.iter().map(|action| match action {
+            Action::Checkpoint { chars } => Action::Checkpoint {
+                chars: chars + source_offset,
+            },
+            other => other.clone(),
+        }));
+    }
+    Ok(Plan {
+        actions,
+        annotations,
+        ..first.clone()
+    })
+}
+
+/// A plan with `like`'s config that presses Enter `enters` times, then waits `pause_ms`. Put
+/// between plans with [`concat`] to separate the texts they type.
+pub fn separator(like: &Plan, enters: usize, pause_ms: u64) -> Plan {
+    let mut actions = Vec::with_capacity(enters * 4 + 1);
+    for _ in 0..enters {
+        actions.extend([
+            Action::Key {
+                keycode: KEY_ENTER,
+                state: KeyState::Pressed,
+            },
+            Action::Wait {
+                ms: SEPARATOR_KEY_HOLD_MS,
+            },
+            Action::Key {
+                keycode: KEY_ENTER,
+                state: KeyState::Released,
+            },
+            Action::Wait {
+                ms: SEPARATOR_ENTER_GAP_MS,
+            },
+        ]);
+    }
+    if pause_ms > 0 {
+        actions.push(Action::Wait { ms: pause_ms });
+    }
+    Plan {
+        version: like.version,
+        config: like.config.clone(),
+        actions,
+        annotations: Vec::new(),
+    }
+}
+
+/// `plan` without its text, for sharing timing traces: every key that types text presses one
+/// of the [`PLACEHOLDER_KEYCODES`] instead, and the embedded keymap is replaced by the layout's
+/// stock keymap (custom keymaps bind the characters a document needs).
+///
+/// Waits, modifiers, CapsLock, editing and navigation keys are kept, so the plan still plays
+/// with the same timing, corrections and cursor movement. With `keep_whitespace`, Space, Enter
+/// and Tab are kept too, which reveals word and paragraph lengths; the plan's annotations and
+/// checkpoints, which reveal paragraph and sentence lengths as well, are kept only then.
+pub fn anonymize(plan: &Plan, keep_whitespace: bool) -> Result<Plan> {
+    let altgr_keycode = KeyboardLayout::for_plan_config(&plan.config)?.altgr_keycode();
+    let is_kept = |keycode: u32| {
+        STRUCTURE_KEYCODES.contains(&keycode)
+            || Some(keycode) == altgr_keycode
+            || (keep_whitespace && WHITESPACE_KEYCODES.contains(&keycode))
+    };
+
+    // (original, placeholder) for each replaced key currently held.
+    let mut held: Vec<(u32, u32)> = Vec::new();
+    let mut actions = Vec::with_capacity(plan.actions.len());
+    for (idx, action) in plan.actions.iter().enumerate() {
+        if let Action::KeyRepeat {
+            keycode,
+            count,
+            interval_ms,
+        } = *action
+        {
+            if is_kept(keycode) {
+                actions.push(action.clone());
+                continue;
+            }
+            let placeholder = PLACEHOLDER_KEYCODES
+                .into_iter()
+                .find(|placeholder| held.iter().all(|&(_, held)| held != *placeholder))
+                .ok_or_else(|| anyhow!("action {idx}: too many text keys held at once"))?;
+            actions.push(Action::KeyRepeat {
+                keycode: placeholder,
+                count,
+                interval_ms,
+            });
+            continue;
+        }
+        let Action::Key { keycode, state } = *action else {
+            if keep_whitespace || !matches!(action, Action::Checkpoint { .. }) {
+                actions.push(action.clone());
+            }
+            continue;
+        };
+        if is_kept(keycode) {
+            actions.push(action.clone());
+            continue;
+        }
+        let placeholder = match state {
+            KeyState::Pressed => {
+                let placeholder = PLACEHOLDER_KEYCODES
+                    .into_iter()
+                    .find(|placeholder| held.iter().all(|&(_, held)| held != *placeholder))
+                    .ok_or_else(|| anyhow!("action {idx}: too many text keys held at once"))?;
+                held.push((keycode, placeholder));
+                placeholder
+            }
+            KeyState::Released => {
+                let Some(pos) = held.iter().position(|&(original, _)| original == keycode) else {
+                    return Err(anyhow!(
+                        "action {idx}: keycode {keycode} released while not held"
+                    ));
+                };
+                held.remove(pos).1
+            }
+        };
+        actions.push(Action::Key {
+            keycode: placeholder,
+            state,
+        });
+    }
+
+    let label = plan.config.layout.as_str();
+    let base_label = label.strip_suffix("+custom").unwrap_or(label);
+    let keymap = keymap_for_layout(&LayoutSpec::from_label(base_label))?;
+    Ok(Plan {
+        actions,
+        config: PlanConfig {
+            layout: keymap.layout,
+            keymap_format: keymap.keymap_format,
+            keymap: keymap.keymap,
+            ..plan.config.clone()
+        },
+        annotations: if keep_whitespace {
+            plan.annotations.clone()
+        } else {
+            Vec::new()
+        },
+        ..plan.clone()
+    })
+}
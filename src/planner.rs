@@ -1,21 +1,229 @@
-use anyhow::{anyhow, ensure, Result};
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
 use rand::Rng;
 use rand_distr::{Distribution, Normal};
 
+use crate::careful::careful_tokens;
+use crate::charmap::CharMap;
+use crate::diff::word_edits;
+use crate::editor::{
+    autocorrection, closing_bracket, is_word_char, CodeEditor, EditorModel, AUTOCORRECTIONS,
+};
+use crate::error::DrafterError;
 use crate::keyboard::{
-    find_first_unsupported_char, keystroke_for_output_char, qwerty_adjacent_char, KeyStroke,
-    KEY_BACKSPACE, KEY_LEFT, KEY_RIGHT,
+    is_left_hand_keycode, KeyStroke, KEY_BACKSPACE, KEY_CAPSLOCK, KEY_DELETE, KEY_DOWN, KEY_END,
+    KEY_HOME, KEY_LEFT, KEY_LEFTSHIFT, KEY_RIGHT, KEY_RIGHTSHIFT, KEY_TAB, KEY_UP,
+};
+use crate::keymap::LayoutSpec;
+use crate::layout::{KeyboardLayout, SmartQuotes};
+use crate::llm::{
+    validate_human_mistakes_for_layout, validate_paragraph_draft_for_layout,
+    validate_phrase_alternatives_for_layout, HumanMistake, ParagraphDraftOptions,
+    ParagraphDraftProvider, ParagraphRephraseOptions, ParagraphRephraseProvider, PhraseAlternative,
+    RevisionTiming,
 };
-use crate::keymap::us_qwerty_keymap;
-use crate::llm::{validate_phrase_alternatives, PhraseAlternative};
-use crate::model::{Action, KeyState, Plan, PlanConfig};
-use crate::word_nav_profile::{compatible_ctrl_jump_is_safe, WordNavProfile};
+use crate::model::{
+    find_trailing_whitespace, strip_trailing_whitespace, Action, Annotation, AnnotationKind,
+    KeyState, ParagraphBreaks, Plan, PlanConfig,
+};
+use crate::plan::PLAN_VERSION;
+use crate::word_nav_profile::WordNavProfile;
+
+/// How the planner handles Tab characters in the draft.
+///
+/// Tab indents in some editors but moves focus to the next field in others (including most web
+/// forms), so typing it is opt-in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TabPolicy {
+    /// Reject drafts that contain tabs.
+    #[default]
+    Reject,
+    /// Press the Tab key for each tab.
+    Literal,
+    /// Replace each tab with this many spaces before planning.
+    Spaces(usize),
+}
+
+/// How the planner handles whitespace the reader cannot see: the spaces and tabs at the ends of
+/// lines, or the newlines that end the draft.
+///
+/// It is typed like any other text by default, but many editors drop it on save, and a
+/// clipboard read-back of the typed text often loses it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhitespacePolicy {
+    /// Type it as written.
+    #[default]
+    Preserve,
+    /// Leave it out of the plan.
+    Strip,
+    /// Reject drafts that have it.
+    Reject,
+}
+
+/// How the planner types a character that editors commonly produce by auto-substitution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubstitutionStrategy {
+    /// Type the character like any other; drafts are rejected if the layout cannot type it.
+    #[default]
+    Reject,
+    /// Type its ASCII spelling (`--` for `—`, `...` for `…`) and rely on the editor to
+    /// substitute it, like smart quotes.
+    Ascii,
+}
+
+/// Editor auto-substitutions (besides smart quotes) the planner may rely on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EditorSubstitutions {
+    pub em_dash: SubstitutionStrategy,
+    pub ellipsis: SubstitutionStrategy,
+    /// The editor corrects the typos of [`crate::editor::AUTOCORRECTIONS`], so
+    /// [`PlannerConfig::autocorrect_typo_rate`] may leave them to it.
+    pub autocorrect: bool,
+}
+
+impl EditorSubstitutions {
+    /// Google Docs' default substitutions turn `--` into `—` and `...` into `…`, and its
+    /// spelling autocorrect fixes classic typos like `teh`.
+    pub const GOOGLE_DOCS: Self = Self {
+        em_dash: SubstitutionStrategy::Ascii,
+        ellipsis: SubstitutionStrategy::Ascii,
+        autocorrect: true,
+    };
+
+    /// ASCII spelling `c` is typed as, if it relies on auto-substitution.
+    pub fn ascii_spelling(&self, c: char) -> Option<&'static str> {
+        self.ascii_spellings()
+            .find(|(spelled, _)| *spelled == c)
+            .map(|(_, ascii)| ascii)
+    }
+
+    /// Characters typed as ASCII spellings, with those spellings.
+    fn ascii_spellings(&self) -> impl Iterator<Item = (char, &'static str)> {
+        [('—', "--", self.em_dash), ('…', "...", self.ellipsis)]
+            .into_iter()
+            .filter(|(_c, _ascii, strategy)| *strategy == SubstitutionStrategy::Ascii)
+            .map(|(c, ascii, _strategy)| (c, ascii))
+    }
+}
+
+/// How key timing varies around the speed drawn from `wpm_min..=wpm_max`. The defaults suit a
+/// typical typist; [`crate::timing_calibration::fit`] fits them to a real one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyRhythm {
+    /// Standard deviation of the delay between releasing a key and pressing the next, as a
+    /// fraction of its mean.
+    pub delay_spread: f64,
+    /// Bounds of that delay, before punctuation and thinking pauses.
+    pub delay_min_ms: u64,
+    pub delay_max_ms: u64,
+    /// Bounds of how long each key is held down.
+    pub hold_min_ms: u64,
+    pub hold_max_ms: u64,
+}
+
+impl Default for KeyRhythm {
+    fn default() -> Self {
+        Self {
+            delay_spread: 0.35,
+            delay_min_ms: 25,
+            delay_max_ms: 900,
+            hold_min_ms: 18,
+            hold_max_ms: 70,
+        }
+    }
+}
+
+impl KeyRhythm {
+    /// Expected time a key is held down.
+    pub fn mean_hold_ms(&self) -> f64 {
+        (self.hold_min_ms + self.hold_max_ms) as f64 / 2.0
+    }
+}
+
+/// Which Shift key the planner presses for shifted keystrokes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShiftProfile {
+    /// Press the Shift key opposite the hand that types the key, like a touch typist.
+    HandAware,
+    /// Always press Left Shift.
+    #[default]
+    LeftOnly,
+}
+
+/// Which kinds of mistakes the planner makes. How often it makes them is up to the rates of
+/// [`PlannerConfig`]: `error_rate_per_word` for mistyped words and doubled spaces, and a rate of
+/// its own for each other kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorMix {
+    /// A letter typed as the one on a physically adjacent key (`tge` for `the`).
+    pub adjacent_substitution: bool,
+    /// Two neighboring letters swapped (`wrod` for `word`), in words of four letters or more.
+    pub transposition: bool,
+    /// A built-in variant of the word: a synonym, or `-ed` typed as `-ing` and back (prose only).
+    pub word_variants: bool,
+    /// A space typed twice.
+    pub double_spaces: bool,
+    /// See [`PlannerConfig::punctuation_error_rate`].
+    pub punctuation: bool,
+    /// See [`PlannerConfig::missing_period_rate`].
+    pub missing_periods: bool,
+    /// See [`PlannerConfig::confusion_rate`].
+    pub confusions: bool,
+    /// See [`PlannerConfig::autocorrect_typo_rate`].
+    pub autocorrect_typos: bool,
+}
+
+impl ErrorMix {
+    pub const ALL: Self = Self {
+        adjacent_substitution: true,
+        transposition: true,
+        word_variants: true,
+        double_spaces: true,
+        punctuation: true,
+        missing_periods: true,
+        confusions: true,
+        autocorrect_typos: true,
+    };
+
+    pub const NONE: Self = Self {
+        adjacent_substitution: false,
+        transposition: false,
+        word_variants: false,
+        double_spaces: false,
+        punctuation: false,
+        missing_periods: false,
+        confusions: false,
+        autocorrect_typos: false,
+    };
+}
+
+impl Default for ErrorMix {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// What kind of text the planner types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TypingMode {
+    /// Prose, with sentence rhythm and the full range of mistakes.
+    #[default]
+    Prose,
+    /// Source code or config files: no word alternatives, doubled spaces or careful tokens, and
+    /// pauses for reaching symbols and starting lines instead of sentence pauses. Indentation is
+    /// typed exactly as written, taking [`PlannerConfig::code_editor`] into account.
+    Code,
+}
 
 #[derive(Debug, Clone)]
 pub struct PlannerConfig {
     pub wpm_min: f64,
     pub wpm_max: f64,
+    pub rhythm: KeyRhythm,
     pub error_rate_per_word: f64,
+    /// Which kinds of mistakes to make.
+    pub error_mix: ErrorMix,
     pub word_variant_share: f64,
     pub immediate_fix_rate: f64,
     pub word_nav_profile: WordNavProfile,
@@ -23,6 +231,64 @@ pub struct PlannerConfig {
     pub stop_corrections_after_progress: f64,
     pub review_pause_ms_min: u64,
     pub review_pause_ms_max: u64,
+    /// Keyboard layout the plan is generated for (and must be played back with).
+    pub layout: LayoutSpec,
+    /// Bind characters the layout cannot type to spare keycodes in a generated keymap
+    /// (Wayland playback only).
+    pub custom_keymap: bool,
+    pub tab_policy: TabPolicy,
+    /// How paragraph breaks are typed; with [`ParagraphBreaks::Single`], every line is a
+    /// paragraph.
+    pub paragraph_breaks: ParagraphBreaks,
+    /// Spaces and tabs at the ends of the draft's lines.
+    pub trailing_whitespace: WhitespacePolicy,
+    /// The newlines that end the draft.
+    pub final_newline: WhitespacePolicy,
+    /// User-supplied character replacements and keystroke overrides.
+    pub charmap: CharMap,
+    /// Probability of toggling CapsLock (instead of holding Shift) for a long ALL-CAPS run; 0 (the
+    /// default) always holds Shift.
+    pub caps_lock_rate: f64,
+    pub shift_profile: ShiftProfile,
+    pub substitutions: EditorSubstitutions,
+    /// Whether smart quotes rely on editor substitution or are typed literally (via spare keys
+    /// in a generated keymap when the layout has none).
+    pub smart_quotes: SmartQuotes,
+    /// Probability of typing an LLM-proposed human mistake (see [`HumanMistake`]) where one is
+    /// available. Words with a single-word mistake use it instead of `error_rate_per_word`.
+    pub llm_mistake_rate: f64,
+    /// Delete mistakes at least this many characters long by holding Backspace down
+    /// ([`Action::KeyRepeat`]) instead of pressing it once per character; 0 never holds it.
+    pub hold_backspace_from: usize,
+    /// Probability of typing a punctuation mark as the one on a physically adjacent key (a
+    /// comma as a period, a semicolon as an apostrophe), fixed like a word typo.
+    pub punctuation_error_rate: f64,
+    /// Probability of leaving out the period that ends a paragraph and adding it back at a
+    /// later review.
+    pub missing_period_rate: f64,
+    /// Probability of typing a word from the built-in table of homophones and common
+    /// confusions as the word it is confused with (`their` as `there`, `its` as `it's`), fixed
+    /// at a later review.
+    pub confusion_rate: f64,
+    /// Probability of typing a word as a classic typo the editor autocorrects (`the` as `teh`)
+    /// and leaving it to the editor, when [`EditorSubstitutions::autocorrect`] says it does.
+    pub autocorrect_typo_rate: f64,
+    /// Probability, at the start of each paragraph, of stepping away for a session break
+    /// lasting between `session_break_ms_min` and `session_break_ms_max`.
+    pub session_break_rate: f64,
+    pub session_break_ms_min: u64,
+    pub session_break_ms_max: u64,
+    /// Re-read the end of the text after a session break at least this long: glance back a
+    /// few lines with Up and Home, then return with Down. 0 never re-reads.
+    pub reread_after_ms: u64,
+    /// Type URLs, email addresses and code-like identifiers (see [`crate::careful`]) slower,
+    /// without mistakes or alternatives, and in one go so no correction lands inside them.
+    /// Prose only.
+    pub careful_tokens: bool,
+    pub mode: TypingMode,
+    /// Edits the editor makes as text is typed, which the plan relies on: it types only the
+    /// indentation the editor does not add, and moves over closing brackets it added.
+    pub code_editor: CodeEditor,
 }
 
 impl Default for PlannerConfig {
@@ -30,7 +296,9 @@ impl Default for PlannerConfig {
         Self {
             wpm_min: 40.0,
             wpm_max: 60.0,
+            rhythm: KeyRhythm::default(),
             error_rate_per_word: 0.05,
+            error_mix: ErrorMix::ALL,
             word_variant_share: 0.35,
             immediate_fix_rate: 0.35,
             word_nav_profile: WordNavProfile::Chrome,
@@ -38,122 +306,403 @@ impl Default for PlannerConfig {
             stop_corrections_after_progress: 0.88,
             review_pause_ms_min: 1200,
             review_pause_ms_max: 2600,
+            layout: LayoutSpec::default(),
+            custom_keymap: false,
+            tab_policy: TabPolicy::Reject,
+            paragraph_breaks: ParagraphBreaks::AsWritten,
+            trailing_whitespace: WhitespacePolicy::Preserve,
+            final_newline: WhitespacePolicy::Preserve,
+            charmap: CharMap::default(),
+            caps_lock_rate: 0.0,
+            shift_profile: ShiftProfile::LeftOnly,
+            substitutions: EditorSubstitutions::default(),
+            smart_quotes: SmartQuotes::Substitute,
+            llm_mistake_rate: 0.5,
+            hold_backspace_from: 0,
+            punctuation_error_rate: 0.0,
+            missing_period_rate: 0.0,
+            confusion_rate: 0.0,
+            autocorrect_typo_rate: 0.0,
+            session_break_rate: 0.0,
+            session_break_ms_min: 45_000,
+            session_break_ms_max: 240_000,
+            reread_after_ms: 30_000,
+            careful_tokens: true,
+            mode: TypingMode::Prose,
+            code_editor: CodeEditor::default(),
         }
     }
 }
 
-fn validate_config(cfg: &PlannerConfig) -> Result<()> {
-    ensure!(cfg.wpm_min.is_finite(), "wpm_min must be finite");
-    ensure!(cfg.wpm_max.is_finite(), "wpm_max must be finite");
-    ensure!(
-        cfg.wpm_min > 0.0 && cfg.wpm_max > 0.0,
-        "wpm_min and wpm_max must be > 0"
-    );
-    ensure!(cfg.wpm_min <= cfg.wpm_max, "wpm_min must be <= wpm_max");
+impl PlannerConfig {
+    /// A builder starting from the defaults that checks each setting as it is set.
+    pub fn builder() -> PlannerConfigBuilder {
+        PlannerConfigBuilder::default()
+    }
+}
 
-    ensure!(
-        (0.0..=1.0).contains(&cfg.error_rate_per_word),
-        "error_rate_per_word must be between 0.0 and 1.0"
-    );
-    ensure!(
-        (0.0..=1.0).contains(&cfg.word_variant_share),
-        "word_variant_share must be between 0.0 and 1.0"
-    );
-    ensure!(
-        (0.0..=1.0).contains(&cfg.immediate_fix_rate),
-        "immediate_fix_rate must be between 0.0 and 1.0"
-    );
-    ensure!(
-        (0.0..=1.0).contains(&cfg.stop_corrections_after_progress),
-        "stop_corrections_after_progress must be between 0.0 and 1.0"
-    );
+/// Builds a [`PlannerConfig`] with fluent setters, reporting the first invalid setting from
+/// [`PlannerConfigBuilder::build`] instead of at planning time.
+#[derive(Debug, Clone, Default)]
+pub struct PlannerConfigBuilder {
+    config: PlannerConfig,
+    /// The first invalid setting.
+    error: Option<String>,
+}
 
-    ensure!(
-        cfg.review_pause_ms_min <= cfg.review_pause_ms_max,
-        "review_pause_ms_min must be <= review_pause_ms_max"
-    );
+impl PlannerConfigBuilder {
+    /// Typing speed is drawn between `min` and `max` words per minute.
+    pub fn wpm(mut self, min: f64, max: f64) -> Self {
+        self.config.wpm_min = min;
+        self.config.wpm_max = max;
+        self.check(check_wpm(min, max))
+    }
 
-    Ok(())
-}
+    pub fn rhythm(mut self, rhythm: KeyRhythm) -> Self {
+        self.config.rhythm = rhythm;
+        self.check(check_rhythm(&rhythm))
+    }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum CorrectionConstraint {
-    None,
-    SentenceOrParagraphBoundary,
-}
+    pub fn error_rate_per_word(mut self, rate: f64) -> Self {
+        self.config.error_rate_per_word = rate;
+        self.check(check_rate("error_rate_per_word", rate))
+    }
 
-#[derive(Debug, Clone)]
-struct OutstandingError {
-    start: usize,
-    wrong: String,
-    correct: String,
-    fix_after_chars: usize,
-    constraint: CorrectionConstraint,
-}
+    pub fn error_mix(mut self, error_mix: ErrorMix) -> Self {
+        self.config.error_mix = error_mix;
+        self
+    }
 
-#[derive(Debug, Default, Clone)]
-struct EditorState {
-    buf: Vec<char>,
-    cursor: usize,
-}
+    pub fn word_variant_share(mut self, share: f64) -> Self {
+        self.config.word_variant_share = share;
+        self.check(check_rate("word_variant_share", share))
+    }
 
-impl EditorState {
-    fn insert_char(&mut self, c: char) {
-        assert!(self.cursor <= self.buf.len());
-        self.buf.insert(self.cursor, c);
-        self.cursor += 1;
+    pub fn immediate_fix_rate(mut self, rate: f64) -> Self {
+        self.config.immediate_fix_rate = rate;
+        self.check(check_rate("immediate_fix_rate", rate))
     }
 
-    fn backspace(&mut self) {
-        if self.cursor == 0 {
-            return;
-        }
-        self.cursor -= 1;
-        self.buf.remove(self.cursor);
+    pub fn word_nav_profile(mut self, profile: WordNavProfile) -> Self {
+        self.config.word_nav_profile = profile;
+        self
+    }
+
+    pub fn max_outstanding_errors(mut self, max: usize) -> Self {
+        self.config.max_outstanding_errors = max;
+        self
+    }
+
+    pub fn stop_corrections_after_progress(mut self, progress: f64) -> Self {
+        self.config.stop_corrections_after_progress = progress;
+        self.check(check_rate("stop_corrections_after_progress", progress))
+    }
+
+    /// Review pauses last between `min_ms` and `max_ms`.
+    pub fn review_pause_ms(mut self, min_ms: u64, max_ms: u64) -> Self {
+        self.config.review_pause_ms_min = min_ms;
+        self.config.review_pause_ms_max = max_ms;
+        self.check(check_review_pause(min_ms, max_ms))
+    }
+
+    pub fn layout(mut self, layout: LayoutSpec) -> Self {
+        self.config.layout = layout;
+        self
+    }
+
+    pub fn custom_keymap(mut self, custom_keymap: bool) -> Self {
+        self.config.custom_keymap = custom_keymap;
+        self
+    }
+
+    pub fn tab_policy(mut self, tab_policy: TabPolicy) -> Self {
+        self.config.tab_policy = tab_policy;
+        self
+    }
+
+    pub fn paragraph_breaks(mut self, paragraph_breaks: ParagraphBreaks) -> Self {
+        self.config.paragraph_breaks = paragraph_breaks;
+        self
+    }
+
+    pub fn trailing_whitespace(mut self, policy: WhitespacePolicy) -> Self {
+        self.config.trailing_whitespace = policy;
+        self
+    }
+
+    pub fn final_newline(mut self, policy: WhitespacePolicy) -> Self {
+        self.config.final_newline = policy;
+        self
+    }
+
+    pub fn charmap(mut self, charmap: CharMap) -> Self {
+        self.config.charmap = charmap;
+        self
+    }
+
+    pub fn caps_lock_rate(mut self, rate: f64) -> Self {
+        self.config.caps_lock_rate = rate;
+        self.check(check_rate("caps_lock_rate", rate))
+    }
+
+    pub fn shift_profile(mut self, shift_profile: ShiftProfile) -> Self {
+        self.config.shift_profile = shift_profile;
+        self
+    }
+
+    pub fn substitutions(mut self, substitutions: EditorSubstitutions) -> Self {
+        self.config.substitutions = substitutions;
+        self
+    }
+
+    pub fn smart_quotes(mut self, smart_quotes: SmartQuotes) -> Self {
+        self.config.smart_quotes = smart_quotes;
+        self
+    }
+
+    pub fn llm_mistake_rate(mut self, rate: f64) -> Self {
+        self.config.llm_mistake_rate = rate;
+        self.check(check_rate("llm_mistake_rate", rate))
+    }
+
+    pub fn punctuation_error_rate(mut self, rate: f64) -> Self {
+        self.config.punctuation_error_rate = rate;
+        self.check(check_rate("punctuation_error_rate", rate))
+    }
+
+    pub fn missing_period_rate(mut self, rate: f64) -> Self {
+        self.config.missing_period_rate = rate;
+        self.check(check_rate("missing_period_rate", rate))
+    }
+
+    pub fn confusion_rate(mut self, rate: f64) -> Self {
+        self.config.confusion_rate = rate;
+        self.check(check_rate("confusion_rate", rate))
+    }
+
+    pub fn autocorrect_typo_rate(mut self, rate: f64) -> Self {
+        self.config.autocorrect_typo_rate = rate;
+        self.check(check_rate("autocorrect_typo_rate", rate))
+    }
+
+    pub fn session_break_rate(mut self, rate: f64) -> Self {
+        self.config.session_break_rate = rate;
+        self.check(check_rate("session_break_rate", rate))
     }
 
-    fn move_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
+    /// Session breaks last between `min_ms` and `max_ms`.
+    pub fn session_break_ms(mut self, min_ms: u64, max_ms: u64) -> Self {
+        self.config.session_break_ms_min = min_ms;
+        self.config.session_break_ms_max = max_ms;
+        self.check(check_session_break(min_ms, max_ms))
+    }
+
+    pub fn reread_after_ms(mut self, ms: u64) -> Self {
+        self.config.reread_after_ms = ms;
+        self
+    }
+
+    pub fn careful_tokens(mut self, careful_tokens: bool) -> Self {
+        self.config.careful_tokens = careful_tokens;
+        self
+    }
+
+    /// Hold Backspace to delete mistakes of at least `chars` characters; 0 never holds it.
+    pub fn mode(mut self, mode: TypingMode) -> Self {
+        self.config.mode = mode;
+        self
+    }
+
+    pub fn code_editor(mut self, code_editor: CodeEditor) -> Self {
+        self.config.code_editor = code_editor;
+        self
+    }
+
+    pub fn hold_backspace_from(mut self, chars: usize) -> Self {
+        self.config.hold_backspace_from = chars;
+        self
+    }
+
+    /// The config, or [`DrafterError::InvalidConfig`] for the first invalid setting.
+    pub fn build(self) -> Result<PlannerConfig, DrafterError> {
+        match self.error {
+            Some(message) => Err(DrafterError::InvalidConfig(message)),
+            None => Ok(self.config),
         }
     }
 
-    fn move_right(&mut self) {
-        if self.cursor < self.buf.len() {
-            self.cursor += 1;
+    fn check(mut self, result: Result<(), DrafterError>) -> Self {
+        if let (None, Err(err)) = (&self.error, result) {
+            self.error = Some(err.to_string());
         }
+        self
     }
+}
+
+fn invalid_config(message: &str) -> DrafterError {
+    DrafterError::InvalidConfig(message.to_string())
+}
 
-    fn move_word_left(&mut self) {
-        self.cursor = crate::word_nav::ctrl_left(&self.buf, self.cursor, is_word_char);
+fn check_wpm(min: f64, max: f64) -> Result<(), DrafterError> {
+    if !min.is_finite() {
+        return Err(invalid_config("wpm_min must be finite"));
+    }
+    if !max.is_finite() {
+        return Err(invalid_config("wpm_max must be finite"));
+    }
+    if min <= 0.0 || max <= 0.0 {
+        return Err(invalid_config("wpm_min and wpm_max must be > 0"));
     }
+    if min > max {
+        return Err(invalid_config("wpm_min must be <= wpm_max"));
+    }
+    Ok(())
+}
 
-    fn move_word_right(&mut self) {
-        self.cursor = crate::word_nav::ctrl_right(&self.buf, self.cursor, is_word_char);
+fn check_rhythm(rhythm: &KeyRhythm) -> Result<(), DrafterError> {
+    if !rhythm.delay_spread.is_finite() || rhythm.delay_spread < 0.0 {
+        return Err(invalid_config("delay_spread must be finite and >= 0"));
+    }
+    if rhythm.delay_min_ms > rhythm.delay_max_ms {
+        return Err(invalid_config("delay_min_ms must be <= delay_max_ms"));
+    }
+    if rhythm.hold_min_ms == 0 || rhythm.hold_min_ms > rhythm.hold_max_ms {
+        return Err(invalid_config("hold_min_ms must be > 0 and <= hold_max_ms"));
     }
+    Ok(())
+}
+
+fn check_rate(name: &str, value: f64) -> Result<(), DrafterError> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(DrafterError::InvalidConfig(format!(
+            "{name} must be between 0.0 and 1.0"
+        )));
+    }
+    Ok(())
+}
+
+fn check_review_pause(min_ms: u64, max_ms: u64) -> Result<(), DrafterError> {
+    if min_ms > max_ms {
+        return Err(invalid_config(
+            "review_pause_ms_min must be <= review_pause_ms_max",
+        ));
+    }
+    Ok(())
+}
+
+fn check_session_break(min_ms: u64, max_ms: u64) -> Result<(), DrafterError> {
+    if min_ms > max_ms {
+        return Err(invalid_config(
+            "session_break_ms_min must be <= session_break_ms_max",
+        ));
+    }
+    Ok(())
+}
+
+/// `cfg` with the rates of the kinds of mistakes its [`ErrorMix`] leaves out set to 0.
+pub(crate) fn apply_error_mix(mut cfg: PlannerConfig) -> PlannerConfig {
+    let mix = cfg.error_mix;
+    if !(mix.adjacent_substitution || mix.transposition || mix.word_variants || mix.double_spaces) {
+        cfg.error_rate_per_word = 0.0;
+    }
+    if !mix.punctuation {
+        cfg.punctuation_error_rate = 0.0;
+    }
+    if !mix.missing_periods {
+        cfg.missing_period_rate = 0.0;
+    }
+    if !mix.confusions {
+        cfg.confusion_rate = 0.0;
+    }
+    if !mix.autocorrect_typos {
+        cfg.autocorrect_typo_rate = 0.0;
+    }
+    cfg
+}
 
-    fn as_string(&self) -> String {
-        self.buf.iter().collect()
+fn validate_config(cfg: &PlannerConfig) -> Result<(), DrafterError> {
+    check_wpm(cfg.wpm_min, cfg.wpm_max)?;
+    check_rhythm(&cfg.rhythm)?;
+    for (name, value) in [
+        ("error_rate_per_word", cfg.error_rate_per_word),
+        ("llm_mistake_rate", cfg.llm_mistake_rate),
+        ("word_variant_share", cfg.word_variant_share),
+        ("immediate_fix_rate", cfg.immediate_fix_rate),
+        (
+            "stop_corrections_after_progress",
+            cfg.stop_corrections_after_progress,
+        ),
+        ("caps_lock_rate", cfg.caps_lock_rate),
+        ("punctuation_error_rate", cfg.punctuation_error_rate),
+        ("missing_period_rate", cfg.missing_period_rate),
+        ("confusion_rate", cfg.confusion_rate),
+        ("autocorrect_typo_rate", cfg.autocorrect_typo_rate),
+        ("session_break_rate", cfg.session_break_rate),
+    ] {
+        check_rate(name, value)?;
     }
+    check_review_pause(cfg.review_pause_ms_min, cfg.review_pause_ms_max)?;
+    check_session_break(cfg.session_break_ms_min, cfg.session_break_ms_max)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CorrectionConstraint {
+    None,
+    SentenceOrParagraphBoundary,
+}
+
+#[derive(Debug, Clone)]
+struct OutstandingError {
+    start: usize,
+    wrong: String,
+    correct: String,
+    fix_after_chars: usize,
+    constraint: CorrectionConstraint,
 }
 
 #[derive(Debug, Clone)]
 struct ActionBuilder {
     actions: Vec<Action>,
+    layout: KeyboardLayout,
+    literal_tabs: bool,
+    hold_backspace_from: usize,
+    rhythm: KeyRhythm,
+    mode: TypingMode,
+    code_editor: CodeEditor,
+    shift_profile: ShiftProfile,
+    /// Shift key that is (or was last) held.
+    shift_keycode: u32,
     shift_down: bool,
     ctrl_down: bool,
+    altgr_down: bool,
+    caps_lock: bool,
     shift_mask: u32,
     ctrl_mask: u32,
+    altgr_mask: u32,
+    caps_mask: u32,
 }
 
 impl ActionBuilder {
-    fn new(shift_mask: u32, ctrl_mask: u32) -> Self {
+    fn new(layout: KeyboardLayout, cfg: &PlannerConfig) -> Self {
+        let keymap = layout.keymap();
         Self {
             actions: Vec::new(),
+            literal_tabs: cfg.tab_policy == TabPolicy::Literal,
+            hold_backspace_from: cfg.hold_backspace_from,
+            rhythm: cfg.rhythm,
+            mode: cfg.mode,
+            code_editor: cfg.code_editor,
+            shift_profile: cfg.shift_profile,
+            shift_keycode: KEY_LEFTSHIFT,
             shift_down: false,
             ctrl_down: false,
-            shift_mask,
-            ctrl_mask,
+            altgr_down: false,
+            caps_lock: false,
+            shift_mask: keymap.shift_mask,
+            ctrl_mask: keymap.ctrl_mask,
+            altgr_mask: keymap.altgr_mask,
+            caps_mask: keymap.caps_mask,
+            layout,
         }
     }
 
@@ -161,6 +710,11 @@ impl ActionBuilder {
         self.actions
     }
 
+    /// Whether no modifier is held and CapsLock is off (keys typing text are always released).
+    fn is_neutral(&self) -> bool {
+        !(self.shift_down || self.ctrl_down || self.altgr_down || self.caps_lock)
+    }
+
     fn wait(&mut self, ms: u64) {
         if ms == 0 {
             return;
@@ -180,11 +734,16 @@ impl ActionBuilder {
         if self.ctrl_down {
             depressed |= self.ctrl_mask;
         }
+        if self.altgr_down {
+            depressed |= self.altgr_mask;
+        }
+
+        let locked = if self.caps_lock { self.caps_mask } else { 0 };
 
         self.actions.push(Action::Modifiers {
             mods_depressed: depressed,
             mods_latched: 0,
-            mods_locked: 0,
+            mods_locked: locked,
             group: 0,
         });
     }
@@ -193,17 +752,15 @@ impl ActionBuilder {
         if self.shift_down == down {
             return;
         }
-        // Use left shift for simplicity.
-        const KEY_LEFTSHIFT: u32 = crate::keyboard::KEY_LEFTSHIFT;
 
         if down {
-            self.key(KEY_LEFTSHIFT, KeyState::Pressed);
+            self.key(self.shift_keycode, KeyState::Pressed);
             self.wait(rng.gen_range(5..=20));
             self.shift_down = true;
             self.set_modifiers();
             self.wait(rng.gen_range(0..=12));
         } else {
-            self.key(KEY_LEFTSHIFT, KeyState::Released);
+            self.key(self.shift_keycode, KeyState::Released);
             self.wait(rng.gen_range(5..=20));
             self.shift_down = false;
             self.set_modifiers();
@@ -232,8 +789,65 @@ impl ActionBuilder {
         }
     }
 
+    fn set_altgr(&mut self, down: bool, rng: &mut impl Rng) {
+        if self.altgr_down == down {
+            return;
+        }
+        // Strokes only require AltGr when the layout has a level-3 key.
+        let Some(keycode) = self.layout.altgr_keycode() else {
+            return;
+        };
+
+        if down {
+            self.key(keycode, KeyState::Pressed);
+            self.wait(rng.gen_range(5..=20));
+            self.altgr_down = true;
+            self.set_modifiers();
+            self.wait(rng.gen_range(0..=12));
+        } else {
+            self.key(keycode, KeyState::Released);
+            self.wait(rng.gen_range(5..=20));
+            self.altgr_down = false;
+            self.set_modifiers();
+            self.wait(rng.gen_range(0..=12));
+        }
+    }
+
+    /// Tap CapsLock to turn it on or off.
+    fn set_caps_lock(&mut self, on: bool, rng: &mut impl Rng) {
+        if self.caps_lock == on || self.caps_mask == 0 {
+            return;
+        }
+        self.set_ctrl(false, rng);
+        self.set_altgr(false, rng);
+        self.set_shift(false, rng);
+
+        self.wait(rng.gen_range(40..=160));
+        self.press_key(KEY_CAPSLOCK, rng);
+        self.wait(rng.gen_range(5..=20));
+        self.caps_lock = on;
+        self.set_modifiers();
+        self.wait(rng.gen_range(40..=160));
+    }
+
+    /// Whether every character of `text` can be typed while CapsLock is on.
+    fn can_type_with_caps_lock(&self, text: &[char]) -> bool {
+        text.iter()
+            .all(|c| self.layout.caps_lock_strokes_for_output_char(*c).is_some())
+    }
+
+    /// Shift key to hold while pressing `keycode`.
+    ///
+    /// A held Shift is kept for following keystrokes (e.g. through an ALL-CAPS word).
+    fn shift_keycode_for(&self, keycode: u32) -> u32 {
+        match self.shift_profile {
+            ShiftProfile::HandAware if is_left_hand_keycode(keycode) => KEY_RIGHTSHIFT,
+            ShiftProfile::HandAware | ShiftProfile::LeftOnly => KEY_LEFTSHIFT,
+        }
+    }
+
     fn press_key(&mut self, keycode: u32, rng: &mut impl Rng) {
-        let hold_ms = rng.gen_range(18..=70);
+        let hold_ms = rng.gen_range(self.rhythm.hold_min_ms..=self.rhythm.hold_max_ms);
         self.key(keycode, KeyState::Pressed);
         self.wait(hold_ms);
         self.key(keycode, KeyState::Released);
@@ -241,43 +855,130 @@ impl ActionBuilder {
 
     fn type_char(&mut self, stroke: KeyStroke, rng: &mut impl Rng) {
         self.set_ctrl(false, rng);
+        self.set_altgr(stroke.altgr, rng);
+        if stroke.shift && !self.shift_down {
+            self.shift_keycode = self.shift_keycode_for(stroke.keycode);
+        }
         self.set_shift(stroke.shift, rng);
         self.press_key(stroke.keycode, rng);
     }
 
+    /// Type the keystroke sequence for `c` (one stroke, or several for dead-key/Compose input).
+    fn type_output_char(&mut self, c: char, rng: &mut impl Rng) -> Result<()> {
+        if c == '\t' && self.literal_tabs {
+            let tab = KeyStroke {
+                keycode: KEY_TAB,
+                shift: false,
+                altgr: false,
+            };
+            self.type_char(tab, rng);
+            return Ok(());
+        }
+
+        if self.caps_lock && self.layout.caps_lock_strokes_for_output_char(c).is_none() {
+            self.set_caps_lock(false, rng);
+        }
+        let strokes = if self.caps_lock {
+            self.layout.caps_lock_strokes_for_output_char(c)
+        } else {
+            self.layout.strokes_for_output_char(c)
+        };
+        let strokes = strokes
+            .ok_or_else(|| {
+                anyhow!(
+                    "unsupported character for {} typing: {c:?} (U+{:04X})",
+                    self.layout.name(),
+                    c as u32
+                )
+            })?
+            .to_vec();
+
+        for (idx, stroke) in strokes.into_iter().enumerate() {
+            if idx > 0 {
+                self.wait(rng.gen_range(30..=120));
+            }
+            self.type_char(stroke, rng);
+        }
+        Ok(())
+    }
+
     fn nav_left(&mut self, rng: &mut impl Rng) {
         self.set_ctrl(false, rng);
+        self.set_altgr(false, rng);
         self.set_shift(false, rng);
         self.press_key(KEY_LEFT, rng);
     }
 
     fn nav_right(&mut self, rng: &mut impl Rng) {
         self.set_ctrl(false, rng);
+        self.set_altgr(false, rng);
         self.set_shift(false, rng);
         self.press_key(KEY_RIGHT, rng);
     }
 
+    /// Press Up or Down (`keycode`).
+    fn nav_vertical(&mut self, keycode: u32, rng: &mut impl Rng) {
+        self.set_ctrl(false, rng);
+        self.set_altgr(false, rng);
+        self.set_shift(false, rng);
+        self.press_key(keycode, rng);
+    }
+
     fn nav_word_left(&mut self, rng: &mut impl Rng) {
         self.set_ctrl(true, rng);
+        self.set_altgr(false, rng);
         self.set_shift(false, rng);
         self.press_key(KEY_LEFT, rng);
     }
 
     fn nav_word_right(&mut self, rng: &mut impl Rng) {
         self.set_ctrl(true, rng);
+        self.set_altgr(false, rng);
         self.set_shift(false, rng);
         self.press_key(KEY_RIGHT, rng);
     }
 
+    fn nav_line_edge(&mut self, keycode: u32, rng: &mut impl Rng) {
+        self.set_ctrl(false, rng);
+        self.set_altgr(false, rng);
+        self.set_shift(false, rng);
+        self.press_key(keycode, rng);
+    }
+
     fn backspace(&mut self, rng: &mut impl Rng) {
         self.set_ctrl(false, rng);
+        self.set_altgr(false, rng);
         self.set_shift(false, rng);
         self.press_key(KEY_BACKSPACE, rng);
     }
-}
 
-fn is_word_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '\'' || c == '’'
+    fn delete(&mut self, rng: &mut impl Rng) {
+        self.set_ctrl(false, rng);
+        self.set_altgr(false, rng);
+        self.set_shift(false, rng);
+        self.press_key(KEY_DELETE, rng);
+    }
+
+    /// Deletes `count` characters before the cursor: Backspace held down to repeat when
+    /// `count` reaches `hold_backspace_from`, else one press per character.
+    fn backspace_run(&mut self, count: usize, rng: &mut impl Rng) {
+        if self.hold_backspace_from == 0 || count < self.hold_backspace_from {
+            for _ in 0..count {
+                self.backspace(rng);
+                self.wait(rng.gen_range(15..=55));
+            }
+            return;
+        }
+        self.set_ctrl(false, rng);
+        self.set_altgr(false, rng);
+        self.set_shift(false, rng);
+        self.actions.push(Action::KeyRepeat {
+            keycode: KEY_BACKSPACE,
+            count: count as u32,
+            interval_ms: rng.gen_range(30..=40),
+        });
+        self.wait(rng.gen_range(15..=55));
+    }
 }
 
 fn apply_case_style(template: &str, lower: &str) -> String {
@@ -318,6 +1019,84 @@ fn synonym_options(word_lower: &str) -> &'static [&'static str] {
     }
 }
 
+/// Words a writer types instead of `word_lower` without noticing: homophones and other
+/// common confusions, in lower case.
+pub(crate) fn confusion_options(word_lower: &str) -> &'static [&'static str] {
+    match word_lower {
+        "their" => &["there", "they're"],
+        "there" => &["their"],
+        "they're" => &["their", "there"],
+        "its" => &["it's"],
+        "it's" => &["its"],
+        "your" => &["you're"],
+        "you're" => &["your"],
+        "whose" => &["who's"],
+        "who's" => &["whose"],
+        "affect" => &["effect"],
+        "effect" => &["affect"],
+        "then" => &["than"],
+        "than" => &["then"],
+        "to" => &["too"],
+        "too" => &["to"],
+        "lose" => &["loose"],
+        "loose" => &["lose"],
+        "accept" => &["except"],
+        "except" => &["accept"],
+        "weather" => &["whether"],
+        "whether" => &["weather"],
+        "principle" => &["principal"],
+        "principal" => &["principle"],
+        "complement" => &["compliment"],
+        "compliment" => &["complement"],
+        "advice" => &["advise"],
+        "advise" => &["advice"],
+        "hear" => &["here"],
+        "here" => &["hear"],
+        "piece" => &["peace"],
+        "peace" => &["piece"],
+        "quite" => &["quiet"],
+        "quiet" => &["quite"],
+        _ => &[],
+    }
+}
+
+/// Maybe (with probability `rate`) the word `word` is confused with, in its case style.
+fn confused_word(
+    word: &str,
+    rate: f64,
+    layout: &KeyboardLayout,
+    rng: &mut impl Rng,
+) -> Option<String> {
+    let options = confusion_options(&word.to_lowercase());
+    if options.is_empty() || !rng.gen_bool(rate) {
+        return None;
+    }
+    let confused = apply_case_style(word, options[rng.gen_range(0..options.len())]);
+    layout.is_supported_text(&confused).then_some(confused)
+}
+
+/// A typo of `word` the editor autocorrects back into it, with probability `rate` if the table
+/// has one.
+fn autocorrectable_typo(
+    word: &str,
+    rate: f64,
+    layout: &KeyboardLayout,
+    rng: &mut impl Rng,
+) -> Option<String> {
+    let lower = word.to_lowercase();
+    let typos: Vec<&str> = AUTOCORRECTIONS
+        .iter()
+        .filter(|(_typo, corrected)| *corrected == lower)
+        .map(|(typo, _corrected)| *typo)
+        .collect();
+    if typos.is_empty() || !rng.gen_bool(rate) {
+        return None;
+    }
+    let typo = apply_case_style(word, typos[rng.gen_range(0..typos.len())]);
+    (autocorrection(&typo).as_deref() == Some(word) && layout.is_supported_text(&typo))
+        .then_some(typo)
+}
+
 fn word_variant(word: &str, rng: &mut impl Rng) -> Option<String> {
     let word_lower = word.to_ascii_lowercase();
 
@@ -342,14 +1121,19 @@ fn word_variant(word: &str, rng: &mut impl Rng) -> Option<String> {
     None
 }
 
-fn word_typo(word: &str, rng: &mut impl Rng) -> Option<String> {
+fn word_typo(
+    word: &str,
+    layout: &KeyboardLayout,
+    mix: &ErrorMix,
+    rng: &mut impl Rng,
+) -> Option<String> {
     let chars: Vec<char> = word.chars().collect();
     if chars.len() < 2 {
         return None;
     }
 
-    // Occasionally swap adjacent letters.
-    if chars.len() >= 4 && rng.gen_bool(0.25) {
+    // Occasionally swap adjacent letters (always, without substitutions).
+    if mix.transposition && chars.len() >= 4 && (!mix.adjacent_substitution || rng.gen_bool(0.25)) {
         let mut out = chars.clone();
         let idx = rng.gen_range(0..out.len() - 1);
         out.swap(idx, idx + 1);
@@ -359,11 +1143,16 @@ fn word_typo(word: &str, rng: &mut impl Rng) -> Option<String> {
         }
     }
 
-    // Single-character substitution with a nearby key.
+    if !mix.adjacent_substitution {
+        return None;
+    }
+
+    // Single-character substitution with a physically adjacent key.
     let idx = rng.gen_range(0..chars.len());
     let mut out = chars.clone();
-    if let Some(adj) = qwerty_adjacent_char(out[idx], rng) {
-        out[idx] = adj;
+    let adjacent = layout.adjacent_chars(out[idx]);
+    if !adjacent.is_empty() {
+        out[idx] = adjacent[rng.gen_range(0..adjacent.len())];
         let out: String = out.into_iter().collect();
         if out != word {
             return Some(out);
@@ -373,13 +1162,54 @@ fn word_typo(word: &str, rng: &mut impl Rng) -> Option<String> {
     None
 }
 
-fn inter_char_delay_ms(wpm: f64, rng: &mut impl Rng) -> u64 {
+/// A punctuation mark on a key physically adjacent to the one that types `c` (e.g. `.` for
+/// `,`, `'` for `;` on US QWERTY), if there is one.
+fn punctuation_typo(c: char, layout: &KeyboardLayout, rng: &mut impl Rng) -> Option<char> {
+    let adjacent: Vec<char> = layout
+        .adjacent_chars(c)
+        .into_iter()
+        .filter(char::is_ascii_punctuation)
+        .collect();
+    (!adjacent.is_empty()).then(|| adjacent[rng.gen_range(0..adjacent.len())])
+}
+
+/// Whether the char at `i` is a single period ending a paragraph (or the text) after a word.
+fn is_paragraph_final_period(chars: &[char], i: usize) -> bool {
+    chars[i] == '.'
+        && i > 0
+        && is_word_char(chars[i - 1])
+        && chars.get(i + 1).is_none_or(|&next| next == '\n')
+}
+
+/// A typo or variant of `word` from the built-in generators.
+fn mechanical_wrong_word(
+    word: &str,
+    cfg: &PlannerConfig,
+    layout: &KeyboardLayout,
+    rng: &mut impl Rng,
+) -> Option<String> {
+    let mix = &cfg.error_mix;
+    // A different word is a bug in code, not a typo.
+    if !mix.word_variants || cfg.mode == TypingMode::Code {
+        return word_typo(word, layout, mix, rng);
+    }
+    let want_variant = rng.gen_bool(cfg.word_variant_share);
+    if want_variant {
+        word_variant(word, rng).or_else(|| word_typo(word, layout, mix, rng))
+    } else {
+        word_typo(word, layout, mix, rng).or_else(|| word_variant(word, rng))
+    }
+}
+
+fn inter_char_delay_ms(wpm: f64, rhythm: &KeyRhythm, rng: &mut impl Rng) -> u64 {
     // Approximate 5 chars per word.
     let mean = 12000.0 / wpm;
-    let stddev = mean * 0.35;
+    let stddev = mean * rhythm.delay_spread;
     let dist = Normal::new(mean, stddev.max(1.0)).unwrap();
     let sample = dist.sample(rng);
-    sample.clamp(25.0, 900.0).round() as u64
+    sample
+        .clamp(rhythm.delay_min_ms as f64, rhythm.delay_max_ms as f64)
+        .round() as u64
 }
 
 fn punctuation_pause_ms(c: char, rng: &mut impl Rng) -> u64 {
@@ -393,21 +1223,187 @@ fn punctuation_pause_ms(c: char, rng: &mut impl Rng) -> u64 {
 
 fn maybe_think_pause_ms(prev: char, rng: &mut impl Rng) -> u64 {
     match prev {
-        '.' | '!' | '?' => {
-            if rng.gen_bool(0.12) {
-                rng.gen_range(700..=2400)
-            } else {
-                0
-            }
+        '.' | '!' | '?' if rng.gen_bool(0.12) => rng.gen_range(700..=2400),
+        '\n' if rng.gen_bool(0.10) => rng.gen_range(600..=2000),
+        _ => 0,
+    }
+}
+
+/// Pause after typing `c` in [`TypingMode::Code`]: symbols take a moment to reach, and a new
+/// line to plan, but a period is no sentence end.
+fn code_pause_ms(c: char, rng: &mut impl Rng) -> u64 {
+    match c {
+        '\n' => rng.gen_range(150..=700),
+        c if c.is_ascii_punctuation() => rng.gen_range(30..=150),
+        _ => 0,
+    }
+}
+
+fn maybe_code_think_pause_ms(prev: char, rng: &mut impl Rng) -> u64 {
+    match prev {
+        '\n' if rng.gen_bool(0.15) => rng.gen_range(800..=3000),
+        _ => 0,
+    }
+}
+
+/// Typing speed for careful tokens, as a share of the plan's WPM.
+pub(crate) const CAREFUL_SPEED: f64 = 0.6;
+
+/// Which of `chars` belong to a careful token, or none without [`PlannerConfig::careful_tokens`]
+/// or in code.
+fn careful_mask(chars: &[char], cfg: &PlannerConfig) -> Vec<bool> {
+    let mut mask = vec![false; chars.len()];
+    if cfg.careful_tokens && cfg.mode == TypingMode::Prose {
+        for token in careful_tokens(chars) {
+            mask[token.range].fill(true);
         }
-        '\n' => {
-            if rng.gen_bool(0.10) {
-                rng.gen_range(600..=2000)
-            } else {
-                0
+    }
+    mask
+}
+
+/// Which of `chars` are opening brackets the text never closes: an editor that closes brackets
+/// adds a closing bracket for them, which has to be deleted.
+///
+/// A closing bracket closes the innermost open bracket of its kind, leaving the ones opened
+/// after it unclosed (like the `(` of a `:(` in a comment inside a block). One with no open
+/// bracket of its kind is typed as usual.
+pub(crate) fn unclosed_brackets(chars: &[char]) -> Vec<bool> {
+    let mut unclosed = vec![false; chars.len()];
+    let mut open: Vec<usize> = Vec::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if closing_bracket(c).is_some() {
+            open.push(i);
+        } else if let Some(k) = open
+            .iter()
+            .rposition(|&opened| closing_bracket(chars[opened]) == Some(c))
+        {
+            for opened in open.drain(k..).skip(1) {
+                unclosed[opened] = true;
             }
         }
-        _ => 0,
+    }
+    for i in open {
+        unclosed[i] = true;
+    }
+    unclosed
+}
+
+/// After the opening bracket at `i` is typed, deletes the closing bracket the editor added if
+/// the text never closes it.
+fn delete_unclosed_bracket(
+    builder: &mut ActionBuilder,
+    editor: &mut EditorModel,
+    unclosed: &[bool],
+    i: usize,
+    rng: &mut impl Rng,
+) {
+    if builder.code_editor.auto_close_brackets && unclosed[i] {
+        builder.wait(rng.gen_range(80..=300));
+        builder.delete(rng);
+        editor.delete(false);
+        builder.wait(rng.gen_range(40..=160));
+    }
+}
+
+/// After Enter, with the new line starting at char `i`, matches the indentation the editor added
+/// to the text's: backspaces what the text does not have and returns how many of the text's
+/// indentation chars are already typed.
+fn match_auto_indent(
+    builder: &mut ActionBuilder,
+    editor: &mut EditorModel,
+    chars: &[char],
+    i: usize,
+    rng: &mut impl Rng,
+) -> usize {
+    if !builder.code_editor.auto_indent {
+        return 0;
+    }
+    let text = editor.text();
+    let added = text[..editor.cursor()]
+        .iter()
+        .rev()
+        .take_while(|&&c| c == ' ' || c == '\t')
+        .count();
+    let kept = text[editor.cursor() - added..editor.cursor()]
+        .iter()
+        .zip(&chars[i..])
+        .take_while(|(added, wanted)| added == wanted)
+        .count();
+    if added > kept {
+        builder.wait(rng.gen_range(60..=260));
+        builder.backspace_run(added - kept, rng);
+        for _ in kept..added {
+            editor.backspace(false);
+        }
+    }
+    kept
+}
+
+/// Minimum number of capital letters in an ALL-CAPS run before CapsLock is considered.
+const CAPS_LOCK_MIN_LETTERS: usize = 6;
+
+/// End (exclusive char index) of the ALL-CAPS run starting with the word at `start`, if it has
+/// enough capital letters to be typed with CapsLock.
+///
+/// A run spans whole words and the punctuation between them, stays on one line, and ends after
+/// its last capital letter.
+fn caps_lock_run_end(chars: &[char], start: usize) -> Option<usize> {
+    if start > 0 && is_word_char(chars[start - 1]) {
+        return None;
+    }
+    if !chars.get(start)?.is_uppercase() {
+        return None;
+    }
+
+    let mut stop = start;
+    while stop < chars.len() {
+        let c = chars[stop];
+        if c == '\n' || (c.is_alphabetic() && !c.is_uppercase()) {
+            break;
+        }
+        stop += 1;
+    }
+    // Do not end the run in the middle of a word (e.g. `HELLO World`).
+    if stop < chars.len() && is_word_char(chars[stop]) {
+        while stop > start && is_word_char(chars[stop - 1]) {
+            stop -= 1;
+        }
+    }
+
+    let run = &chars[start..stop];
+    let last_upper = run.iter().rposition(|c| c.is_uppercase())?;
+    let letters = run.iter().filter(|c| c.is_uppercase()).count();
+    (letters >= CAPS_LOCK_MIN_LETTERS).then_some(start + last_upper + 1)
+}
+
+/// Turn CapsLock off once the current ALL-CAPS run is typed, and maybe turn it on for a run
+/// starting at `i`.
+fn update_caps_lock(
+    builder: &mut ActionBuilder,
+    caps_run_end: &mut Option<usize>,
+    chars: &[char],
+    i: usize,
+    caps_lock_rate: f64,
+    rng: &mut impl Rng,
+) {
+    // Characters CapsLock cannot type turn it off early.
+    if !builder.caps_lock {
+        *caps_run_end = None;
+    }
+    if caps_run_end.is_some_and(|end| i >= end) {
+        builder.set_caps_lock(false, rng);
+        *caps_run_end = None;
+    }
+    if caps_run_end.is_some() || caps_lock_rate == 0.0 {
+        return;
+    }
+
+    let Some(end) = caps_lock_run_end(chars, i) else {
+        return;
+    };
+    if builder.can_type_with_caps_lock(&chars[i..end]) && rng.gen_bool(caps_lock_rate) {
+        builder.set_caps_lock(true, rng);
+        *caps_run_end = builder.caps_lock.then_some(end);
     }
 }
 
@@ -418,14 +1414,123 @@ fn byte_index_to_line_col(text: &str, byte_idx: usize) -> (usize, usize) {
         if i >= byte_idx {
             break;
         }
-        if c == '\n' {
-            line += 1;
-            col = 1;
-        } else {
-            col += 1;
+        if c == '\n' {
+            line += 1;
+            col = 1;
+        } else {
+            col += 1;
+        }
+    }
+    (line, col)
+}
+
+fn planner_layout(cfg: &PlannerConfig, text: &str) -> Result<KeyboardLayout> {
+    let mut layout = if cfg.custom_keymap || cfg.smart_quotes == SmartQuotes::Literal {
+        // Characters with charmap keystrokes or ASCII spellings do not need spare keys. Without
+        // a custom keymap, only literal smart quotes get them.
+        let uncovered: String = text
+            .chars()
+            .filter(|c| cfg.custom_keymap || is_smart_quote(*c))
+            .filter(|c| !cfg.charmap.key_overrides().any(|(mapped, _)| mapped == *c))
+            .filter(|c| cfg.substitutions.ascii_spelling(*c).is_none())
+            .collect();
+        KeyboardLayout::from_spec_covering(&cfg.layout, &uncovered, cfg.smart_quotes)?
+    } else {
+        KeyboardLayout::from_spec(&cfg.layout)?
+    };
+
+    for (c, strokes) in cfg.charmap.key_overrides() {
+        layout.override_strokes(c, strokes)?;
+    }
+
+    for (c, ascii) in cfg.substitutions.ascii_spellings() {
+        if cfg.charmap.contains(c) {
+            continue;
+        }
+        let mut strokes = Vec::new();
+        for typed in ascii.chars() {
+            let typed_strokes = layout.strokes_for_output_char(typed).ok_or_else(|| {
+                anyhow!(
+                    "cannot type {c:?} as {ascii:?}: layout {} has no {typed:?}",
+                    layout.name()
+                )
+            })?;
+            strokes.extend_from_slice(typed_strokes);
+        }
+        layout.override_strokes(c, &strokes)?;
+    }
+    Ok(layout)
+}
+
+fn is_smart_quote(c: char) -> bool {
+    matches!(c, '’' | '‘' | '”' | '“')
+}
+
+/// [`prepare_text`] for the whole draft, after checking its invisible whitespace against the
+/// whitespace policies and stripping what they strip.
+fn prepare_document(text: &str, cfg: &PlannerConfig) -> Result<String, DrafterError> {
+    if cfg.trailing_whitespace == WhitespacePolicy::Reject {
+        if let Some(&(line, column)) = find_trailing_whitespace(text).first() {
+            return Err(DrafterError::TrailingWhitespace { line, column });
+        }
+    }
+    if cfg.final_newline == WhitespacePolicy::Reject && text.ends_with('\n') {
+        return Err(DrafterError::FinalNewline);
+    }
+    Ok(prepare_text(&strip_whitespace(text, cfg), cfg))
+}
+
+/// `text` without the invisible whitespace that `cfg`'s whitespace policies strip.
+pub(crate) fn strip_whitespace(text: &str, cfg: &PlannerConfig) -> String {
+    let mut text = if cfg.trailing_whitespace == WhitespacePolicy::Strip {
+        strip_trailing_whitespace(text)
+    } else {
+        text.to_string()
+    };
+    if cfg.final_newline == WhitespacePolicy::Strip {
+        text.truncate(text.trim_end_matches('\n').len());
+    }
+    text
+}
+
+/// Apply charmap replacements, then the tab policy and the paragraph breaks.
+fn prepare_text(text: &str, cfg: &PlannerConfig) -> String {
+    let text = apply_tab_policy(&cfg.charmap.apply(text), cfg.tab_policy);
+    cfg.paragraph_breaks.apply(&text)
+}
+
+/// Expand tabs for [`TabPolicy::Spaces`]; other policies leave the text unchanged.
+fn apply_tab_policy(text: &str, policy: TabPolicy) -> String {
+    match policy {
+        TabPolicy::Spaces(width) => text.replace('\t', &" ".repeat(width)),
+        TabPolicy::Reject | TabPolicy::Literal => text.to_string(),
+    }
+}
+
+fn ensure_layout_supports_text(
+    final_text: &str,
+    layout: &KeyboardLayout,
+    tab_policy: TabPolicy,
+) -> Result<()> {
+    let literal_tabs = tab_policy == TabPolicy::Literal;
+    let unsupported = final_text
+        .char_indices()
+        .find(|&(_idx, c)| !(layout.is_supported_char(c) || (literal_tabs && c == '\t')));
+
+    if let Some((byte_idx, c)) = unsupported {
+        let (line, column) = byte_index_to_line_col(final_text, byte_idx);
+        if c == '\t' {
+            return Err(DrafterError::TabRejected { line, column }.into());
+        }
+        return Err(DrafterError::UnsupportedCharacter {
+            ch: c,
+            line,
+            column,
+            layout: layout.name().to_string(),
         }
+        .into());
     }
-    (line, col)
+    Ok(())
 }
 
 fn sentence_or_paragraph_boundary(c: char) -> bool {
@@ -438,9 +1543,37 @@ struct PhraseSpan {
     original: String,
     alternative: String,
     original_len_chars: usize,
+    revision: SpanRevision,
 }
 
-fn paragraph_byte_spans(text: &str) -> Vec<(usize, usize)> {
+/// When a typed phrase span is edited back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpanRevision {
+    /// After a random delay, at a sentence or paragraph boundary.
+    Delayed,
+    /// Right after the span is typed.
+    Immediately,
+    /// In an editing pass once the paragraph ending at this char index is typed.
+    ParagraphPass(usize),
+    /// In the review pass at the end.
+    FinalReview,
+}
+
+impl SpanRevision {
+    fn from_hint(hint: Option<RevisionTiming>, paragraph_end: usize) -> Self {
+        match hint {
+            None => SpanRevision::Delayed,
+            Some(RevisionTiming::Immediately) => SpanRevision::Immediately,
+            Some(RevisionTiming::EndOfParagraph) => SpanRevision::ParagraphPass(paragraph_end),
+            Some(RevisionTiming::FinalReview) => SpanRevision::FinalReview,
+        }
+    }
+}
+
+/// Byte ranges of the paragraphs of `text`: separated by blank lines, or by any newline with
+/// [`ParagraphBreaks::Single`].
+fn paragraph_byte_spans(text: &str, breaks: ParagraphBreaks) -> Vec<(usize, usize)> {
+    let single = breaks == ParagraphBreaks::Single;
     let bytes = text.as_bytes();
     let len = bytes.len();
     let mut spans = Vec::new();
@@ -456,7 +1589,7 @@ fn paragraph_byte_spans(text: &str) -> Vec<(usize, usize)> {
 
         let start = idx;
         while idx < len {
-            if bytes[idx] == b'\n' && idx + 1 < len && bytes[idx + 1] == b'\n' {
+            if bytes[idx] == b'\n' && (single || (idx + 1 < len && bytes[idx + 1] == b'\n')) {
                 break;
             }
             idx += 1;
@@ -472,15 +1605,124 @@ fn paragraph_byte_spans(text: &str) -> Vec<(usize, usize)> {
     spans
 }
 
+/// The non-empty paragraphs of `text` (separated by blank lines), in the order
+/// [`generate_plan_with_phrase_alternatives`] expects their alternatives.
+pub fn split_paragraphs(text: &str) -> Vec<String> {
+    split_paragraphs_with(text, ParagraphBreaks::AsWritten)
+}
+
+/// Like [`split_paragraphs`], for a plan typing paragraph breaks as `breaks` (see
+/// [`PlannerConfig::paragraph_breaks`]).
+pub fn split_paragraphs_with(text: &str, breaks: ParagraphBreaks) -> Vec<String> {
+    paragraph_byte_spans(text, breaks)
+        .into_iter()
+        .map(|(start, end)| text[start..end].to_string())
+        .collect()
+}
+
 fn byte_index_to_char_index(text: &str, byte_idx: usize) -> usize {
     text[..byte_idx].chars().count()
 }
 
+/// Emits [`Action::Checkpoint`]s where the editor holds a prefix of the text and the keyboard
+/// is neutral.
+#[derive(Debug, Default)]
+struct Checkpoints {
+    /// Chars of the text at the last checkpoint.
+    last: usize,
+}
+
+impl Checkpoints {
+    /// Marks a checkpoint after the first `i` chars of `chars`, if the editor holds exactly them
+    /// with the cursor at the end and no modifier is held. Call it only with no edits pending:
+    /// text before the last checkpoint is assumed unchanged.
+    fn mark(
+        &mut self,
+        builder: &mut ActionBuilder,
+        editor: &EditorModel,
+        chars: &[char],
+        i: usize,
+    ) {
+        let text = editor.text();
+        let consistent = text.len() == i
+            && editor.at_end()
+            && !editor.has_selection()
+            && text.get(self.last..) == chars.get(self.last..i);
+        if i > self.last && consistent && builder.is_neutral() {
+            builder.actions.push(Action::Checkpoint { chars: i });
+            self.last = i;
+        }
+    }
+}
+
+/// Records which actions type each paragraph, as [`AnnotationKind::Paragraph`] annotations.
+struct ParagraphMarks {
+    /// Char ranges of the paragraphs.
+    paragraphs: Vec<(usize, usize)>,
+    next: usize,
+    open_at: Option<usize>,
+    annotations: Vec<Annotation>,
+}
+
+impl ParagraphMarks {
+    fn new(text: &str, breaks: ParagraphBreaks) -> Self {
+        let paragraphs = paragraph_byte_spans(text, breaks)
+            .into_iter()
+            .map(|(start, end)| {
+                (
+                    byte_index_to_char_index(text, start),
+                    byte_index_to_char_index(text, end),
+                )
+            })
+            .collect();
+        Self {
+            paragraphs,
+            next: 0,
+            open_at: None,
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Called before typing from char `i`, with the number of actions so far.
+    fn before(&mut self, i: usize, actions: usize) {
+        let starts = self
+            .paragraphs
+            .get(self.next)
+            .is_some_and(|&(start, _)| start <= i);
+        if starts && self.open_at.is_none() {
+            self.open_at = Some(actions);
+        }
+    }
+
+    /// Called once the text up to char `i` is typed (and any edits due then are made), with
+    /// the number of actions so far.
+    fn after(&mut self, i: usize, actions: usize) {
+        let Some(&(source_start, source_end)) = self.paragraphs.get(self.next) else {
+            return;
+        };
+        if i < source_end {
+            return;
+        }
+        if let Some(action_start) = self.open_at.take() {
+            self.annotations.push(Annotation {
+                kind: AnnotationKind::Paragraph,
+                action_start,
+                action_end: actions,
+                source_start,
+                source_end,
+            });
+        }
+        self.next += 1;
+    }
+}
+
 fn phrase_spans_from_paragraph_alternatives(
     final_text: &str,
+    breaks: ParagraphBreaks,
     alternatives_by_paragraph: &[Vec<PhraseAlternative>],
+    layout: &KeyboardLayout,
 ) -> Result<Vec<PhraseSpan>> {
-    let paragraph_spans = paragraph_byte_spans(final_text);
+    let paragraph_spans = paragraph_byte_spans(final_text, breaks);
     if alternatives_by_paragraph.len() != paragraph_spans.len() {
         return Err(anyhow!(
             "expected {} paragraph alternative lists, got {}",
@@ -495,11 +1737,12 @@ fn phrase_spans_from_paragraph_alternatives(
     for (idx, (start_byte, end_byte)) in paragraph_spans.iter().copied().enumerate() {
         let paragraph = &final_text[start_byte..end_byte];
         let items = &alternatives_by_paragraph[idx];
-        if let Err(err) = validate_phrase_alternatives(paragraph, items) {
+        if let Err(err) = validate_phrase_alternatives_for_layout(paragraph, items, layout) {
             return Err(anyhow!(
                 "phrase alternatives failed validation for paragraph {idx}: {err}"
             ));
         }
+        let paragraph_end = byte_index_to_char_index(final_text, end_byte);
 
         for item in items {
             let local_start_byte = paragraph
@@ -518,6 +1761,7 @@ fn phrase_spans_from_paragraph_alternatives(
                 original: item.original.clone(),
                 alternative: item.alternative.clone(),
                 original_len_chars,
+                revision: SpanRevision::from_hint(item.revise, paragraph_end),
             });
         }
     }
@@ -534,205 +1778,396 @@ fn phrase_spans_from_paragraph_alternatives(
     Ok(spans)
 }
 
+/// The word edits that turn each paragraph's first draft into the paragraph.
+fn phrase_spans_from_paragraph_drafts(
+    final_text: &str,
+    breaks: ParagraphBreaks,
+    drafts_by_paragraph: &[String],
+    layout: &KeyboardLayout,
+) -> Result<Vec<PhraseSpan>> {
+    let paragraph_spans = paragraph_byte_spans(final_text, breaks);
+    if drafts_by_paragraph.len() != paragraph_spans.len() {
+        return Err(anyhow!(
+            "expected {} paragraph drafts, got {}",
+            paragraph_spans.len(),
+            drafts_by_paragraph.len()
+        ));
+    }
+
+    let mut spans: Vec<PhraseSpan> = Vec::new();
+    for (idx, (start_byte, end_byte)) in paragraph_spans.iter().copied().enumerate() {
+        let paragraph = &final_text[start_byte..end_byte];
+        let draft = &drafts_by_paragraph[idx];
+        if draft == paragraph {
+            continue;
+        }
+        if let Err(err) = validate_paragraph_draft_for_layout(paragraph, draft, layout) {
+            return Err(anyhow!(
+                "first draft failed validation for paragraph {idx}: {err}"
+            ));
+        }
+
+        let paragraph_start = byte_index_to_char_index(final_text, start_byte);
+        let paragraph_end = paragraph_start + paragraph.chars().count();
+        for edit in word_edits(draft, paragraph) {
+            spans.push(PhraseSpan {
+                start: paragraph_start + edit.start,
+                original_len_chars: edit.target.chars().count(),
+                original: edit.target,
+                alternative: edit.draft,
+                revision: SpanRevision::ParagraphPass(paragraph_end),
+            });
+        }
+    }
+
+    Ok(spans)
+}
+
+/// An LLM-proposed single-word mistake, keyed by the char index of `original` in the final text.
+#[derive(Debug, Clone)]
+struct WordMistake {
+    original: String,
+    mistake: String,
+}
+
+/// Sort LLM-proposed human mistakes into single-word mistakes (for the per-word error roll) and
+/// informal rephrasings, which join `phrase_spans` with probability `llm_mistake_rate` unless
+/// they overlap an alternative.
+fn word_mistakes_from_paragraph_mistakes(
+    final_text: &str,
+    breaks: ParagraphBreaks,
+    mistakes_by_paragraph: &[Vec<HumanMistake>],
+    layout: &KeyboardLayout,
+    phrase_spans: &mut Vec<PhraseSpan>,
+    llm_mistake_rate: f64,
+    rng: &mut impl Rng,
+) -> Result<HashMap<usize, WordMistake>> {
+    let paragraph_spans = paragraph_byte_spans(final_text, breaks);
+    if mistakes_by_paragraph.len() != paragraph_spans.len() {
+        return Err(anyhow!(
+            "expected {} paragraph mistake lists, got {}",
+            paragraph_spans.len(),
+            mistakes_by_paragraph.len()
+        ));
+    }
+
+    let mut word_mistakes = HashMap::new();
+    let mut informal_spans: Vec<PhraseSpan> = Vec::new();
+
+    for (idx, (start_byte, end_byte)) in paragraph_spans.iter().copied().enumerate() {
+        let paragraph = &final_text[start_byte..end_byte];
+        let items = &mistakes_by_paragraph[idx];
+        if let Err(err) = validate_human_mistakes_for_layout(paragraph, items, layout) {
+            return Err(anyhow!(
+                "human mistakes failed validation for paragraph {idx}: {err}"
+            ));
+        }
+
+        for item in items {
+            let local_start_byte = paragraph
+                .find(&item.original)
+                .ok_or_else(|| anyhow!("original not found in paragraph {idx}"))?;
+            let start = byte_index_to_char_index(final_text, start_byte + local_start_byte);
+            let original_len_chars = item.original.chars().count();
+
+            if item.kind.is_single_word() {
+                word_mistakes.insert(
+                    start,
+                    WordMistake {
+                        original: item.original.clone(),
+                        mistake: item.mistake.clone(),
+                    },
+                );
+                continue;
+            }
+
+            let end = start + original_len_chars;
+            let overlaps = phrase_spans
+                .iter()
+                .any(|span| start < span.start + span.original_len_chars && span.start < end);
+            if !overlaps && rng.gen_bool(llm_mistake_rate) {
+                informal_spans.push(PhraseSpan {
+                    start,
+                    original: item.original.clone(),
+                    alternative: item.mistake.clone(),
+                    original_len_chars,
+                    revision: SpanRevision::Delayed,
+                });
+            }
+        }
+    }
+
+    phrase_spans.extend(informal_spans);
+    phrase_spans.sort_by_key(|span| span.start);
+    Ok(word_mistakes)
+}
+
 fn type_string(
     builder: &mut ActionBuilder,
-    editor: &mut EditorState,
+    editor: &mut EditorModel,
     s: &str,
     wpm: f64,
     rng: &mut impl Rng,
 ) -> Result<()> {
     for c in s.chars() {
-        let stroke = keystroke_for_output_char(c).ok_or_else(|| {
-            anyhow!(
-                "unsupported character for US-QWERTY typing: {c:?} (U+{:04X})",
-                c as u32
-            )
-        })?;
-        builder.type_char(stroke, rng);
-        editor.insert_char(c);
-
-        let mut delay = inter_char_delay_ms(wpm, rng);
-        delay += punctuation_pause_ms(c, rng);
-        delay += maybe_think_pause_ms(c, rng);
+        builder.type_output_char(c, rng)?;
+        editor.type_char(c, builder.code_editor);
+
+        let mut delay = inter_char_delay_ms(wpm, &builder.rhythm, rng);
+        match builder.mode {
+            TypingMode::Prose => {
+                delay += punctuation_pause_ms(c, rng);
+                delay += maybe_think_pause_ms(c, rng);
+            }
+            TypingMode::Code => {
+                delay += code_pause_ms(c, rng);
+                delay += maybe_code_think_pause_ms(c, rng);
+            }
+        }
         builder.wait(delay);
     }
     Ok(())
 }
 
+/// Types a careful token: slower, and without the pauses its punctuation would get in a
+/// sentence.
+fn type_careful(
+    builder: &mut ActionBuilder,
+    editor: &mut EditorModel,
+    s: &str,
+    wpm: f64,
+    rng: &mut impl Rng,
+) -> Result<()> {
+    for c in s.chars() {
+        builder.type_output_char(c, rng)?;
+        editor.type_char(c, builder.code_editor);
+        builder.wait(inter_char_delay_ms(
+            wpm * CAREFUL_SPEED,
+            &builder.rhythm,
+            rng,
+        ));
+    }
+    Ok(())
+}
+
 fn replace_at_end(
     builder: &mut ActionBuilder,
-    editor: &mut EditorState,
+    editor: &mut EditorModel,
     wrong: &str,
     correct: &str,
     wpm: f64,
     rng: &mut impl Rng,
 ) -> Result<()> {
-    // Cursor must be at end of wrong.
-    debug_assert!(editor.cursor == editor.buf.len());
+    // Cursor must be at end of wrong (and of the text, but for brackets the editor closed).
+    debug_assert!(editor.text()[..editor.cursor()].ends_with(&wrong.chars().collect::<Vec<_>>()));
 
     builder.wait(rng.gen_range(60..=260));
 
     let wrong_len = wrong.chars().count();
+    builder.backspace_run(wrong_len, rng);
     for _ in 0..wrong_len {
-        builder.backspace(rng);
-        editor.backspace();
-        builder.wait(rng.gen_range(15..=55));
+        editor.backspace(false);
     }
 
     type_string(builder, editor, correct, wpm, rng)
 }
 
+/// At the start of a paragraph typed at the end of the text, steps away for a session break with
+/// probability `cfg.session_break_rate`, then re-reads if it lasted `cfg.reread_after_ms` or
+/// longer.
+fn maybe_session_break(
+    builder: &mut ActionBuilder,
+    editor: &mut EditorModel,
+    chars: &[char],
+    i: usize,
+    cfg: &PlannerConfig,
+    rng: &mut impl Rng,
+) {
+    let paragraph_start = i < chars.len()
+        && chars[i] != '\n'
+        && i.checked_sub(1).is_some_and(|prev| chars[prev] == '\n')
+        && (cfg.paragraph_breaks == ParagraphBreaks::Single
+            || i.checked_sub(2).is_some_and(|prev| chars[prev] == '\n'));
+    // Checked before drawing, so plans without breaks stay identical per seed.
+    if cfg.session_break_rate == 0.0
+        || !paragraph_start
+        || editor.cursor() != editor.text().len()
+        || !rng.gen_bool(cfg.session_break_rate)
+    {
+        return;
+    }
+    let break_ms = rng.gen_range(cfg.session_break_ms_min..=cfg.session_break_ms_max);
+    builder.wait(break_ms);
+    if cfg.reread_after_ms > 0 && break_ms >= cfg.reread_after_ms {
+        reread(builder, editor, rng);
+    }
+}
+
+/// Reorients after a long interruption: a pause, a few lines back with Up and to their start
+/// with Home to read them over, then back down to the end of the text. The cursor must be at
+/// the start of an empty last line, where Down returns however the lines wrap.
+fn reread(builder: &mut ActionBuilder, editor: &mut EditorModel, rng: &mut impl Rng) {
+    debug_assert!(editor.cursor() == editor.text().len());
+    builder.wait(rng.gen_range(1500..=4000));
+
+    let lines_above = editor.text().iter().filter(|&&c| c == '\n').count();
+    let ups = rng.gen_range(2..=4).min(lines_above);
+    for _ in 0..ups {
+        builder.nav_vertical(KEY_UP, rng);
+        editor.move_up(false);
+        builder.wait(rng.gen_range(250..=900));
+    }
+    builder.nav_line_edge(KEY_HOME, rng);
+    editor.home(false, false);
+    builder.wait(rng.gen_range(1500..=5000));
+
+    for _ in 0..ups {
+        builder.nav_vertical(KEY_DOWN, rng);
+        editor.move_down(false);
+        builder.wait(rng.gen_range(60..=200));
+    }
+    debug_assert!(editor.cursor() == editor.text().len());
+    builder.set_ctrl(false, rng);
+    builder.wait(rng.gen_range(300..=900));
+}
+
 fn navigate_left_to(
     builder: &mut ActionBuilder,
-    editor: &mut EditorState,
+    editor: &mut EditorModel,
     target: usize,
-    profile: WordNavProfile,
+    profile: &WordNavProfile,
     rng: &mut impl Rng,
 ) {
-    let target = target.min(editor.buf.len());
-
-    match profile {
-        WordNavProfile::Chrome => {
-            while editor.cursor > target {
-                let ctrl_target =
-                    crate::word_nav::ctrl_left(&editor.buf, editor.cursor, is_word_char);
-                let ctrl_delta = editor.cursor.saturating_sub(ctrl_target);
-                let remaining = editor.cursor - target;
-                let crosses_newline = editor.buf[ctrl_target..editor.cursor]
-                    .iter()
-                    .any(|c| *c == '\n');
-
-                if ctrl_target >= target && ctrl_delta >= 4 && remaining >= 12 && !crosses_newline {
-                    builder.nav_word_left(rng);
-                    editor.move_word_left();
-                } else {
-                    builder.nav_left(rng);
-                    editor.move_left();
-                }
-
-                if rng.gen_bool(0.03) {
-                    builder.wait(rng.gen_range(40..=180));
-                } else {
-                    builder.wait(rng.gen_range(6..=22));
-                }
+    let target = target.min(editor.text().len());
+
+    while editor.cursor() > target {
+        if let Some(start) = profile.line_edge_jump(editor.text(), editor.cursor(), target) {
+            builder.nav_line_edge(KEY_HOME, rng);
+            editor.home(false, false);
+            builder.wait(rng.gen_range(40..=140));
+            if start < target {
+                navigate_right_to(builder, editor, target, profile, rng);
+                return;
             }
+            continue;
         }
-        WordNavProfile::Compatible => {
-            while editor.cursor > target {
-                let ctrl_target =
-                    crate::word_nav::ctrl_left(&editor.buf, editor.cursor, is_word_char);
-                let ctrl_delta = editor.cursor.saturating_sub(ctrl_target);
-                let remaining = editor.cursor - target;
-                let safe_jump =
-                    compatible_ctrl_jump_is_safe(&editor.buf, editor.cursor, ctrl_target);
-
-                if ctrl_target >= target && ctrl_delta >= 4 && remaining >= 12 && safe_jump {
-                    builder.nav_word_left(rng);
-                    editor.move_word_left();
-                } else {
-                    builder.nav_left(rng);
-                    editor.move_left();
-                }
 
-                if rng.gen_bool(0.03) {
-                    builder.wait(rng.gen_range(40..=180));
-                } else {
-                    builder.wait(rng.gen_range(6..=22));
-                }
-            }
+        let ctrl_target = crate::word_nav::ctrl_left(editor.text(), editor.cursor(), is_word_char);
+        let ctrl_delta = editor.cursor().saturating_sub(ctrl_target);
+        let remaining = editor.cursor() - target;
+        let allowed = profile.allows_ctrl_jump(editor.text(), editor.cursor(), ctrl_target);
+
+        if ctrl_target >= target
+            && ctrl_delta >= profile.min_ctrl_jump()
+            && remaining >= 12
+            && allowed
+        {
+            builder.nav_word_left(rng);
+            editor.move_word_left(false);
+        } else {
+            builder.nav_left(rng);
+            editor.move_left(false);
+        }
 
-            builder.set_ctrl(false, rng);
+        if rng.gen_bool(0.03) {
+            builder.wait(rng.gen_range(40..=180));
+        } else {
+            builder.wait(rng.gen_range(6..=22));
         }
     }
+
+    // Chrome plans have always released Ctrl with the next keystroke; keep them identical per seed.
+    if *profile != WordNavProfile::Chrome {
+        builder.set_ctrl(false, rng);
+    }
 }
 
 fn navigate_right_to(
     builder: &mut ActionBuilder,
-    editor: &mut EditorState,
+    editor: &mut EditorModel,
     target: usize,
-    profile: WordNavProfile,
+    profile: &WordNavProfile,
     rng: &mut impl Rng,
 ) {
-    let target = target.min(editor.buf.len());
-
-    match profile {
-        WordNavProfile::Chrome => {
-            while editor.cursor < target {
-                let ctrl_target =
-                    crate::word_nav::ctrl_right(&editor.buf, editor.cursor, is_word_char);
-                let ctrl_delta = ctrl_target.saturating_sub(editor.cursor);
-                let remaining = target - editor.cursor;
-                let crosses_newline = editor.buf[editor.cursor..ctrl_target]
-                    .iter()
-                    .any(|c| *c == '\n');
-
-                if ctrl_target <= target && ctrl_delta >= 4 && remaining >= 12 && !crosses_newline {
-                    builder.nav_word_right(rng);
-                    editor.move_word_right();
-                } else {
-                    builder.nav_right(rng);
-                    editor.move_right();
-                }
-
-                builder.wait(rng.gen_range(6..=22));
+    let target = target.min(editor.text().len());
+
+    while editor.cursor() < target {
+        if let Some(end) = profile.line_edge_jump(editor.text(), editor.cursor(), target) {
+            builder.nav_line_edge(KEY_END, rng);
+            editor.end(false, false);
+            builder.wait(rng.gen_range(40..=140));
+            if end > target {
+                navigate_left_to(builder, editor, target, profile, rng);
+                return;
             }
+            continue;
+        }
 
-            builder.set_ctrl(false, rng);
+        let ctrl_target = crate::word_nav::ctrl_right(editor.text(), editor.cursor(), is_word_char);
+        let ctrl_delta = ctrl_target.saturating_sub(editor.cursor());
+        let remaining = target - editor.cursor();
+        let allowed = profile.allows_ctrl_jump(editor.text(), editor.cursor(), ctrl_target);
+
+        if ctrl_target <= target
+            && ctrl_delta >= profile.min_ctrl_jump()
+            && remaining >= 12
+            && allowed
+        {
+            builder.nav_word_right(rng);
+            editor.move_word_right(false);
+        } else {
+            builder.nav_right(rng);
+            editor.move_right(false);
         }
-        WordNavProfile::Compatible => {
-            while editor.cursor < target {
-                let ctrl_target =
-                    crate::word_nav::ctrl_right(&editor.buf, editor.cursor, is_word_char);
-                let ctrl_delta = ctrl_target.saturating_sub(editor.cursor);
-                let remaining = target - editor.cursor;
 
-                let safe_jump =
-                    compatible_ctrl_jump_is_safe(&editor.buf, editor.cursor, ctrl_target);
+        builder.wait(rng.gen_range(6..=22));
+    }
 
-                if ctrl_target <= target && ctrl_delta >= 4 && remaining >= 12 && safe_jump {
-                    builder.nav_word_right(rng);
-                    editor.move_word_right();
-                } else {
-                    builder.nav_right(rng);
-                    editor.move_right();
-                }
+    builder.set_ctrl(false, rng);
+}
 
-                builder.wait(rng.gen_range(6..=22));
-            }
+/// How many chars fixing `err` adds to the text (negative if it removes some).
+fn length_delta(err: &OutstandingError) -> isize {
+    err.correct.chars().count() as isize - err.wrong.chars().count() as isize
+}
 
-            builder.set_ctrl(false, rng);
-        }
+/// Moves pending `edits` that start after `pos` by `delta` chars, after the text at `pos` was
+/// edited.
+fn shift_edits_after(edits: &mut [OutstandingError], pos: usize, delta: isize) {
+    for edit in edits.iter_mut().filter(|edit| edit.start > pos) {
+        edit.start = edit.start.saturating_add_signed(delta);
     }
 }
 
 fn fix_error_at_position(
     builder: &mut ActionBuilder,
-    editor: &mut EditorState,
+    editor: &mut EditorModel,
     err: OutstandingError,
     wpm: f64,
-    profile: WordNavProfile,
+    profile: &WordNavProfile,
     rng: &mut impl Rng,
 ) -> Result<()> {
     let wrong_len = err.wrong.chars().count();
     let target_end = err.start + wrong_len;
-    if target_end > editor.cursor {
+    if target_end > editor.cursor() {
         return Err(anyhow!("internal error: correction target after cursor"));
     }
+    // Closing brackets the editor added stay after the cursor.
+    let after = editor.text().len() - editor.cursor();
 
     navigate_left_to(builder, editor, target_end, profile, rng);
 
     builder.wait(rng.gen_range(50..=220));
 
+    builder.backspace_run(wrong_len, rng);
     for _ in 0..wrong_len {
-        builder.backspace(rng);
-        editor.backspace();
-        builder.wait(rng.gen_range(15..=55));
+        editor.backspace(false);
     }
 
     type_string(builder, editor, &err.correct, wpm, rng)?;
 
     // Return to end.
-    navigate_right_to(builder, editor, editor.buf.len(), profile, rng);
+    navigate_right_to(builder, editor, editor.text().len() - after, profile, rng);
 
     Ok(())
 }
@@ -742,132 +2177,401 @@ pub fn generate_plan_with_phrase_alternatives(
     cfg: PlannerConfig,
     alternatives_by_paragraph: &[Vec<PhraseAlternative>],
     rng: &mut impl Rng,
-) -> Result<Plan> {
-    if let Some((byte_idx, c)) = find_first_unsupported_char(final_text) {
-        let (line, col) = byte_index_to_line_col(final_text, byte_idx);
-        return Err(anyhow!(
-            "unsupported character {c:?} (U+{:04X}) at line {line}, column {col}. Supported: ASCII, newline, and smart quotes (’ ‘ ” “). Tabs are not allowed.",
-            c as u32
-        ));
+) -> Result<Plan, DrafterError> {
+    let no_mistakes = vec![Vec::new(); alternatives_by_paragraph.len()];
+    generate_plan_with_human_mistakes(
+        final_text,
+        cfg,
+        alternatives_by_paragraph,
+        &no_mistakes,
+        rng,
+    )
+}
+
+/// Like [`generate_plan_with_phrase_alternatives`], also typing LLM-proposed human mistakes
+/// (one list per paragraph) in place of the mechanical typos where available.
+pub fn generate_plan_with_human_mistakes(
+    final_text: &str,
+    cfg: PlannerConfig,
+    alternatives_by_paragraph: &[Vec<PhraseAlternative>],
+    mistakes_by_paragraph: &[Vec<HumanMistake>],
+    rng: &mut impl Rng,
+) -> Result<Plan, DrafterError> {
+    // Validated up front: informal mistakes are drawn before planning starts.
+    validate_config(&cfg)?;
+    let expanded_text = prepare_document(final_text, &cfg)?;
+    let final_text = expanded_text.as_str();
+    let alternatives_by_paragraph: Vec<Vec<PhraseAlternative>> = alternatives_by_paragraph
+        .iter()
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| PhraseAlternative {
+                    original: prepare_text(&item.original, &cfg),
+                    alternative: prepare_text(&item.alternative, &cfg),
+                    revise: item.revise,
+                })
+                .collect()
+        })
+        .collect();
+    let mistakes_by_paragraph: Vec<Vec<HumanMistake>> = mistakes_by_paragraph
+        .iter()
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| HumanMistake {
+                    original: prepare_text(&item.original, &cfg),
+                    mistake: prepare_text(&item.mistake, &cfg),
+                    kind: item.kind,
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut covered_text = final_text.to_string();
+    for item in alternatives_by_paragraph.iter().flatten() {
+        covered_text.push_str(&item.alternative);
+    }
+    for item in mistakes_by_paragraph.iter().flatten() {
+        covered_text.push_str(&item.mistake);
+    }
+    let layout = planner_layout(&cfg, &covered_text)?;
+    ensure_layout_supports_text(final_text, &layout, cfg.tab_policy)?;
+
+    let mut phrase_spans = phrase_spans_from_paragraph_alternatives(
+        final_text,
+        cfg.paragraph_breaks,
+        &alternatives_by_paragraph,
+        &layout,
+    )?;
+    let word_mistakes = word_mistakes_from_paragraph_mistakes(
+        final_text,
+        cfg.paragraph_breaks,
+        &mistakes_by_paragraph,
+        &layout,
+        &mut phrase_spans,
+        cfg.llm_mistake_rate,
+        rng,
+    )?;
+
+    Ok(generate_plan_impl(
+        final_text,
+        cfg,
+        layout,
+        &phrase_spans,
+        &word_mistakes,
+        rng,
+    )?)
+}
+
+/// Type an LLM-written first draft of each paragraph (one per paragraph, see [`split_paragraphs`])
+/// and, once a paragraph is typed, edit it into the final text word by word. A draft equal to its
+/// paragraph is typed as is.
+pub fn generate_plan_with_paragraph_drafts(
+    final_text: &str,
+    cfg: PlannerConfig,
+    drafts_by_paragraph: &[String],
+    rng: &mut impl Rng,
+) -> Result<Plan, DrafterError> {
+    let expanded_text = prepare_document(final_text, &cfg)?;
+    let final_text = expanded_text.as_str();
+    let drafts_by_paragraph: Vec<String> = drafts_by_paragraph
+        .iter()
+        .map(|draft| prepare_text(draft, &cfg))
+        .collect();
+
+    let mut covered_text = final_text.to_string();
+    for draft in &drafts_by_paragraph {
+        covered_text.push_str(draft);
     }
+    let layout = planner_layout(&cfg, &covered_text)?;
+    ensure_layout_supports_text(final_text, &layout, cfg.tab_policy)?;
+
+    let phrase_spans = phrase_spans_from_paragraph_drafts(
+        final_text,
+        cfg.paragraph_breaks,
+        &drafts_by_paragraph,
+        &layout,
+    )?;
+
+    Ok(generate_plan_impl(
+        final_text,
+        cfg,
+        layout,
+        &phrase_spans,
+        &HashMap::new(),
+        rng,
+    )?)
+}
 
-    let phrase_spans =
-        phrase_spans_from_paragraph_alternatives(final_text, alternatives_by_paragraph)?;
+/// Like [`generate_plan_with_phrase_alternatives`], fetching the alternatives for the
+/// paragraphs of `final_text` from `provider`.
+pub async fn generate_plan_with_rephrase_provider(
+    final_text: &str,
+    cfg: PlannerConfig,
+    provider: &impl ParagraphRephraseProvider,
+    options: ParagraphRephraseOptions,
+    rng: &mut impl Rng,
+) -> Result<Plan, DrafterError> {
+    let paragraphs = split_paragraphs_with(final_text, cfg.paragraph_breaks);
+    let alternatives_by_paragraph = provider.rephrase_paragraphs(&paragraphs, options).await?;
+    generate_plan_with_phrase_alternatives(final_text, cfg, &alternatives_by_paragraph, rng)
+}
 
-    generate_plan_impl(final_text, cfg, &phrase_spans, rng)
+/// Like [`generate_plan_with_paragraph_drafts`], fetching the drafts for the paragraphs of
+/// `final_text` from `provider`.
+pub async fn generate_plan_with_draft_provider(
+    final_text: &str,
+    cfg: PlannerConfig,
+    provider: &impl ParagraphDraftProvider,
+    options: ParagraphDraftOptions,
+    rng: &mut impl Rng,
+) -> Result<Plan, DrafterError> {
+    let paragraphs = split_paragraphs_with(final_text, cfg.paragraph_breaks);
+    let drafts_by_paragraph = provider.draft_paragraphs(&paragraphs, options).await?;
+    generate_plan_with_paragraph_drafts(final_text, cfg, &drafts_by_paragraph, rng)
 }
 
-pub fn generate_plan(final_text: &str, cfg: PlannerConfig, rng: &mut impl Rng) -> Result<Plan> {
-    if cfg.error_rate_per_word == 0.0 {
+pub fn generate_plan(
+    final_text: &str,
+    cfg: PlannerConfig,
+    rng: &mut impl Rng,
+) -> Result<Plan, DrafterError> {
+    let cfg = apply_error_mix(cfg);
+    if cfg.error_rate_per_word == 0.0
+        && cfg.punctuation_error_rate == 0.0
+        && cfg.missing_period_rate == 0.0
+        && cfg.confusion_rate == 0.0
+        && (cfg.autocorrect_typo_rate == 0.0 || !cfg.substitutions.autocorrect)
+    {
         return generate_plan_no_revision(final_text, cfg, rng);
     }
-    generate_plan_impl(final_text, cfg, &[], rng)
+    let expanded_text = prepare_document(final_text, &cfg)?;
+    let final_text = expanded_text.as_str();
+    let layout = planner_layout(&cfg, final_text)?;
+    Ok(generate_plan_impl(
+        final_text,
+        cfg,
+        layout,
+        &[],
+        &HashMap::new(),
+        rng,
+    )?)
 }
 
 pub fn generate_plan_no_revision(
     final_text: &str,
     cfg: PlannerConfig,
     rng: &mut impl Rng,
-) -> Result<Plan> {
+) -> Result<Plan, DrafterError> {
     validate_config(&cfg)?;
 
-    if let Some((byte_idx, c)) = find_first_unsupported_char(final_text) {
-        let (line, col) = byte_index_to_line_col(final_text, byte_idx);
-        return Err(anyhow!(
-            "unsupported character {:?} (U+{:04X}) at line {}, column {}. Supported: ASCII, newline, and smart quotes. Tabs are not allowed.",
-            c, c as u32, line, col
-        ));
-    }
+    let expanded_text = prepare_document(final_text, &cfg)?;
+    let final_text = expanded_text.as_str();
+    let layout = planner_layout(&cfg, final_text)?;
+    ensure_layout_supports_text(final_text, &layout, cfg.tab_policy)?;
 
-    let keymap = us_qwerty_keymap()?;
+    let keymap = layout.keymap().clone();
     let wpm_target = rng.gen_range(cfg.wpm_min..=cfg.wpm_max);
+    tracing::debug!(
+        chars = final_text.chars().count(),
+        layout = keymap.layout.as_str(),
+        wpm_target,
+        "planning"
+    );
 
-    let mut builder = ActionBuilder::new(keymap.shift_mask, keymap.ctrl_mask);
-    let mut editor = EditorState::default();
+    let mut builder = ActionBuilder::new(layout, &cfg);
+    let mut editor = EditorModel::new();
 
     builder.set_modifiers();
     builder.wait(rng.gen_range(250..=600));
 
-    type_string(&mut builder, &mut editor, final_text, wpm_target, rng)?;
+    let chars: Vec<char> = final_text.chars().collect();
+    let mut caps_run_end = None;
+    let careful = careful_mask(&chars, &cfg);
+    let unclosed = unclosed_brackets(&chars);
+    let mut marks = ParagraphMarks::new(final_text, cfg.paragraph_breaks);
+    let mut checkpoints = Checkpoints::default();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        marks.before(i, builder.actions.len());
+        update_caps_lock(
+            &mut builder,
+            &mut caps_run_end,
+            &chars,
+            i,
+            cfg.caps_lock_rate,
+            rng,
+        );
+        let mut buf = [0; 4];
+        let typed = c.encode_utf8(&mut buf);
+        if careful[i] {
+            type_careful(&mut builder, &mut editor, typed, wpm_target, rng)?;
+        } else {
+            type_string(&mut builder, &mut editor, typed, wpm_target, rng)?;
+        }
+        delete_unclosed_bracket(&mut builder, &mut editor, &unclosed, i, rng);
+        i += 1;
+        if c == '\n' {
+            i += match_auto_indent(&mut builder, &mut editor, &chars, i, rng);
+        }
+        marks.after(i, builder.actions.len());
+        let in_token = careful.get(i).is_some_and(|&next| next);
+        if sentence_or_paragraph_boundary(chars[i - 1]) && !in_token {
+            checkpoints.mark(&mut builder, &editor, &chars, i);
+        }
+        maybe_session_break(&mut builder, &mut editor, &chars, i, &cfg, rng);
+    }
 
+    builder.set_caps_lock(false, rng);
     builder.set_shift(false, rng);
+    builder.set_altgr(false, rng);
     builder.set_ctrl(false, rng);
     builder.set_modifiers();
+    checkpoints.mark(&mut builder, &editor, &chars, chars.len());
 
     let final_simulated = editor.as_string();
     if final_simulated != final_text {
-        return Err(anyhow!(
-            "planner bug: simulated text does not match final draft"
-        ));
+        return Err(anyhow!("planner bug: simulated text does not match final draft").into());
     }
 
+    let actions = builder.into_actions();
+    tracing::info!(
+        chars = final_text.chars().count(),
+        actions = actions.len(),
+        wpm_target,
+        "generated plan"
+    );
     Ok(Plan {
-        version: 1,
+        version: PLAN_VERSION,
         config: PlanConfig {
             layout: keymap.layout,
             keymap_format: keymap.keymap_format,
             keymap: keymap.keymap,
             wpm_target,
+            word_nav_profile: None,
+            paragraph_breaks: cfg.paragraph_breaks,
+            strip_trailing_whitespace: cfg.trailing_whitespace == WhitespacePolicy::Strip,
+            strip_final_newline: cfg.final_newline == WhitespacePolicy::Strip,
         },
-        actions: builder.into_actions(),
+        actions,
+        annotations: marks.annotations,
     })
 }
 
 fn generate_plan_impl(
     final_text: &str,
     cfg: PlannerConfig,
+    layout: KeyboardLayout,
     phrase_spans: &[PhraseSpan],
+    word_mistakes: &HashMap<usize, WordMistake>,
     rng: &mut impl Rng,
 ) -> Result<Plan> {
     validate_config(&cfg)?;
+    let cfg = apply_error_mix(cfg);
+    ensure_layout_supports_text(final_text, &layout, cfg.tab_policy)?;
 
-    if let Some((byte_idx, c)) = find_first_unsupported_char(final_text) {
-        let (line, col) = byte_index_to_line_col(final_text, byte_idx);
-        return Err(anyhow!(
-            "unsupported character {c:?} (U+{:04X}) at line {line}, column {col}. Supported: ASCII, newline, and smart quotes (’ ‘ ” “). Tabs are not allowed.",
-            c as u32
-        ));
-    }
-
-    let keymap = us_qwerty_keymap()?;
+    let keymap = layout.keymap().clone();
 
     let wpm_target = rng.gen_range(cfg.wpm_min..=cfg.wpm_max);
+    tracing::debug!(
+        chars = final_text.chars().count(),
+        layout = keymap.layout.as_str(),
+        wpm_target,
+        "planning"
+    );
 
-    let mut builder = ActionBuilder::new(keymap.shift_mask, keymap.ctrl_mask);
-    let mut editor = EditorState::default();
+    let mut builder = ActionBuilder::new(layout, &cfg);
+    let mut editor = EditorModel::new();
     let mut outstanding: Vec<OutstandingError> = Vec::new();
+    // Edits of the paragraph being typed (first-draft edits and alternatives hinted for the end
+    // of the paragraph), made once it is complete.
+    let mut paragraph_edits: Vec<OutstandingError> = Vec::new();
+    let mut paragraph_pass_at: Option<usize> = None;
+    // Alternatives hinted for the final review, edited back after typing.
+    let mut final_edits: Vec<OutstandingError> = Vec::new();
 
     // Ensure compositor and clients start from a neutral modifier state.
     builder.set_modifiers();
     builder.wait(rng.gen_range(250..=600));
 
     let chars: Vec<char> = final_text.chars().collect();
+    let careful = careful_mask(&chars, &cfg);
+    let unclosed = unclosed_brackets(&chars);
+    // Careful tokens are typed as they are, and so is text the editor would edit as it is typed.
+    let phrase_spans: Vec<&PhraseSpan> = phrase_spans
+        .iter()
+        .filter(|span| !careful[span.start..span.start + span.original_len_chars].contains(&true))
+        .filter(|span| {
+            !(span.original.chars())
+                .chain(span.alternative.chars())
+                .any(|c| cfg.code_editor.edits_after(c))
+        })
+        .collect();
     let mut i = 0usize;
     let mut phrase_idx = 0usize;
     let mut last_char: char;
+    let mut caps_run_end: Option<usize> = None;
+    let mut marks = ParagraphMarks::new(final_text, cfg.paragraph_breaks);
+    let mut checkpoints = Checkpoints::default();
 
     while i < chars.len() {
+        marks.before(i, builder.actions.len());
+        update_caps_lock(
+            &mut builder,
+            &mut caps_run_end,
+            &chars,
+            i,
+            cfg.caps_lock_rate,
+            rng,
+        );
+
         let progress = (i as f64) / (chars.len() as f64);
         let next_phrase_start = phrase_spans.get(phrase_idx).map(|span| span.start);
 
         if next_phrase_start == Some(i) {
             let span = &phrase_spans[phrase_idx];
-            let typed: &str;
+            // Spans edited back from `outstanding` count towards its limit.
+            let revision = match span.revision {
+                SpanRevision::Delayed | SpanRevision::Immediately
+                    if outstanding.len() >= cfg.max_outstanding_errors =>
+                {
+                    None
+                }
+                revision => Some(revision),
+            };
 
-            if outstanding.len() < cfg.max_outstanding_errors {
-                let start_cursor = editor.cursor;
-                typed = span.alternative.as_str();
-                type_string(&mut builder, &mut editor, typed, wpm_target, rng)?;
-                outstanding.push(OutstandingError {
+            let start_cursor = editor.cursor();
+            let typed = match revision {
+                Some(_) => span.alternative.as_str(),
+                None => span.original.as_str(),
+            };
+            type_string(&mut builder, &mut editor, typed, wpm_target, rng)?;
+
+            if let Some(revision) = revision {
+                let edit = OutstandingError {
                     start: start_cursor,
                     wrong: span.alternative.clone(),
                     correct: span.original.clone(),
-                    fix_after_chars: rng.gen_range(90..=420),
+                    fix_after_chars: 0,
                     constraint: CorrectionConstraint::SentenceOrParagraphBoundary,
-                });
-            } else {
-                typed = span.original.as_str();
-                type_string(&mut builder, &mut editor, typed, wpm_target, rng)?;
+                };
+                match revision {
+                    SpanRevision::Delayed => outstanding.push(OutstandingError {
+                        fix_after_chars: rng.gen_range(90..=420),
+                        ..edit
+                    }),
+                    SpanRevision::Immediately => outstanding.push(OutstandingError {
+                        constraint: CorrectionConstraint::None,
+                        ..edit
+                    }),
+                    SpanRevision::ParagraphPass(paragraph_end) => {
+                        paragraph_edits.push(edit);
+                        paragraph_pass_at = Some(paragraph_end);
+                    }
+                    SpanRevision::FinalReview => final_edits.push(edit),
+                }
             }
 
             last_char = typed
@@ -877,81 +2581,101 @@ fn generate_plan_impl(
 
             i += span.original_len_chars;
             phrase_idx += 1;
+        } else if careful[i] {
+            // In one go, so no correction lands inside it.
+            let end = (i..chars.len())
+                .find(|&j| !careful[j])
+                .unwrap_or(chars.len());
+            builder.set_caps_lock(false, rng);
+            caps_run_end = None;
+            // Stopping only to delete closing brackets the editor added.
+            let mut from = i;
+            for j in i..end {
+                if j + 1 == end || (cfg.code_editor.auto_close_brackets && unclosed[j]) {
+                    let piece: String = chars[from..=j].iter().collect();
+                    type_careful(&mut builder, &mut editor, &piece, wpm_target, rng)?;
+                    delete_unclosed_bracket(&mut builder, &mut editor, &unclosed, j, rng);
+                    from = j + 1;
+                }
+            }
+            last_char = chars[end - 1];
+            i = end;
         } else if is_word_char(chars[i]) {
             let start = i;
             i += 1;
-            while i < chars.len() && is_word_char(chars[i]) {
+            // An opening quote is a word character, but the token after it is not.
+            while i < chars.len() && is_word_char(chars[i]) && !careful[i] {
                 i += 1;
             }
             let word_end = i;
 
-            if let Some(p) = next_phrase_start {
-                if p > start && p < word_end {
-                    let prefix: String = chars[start..p].iter().collect();
-                    type_string(&mut builder, &mut editor, &prefix, wpm_target, rng)?;
-                    last_char = chars[p - 1];
-                    i = p;
-                } else {
-                    let word: String = chars[start..word_end].iter().collect();
-
-                    let inject_error = rng.gen_bool(cfg.error_rate_per_word)
-                        && outstanding.len() < cfg.max_outstanding_errors;
-
-                    if inject_error {
-                        let want_variant = rng.gen_bool(cfg.word_variant_share);
-                        let wrong = if want_variant {
-                            word_variant(&word, rng).or_else(|| word_typo(&word, rng))
-                        } else {
-                            word_typo(&word, rng).or_else(|| word_variant(&word, rng))
-                        };
-
-                        if let Some(wrong_word) = wrong {
-                            let word_start_cursor = editor.cursor;
-                            type_string(&mut builder, &mut editor, &wrong_word, wpm_target, rng)?;
-
-                            if rng.gen_bool(cfg.immediate_fix_rate) {
-                                replace_at_end(
-                                    &mut builder,
-                                    &mut editor,
-                                    &wrong_word,
-                                    &word,
-                                    wpm_target,
-                                    rng,
-                                )?;
-                            } else {
-                                outstanding.push(OutstandingError {
-                                    start: word_start_cursor,
-                                    wrong: wrong_word,
-                                    correct: word,
-                                    fix_after_chars: rng.gen_range(25..=220),
-                                    constraint: CorrectionConstraint::None,
-                                });
-                            }
-                        } else {
-                            type_string(&mut builder, &mut editor, &word, wpm_target, rng)?;
-                        }
-                    } else {
-                        type_string(&mut builder, &mut editor, &word, wpm_target, rng)?;
-                    }
-
-                    last_char = chars[word_end - 1];
-                }
+            if let Some(p) = next_phrase_start.filter(|&p| p > start && p < word_end) {
+                let prefix: String = chars[start..p].iter().collect();
+                type_string(&mut builder, &mut editor, &prefix, wpm_target, rng)?;
+                last_char = chars[p - 1];
+                i = p;
             } else {
                 let word: String = chars[start..word_end].iter().collect();
-
-                let inject_error = rng.gen_bool(cfg.error_rate_per_word)
-                    && outstanding.len() < cfg.max_outstanding_errors;
-
-                if inject_error {
-                    let want_variant = rng.gen_bool(cfg.word_variant_share);
-                    let wrong = if want_variant {
-                        word_variant(&word, rng).or_else(|| word_typo(&word, rng))
-                    } else {
-                        word_typo(&word, rng).or_else(|| word_variant(&word, rng))
+                let llm_mistake = word_mistakes
+                    .get(&start)
+                    .filter(|mistake| mistake.original == word)
+                    .map(|mistake| mistake.mistake.as_str());
+
+                let can_err = outstanding.len() < cfg.max_outstanding_errors;
+                let confusion = (cfg.confusion_rate > 0.0 && llm_mistake.is_none() && can_err)
+                    .then(|| confused_word(&word, cfg.confusion_rate, &builder.layout, rng))
+                    .flatten();
+                // The editor corrects the typo once the space after it is typed, so the space
+                // is typed with it.
+                let autocorrect = cfg.substitutions.autocorrect
+                    && cfg.autocorrect_typo_rate > 0.0
+                    && confusion.is_none()
+                    && llm_mistake.is_none()
+                    && chars.get(word_end) == Some(&' ')
+                    && next_phrase_start != Some(word_end);
+                let autocorrect_typo = autocorrect
+                    .then(|| {
+                        autocorrectable_typo(&word, cfg.autocorrect_typo_rate, &builder.layout, rng)
+                    })
+                    .flatten();
+                let error_rate = match llm_mistake {
+                    Some(_) => cfg.llm_mistake_rate,
+                    None => cfg.error_rate_per_word,
+                };
+                let inject_error =
+                    rng.gen_bool(error_rate) && outstanding.len() < cfg.max_outstanding_errors;
+
+                if let Some(confused) = confusion {
+                    // Looks right while typing, so it is only caught when reading it over.
+                    let word_start_cursor = editor.cursor();
+                    type_string(&mut builder, &mut editor, &confused, wpm_target, rng)?;
+                    outstanding.push(OutstandingError {
+                        start: word_start_cursor,
+                        wrong: confused,
+                        correct: word,
+                        fix_after_chars: rng.gen_range(120..=480),
+                        constraint: CorrectionConstraint::SentenceOrParagraphBoundary,
+                    });
+                } else if let Some(typo) = autocorrect_typo {
+                    // Never corrected here: the editor model predicts the editor's fix.
+                    type_string(&mut builder, &mut editor, &typo, wpm_target, rng)?;
+                    type_string(&mut builder, &mut editor, " ", wpm_target, rng)?;
+                    if !editor.autocorrect() {
+                        return Err(anyhow!("{typo:?} is not autocorrected"));
+                    }
+                    i += 1;
+                } else if inject_error {
+                    let wrong = match llm_mistake {
+                        Some(mistake) => Some(mistake.to_string()),
+                        None => mechanical_wrong_word(&word, &cfg, &builder.layout, rng),
                     };
+                    let wrong = wrong.filter(|w| {
+                        builder.layout.is_supported_text(w)
+                            && !w.chars().any(|c| cfg.code_editor.edits_after(c))
+                    });
 
                     if let Some(wrong_word) = wrong {
-                        let word_start_cursor = editor.cursor;
+                        let word_start_cursor = editor.cursor();
                         type_string(&mut builder, &mut editor, &wrong_word, wpm_target, rng)?;
 
                         if rng.gen_bool(cfg.immediate_fix_rate) {
@@ -979,19 +2703,61 @@ fn generate_plan_impl(
                     type_string(&mut builder, &mut editor, &word, wpm_target, rng)?;
                 }
 
-                last_char = chars[word_end - 1];
+                last_char = chars[i - 1];
             }
         } else {
             let c = chars[i];
             i += 1;
+            let can_err = outstanding.len() < cfg.max_outstanding_errors;
+            let mut omitted = false;
+            let wrong_punctuation = (cfg.punctuation_error_rate > 0.0
+                && c.is_ascii_punctuation()
+                && can_err
+                && rng.gen_bool(cfg.punctuation_error_rate))
+            .then(|| punctuation_typo(c, &builder.layout, rng))
+            .flatten()
+            .filter(|&wrong| {
+                !(cfg.code_editor.edits_after(c) || cfg.code_editor.edits_after(wrong))
+            });
 
-            // Occasional double-space typo (only when errors are enabled).
-            if cfg.error_rate_per_word > 0.0
+            if let Some(wrong) = wrong_punctuation {
+                let start_cursor = editor.cursor();
+                let (wrong, correct) = (wrong.to_string(), c.to_string());
+                type_string(&mut builder, &mut editor, &wrong, wpm_target, rng)?;
+                if rng.gen_bool(cfg.immediate_fix_rate) {
+                    replace_at_end(&mut builder, &mut editor, &wrong, &correct, wpm_target, rng)?;
+                } else {
+                    outstanding.push(OutstandingError {
+                        start: start_cursor,
+                        wrong,
+                        correct,
+                        fix_after_chars: rng.gen_range(25..=220),
+                        constraint: CorrectionConstraint::None,
+                    });
+                }
+            } else if cfg.missing_period_rate > 0.0
+                && is_paragraph_final_period(&chars, i - 1)
+                && can_err
+                && rng.gen_bool(cfg.missing_period_rate)
+            {
+                // Left out, and noticed when reviewing the paragraph later.
+                outstanding.push(OutstandingError {
+                    start: editor.cursor(),
+                    wrong: String::new(),
+                    correct: c.to_string(),
+                    fix_after_chars: rng.gen_range(60..=400),
+                    constraint: CorrectionConstraint::SentenceOrParagraphBoundary,
+                });
+                omitted = true;
+            } else if cfg.mode == TypingMode::Prose
+                && cfg.error_mix.double_spaces
+                && cfg.error_rate_per_word > 0.0
                 && c == ' '
                 && rng.gen_bool(0.015)
                 && outstanding.len() < cfg.max_outstanding_errors
             {
-                let start_cursor = editor.cursor;
+                // Occasional double-space typo (only when errors are enabled).
+                let start_cursor = editor.cursor();
                 type_string(&mut builder, &mut editor, "  ", wpm_target, rng)?;
                 outstanding.push(OutstandingError {
                     start: start_cursor,
@@ -1002,15 +2768,52 @@ fn generate_plan_impl(
                 });
             } else {
                 type_string(&mut builder, &mut editor, &c.to_string(), wpm_target, rng)?;
+                delete_unclosed_bracket(&mut builder, &mut editor, &unclosed, i - 1, rng);
+                if c == '\n' {
+                    i += match_auto_indent(&mut builder, &mut editor, &chars, i, rng);
+                }
             }
 
-            last_char = c;
+            // An omitted period leaves the text ending in the word before it.
+            last_char = if omitted { chars[i - 2] } else { c };
+        }
+
+        // Edit a fully typed first-draft paragraph into shape, front to back, together with any
+        // typos made along the way.
+        if paragraph_pass_at.is_some_and(|end| i >= end) {
+            paragraph_pass_at = None;
+            builder.set_caps_lock(false, rng);
+            caps_run_end = None;
+            builder.wait(rng.gen_range(cfg.review_pause_ms_min..=cfg.review_pause_ms_max));
+
+            let mut edits = std::mem::take(&mut paragraph_edits);
+            edits.append(&mut outstanding);
+            edits.sort_by_key(|err| err.start);
+
+            // Earlier edits shift the text after them.
+            let mut shift = 0isize;
+            for mut err in edits {
+                err.start = err.start.saturating_add_signed(shift);
+                let delta = length_delta(&err);
+                shift += delta;
+                shift_edits_after(&mut final_edits, err.start, delta);
+                fix_error_at_position(
+                    &mut builder,
+                    &mut editor,
+                    err,
+                    wpm_target,
+                    &cfg.word_nav_profile,
+                    rng,
+                )?;
+                builder.wait(rng.gen_range(80..=420));
+            }
         }
 
-        // Occasionally fix a recent mistake (delayed correction).
-        if let Some(err) = outstanding.last() {
+        // Occasionally fix a recent mistake (delayed correction), but not in the middle of a
+        // first draft.
+        if let Some(err) = outstanding.last().filter(|_| paragraph_edits.is_empty()) {
             let wrong_len = err.wrong.chars().count();
-            let age = editor.cursor.saturating_sub(err.start + wrong_len);
+            let age = editor.cursor().saturating_sub(err.start + wrong_len);
             let late_stage = progress >= cfg.stop_corrections_after_progress;
 
             let force_fix = outstanding.len() >= cfg.max_outstanding_errors;
@@ -1035,29 +2838,44 @@ fn generate_plan_impl(
 
             if should_fix {
                 let err = outstanding.pop().unwrap();
+                shift_edits_after(&mut final_edits, err.start, length_delta(&err));
+                builder.set_caps_lock(false, rng);
+                caps_run_end = None;
                 fix_error_at_position(
                     &mut builder,
                     &mut editor,
                     err,
                     wpm_target,
-                    cfg.word_nav_profile,
+                    &cfg.word_nav_profile,
                     rng,
                 )?;
                 builder.wait(rng.gen_range(80..=420));
             }
         }
+        marks.after(i, builder.actions.len());
+        let pending =
+            !(outstanding.is_empty() && paragraph_edits.is_empty() && final_edits.is_empty());
+        if sentence_or_paragraph_boundary(last_char) && !pending {
+            checkpoints.mark(&mut builder, &editor, &chars, i);
+        }
+        maybe_session_break(&mut builder, &mut editor, &chars, i, &cfg, rng);
     }
 
+    builder.set_caps_lock(false, rng);
+
     // Always do a near-end review pass.
     builder.wait(rng.gen_range(cfg.review_pause_ms_min..=cfg.review_pause_ms_max));
 
+    // Back to front, so each edit leaves the positions of the ones still to make alone.
+    outstanding.append(&mut final_edits);
+    outstanding.sort_by_key(|err| err.start);
     while let Some(err) = outstanding.pop() {
         fix_error_at_position(
             &mut builder,
             &mut editor,
             err,
             wpm_target,
-            cfg.word_nav_profile,
+            &cfg.word_nav_profile,
             rng,
         )?;
         builder.wait(rng.gen_range(120..=520));
@@ -1065,8 +2883,10 @@ fn generate_plan_impl(
 
     // Return to neutral modifiers.
     builder.set_shift(false, rng);
+    builder.set_altgr(false, rng);
     builder.set_ctrl(false, rng);
     builder.set_modifiers();
+    checkpoints.mark(&mut builder, &editor, &chars, chars.len());
 
     let final_simulated = editor.as_string();
     if final_simulated != final_text {
@@ -1075,14 +2895,26 @@ fn generate_plan_impl(
         ));
     }
 
+    let actions = builder.into_actions();
+    tracing::info!(
+        chars = final_text.chars().count(),
+        actions = actions.len(),
+        wpm_target,
+        "generated plan"
+    );
     Ok(Plan {
-        version: 1,
+        version: PLAN_VERSION,
         config: PlanConfig {
             layout: keymap.layout,
             keymap_format: keymap.keymap_format,
             keymap: keymap.keymap,
             wpm_target,
+            word_nav_profile: Some(cfg.word_nav_profile.name().to_string()),
+            paragraph_breaks: cfg.paragraph_breaks,
+            strip_trailing_whitespace: cfg.trailing_whitespace == WhitespacePolicy::Strip,
+            strip_final_newline: cfg.final_newline == WhitespacePolicy::Strip,
         },
-        actions: builder.into_actions(),
+        actions,
+        annotations: marks.annotations,
     })
 }
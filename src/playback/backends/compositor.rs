@@ -0,0 +1,112 @@
+//! Which compositor the Wayland backend types into: [`Compositor::identify`] probes the globals
+//! it advertises (and the session environment) at connect time, for `drafter doctor` and the
+//! playback log.
+//!
+//! Playback treats every compositor the same. A compositor whose virtual keyboard needs its own
+//! delays or modifier handling gets them here once an issue or a measurement shows what it needs.
+
+/// A compositor `identify` can tell apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compositor {
+    Sway,
+    Hyprland,
+    River,
+    /// Any other compositor. KDE Plasma and GNOME only emulate input through libei, so playback
+    /// never reaches them.
+    Unknown,
+}
+
+impl Compositor {
+    /// Identifies the compositor from the `(interface, version)` globals it advertises, which
+    /// name Hyprland and river through their private protocols, falling back to
+    /// `XDG_CURRENT_DESKTOP` and `SWAYSOCK` as read by `env` (sway has no globals of its own).
+    pub(crate) fn identify(
+        advertised: &[(String, u32)],
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let advertises = |prefixes: &[&str]| {
+            advertised
+                .iter()
+                .any(|(interface, _)| prefixes.iter().any(|prefix| interface.starts_with(prefix)))
+        };
+        if advertises(&["hyprland_"]) {
+            return Compositor::Hyprland;
+        }
+        if advertises(&["zriver_", "river_"]) {
+            return Compositor::River;
+        }
+
+        let desktops = env("XDG_CURRENT_DESKTOP")
+            .unwrap_or_default()
+            .to_lowercase();
+        let desktop = |name: &str| desktops.split(':').any(|desktop| desktop == name);
+        if desktop("hyprland") || env("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Compositor::Hyprland
+        } else if desktop("river") {
+            Compositor::River
+        } else if desktop("sway") || env("SWAYSOCK").is_some() {
+            Compositor::Sway
+        } else {
+            Compositor::Unknown
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Compositor::Sway => "sway",
+            Compositor::Hyprland => "Hyprland",
+            Compositor::River => "river",
+            Compositor::Unknown => "unknown compositor",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compositor;
+
+    fn globals(interfaces: &[&str]) -> Vec<(String, u32)> {
+        interfaces
+            .iter()
+            .map(|interface| (interface.to_string(), 1))
+            .collect()
+    }
+
+    #[test]
+    fn identifies_compositors_by_their_private_globals() {
+        let no_env = |_: &str| None;
+        for (interface, compositor) in [
+            ("hyprland_toplevel_export_manager_v1", Compositor::Hyprland),
+            ("zriver_control_v1", Compositor::River),
+            (
+                "org_kde_kwin_server_decoration_manager",
+                Compositor::Unknown,
+            ),
+            ("zwlr_layer_shell_v1", Compositor::Unknown),
+        ] {
+            let advertised = globals(&["wl_seat", "zwp_virtual_keyboard_manager_v1", interface]);
+            assert_eq!(Compositor::identify(&advertised, no_env), compositor);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_the_session_environment() {
+        let advertised = globals(&["wl_seat", "zwlr_layer_shell_v1"]);
+        let identify = |name: &'static str, value: &'static str| {
+            Compositor::identify(&advertised, move |var: &str| {
+                (var == name).then(|| value.to_string())
+            })
+        };
+        assert_eq!(identify("SWAYSOCK", "/run/sway.sock"), Compositor::Sway);
+        assert_eq!(identify("XDG_CURRENT_DESKTOP", "sway"), Compositor::Sway);
+        assert_eq!(
+            identify("XDG_CURRENT_DESKTOP", "Hyprland"),
+            Compositor::Hyprland
+        );
+        assert_eq!(identify("XDG_CURRENT_DESKTOP", "KDE"), Compositor::Unknown);
+        assert_eq!(
+            identify("XDG_CURRENT_DESKTOP", "ubuntu:GNOME"),
+            Compositor::Unknown
+        );
+    }
+}
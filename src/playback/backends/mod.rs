@@ -1,4 +1,6 @@
 #[cfg(feature = "wayland")]
+pub(crate) mod compositor;
+#[cfg(feature = "wayland")]
 pub mod wayland;
 
 #[cfg(feature = "x11")]
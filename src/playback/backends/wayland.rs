@@ -1,23 +1,35 @@
 use std::collections::HashMap;
+use std::fs::File;
 use std::io::Write;
 use std::os::fd::{AsFd, FromRawFd, IntoRawFd, OwnedFd};
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
+use std::os::unix::fs::FileExt;
 use std::time::Instant;
 
 use anyhow::{anyhow, Context, Result};
 use memfd::MemfdOptions;
-use wayland_client::globals::{registry_queue_init, GlobalListContents};
-use wayland_client::protocol::{wl_registry, wl_seat};
-use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
-
-use crate::model::{Action, KeyState, Plan};
-use crate::playback::util::{print_trace_line, sleep_interruptible};
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::{registry_queue_init, GlobalList, GlobalListContents};
+use wayland_client::protocol::{wl_keyboard, wl_registry, wl_seat};
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle, WEnum};
+use xkbcommon::xkb;
+
+use crate::error::DrafterError;
+use super::compositor::Compositor;
+use crate::model::{KeyState, Plan, PlanConfig};
+use crate::playback::doctor::DoctorCheck;
+use crate::playback::sink::{play_actions, PlaybackSource};
+use crate::playback::util::{countdown, report_focused_app};
+use crate::playback::{
+    CancellationToken, FocusedApp, KeySink, PlaybackObserver, PlaybackOptions, SeatInfo, SystemClock,
+};
+use crate::protocols::wlr_foreign_toplevel_management_unstable_v1::zwlr_foreign_toplevel_handle_v1::{
+    self, ZwlrForeignToplevelHandleV1,
+};
+use crate::protocols::wlr_foreign_toplevel_management_unstable_v1::zwlr_foreign_toplevel_manager_v1::{
+    self, ZwlrForeignToplevelManagerV1,
+};
 use crate::protocols::virtual_keyboard_unstable_v1::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
 use crate::protocols::virtual_keyboard_unstable_v1::zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1;
-use crate::trace::plan_console_trace;
 
 #[derive(Debug, Clone)]
 struct SeatData {
@@ -27,6 +39,8 @@ struct SeatData {
 #[derive(Debug, Default)]
 struct State {
     seat_names_by_global: HashMap<u32, String>,
+    seat_capabilities_by_global: HashMap<u32, wl_seat::Capability>,
+    session_keymap: Option<String>,
 }
 
 impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
@@ -50,8 +64,101 @@ impl Dispatch<wl_seat::WlSeat, SeatData> for State {
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
-        if let wl_seat::Event::Name { name } = event {
-            state.seat_names_by_global.insert(data.global_name, name);
+        match event {
+            wl_seat::Event::Name { name } => {
+                state.seat_names_by_global.insert(data.global_name, name);
+            }
+            wl_seat::Event::Capabilities {
+                capabilities: WEnum::Value(capabilities),
+            } => {
+                state
+                    .seat_capabilities_by_global
+                    .insert(data.global_name, capabilities);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl State {
+    fn seat_has_keyboard(&self, global: u32) -> bool {
+        self.seat_capabilities_by_global
+            .get(&global)
+            .is_some_and(|capabilities| capabilities.contains(wl_seat::Capability::Keyboard))
+    }
+
+    fn seat_info(&self, global: u32) -> SeatInfo {
+        let capabilities = self
+            .seat_capabilities_by_global
+            .get(&global)
+            .copied()
+            .unwrap_or_else(wl_seat::Capability::empty);
+        SeatInfo {
+            name: self.seat_names_by_global.get(&global).cloned(),
+            keyboard: capabilities.contains(wl_seat::Capability::Keyboard),
+            pointer: capabilities.contains(wl_seat::Capability::Pointer),
+            touch: capabilities.contains(wl_seat::Capability::Touch),
+        }
+    }
+}
+
+/// Binds every advertised `wl_seat`; a roundtrip then delivers their names and capabilities.
+fn bind_seats(globals: &GlobalList, qh: &QueueHandle<State>) -> Vec<(u32, wl_seat::WlSeat)> {
+    globals
+        .contents()
+        .clone_list()
+        .into_iter()
+        .filter(|g| g.interface == wl_seat::WlSeat::interface().name)
+        .map(|g| {
+            let seat = globals.registry().bind(
+                g.name,
+                g.version.min(7),
+                qh,
+                SeatData {
+                    global_name: g.name,
+                },
+            );
+            (g.name, seat)
+        })
+        .collect()
+}
+
+/// The seats the compositor advertises, in the order it advertises them.
+pub fn seats_wayland() -> Result<Vec<SeatInfo>> {
+    let conn = Connection::connect_to_env().context("failed to connect to Wayland")?;
+    let (globals, mut event_queue) =
+        registry_queue_init(&conn).context("failed to init Wayland registry")?;
+    let mut state = State::default();
+    let seats = bind_seats(&globals, &event_queue.handle());
+    event_queue
+        .roundtrip(&mut state)
+        .context("Wayland roundtrip (seat discovery) failed")?;
+    Ok(seats
+        .iter()
+        .map(|(global, _)| state.seat_info(*global))
+        .collect())
+}
+
+impl Dispatch<wl_keyboard::WlKeyboard, ()> for State {
+    fn event(
+        state: &mut Self,
+        _proxy: &wl_keyboard::WlKeyboard,
+        event: wl_keyboard::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_keyboard::Event::Keymap {
+            format: WEnum::Value(wl_keyboard::KeymapFormat::XkbV1),
+            fd,
+            size,
+        } = event
+        {
+            let mut buf = vec![0u8; size as usize];
+            if File::from(fd).read_exact_at(&mut buf, 0).is_ok() {
+                let keymap = String::from_utf8_lossy(&buf);
+                state.session_keymap = Some(keymap.trim_end_matches('\0').to_string());
+            }
         }
     }
 }
@@ -80,6 +187,109 @@ impl Dispatch<ZwpVirtualKeyboardV1, ()> for State {
     }
 }
 
+/// Toplevels announced by `zwlr_foreign_toplevel_manager_v1`, by handle.
+#[derive(Debug, Default)]
+struct ToplevelState {
+    toplevels: HashMap<ObjectId, Toplevel>,
+}
+
+#[derive(Debug, Default)]
+struct Toplevel {
+    app_id: Option<String>,
+    activated: bool,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for ToplevelState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for ToplevelState {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            state.toplevels.insert(toplevel.id(), Toplevel::default());
+        }
+    }
+
+    event_created_child!(ToplevelState, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for ToplevelState {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let toplevel = state.toplevels.entry(proxy.id()).or_default();
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                toplevel.app_id = Some(app_id);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: states } => {
+                let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+                toplevel.activated = states.chunks_exact(4).any(|chunk| {
+                    u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) == activated
+                });
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                state.toplevels.remove(&proxy.id());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The app id of the activated toplevel, from `zwlr_foreign_toplevel_manager_v1`; `None` when
+/// the compositor does not offer the protocol (e.g. GNOME and KDE).
+pub fn focused_app_wayland() -> Result<Option<FocusedApp>> {
+    let conn = Connection::connect_to_env().context("failed to connect to Wayland")?;
+    let (globals, mut event_queue) =
+        registry_queue_init(&conn).context("failed to init Wayland registry")?;
+    let qh = event_queue.handle();
+    let mut state = ToplevelState::default();
+
+    let Ok(manager) = globals.bind::<ZwlrForeignToplevelManagerV1, _, _>(&qh, 1..=3, ()) else {
+        return Ok(None);
+    };
+    // The first roundtrip announces the toplevels, the second delivers their app ids and states.
+    for _ in 0..2 {
+        event_queue
+            .roundtrip(&mut state)
+            .context("Wayland roundtrip (toplevel discovery) failed")?;
+    }
+    manager.stop();
+    conn.flush().ok();
+
+    Ok(state
+        .toplevels
+        .into_values()
+        .find(|toplevel| toplevel.activated)
+        .and_then(|toplevel| toplevel.app_id)
+        .filter(|app_id| !app_id.is_empty())
+        .map(|app_id| FocusedApp {
+            app_ids: vec![app_id],
+        }))
+}
+
 fn key_state_to_u32(state: KeyState) -> u32 {
     match state {
         KeyState::Released => 0,
@@ -87,6 +297,76 @@ fn key_state_to_u32(state: KeyState) -> u32 {
     }
 }
 
+/// Name of the first layout group in an XKB keymap string (e.g. "English (US)").
+fn keymap_layout_name(keymap: &str) -> Option<String> {
+    let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+    let keymap = xkb::Keymap::new_from_string(
+        &context,
+        keymap.to_string(),
+        xkb::KEYMAP_FORMAT_TEXT_V1,
+        xkb::KEYMAP_COMPILE_NO_FLAGS,
+    )?;
+    Some(keymap.layout_get_name(0).to_string())
+}
+
+/// Warn when the keys are about to reach an XWayland app, which the virtual keyboard types into
+/// through Xwayland's own keymap handling, less reliably than into native clients.
+///
+/// Without [`PlaybackOptions::xwayland_target`], checks the focus on the Xwayland server: it only
+/// gives an X11 window the input focus while an XWayland app is focused.
+fn warn_on_xwayland_target(options: &PlaybackOptions) {
+    let advice = "if characters or modifiers come out wrong, play with --backend x11";
+    if options.xwayland_target {
+        eprintln!(
+            "warning: typing into an XWayland app through the Wayland virtual keyboard; {advice}"
+        );
+        tracing::warn!("typing into an XWayland app through the Wayland virtual keyboard");
+        return;
+    }
+
+    #[cfg(feature = "x11")]
+    if std::env::var_os("DISPLAY").is_some_and(|display| !display.is_empty()) {
+        match crate::playback::backends::x11::focused_app_x11() {
+            Ok(Some(app)) => {
+                eprintln!(
+                    "warning: the focused window belongs to {app}, an XWayland app; {advice} (or --xwayland)"
+                );
+                tracing::warn!(app = %app, "the focused window belongs to an XWayland app");
+            }
+            Ok(None) => {}
+            Err(err) => tracing::debug!("could not check the Xwayland focus: {err:#}"),
+        }
+    }
+    #[cfg(not(feature = "x11"))]
+    let _ = advice;
+}
+
+/// Warn when the seat's active layout differs from the plan's.
+///
+/// The plan's keymap is uploaded to the virtual keyboard, so most clients interpret keys
+/// correctly regardless; clients that ignore per-device keymaps (e.g. some Xwayland apps) may not.
+fn warn_on_session_layout_mismatch(session_keymap: Option<&str>, config: &PlanConfig) {
+    let Some(session_keymap) = session_keymap else {
+        return;
+    };
+    let (Some(session), Some(planned)) = (
+        keymap_layout_name(session_keymap),
+        keymap_layout_name(&config.keymap),
+    ) else {
+        return;
+    };
+    if session != planned {
+        eprintln!(
+            "warning: session keyboard layout is \"{session}\" but the plan was generated for \"{planned}\"; clients that ignore the virtual keyboard's keymap (e.g. some Xwayland apps) may receive the wrong characters"
+        );
+        tracing::warn!(
+            session,
+            planned,
+            "session keyboard layout differs from the plan's"
+        );
+    }
+}
+
 fn make_keymap_fd(keymap: &str) -> Result<(OwnedFd, u32)> {
     let memfd = MemfdOptions::default()
         .allow_sealing(true)
@@ -97,7 +377,7 @@ fn make_keymap_fd(keymap: &str) -> Result<(OwnedFd, u32)> {
     file.write_all(keymap.as_bytes())?;
     file.write_all(&[0])?;
 
-    let size = (keymap.as_bytes().len() + 1)
+    let size = (keymap.len() + 1)
         .try_into()
         .map_err(|_| anyhow!("keymap too large"))?;
 
@@ -107,30 +387,274 @@ fn make_keymap_fd(keymap: &str) -> Result<(OwnedFd, u32)> {
     Ok((owned_fd, size))
 }
 
-pub fn play_plan_wayland(
-    plan: &Plan,
-    countdown_secs: u64,
-    trace: bool,
-    seat_name: Option<&str>,
-) -> Result<()> {
-    let stop = Arc::new(AtomicBool::new(false));
+/// The manager global of `zwp_virtual_keyboard_v1`, the virtual keyboard protocol offered by
+/// wlroots-based compositors (sway, Hyprland, ...).
+const VIRTUAL_KEYBOARD_MANAGER: &str = "zwp_virtual_keyboard_manager_v1";
+
+/// Fails with a hint unless [`VIRTUAL_KEYBOARD_MANAGER`] is among the `(interface, version)`
+/// globals the compositor advertises.
+fn require_virtual_keyboard(advertised: &[(String, u32)]) -> Result<()> {
+    if advertised
+        .iter()
+        .any(|(interface, _)| interface == VIRTUAL_KEYBOARD_MANAGER)
     {
-        let stop = stop.clone();
-        ctrlc::set_handler(move || {
-            stop.store(true, Ordering::SeqCst);
+        return Ok(());
+    }
+    Err(anyhow!(
+        "the compositor does not offer {VIRTUAL_KEYBOARD_MANAGER}; GNOME and KDE Plasma only \
+         emulate input through libei, which drafter does not support, but X11 apps there can be \
+         typed into with --backend x11"
+    ))
+}
+
+/// Whether the compositor can be reached and offers a virtual keyboard, for
+/// [`crate::playback::preflight_backend_chain`].
+pub(crate) fn probe_wayland() -> Result<()> {
+    let conn = Connection::connect_to_env().context("failed to connect to Wayland")?;
+    let (globals, _event_queue) =
+        registry_queue_init::<State>(&conn).context("failed to init Wayland registry")?;
+    let advertised: Vec<_> = globals
+        .contents()
+        .clone_list()
+        .into_iter()
+        .map(|g| (g.interface, g.version))
+        .collect();
+    require_virtual_keyboard(&advertised)
+}
+
+/// The checks of `drafter doctor` for the Wayland backend; see [`crate::playback::doctor`].
+pub(crate) fn diagnose_wayland(
+    options: &PlaybackOptions,
+    plan: Option<&PlanConfig>,
+) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+    let conn = match Connection::connect_to_env() {
+        Ok(conn) => conn,
+        Err(err) => {
+            checks.push(DoctorCheck::fail(
+                "wayland connection",
+                format!("failed to connect to Wayland: {err}"),
+                Some("check that WAYLAND_DISPLAY names the compositor's socket".to_string()),
+            ));
+            return checks;
+        }
+    };
+    let (globals, mut event_queue) = match registry_queue_init::<State>(&conn) {
+        Ok(init) => init,
+        Err(err) => {
+            checks.push(DoctorCheck::fail(
+                "wayland connection",
+                format!("failed to init Wayland registry: {err}"),
+                None,
+            ));
+            return checks;
+        }
+    };
+    checks.push(DoctorCheck::pass("wayland connection", "connected"));
+    let qh = event_queue.handle();
+    let mut state = State::default();
+
+    let advertised: Vec<_> = globals
+        .contents()
+        .clone_list()
+        .into_iter()
+        .map(|g| (g.interface, g.version))
+        .collect();
+    let is_advertised = |name: &str| advertised.iter().any(|(interface, _)| interface == name);
+    checks.push(match require_virtual_keyboard(&advertised) {
+        Ok(()) => DoctorCheck::pass("virtual keyboard", VIRTUAL_KEYBOARD_MANAGER),
+        Err(err) => DoctorCheck::fail("virtual keyboard", format!("{err:#}"), None),
+    });
+    checks.push(compositor_check(Compositor::identify(
+        &advertised,
+        |name| std::env::var(name).ok(),
+    )));
+
+    let seats = bind_seats(&globals, &qh);
+    if let Err(err) = event_queue.roundtrip(&mut state) {
+        checks.push(DoctorCheck::fail(
+            "seats",
+            format!("Wayland roundtrip (seat discovery) failed: {err}"),
+            None,
+        ));
+        return checks;
+    }
+    let seat_name = |global: u32| {
+        state
+            .seat_names_by_global
+            .get(&global)
+            .cloned()
+            .unwrap_or_else(|| format!("#{global}"))
+    };
+    let listed = seats
+        .iter()
+        .map(|(global, _)| {
+            let capabilities = state.seat_info(*global).capabilities();
+            if capabilities.is_empty() {
+                seat_name(*global)
+            } else {
+                format!("{} ({})", seat_name(*global), capabilities.join(", "))
+            }
         })
-        .context("failed to install Ctrl+C handler")?;
+        .collect::<Vec<_>>()
+        .join(", ");
+    let chosen = match options.seat_name.as_deref() {
+        Some(requested) => seats.iter().find(|(global, _)| {
+            state.seat_names_by_global.get(global).map(String::as_str) == Some(requested)
+        }),
+        None => seats.first(),
+    };
+    let Some((seat_global, seat)) = chosen else {
+        checks.push(match options.seat_name.as_deref() {
+            _ if seats.is_empty() => DoctorCheck::fail(
+                "seats",
+                "the compositor advertises no wl_seat",
+                Some("the compositor has no input seat to type on".to_string()),
+            ),
+            Some(requested) => DoctorCheck::fail(
+                "seats",
+                format!("no seat named {requested:?} among {listed}"),
+                Some("pass one of the listed seats to --seat".to_string()),
+            ),
+            None => unreachable!("the first seat exists"),
+        });
+        return checks;
+    };
+    checks.push(DoctorCheck::pass(
+        "seats",
+        format!("{listed}; typing on {}", seat_name(*seat_global)),
+    ));
+
+    checks.push(if state.seat_has_keyboard(*seat_global) {
+        let session_keyboard = seat.get_keyboard(&qh, ());
+        let _ = event_queue.roundtrip(&mut state);
+        if session_keyboard.version() >= 3 {
+            session_keyboard.release();
+        }
+        layout_check(state.session_keymap.as_deref(), plan)
+    } else {
+        DoctorCheck::skip(
+            "keyboard layout",
+            "the seat has no keyboard to read the session layout from",
+        )
+    });
+
+    checks.push(if is_advertised("zwlr_foreign_toplevel_manager_v1") {
+        match focused_app_wayland() {
+            Ok(Some(app)) => DoctorCheck::pass("focus", format!("{app} is focused")),
+            Ok(None) => DoctorCheck::warn(
+                "focus",
+                "no focused application reported",
+                "focus the target editor before the countdown ends",
+            ),
+            Err(err) => DoctorCheck::warn(
+                "focus",
+                format!("could not read the focused application: {err:#}"),
+                "--profile auto falls back to the compatible profile",
+            ),
+        }
+    } else {
+        DoctorCheck::warn(
+            "focus",
+            "the compositor does not report the focused application \
+             (no zwlr_foreign_toplevel_manager_v1)",
+            "playback still types into whatever is focused; --profile auto falls back to the \
+             compatible profile, so name the editor's profile instead",
+        )
+    });
+
+    checks.push(if is_advertised("zwp_idle_inhibit_manager_v1") {
+        DoctorCheck::pass("idle inhibit", "zwp_idle_inhibit_manager_v1")
+    } else {
+        DoctorCheck::warn(
+            "idle inhibit",
+            "the compositor offers no idle inhibitor (zwp_idle_inhibit_manager_v1)",
+            "if the screen locks during long pauses, raise the idle timeout for the playback",
+        )
+    });
+
+    checks.push(xwayland_focus_check());
+    checks
+}
+
+/// The compositor, as far as its globals and the session environment tell.
+fn compositor_check(compositor: Compositor) -> DoctorCheck {
+    DoctorCheck::pass("compositor", compositor.name())
+}
+
+/// The session layout against the plan's (or just the session's, without a plan).
+fn layout_check(session_keymap: Option<&str>, plan: Option<&PlanConfig>) -> DoctorCheck {
+    let Some(session) = session_keymap.and_then(keymap_layout_name) else {
+        return DoctorCheck::skip("keyboard layout", "the seat sent no readable keymap");
+    };
+    let Some(planned) = plan.and_then(|config| keymap_layout_name(&config.keymap)) else {
+        return DoctorCheck::pass("keyboard layout", format!("session layout \"{session}\""));
+    };
+    if session == planned {
+        DoctorCheck::pass("keyboard layout", format!("\"{session}\", as planned"))
+    } else {
+        DoctorCheck::warn(
+            "keyboard layout",
+            format!("session layout is \"{session}\" but the plan was generated for \"{planned}\""),
+            "the plan's keymap is sent with its keys, but clients that ignore it (e.g. some \
+             XWayland apps) type the wrong characters; switch the session layout to match",
+        )
     }
+}
+
+/// Whether an XWayland app has the focus, which the virtual keyboard types into less reliably.
+fn xwayland_focus_check() -> DoctorCheck {
+    #[cfg(feature = "x11")]
+    if std::env::var_os("DISPLAY").is_some_and(|display| !display.is_empty()) {
+        return match crate::playback::backends::x11::focused_app_x11() {
+            Ok(Some(app)) => DoctorCheck::warn(
+                "xwayland",
+                format!("the focused window belongs to {app}, an XWayland app"),
+                "if characters or modifiers come out wrong, play with --backend x11 (or --xwayland)",
+            ),
+            Ok(None) => DoctorCheck::pass("xwayland", "no XWayland window is focused"),
+            Err(err) => DoctorCheck::skip(
+                "xwayland",
+                format!("could not check the Xwayland focus: {err:#}"),
+            ),
+        };
+    }
+    DoctorCheck::skip("xwayland", "DISPLAY is not set or X11 support is disabled")
+}
 
+pub fn play_plan_wayland(
+    plan: &Plan,
+    options: &PlaybackOptions,
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<()> {
+    play_wayland(PlaybackSource::plan(plan), options, cancel, observer)
+}
+
+pub(crate) fn play_wayland(
+    source: PlaybackSource<'_>,
+    options: &PlaybackOptions,
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<()> {
     let conn = Connection::connect_to_env().context("failed to connect to Wayland")?;
     let (globals, mut event_queue) =
         registry_queue_init(&conn).context("failed to init Wayland registry")?;
     let qh = event_queue.handle();
     let mut state = State::default();
 
+    let advertised: Vec<_> = globals
+        .contents()
+        .clone_list()
+        .into_iter()
+        .map(|g| (g.interface, g.version))
+        .collect();
+    require_virtual_keyboard(&advertised)?;
+    let compositor = Compositor::identify(&advertised, |name| std::env::var(name).ok());
+    tracing::debug!(compositor = compositor.name(), "identified the compositor");
     let manager: ZwpVirtualKeyboardManagerV1 = globals
         .bind(&qh, 1..=1, ())
-        .context("zwp_virtual_keyboard_manager_v1 not available (is sway/wlroots exposing it?)")?;
+        .with_context(|| format!("failed to bind {VIRTUAL_KEYBOARD_MANAGER}"))?;
 
     let seat_globals: Vec<_> = globals
         .contents()
@@ -143,21 +667,9 @@ pub fn play_plan_wayland(
         return Err(anyhow!("wl_seat not available (no seats advertised)"));
     }
 
-    let seat: wl_seat::WlSeat = match seat_name {
+    let seat: wl_seat::WlSeat = match options.seat_name.as_deref() {
         Some(requested) => {
-            let mut seats = Vec::with_capacity(seat_globals.len());
-            for g in seat_globals.iter() {
-                let version = g.version.min(7);
-                let seat: wl_seat::WlSeat = globals.registry().bind(
-                    g.name,
-                    version,
-                    &qh,
-                    SeatData {
-                        global_name: g.name,
-                    },
-                );
-                seats.push((g.name, seat));
-            }
+            let seats = bind_seats(&globals, &qh);
 
             event_queue
                 .roundtrip(&mut state)
@@ -186,10 +698,11 @@ pub fn play_plan_wayland(
                     ));
                 }
 
-                return Err(anyhow!(
-                    "requested seat {requested:?} not found; available seats: {}",
-                    names.join(", ")
-                ));
+                return Err(DrafterError::SeatNotFound {
+                    requested: requested.to_string(),
+                    available: names,
+                }
+                .into());
             }
         }
         None => {
@@ -212,103 +725,128 @@ pub fn play_plan_wayland(
         .roundtrip(&mut state)
         .context("Wayland roundtrip failed")?;
 
-    let (keymap_fd, keymap_size) = make_keymap_fd(&plan.config.keymap)?;
-    keyboard.keymap(plan.config.keymap_format, keymap_fd.as_fd(), keymap_size);
+    let seat_global = seat.data::<SeatData>().map(|data| data.global_name);
+    let seat_has_keyboard = seat_global.is_some_and(|global| state.seat_has_keyboard(global));
+    if seat_has_keyboard {
+        let session_keyboard = seat.get_keyboard(&qh, ());
+        event_queue
+            .roundtrip(&mut state)
+            .context("Wayland roundtrip (session keymap) failed")?;
+        if session_keyboard.version() >= 3 {
+            session_keyboard.release();
+        }
+        warn_on_session_layout_mismatch(state.session_keymap.as_deref(), source.config);
+    }
+
+    let (keymap_fd, keymap_size) = make_keymap_fd(&source.config.keymap)?;
+    keyboard.keymap(source.config.keymap_format, keymap_fd.as_fd(), keymap_size);
 
     conn.flush().context("Wayland flush failed")?;
 
-    if countdown_secs > 0 {
-        eprintln!("Focus the target editor window. Starting in {countdown_secs}s...");
-        for remaining in (1..=countdown_secs).rev() {
-            if stop.load(Ordering::SeqCst) {
-                return Err(anyhow!("aborted"));
-            }
-            eprintln!("{remaining}...");
-            sleep_interruptible(stop.as_ref(), 1000);
-        }
-        if stop.load(Ordering::SeqCst) {
-            return Err(anyhow!("aborted"));
-        }
+    countdown(cancel, &mut SystemClock, options.countdown_secs, observer)?;
+
+    if options.detect_focus {
+        report_focused_app(focused_app_wayland(), observer);
     }
+    warn_on_xwayland_target(options);
+
+    tracing::info!(
+        backend = "wayland",
+        actions = source.total_actions,
+        "playback started"
+    );
+    let mut sink = WaylandSink {
+        conn: &conn,
+        keyboard: &keyboard,
+        start: Instant::now(),
+    };
+    play_actions(
+        source.with_options(options),
+        &mut sink,
+        &mut SystemClock,
+        cancel,
+        observer,
+    )
+}
 
-    let trace_events = trace.then(|| plan_console_trace(&plan.actions));
-    let mut next_trace_event = 0usize;
+/// Sends keys through a virtual keyboard, flushing each.
+struct WaylandSink<'a> {
+    conn: &'a Connection,
+    keyboard: &'a ZwpVirtualKeyboardV1,
+    start: Instant,
+}
 
-    let start = Instant::now();
+impl WaylandSink<'_> {
+    fn time_ms(&self) -> u32 {
+        self.start
+            .elapsed()
+            .as_millis()
+            .try_into()
+            .unwrap_or(u32::MAX)
+    }
+}
 
-    let reset_modifiers_best_effort = |keyboard: &ZwpVirtualKeyboardV1| {
-        keyboard.modifiers(0, 0, 0, 0);
+impl KeySink for WaylandSink<'_> {
+    fn send_key(&mut self, keycode: u32, state: KeyState) -> Result<()> {
+        self.keyboard
+            .key(self.time_ms(), keycode, key_state_to_u32(state));
+        self.conn.flush().with_context(|| {
+            format!("Wayland flush failed (key keycode={keycode} state={state:?})")
+        })
+    }
 
-        let t = start.elapsed().as_millis();
-        let time_ms: u32 = t.try_into().unwrap_or(u32::MAX);
+    fn send_modifiers(
+        &mut self,
+        mods_depressed: u32,
+        mods_latched: u32,
+        mods_locked: u32,
+        group: u32,
+    ) -> Result<()> {
+        self.keyboard
+            .modifiers(mods_depressed, mods_latched, mods_locked, group);
+        self.conn
+            .flush()
+            .context("Wayland flush failed (modifiers)")
+    }
+
+    fn reset(&mut self) {
+        self.keyboard.modifiers(0, 0, 0, 0);
 
         // Best-effort releases. We may send releases even if not down; this is intended to
         // reduce the chance of leaving stuck modifiers if playback is aborted mid-run.
+        let time_ms = self.time_ms();
         for keycode in super::COMMON_MODIFIER_KEYCODES {
-            keyboard.key(time_ms, keycode, 0);
+            self.keyboard.key(time_ms, keycode, 0);
         }
-    };
+        let _ = self.conn.flush();
+    }
+}
 
-    for (action_index, action) in plan.actions.iter().enumerate() {
-        if stop.load(Ordering::SeqCst) {
-            break;
-        }
+#[cfg(test)]
+mod tests {
+    use super::require_virtual_keyboard;
 
-        if let Some(events) = &trace_events {
-            while next_trace_event < events.len()
-                && events[next_trace_event].action_index == action_index
-            {
-                print_trace_line(&events[next_trace_event].line);
-                next_trace_event += 1;
-            }
-        }
-
-        match action {
-            Action::Wait { ms } => {
-                sleep_interruptible(stop.as_ref(), *ms);
-            }
-            Action::Modifiers {
-                mods_depressed,
-                mods_latched,
-                mods_locked,
-                group,
-            } => {
-                keyboard.modifiers(*mods_depressed, *mods_latched, *mods_locked, *group);
-                if let Err(e) = conn.flush().with_context(|| {
-                    format!("Wayland flush failed (action_index={action_index}, action=modifiers)")
-                }) {
-                    eprintln!("Playback error. Attempting to reset modifiers...");
-                    reset_modifiers_best_effort(&keyboard);
-                    let _ = conn.flush();
-                    return Err(e);
-                }
-            }
-            Action::Key { keycode, state } => {
-                let t = start.elapsed().as_millis();
-                let time_ms: u32 = t.try_into().unwrap_or(u32::MAX);
-                keyboard.key(time_ms, *keycode, key_state_to_u32(*state));
-                if let Err(e) = conn.flush().with_context(|| {
-                    format!(
-                        "Wayland flush failed (action_index={action_index}, action=key keycode={keycode} state={state:?})"
-                    )
-                }) {
-                    eprintln!("Playback error. Attempting to reset modifiers...");
-                    reset_modifiers_best_effort(&keyboard);
-                    let _ = conn.flush();
-                    return Err(e);
-                }
-            }
-        }
+    fn globals(interfaces: &[(&str, u32)]) -> Vec<(String, u32)> {
+        interfaces
+            .iter()
+            .map(|(interface, version)| (interface.to_string(), *version))
+            .collect()
     }
 
-    if stop.load(Ordering::SeqCst) {
-        eprintln!("Aborted. Attempting to reset modifiers...");
-        reset_modifiers_best_effort(&keyboard);
-        conn.flush().ok();
-        return Err(anyhow!("aborted"));
+    #[test]
+    fn accepts_compositors_with_a_virtual_keyboard() {
+        let advertised = globals(&[("wl_seat", 7), ("zwp_virtual_keyboard_manager_v1", 1)]);
+        assert!(require_virtual_keyboard(&advertised).is_ok());
     }
 
-    conn.flush().ok();
-
-    Ok(())
+    #[test]
+    fn explains_compositors_without_a_virtual_keyboard() {
+        let err = require_virtual_keyboard(&globals(&[("wl_seat", 9)])).unwrap_err();
+        let message = format!("{err:#}");
+        assert!(
+            message.contains("zwp_virtual_keyboard_manager_v1"),
+            "{message}"
+        );
+        assert!(message.contains("--backend x11"), "{message}");
+    }
 }
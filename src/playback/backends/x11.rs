@@ -1,17 +1,19 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
-
 use anyhow::{anyhow, Context, Result};
 use x11rb::connection::Connection;
 use x11rb::protocol::xproto::{ConnectionExt as _, GetInputFocusReply};
 use x11rb::protocol::xtest::ConnectionExt as _;
 use x11rb::protocol::{xproto, xtest};
 
-use crate::model::{Action, KeyState, Plan};
-use crate::playback::util::{print_trace_line, sleep_interruptible};
-use crate::trace::plan_console_trace;
+use crate::geometry::Geometry;
+use crate::keyboard::{KEY_102ND, KEY_CAPSLOCK};
+use crate::keymap::LayoutSpec;
+use crate::model::{Action, KeyState, Plan, PlanConfig};
+use crate::playback::doctor::DoctorCheck;
+use crate::playback::sink::{play_actions, PlaybackSource};
+use crate::playback::util::{countdown, layout_mismatch, report_focused_app};
+use crate::playback::{
+    CancellationToken, FocusedApp, KeySink, PlaybackObserver, PlaybackOptions, SystemClock,
+};
 
 fn evdev_to_x11_keycode(evdev_keycode: u32) -> Result<u8> {
     // On most Linux Xorg setups, X11 keycodes are evdev + 8.
@@ -49,6 +51,10 @@ fn query_xtest(conn: &impl Connection) -> Result<()> {
     Ok(())
 }
 
+// X11 special focus value: PointerRoot means the focused window follows the pointer.
+// (`focus` is a `Window` newtype in the protocol, but x11rb models it as `u32`.)
+const POINTER_ROOT: xproto::Window = 1;
+
 fn get_focus(conn: &impl Connection) -> Result<GetInputFocusReply> {
     conn.get_input_focus()
         .context("failed to request input focus")?
@@ -56,6 +62,59 @@ fn get_focus(conn: &impl Connection) -> Result<GetInputFocusReply> {
         .context("failed to read input focus reply")
 }
 
+/// `WM_CLASS` of `window`, or of its nearest ancestor that has one: focus often rests on a
+/// child window of the application's toplevel.
+fn window_app(conn: &impl Connection, window: xproto::Window) -> Result<Option<FocusedApp>> {
+    let mut window = window;
+    loop {
+        let reply = conn
+            .get_property(
+                false,
+                window,
+                xproto::AtomEnum::WM_CLASS,
+                xproto::AtomEnum::STRING,
+                0,
+                256,
+            )
+            .context("failed to send GetProperty request")?
+            .reply()
+            .context("failed to read WM_CLASS")?;
+        let app_ids = parse_wm_class(&reply.value);
+        if !app_ids.is_empty() {
+            return Ok(Some(FocusedApp { app_ids }));
+        }
+
+        let tree = conn
+            .query_tree(window)
+            .context("failed to send QueryTree request")?
+            .reply()
+            .context("failed to read the window tree")?;
+        if tree.parent == x11rb::NONE || window == tree.root {
+            return Ok(None);
+        }
+        window = tree.parent;
+    }
+}
+
+/// Parse `WM_CLASS` (NUL-terminated instance and class names).
+fn parse_wm_class(value: &[u8]) -> Vec<String> {
+    value
+        .split(|b| *b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect()
+}
+
+/// The application owning the window with the X11 input focus.
+pub fn focused_app_x11() -> Result<Option<FocusedApp>> {
+    let (conn, _) = x11rb::connect(None).context("failed to connect to X11")?;
+    let focus = get_focus(&conn)?;
+    if focus.focus == x11rb::NONE || focus.focus == POINTER_ROOT {
+        return Ok(None);
+    }
+    window_app(&conn, focus.focus)
+}
+
 fn keysym_for_keycode(conn: &impl Connection, keycode: u8, index: usize) -> Result<xproto::Keysym> {
     let reply = conn
         .get_keyboard_mapping(keycode, 1)
@@ -214,6 +273,121 @@ fn validate_us_keymap(conn: &impl Connection) -> Result<()> {
     Ok(())
 }
 
+/// Read the server's active layout from the `_XKB_RULES_NAMES` root window property.
+///
+/// Only the first layout group is returned. Returns `None` when the property is missing.
+fn session_layout(conn: &impl Connection, root: xproto::Window) -> Result<Option<LayoutSpec>> {
+    let atom = conn
+        .intern_atom(true, b"_XKB_RULES_NAMES")
+        .context("failed to send InternAtom request")?
+        .reply()
+        .context("failed to intern _XKB_RULES_NAMES")?
+        .atom;
+    if atom == x11rb::NONE {
+        return Ok(None);
+    }
+
+    let reply = conn
+        .get_property(false, root, atom, xproto::AtomEnum::STRING, 0, 1024)
+        .context("failed to send GetProperty request")?
+        .reply()
+        .context("failed to read _XKB_RULES_NAMES")?;
+
+    Ok(parse_rules_names(&reply.value))
+}
+
+/// Parse `_XKB_RULES_NAMES` (NUL-separated rules, model, layout, variant, options).
+fn parse_rules_names(value: &[u8]) -> Option<LayoutSpec> {
+    let fields: Vec<String> = value
+        .split(|b| *b == 0)
+        .map(|field| String::from_utf8_lossy(field).into_owned())
+        .collect();
+    let first_group = |idx: usize| {
+        fields
+            .get(idx)
+            .and_then(|field| field.split(',').next())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let layout = first_group(2);
+    if layout.is_empty() {
+        return None;
+    }
+
+    Some(LayoutSpec {
+        layout,
+        variant: first_group(3),
+        options: None,
+        geometry: Geometry::from_xkb_model(&first_group(1)),
+    })
+}
+
+fn plan_uses_102nd_key(plan: &Plan) -> bool {
+    plan.actions.iter().any(|a| {
+        matches!(a, Action::Key { keycode, .. } | Action::KeyRepeat { keycode, .. }
+                if *keycode == KEY_102ND)
+    })
+}
+
+/// Why keys planned for `layout` (a [`PlanConfig::layout`] label) come out differently on a
+/// server with the `session` layout, if they do.
+fn layout_mismatch_reason(session: &LayoutSpec, layout: &str) -> Option<String> {
+    let expected = LayoutSpec::from_label(layout);
+    (session.layout != expected.layout || session.variant != expected.variant).then(|| {
+        format!(
+            "X server keyboard layout is `{}` but the plan was generated for `{}`",
+            session.label(),
+            expected.label()
+        )
+    })
+}
+
+/// X11 has no per-client keymap, so the server's layout must match the plan's. `plan`, when
+/// the whole plan is known, is scanned for keys the server's keyboard model may lack.
+fn check_session_layout(
+    conn: &impl Connection,
+    root: xproto::Window,
+    layout: &str,
+    plan: Option<&Plan>,
+    force: bool,
+) -> Result<()> {
+    if layout.ends_with("+custom") {
+        return Err(anyhow!(
+            "this plan uses a custom keymap, which X11 playback cannot load; use the Wayland backend"
+        ));
+    }
+
+    let expected = LayoutSpec::from_label(layout);
+    match session_layout(conn, root)? {
+        Some(session) => {
+            if let Some(reason) = layout_mismatch_reason(&session, layout) {
+                layout_mismatch(&reason, force)?;
+            } else if session.geometry == Geometry::Ansi && plan.is_some_and(plan_uses_102nd_key) {
+                // A streamed plan is not scanned ahead for the key.
+                eprintln!(
+                    "warning: the X server keyboard model is ANSI, but the plan types the ISO key between left Shift and Z; re-plan with `--geometry ansi` if that key is missing"
+                );
+                tracing::warn!("X server keyboard model is ANSI but the plan types the ISO key");
+            }
+        }
+        None => {
+            eprintln!(
+                "warning: could not determine the X server keyboard layout (_XKB_RULES_NAMES is not set)"
+            );
+            tracing::warn!("could not determine the X server keyboard layout");
+        }
+    }
+
+    if expected.layout == "us" && expected.variant.is_empty() {
+        if let Err(err) = validate_us_keymap(conn) {
+            layout_mismatch(&format!("{err:#}"), force)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn xtest_key(
     conn: &impl Connection,
     root: xproto::Window,
@@ -243,19 +417,173 @@ fn reset_common_modifiers_best_effort(conn: &impl Connection, root: xproto::Wind
     let _ = conn.flush();
 }
 
-pub fn play_plan_x11(plan: &Plan, countdown_secs: u64, trace: bool) -> Result<()> {
-    let stop = Arc::new(AtomicBool::new(false));
-    {
-        let stop = stop.clone();
-        ctrlc::set_handler(move || {
-            stop.store(true, Ordering::SeqCst);
-        })
-        .context("failed to install Ctrl+C handler")?;
+fn toggle_caps_lock_best_effort(conn: &impl Connection, root: xproto::Window) {
+    if let Ok(code) = evdev_to_x11_keycode(KEY_CAPSLOCK) {
+        let _ = xtest_key(conn, root, code, KeyState::Pressed);
+        let _ = xtest_key(conn, root, code, KeyState::Released);
     }
+    let _ = conn.flush();
+}
 
+/// Whether the X server can be reached and offers XTEST, for
+/// [`crate::playback::preflight_backend_chain`].
+pub(crate) fn probe_x11() -> Result<()> {
+    let (conn, _) = x11rb::connect(None).context("failed to connect to X11")?;
+    query_xtest(&conn)
+}
+
+/// The checks of `drafter doctor` for the X11 backend; see [`crate::playback::doctor`].
+pub(crate) fn diagnose_x11(plan: Option<&PlanConfig>) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+    let (conn, screen_num) = match x11rb::connect(None) {
+        Ok(connected) => connected,
+        Err(err) => {
+            checks.push(DoctorCheck::fail(
+                "x11 connection",
+                format!("failed to connect to X11: {err}"),
+                Some("check that DISPLAY names a running X server".to_string()),
+            ));
+            return checks;
+        }
+    };
+    let Some(root) = conn.setup().roots.get(screen_num).map(|screen| screen.root) else {
+        checks.push(DoctorCheck::fail(
+            "x11 connection",
+            "invalid X11 screen index",
+            None,
+        ));
+        return checks;
+    };
+    checks.push(DoctorCheck::pass("x11 connection", "connected"));
+
+    if let Err(err) = query_xtest(&conn) {
+        checks.push(DoctorCheck::fail(
+            "xtest",
+            format!("{err:#}"),
+            Some("enable the XTEST extension in the X server configuration".to_string()),
+        ));
+        return checks;
+    }
+    let version = conn
+        .xtest_get_version(2, 2)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .map(|reply| format!(" {}.{}", reply.major_version, reply.minor_version))
+        .unwrap_or_default();
+    checks.push(DoctorCheck::pass("xtest", format!("XTEST{version}")));
+
+    checks.push(layout_check(&conn, root, plan));
+
+    checks.push(match get_focus(&conn) {
+        Ok(focus) if focus.focus == x11rb::NONE || focus.focus == POINTER_ROOT => {
+            DoctorCheck::fail(
+                "focus",
+                "no window has explicit input focus (PointerRoot or none)",
+                Some(
+                    "click into the target editor window before the countdown ends; \
+                     focus-follows-mouse setups leave the focus at PointerRoot"
+                        .to_string(),
+                ),
+            )
+        }
+        Ok(focus) => match window_app(&conn, focus.focus) {
+            Ok(Some(app)) => DoctorCheck::pass("focus", format!("{app} is focused")),
+            _ => DoctorCheck::pass("focus", format!("window {:#x} is focused", focus.focus)),
+        },
+        Err(err) => DoctorCheck::fail("focus", format!("{err:#}"), None),
+    });
+
+    // The X server counts fake input as user activity, resetting the screen saver.
+    checks.push(DoctorCheck::pass(
+        "idle inhibit",
+        "XTEST key events reset the screen saver timer",
+    ));
+    checks
+}
+
+/// The server layout against the plan's, as playback checks it (or just the server's, without
+/// a plan).
+fn layout_check(
+    conn: &impl Connection,
+    root: xproto::Window,
+    plan: Option<&PlanConfig>,
+) -> DoctorCheck {
+    let session =
+        match session_layout(conn, root) {
+            Ok(Some(session)) => session,
+            Ok(None) => return DoctorCheck::warn(
+                "keyboard layout",
+                "could not determine the X server keyboard layout (_XKB_RULES_NAMES is not set)",
+                "make sure the server layout matches the plan's, e.g. with setxkbmap",
+            ),
+            Err(err) => return DoctorCheck::fail("keyboard layout", format!("{err:#}"), None),
+        };
+    let Some(config) = plan else {
+        return DoctorCheck::pass(
+            "keyboard layout",
+            format!("X server layout `{}`", session.label()),
+        );
+    };
+    if config.layout.ends_with("+custom") {
+        return DoctorCheck::fail(
+            "keyboard layout",
+            "the plan uses a custom keymap, which X11 playback cannot load",
+            Some("play it with --backend wayland".to_string()),
+        );
+    }
+    let expected = LayoutSpec::from_label(&config.layout);
+    if let Some(reason) = layout_mismatch_reason(&session, &config.layout) {
+        return DoctorCheck::fail(
+            "keyboard layout",
+            reason,
+            Some(format!(
+                "run `setxkbmap {}` or re-plan for the server layout (--force plays anyway)",
+                [expected.layout.as_str(), expected.variant.as_str()]
+                    .join(" ")
+                    .trim_end()
+            )),
+        );
+    }
+    if expected.layout == "us" && expected.variant.is_empty() {
+        if let Err(err) = validate_us_keymap(conn) {
+            return DoctorCheck::fail("keyboard layout", format!("{err:#}"), None);
+        }
+    }
+    DoctorCheck::pass(
+        "keyboard layout",
+        format!("`{}`, as planned", session.label()),
+    )
+}
+
+/// [`crate::playback::preflight_layout`] for X11.
+pub(crate) fn preflight_layout_x11(layout: &str, force: bool) -> Result<()> {
+    let (conn, screen_num) = x11rb::connect(None).context("failed to connect to X11")?;
+    let root = conn
+        .setup()
+        .roots
+        .get(screen_num)
+        .ok_or_else(|| anyhow!("invalid X11 screen index"))?
+        .root;
+    check_session_layout(&conn, root, layout, None, force)
+}
+
+pub fn play_plan_x11(
+    plan: &Plan,
+    options: &PlaybackOptions,
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<()> {
+    play_x11(PlaybackSource::plan(plan), options, cancel, observer)
+}
+
+pub(crate) fn play_x11(
+    source: PlaybackSource<'_>,
+    options: &PlaybackOptions,
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<()> {
     let (conn, screen_num) = x11rb::connect(None).context("failed to connect to X11")?;
     query_xtest(&conn)?;
-    validate_us_keymap(&conn)?;
 
     let setup = conn.setup();
     let screen = setup
@@ -263,25 +591,18 @@ pub fn play_plan_x11(plan: &Plan, countdown_secs: u64, trace: bool) -> Result<()
         .get(screen_num)
         .ok_or_else(|| anyhow!("invalid X11 screen index"))?;
 
-    if countdown_secs > 0 {
-        eprintln!("Focus the target editor window. Starting in {countdown_secs}s...");
-        for remaining in (1..=countdown_secs).rev() {
-            if stop.load(Ordering::SeqCst) {
-                return Err(anyhow!("aborted"));
-            }
-            eprintln!("{remaining}...");
-            sleep_interruptible(stop.as_ref(), 1000);
-        }
-        if stop.load(Ordering::SeqCst) {
-            return Err(anyhow!("aborted"));
-        }
-    }
+    check_session_layout(
+        &conn,
+        screen.root,
+        &source.config.layout,
+        source.plan,
+        options.force,
+    )?;
+
+    countdown(cancel, &mut SystemClock, options.countdown_secs, observer)?;
 
     // Sanity check: require explicit input focus.
     let focus = get_focus(&conn)?;
-    // X11 special focus value: PointerRoot means the focused window follows the pointer.
-    // (`focus` is a `Window` newtype in the protocol, but x11rb models it as `u32`.)
-    const POINTER_ROOT: xproto::Window = 1;
     if focus.focus == x11rb::NONE {
         return Err(anyhow!(
             "no X11 input focus detected; click into the target editor before starting"
@@ -293,52 +614,110 @@ pub fn play_plan_x11(plan: &Plan, countdown_secs: u64, trace: bool) -> Result<()
         ));
     }
 
+    if options.detect_focus {
+        report_focused_app(window_app(&conn, focus.focus), observer);
+    }
+
     // Unlike Wayland, X11 has no way to set per-client modifier state. Reset common modifiers
     // to try to start from a neutral state (e.g. if a previous run was aborted).
     reset_common_modifiers_best_effort(&conn, screen.root);
 
-    let trace_events = trace.then(|| plan_console_trace(&plan.actions));
-    let mut next_trace_event = 0usize;
+    tracing::info!(
+        backend = "x11",
+        actions = source.total_actions,
+        "playback started"
+    );
+    let mut sink = X11Sink {
+        conn: &conn,
+        root: screen.root,
+        caps_lock_toggled: false,
+    };
+    play_actions(
+        source.with_options(options),
+        &mut sink,
+        &mut SystemClock,
+        cancel,
+        observer,
+    )
+}
 
-    for (action_index, action) in plan.actions.iter().enumerate() {
-        if stop.load(Ordering::SeqCst) {
-            break;
-        }
+/// Sends keys through XTEST, flushing each.
+struct X11Sink<'a, C: Connection> {
+    conn: &'a C,
+    root: xproto::Window,
+    /// CapsLock is a server-side lock on X11; whether the plan left it toggled.
+    caps_lock_toggled: bool,
+}
 
-        if let Some(events) = &trace_events {
-            while next_trace_event < events.len()
-                && events[next_trace_event].action_index == action_index
-            {
-                print_trace_line(&events[next_trace_event].line);
-                next_trace_event += 1;
-            }
+impl<C: Connection> KeySink for X11Sink<'_, C> {
+    fn send_key(&mut self, keycode: u32, state: KeyState) -> Result<()> {
+        let x11_keycode = evdev_to_x11_keycode(keycode)?;
+        if keycode == KEY_CAPSLOCK && state == KeyState::Pressed {
+            self.caps_lock_toggled = !self.caps_lock_toggled;
         }
 
-        match action {
-            Action::Wait { ms } => {
-                sleep_interruptible(stop.as_ref(), *ms);
-            }
-            Action::Modifiers { .. } => {
-                // No-op on X11. We rely on explicit modifier key presses/releases.
-            }
-            Action::Key { keycode, state } => {
-                let x11_keycode = evdev_to_x11_keycode(*keycode)?;
+        // Note: we don't attempt to set timestamps; XTEST supports CURRENT_TIME.
+        xtest_key(self.conn, self.root, x11_keycode, state)?;
+        self.conn
+            .flush()
+            .context("failed to flush X11 connection")?;
+        Ok(())
+    }
 
-                // Note: we don't attempt to set timestamps; XTEST supports CURRENT_TIME.
-                xtest_key(&conn, screen.root, x11_keycode, *state)?;
-                conn.flush().context("failed to flush X11 connection")?;
-            }
-        }
+    fn send_modifiers(&mut self, _: u32, _: u32, _: u32, _: u32) -> Result<()> {
+        // No-op on X11. We rely on explicit modifier key presses/releases.
+        Ok(())
     }
 
-    if stop.load(Ordering::SeqCst) {
-        eprintln!("Aborted. Attempting to reset modifiers...");
+    fn reset(&mut self) {
+        reset_common_modifiers_best_effort(self.conn, self.root);
+        if self.caps_lock_toggled {
+            toggle_caps_lock_best_effort(self.conn, self.root);
+        }
+    }
+}
 
-        reset_common_modifiers_best_effort(&conn, screen.root);
+#[cfg(test)]
+mod tests {
+    use super::{layout_mismatch_reason, parse_rules_names, parse_wm_class};
+    use crate::geometry::Geometry;
+    use crate::keymap::LayoutSpec;
+
+    #[test]
+    fn parses_first_layout_group() {
+        let spec = parse_rules_names(b"evdev\0pc105\0de,us\0nodeadkeys,\0grp:alt_shift_toggle\0")
+            .expect("layout present");
+        assert_eq!(spec.layout, "de");
+        assert_eq!(spec.variant, "nodeadkeys");
+        assert_eq!(spec.geometry, Geometry::Iso);
+
+        let spec = parse_rules_names(b"evdev\0pc104\0us\0\0\0").expect("layout present");
+        assert_eq!(spec.label(), "us");
+        assert_eq!(spec.geometry, Geometry::Ansi);
+
+        assert!(parse_rules_names(b"").is_none());
+    }
 
-        return Err(anyhow!("aborted"));
+    #[test]
+    fn layouts_match_by_layout_and_variant() {
+        let session = LayoutSpec {
+            variant: "nodeadkeys".to_string(),
+            ..LayoutSpec::new("de")
+        };
+        assert_eq!(layout_mismatch_reason(&session, "de(nodeadkeys)"), None);
+        assert_eq!(
+            layout_mismatch_reason(&session, "de").as_deref(),
+            Some(
+                "X server keyboard layout is `de(nodeadkeys)` but the plan was generated for `de`"
+            )
+        );
+        assert!(layout_mismatch_reason(&LayoutSpec::new("us"), "fr").is_some());
     }
 
-    conn.flush().context("failed to flush X11 connection")?;
-    Ok(())
+    #[test]
+    fn parses_wm_class_instance_and_class() {
+        assert_eq!(parse_wm_class(b"gedit\0Gedit\0"), ["gedit", "Gedit"]);
+        assert_eq!(parse_wm_class(b"code\0"), ["code"]);
+        assert!(parse_wm_class(b"").is_empty());
+    }
 }
@@ -0,0 +1,162 @@
+//! Checking the session before a real playback (`drafter doctor`): which backend would play,
+//! and whether the compositor or X server offers what it needs (the virtual keyboard protocol
+//! or XTEST, a seat with a keyboard, a layout matching the plan's, a focused window, idle
+//! inhibition), each as a [`DoctorCheck`] with a hint to fix what fails.
+
+use serde::Serialize;
+
+use super::{backend_unavailable_message, preflight_options, PlaybackBackend, PlaybackOptions};
+use crate::model::PlanConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    /// Playback works, but may go wrong in some applications or lacks a convenience.
+    Warn,
+    /// Playback would fail.
+    Fail,
+    /// Not checked, e.g. the layout when the seat has no keyboard.
+    Skip,
+}
+
+impl CheckStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skip => "SKIP",
+        }
+    }
+}
+
+/// One thing playback needs, e.g. `virtual keyboard`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DoctorCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// What was found.
+    pub detail: String,
+    /// How to fix a failure or warning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl DoctorCheck {
+    pub(crate) fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "wayland", feature = "x11")), allow(dead_code))]
+    pub(crate) fn warn(
+        name: &'static str,
+        detail: impl Into<String>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    pub(crate) fn fail(
+        name: &'static str,
+        detail: impl Into<String>,
+        hint: Option<String>,
+    ) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint,
+        }
+    }
+
+    #[cfg_attr(not(feature = "wayland"), allow(dead_code))]
+    pub(crate) fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Skip,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+}
+
+/// The checks of [`diagnose`], in order. Checks that depend on a failed one are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    /// Whether no check failed.
+    pub fn passed(&self) -> bool {
+        self.failures() == 0
+    }
+
+    pub fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count()
+    }
+}
+
+/// Checks what playing with `options` needs, without typing anything. With `plan`, the session
+/// layout is compared with the plan's.
+pub fn diagnose(options: &PlaybackOptions, plan: Option<&PlanConfig>) -> DoctorReport {
+    let mut checks = vec![session_check()];
+    let backend = match preflight_options(options) {
+        Ok(backend) => backend,
+        Err(err) => {
+            checks.push(DoctorCheck::fail("backend", format!("{err:#}"), None));
+            return DoctorReport { checks };
+        }
+    };
+
+    match backend {
+        PlaybackBackend::Wayland => {
+            checks.push(DoctorCheck::pass("backend", "wayland (virtual keyboard)"));
+            #[cfg(feature = "wayland")]
+            checks.extend(super::backends::wayland::diagnose_wayland(options, plan));
+        }
+        PlaybackBackend::X11 => {
+            checks.push(DoctorCheck::pass("backend", "x11 (XTEST)"));
+            #[cfg(feature = "x11")]
+            checks.extend(super::backends::x11::diagnose_x11(plan));
+        }
+        PlaybackBackend::Auto => {}
+    }
+    #[cfg(not(any(feature = "wayland", feature = "x11")))]
+    let _ = plan;
+    DoctorReport { checks }
+}
+
+fn session_check() -> DoctorCheck {
+    let detail = backend_unavailable_message();
+    let graphical = ["WAYLAND_DISPLAY", "WAYLAND_SOCKET", "DISPLAY"]
+        .into_iter()
+        .any(super::env_is_set);
+    if graphical {
+        DoctorCheck::pass("session", detail)
+    } else {
+        DoctorCheck::fail(
+            "session",
+            detail,
+            Some(
+                "run drafter from a terminal inside the graphical session, or export its \
+                 WAYLAND_DISPLAY or DISPLAY"
+                    .to_string(),
+            ),
+        )
+    }
+}
@@ -1,29 +1,203 @@
 pub mod backends;
+pub mod doctor;
+mod observer;
+mod sink;
+mod task;
 mod util;
 
-use anyhow::{anyhow, Result};
+use std::io::BufRead;
 
+use anyhow::anyhow;
+use serde::Serialize;
+
+use crate::error::DrafterError;
 use crate::model::Plan;
+use crate::plan::PlanStream;
+use crate::trace::{console_trace_for_plan, ConsoleTrace, TraceLevel, TraceLine};
+
+use observer::Observed;
+pub(crate) use sink::clamp_waits;
+use sink::PlaybackSource;
+
+pub use observer::{ConsoleObserver, PlaybackObserver};
+pub use sink::{Clock, KeySink, SystemClock, FAST_FORWARD_WAIT_MS};
+pub use task::{ctrl_c_token, CancellationToken, NextEvent, PlaybackTask};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum PlaybackBackend {
+    #[default]
     Auto,
     Wayland,
     X11,
 }
 
+impl PlaybackBackend {
+    /// The name `--backend` takes.
+    pub fn name(self) -> &'static str {
+        match self {
+            PlaybackBackend::Auto => "auto",
+            PlaybackBackend::Wayland => "wayland",
+            PlaybackBackend::X11 => "x11",
+        }
+    }
+}
+
+/// The backend [`preflight_backend_chain`] picked, and the ones before it that it skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendChoice {
+    pub backend: PlaybackBackend,
+    /// Each backend passed over, as requested, with why it cannot play.
+    pub skipped: Vec<(PlaybackBackend, String)>,
+}
+
+/// How [`play_plan`], [`play_plan_with`] and [`play_plan_async`] play a plan.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaybackOptions {
+    /// Seconds to count down for the user to focus the editor before the first key.
+    pub countdown_secs: u64,
+    /// Report the console trace at this level to [`PlaybackObserver::on_trace_event`].
+    pub trace: Option<TraceLevel>,
+    /// Wayland seat to type on; the first one if `None`.
+    pub seat_name: Option<String>,
+    pub backend: PlaybackBackend,
+    /// Play even if the session keyboard layout does not match the plan's (X11).
+    pub force: bool,
+    /// Detect the focused application after the countdown and report it to
+    /// [`PlaybackObserver::on_focus`].
+    pub detect_focus: bool,
+    /// The target application runs under XWayland; see [`resolve_backend_for`].
+    pub xwayland_target: bool,
+    /// Cut every wait (and so every key hold and pause) to [`FAST_FORWARD_WAIT_MS`], to check
+    /// a plan types the right text end to end in seconds before the realistic run.
+    pub fast_forward: bool,
+    /// Cut every wait longer than this many milliseconds to it, e.g. to sit through shorter
+    /// thinking pauses while testing; shorter waits, and so the typing rhythm, are kept.
+    pub max_wait_ms: Option<u64>,
+}
+
+impl PlaybackOptions {
+    /// How long `plan` takes to play with these options, after the countdown: its waits, cut
+    /// by [`PlaybackOptions::fast_forward`] or [`PlaybackOptions::max_wait_ms`].
+    pub fn played_ms(&self, plan: &Plan) -> u64 {
+        PlaybackSource::plan(plan).with_options(self).total_ms
+    }
+}
+
+/// Parses a playback length such as `--max-duration`'s: a number of `ms`, `s`, `m` or `h`,
+/// above zero.
+pub fn parse_duration_ms(arg: &str) -> Result<u64, String> {
+    let invalid = || format!("expected a duration such as 45m, 2h, 90s or 1500ms, got {arg:?}");
+    let (number, unit_ms) = if let Some(ms) = arg.strip_suffix("ms") {
+        (ms, 1.0)
+    } else if let Some(secs) = arg.strip_suffix('s') {
+        (secs, 1000.0)
+    } else if let Some(mins) = arg.strip_suffix('m') {
+        (mins, 60_000.0)
+    } else if let Some(hours) = arg.strip_suffix('h') {
+        (hours, 3_600_000.0)
+    } else {
+        return Err(invalid());
+    };
+    match number.parse::<f64>() {
+        Ok(value) if value.is_finite() && value > 0.0 => Ok((value * unit_ms).round() as u64),
+        _ => Err(invalid()),
+    }
+}
+
+/// Fails when a plan playing for `played_ms` (see [`PlaybackOptions::played_ms`]) is longer
+/// than `max_ms`, `--max-duration`.
+pub fn check_max_duration(played_ms: u64, max_ms: Option<u64>) -> anyhow::Result<()> {
+    match max_ms {
+        Some(max_ms) if played_ms > max_ms => Err(anyhow!(
+            "refusing to play: the plan takes ~{:.1} min, longer than --max-duration ({:.1} min)",
+            (played_ms as f64) / 1000.0 / 60.0,
+            (max_ms as f64) / 1000.0 / 60.0
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// What a [`PlaybackTask`] reports as it happens, as its [`PlaybackObserver`] calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlaybackEvent {
+    /// The countdown before the first key has `remaining_secs` seconds left.
+    Countdown { remaining_secs: u64 },
+    /// The application focused once the countdown is over, with
+    /// [`PlaybackOptions::detect_focus`].
+    Focused(FocusedApp),
+    /// A key event is about to be sent; counts what was played before it.
+    Progress(PlaybackProgress),
+    /// A console trace line, with [`PlaybackOptions::trace`].
+    Trace(TraceLine),
+    /// The playback is about to wait `ms` milliseconds, a pause.
+    Pause { ms: u64 },
+}
+
+/// How much of a plan has been played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaybackProgress {
+    /// Actions played so far, of `total_actions`.
+    pub actions_played: usize,
+    pub total_actions: usize,
+    /// Planned time played so far (the waits), of `total_ms`.
+    pub played_ms: u64,
+    pub total_ms: u64,
+}
+
+/// The application owning the focused window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusedApp {
+    /// The X11 `WM_CLASS` instance and class (e.g. `gedit`, `Gedit`), or the Wayland app id.
+    pub app_ids: Vec<String>,
+}
+
+impl std::fmt::Display for FocusedApp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.app_ids.join("/"))
+    }
+}
+
+/// A Wayland seat, as [`wayland_seats`] lists it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SeatInfo {
+    /// What `--seat` takes; `None` from compositors older than `wl_seat` version 2, whose seats
+    /// have no name to pick them by.
+    pub name: Option<String>,
+    pub keyboard: bool,
+    pub pointer: bool,
+    pub touch: bool,
+}
+
+impl SeatInfo {
+    /// The input devices the seat has, e.g. `["keyboard", "pointer"]`.
+    pub fn capabilities(&self) -> Vec<&'static str> {
+        [
+            (self.keyboard, "keyboard"),
+            (self.pointer, "pointer"),
+            (self.touch, "touch"),
+        ]
+        .into_iter()
+        .filter_map(|(has, name)| has.then_some(name))
+        .collect()
+    }
+}
+
 fn env_is_set(name: &str) -> bool {
     std::env::var_os(name)
         .map(|v| !v.is_empty())
         .unwrap_or(false)
 }
 
-fn auto_backend() -> PlaybackBackend {
+fn auto_backend(xwayland_target: bool) -> PlaybackBackend {
     let wayland_env = env_is_set("WAYLAND_DISPLAY") || env_is_set("WAYLAND_SOCKET");
     let x11_env = env_is_set("DISPLAY");
 
-    // Prefer Wayland if both are present (common in Wayland sessions with Xwayland).
+    // Prefer Wayland if both are present (common in Wayland sessions with Xwayland), unless the
+    // keys are for an XWayland app, which XTEST on the Xwayland server reaches directly.
     if wayland_env {
+        if xwayland_target && cfg!(feature = "x11") && x11_env {
+            return PlaybackBackend::X11;
+        }
         if cfg!(feature = "wayland") {
             return PlaybackBackend::Wayland;
         }
@@ -73,7 +247,7 @@ fn backend_unavailable_message() -> String {
 fn require_supported_backend(
     #[allow(unused_variables)] selected: PlaybackBackend,
     resolved: PlaybackBackend,
-) -> Result<()> {
+) -> Result<(), DrafterError> {
     match resolved {
         PlaybackBackend::Wayland => {
             #[cfg(feature = "wayland")]
@@ -87,11 +261,11 @@ fn require_supported_backend(
                     PlaybackBackend::Auto => "detected",
                     _ => "requested",
                 };
-                Err(anyhow!(
+                Err(DrafterError::BackendUnavailable(format!(
                     "Wayland backend {how} but is disabled in this build. (Rebuild with `--features wayland`.) {details}",
                     how = how,
                     details = backend_unavailable_message()
-                ))
+                )))
             }
         }
         PlaybackBackend::X11 => {
@@ -106,11 +280,11 @@ fn require_supported_backend(
                     PlaybackBackend::Auto => "detected",
                     _ => "requested",
                 };
-                Err(anyhow!(
+                Err(DrafterError::BackendUnavailable(format!(
                     "X11 backend {how} but is disabled in this build. (Rebuild with `--features x11`.) {details}",
                     how = how,
                     details = backend_unavailable_message()
-                ))
+                )))
             }
         }
         PlaybackBackend::Auto => {
@@ -129,20 +303,32 @@ fn require_supported_backend(
                 "Try forcing a backend."
             };
 
-            Err(anyhow!(
+            Err(DrafterError::BackendUnavailable(format!(
                 "No supported playback backend detected. {details}\n\
                  {hint} {}",
                 forced.join(" or "),
                 details = backend_unavailable_message(),
                 hint = hint,
-            ))
+            )))
         }
     }
 }
 
-pub fn resolve_backend(requested: PlaybackBackend) -> Result<PlaybackBackend> {
+pub fn resolve_backend(requested: PlaybackBackend) -> Result<PlaybackBackend, DrafterError> {
+    resolve_backend_for(requested, false)
+}
+
+/// Like [`resolve_backend`], for keys typed into an XWayland app when `xwayland_target` is set:
+/// the Wayland virtual keyboard reaches XWayland apps through Xwayland's own keymap handling,
+/// where keys and modifiers can come out differently than in native clients, so in a Wayland
+/// session with `DISPLAY` set, `Auto` picks X11 (XTEST on the Xwayland server) instead. The
+/// Wayland backend warns when the keys still go through it.
+pub fn resolve_backend_for(
+    requested: PlaybackBackend,
+    xwayland_target: bool,
+) -> Result<PlaybackBackend, DrafterError> {
     let resolved = match requested {
-        PlaybackBackend::Auto => auto_backend(),
+        PlaybackBackend::Auto => auto_backend(xwayland_target),
         other => other,
     };
 
@@ -153,64 +339,387 @@ pub fn resolve_backend(requested: PlaybackBackend) -> Result<PlaybackBackend> {
 pub fn preflight_backend(
     requested: PlaybackBackend,
     seat_name: Option<&str>,
-) -> Result<PlaybackBackend> {
+) -> Result<PlaybackBackend, DrafterError> {
+    preflight_backend_for(requested, seat_name, false)
+}
+
+/// [`preflight_backend`], resolving the backend with [`resolve_backend_for`].
+pub fn preflight_backend_for(
+    requested: PlaybackBackend,
+    seat_name: Option<&str>,
+    xwayland_target: bool,
+) -> Result<PlaybackBackend, DrafterError> {
     if let Some(name) = seat_name {
         if name.trim().is_empty() {
-            return Err(anyhow!("--seat must not be empty"));
+            return Err(anyhow!("--seat must not be empty").into());
         }
     }
 
-    let resolved = resolve_backend(requested)?;
+    let resolved = resolve_backend_for(requested, xwayland_target)?;
 
     if seat_name.is_some() && resolved == PlaybackBackend::X11 {
-        return Err(anyhow!(
-            "--seat is Wayland-only and is not supported on X11"
-        ));
+        return Err(anyhow!("--seat is Wayland-only and is not supported on X11").into());
     }
 
     Ok(resolved)
 }
 
+/// [`preflight_backend_for`] over an ordered preference list such as `wayland,x11`: the first
+/// backend of `chain` that can play in this session. A single backend fails as
+/// [`preflight_backend_for`] does. With several, each is also probed (connecting to its display
+/// server and checking for the virtual keyboard protocol or XTEST), and one that is compiled
+/// out, has no session or fails the probe is skipped; the error lists every reason when none
+/// is left.
+pub fn preflight_backend_chain(
+    chain: &[PlaybackBackend],
+    seat_name: Option<&str>,
+    xwayland_target: bool,
+) -> Result<BackendChoice, DrafterError> {
+    if let [requested] = chain {
+        return Ok(BackendChoice {
+            backend: preflight_backend_for(*requested, seat_name, xwayland_target)?,
+            skipped: Vec::new(),
+        });
+    }
+    if chain.is_empty() {
+        return Err(anyhow!("no playback backend given").into());
+    }
+
+    let mut skipped = Vec::new();
+    for &requested in chain {
+        let usable = preflight_backend_for(requested, seat_name, xwayland_target)
+            .and_then(|backend| probe_backend(backend).map(|()| backend));
+        match usable {
+            Ok(backend) => return Ok(BackendChoice { backend, skipped }),
+            Err(err) => {
+                tracing::info!(backend = requested.name(), "skipping backend: {err:#}");
+                skipped.push((requested, format!("{err:#}")));
+            }
+        }
+    }
+    let reasons: Vec<String> = skipped
+        .iter()
+        .map(|(backend, reason)| format!("{}: {reason}", backend.name()))
+        .collect();
+    Err(DrafterError::BackendUnavailable(format!(
+        "none of the playback backends can play in this session.\n{}",
+        reasons.join("\n")
+    )))
+}
+
+/// Checks, before planning or the countdown, that keys planned for `layout` (a
+/// [`crate::model::PlanConfig::layout`] label) type as planned with the resolved `backend`. X11
+/// applies the server's keymap to XTEST keys, so the server layout must match, and custom
+/// keymaps cannot be loaded; the Wayland virtual keyboard sends the plan's keymap with its keys,
+/// so any layout passes. A mismatch fails with [`DrafterError::LayoutMismatch`], or with `force`
+/// only warns.
+///
+/// Playback checks the layout again with the whole plan before its countdown.
+pub fn preflight_layout(
+    backend: PlaybackBackend,
+    layout: &str,
+    force: bool,
+) -> Result<(), DrafterError> {
+    #[cfg(not(feature = "x11"))]
+    let _ = (layout, force);
+
+    match backend {
+        #[cfg(feature = "x11")]
+        PlaybackBackend::X11 => Ok(backends::x11::preflight_layout_x11(layout, force)?),
+        _ => Ok(()),
+    }
+}
+
+/// Connects to the display server of a resolved `backend` and checks it offers what playback
+/// needs, without typing anything.
+fn probe_backend(backend: PlaybackBackend) -> Result<(), DrafterError> {
+    match backend {
+        #[cfg(feature = "wayland")]
+        PlaybackBackend::Wayland => Ok(backends::wayland::probe_wayland()?),
+        #[cfg(feature = "x11")]
+        PlaybackBackend::X11 => Ok(backends::x11::probe_x11()?),
+        // Compiled-out backends were rejected when resolving.
+        _ => Ok(()),
+    }
+}
+
+fn preflight_options(options: &PlaybackOptions) -> Result<PlaybackBackend, DrafterError> {
+    preflight_backend_for(
+        options.backend,
+        options.seat_name.as_deref(),
+        options.xwayland_target,
+    )
+}
+
+/// The seats the Wayland compositor advertises, for `--seat`.
+pub fn wayland_seats() -> Result<Vec<SeatInfo>, DrafterError> {
+    #[cfg(feature = "wayland")]
+    {
+        Ok(backends::wayland::seats_wayland()?)
+    }
+
+    #[cfg(not(feature = "wayland"))]
+    {
+        Err(DrafterError::BackendUnavailable(
+            "Wayland backend is disabled in this build (rebuild with `--features wayland`)."
+                .to_string(),
+        ))
+    }
+}
+
+/// The application owning the focused window: X11 `WM_CLASS`, or on Wayland the app id of the
+/// activated toplevel from `zwlr_foreign_toplevel_manager_v1`. `None` when the display server
+/// does not tell, e.g. Wayland compositors without that protocol (GNOME, KDE).
+pub fn focused_app(backend: PlaybackBackend) -> Result<Option<FocusedApp>, DrafterError> {
+    match resolve_backend(backend)? {
+        PlaybackBackend::Wayland => {
+            #[cfg(feature = "wayland")]
+            {
+                Ok(backends::wayland::focused_app_wayland()?)
+            }
+
+            #[cfg(not(feature = "wayland"))]
+            {
+                Err(DrafterError::BackendUnavailable(
+                    "Wayland backend is disabled in this build (rebuild with `--features wayland`)."
+                        .to_string(),
+                ))
+            }
+        }
+        PlaybackBackend::X11 => {
+            #[cfg(feature = "x11")]
+            {
+                Ok(backends::x11::focused_app_x11()?)
+            }
+
+            #[cfg(not(feature = "x11"))]
+            {
+                Err(DrafterError::BackendUnavailable(
+                    "X11 backend is disabled in this build (rebuild with `--features x11`)."
+                        .to_string(),
+                ))
+            }
+        }
+        PlaybackBackend::Auto => Err(anyhow!("no backend resolved").into()),
+    }
+}
+
+/// Plays `plan` after counting down [`PlaybackOptions::countdown_secs`] for the user to focus
+/// the editor, until it ends or Ctrl+C is pressed, reporting what happens to `observer`.
 pub fn play_plan(
     plan: &Plan,
-    countdown_secs: u64,
-    trace: bool,
-    seat_name: Option<&str>,
+    options: &PlaybackOptions,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<(), DrafterError> {
+    let cancel = task::ctrl_c_token()?;
+    play_plan_with(plan, options, &cancel, observer)
+}
+
+/// Plays `plan` on the calling thread, reporting what happens to `observer`, until it ends or
+/// `cancel` is cancelled. Unlike [`play_plan`], installs no Ctrl+C handler.
+pub fn play_plan_with(
+    plan: &Plan,
+    options: &PlaybackOptions,
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<(), DrafterError> {
+    let result = preflight_options(options).and_then(|backend| {
+        play_observed(plan_trace(plan, options)?, observer, |observer| {
+            play_in_session(
+                PlaybackSource::plan(plan),
+                options,
+                backend,
+                cancel,
+                observer,
+            )
+        })
+    });
+    if let Err(err) = &result {
+        observer.on_error(err);
+    }
+    result
+}
+
+/// Plays `plan` into `sink` instead of a display session, waiting on `clock`: counts down,
+/// reports to `observer` and stops when `cancel` is cancelled like [`play_plan_with`]. The
+/// backend, seat, layout and focus options do not apply.
+///
+/// With a fake sink and clock, this runs a playback without a display server or real time.
+pub fn play_plan_to(
+    plan: &Plan,
+    options: &PlaybackOptions,
+    sink: &mut dyn KeySink,
+    clock: &mut dyn Clock,
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<(), DrafterError> {
+    let result = plan_trace(plan, options).and_then(|trace| {
+        play_observed(trace, observer, |observer| {
+            util::countdown(cancel, clock, options.countdown_secs, observer)?;
+            let source = PlaybackSource::plan(plan).with_options(options);
+            sink::play_actions(source, sink, clock, cancel, observer)?;
+            Ok(())
+        })
+    });
+    if let Err(err) = &result {
+        observer.on_error(err);
+    }
+    result
+}
+
+/// Plays a plan stream like [`play_plan`], reading each action just before it is played, so
+/// memory stays constant however long the plan is. The console trace needs the whole plan, so
+/// [`PlaybackOptions::trace`] is rejected.
+pub fn play_stream<R: BufRead>(
+    stream: &mut PlanStream<R>,
+    options: &PlaybackOptions,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<(), DrafterError> {
+    let cancel = task::ctrl_c_token()?;
+    play_stream_with(stream, options, &cancel, observer)
+}
+
+/// Plays a plan stream like [`play_plan_with`]; see [`play_stream`].
+pub fn play_stream_with<R: BufRead>(
+    stream: &mut PlanStream<R>,
+    options: &PlaybackOptions,
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<(), DrafterError> {
+    let result = stream_options(options)
+        .and_then(|()| preflight_options(options))
+        .and_then(|backend| {
+            play_observed(None, observer, |observer| {
+                let source = PlaybackSource::stream(stream);
+                play_in_session(source, options, backend, cancel, observer)
+            })
+        });
+    if let Err(err) = &result {
+        observer.on_error(err);
+    }
+    result
+}
+
+/// Plays a plan stream into `sink` like [`play_plan_to`]; see [`play_stream`].
+pub fn play_stream_to<R: BufRead>(
+    stream: &mut PlanStream<R>,
+    options: &PlaybackOptions,
+    sink: &mut dyn KeySink,
+    clock: &mut dyn Clock,
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<(), DrafterError> {
+    let result = stream_options(options).and_then(|()| {
+        play_observed(None, observer, |observer| {
+            util::countdown(cancel, clock, options.countdown_secs, observer)?;
+            sink::play_actions(
+                PlaybackSource::stream(stream).with_options(options),
+                sink,
+                clock,
+                cancel,
+                observer,
+            )?;
+            Ok(())
+        })
+    });
+    if let Err(err) = &result {
+        observer.on_error(err);
+    }
+    result
+}
+
+fn stream_options(options: &PlaybackOptions) -> Result<(), DrafterError> {
+    if options.trace.is_some() {
+        return Err(anyhow!(
+            "the console trace needs the whole plan and cannot follow a plan stream"
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// The console trace of `plan` at [`PlaybackOptions::trace`], if any.
+fn plan_trace(
+    plan: &Plan,
+    options: &PlaybackOptions,
+) -> Result<Option<ConsoleTrace>, DrafterError> {
+    // Decode with the plan's layout so dead-key/Compose sequences trace as single characters.
+    Ok(options
+        .trace
+        .map(|level| console_trace_for_plan(plan, level))
+        .transpose()?)
+}
+
+/// Runs `play` with `observer` wrapped to also receive the console `trace`, pauses and summary.
+fn play_observed(
+    trace: Option<ConsoleTrace>,
+    observer: &mut dyn PlaybackObserver,
+    play: impl FnOnce(&mut dyn PlaybackObserver) -> Result<(), DrafterError>,
+) -> Result<(), DrafterError> {
+    let mut observer = Observed::new(observer, trace);
+    play(&mut observer)?;
+    observer.complete();
+    Ok(())
+}
+
+fn play_in_session(
+    source: PlaybackSource<'_>,
+    options: &PlaybackOptions,
     backend: PlaybackBackend,
-) -> Result<()> {
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<(), DrafterError> {
     #[cfg(all(not(feature = "wayland"), not(feature = "x11")))]
-    let _ = (plan, countdown_secs, trace, seat_name);
-
-    let backend = preflight_backend(backend, seat_name)?;
+    let _ = (source, options, cancel, observer);
 
     match backend {
         PlaybackBackend::Wayland => {
             #[cfg(feature = "wayland")]
             {
-                backends::wayland::play_plan_wayland(plan, countdown_secs, trace, seat_name)
+                backends::wayland::play_wayland(source, options, cancel, observer)
+                    .map_err(DrafterError::from)
             }
 
             #[cfg(not(feature = "wayland"))]
             {
-                let _ = seat_name;
-                Err(anyhow!(
+                Err(DrafterError::BackendUnavailable(
                     "Wayland backend is disabled in this build (rebuild with `--features wayland`)."
+                        .to_string(),
                 ))
             }
         }
         PlaybackBackend::X11 => {
             #[cfg(feature = "x11")]
             {
-                backends::x11::play_plan_x11(plan, countdown_secs, trace)
+                backends::x11::play_x11(source, options, cancel, observer)
+                    .map_err(DrafterError::from)
             }
 
             #[cfg(not(feature = "x11"))]
             {
-                Err(anyhow!(
+                Err(DrafterError::BackendUnavailable(
                     "X11 backend is disabled in this build (rebuild with `--features x11`)."
+                        .to_string(),
                 ))
             }
         }
-        PlaybackBackend::Auto => Err(anyhow!("no backend resolved")),
+        PlaybackBackend::Auto => Err(anyhow!("no backend resolved").into()),
     }
 }
+
+/// Starts playing `plan` in the background, without blocking or installing a Ctrl+C handler.
+///
+/// Backend and seat problems are reported right away, before the countdown. Await the returned
+/// task for the result, and read what happens with [`PlaybackTask::next_event`]; `cancel` (or
+/// dropping the task) stops the playback.
+pub fn play_plan_async(
+    plan: Plan,
+    options: PlaybackOptions,
+    cancel: CancellationToken,
+) -> Result<PlaybackTask, DrafterError> {
+    preflight_options(&options)?;
+    let thread_cancel = cancel.clone();
+    PlaybackTask::spawn(cancel, move |observer| {
+        play_plan_with(&plan, &options, &thread_cancel, observer)
+    })
+}
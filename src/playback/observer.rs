@@ -0,0 +1,296 @@
+//! Following a playback as it happens: [`PlaybackObserver`] is told about the countdown, each
+//! action, the console trace, pauses and how the playback ended, so embedders can drive progress
+//! bars, logging or safety checks without parsing stderr.
+//!
+//! [`ConsoleObserver`] is the CLI's implementation, printing the countdown and the console trace.
+
+use crate::error::DrafterError;
+use crate::model::Action;
+use crate::playback::{FocusedApp, PlaybackEvent, PlaybackProgress};
+use crate::trace::{ConsoleTrace, TraceDetail, TraceLine, TraceLineKind, TRACE_PAUSE_MS};
+
+/// Receives what happens during a playback. Every method does nothing by default.
+pub trait PlaybackObserver {
+    /// The countdown before the first key has `remaining_secs` of `total_secs` seconds left.
+    fn on_countdown(&mut self, remaining_secs: u64, total_secs: u64) {
+        let _ = (remaining_secs, total_secs);
+    }
+
+    /// The application focused once the countdown is over, with
+    /// [`super::PlaybackOptions::detect_focus`].
+    fn on_focus(&mut self, app: &FocusedApp) {
+        let _ = app;
+    }
+
+    /// Called before each action is played, with what was played before it. Cancelling the
+    /// playback's token here stops it before the action.
+    fn on_action(&mut self, action_index: usize, action: &Action, progress: &PlaybackProgress) {
+        let _ = (action_index, action, progress);
+    }
+
+    /// The key of the [`Action::KeyRepeat`] at `action_index` has been tapped `taps` times, so a
+    /// playback stopped during the repeat can resume with the taps left.
+    fn on_repeat_tap(&mut self, action_index: usize, taps: u32) {
+        let _ = (action_index, taps);
+    }
+
+    /// A console trace line, before the action it belongs to, with
+    /// [`super::PlaybackOptions::trace`].
+    fn on_trace_event(&mut self, line: &TraceLine) {
+        let _ = line;
+    }
+
+    /// The playback is about to wait `ms` milliseconds, at least [`TRACE_PAUSE_MS`].
+    fn on_pause(&mut self, action_index: usize, ms: u64) {
+        let _ = (action_index, ms);
+    }
+
+    /// The playback failed or was cancelled ([`DrafterError::Aborted`]); nothing else is reported
+    /// after it.
+    fn on_error(&mut self, err: &DrafterError) {
+        let _ = err;
+    }
+
+    /// Every action was played. `trace_summary` is the console trace's summary, at the levels
+    /// that have one.
+    fn on_complete(&mut self, trace_summary: Option<&str>) {
+        let _ = trace_summary;
+    }
+}
+
+/// Ignores everything.
+impl PlaybackObserver for () {}
+
+/// Prints the countdown and the console trace to stderr, as `drafter play` does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConsoleObserver {
+    detail: TraceDetail,
+}
+
+impl ConsoleObserver {
+    pub fn new(detail: TraceDetail) -> Self {
+        Self { detail }
+    }
+}
+
+impl PlaybackObserver for ConsoleObserver {
+    fn on_countdown(&mut self, remaining_secs: u64, total_secs: u64) {
+        if remaining_secs == total_secs {
+            eprintln!("Focus the target editor window. Starting in {total_secs}s...");
+        }
+        eprintln!("{remaining_secs}...");
+    }
+
+    fn on_trace_event(&mut self, line: &TraceLine) {
+        print_trace_line(line, self.detail);
+    }
+
+    fn on_complete(&mut self, trace_summary: Option<&str>) {
+        if let Some(summary) = trace_summary {
+            eprintln!("{summary}");
+            tracing::info!("{summary}");
+        }
+    }
+}
+
+fn print_trace_line(line: &TraceLine, detail: TraceDetail) {
+    const RESET: &str = "\x1b[0m";
+    const DIM: &str = "\x1b[2m";
+    const TYPING: &str = "\x1b[34m";
+    const REPLACE: &str = "\x1b[33m";
+
+    let annotation = match detail {
+        TraceDetail::Plain => String::new(),
+        TraceDetail::Positions => format!("{DIM}[{}]{RESET} ", line.annotation()),
+    };
+    let text = &line.line;
+    // The line quotes the draft, so the log file only gets it at trace level.
+    tracing::info!(
+        action_index = line.action_index,
+        offset_ms = line.offset_ms,
+        cursor_line = line.cursor.line,
+        cursor_column = line.cursor.column,
+        kind = line.kind.name(),
+        "trace line"
+    );
+    tracing::trace!(action_index = line.action_index, "{text}");
+    match line.kind {
+        TraceLineKind::Typing => {
+            let rest = text.strip_prefix("Typing").unwrap_or(text);
+            eprintln!("{annotation}{TYPING}Typing{RESET}{rest}");
+        }
+        TraceLineKind::Replace => {
+            let rest = text.strip_prefix("Replace").unwrap_or(text);
+            eprintln!("{annotation}{REPLACE}Replace{RESET}{rest}");
+        }
+        TraceLineKind::Navigation | TraceLineKind::Pause => eprintln!("{annotation}{text}"),
+        TraceLineKind::Action => eprintln!("{annotation}{DIM}{text}{RESET}"),
+    }
+}
+
+/// Queues what happens as [`PlaybackEvent`]s, for [`super::PlaybackTask`].
+pub(crate) struct EventSink<F: FnMut(PlaybackEvent)>(pub(crate) F);
+
+impl<F: FnMut(PlaybackEvent)> PlaybackObserver for EventSink<F> {
+    fn on_countdown(&mut self, remaining_secs: u64, _total_secs: u64) {
+        (self.0)(PlaybackEvent::Countdown { remaining_secs });
+    }
+
+    fn on_focus(&mut self, app: &FocusedApp) {
+        (self.0)(PlaybackEvent::Focused(app.clone()));
+    }
+
+    fn on_action(&mut self, _action_index: usize, action: &Action, progress: &PlaybackProgress) {
+        if matches!(action, Action::Key { .. } | Action::KeyRepeat { .. }) {
+            (self.0)(PlaybackEvent::Progress(progress.clone()));
+        }
+    }
+
+    fn on_trace_event(&mut self, line: &TraceLine) {
+        (self.0)(PlaybackEvent::Trace(line.clone()));
+    }
+
+    fn on_pause(&mut self, _action_index: usize, ms: u64) {
+        (self.0)(PlaybackEvent::Pause { ms });
+    }
+}
+
+/// Wraps the observer the backends report to, adding what every backend reports alike: the
+/// console trace lines, pauses and the trace summary.
+pub(crate) struct Observed<'a> {
+    inner: &'a mut dyn PlaybackObserver,
+    trace: Option<ConsoleTrace>,
+    next_trace_line: usize,
+}
+
+impl<'a> Observed<'a> {
+    pub(crate) fn new(inner: &'a mut dyn PlaybackObserver, trace: Option<ConsoleTrace>) -> Self {
+        Self {
+            inner,
+            trace,
+            next_trace_line: 0,
+        }
+    }
+
+    /// Reports that every action of the plan was played.
+    pub(crate) fn complete(&mut self) {
+        let summary = self
+            .trace
+            .as_ref()
+            .and_then(|trace| trace.summary.as_deref());
+        self.inner.on_complete(summary);
+    }
+}
+
+impl PlaybackObserver for Observed<'_> {
+    fn on_countdown(&mut self, remaining_secs: u64, total_secs: u64) {
+        self.inner.on_countdown(remaining_secs, total_secs);
+    }
+
+    fn on_focus(&mut self, app: &FocusedApp) {
+        self.inner.on_focus(app);
+    }
+
+    fn on_action(&mut self, action_index: usize, action: &Action, progress: &PlaybackProgress) {
+        if let Some(trace) = &self.trace {
+            while let Some(line) = trace
+                .lines
+                .get(self.next_trace_line)
+                .filter(|line| line.action_index == action_index)
+            {
+                self.inner.on_trace_event(line);
+                self.next_trace_line += 1;
+            }
+        }
+        self.inner.on_action(action_index, action, progress);
+        if let Action::Wait { ms } = action {
+            if *ms >= TRACE_PAUSE_MS {
+                self.inner.on_pause(action_index, *ms);
+            }
+        }
+    }
+
+    fn on_repeat_tap(&mut self, action_index: usize, taps: u32) {
+        self.inner.on_repeat_tap(action_index, taps);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Observed, PlaybackObserver};
+    use crate::model::Action;
+    use crate::planner::{generate_plan, PlannerConfig};
+    use crate::playback::PlaybackProgress;
+    use crate::trace::{console_trace_for_plan, TraceLevel, TraceLine, TRACE_PAUSE_MS};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[derive(Default)]
+    struct Recorder {
+        lines: Vec<TraceLine>,
+        actions: usize,
+        pauses: Vec<(usize, u64)>,
+        summary: Option<String>,
+    }
+
+    impl PlaybackObserver for Recorder {
+        fn on_action(&mut self, _: usize, _: &Action, progress: &PlaybackProgress) {
+            assert_eq!(progress.actions_played, self.actions);
+            self.actions += 1;
+        }
+
+        fn on_trace_event(&mut self, line: &TraceLine) {
+            assert_eq!(line.action_index, self.actions);
+            self.lines.push(line.clone());
+        }
+
+        fn on_pause(&mut self, action_index: usize, ms: u64) {
+            self.pauses.push((action_index, ms));
+        }
+
+        fn on_complete(&mut self, trace_summary: Option<&str>) {
+            self.summary = trace_summary.map(str::to_string);
+        }
+    }
+
+    #[test]
+    fn reports_trace_lines_pauses_and_summary_around_actions() {
+        let text =
+            "The quick brown fox jumps over the lazy dog.\nPack my box with five dozen jugs.";
+        let plan = generate_plan(
+            text,
+            PlannerConfig::default(),
+            &mut StdRng::seed_from_u64(3),
+        )
+        .unwrap();
+        let trace = console_trace_for_plan(&plan, TraceLevel::Verbose).unwrap();
+
+        let mut recorder = Recorder::default();
+        let mut observed = Observed::new(&mut recorder, Some(trace.clone()));
+        let mut progress = PlaybackProgress {
+            actions_played: 0,
+            total_actions: plan.actions.len(),
+            played_ms: 0,
+            total_ms: 0,
+        };
+        for (action_index, action) in plan.actions.iter().enumerate() {
+            observed.on_action(action_index, action, &progress);
+            progress.actions_played += 1;
+        }
+        observed.complete();
+
+        assert_eq!(recorder.actions, plan.actions.len());
+        assert_eq!(recorder.lines, trace.lines);
+        assert_eq!(recorder.summary, trace.summary);
+        let pauses: Vec<(usize, u64)> = plan
+            .actions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, action)| match action {
+                Action::Wait { ms } if *ms >= TRACE_PAUSE_MS => Some((index, *ms)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(recorder.pauses, pauses);
+    }
+}
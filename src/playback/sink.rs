@@ -0,0 +1,228 @@
+//! Where played actions go and how playback waits: the backends send keys through a
+//! [`KeySink`] and wait on a [`Clock`], so the playback loop (progress, the console trace,
+//! cancellation and resetting modifiers after an abort) is shared by every backend and runs in
+//! tests with a fake sink and virtual time, see [`super::play_plan_to`].
+
+use std::io::BufRead;
+
+use anyhow::Result;
+
+use super::util::ProgressTracker;
+use super::{CancellationToken, PlaybackObserver, PlaybackOptions};
+use crate::error::DrafterError;
+use crate::model::{Action, KeyState, Plan, PlanConfig};
+use crate::plan::PlanStream;
+
+/// Receives the key events and modifier states of a playback.
+pub trait KeySink {
+    /// Sends a press or release of `keycode` (a Linux evdev keycode).
+    fn send_key(&mut self, keycode: u32, state: KeyState) -> Result<()>;
+
+    /// Sends the XKB modifier and group state, as in [`Action::Modifiers`]. Sinks that follow
+    /// the modifier keys instead may ignore it.
+    fn send_modifiers(
+        &mut self,
+        mods_depressed: u32,
+        mods_latched: u32,
+        mods_locked: u32,
+        group: u32,
+    ) -> Result<()>;
+
+    /// Best effort to leave no modifier held (and CapsLock as it was) after the playback was
+    /// cancelled or a send failed.
+    fn reset(&mut self);
+}
+
+/// Waits between actions.
+pub trait Clock {
+    /// Sleeps for `ms` milliseconds, or until `cancel` is cancelled. Returns whether it was
+    /// cancelled.
+    fn sleep(&mut self, ms: u64, cancel: &CancellationToken) -> bool;
+}
+
+/// Real time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn sleep(&mut self, ms: u64, cancel: &CancellationToken) -> bool {
+        cancel.sleep(ms)
+    }
+}
+
+/// The actions a playback plays: those of a loaded plan, or those read one at a time from a
+/// plan stream.
+pub(crate) struct PlaybackSource<'a> {
+    #[cfg_attr(not(any(feature = "wayland", feature = "x11")), allow(dead_code))]
+    pub(crate) config: &'a PlanConfig,
+    /// The whole plan when it is loaded, for checks that look at every action up front.
+    #[cfg_attr(not(feature = "x11"), allow(dead_code))]
+    pub(crate) plan: Option<&'a Plan>,
+    pub(crate) total_actions: usize,
+    pub(crate) total_ms: u64,
+    actions: Box<dyn Iterator<Item = Result<Action>> + 'a>,
+}
+
+impl<'a> PlaybackSource<'a> {
+    pub(crate) fn plan(plan: &'a Plan) -> Self {
+        Self {
+            config: &plan.config,
+            plan: Some(plan),
+            total_actions: plan.actions.len(),
+            total_ms: crate::sim::stats(plan).total_wait_ms,
+            actions: Box::new(plan.actions.iter().cloned().map(Ok)),
+        }
+    }
+
+    pub(crate) fn stream<R: BufRead + 'a>(stream: &'a mut PlanStream<R>) -> Self {
+        Self {
+            config: &stream.header.config,
+            plan: None,
+            total_actions: stream.header.action_count,
+            total_ms: stream.header.total_wait_ms,
+            actions: Box::new(&mut stream.actions),
+        }
+    }
+
+    /// The actions as `options` play them: every wait and key repeat interval cut to
+    /// [`FAST_FORWARD_WAIT_MS`] with [`PlaybackOptions::fast_forward`], or to
+    /// [`PlaybackOptions::max_wait_ms`].
+    pub(crate) fn with_options(mut self, options: &PlaybackOptions) -> Self {
+        let max_ms = if options.fast_forward {
+            FAST_FORWARD_WAIT_MS
+        } else if let Some(max_ms) = options.max_wait_ms {
+            max_ms
+        } else {
+            return self;
+        };
+        self.total_ms = match self.plan {
+            Some(plan) => plan
+                .actions
+                .iter()
+                .map(|action| clamp_waits(action.clone(), max_ms).duration_ms())
+                .sum(),
+            // Streams only say how long they take as planned; every action being a wait bounds it.
+            None => self
+                .total_ms
+                .min((self.total_actions as u64).saturating_mul(max_ms)),
+        };
+        self.actions = Box::new(
+            self.actions
+                .map(move |action| action.map(|action| clamp_waits(action, max_ms))),
+        );
+        self
+    }
+}
+
+/// The longest wait of a fast-forwarded playback, so keys still arrive in order in editors
+/// that process them asynchronously.
+pub const FAST_FORWARD_WAIT_MS: u64 = 2;
+
+pub(crate) fn clamp_waits(action: Action, max_ms: u64) -> Action {
+    match action {
+        Action::Wait { ms } => Action::Wait { ms: ms.min(max_ms) },
+        Action::KeyRepeat {
+            keycode,
+            count,
+            interval_ms,
+        } => Action::KeyRepeat {
+            keycode,
+            count,
+            interval_ms: interval_ms.min(max_ms),
+        },
+        action => action,
+    }
+}
+
+/// Plays the actions of `source` into `sink`, reporting each to `observer` first. Resets the
+/// sink and fails with [`DrafterError::Aborted`] when cancelled, or with the error of a send
+/// (or of reading a streamed action) that failed.
+pub(crate) fn play_actions(
+    source: PlaybackSource<'_>,
+    sink: &mut dyn KeySink,
+    clock: &mut dyn Clock,
+    cancel: &CancellationToken,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<()> {
+    let mut progress = ProgressTracker::new(source.total_actions, source.total_ms);
+    for (action_index, action) in source.actions.enumerate() {
+        let action = match action {
+            Ok(action) => action,
+            Err(err) => {
+                eprintln!("Playback error. Attempting to reset modifiers...");
+                sink.reset();
+                return Err(err.context(format!("failed to read action {action_index}")));
+            }
+        };
+        observer.on_action(action_index, &action, progress.current());
+        if cancel.is_cancelled() {
+            break;
+        }
+
+        tracing::trace!(action_index, ?action, "action");
+        let sent = match action {
+            Action::Wait { ms } => {
+                if clock.sleep(ms, cancel) {
+                    break;
+                }
+                Ok(())
+            }
+            Action::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+            } => sink.send_modifiers(mods_depressed, mods_latched, mods_locked, group),
+            Action::Key { keycode, state } => sink.send_key(keycode, state),
+            Action::KeyRepeat {
+                keycode,
+                count,
+                interval_ms,
+            } => match repeat_key(sink, clock, cancel, keycode, count, interval_ms, |taps| {
+                observer.on_repeat_tap(action_index, taps)
+            }) {
+                Ok(true) => break,
+                sent => sent.map(drop),
+            },
+            Action::Checkpoint { .. } => Ok(()),
+        };
+        if let Err(err) = sent {
+            eprintln!("Playback error. Attempting to reset modifiers...");
+            sink.reset();
+            return Err(err.context(format!("failed to play action {action_index}")));
+        }
+        progress.played(&action);
+    }
+
+    if cancel.is_cancelled() {
+        eprintln!("Aborted. Attempting to reset modifiers...");
+        tracing::warn!("playback aborted");
+        sink.reset();
+        return Err(DrafterError::Aborted.into());
+    }
+    tracing::info!("playback finished");
+    Ok(())
+}
+
+/// Taps `keycode` `count` times, `interval_ms` apart, as a held key repeats, calling `tapped`
+/// with the taps sent so far after each. Returns whether it was cancelled; a cancelled repeat
+/// is left released.
+fn repeat_key(
+    sink: &mut dyn KeySink,
+    clock: &mut dyn Clock,
+    cancel: &CancellationToken,
+    keycode: u32,
+    count: u32,
+    interval_ms: u64,
+    mut tapped: impl FnMut(u32),
+) -> Result<bool> {
+    for tap in 0..count {
+        if tap > 0 && clock.sleep(interval_ms, cancel) {
+            return Ok(true);
+        }
+        sink.send_key(keycode, KeyState::Pressed)?;
+        sink.send_key(keycode, KeyState::Released)?;
+        tapped(tap + 1);
+    }
+    Ok(false)
+}
@@ -0,0 +1,325 @@
+//! Cancelling playback and running it in the background, for library users that cannot block a
+//! thread on [`super::play_plan`] or rely on its Ctrl+C handler (e.g. GUI apps).
+//!
+//! [`PlaybackTask`] plays a plan on a thread of its own and is polled like any future, so it
+//! works with every async runtime (or none, with a simple `block_on`).
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::Read;
+use std::os::fd::{BorrowedFd, IntoRawFd};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+use super::observer::EventSink;
+use super::{PlaybackEvent, PlaybackObserver};
+use crate::error::DrafterError;
+
+/// Stops a playback early: it releases the modifiers it holds and returns an `aborted` error.
+///
+/// Clones share their state, so one clone can cancel a playback started with another.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<TokenState>,
+}
+
+#[derive(Debug, Default)]
+struct TokenState {
+    flags: Mutex<TokenFlags>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct TokenFlags {
+    cancelled: bool,
+    /// How many times [`CancellationToken::skip_wait`] was called.
+    skips: u64,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        lock(&self.inner.flags).cancelled = true;
+        self.inner.changed.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        lock(&self.inner.flags).cancelled
+    }
+
+    /// Ends the wait the playback is in (a pause, or a second of the countdown) right away,
+    /// without cancelling it. Does nothing while keys are being sent.
+    pub fn skip_wait(&self) {
+        lock(&self.inner.flags).skips += 1;
+        self.inner.changed.notify_all();
+    }
+
+    /// Sleeps for `ms` milliseconds, or until cancelled or [`CancellationToken::skip_wait`] is
+    /// called. Returns whether it was cancelled.
+    pub(crate) fn sleep(&self, ms: u64) -> bool {
+        let deadline = Instant::now() + Duration::from_millis(ms);
+        let mut flags = lock(&self.inner.flags);
+        let skips = flags.skips;
+        while !flags.cancelled && flags.skips == skips {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            flags = self
+                .inner
+                .changed
+                .wait_timeout(flags, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        flags.cancelled
+    }
+}
+
+/// The process-wide token cancelled by Ctrl+C, whose wait SIGUSR1 skips, installing the
+/// handlers on first use. [`super::play_plan`] plays with it.
+pub fn ctrl_c_token() -> Result<CancellationToken, DrafterError> {
+    static CTRL_C: Mutex<Option<CancellationToken>> = Mutex::new(None);
+
+    let mut installed = lock(&CTRL_C);
+    if let Some(token) = installed.as_ref() {
+        return Ok(token.clone());
+    }
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+    ctrlc::set_handler(move || handler_token.cancel())
+        .map_err(|err| anyhow!("failed to install Ctrl+C handler: {err}"))?;
+    skip_wait_on_sigusr1(token.clone())
+        .map_err(|err| anyhow!("failed to install SIGUSR1 handler: {err}"))?;
+    *installed = Some(token.clone());
+    Ok(token)
+}
+
+/// Write end of the pipe the SIGUSR1 handler wakes the skipping thread through.
+static SKIP_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn on_sigusr1(_: nix::libc::c_int) {
+    let fd = SKIP_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        // Only async-signal-safe calls here: the thread does the rest.
+        // SAFETY: the write end is kept open for the life of the process.
+        let _ = nix::unistd::write(unsafe { BorrowedFd::borrow_raw(fd) }, &[0]);
+    }
+}
+
+/// Calls [`CancellationToken::skip_wait`] on `token` for every SIGUSR1 the process receives,
+/// e.g. `pkill -USR1 -x drafter` bound to a desktop shortcut, which works without taking the
+/// focus away from the editor being typed into.
+fn skip_wait_on_sigusr1(token: CancellationToken) -> nix::Result<()> {
+    let (read_end, write_end) = nix::unistd::pipe()?;
+    SKIP_PIPE.store(write_end.into_raw_fd(), Ordering::Relaxed);
+    let action = SigAction::new(
+        SigHandler::Handler(on_sigusr1),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    // SAFETY: the handler only loads an atomic and writes to a pipe.
+    unsafe { sigaction(Signal::SIGUSR1, &action) }?;
+    std::thread::Builder::new()
+        .name("drafter-skip-wait".to_string())
+        .spawn(move || {
+            let mut pipe = std::fs::File::from(read_end);
+            let mut byte = [0u8];
+            while matches!(pipe.read(&mut byte), Ok(1)) {
+                token.skip_wait();
+            }
+        })
+        .map_err(|_| nix::Error::EAGAIN)?;
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// A plan playing in the background, started by [`super::play_plan_async`].
+///
+/// Awaiting the task gives the playback's result; [`PlaybackTask::next_event`] yields its
+/// [`PlaybackEvent`]s as they happen. Dropping the task cancels the playback.
+#[derive(Debug)]
+pub struct PlaybackTask {
+    shared: Arc<Mutex<Shared>>,
+    cancel: CancellationToken,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    /// Events not yet yielded by [`PlaybackTask::next_event`]; see [`Shared::push_event`].
+    events: VecDeque<PlaybackEvent>,
+    result: Option<Result<(), DrafterError>>,
+    finished: bool,
+    result_waker: Option<Waker>,
+    event_waker: Option<Waker>,
+}
+
+impl Shared {
+    /// Queues `event`, replacing a [`PlaybackEvent::Progress`] still at the back of the queue
+    /// with a newer one: progress comes before every key, so a caller that reads events slower
+    /// than they are typed (or not at all) would otherwise queue one per key of the plan.
+    fn push_event(&mut self, event: PlaybackEvent) {
+        match (self.events.back_mut(), event) {
+            (Some(PlaybackEvent::Progress(queued)), PlaybackEvent::Progress(progress)) => {
+                *queued = progress;
+            }
+            (_, event) => self.events.push_back(event),
+        }
+    }
+}
+
+impl PlaybackTask {
+    /// Runs `play` on a new thread, passing it an observer that queues its events.
+    pub(crate) fn spawn(
+        cancel: CancellationToken,
+        play: impl FnOnce(&mut dyn PlaybackObserver) -> Result<(), DrafterError> + Send + 'static,
+    ) -> Result<Self, DrafterError> {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let thread_shared = shared.clone();
+        std::thread::Builder::new()
+            .name("drafter-playback".to_string())
+            .spawn(move || {
+                let mut events = EventSink(|event| {
+                    let mut shared = lock(&thread_shared);
+                    shared.push_event(event);
+                    if let Some(waker) = shared.event_waker.take() {
+                        waker.wake();
+                    }
+                });
+                let result =
+                    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| play(&mut events)))
+                        .unwrap_or_else(|_| Err(anyhow!("playback thread panicked").into()));
+
+                let mut shared = lock(&thread_shared);
+                shared.result = Some(result);
+                shared.finished = true;
+                for waker in [shared.result_waker.take(), shared.event_waker.take()] {
+                    waker.into_iter().for_each(Waker::wake);
+                }
+            })
+            .map_err(|err| anyhow!("failed to start the playback thread: {err}"))?;
+        Ok(Self { shared, cancel })
+    }
+
+    /// The token cancelling this playback.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// The next event of the playback, or `None` once it has finished and every event was
+    /// yielded. Consecutive [`PlaybackEvent::Progress`] events not read yet are merged into the
+    /// latest one.
+    pub fn next_event(&mut self) -> NextEvent<'_> {
+        NextEvent { task: self }
+    }
+}
+
+impl Future for PlaybackTask {
+    type Output = Result<(), DrafterError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = lock(&self.shared);
+        if let Some(result) = shared.result.take() {
+            return Poll::Ready(result);
+        }
+        if shared.finished {
+            return Poll::Ready(Err(anyhow!("playback task polled after completion").into()));
+        }
+        shared.result_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for PlaybackTask {
+    fn drop(&mut self) {
+        if !lock(&self.shared).finished {
+            self.cancel.cancel();
+        }
+    }
+}
+
+/// Future returned by [`PlaybackTask::next_event`].
+#[derive(Debug)]
+pub struct NextEvent<'a> {
+    task: &'a mut PlaybackTask,
+}
+
+impl Future for NextEvent<'_> {
+    type Output = Option<PlaybackEvent>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = lock(&self.task.shared);
+        if let Some(event) = shared.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if shared.finished {
+            return Poll::Ready(None);
+        }
+        shared.event_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lock, CancellationToken, PlaybackTask};
+    use crate::model::{Action, KeyState};
+    use crate::playback::{PlaybackEvent, PlaybackProgress};
+
+    fn progress(actions_played: usize, total_actions: usize) -> PlaybackProgress {
+        PlaybackProgress {
+            actions_played,
+            total_actions,
+            played_ms: 0,
+            total_ms: 0,
+        }
+    }
+
+    #[test]
+    fn keeps_only_the_latest_progress_of_events_not_read() {
+        const KEYS: usize = 100_000;
+        let key = Action::Key {
+            keycode: 30,
+            state: KeyState::Pressed,
+        };
+        let task = PlaybackTask::spawn(CancellationToken::new(), move |observer| {
+            observer.on_countdown(1, 1);
+            for index in 0..KEYS {
+                observer.on_action(index, &key, &progress(index, KEYS));
+                if index == KEYS / 2 {
+                    observer.on_pause(index, 1_000);
+                }
+            }
+            Ok(())
+        })
+        .unwrap();
+        while !lock(&task.shared).finished {
+            std::thread::yield_now();
+        }
+
+        let events: Vec<_> = lock(&task.shared).events.drain(..).collect();
+        assert_eq!(
+            events,
+            [
+                PlaybackEvent::Countdown { remaining_secs: 1 },
+                PlaybackEvent::Progress(progress(KEYS / 2, KEYS)),
+                PlaybackEvent::Pause { ms: 1_000 },
+                PlaybackEvent::Progress(progress(KEYS - 1, KEYS)),
+            ]
+        );
+    }
+}
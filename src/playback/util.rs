@@ -1,28 +1,91 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
-
-pub(crate) fn sleep_interruptible(stop: &AtomicBool, ms: u64) {
-    let mut remaining = ms;
-    while remaining > 0 {
-        if stop.load(Ordering::SeqCst) {
-            return;
+use anyhow::Result;
+
+use crate::error::DrafterError;
+use crate::model::Action;
+#[cfg(any(feature = "wayland", feature = "x11"))]
+use crate::playback::FocusedApp;
+use crate::playback::{CancellationToken, Clock, PlaybackObserver, PlaybackProgress};
+
+/// Counts down `secs` seconds on `clock` for the user to focus the editor, reporting each
+/// second to `observer`. Fails with `aborted` when cancelled.
+pub(crate) fn countdown(
+    cancel: &CancellationToken,
+    clock: &mut dyn Clock,
+    secs: u64,
+    observer: &mut dyn PlaybackObserver,
+) -> Result<()> {
+    if secs == 0 {
+        return Ok(());
+    }
+    tracing::info!(countdown_secs = secs, "counting down before playback");
+    for remaining_secs in (1..=secs).rev() {
+        if cancel.is_cancelled() {
+            return Err(DrafterError::Aborted.into());
         }
-        let step = remaining.min(50);
-        std::thread::sleep(Duration::from_millis(step));
-        remaining -= step;
+        observer.on_countdown(remaining_secs, secs);
+        clock.sleep(1000, cancel);
     }
+    if cancel.is_cancelled() {
+        return Err(DrafterError::Aborted.into());
+    }
+    Ok(())
+}
+
+/// Tracks how much of a plan has been played, for [`PlaybackObserver::on_action`].
+pub(crate) struct ProgressTracker {
+    progress: PlaybackProgress,
 }
 
-pub(crate) fn print_trace_line(line: &str) {
-    const RESET: &str = "\x1b[0m";
-    const TYPING: &str = "\x1b[34m";
-    const REPLACE: &str = "\x1b[33m";
-
-    if let Some(rest) = line.strip_prefix("Typing") {
-        eprintln!("{TYPING}Typing{RESET}{rest}");
-    } else if let Some(rest) = line.strip_prefix("Replace") {
-        eprintln!("{REPLACE}Replace{RESET}{rest}");
-    } else {
-        eprintln!("{line}");
+impl ProgressTracker {
+    pub(crate) fn new(total_actions: usize, total_ms: u64) -> Self {
+        Self {
+            progress: PlaybackProgress {
+                actions_played: 0,
+                total_actions,
+                played_ms: 0,
+                total_ms,
+            },
+        }
+    }
+
+    pub(crate) fn current(&self) -> &PlaybackProgress {
+        &self.progress
+    }
+
+    /// Records that `action` was played.
+    pub(crate) fn played(&mut self, action: &Action) {
+        self.progress.actions_played += 1;
+        self.progress.played_ms += action.duration_ms();
+    }
+}
+
+/// Abort on a session/plan keyboard layout mismatch, or only warn when `force` is set.
+#[cfg(feature = "x11")]
+pub(crate) fn layout_mismatch(message: &str, force: bool) -> Result<()> {
+    if force {
+        eprintln!("warning: {message} (continuing because of --force)");
+        tracing::warn!("{message} (continuing because of --force)");
+        return Ok(());
+    }
+    Err(DrafterError::LayoutMismatch(message.to_string()).into())
+}
+
+/// Reports the detected focused application to `observer`; detection failures only warn, since
+/// they do not stop playback.
+#[cfg(any(feature = "wayland", feature = "x11"))]
+pub(crate) fn report_focused_app(
+    app: Result<Option<FocusedApp>>,
+    observer: &mut dyn PlaybackObserver,
+) {
+    match app {
+        Ok(Some(app)) => {
+            tracing::info!(app = %app, "detected the focused application");
+            observer.on_focus(&app);
+        }
+        Ok(None) => tracing::info!("could not tell which application is focused"),
+        Err(err) => {
+            eprintln!("warning: could not detect the focused application: {err:#}");
+            tracing::warn!("could not detect the focused application: {err:#}");
+        }
     }
 }
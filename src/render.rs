@@ -0,0 +1,561 @@
+//! Rendering plans as recordings (`drafter render`), to preview them without a display server.
+//!
+//! [`asciinema_cast`] replays a plan with [`crate::sim::replay`] and writes an asciinema v2
+//! cast of a terminal showing the editor: typing at the end of the text is written as it
+//! happens, and every other edit (corrections, cursor movement, selections) redraws the
+//! screen with the cursor where the editor has it and the selection in reverse video.
+//!
+//! [`play_in_terminal`] writes the same screen to the terminal as the plan's timing goes
+//! (`drafter preview`), to rehearse a plan without a compositor or the target application.
+//!
+//! [`html_report`] writes a single HTML file with a player showing the text at any time of
+//! the plan (every character ever typed is embedded with when it was typed and deleted), the
+//! corrections from the console trace, and a chart of the rolling typing speed from
+//! [`crate::analysis`].
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::ops::Range;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::json;
+
+use crate::analysis::{analyze, AnalysisOptions};
+use crate::editor::EditorModel;
+use crate::heatmap::xml_escape;
+use crate::model::Plan;
+use crate::plan_edit;
+use crate::playback::{clamp_waits, CancellationToken};
+use crate::sim::{self, EditorBehavior, SimEdit};
+use crate::trace::{trace_events_for_plan, TraceEventKind};
+
+/// Trailing window of the typing speed chart of [`html_report`].
+const REPORT_WPM_WINDOW_MS: u64 = 30_000;
+/// Points of the typing speed chart of [`html_report`].
+const REPORT_WPM_POINTS: u64 = 200;
+/// Size of the typing speed chart of [`html_report`], in pixels.
+const REPORT_CHART_WIDTH: f64 = 800.0;
+const REPORT_CHART_HEIGHT: f64 = 160.0;
+
+/// Page of [`html_report`]; `{{TITLE}}`, `{{SUMMARY}}`, `{{CHART}}` and `{{DATA}}` are filled
+/// in.
+const REPORT_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{TITLE}}</title>
+<style>
+  body { font-family: sans-serif; margin: 1.5em auto; max-width: 60em; color: #222; }
+  h1 { font-size: 1.4em; margin-bottom: 0.2em; }
+  .summary { color: #555; margin-top: 0; }
+  .controls { display: flex; gap: 0.8em; align-items: center; margin: 1em 0 0.5em; }
+  .controls input[type=range] { flex: 1; }
+  #time { font-variant-numeric: tabular-nums; min-width: 9em; }
+  #doc { white-space: pre-wrap; word-wrap: break-word; font: 15px/1.5 monospace;
+         border: 1px solid #ccc; border-radius: 4px; padding: 0.8em; height: 24em; overflow-y: auto; }
+  #caret { border-left: 2px solid #d7301f; margin-right: -2px; }
+  #sel { background: #9cc3f5; }
+  #chart { display: block; margin-top: 0.5em; }
+  #corrections { font-size: 0.9em; padding-left: 0; list-style: none; max-height: 16em; overflow-y: auto; }
+  #corrections li { margin: 0.15em 0; }
+  #corrections button { font: inherit; font-variant-numeric: tabular-nums; cursor: pointer; }
+  #corrections .where { color: #777; margin: 0 0.5em; }
+</style>
+</head>
+<body>
+<h1>{{TITLE}}</h1>
+<p class="summary">{{SUMMARY}}</p>
+<div class="controls">
+  <button id="play">Play</button>
+  <label>Speed <select id="speed">
+    <option value="1">1×</option><option value="5">5×</option><option value="20" selected>20×</option>
+    <option value="100">100×</option><option value="500">500×</option>
+  </select></label>
+  <input id="seek" type="range" min="0" step="1" value="0">
+  <span id="time"></span>
+</div>
+<div id="doc"><span id="pre"></span><span id="caret"></span><span id="sel"></span><span id="post"></span></div>
+<h2>Typing speed</h2>
+{{CHART}}
+<h2>Corrections</h2>
+<ol id="corrections"></ol>
+<script type="application/json" id="plan-data">{{DATA}}</script>
+<script>
+"use strict";
+const data = JSON.parse(document.getElementById("plan-data").textContent);
+const $ = (id) => document.getElementById(id);
+const seek = $("seek");
+seek.max = data.total_ms;
+let now = 0, playing = false, last = null;
+
+function clock(ms) {
+  const s = Math.floor(ms / 1000);
+  const h = Math.floor(s / 3600), m = Math.floor(s / 60) % 60, sec = String(s % 60).padStart(2, "0");
+  return h > 0 ? `${h}:${String(m).padStart(2, "0")}:${sec}` : `${m}:${sec}`;
+}
+
+function cursorAt(t) {
+  let lo = 0, hi = data.cursor.length;
+  while (lo < hi) {
+    const mid = (lo + hi) >> 1;
+    if (data.cursor[mid][0] <= t) lo = mid + 1; else hi = mid;
+  }
+  return lo > 0 ? data.cursor[lo - 1] : [0, -1, null];
+}
+
+function render() {
+  const [, caret, other] = cursorAt(now);
+  const lo = other === null ? caret : Math.min(caret, other);
+  const hi = other === null ? caret : Math.max(caret, other);
+  let pre = "", sel = "", post = "";
+  data.chars.forEach(([c, typed, deleted], idx) => {
+    if (typed > now || (deleted !== null && deleted <= now)) return;
+    if (idx <= lo) pre += c; else if (idx <= hi) sel += c; else post += c;
+  });
+  const doc = $("doc");
+  if (other !== null && other < caret) {
+    doc.replaceChildren($("pre"), $("sel"), $("caret"), $("post"));
+  } else {
+    doc.replaceChildren($("pre"), $("caret"), $("sel"), $("post"));
+  }
+  $("pre").textContent = pre;
+  $("sel").textContent = sel;
+  $("post").textContent = post;
+  $("caret").scrollIntoView({ block: "nearest" });
+  seek.value = now;
+  $("time").textContent = `${clock(now)} / ${clock(data.total_ms)}`;
+  const marker = $("marker");
+  if (marker) {
+    const x = data.total_ms > 0 ? (now / data.total_ms) * {{CHART_WIDTH}} : 0;
+    marker.setAttribute("x1", x);
+    marker.setAttribute("x2", x);
+  }
+}
+
+function frame(stamp) {
+  if (!playing) return;
+  if (last !== null) now = Math.min(data.total_ms, now + (stamp - last) * Number($("speed").value));
+  last = stamp;
+  render();
+  if (now >= data.total_ms) setPlaying(false); else requestAnimationFrame(frame);
+}
+
+function setPlaying(on) {
+  playing = on;
+  last = null;
+  $("play").textContent = on ? "Pause" : "Play";
+  if (on) {
+    if (now >= data.total_ms) now = 0;
+    requestAnimationFrame(frame);
+  }
+}
+
+$("play").addEventListener("click", () => setPlaying(!playing));
+seek.addEventListener("input", () => { now = Number(seek.value); render(); });
+
+for (const correction of data.corrections) {
+  const item = document.createElement("li");
+  const jump = document.createElement("button");
+  jump.textContent = clock(correction.at_ms);
+  jump.addEventListener("click", () => { now = correction.at_ms; render(); });
+  const where = document.createElement("span");
+  where.className = "where";
+  where.textContent = `L${correction.line}:${correction.column}`;
+  item.append(jump, where, correction.text);
+  $("corrections").append(item);
+}
+if (data.corrections.length === 0) $("corrections").textContent = "None.";
+render();
+</script>
+</body>
+</html>
+"##;
+
+/// Terminal size and title of a cast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CastOptions {
+    pub width: usize,
+    pub height: usize,
+    pub title: Option<String>,
+}
+
+impl Default for CastOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            height: 24,
+            title: None,
+        }
+    }
+}
+
+/// An asciinema v2 cast of `plan` being typed into an empty editor, with the plan's timing.
+pub fn asciinema_cast(plan: &Plan, options: &CastOptions) -> Result<String> {
+    let width = options.width.max(1);
+    let height = options.height.max(1);
+    let mut header = json!({
+        "version": 2,
+        "width": width,
+        "height": height,
+    });
+    if let Some(title) = &options.title {
+        header["title"] = title.clone().into();
+    }
+
+    let mut cast = serde_json::to_string(&header).context("failed to serialize cast header")?;
+    cast.push('\n');
+    for (at_ms, output) in terminal_output(plan, width, height)? {
+        let event = json!([at_ms as f64 / 1000.0, "o", output]);
+        cast.push_str(&event.to_string());
+        cast.push('\n');
+    }
+    Ok(cast)
+}
+
+/// How [`play_in_terminal`] plays a plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalPreview {
+    pub width: usize,
+    pub height: usize,
+    /// How many times faster than planned to play: `2.0` halves every wait.
+    pub speed: f64,
+    /// Waits longer than this are cut to it, as with `PlaybackOptions::max_wait_ms`, before
+    /// `speed` applies.
+    pub max_wait_ms: Option<u64>,
+}
+
+impl Default for TerminalPreview {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            height: 24,
+            speed: 1.0,
+            max_wait_ms: None,
+        }
+    }
+}
+
+/// Plays `plan` in the terminal `out` writes to, as it would be typed into an empty editor:
+/// the screen of [`asciinema_cast`], written as the plan's (scaled) timing goes. Stops early,
+/// returning `false`, when `cancel` is cancelled; [`CancellationToken::skip_wait`] skips the
+/// pause in progress.
+pub fn play_in_terminal(
+    plan: &Plan,
+    options: &TerminalPreview,
+    out: &mut impl Write,
+    cancel: &CancellationToken,
+) -> Result<bool> {
+    let mut plan = plan.clone();
+    if let Some(max_ms) = options.max_wait_ms {
+        plan.actions = plan
+            .actions
+            .into_iter()
+            .map(|action| clamp_waits(action, max_ms))
+            .collect();
+    }
+    if !(options.speed.is_finite() && options.speed > 0.0) {
+        return Err(anyhow!(
+            "preview speed must be a positive number, got {}",
+            options.speed
+        ));
+    }
+    if options.speed != 1.0 {
+        plan = plan_edit::scale_waits(&plan, 1.0 / options.speed)?;
+    }
+
+    let frames = terminal_output(&plan, options.width.max(1), options.height.max(1))?;
+    out.write_all(b"\x1b[H\x1b[2J")
+        .and_then(|()| out.flush())
+        .context("failed to write to the terminal")?;
+    let mut last_ms = 0;
+    for (at_ms, output) in frames {
+        if cancel.sleep(at_ms - last_ms) {
+            return Ok(false);
+        }
+        last_ms = at_ms;
+        out.write_all(output.as_bytes())
+            .and_then(|()| out.flush())
+            .context("failed to write to the terminal")?;
+    }
+    Ok(!cancel.is_cancelled())
+}
+
+/// What a `width` x `height` terminal showing the editor is sent while `plan` is typed, with
+/// when: typing at the end of the text as is, and a [`redraw`] for every other edit.
+fn terminal_output(plan: &Plan, width: usize, height: usize) -> Result<Vec<(u64, String)>> {
+    let mut output = Vec::new();
+    sim::replay(plan, EditorBehavior::default(), |now_ms, editor, edit| {
+        let sent = match edit {
+            SimEdit::Appended('\n') => "\r\n".to_string(),
+            SimEdit::Appended(c) => c.to_string(),
+            SimEdit::Edited => redraw(editor, width, height),
+        };
+        output.push((now_ms, sent));
+    })
+    .context("failed to replay plan")?;
+    Ok(output)
+}
+
+/// Escape sequences clearing the screen and drawing the editor's text, scrolled so the cursor
+/// is on screen.
+fn redraw(editor: &EditorModel, width: usize, height: usize) -> String {
+    let text = editor.text();
+    let rows = screen_rows(text, width);
+    let cursor = editor.cursor();
+    let line_start = text[..cursor]
+        .iter()
+        .rposition(|c| *c == '\n')
+        .map_or(0, |newline| newline + 1);
+    let first_row = rows
+        .iter()
+        .position(|row| row.start == line_start)
+        .unwrap_or(0);
+    // After a full row, the cursor is at the start of the next one, as the terminal would put
+    // the next character typed.
+    let cursor_row = first_row + (cursor - line_start) / width;
+    let cursor_col = (cursor - line_start) % width;
+    let top = (cursor_row + 1).saturating_sub(height);
+    let selection = editor.selection();
+
+    let mut out = String::from("\x1b[H\x1b[2J");
+    for (idx, row) in rows.iter().enumerate().skip(top).take(height) {
+        if idx > top {
+            out.push_str("\r\n");
+        }
+        let mut selected = false;
+        for pos in row.clone() {
+            let in_selection = selection
+                .as_ref()
+                .is_some_and(|selection| selection.contains(&pos));
+            if in_selection != selected {
+                out.push_str(if in_selection { "\x1b[7m" } else { "\x1b[27m" });
+                selected = in_selection;
+            }
+            out.push(text[pos]);
+        }
+        if selected {
+            out.push_str("\x1b[27m");
+        }
+    }
+    out.push_str(&format!(
+        "\x1b[{};{}H",
+        cursor_row - top + 1,
+        cursor_col + 1
+    ));
+    out
+}
+
+/// Ranges of `text` shown on each screen row: its lines, wrapped at `width` characters, without
+/// the newlines.
+fn screen_rows(text: &[char], width: usize) -> Vec<Range<usize>> {
+    let mut rows = Vec::new();
+    let mut line_start = 0;
+    for line in text.split(|c| *c == '\n') {
+        let line_end = line_start + line.len();
+        let mut row_start = line_start;
+        loop {
+            let row_end = (row_start + width).min(line_end);
+            rows.push(row_start..row_end);
+            if row_end == line_end {
+                break;
+            }
+            row_start = row_end;
+        }
+        line_start = line_end + 1;
+    }
+    rows
+}
+
+/// Every character a plan types, in document order with the deleted ones where they were,
+/// and the cursor after each edit: what the HTML player needs to show the text at any time.
+#[derive(Debug, Default)]
+struct TypingHistory {
+    /// Character, when it was typed and when it was deleted, in document order.
+    chars: Vec<(char, u64, Option<u64>)>,
+    /// Time, the index in `chars` of the character before the cursor (`-1` at the start), and
+    /// the same for the other end of the selection, if any.
+    cursor: Vec<(u64, i64, Option<i64>)>,
+}
+
+/// Node of the list of every character typed, in document order, before any typed character.
+const HISTORY_HEAD: usize = usize::MAX;
+
+fn typing_history(plan: &Plan) -> Result<TypingHistory> {
+    // Per insertion id: the character, when it was typed and deleted, and the next id in
+    // document order.
+    let mut typed: Vec<(char, u64, Option<u64>)> = Vec::new();
+    let mut next: Vec<Option<usize>> = Vec::new();
+    let mut first: Option<usize> = None;
+    // Ids of the characters in the editor, as after the last edit.
+    let mut visible: Vec<usize> = Vec::new();
+    let mut cursor_ids: Vec<(u64, usize, Option<usize>)> = Vec::new();
+    let (mut cursor, mut selection_start) = (0usize, None::<usize>);
+
+    sim::replay(plan, EditorBehavior::default(), |now_ms, editor, _| {
+        // Without undo, every edit changes the text from the cursor or the selection on.
+        let at = cursor
+            .min(selection_start.unwrap_or(usize::MAX))
+            .min(editor.cursor());
+        let ids = editor.char_ids();
+        let inserted = ids.get(at).filter(|&&id| id >= typed.len()).copied();
+        let removed = visible.len() + usize::from(inserted.is_some()) - ids.len();
+        for &id in &visible[at..at + removed] {
+            typed[id].2 = Some(now_ms);
+        }
+        if let Some(id) = inserted {
+            typed.push((editor.text()[at], now_ms, None));
+            let after = at.checked_sub(1).map_or(HISTORY_HEAD, |prev| visible[prev]);
+            let following = if after == HISTORY_HEAD {
+                first.replace(id)
+            } else {
+                next[after].replace(id)
+            };
+            next.push(following);
+        }
+        visible.splice(at..at + removed, inserted);
+
+        let before = |pos: usize| {
+            pos.checked_sub(1)
+                .map_or(HISTORY_HEAD, |prev| visible[prev])
+        };
+        cursor = editor.cursor();
+        let selection = editor.selection();
+        let other_end = selection.as_ref().map(|selection| {
+            if selection.start == cursor {
+                selection.end
+            } else {
+                selection.start
+            }
+        });
+        cursor_ids.push((now_ms, before(cursor), other_end.map(before)));
+        selection_start = selection.map(|selection| selection.start);
+    })
+    .context("failed to replay plan")?;
+
+    let mut order = vec![0i64; typed.len()];
+    let mut history = TypingHistory::default();
+    let mut node = first;
+    while let Some(id) = node {
+        order[id] = history.chars.len() as i64;
+        history.chars.push(typed[id]);
+        node = next[id];
+    }
+    let position = |id: usize| if id == HISTORY_HEAD { -1 } else { order[id] };
+    history.cursor = cursor_ids
+        .into_iter()
+        .map(|(at_ms, cursor, other_end)| (at_ms, position(cursor), other_end.map(position)))
+        .collect();
+    Ok(history)
+}
+
+/// A self-contained HTML page previewing `plan`: a player showing the document being typed and
+/// corrected with the planned timing, the list of corrections, and a chart of typing speed.
+pub fn html_report(plan: &Plan, title: &str) -> Result<String> {
+    let history = typing_history(plan)?;
+    let total_ms = sim::stats(plan).total_wait_ms;
+
+    let corrections: Vec<serde_json::Value> = trace_events_for_plan(plan)?
+        .into_iter()
+        .filter(|event| event.kind == TraceEventKind::Replace)
+        .map(|event| {
+            json!({
+                "at_ms": event.offset_ms,
+                "line": event.cursor.line,
+                "column": event.cursor.column,
+                "text": event.line,
+            })
+        })
+        .collect();
+
+    let options = AnalysisOptions {
+        wpm_window_ms: REPORT_WPM_WINDOW_MS,
+        longest_pauses: 0,
+        rolling_wpm_step_ms: Some((total_ms / REPORT_WPM_POINTS).max(1)),
+        pause_threshold_ms: None,
+    };
+    let analysis = analyze(plan, &options)?;
+    let samples: Vec<(u64, f64)> = analysis
+        .rolling_wpm
+        .iter()
+        .map(|sample| (sample.at_ms, sample.wpm))
+        .collect();
+
+    let data = json!({
+        "total_ms": total_ms,
+        "chars": history
+            .chars
+            .iter()
+            .map(|(c, typed, deleted)| json!([c.to_string(), typed, deleted]))
+            .collect::<Vec<_>>(),
+        "cursor": history.cursor,
+        "corrections": corrections,
+    });
+    // Keep `</script>` in the text from ending the data early.
+    let data = data.to_string().replace("</", "<\\/");
+
+    let summary = format!(
+        "{} min, {} characters typed, {} in the final text, {} corrections",
+        (total_ms as f64 / 60_000.0 * 10.0).round() / 10.0,
+        analysis.typed_chars,
+        history
+            .chars
+            .iter()
+            .filter(|(_, _, deleted)| deleted.is_none())
+            .count(),
+        corrections.len()
+    );
+    Ok(REPORT_TEMPLATE
+        .replace("{{TITLE}}", &xml_escape(title))
+        .replace("{{SUMMARY}}", &summary)
+        .replace("{{CHART}}", &wpm_chart_svg(&samples, total_ms))
+        .replace("{{CHART_WIDTH}}", &REPORT_CHART_WIDTH.to_string())
+        .replace("{{DATA}}", &data))
+}
+
+/// Line chart of `(time, WPM)` samples over `total_ms`, with the `marker` line the player
+/// moves along.
+fn wpm_chart_svg(samples: &[(u64, f64)], total_ms: u64) -> String {
+    let (width, height) = (REPORT_CHART_WIDTH, REPORT_CHART_HEIGHT);
+    let fastest = samples.iter().map(|(_, wpm)| *wpm).fold(1.0, f64::max);
+    let points: Vec<String> = samples
+        .iter()
+        .map(|&(at_ms, wpm)| {
+            let x = at_ms as f64 / total_ms.max(1) as f64 * width;
+            let y = height - wpm / fastest * (height - 10.0);
+            format!("{x:.1},{y:.1}")
+        })
+        .collect();
+    let mut svg = format!(
+        "<svg id=\"chart\" xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" \
+         height=\"{}\" viewBox=\"0 0 {width} {}\" font-size=\"11\">\n",
+        height + 16.0,
+        height + 16.0
+    );
+    let _ = writeln!(
+        svg,
+        "  <rect width=\"{width}\" height=\"{height}\" fill=\"#fafafa\" stroke=\"#ddd\"/>"
+    );
+    let _ = writeln!(
+        svg,
+        "  <polyline points=\"{}\" fill=\"none\" stroke=\"#2b6cb0\" stroke-width=\"1.5\"/>",
+        points.join(" ")
+    );
+    let _ = writeln!(
+        svg,
+        "  <text x=\"4\" y=\"20\" fill=\"#555\">{fastest:.0} WPM</text>"
+    );
+    let _ = writeln!(
+        svg,
+        "  <text x=\"0\" y=\"{}\" fill=\"#555\">0:00</text>\n  \
+         <text x=\"{width}\" y=\"{}\" fill=\"#555\" text-anchor=\"end\">{:.1} min</text>",
+        height + 13.0,
+        height + 13.0,
+        total_ms as f64 / 60_000.0
+    );
+    let _ = writeln!(
+        svg,
+        "  <line id=\"marker\" x1=\"0\" x2=\"0\" y1=\"0\" y2=\"{height}\" stroke=\"#d7301f\"/>"
+    );
+    svg.push_str("</svg>");
+    svg
+}
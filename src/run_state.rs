@@ -0,0 +1,199 @@
+//! Where a stopped `drafter run` left off: the plans it was playing and the first action not
+//! played, stashed in a state directory so `drafter run --resume-last` can play the rest without
+//! planning again.
+//!
+//! The directory holds each plan as `plan.json`, `plan-2.json`, ... (playable with `drafter
+//! play`) and `progress.json`:
+//!
+//! ```json
+//! { "plans": ["plan.json", "plan-2.json"], "action_index": 1834, "repeat_taps": 0 }
+//! ```
+//!
+//! `--state-dir` may name any directory, so only these files are ever written or removed there.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Action, Plan};
+use crate::plan_edit;
+use crate::sim::{self, EditorBehavior};
+
+const PROGRESS_FILE: &str = "progress.json";
+
+/// The state directory used without `--state-dir`: `$XDG_STATE_HOME/drafter/last-run`, falling
+/// back to `~/.local/state/drafter/last-run`.
+pub fn default_dir() -> Option<PathBuf> {
+    let non_empty = |var: &str| std::env::var_os(var).filter(|value| !value.is_empty());
+    let base = non_empty("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(base.join("drafter").join("last-run"))
+}
+
+/// A run stopped partway: the plans still to play, one after the other, and how far into the
+/// first one playback got.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunState {
+    pub plans: Vec<Plan>,
+    /// Index of the first action of `plans[0]` that was not played in full. A wait cut short is
+    /// played again in full.
+    pub action_index: usize,
+    /// Taps already sent of the [`Action::KeyRepeat`] at `action_index`, if it is one.
+    pub repeat_taps: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Progress {
+    plans: Vec<PathBuf>,
+    action_index: usize,
+    #[serde(default)]
+    repeat_taps: u32,
+}
+
+impl RunState {
+    /// Writes the state to `dir`, creating it and replacing any state already there. Other files
+    /// in `dir` are left alone.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        clear(dir)?;
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let mut files = Vec::new();
+        for (idx, plan) in self.plans.iter().enumerate() {
+            let file = match idx {
+                0 => PathBuf::from("plan.json"),
+                _ => PathBuf::from(format!("plan-{}.json", idx + 1)),
+            };
+            let json = serde_json::to_string_pretty(plan).context("failed to serialize plan")?;
+            write(&dir.join(&file), &json)?;
+            files.push(file);
+        }
+        let progress = Progress {
+            plans: files,
+            action_index: self.action_index,
+            repeat_taps: self.repeat_taps,
+        };
+        let json = serde_json::to_string_pretty(&progress).context("failed to serialize run")?;
+        write(&dir.join(PROGRESS_FILE), &json)
+    }
+
+    /// Reads the state [`RunState::save`] wrote to `dir`.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(PROGRESS_FILE);
+        if !path.exists() {
+            return Err(anyhow!(
+                "no stopped run to resume in {}: nothing was stashed, or the run finished",
+                dir.display()
+            ));
+        }
+        let progress: Progress = serde_json::from_str(&read(&path)?)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        let plans = progress
+            .plans
+            .iter()
+            .map(|file| {
+                let path = dir.join(file);
+                crate::plan::load(&read(&path)?)
+                    .with_context(|| format!("failed to load {}", path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let Some(first) = plans.first() else {
+            return Err(anyhow!("{} lists no plans", path.display()));
+        };
+        if progress.action_index > first.actions.len() {
+            return Err(anyhow!(
+                "{} stops at action {} of a plan with {}",
+                path.display(),
+                progress.action_index,
+                first.actions.len()
+            ));
+        }
+        Ok(Self {
+            plans,
+            action_index: progress.action_index,
+            repeat_taps: progress.repeat_taps,
+        })
+    }
+
+    /// The plans left to play: the first one from [`RunState::action_index`] on, with only the
+    /// modifiers held there pressed again (see [`plan_edit::resume_at`]) and only the taps left
+    /// of a key repeat stopped partway, then the others. A first plan played to its end is left
+    /// out.
+    pub fn remaining(&self) -> Result<Vec<Plan>> {
+        let mut plans = self.plans.clone();
+        if self.action_index >= plans[0].actions.len() {
+            plans.remove(0);
+        } else if self.action_index > 0 || self.repeat_taps > 0 {
+            let mut first = plans[0].clone();
+            if let Some(Action::KeyRepeat { count, .. }) = first.actions.get_mut(self.action_index)
+            {
+                *count = count.saturating_sub(self.repeat_taps);
+            }
+            plans[0] = plan_edit::resume_at(&first, self.action_index)?;
+        }
+        Ok(plans)
+    }
+
+    /// The text the first plan had typed when it stopped, and how many chars before its end the
+    /// cursor was.
+    pub fn typed_so_far(&self) -> Result<(String, usize)> {
+        let plan = &self.plans[0];
+        let mut actions = plan.actions[..self.action_index].to_vec();
+        if let Some(&Action::KeyRepeat {
+            keycode,
+            interval_ms,
+            ..
+        }) = plan.actions.get(self.action_index)
+        {
+            actions.push(Action::KeyRepeat {
+                keycode,
+                count: self.repeat_taps,
+                interval_ms,
+            });
+        }
+        let played = Plan {
+            actions,
+            ..plan.clone()
+        };
+        let editor = sim::replay(&played, EditorBehavior::default(), |_, _, _| {})?;
+        Ok((editor.as_string(), editor.text().len() - editor.cursor()))
+    }
+}
+
+/// Removes the state in `dir`, if any: `progress.json` and the plan files, but not `dir` itself
+/// or anything else in it.
+pub fn clear(dir: &Path) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        entries => entries.with_context(|| format!("failed to read {}", dir.display()))?,
+    };
+    for entry in entries {
+        let entry = entry.with_context(|| format!("failed to read {}", dir.display()))?;
+        let name = entry.file_name();
+        if !name.to_str().is_some_and(is_state_file) || !entry.path().is_file() {
+            continue;
+        }
+        fs::remove_file(entry.path())
+            .with_context(|| format!("failed to remove {}", entry.path().display()))?;
+    }
+    Ok(())
+}
+
+/// Whether `name` is a file [`RunState::save`] writes: `progress.json`, `plan.json` or
+/// `plan-N.json`.
+fn is_state_file(name: &str) -> bool {
+    let numbered = name
+        .strip_prefix("plan-")
+        .and_then(|rest| rest.strip_suffix(".json"))
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    name == PROGRESS_FILE || name == "plan.json" || numbered
+}
+
+fn read(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+fn write(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
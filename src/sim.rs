@@ -1,11 +1,10 @@
-use std::collections::HashMap;
-
 use anyhow::{anyhow, Result};
 
+use crate::editor::{CodeEditor, EditorModel};
 use crate::keyboard::{
-    keystroke_for_output_char, KEY_BACKSPACE, KEY_DELETE, KEY_LEFT, KEY_LEFTCTRL, KEY_LEFTSHIFT,
-    KEY_RIGHT, KEY_RIGHTCTRL, KEY_RIGHTSHIFT,
+    KeyStroke, KEY_CAPSLOCK, KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_RIGHTCTRL, KEY_RIGHTSHIFT,
 };
+use crate::layout::{Decoded, KeyboardLayout};
 use crate::model::{Action, KeyState, Plan};
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -13,6 +12,7 @@ pub struct PlanStats {
     pub actions: usize,
     pub key_events: usize,
     pub modifier_updates: usize,
+    /// Planned time: the waits, and the time held keys take to repeat.
     pub total_wait_ms: u64,
 }
 
@@ -29,101 +29,132 @@ pub fn stats(plan: &Plan) -> PlanStats {
             }
             Action::Modifiers { .. } => out.modifier_updates += 1,
             Action::Key { .. } => out.key_events += 1,
+            Action::KeyRepeat { count, .. } => {
+                out.key_events += 2 * *count as usize;
+                out.total_wait_ms = out.total_wait_ms.saturating_add(a.duration_ms());
+            }
+            Action::Checkpoint { .. } => {}
         }
     }
 
     out
 }
 
-#[derive(Debug, Default, Clone)]
-struct SimEditorState {
-    buf: Vec<char>,
-    cursor: usize,
+/// Editor features [`simulate_typed_text_with`] models that plain text fields may not have.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EditorBehavior {
+    /// Ctrl+Z undoes and Ctrl+Y or Ctrl+Shift+Z redoes, with edits grouped as described in
+    /// [`crate::editor`]. The keys are found by the characters they type on the plan's layout.
+    pub undo: bool,
+    /// Typing whitespace after one of the typos in [`crate::editor::AUTOCORRECTIONS`] corrects
+    /// it, see [`EditorModel::autocorrect`].
+    pub autocorrect: bool,
+    /// Enter keeps the indentation and brackets close themselves, see [`CodeEditor`].
+    pub code_editor: CodeEditor,
 }
 
-impl SimEditorState {
-    fn insert_char(&mut self, c: char) {
-        self.buf.insert(self.cursor, c);
-        self.cursor += 1;
-    }
-
-    fn backspace(&mut self) {
-        if self.cursor == 0 {
-            return;
-        }
-        self.cursor -= 1;
-        self.buf.remove(self.cursor);
-    }
-
-    fn delete(&mut self) {
-        if self.cursor >= self.buf.len() {
-            return;
-        }
-        self.buf.remove(self.cursor);
-    }
-
-    fn move_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-        }
-    }
-
-    fn move_right(&mut self) {
-        if self.cursor < self.buf.len() {
-            self.cursor += 1;
-        }
-    }
-
-    fn move_word_left(&mut self) {
-        self.cursor = crate::word_nav::ctrl_left(&self.buf, self.cursor, is_word_char);
-    }
-
-    fn move_word_right(&mut self) {
-        self.cursor = crate::word_nav::ctrl_right(&self.buf, self.cursor, is_word_char);
-    }
-
-    fn as_string(&self) -> String {
-        self.buf.iter().collect()
-    }
+/// Simulate the final editor text produced by a plan.
+///
+/// This is intended for tests/debugging. It applies keystrokes to an [`EditorModel`] (cursor
+/// movement, selection, insertion and deletion), decoding them (including dead-key and Compose
+/// sequences) with the plan's keyboard layout and CapsLock state. It does not model editor-specific behaviors such
+/// as smart-quote or em-dash/ellipsis auto-substitution, nor undo, autocorrect, auto-indent or
+/// auto-closing brackets; see [`simulate_typed_text_with`].
+pub fn simulate_typed_text(plan: &Plan) -> Result<String> {
+    simulate_typed_text_with(plan, EditorBehavior::default())
 }
 
-fn is_word_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '\''
+/// [`simulate_typed_text`] in an editor with `behavior`.
+pub fn simulate_typed_text_with(plan: &Plan, behavior: EditorBehavior) -> Result<String> {
+    Ok(replay(plan, behavior, |_, _, _| {})?.as_string())
 }
 
-fn us_qwerty_keystroke_map() -> HashMap<(u32, bool), char> {
-    let mut map = HashMap::new();
-
-    let mut candidates = Vec::new();
-    candidates.push('\n');
-    candidates.push(' ');
-    for b in 33u8..=126u8 {
-        candidates.push(b as char);
-    }
-
-    for c in candidates {
-        if let Some(stroke) = keystroke_for_output_char(c) {
-            map.insert((stroke.keycode, stroke.shift), c);
-        }
+/// Characters of context shown around a difference by [`verify_typed_text`].
+const DIFFERENCE_CONTEXT_CHARS: usize = 20;
+
+/// Checks that `plan` types exactly `expected` (see [`simulate_typed_text`]), and says where
+/// the texts first differ if not. `expected` is the draft: its whitespace and paragraph breaks
+/// are compared as the plan types them (see [`crate::model::PlanConfig::typed_text`]).
+pub fn verify_typed_text(plan: &Plan, expected: &str) -> Result<()> {
+    let typed = simulate_typed_text(plan)?;
+    let expected = plan.config.typed_text(expected);
+    let expected = expected.as_str();
+    if typed == expected {
+        return Ok(());
     }
+    let common = expected
+        .chars()
+        .zip(typed.chars())
+        .take_while(|(expected, typed)| expected == typed)
+        .count();
+    let before: String = expected.chars().take(common).collect();
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    let rest = |text: &str| -> String {
+        text.chars()
+            .skip(common)
+            .take(DIFFERENCE_CONTEXT_CHARS)
+            .collect()
+    };
+    let (expected_rest, typed_rest) = (rest(expected), rest(&typed));
+    let detail = if expected_rest.is_empty() {
+        format!("the plan types {typed_rest:?} after the end")
+    } else if typed_rest.is_empty() {
+        format!("the plan stops before {expected_rest:?}")
+    } else {
+        format!("expected {expected_rest:?}, the plan types {typed_rest:?}")
+    };
+    Err(anyhow!(
+        "typed text differs at line {line}, column {column}: {detail}"
+    ))
+}
 
-    map
+/// How a key press changed the editor, for [`replay`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimEdit {
+    /// The character was typed at the end of the text, with nothing selected.
+    Appended(char),
+    /// Anything else: typing elsewhere or over a selection, deleting, moving the cursor,
+    /// undo and redo.
+    Edited,
 }
 
-/// Simulate the final editor text produced by a plan.
-///
-/// This is intended for tests/debugging. It applies basic cursor movement and
-/// insertion/deletion for a US-QWERTY layout. It does not model editor-specific
-/// behaviors such as smart-quote auto-substitution.
-pub fn simulate_typed_text(plan: &Plan) -> Result<String> {
-    let mut editor = SimEditorState::default();
+/// Applies `plan` to an editor with `behavior` like [`simulate_typed_text_with`], calling
+/// `on_edit` with the planned time in milliseconds and the editor after every key press that
+/// changed its text, cursor or selection (or may have). Returns the final editor.
+pub fn replay(
+    plan: &Plan,
+    behavior: EditorBehavior,
+    mut on_edit: impl FnMut(u64, &EditorModel, SimEdit),
+) -> Result<EditorModel> {
+    let mut now_ms = 0u64;
+    let mut editor = if behavior.undo {
+        EditorModel::with_undo()
+    } else {
+        EditorModel::new()
+    };
     let mut shift_down = false;
     let mut ctrl_down = false;
-    let keystrokes = us_qwerty_keystroke_map();
+    let mut altgr_down = false;
+    let layout = KeyboardLayout::for_plan_config(&plan.config)?;
+    let mut decoder = layout.decoder();
+    let altgr_keycode = decoder.altgr_keycode();
+    let letter_keycode = |c: char| match layout.strokes_for_output_char(c) {
+        Some([stroke]) if behavior.undo && !stroke.altgr => Some(stroke.keycode),
+        _ => None,
+    };
+    let (undo_keycode, redo_keycode) = (letter_keycode('z'), letter_keycode('y'));
 
-    for action in &plan.actions {
-        let Action::Key { keycode, state } = action else {
-            continue;
+    for action in plan.actions.iter().flat_map(Action::expand) {
+        let (keycode, state) = match &action {
+            Action::Key { keycode, state } => (keycode, state),
+            Action::Wait { ms } => {
+                now_ms = now_ms.saturating_add(*ms);
+                continue;
+            }
+            Action::Modifiers { .. } | Action::Checkpoint { .. } | Action::KeyRepeat { .. } => {
+                continue
+            }
         };
 
         match (*keycode, *state) {
@@ -143,47 +174,76 @@ pub fn simulate_typed_text(plan: &Plan) -> Result<String> {
                 ctrl_down = false;
                 continue;
             }
+            (KEY_CAPSLOCK, KeyState::Pressed) => {
+                decoder.toggle_caps_lock();
+                continue;
+            }
+            (keycode, state) if Some(keycode) == altgr_keycode => {
+                altgr_down = state == KeyState::Pressed;
+                continue;
+            }
             (_, KeyState::Released) => continue,
             _ => {}
         }
 
-        match *keycode {
-            KEY_LEFT => {
-                if ctrl_down {
-                    editor.move_word_left();
-                } else {
-                    editor.move_left();
-                }
+        if editor
+            .press_edit_key(*keycode, shift_down, ctrl_down)
+            .is_some()
+        {
+            decoder.reset();
+            on_edit(now_ms, &editor, SimEdit::Edited);
+            continue;
+        }
+
+        if ctrl_down && Some(*keycode) == undo_keycode {
+            if shift_down {
+                editor.redo();
+            } else {
+                editor.undo();
             }
-            KEY_RIGHT => {
-                if ctrl_down {
-                    editor.move_word_right();
+            decoder.reset();
+            on_edit(now_ms, &editor, SimEdit::Edited);
+            continue;
+        }
+        if ctrl_down && Some(*keycode) == redo_keycode {
+            editor.redo();
+            decoder.reset();
+            on_edit(now_ms, &editor, SimEdit::Edited);
+            continue;
+        }
+
+        if ctrl_down {
+            return Err(anyhow!(
+                "simulate_typed_text does not support Ctrl+keycode {keycode}"
+            ));
+        }
+
+        let stroke = KeyStroke {
+            keycode: *keycode,
+            shift: shift_down,
+            altgr: altgr_down,
+        };
+        match decoder.feed(stroke) {
+            Decoded::Char(c) => {
+                let appended = editor.at_end() && !editor.has_selection();
+                let added = editor.type_char(c, behavior.code_editor);
+                let corrected = behavior.autocorrect && editor.autocorrect();
+                let edit = if appended && added == 0 && !corrected {
+                    SimEdit::Appended(c)
                 } else {
-                    editor.move_right();
-                }
+                    SimEdit::Edited
+                };
+                on_edit(now_ms, &editor, edit);
             }
-            KEY_BACKSPACE => editor.backspace(),
-            KEY_DELETE => editor.delete(),
-            _ => {
-                if ctrl_down {
-                    return Err(anyhow!(
-                        "simulate_typed_text does not support Ctrl+keycode {keycode}"
-                    ));
-                }
-
-                let c = keystrokes
-                    .get(&(*keycode, shift_down))
-                    .copied()
-                    .ok_or_else(|| {
-                        anyhow!(
-                            "simulate_typed_text does not support keycode {keycode} (shift={shift_down})"
-                        )
-                    })?;
-
-                editor.insert_char(c);
+            Decoded::Pending => {}
+            Decoded::Unknown => {
+                return Err(anyhow!(
+                    "simulate_typed_text does not support keycode {keycode} (shift={shift_down}, altgr={altgr_down}, caps_lock={})",
+                    decoder.caps_lock()
+                ));
             }
         }
     }
 
-    Ok(editor.as_string())
+    Ok(editor)
 }
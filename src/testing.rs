@@ -0,0 +1,90 @@
+//! Checks of the invariants every playable plan keeps, for tests and property-testing harnesses
+//! that build or generate plans.
+//!
+//! Each `check_*` function returns an error describing the first violation, so it works with `?`
+//! in tests returning `Result` and with `prop_assert!(check(..).is_ok(), ..)`.
+//! [`assert_plan_invariants`] runs the checks that hold for any plan and panics with the first
+//! failure.
+
+use std::ops::RangeInclusive;
+
+use anyhow::{anyhow, Result};
+
+use crate::model::{Action, Plan};
+use crate::plan_edit::KeyboardState;
+use crate::playback::backends::COMMON_MODIFIER_KEYCODES;
+use crate::sim;
+
+/// Checks that `plan` never presses a held key or releases a key that is not held, and releases
+/// every key other than a modifier (see [`check_no_stuck_modifiers`]).
+pub fn check_balanced_keys(plan: &Plan) -> Result<()> {
+    let state = KeyboardState::after(&plan.actions)?;
+    let held: Vec<u32> = state
+        .held
+        .into_iter()
+        .filter(|keycode| !COMMON_MODIFIER_KEYCODES.contains(keycode))
+        .collect();
+    if !held.is_empty() {
+        return Err(anyhow!("plan ends with keycodes {held:?} held"));
+    }
+    Ok(())
+}
+
+/// Checks that `plan` ends with Shift, Ctrl and Alt released, CapsLock off and, if it sends
+/// modifier states, no modifier set.
+pub fn check_no_stuck_modifiers(plan: &Plan) -> Result<()> {
+    let state = KeyboardState::after(&plan.actions)?;
+    let held: Vec<u32> = state
+        .held
+        .iter()
+        .copied()
+        .filter(|keycode| COMMON_MODIFIER_KEYCODES.contains(keycode))
+        .collect();
+    if !held.is_empty() {
+        return Err(anyhow!("plan ends with modifier keycodes {held:?} held"));
+    }
+    if state.caps_lock {
+        return Err(anyhow!("plan ends with CapsLock on"));
+    }
+    if !state.modifiers_neutral() {
+        return Err(anyhow!("plan ends with modifiers set"));
+    }
+    Ok(())
+}
+
+/// Checks that simulating `plan` leaves exactly `expected` in the editor; see
+/// [`sim::verify_typed_text`].
+pub fn check_typed_text(plan: &Plan, expected: &str) -> Result<()> {
+    sim::verify_typed_text(plan, expected)
+}
+
+/// Checks that every wait in `plan` lasts `bounds` milliseconds.
+pub fn check_waits_within(plan: &Plan, bounds: RangeInclusive<u64>) -> Result<()> {
+    for (idx, action) in plan.actions.iter().enumerate() {
+        if let Action::Wait { ms } = action {
+            if !bounds.contains(ms) {
+                return Err(anyhow!(
+                    "action {idx} waits {ms} ms, outside {}..={} ms",
+                    bounds.start(),
+                    bounds.end()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs [`check_balanced_keys`], [`check_no_stuck_modifiers`] and [`check_typed_text`].
+pub fn check_plan_invariants(plan: &Plan, expected: &str) -> Result<()> {
+    check_balanced_keys(plan)?;
+    check_no_stuck_modifiers(plan)?;
+    check_typed_text(plan, expected)
+}
+
+/// Panics unless `plan` passes [`check_plan_invariants`].
+#[track_caller]
+pub fn assert_plan_invariants(plan: &Plan, expected: &str) {
+    if let Err(err) = check_plan_invariants(plan, expected) {
+        panic!("plan invariant violated: {err:#}");
+    }
+}
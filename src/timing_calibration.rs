@@ -0,0 +1,249 @@
+//! Fitting the planner's key timing to a real typist (`calibrate-timing`).
+//!
+//! [`read_keystrokes`] reads a CSV export of real typing: either one inter-key latency per row
+//! (with an optional hold time), as keystroke logging tools export them, or the press and
+//! release times of each key, as in public keystroke datasets. [`fit`] turns it into the
+//! typing speed range and [`KeyRhythm`] whose plans match it statistically: the speed from the
+//! mean latency of windows of [`WINDOW_KEYS`] keys, the spread of the delays within a window,
+//! and the bounds of the delays and hold times.
+
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::planner::KeyRhythm;
+
+/// Keys per window whose mean latency gives one typing speed sample.
+pub const WINDOW_KEYS: usize = 50;
+/// Latencies of at least this many milliseconds are pauses (thinking, or a new session), which
+/// the planner adds on its own, and are left out of the fit.
+pub const PAUSE_MS: f64 = 2000.0;
+/// Fewer latencies than this are too few to fit.
+pub const MIN_LATENCIES: usize = 2 * WINDOW_KEYS;
+
+const LATENCY_COLUMNS: &[&str] = &[
+    "latency",
+    "latencyms",
+    "ikl",
+    "flight",
+    "flightms",
+    "delayms",
+];
+const HOLD_COLUMNS: &[&str] = &[
+    "hold",
+    "holdms",
+    "dwell",
+    "dwellms",
+    "duration",
+    "durationms",
+];
+const PRESS_COLUMNS: &[&str] = &["press", "presstime", "pressms", "keydown"];
+const RELEASE_COLUMNS: &[&str] = &["release", "releasetime", "releasems", "keyup"];
+
+/// Real keystroke timing, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeystrokeLog {
+    /// Press-to-press latencies of consecutive keys, in typing order.
+    pub latencies_ms: Vec<f64>,
+    /// Press-to-release times, if the export has them.
+    pub holds_ms: Vec<f64>,
+}
+
+/// Reads a CSV (or tab-separated) keystroke export with a header row. Columns are found by
+/// name, ignoring case, spaces, `-` and `_`:
+///
+/// - `latency`, `latency_ms`, `ikl`, `flight_ms` or `delay_ms`: press-to-press latency, with
+///   an optional `hold_ms`, `dwell_ms` or `duration_ms`;
+/// - or `press_time` (`keydown`) and `release_time` (`keyup`): key timestamps in
+///   milliseconds, in typing order.
+///
+/// Other columns are ignored, and so are rows with an empty time.
+pub fn read_keystrokes(reader: impl BufRead) -> Result<KeystrokeLog> {
+    let mut lines = reader.lines();
+    let header = lines.next().context("the keystroke CSV is empty")??;
+    let delimiter = if header.contains('\t') { '\t' } else { ',' };
+    let names: Vec<String> = split_row(&header, delimiter)
+        .iter()
+        .map(|name| {
+            name.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect()
+        })
+        .collect();
+    let column = |candidates: &[&str]| {
+        names
+            .iter()
+            .position(|name| candidates.contains(&name.as_str()))
+    };
+
+    let (latency, hold) = (column(LATENCY_COLUMNS), column(HOLD_COLUMNS));
+    let (press, release) = (column(PRESS_COLUMNS), column(RELEASE_COLUMNS));
+    if latency.is_none() && press.is_none() {
+        bail!(
+            "the keystroke CSV has no latency column ({}) nor press times ({})",
+            LATENCY_COLUMNS.join(", "),
+            PRESS_COLUMNS.join(", ")
+        );
+    }
+
+    let mut log = KeystrokeLog::default();
+    let mut last_press: Option<f64> = None;
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = split_row(&line, delimiter);
+        let value = |column: Option<usize>| -> Result<Option<f64>> {
+            let Some(field) = column.and_then(|column| row.get(column)) else {
+                return Ok(None);
+            };
+            if field.is_empty() {
+                return Ok(None);
+            }
+            field.parse().map(Some).map_err(|_| {
+                anyhow!(
+                    "line {}: {field:?} is not a number of milliseconds",
+                    index + 2
+                )
+            })
+        };
+
+        if latency.is_some() {
+            if let Some(ms) = value(latency)? {
+                log.latencies_ms.push(ms);
+            }
+            if let Some(ms) = value(hold)? {
+                log.holds_ms.push(ms);
+            }
+        } else if let Some(pressed) = value(press)? {
+            if let Some(previous) = last_press.replace(pressed) {
+                log.latencies_ms.push(pressed - previous);
+            }
+            if let Some(released) = value(release)? {
+                log.holds_ms.push(released - pressed);
+            }
+        }
+    }
+    Ok(log)
+}
+
+fn split_row(line: &str, delimiter: char) -> Vec<String> {
+    line.split(delimiter)
+        .map(|field| field.trim().trim_matches('"').trim().to_string())
+        .collect()
+}
+
+/// Planner timing fitted to a [`KeystrokeLog`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimingFit {
+    pub wpm_min: f64,
+    pub wpm_max: f64,
+    pub rhythm: KeyRhythm,
+    /// Latencies the fit used.
+    pub keystrokes: usize,
+    /// Latencies left out as pauses (or negative, from keys pressed out of order).
+    pub pauses: usize,
+}
+
+impl TimingFit {
+    /// The fit as command-line flags (without `--`) and their values, e.g. to save in a config
+    /// profile.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("wpm-min", format!("{:.1}", self.wpm_min)),
+            ("wpm-max", format!("{:.1}", self.wpm_max)),
+            (
+                "key-delay-spread",
+                format!("{:.3}", self.rhythm.delay_spread),
+            ),
+            ("key-delay-min-ms", self.rhythm.delay_min_ms.to_string()),
+            ("key-delay-max-ms", self.rhythm.delay_max_ms.to_string()),
+            ("key-hold-min-ms", self.rhythm.hold_min_ms.to_string()),
+            ("key-hold-max-ms", self.rhythm.hold_max_ms.to_string()),
+        ]
+    }
+}
+
+/// Fits the planner's speed range and [`KeyRhythm`] to `log`.
+///
+/// The planner waits a delay after each key's hold, so a latency is the hold plus the delay:
+/// the holds' 5th to 95th percentiles bound the hold time (the default bounds are kept without
+/// hold times), and the latencies less the mean hold are the delays. Their 1st and 99th
+/// percentiles bound the delay, their spread is the mean coefficient of variation within
+/// windows of [`WINDOW_KEYS`] keys (so a typist slowing down over a session does not count as
+/// spread), and the 10th and 90th percentiles of the windows' speeds (at five characters per
+/// word) are the speed range.
+pub fn fit(log: &KeystrokeLog) -> Result<TimingFit> {
+    let latencies: Vec<f64> = log
+        .latencies_ms
+        .iter()
+        .copied()
+        .filter(|ms| ms.is_finite() && *ms > 0.0 && *ms < PAUSE_MS)
+        .collect();
+    if latencies.len() < MIN_LATENCIES {
+        bail!(
+            "too few keystrokes to fit: {} latencies under {PAUSE_MS} ms, need at least \
+             {MIN_LATENCIES}",
+            latencies.len()
+        );
+    }
+
+    let mut rhythm = KeyRhythm::default();
+    let mut holds: Vec<f64> = log
+        .holds_ms
+        .iter()
+        .copied()
+        .filter(|ms| ms.is_finite() && *ms > 0.0 && *ms < PAUSE_MS)
+        .collect();
+    if !holds.is_empty() {
+        holds.sort_by(f64::total_cmp);
+        rhythm.hold_min_ms = (percentile(&holds, 0.05).round() as u64).max(1);
+        rhythm.hold_max_ms = (percentile(&holds, 0.95).round() as u64).max(rhythm.hold_min_ms);
+    }
+    let hold_ms = rhythm.mean_hold_ms();
+
+    // At least a millisecond: keys pressed before the previous one is released overlap.
+    let delays: Vec<f64> = latencies.iter().map(|ms| (ms - hold_ms).max(1.0)).collect();
+    let windows: Vec<&[f64]> = delays.chunks_exact(WINDOW_KEYS).collect();
+    let mut wpms: Vec<f64> = windows
+        .iter()
+        .map(|window| 12_000.0 / mean(window))
+        .collect();
+    wpms.sort_by(f64::total_cmp);
+    rhythm.delay_spread = windows
+        .iter()
+        .map(|window| {
+            let mean = mean(window);
+            let variance =
+                window.iter().map(|ms| (ms - mean).powi(2)).sum::<f64>() / window.len() as f64;
+            variance.sqrt() / mean
+        })
+        .sum::<f64>()
+        / windows.len() as f64;
+
+    let mut sorted = delays;
+    sorted.sort_by(f64::total_cmp);
+    rhythm.delay_min_ms = (percentile(&sorted, 0.01).round() as u64).max(1);
+    rhythm.delay_max_ms = (percentile(&sorted, 0.99).round() as u64).max(rhythm.delay_min_ms);
+
+    Ok(TimingFit {
+        wpm_min: percentile(&wpms, 0.1),
+        wpm_max: percentile(&wpms, 0.9),
+        rhythm,
+        keystrokes: latencies.len(),
+        pauses: log.latencies_ms.len() - latencies.len(),
+    })
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The `p` quantile of sorted, non-empty `values`, interpolating between neighbors.
+fn percentile(values: &[f64], p: f64) -> f64 {
+    let rank = p * (values.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    values[below] + (values[above] - values[below]) * (rank - below as f64)
+}
@@ -1,66 +1,17 @@
 use std::collections::HashMap;
+use std::ops::Range;
 
+use anyhow::Result;
+use serde::Serialize;
+
+pub use crate::editor::CursorPosition;
+use crate::editor::{is_word_char, EditorModel};
 use crate::keyboard::{
-    keystroke_for_output_char, KEY_BACKSPACE, KEY_DELETE, KEY_DOWN, KEY_END, KEY_HOME, KEY_LEFT,
+    KeyStroke, KEY_BACKSPACE, KEY_CAPSLOCK, KEY_DELETE, KEY_DOWN, KEY_END, KEY_HOME, KEY_LEFT,
     KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_RIGHT, KEY_RIGHTCTRL, KEY_RIGHTSHIFT, KEY_UP,
 };
-use crate::model::{Action, KeyState};
-
-#[derive(Debug, Default, Clone)]
-struct EditorState {
-    buf: Vec<char>,
-    cursor: usize,
-}
-
-impl EditorState {
-    fn insert_char(&mut self, c: char) {
-        self.buf.insert(self.cursor, c);
-        self.cursor += 1;
-    }
-
-    fn backspace(&mut self) -> Option<char> {
-        if self.cursor == 0 {
-            return None;
-        }
-        self.cursor -= 1;
-        Some(self.buf.remove(self.cursor))
-    }
-
-    fn delete(&mut self) -> Option<char> {
-        if self.cursor >= self.buf.len() {
-            return None;
-        }
-        Some(self.buf.remove(self.cursor))
-    }
-
-    fn move_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-        }
-    }
-
-    fn move_right(&mut self) {
-        if self.cursor < self.buf.len() {
-            self.cursor += 1;
-        }
-    }
-
-    fn move_word_left(&mut self) {
-        self.cursor = crate::word_nav::ctrl_left(&self.buf, self.cursor, is_word_char);
-    }
-
-    fn move_word_right(&mut self) {
-        self.cursor = crate::word_nav::ctrl_right(&self.buf, self.cursor, is_word_char);
-    }
-
-    fn home(&mut self) {
-        self.cursor = 0;
-    }
-
-    fn end(&mut self) {
-        self.cursor = self.buf.len();
-    }
-}
+use crate::layout::{Decoded, KeyDecoder, KeyboardLayout};
+use crate::model::{Action, KeyState, Plan};
 
 #[derive(Debug, Default, Clone)]
 struct CorrectionState {
@@ -87,11 +38,12 @@ impl CorrectionState {
 
 #[derive(Debug, Default, Clone)]
 pub struct PlaybackTracer {
-    keystrokes: HashMap<(u32, bool), char>,
-    editor: EditorState,
+    decoder: KeyDecoder,
+    editor: EditorModel,
 
     shift_down: bool,
     ctrl_down: bool,
+    altgr_down: bool,
 
     typing_run: String,
     correction: Option<CorrectionState>,
@@ -100,14 +52,28 @@ pub struct PlaybackTracer {
 }
 
 impl PlaybackTracer {
+    /// Decodes keystrokes with the built-in US-QWERTY mapping; see [`Self::with_layout`].
     pub fn new() -> Self {
         Self {
-            keystrokes: us_qwerty_keystroke_map(),
+            decoder: KeyDecoder::us_qwerty(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_layout(layout: &KeyboardLayout) -> Self {
+        Self {
+            decoder: layout.decoder(),
             ..Default::default()
         }
     }
 
     pub fn observe_action(&mut self, action: &Action) {
+        if let Action::KeyRepeat { .. } = action {
+            for tap in action.expand() {
+                self.observe_action(&tap);
+            }
+            return;
+        }
         let Action::Key { keycode, state } = action else {
             return;
         };
@@ -126,12 +92,20 @@ impl PlaybackTracer {
         self.drain_lines()
     }
 
-    fn decode_char(&self, keycode: u32) -> Option<char> {
-        self.keystrokes.get(&(keycode, self.shift_down)).copied()
+    fn decode_char(&mut self, keycode: u32) -> Option<char> {
+        let stroke = KeyStroke {
+            keycode,
+            shift: self.shift_down,
+            altgr: self.altgr_down,
+        };
+        match self.decoder.feed(stroke) {
+            Decoded::Char(c) => Some(c),
+            Decoded::Pending | Decoded::Unknown => None,
+        }
     }
 
     fn ensure_correction(&mut self) -> &mut CorrectionState {
-        let started_at_end = self.editor.cursor == self.editor.buf.len();
+        let started_at_end = self.editor.at_end();
         self.correction.get_or_insert_with(|| CorrectionState {
             started_at_end,
             ..Default::default()
@@ -195,6 +169,14 @@ impl PlaybackTracer {
             self.ctrl_down = true;
             return;
         }
+        if Some(keycode) == self.decoder.altgr_keycode() {
+            self.altgr_down = true;
+            return;
+        }
+        if keycode == KEY_CAPSLOCK {
+            self.decoder.toggle_caps_lock();
+            return;
+        }
 
         let decoded_char = if self.ctrl_down {
             None
@@ -207,37 +189,19 @@ impl PlaybackTracer {
         if is_edit_key(keycode) {
             self.flush_typing_run_on_edit();
 
-            match keycode {
-                KEY_LEFT => {
-                    if self.ctrl_down {
-                        self.editor.move_word_left();
-                    } else {
-                        self.editor.move_left();
-                    }
+            let deleted = self
+                .editor
+                .press_edit_key(keycode, self.shift_down, self.ctrl_down)
+                .unwrap_or_default();
+            if !deleted.is_empty() {
+                let correction = self.ensure_correction();
+                if keycode == KEY_BACKSPACE {
+                    correction
+                        .deleted_backspace
+                        .extend(deleted.into_iter().rev());
+                } else {
+                    correction.deleted_delete.extend(deleted);
                 }
-                KEY_RIGHT => {
-                    if self.ctrl_down {
-                        self.editor.move_word_right();
-                    } else {
-                        self.editor.move_right();
-                    }
-                }
-                KEY_HOME => self.editor.home(),
-                KEY_END => self.editor.end(),
-                KEY_UP | KEY_DOWN => {}
-                KEY_BACKSPACE => {
-                    let deleted = self.editor.backspace();
-                    if let Some(c) = deleted {
-                        self.ensure_correction().deleted_backspace.push(c);
-                    }
-                }
-                KEY_DELETE => {
-                    let deleted = self.editor.delete();
-                    if let Some(c) = deleted {
-                        self.ensure_correction().deleted_delete.push(c);
-                    }
-                }
-                _ => {}
             }
 
             return;
@@ -247,6 +211,11 @@ impl PlaybackTracer {
             return;
         };
 
+        // Typing over a selection replaces it.
+        if self.editor.has_selection() {
+            let replaced = self.editor.delete_selection();
+            self.ensure_correction().deleted_delete.extend(replaced);
+        }
         self.editor.insert_char(c);
 
         if let Some(correction) = &mut self.correction {
@@ -254,7 +223,7 @@ impl PlaybackTracer {
             return;
         }
 
-        if self.editor.cursor == self.editor.buf.len() {
+        if self.editor.at_end() {
             self.typing_run.push(c);
         }
     }
@@ -266,26 +235,551 @@ impl PlaybackTracer {
         if keycode == KEY_LEFTCTRL || keycode == KEY_RIGHTCTRL {
             self.ctrl_down = false;
         }
+        if Some(keycode) == self.decoder.altgr_keycode() {
+            self.altgr_down = false;
+        }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TraceEvent {
     pub action_index: usize,
+    /// Planned time from the start of the plan when the event begins (the waits before it), in
+    /// milliseconds.
+    pub offset_ms: u64,
+    /// Where in the editor the event starts typing.
+    pub cursor: CursorPosition,
+    pub kind: TraceEventKind,
+    /// Characters of the final text (as char offsets) that the event typed, or `None` if all
+    /// of them are deleted later.
+    pub text_range: Option<Range<usize>>,
     pub line: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceEventKind {
+    /// Text typed at the end of the document.
+    Typing,
+    /// Text deleted and typed again, at the end or after moving back.
+    Replace,
+}
+
+impl TraceEventKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TraceEventKind::Typing => "typing",
+            TraceEventKind::Replace => "replace",
+        }
+    }
+}
+
+impl TraceEvent {
+    /// The event's start time and cursor position, e.g. `0:12.9 L3:14`.
+    pub fn annotation(&self) -> String {
+        annotation(self.offset_ms, self.cursor)
+    }
+}
+
+fn annotation(offset_ms: u64, cursor: CursorPosition) -> String {
+    format!(
+        "{} L{}:{}",
+        planned_time(offset_ms),
+        cursor.line,
+        cursor.column
+    )
+}
+
+/// E.g. `0:12.9`.
+fn planned_time(ms: u64) -> String {
+    format!("{}:{:04.1}", ms / 60_000, (ms % 60_000) as f64 / 1000.0)
+}
+
+/// What the console trace prints for each event during playback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceDetail {
+    /// Only what is typed or replaced.
+    #[default]
+    Plain,
+    /// Also the event's [`TraceEvent::annotation`].
+    Positions,
+}
+
+/// How much the console trace prints during playback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TraceLevel {
+    /// Only a summary once the plan has been played.
+    Quiet,
+    /// What is typed or replaced.
+    #[default]
+    Normal,
+    /// Also every cursor movement and every pause of at least [`TRACE_PAUSE_MS`], and the
+    /// summary.
+    Verbose,
+    /// Also every action, with its index in the plan.
+    Debug,
+}
+
+/// Shortest wait the verbose trace reports as a pause, in milliseconds.
+pub const TRACE_PAUSE_MS: u64 = 1_000;
+
+/// A line of the console trace, printed before the action it belongs to is played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceLine {
+    pub action_index: usize,
+    /// Planned time from the start of the plan, in milliseconds.
+    pub offset_ms: u64,
+    /// Where the cursor is when the line is printed, or for typing and replacements, where the
+    /// text is typed.
+    pub cursor: CursorPosition,
+    pub kind: TraceLineKind,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TraceLineKind {
+    /// A [`TraceEventKind::Typing`] event.
+    Typing,
+    /// A [`TraceEventKind::Replace`] event.
+    Replace,
+    /// One or more presses of the same arrow, Home or End key (verbose).
+    Navigation,
+    /// Waits of at least [`TRACE_PAUSE_MS`] in a row (verbose).
+    Pause,
+    /// A single action (debug).
+    Action,
+}
+
+impl TraceLineKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TraceLineKind::Typing => "typing",
+            TraceLineKind::Replace => "replace",
+            TraceLineKind::Navigation => "navigation",
+            TraceLineKind::Pause => "pause",
+            TraceLineKind::Action => "action",
+        }
+    }
+}
+
+impl TraceLine {
+    /// The line's time and cursor position, e.g. `0:12.9 L3:14`.
+    pub fn annotation(&self) -> String {
+        annotation(self.offset_ms, self.cursor)
+    }
+}
+
+/// The console trace of a plan at some [`TraceLevel`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsoleTrace {
+    /// Lines in the order they are printed, by action index.
+    pub lines: Vec<TraceLine>,
+    /// Printed after the last action, at every level but [`TraceLevel::Normal`].
+    pub summary: Option<String>,
+}
+
+impl ConsoleTrace {
+    /// The lines with their planned times (and with `positions`, where the cursor is), then the
+    /// summary; with `limit`, only the first `limit` lines and how many more there are until
+    /// `total_ms`, the plan's length. One line of text per line.
+    pub fn timeline(&self, positions: bool, limit: Option<usize>, total_ms: u64) -> String {
+        let shown = limit.map_or(self.lines.len(), |limit| limit.min(self.lines.len()));
+        let mut out = String::new();
+        for line in &self.lines[..shown] {
+            let time = planned_time(line.offset_ms);
+            if positions {
+                let position = format!("L{}:{}", line.cursor.line, line.cursor.column);
+                out.push_str(&format!("{time:>9}  {position:<9} {}\n", line.line));
+            } else {
+                out.push_str(&format!("{time:>9}  {}\n", line.line));
+            }
+        }
+        if shown < self.lines.len() {
+            let more = self.lines.len() - shown;
+            out.push_str(&format!(
+                "... {more} more line{}, until {}\n",
+                if more == 1 { "" } else { "s" },
+                planned_time(total_ms)
+            ));
+        } else if let Some(summary) = &self.summary {
+            out.push_str(&format!("{summary}\n"));
+        }
+        out
+    }
+}
+
+/// A trace event as written by `--trace-out`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceRecord {
+    pub action_index: usize,
+    /// Planned time from the start of the plan, in milliseconds.
+    pub offset_ms: u64,
+    pub cursor_line: usize,
+    pub cursor_column: usize,
+    pub kind: TraceEventKind,
+    /// Char offsets of the final text the event typed (end exclusive), or `None`.
+    pub text_start: Option<usize>,
+    pub text_end: Option<usize>,
+    pub line: String,
+}
+
+/// `events` as `--trace-out` records.
+pub fn trace_records(events: &[TraceEvent]) -> Vec<TraceRecord> {
+    events
+        .iter()
+        .map(|event| TraceRecord {
+            action_index: event.action_index,
+            offset_ms: event.offset_ms,
+            cursor_line: event.cursor.line,
+            cursor_column: event.cursor.column,
+            kind: event.kind,
+            text_start: event.text_range.as_ref().map(|range| range.start),
+            text_end: event.text_range.as_ref().map(|range| range.end),
+            line: event.line.clone(),
+        })
+        .collect()
+}
+
+/// `records` as CSV with a header row.
+pub fn trace_records_csv(records: &[TraceRecord]) -> String {
+    let mut csv = String::from(
+        "action_index,offset_ms,cursor_line,cursor_column,kind,text_start,text_end,line\n",
+    );
+    let optional = |value: Option<usize>| value.map(|v| v.to_string()).unwrap_or_default();
+    for record in records {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},\"{}\"\n",
+            record.action_index,
+            record.offset_ms,
+            record.cursor_line,
+            record.cursor_column,
+            record.kind.name(),
+            optional(record.text_start),
+            optional(record.text_end),
+            record.line.replace('"', "\"\"")
+        ));
+    }
+    csv
+}
+
+/// Longest time [`trace_records_subtitles`] shows a replacement.
+pub const REPLACE_CAPTION_MAX_MS: u64 = 4_000;
+
+/// Subtitle formats of [`trace_records_subtitles`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    /// SubRip (`.srt`).
+    Srt,
+    /// WebVTT (`.vtt`).
+    Vtt,
+}
+
+/// `records` as subtitles, to caption a recording of the plan's playback: each line is shown
+/// from its planned start until the next one starts, and the last one until `end_ms`.
+/// Replacements are shown for at most [`REPLACE_CAPTION_MAX_MS`], since typing goes on after
+/// them without a trace line of its own until the next event.
+pub fn trace_records_subtitles(
+    records: &[TraceRecord],
+    end_ms: u64,
+    format: SubtitleFormat,
+) -> String {
+    let timestamp = |ms: u64| {
+        let separator = match format {
+            SubtitleFormat::Srt => ',',
+            SubtitleFormat::Vtt => '.',
+        };
+        format!(
+            "{:02}:{:02}:{:02}{separator}{:03}",
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1000 % 60,
+            ms % 1000
+        )
+    };
+    let mut out = match format {
+        SubtitleFormat::Srt => String::new(),
+        SubtitleFormat::Vtt => String::from("WEBVTT\n\n"),
+    };
+    let mut cue = 0;
+    for (idx, record) in records.iter().enumerate() {
+        let start_ms = record.offset_ms;
+        let mut until_ms = records.get(idx + 1).map_or(end_ms, |next| next.offset_ms);
+        if record.kind == TraceEventKind::Replace {
+            until_ms = until_ms.min(start_ms + REPLACE_CAPTION_MAX_MS);
+        }
+        if until_ms <= start_ms {
+            continue;
+        }
+        cue += 1;
+        let text = record.line.replace('\n', " ");
+        let text = match format {
+            SubtitleFormat::Srt => text,
+            SubtitleFormat::Vtt => text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;"),
+        };
+        out.push_str(&format!(
+            "{cue}\n{} --> {}\n{text}\n\n",
+            timestamp(start_ms),
+            timestamp(until_ms)
+        ));
+    }
+    out
+}
+
 /// Precompute console trace events so they can be printed *before* the associated
 /// typing/correction sequence starts during playback.
+///
+/// Keystrokes are decoded with the built-in US-QWERTY mapping, for actions built without a
+/// keymap; use [`trace_events_for_plan`] for plans, which carry the keymap they were generated for.
 pub fn plan_console_trace(actions: &[Action]) -> Vec<TraceEvent> {
-    let mut planner = TracePlanner::new();
+    trace_with_decoder(actions, KeyDecoder::us_qwerty())
+}
+
+/// Like [`plan_console_trace`], decoding keystrokes (including dead-key and Compose sequences)
+/// with `layout`.
+pub fn plan_console_trace_for_layout(
+    actions: &[Action],
+    layout: &KeyboardLayout,
+) -> Vec<TraceEvent> {
+    trace_with_decoder(actions, layout.decoder())
+}
+
+/// The trace events of `plan`, decoding keystrokes with the XKB keymap embedded in the plan, so
+/// they match what playback types whatever layout the plan was generated for.
+pub fn trace_events_for_plan(plan: &Plan) -> Result<Vec<TraceEvent>> {
+    let layout = KeyboardLayout::for_plan_config(&plan.config)?;
+    Ok(plan_console_trace_for_layout(&plan.actions, &layout))
+}
+
+/// The console trace of `actions` at `level`, decoding keystrokes with the built-in US-QWERTY
+/// mapping like [`plan_console_trace`]; use [`console_trace_for_plan`] for plans.
+pub fn console_trace(actions: &[Action], level: TraceLevel) -> ConsoleTrace {
+    console_trace_with_decoder(actions, KeyDecoder::us_qwerty(), level)
+}
+
+/// Like [`console_trace`], decoding keystrokes with `layout`.
+pub fn console_trace_for_layout(
+    actions: &[Action],
+    layout: &KeyboardLayout,
+    level: TraceLevel,
+) -> ConsoleTrace {
+    console_trace_with_decoder(actions, layout.decoder(), level)
+}
+
+/// The console trace of `plan` at `level`, decoding keystrokes with the plan's embedded keymap.
+pub fn console_trace_for_plan(plan: &Plan, level: TraceLevel) -> Result<ConsoleTrace> {
+    let layout = KeyboardLayout::for_plan_config(&plan.config)?;
+    Ok(console_trace_for_layout(&plan.actions, &layout, level))
+}
+
+fn console_trace_with_decoder(
+    actions: &[Action],
+    decoder: KeyDecoder,
+    level: TraceLevel,
+) -> ConsoleTrace {
+    let planner = run_trace_planner(actions, decoder);
+    let offsets = action_offsets(actions);
+    let mut lines = Vec::new();
+
+    if level >= TraceLevel::Normal {
+        lines.extend(planner.events.iter().map(|event| TraceLine {
+            action_index: event.action_index,
+            offset_ms: event.offset_ms,
+            cursor: event.cursor,
+            kind: match event.kind {
+                TraceEventKind::Typing => TraceLineKind::Typing,
+                TraceEventKind::Replace => TraceLineKind::Replace,
+            },
+            line: event.line.clone(),
+        }));
+    }
+
+    let line = |action_index: usize, kind: TraceLineKind, line: String| TraceLine {
+        action_index,
+        offset_ms: offsets[action_index],
+        cursor: planner.positions[action_index],
+        kind,
+        line,
+    };
+    if level >= TraceLevel::Verbose {
+        for navigation in &planner.navigations {
+            lines.push(line(
+                navigation.action_index,
+                TraceLineKind::Navigation,
+                navigation.line(),
+            ));
+        }
+
+        let mut waits: Option<(usize, u64)> = None;
+        for (action_index, action) in actions.iter().enumerate() {
+            match (action, &mut waits) {
+                (Action::Wait { ms }, Some((_, total_ms))) => {
+                    *total_ms = total_ms.saturating_add(*ms)
+                }
+                (Action::Wait { ms }, None) => waits = Some((action_index, *ms)),
+                (_, _) => {
+                    if let Some((start, total_ms)) = waits.take() {
+                        if total_ms >= TRACE_PAUSE_MS {
+                            lines.push(line(start, TraceLineKind::Pause, pause_line(total_ms)));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some((start, total_ms)) = waits {
+            if total_ms >= TRACE_PAUSE_MS {
+                lines.push(line(start, TraceLineKind::Pause, pause_line(total_ms)));
+            }
+        }
+    }
+    if level >= TraceLevel::Debug {
+        for (action_index, action) in actions.iter().enumerate() {
+            lines.push(line(
+                action_index,
+                TraceLineKind::Action,
+                format!("#{action_index} {}", action_line(action)),
+            ));
+        }
+    }
+    // Events first, then what happens at the action, then the action itself.
+    lines.sort_by_key(|line| (line.action_index, line.kind));
+
+    let summary = (level != TraceLevel::Normal).then(|| {
+        let corrections = planner
+            .events
+            .iter()
+            .filter(|event| event.kind == TraceEventKind::Replace)
+            .count();
+        format!(
+            "Typed {} characters in {} with {corrections} correction{}.",
+            planner.editor.text().len(),
+            planned_time(offsets[actions.len()]),
+            if corrections == 1 { "" } else { "s" }
+        )
+    });
+    ConsoleTrace { lines, summary }
+}
+
+fn trace_with_decoder(actions: &[Action], decoder: KeyDecoder) -> Vec<TraceEvent> {
+    run_trace_planner(actions, decoder).events
+}
+
+/// Runs `actions` through a [`TracePlanner`] and fills in the times and final text ranges of
+/// its events.
+fn run_trace_planner(actions: &[Action], decoder: KeyDecoder) -> TracePlanner {
+    let mut planner = TracePlanner::new(decoder);
     for (action_index, action) in actions.iter().enumerate() {
         planner.observe_action(action_index, action);
     }
     planner.finish();
 
+    // Where the characters each event typed ended up in the final text.
+    let final_offsets: HashMap<usize, usize> = planner
+        .editor
+        .char_ids()
+        .iter()
+        .enumerate()
+        .map(|(offset, id)| (*id, offset))
+        .collect();
+    for (event, ids) in planner.events.iter_mut().zip(&planner.event_ids) {
+        let mut offsets = ids.iter().filter_map(|id| final_offsets.get(id));
+        let first = offsets.next().copied();
+        event.text_range = first.map(|first| {
+            let (start, end) = offsets.fold((first, first), |(start, end), offset| {
+                (start.min(*offset), end.max(*offset))
+            });
+            start..end + 1
+        });
+    }
+
     planner.events.sort_by_key(|event| event.action_index);
-    planner.events
+    let offsets = action_offsets(actions);
+    for event in &mut planner.events {
+        event.offset_ms = offsets[event.action_index];
+    }
+    planner
+}
+
+/// Planned start time of each action and, last, the end of the plan, in milliseconds.
+fn action_offsets(actions: &[Action]) -> Vec<u64> {
+    let mut elapsed_ms = 0u64;
+    let mut offsets = Vec::with_capacity(actions.len() + 1);
+    for action in actions {
+        offsets.push(elapsed_ms);
+        elapsed_ms = elapsed_ms.saturating_add(action.duration_ms());
+    }
+    offsets.push(elapsed_ms);
+    offsets
+}
+
+fn pause_line(ms: u64) -> String {
+    format!("Pause {:.1} s", ms as f64 / 1000.0)
+}
+
+/// An action as in the plan's JSON, e.g. `key 30 pressed`.
+fn action_line(action: &Action) -> String {
+    match action {
+        Action::Wait { ms } => format!("wait {ms} ms"),
+        Action::Modifiers {
+            mods_depressed,
+            mods_latched,
+            mods_locked,
+            group,
+        } => format!(
+            "modifiers depressed={mods_depressed} latched={mods_latched} locked={mods_locked} \
+             group={group}"
+        ),
+        Action::Key { keycode, state } => match state {
+            KeyState::Pressed => format!("key {keycode} pressed"),
+            KeyState::Released => format!("key {keycode} released"),
+        },
+        Action::KeyRepeat {
+            keycode,
+            count,
+            interval_ms,
+        } => format!("key {keycode} repeated x{count} every {interval_ms} ms"),
+        Action::Checkpoint { chars } => format!("checkpoint at {chars} chars"),
+    }
+}
+
+/// Presses of the same navigation key in a row, with the same modifiers.
+#[derive(Debug, Clone)]
+struct Navigation {
+    action_index: usize,
+    keycode: u32,
+    shift: bool,
+    ctrl: bool,
+    presses: usize,
+    /// Cursor position after the last press.
+    to: CursorPosition,
+}
+
+impl Navigation {
+    /// E.g. `Select Ctrl+Left x2 to L1:4`.
+    fn line(&self) -> String {
+        let key = match self.keycode {
+            KEY_LEFT => "Left",
+            KEY_RIGHT => "Right",
+            KEY_UP => "Up",
+            KEY_DOWN => "Down",
+            KEY_HOME => "Home",
+            _ => "End",
+        };
+        format!(
+            "{} {}{key}{} to L{}:{}",
+            if self.shift { "Select" } else { "Move" },
+            if self.ctrl { "Ctrl+" } else { "" },
+            if self.presses > 1 {
+                format!(" x{}", self.presses)
+            } else {
+                String::new()
+            },
+            self.to.line,
+            self.to.column
+        )
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -294,6 +788,9 @@ struct ScheduledCorrection {
     deleted_backspace: Vec<char>,
     deleted_delete: Vec<char>,
     inserted: String,
+    inserted_ids: Vec<usize>,
+    /// Cursor position of the first inserted character.
+    insert_position: Option<CursorPosition>,
     left_end: bool,
 }
 
@@ -314,43 +811,78 @@ impl ScheduledCorrection {
 
 #[derive(Debug, Default, Clone)]
 struct TracePlanner {
-    keystrokes: HashMap<(u32, bool), char>,
-    editor: EditorState,
+    decoder: KeyDecoder,
+    editor: EditorModel,
 
     shift_down: bool,
     ctrl_down: bool,
+    altgr_down: bool,
 
     typing_run_start_action: Option<usize>,
     typing_run: String,
+    typing_run_ids: Vec<usize>,
+    typing_run_position: CursorPosition,
+    /// Action index of the first keystroke of a pending dead-key/Compose sequence.
+    sequence_start_action: Option<usize>,
 
     correction: Option<ScheduledCorrection>,
     events: Vec<TraceEvent>,
+    /// Insertion ids of the characters typed by each of `events`.
+    event_ids: Vec<Vec<usize>>,
+
+    /// Cursor position before each action.
+    positions: Vec<CursorPosition>,
+    navigations: Vec<Navigation>,
+    /// Whether the last key pressed (other than a modifier) was `navigations.last()`.
+    navigating: bool,
 }
 
 impl TracePlanner {
-    fn new() -> Self {
+    fn new(decoder: KeyDecoder) -> Self {
         Self {
-            keystrokes: us_qwerty_keystroke_map(),
+            decoder,
             ..Default::default()
         }
     }
 
     fn observe_action(&mut self, action_index: usize, action: &Action) {
-        let Action::Key { keycode, state } = action else {
-            return;
-        };
-        match state {
-            KeyState::Pressed => self.handle_key_pressed(action_index, *keycode),
-            KeyState::Released => self.handle_key_released(*keycode),
+        self.positions.push(self.editor.cursor_position());
+        for action in action.expand() {
+            let Action::Key { keycode, state } = action else {
+                continue;
+            };
+            match state {
+                KeyState::Pressed => self.handle_key_pressed(action_index, keycode),
+                KeyState::Released => self.handle_key_released(keycode),
+            }
         }
     }
 
     fn finish(&mut self) {
         self.finish_correction();
+        self.positions.push(self.editor.cursor_position());
     }
 
-    fn decode_char(&self, keycode: u32) -> Option<char> {
-        self.keystrokes.get(&(keycode, self.shift_down)).copied()
+    fn decode_char(&mut self, action_index: usize, keycode: u32) -> Option<(usize, char)> {
+        let stroke = KeyStroke {
+            keycode,
+            shift: self.shift_down,
+            altgr: self.altgr_down,
+        };
+        match self.decoder.feed(stroke) {
+            Decoded::Char(c) => {
+                let start = self.sequence_start_action.take().unwrap_or(action_index);
+                Some((start, c))
+            }
+            Decoded::Pending => {
+                self.sequence_start_action.get_or_insert(action_index);
+                None
+            }
+            Decoded::Unknown => {
+                self.sequence_start_action = None;
+                None
+            }
+        }
     }
 
     fn finish_correction(&mut self) {
@@ -366,12 +898,17 @@ impl TracePlanner {
         let correct = correction.inserted;
         self.events.push(TraceEvent {
             action_index: start_action_index,
+            offset_ms: 0,
+            cursor: correction.insert_position.unwrap_or_default(),
+            kind: TraceEventKind::Replace,
+            text_range: None,
             line: format!(
                 "Replace \"{}\" with \"{}\"...",
                 escape_for_log(&wrong),
                 escape_for_log(&correct)
             ),
         });
+        self.event_ids.push(correction.inserted_ids);
     }
 
     fn maybe_finish_correction_before_key(&mut self, keycode: u32, decoded_char: Option<char>) {
@@ -379,7 +916,7 @@ impl TracePlanner {
             return;
         };
 
-        let at_end = self.editor.cursor == self.editor.buf.len();
+        let at_end = self.editor.at_end();
 
         let should_finish = if correction.left_end {
             at_end && (is_edit_key(keycode) || decoded_char.is_some())
@@ -404,6 +941,7 @@ impl TracePlanner {
     fn flush_typing_run_on_edit(&mut self) {
         let Some(start_idx) = self.typing_run_start_action else {
             self.typing_run.clear();
+            self.typing_run_ids.clear();
             return;
         };
         if self.typing_run.is_empty() {
@@ -413,8 +951,14 @@ impl TracePlanner {
 
         self.events.push(TraceEvent {
             action_index: start_idx,
+            offset_ms: 0,
+            cursor: self.typing_run_position,
+            kind: TraceEventKind::Typing,
+            text_range: None,
             line: format!("Typing \"{}\"...", escape_for_log(&self.typing_run)),
         });
+        self.event_ids
+            .push(std::mem::take(&mut self.typing_run_ids));
         self.typing_run.clear();
         self.typing_run_start_action = None;
     }
@@ -428,12 +972,23 @@ impl TracePlanner {
             self.ctrl_down = true;
             return;
         }
+        if Some(keycode) == self.decoder.altgr_keycode() {
+            self.altgr_down = true;
+            return;
+        }
+        if keycode == KEY_CAPSLOCK {
+            self.decoder.toggle_caps_lock();
+            return;
+        }
 
-        let decoded_char = if self.ctrl_down {
+        self.navigating &= is_navigation_key(keycode);
+
+        let decoded = if self.ctrl_down {
             None
         } else {
-            self.decode_char(keycode)
+            self.decode_char(action_index, keycode)
         };
+        let decoded_char = decoded.map(|(_start, c)| c);
 
         self.maybe_finish_correction_before_key(keycode, decoded_char);
 
@@ -443,75 +998,101 @@ impl TracePlanner {
             if self.correction.is_none() {
                 self.correction = Some(ScheduledCorrection {
                     start_action_index: action_index,
-                    left_end: self.editor.cursor < self.editor.buf.len(),
+                    left_end: !self.editor.at_end(),
                     ..Default::default()
                 });
             }
 
-            match keycode {
-                KEY_LEFT => {
-                    if self.ctrl_down {
-                        self.editor.move_word_left();
-                    } else {
-                        self.editor.move_left();
-                    }
-                }
-                KEY_RIGHT => {
-                    if self.ctrl_down {
-                        self.editor.move_word_right();
-                    } else {
-                        self.editor.move_right();
-                    }
-                }
-                KEY_HOME => self.editor.home(),
-                KEY_END => self.editor.end(),
-                KEY_UP | KEY_DOWN => {}
-                KEY_BACKSPACE => {
-                    let deleted = self.editor.backspace();
-                    if let Some(c) = deleted {
-                        if let Some(correction) = &mut self.correction {
-                            correction.deleted_backspace.push(c);
-                        }
-                    }
-                }
-                KEY_DELETE => {
-                    let deleted = self.editor.delete();
-                    if let Some(c) = deleted {
-                        if let Some(correction) = &mut self.correction {
-                            correction.deleted_delete.push(c);
-                        }
-                    }
+            let deleted = self
+                .editor
+                .press_edit_key(keycode, self.shift_down, self.ctrl_down)
+                .unwrap_or_default();
+            if is_navigation_key(keycode) {
+                self.record_navigation(action_index, keycode);
+            }
+            if let Some(correction) = &mut self.correction {
+                if keycode == KEY_BACKSPACE {
+                    correction
+                        .deleted_backspace
+                        .extend(deleted.into_iter().rev());
+                } else {
+                    correction.deleted_delete.extend(deleted);
                 }
-                _ => {}
             }
 
             if let Some(correction) = &mut self.correction {
-                correction.left_end |= self.editor.cursor < self.editor.buf.len();
+                correction.left_end |= !self.editor.at_end();
             }
 
             return;
         }
 
-        let Some(c) = decoded_char else {
+        let Some((char_start_action, c)) = decoded else {
             return;
         };
 
-        self.editor.insert_char(c);
+        // Typing over a selection replaces it.
+        if self.editor.has_selection() {
+            let replaced = self.editor.delete_selection();
+            // Selecting back from the end left it, but the replacement is typed at the end.
+            let left_end = !self.editor.at_end();
+            let correction = self.correction.get_or_insert_with(|| ScheduledCorrection {
+                start_action_index: action_index,
+                ..Default::default()
+            });
+            correction.left_end = left_end;
+            correction.deleted_delete.extend(replaced);
+        }
+        if let Some(correction) = &mut self.correction {
+            if correction.insert_position.is_none() {
+                correction.insert_position = Some(self.editor.cursor_position());
+            }
+        } else if self.typing_run.is_empty() {
+            self.typing_run_position = self.editor.cursor_position();
+        }
+        let id = self.editor.insert_char(c);
 
         if let Some(correction) = &mut self.correction {
             correction.inserted.push(c);
-            correction.left_end |= self.editor.cursor < self.editor.buf.len();
+            correction.inserted_ids.push(id);
+            correction.left_end |= !self.editor.at_end();
             return;
         }
 
-        if self.editor.cursor == self.editor.buf.len() {
+        if self.editor.at_end() {
             if self.typing_run.is_empty() {
-                self.typing_run_start_action = Some(action_index);
+                self.typing_run_start_action = Some(char_start_action);
             }
             self.typing_run.push(c);
+            self.typing_run_ids.push(id);
         }
     }
 
+    /// Adds a press of an arrow, Home or End key to the last navigation if it pressed the same
+    /// key just before.
+    fn record_navigation(&mut self, action_index: usize, keycode: u32) {
+        let to = self.editor.cursor_position();
+        if let Some(last) = self.navigations.last_mut().filter(|last| {
+            self.navigating
+                && last.keycode == keycode
+                && last.shift == self.shift_down
+                && last.ctrl == self.ctrl_down
+        }) {
+            last.presses += 1;
+            last.to = to;
+            return;
+        }
+        self.navigations.push(Navigation {
+            action_index,
+            keycode,
+            shift: self.shift_down,
+            ctrl: self.ctrl_down,
+            presses: 1,
+            to,
+        });
+        self.navigating = true;
+    }
+
     fn handle_key_released(&mut self, keycode: u32) {
         if keycode == KEY_LEFTSHIFT || keycode == KEY_RIGHTSHIFT {
             self.shift_down = false;
@@ -519,26 +1100,10 @@ impl TracePlanner {
         if keycode == KEY_LEFTCTRL || keycode == KEY_RIGHTCTRL {
             self.ctrl_down = false;
         }
-    }
-}
-
-fn us_qwerty_keystroke_map() -> HashMap<(u32, bool), char> {
-    let mut map = HashMap::new();
-
-    let mut candidates = Vec::new();
-    candidates.push('\n');
-    candidates.push(' ');
-    for b in 33u8..=126u8 {
-        candidates.push(b as char);
-    }
-
-    for c in candidates {
-        if let Some(stroke) = keystroke_for_output_char(c) {
-            map.insert((stroke.keycode, stroke.shift), c);
+        if Some(keycode) == self.decoder.altgr_keycode() {
+            self.altgr_down = false;
         }
     }
-
-    map
 }
 
 fn is_edit_key(keycode: u32) -> bool {
@@ -548,8 +1113,11 @@ fn is_edit_key(keycode: u32) -> bool {
     )
 }
 
-fn is_word_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '\''
+fn is_navigation_key(keycode: u32) -> bool {
+    matches!(
+        keycode,
+        KEY_LEFT | KEY_RIGHT | KEY_UP | KEY_DOWN | KEY_HOME | KEY_END
+    )
 }
 
 fn escape_for_log(s: &str) -> String {
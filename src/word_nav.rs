@@ -89,3 +89,181 @@ pub fn ctrl_right(buf: &[char], cursor: usize, is_word_char: impl Fn(char) -> bo
 
     idx
 }
+
+/// Editor toolkits with their own Ctrl+Left/Right stop positions.
+///
+/// All of them skip whitespace (including line breaks) and treat letters and digits as word
+/// characters; they differ in punctuation and where Ctrl+Right stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toolkit {
+    /// GTK text views (Pango word boundaries): punctuation is skipped like whitespace;
+    /// Ctrl+Left stops at word starts, Ctrl+Right at word ends.
+    Gtk,
+    /// Qt widgets: every punctuation character is a stop of its own; both directions stop at
+    /// starts, so Ctrl+Right also skips the whitespace after a word.
+    Qt,
+    /// LibreOffice Writer: like Qt, but a run of punctuation is a single stop.
+    LibreOffice,
+    /// VS Code (`cursorWordStartLeft` / `cursorWordEndRight`): runs of its default word
+    /// separators are stops of their own; Ctrl+Left stops at starts, Ctrl+Right at ends.
+    VsCode,
+    /// Rules from a user-defined profile; Ctrl+Left stops at starts, Ctrl+Right at ends.
+    Custom(CustomRules),
+}
+
+/// Stop rules of a user-defined word navigation profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CustomRules {
+    /// Punctuation that counts as part of words.
+    pub word_chars: WordChars,
+    /// Whether a run of other punctuation is a stop of its own; if not, it is skipped like
+    /// whitespace.
+    pub punctuation_runs: bool,
+}
+
+/// Classes of punctuation a user-defined profile can count as part of words, besides letters
+/// and digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WordChars {
+    /// `'` and `’`, as in `don't`.
+    pub apostrophes: bool,
+    /// `_`, as in `snake_case`.
+    pub underscores: bool,
+    /// `-`, as in `mid-sentence`.
+    pub hyphens: bool,
+    /// `.`, as in `e.g`.
+    pub periods: bool,
+}
+
+impl WordChars {
+    /// The names of the classes in the config file, in field order.
+    pub const NAMES: [&'static str; 4] = ["apostrophes", "underscores", "hyphens", "periods"];
+
+    /// Turns on the class called `name` (one of [`WordChars::NAMES`]); false if there is none.
+    pub fn insert(&mut self, name: &str) -> bool {
+        let class = match name {
+            "apostrophes" => &mut self.apostrophes,
+            "underscores" => &mut self.underscores,
+            "hyphens" => &mut self.hyphens,
+            "periods" => &mut self.periods,
+            _ => return false,
+        };
+        *class = true;
+        true
+    }
+
+    pub fn contains(self, c: char) -> bool {
+        match c {
+            '\'' | '’' => self.apostrophes,
+            '_' => self.underscores,
+            '-' => self.hyphens,
+            '.' => self.periods,
+            _ => false,
+        }
+    }
+}
+
+/// VS Code's default `editor.wordSeparators`.
+const VS_CODE_WORD_SEPARATORS: &str = "`~!@#$%^&*()-=+[{]}\\|;:'\",.<>/?";
+
+impl Toolkit {
+    fn class(self, buf: &[char], idx: usize) -> CharClass {
+        let c = buf[idx];
+        if c.is_whitespace() {
+            return CharClass::Whitespace;
+        }
+        let word = match self {
+            Toolkit::VsCode => !VS_CODE_WORD_SEPARATORS.contains(c),
+            Toolkit::Gtk | Toolkit::Qt | Toolkit::LibreOffice => {
+                c.is_alphanumeric() || c == '_' || joins_word(buf, idx)
+            }
+            Toolkit::Custom(rules) => c.is_alphanumeric() || rules.word_chars.contains(c),
+        };
+        if word {
+            CharClass::Word
+        } else {
+            CharClass::Punctuation
+        }
+    }
+
+    /// Whether Ctrl+arrows pass over characters of `class` without stopping.
+    fn skips(self, class: CharClass) -> bool {
+        let skips_punctuation = match self {
+            Toolkit::Gtk => true,
+            Toolkit::Custom(rules) => !rules.punctuation_runs,
+            Toolkit::Qt | Toolkit::LibreOffice | Toolkit::VsCode => false,
+        };
+        class == CharClass::Whitespace || (skips_punctuation && class == CharClass::Punctuation)
+    }
+
+    /// Whether there is a stop between `buf[idx - 1]` and `buf[idx]`.
+    fn is_boundary(self, buf: &[char], idx: usize) -> bool {
+        let class = self.class(buf, idx);
+        class != self.class(buf, idx - 1)
+            || (self == Toolkit::Qt && class == CharClass::Punctuation)
+    }
+}
+
+/// Whether `buf[idx]` joins the letters or digits around it into one word, as apostrophes
+/// and periods do between letters (`don't`, `e.g`) and commas between digits (`1,000`).
+fn joins_word(buf: &[char], idx: usize) -> bool {
+    let (Some(before), Some(after)) = (idx.checked_sub(1).map(|i| buf[i]), buf.get(idx + 1)) else {
+        return false;
+    };
+    match buf[idx] {
+        '\'' | '’' | '.' | ':' => before.is_alphanumeric() && after.is_alphanumeric(),
+        ',' | ';' => before.is_ascii_digit() && after.is_ascii_digit(),
+        _ => false,
+    }
+}
+
+/// Where Ctrl+Left from `cursor` stops in `toolkit`: the start of the previous stop, after
+/// skipping what the toolkit skips.
+pub fn toolkit_ctrl_left(buf: &[char], cursor: usize, toolkit: Toolkit) -> usize {
+    let mut idx = cursor.min(buf.len());
+    while idx > 0 && toolkit.skips(toolkit.class(buf, idx - 1)) {
+        idx -= 1;
+    }
+    if idx == 0 {
+        return 0;
+    }
+    idx -= 1;
+    while idx > 0 && !toolkit.is_boundary(buf, idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+/// Where Ctrl+Right from `cursor` stops in `toolkit`.
+pub fn toolkit_ctrl_right(buf: &[char], cursor: usize, toolkit: Toolkit) -> usize {
+    let len = buf.len();
+    let mut idx = cursor.min(len);
+    let skip = |idx: &mut usize| {
+        while *idx < len && toolkit.skips(toolkit.class(buf, *idx)) {
+            *idx += 1;
+        }
+    };
+    let to_end = |idx: &mut usize| {
+        if *idx < len {
+            *idx += 1;
+            while *idx < len && !toolkit.is_boundary(buf, *idx) {
+                *idx += 1;
+            }
+        }
+    };
+    match toolkit {
+        // To the end of the next word.
+        Toolkit::Gtk | Toolkit::VsCode | Toolkit::Custom(_) => {
+            skip(&mut idx);
+            to_end(&mut idx);
+        }
+        // Past the current stop and the whitespace after it.
+        Toolkit::Qt | Toolkit::LibreOffice => {
+            if idx < len && !toolkit.skips(toolkit.class(buf, idx)) {
+                to_end(&mut idx);
+            }
+            skip(&mut idx);
+        }
+    }
+    idx
+}
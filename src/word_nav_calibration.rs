@@ -0,0 +1,442 @@
+//! Calibrating word navigation against a real editor (`calibrate-wordnav`).
+//!
+//! A probe plan types [`PROBE_TEXT`] into an empty scratch document, presses Ctrl+Left or
+//! Ctrl+Right again and again and types a numbered marker (`<1>`, `<2>`, ...) wherever the cursor
+//! lands. The user saves the document and passes the file back: drafter never reads the editor
+//! or the clipboard. [`expected_document`] replays the same probe on a profile's stop rules, and
+//! [`calibrate`] compares where the markers landed in the saved probes with where each candidate
+//! profile puts them.
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::editor::is_word_char;
+use crate::keyboard::{
+    keystroke_for_output_char, KeyStroke, KEY_END, KEY_HOME, KEY_LEFT, KEY_LEFTCTRL, KEY_LEFTSHIFT,
+    KEY_RIGHT,
+};
+use crate::keymap::{us_qwerty_keymap, KeymapInfo};
+use crate::model::{Action, KeyState, ParagraphBreaks, Plan, PlanConfig};
+use crate::plan::PLAN_VERSION;
+use crate::word_nav::{ctrl_left, ctrl_right, toolkit_ctrl_left, toolkit_ctrl_right};
+use crate::word_nav_profile::WordNavProfile;
+
+/// The line the probes navigate: apostrophes, hyphens, trailing punctuation, ellipses,
+/// underscores, brackets, digit groups, abbreviations and slashes, where editors disagree.
+pub const PROBE_TEXT: &str = "Probe: don't stop-now, wait... foo_bar (x) 1,000 e.g. a/b end";
+
+/// Ctrl+arrow steps per probe; enough to reach the end of [`PROBE_TEXT`] in every profile.
+pub const DEFAULT_STEPS: u8 = 40;
+
+/// Profiles [`calibrate`] tries, in order of preference when several match.
+pub const CANDIDATES: [WordNavProfile; 5] = [
+    WordNavProfile::Chrome,
+    WordNavProfile::Gtk,
+    WordNavProfile::Qt,
+    WordNavProfile::LibreOffice,
+    WordNavProfile::VsCode,
+];
+
+/// Which arrow a probe presses with Ctrl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeDirection {
+    /// Starts at the end of the text; the cursor goes back before each marker it types.
+    Left,
+    /// Starts at the beginning of the text; the cursor stays after each marker it types.
+    Right,
+}
+
+/// Key timing of probe plans, which type as fast as the editor accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeTiming {
+    /// Press to release of each key tap.
+    pub hold_ms: u64,
+    /// After each key release.
+    pub between_ms: u64,
+    /// Around modifier transitions (Ctrl/Shift changes).
+    pub modifier_ms: u64,
+}
+
+impl Default for ProbeTiming {
+    fn default() -> Self {
+        Self {
+            hold_ms: 3,
+            between_ms: 1,
+            modifier_ms: 1,
+        }
+    }
+}
+
+/// Builds US-QWERTY plans of fast key taps, without the planner's human timing.
+#[derive(Debug, Clone)]
+pub struct ProbePlanBuilder {
+    keymap: KeymapInfo,
+    timing: ProbeTiming,
+    actions: Vec<Action>,
+    shift_down: bool,
+    ctrl_down: bool,
+}
+
+impl ProbePlanBuilder {
+    pub fn new(timing: ProbeTiming) -> Result<Self> {
+        let mut builder = Self {
+            keymap: us_qwerty_keymap()?,
+            timing,
+            actions: Vec::new(),
+            shift_down: false,
+            ctrl_down: false,
+        };
+        builder.push_modifiers();
+        Ok(builder)
+    }
+
+    /// The plan so far, with Shift and Ctrl released.
+    pub fn into_plan(mut self) -> Plan {
+        self.set_shift(false);
+        self.set_ctrl(false);
+        self.push_modifiers();
+        Plan {
+            version: PLAN_VERSION,
+            config: PlanConfig {
+                layout: self.keymap.layout,
+                keymap_format: self.keymap.keymap_format,
+                keymap: self.keymap.keymap,
+                wpm_target: 999.0,
+                word_nav_profile: None,
+                paragraph_breaks: ParagraphBreaks::AsWritten,
+                strip_trailing_whitespace: false,
+                strip_final_newline: false,
+            },
+            actions: self.actions,
+            annotations: Vec::new(),
+        }
+    }
+
+    pub fn wait(&mut self, ms: u64) {
+        if ms == 0 {
+            return;
+        }
+        self.actions.push(Action::Wait { ms });
+    }
+
+    fn key(&mut self, keycode: u32, state: KeyState) {
+        self.actions.push(Action::Key { keycode, state });
+    }
+
+    fn push_modifiers(&mut self) {
+        let mut depressed = 0u32;
+        if self.shift_down {
+            depressed |= self.keymap.shift_mask;
+        }
+        if self.ctrl_down {
+            depressed |= self.keymap.ctrl_mask;
+        }
+
+        self.actions.push(Action::Modifiers {
+            mods_depressed: depressed,
+            mods_latched: 0,
+            mods_locked: 0,
+            group: 0,
+        });
+    }
+
+    fn set_modifier(&mut self, keycode: u32, down: bool) {
+        self.key(
+            keycode,
+            if down {
+                KeyState::Pressed
+            } else {
+                KeyState::Released
+            },
+        );
+        self.wait(self.timing.modifier_ms);
+        if keycode == KEY_LEFTCTRL {
+            self.ctrl_down = down;
+        } else {
+            self.shift_down = down;
+        }
+        self.push_modifiers();
+        self.wait(self.timing.modifier_ms);
+    }
+
+    fn set_shift(&mut self, down: bool) {
+        if self.shift_down != down {
+            self.set_modifier(KEY_LEFTSHIFT, down);
+        }
+    }
+
+    fn set_ctrl(&mut self, down: bool) {
+        if self.ctrl_down != down {
+            self.set_modifier(KEY_LEFTCTRL, down);
+        }
+    }
+
+    fn tap_key(&mut self, keycode: u32) {
+        self.key(keycode, KeyState::Pressed);
+        self.wait(self.timing.hold_ms);
+        self.key(keycode, KeyState::Released);
+        self.wait(self.timing.between_ms);
+    }
+
+    fn type_char(&mut self, stroke: KeyStroke) {
+        self.set_ctrl(false);
+        self.set_shift(stroke.shift);
+        self.tap_key(stroke.keycode);
+    }
+
+    pub fn type_string(&mut self, s: &str) -> Result<()> {
+        for c in s.chars() {
+            let stroke =
+                keystroke_for_output_char(c).ok_or_else(|| anyhow!("unsupported char {c:?}"))?;
+            self.type_char(stroke);
+        }
+        Ok(())
+    }
+
+    pub fn nav_left(&mut self) {
+        self.set_ctrl(false);
+        self.set_shift(false);
+        self.tap_key(KEY_LEFT);
+    }
+
+    /// Taps `keycode` without modifiers, e.g. Home.
+    pub fn tap(&mut self, keycode: u32) {
+        self.set_ctrl(false);
+        self.set_shift(false);
+        self.tap_key(keycode);
+    }
+
+    /// Taps `keycode` with Ctrl held, e.g. Ctrl+Left or Ctrl+End.
+    pub fn ctrl_tap(&mut self, keycode: u32) {
+        self.set_shift(false);
+        self.set_ctrl(true);
+        self.tap_key(keycode);
+        self.set_ctrl(false);
+    }
+
+    /// Presses Ctrl+Left or Ctrl+Right `steps` times from the current cursor position, typing
+    /// the marker `<N>` after the Nth; Ctrl+Left probes move back over each marker.
+    pub fn probe(&mut self, direction: ProbeDirection, steps: u8) -> Result<()> {
+        for idx in 1..=steps {
+            let marker = format!("<{idx}>");
+            match direction {
+                ProbeDirection::Left => {
+                    self.ctrl_tap(KEY_LEFT);
+                    self.type_string(&marker)?;
+                    // Move back over the marker so the next Ctrl+Left starts
+                    // from the same landing position.
+                    for _ in 0..marker.chars().count() {
+                        self.nav_left();
+                    }
+                }
+                ProbeDirection::Right => {
+                    self.ctrl_tap(KEY_RIGHT);
+                    self.type_string(&marker)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The plan `calibrate-wordnav` plays into an empty scratch document: [`PROBE_TEXT`] probed
+/// from its end (Ctrl+Left), then Ctrl+End, Enter and [`PROBE_TEXT`] again on a second line,
+/// probed from its start (Home, Ctrl+Right).
+///
+/// The second line is typed only after the first probe, so neither probe can run across the line
+/// break: each one stops at an edge of the document however many steps it takes.
+pub fn calibration_plan(steps: u8, timing: ProbeTiming) -> Result<Plan> {
+    let mut b = ProbePlanBuilder::new(timing)?;
+    b.type_string(PROBE_TEXT)?;
+    b.probe(ProbeDirection::Left, steps)?;
+    b.ctrl_tap(KEY_END);
+    b.type_string("\n")?;
+    b.type_string(PROBE_TEXT)?;
+    b.tap(KEY_HOME);
+    b.probe(ProbeDirection::Right, steps)?;
+    Ok(b.into_plan())
+}
+
+/// Where Ctrl+Left/Right from `cursor` stops under `profile`; `Chrome` and `Compatible` use the
+/// editor model's stops.
+fn stop(profile: &WordNavProfile, buf: &[char], cursor: usize, direction: ProbeDirection) -> usize {
+    match (profile.toolkit(), direction) {
+        (None, ProbeDirection::Left) => ctrl_left(buf, cursor, is_word_char),
+        (None, ProbeDirection::Right) => ctrl_right(buf, cursor, is_word_char),
+        (Some(toolkit), ProbeDirection::Left) => toolkit_ctrl_left(buf, cursor, toolkit),
+        (Some(toolkit), ProbeDirection::Right) => toolkit_ctrl_right(buf, cursor, toolkit),
+    }
+}
+
+/// The document a calibration probe leaves in an editor that navigates like `profile`.
+pub fn expected_document(profile: &WordNavProfile, direction: ProbeDirection, steps: u8) -> String {
+    let mut buf: Vec<char> = PROBE_TEXT.chars().collect();
+    let mut cursor = match direction {
+        ProbeDirection::Left => buf.len(),
+        ProbeDirection::Right => 0,
+    };
+    for idx in 1..=steps {
+        cursor = stop(profile, &buf, cursor, direction);
+        let marker: Vec<char> = format!("<{idx}>").chars().collect();
+        let len = marker.len();
+        buf.splice(cursor..cursor, marker);
+        if direction == ProbeDirection::Right {
+            cursor += len;
+        }
+    }
+    buf.into_iter().collect()
+}
+
+/// Where each marker `<1>`..`<steps>` sits in `document`, as a char offset into the document
+/// with all markers removed; `None` for markers that are missing.
+pub fn marker_positions(document: &str, steps: u8) -> Vec<Option<usize>> {
+    let chars: Vec<char> = document.chars().collect();
+    let mut positions = vec![None; usize::from(steps)];
+    let mut offset = 0;
+    let mut idx = 0;
+    while idx < chars.len() {
+        if let Some((number, len)) = marker_at(&chars[idx..]) {
+            if (1..=usize::from(steps)).contains(&number) {
+                positions[number - 1].get_or_insert(offset);
+                idx += len;
+                continue;
+            }
+        }
+        offset += 1;
+        idx += 1;
+    }
+    positions
+}
+
+/// The number and length of the `<N>` marker at the start of `chars`, if any.
+fn marker_at(chars: &[char]) -> Option<(usize, usize)> {
+    if chars.first() != Some(&'<') {
+        return None;
+    }
+    let digits = chars[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 || chars.get(1 + digits) != Some(&'>') {
+        return None;
+    }
+    let number: String = chars[1..1 + digits].iter().collect();
+    Some((number.parse().ok()?, digits + 2))
+}
+
+/// `document` without its markers.
+fn strip_markers(document: &str) -> String {
+    let chars: Vec<char> = document.chars().collect();
+    let mut text = String::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        match marker_at(&chars[idx..]) {
+            Some((_, len)) => idx += len,
+            None => {
+                text.push(chars[idx]);
+                idx += 1;
+            }
+        }
+    }
+    text
+}
+
+/// How well one candidate profile predicts the markers of a calibration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileMatch {
+    pub profile: WordNavProfile,
+    /// Markers of both probes the profile puts where the editor did.
+    pub matching: usize,
+    pub total: usize,
+}
+
+impl ProfileMatch {
+    pub fn is_exact(&self) -> bool {
+        self.matching == self.total
+    }
+}
+
+/// The result of comparing an editor's probe documents with every candidate profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Calibration {
+    /// Where the editor's Ctrl+Left markers landed in [`PROBE_TEXT`].
+    pub left: Vec<Option<usize>>,
+    /// Where the editor's Ctrl+Right markers landed in [`PROBE_TEXT`].
+    pub right: Vec<Option<usize>>,
+    /// Every candidate, best match first (ties in [`CANDIDATES`] order).
+    pub matches: Vec<ProfileMatch>,
+}
+
+impl Calibration {
+    /// The first profile that predicts every marker, or `Compatible` if none does.
+    pub fn recommended(&self) -> WordNavProfile {
+        self.matches
+            .iter()
+            .find(|m| m.is_exact())
+            .map_or(WordNavProfile::Compatible, |m| m.profile.clone())
+    }
+}
+
+/// Compares the saved document of a [`calibration_plan`] of `steps` steps with every candidate in
+/// [`CANDIDATES`]: its first line is the Ctrl+Left probe, its second the Ctrl+Right probe.
+///
+/// Fails unless the document has exactly these two lines (and an optional final newline).
+pub fn calibrate_document(document: &str, steps: u8) -> Result<Calibration> {
+    let document = document.replace("\r\n", "\n");
+    let lines: Vec<&str> = document.trim_end_matches('\n').split('\n').collect();
+    let [left, right] = lines[..] else {
+        bail!(
+            "the saved probe has {} lines instead of 2; run calibrate-wordnav in an empty \
+             plain-text document and save it without further edits",
+            lines.len()
+        );
+    };
+    calibrate(left, right, steps)
+}
+
+/// Compares the Ctrl+Left and Ctrl+Right probe lines of `steps` steps from an editor with every
+/// candidate in [`CANDIDATES`].
+///
+/// Fails if a document is not [`PROBE_TEXT`] with markers, e.g. because the editor was not
+/// empty, auto-corrected the text or closed the `<` of a marker.
+pub fn calibrate(left_document: &str, right_document: &str, steps: u8) -> Result<Calibration> {
+    for (name, document) in [("Ctrl+Left", left_document), ("Ctrl+Right", right_document)] {
+        let text = strip_markers(document.trim_end_matches('\n'));
+        if text != PROBE_TEXT {
+            bail!(
+                "the {name} probe saved {text:?} instead of the probe text {PROBE_TEXT:?}; \
+                 focus an empty plain-text document without auto-correction"
+            );
+        }
+    }
+
+    let left = marker_positions(left_document, steps);
+    let right = marker_positions(right_document, steps);
+    let mut matches: Vec<ProfileMatch> = CANDIDATES
+        .iter()
+        .map(|profile| {
+            let expected_left = marker_positions(
+                &expected_document(profile, ProbeDirection::Left, steps),
+                steps,
+            );
+            let expected_right = marker_positions(
+                &expected_document(profile, ProbeDirection::Right, steps),
+                steps,
+            );
+            let matching = left
+                .iter()
+                .zip(&expected_left)
+                .chain(right.iter().zip(&expected_right))
+                .filter(|(actual, expected)| actual == expected)
+                .count();
+            ProfileMatch {
+                profile: profile.clone(),
+                matching,
+                total: 2 * usize::from(steps),
+            }
+        })
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse(m.matching));
+
+    Ok(Calibration {
+        left,
+        right,
+        matches,
+    })
+}
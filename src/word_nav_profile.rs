@@ -1,4 +1,50 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+use crate::word_nav::{toolkit_ctrl_left, toolkit_ctrl_right, CustomRules, Toolkit};
+
+/// Smallest Ctrl+Left/Right jump, in chars, the built-in profiles use instead of arrow presses.
+pub const DEFAULT_MIN_CTRL_JUMP: usize = 4;
+
+/// Longest line, in chars, on which `CompatibleHomeEnd` presses Home/End. Longer lines may
+/// soft-wrap, and Home/End then stop at the edge of the wrapped row instead of the line.
+pub const HOME_END_MAX_LINE_CHARS: usize = 80;
+
+/// The built-in profiles.
+pub const BUILTIN_PROFILES: [WordNavProfile; 7] = [
+    WordNavProfile::Chrome,
+    WordNavProfile::Compatible,
+    WordNavProfile::CompatibleHomeEnd,
+    WordNavProfile::Gtk,
+    WordNavProfile::Qt,
+    WordNavProfile::LibreOffice,
+    WordNavProfile::VsCode,
+];
+
+/// Built-in profiles of common editors, keyed by lowercase application id: the X11 `WM_CLASS`
+/// instance or class, or the Wayland app id. `[word-nav-apps]` in the config file adds to and
+/// overrides these.
+pub const DEFAULT_APP_PROFILES: &[(&str, &str)] = &[
+    ("chromium", "chrome"),
+    ("chromium-browser", "chrome"),
+    ("google-chrome", "chrome"),
+    ("code", "vscode"),
+    ("code-oss", "vscode"),
+    ("codium", "vscode"),
+    ("vscodium", "vscode"),
+    ("gedit", "gtk"),
+    ("org.gnome.gedit", "gtk"),
+    ("gnome-text-editor", "gtk"),
+    ("org.gnome.texteditor", "gtk"),
+    ("mousepad", "gtk"),
+    ("org.xfce.mousepad", "gtk"),
+    ("pluma", "gtk"),
+    ("kate", "qt"),
+    ("org.kde.kate", "qt"),
+    ("kwrite", "qt"),
+    ("org.kde.kwrite", "qt"),
+    ("libreoffice-writer", "libreoffice"),
+    ("soffice", "libreoffice"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum WordNavProfile {
     /// Current behavior; best for Chrome/Docs-like editors.
     #[default]
@@ -7,6 +53,163 @@ pub enum WordNavProfile {
     /// Conservative mode: only use Ctrl+Left/Right when the jump span is
     /// highly likely to behave consistently across apps/toolkits.
     Compatible,
+
+    /// `Compatible`, plus Home/End to reach targets near the edges of short lines instead of
+    /// long runs of Left/Right.
+    CompatibleHomeEnd,
+
+    /// GTK text views (gedit, GNOME Text Editor, GTK entries).
+    Gtk,
+
+    /// Qt widgets (KDE apps, Kate, Qt Creator).
+    Qt,
+
+    /// LibreOffice Writer.
+    LibreOffice,
+
+    /// VS Code with its default word separators.
+    VsCode,
+
+    /// A user-defined profile (`[word-nav.NAME]` in the config file).
+    Custom(CustomWordNav),
+}
+
+/// A user-defined word navigation profile, for editors no built-in profile matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomWordNav {
+    /// The profile's name, `NAME` in `[word-nav.NAME]`.
+    pub name: String,
+    /// Where the editor's Ctrl+Left/Right stop.
+    pub rules: CustomRules,
+    /// Whether Ctrl+Left/Right may be used across line breaks.
+    pub crosses_newlines: bool,
+    /// Smallest jump, in chars, worth a Ctrl+Left/Right instead of arrow presses.
+    pub min_jump: usize,
+}
+
+impl Default for CustomWordNav {
+    fn default() -> Self {
+        Self {
+            name: "custom".to_string(),
+            rules: CustomRules {
+                punctuation_runs: true,
+                ..Default::default()
+            },
+            crosses_newlines: false,
+            min_jump: DEFAULT_MIN_CTRL_JUMP,
+        }
+    }
+}
+
+impl WordNavProfile {
+    /// The built-in profile called `name`, ignoring case.
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_PROFILES
+            .into_iter()
+            .find(|profile| profile.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The profile's name: its `--profile` value, or the name of a user-defined profile.
+    pub fn name(&self) -> &str {
+        match self {
+            WordNavProfile::Chrome => "chrome",
+            WordNavProfile::Compatible => "compatible",
+            WordNavProfile::CompatibleHomeEnd => "compatible-home-end",
+            WordNavProfile::Gtk => "gtk",
+            WordNavProfile::Qt => "qt",
+            WordNavProfile::LibreOffice => "libreoffice",
+            WordNavProfile::VsCode => "vscode",
+            WordNavProfile::Custom(custom) => &custom.name,
+        }
+    }
+
+    /// The toolkit whose stop positions this profile encodes, if any.
+    pub fn toolkit(&self) -> Option<Toolkit> {
+        match self {
+            WordNavProfile::Chrome
+            | WordNavProfile::Compatible
+            | WordNavProfile::CompatibleHomeEnd => None,
+            WordNavProfile::Gtk => Some(Toolkit::Gtk),
+            WordNavProfile::Qt => Some(Toolkit::Qt),
+            WordNavProfile::LibreOffice => Some(Toolkit::LibreOffice),
+            WordNavProfile::VsCode => Some(Toolkit::VsCode),
+            WordNavProfile::Custom(custom) => Some(Toolkit::Custom(custom.rules)),
+        }
+    }
+
+    /// Smallest jump, in chars, the planner makes with Ctrl+Left/Right.
+    pub fn min_ctrl_jump(&self) -> usize {
+        match self {
+            WordNavProfile::Custom(custom) => custom.min_jump,
+            _ => DEFAULT_MIN_CTRL_JUMP,
+        }
+    }
+
+    /// Whether the planner may press Ctrl+Left/Right to move from `from` to `to`, the stop of
+    /// the editor model (`word_nav::ctrl_left`/`ctrl_right` with `editor::is_word_char`).
+    ///
+    /// - `Chrome`: any jump within a line.
+    /// - `Compatible`, `CompatibleHomeEnd`: only jumps passing `compatible_ctrl_jump_is_safe()`.
+    /// - Toolkit profiles: jumps within a line where the toolkit stops at `to` as well, so the
+    ///   plan leaves the cursor where the simulator expects it.
+    /// - `Custom`: like toolkit profiles, crossing lines only if the profile allows it.
+    pub fn allows_ctrl_jump(&self, buf: &[char], from: usize, to: usize) -> bool {
+        let len = buf.len();
+        let (from, to) = (from.min(len), to.min(len));
+        let crosses_newline = buf[from.min(to)..from.max(to)].contains(&'\n');
+        match self.toolkit() {
+            None if *self != WordNavProfile::Chrome => compatible_ctrl_jump_is_safe(buf, from, to),
+            None => !crosses_newline,
+            Some(toolkit) => {
+                let stop = if to < from {
+                    toolkit_ctrl_left(buf, from, toolkit)
+                } else {
+                    toolkit_ctrl_right(buf, from, toolkit)
+                };
+                let crosses_newlines = match self {
+                    WordNavProfile::Custom(custom) => custom.crosses_newlines,
+                    _ => false,
+                };
+                (crosses_newlines || !crosses_newline) && stop == to
+            }
+        }
+    }
+
+    /// Where Home (for `to < from`) or End (for `to > from`) takes the cursor on its way from
+    /// `from` to `to`, if this profile uses them and that saves arrow presses.
+    ///
+    /// Only `CompatibleHomeEnd` does, and only on lines of at most [`HOME_END_MAX_LINE_CHARS`]:
+    /// Home when `to` is before the line or nearer its start than `from`, End likewise.
+    pub fn line_edge_jump(&self, buf: &[char], from: usize, to: usize) -> Option<usize> {
+        if *self != WordNavProfile::CompatibleHomeEnd {
+            return None;
+        }
+        let len = buf.len();
+        let (from, to) = (from.min(len), to.min(len));
+        let start = buf[..from]
+            .iter()
+            .rposition(|&c| c == '\n')
+            .map_or(0, |i| i + 1);
+        let end = buf[from..]
+            .iter()
+            .position(|&c| c == '\n')
+            .map_or(len, |i| from + i);
+        if end - start > HOME_END_MAX_LINE_CHARS {
+            return None;
+        }
+
+        // One Home/End press must replace at least two arrow presses.
+        let (edge, saves) = if to < from {
+            let arrows_after = to.saturating_sub(start) + 1;
+            (start, to <= start || arrows_after < from - to)
+        } else if to > from {
+            let arrows_after = end.saturating_sub(to) + 1;
+            (end, to >= end || arrows_after < to - from)
+        } else {
+            return None;
+        };
+        (from.abs_diff(edge) >= 2 && saves).then_some(edge)
+    }
 }
 
 /// Conservative predicate for deciding whether a Ctrl+Left/Right *jump span* is safe.
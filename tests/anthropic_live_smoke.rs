@@ -0,0 +1,26 @@
+#![cfg(feature = "llm")]
+
+use anyhow::Result;
+use drafter::llm::anthropic::AnthropicParagraphRephraseClient;
+use drafter::llm::{validate_phrase_alternatives, ParagraphRephraseOptions};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[ignore]
+async fn anthropic_rephrase_returns_valid_alternatives() -> Result<()> {
+    let client = AnthropicParagraphRephraseClient::from_env()?.with_max_concurrency(2);
+
+    let paragraphs = vec![
+        "The quick brown fox jumps over the lazy dog near the river bank.".to_string(),
+        "We reviewed the proposal and decided to move forward next week.".to_string(),
+    ];
+    let results = client
+        .rephrase_paragraphs(&paragraphs, ParagraphRephraseOptions::default())
+        .await?;
+
+    assert_eq!(results.len(), paragraphs.len());
+    for (paragraph, items) in paragraphs.iter().zip(&results) {
+        validate_phrase_alternatives(paragraph, items)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::{KEY_CAPSLOCK, KEY_LEFTSHIFT, KEY_RIGHTSHIFT};
+use drafter::keymap::LayoutSpec;
+use drafter::model::{Action, KeyState, Plan};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+use drafter::trace::plan_console_trace;
+
+const TEXT: &str = "IMPORTANT NOTICE: READ THIS FIRST!\nThe rest is normal text, mostly.\n";
+
+fn cfg(error_rate: f64, caps_lock_rate: f64) -> PlannerConfig {
+    PlannerConfig {
+        error_rate_per_word: error_rate,
+        caps_lock_rate,
+        ..Default::default()
+    }
+}
+
+fn presses(plan: &Plan, keycode: u32) -> usize {
+    plan.actions
+        .iter()
+        .filter(
+            |a| matches!(a, Action::Key { keycode: k, state: KeyState::Pressed } if *k == keycode),
+        )
+        .count()
+}
+
+#[test]
+fn caps_lock_types_all_caps_runs() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let plan = generate_plan(TEXT, cfg(0.0, 1.0), &mut rng).unwrap();
+
+    assert_eq!(presses(&plan, KEY_CAPSLOCK), 2);
+    // Shift is only needed for `:`, `!` and the capital `T` of the second line.
+    assert_eq!(
+        presses(&plan, KEY_LEFTSHIFT) + presses(&plan, KEY_RIGHTSHIFT),
+        3
+    );
+    assert!(plan.actions.iter().any(|a| matches!(
+        a,
+        Action::Modifiers { mods_locked, .. } if *mods_locked != 0
+    )));
+    assert!(matches!(
+        plan.actions.last(),
+        Some(Action::Modifiers { mods_locked: 0, .. })
+    ));
+    assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT);
+}
+
+#[test]
+fn caps_lock_is_off_by_default() {
+    for seed in 0..10 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let plan = generate_plan(TEXT, PlannerConfig::default(), &mut rng).unwrap();
+        assert_eq!(presses(&plan, KEY_CAPSLOCK), 0, "seed {seed}");
+    }
+}
+
+#[test]
+fn caps_lock_plans_roundtrip_with_errors() {
+    let text = "WARNING: DO NOT REMOVE THE COVER. ALL RIGHTS RESERVED, 2024.\nThanks.\n";
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let plan = generate_plan(text, cfg(0.5, 1.0), &mut rng).unwrap();
+        assert!(
+            presses(&plan, KEY_CAPSLOCK).is_multiple_of(2),
+            "seed {seed}"
+        );
+        assert_eq!(simulate_typed_text(&plan).unwrap(), text, "seed {seed}");
+    }
+}
+
+#[test]
+fn caps_lock_works_with_dead_keys() {
+    let text = "ÉTÉ À PARIS, DÉJÀ VU\n";
+    let cfg = PlannerConfig {
+        layout: LayoutSpec::new("de"),
+        ..cfg(0.0, 1.0)
+    };
+    let plan = generate_plan(text, cfg, &mut StdRng::seed_from_u64(4)).unwrap();
+
+    assert_eq!(presses(&plan, KEY_CAPSLOCK), 2);
+    assert_eq!(simulate_typed_text(&plan).unwrap(), text);
+}
+
+#[test]
+fn short_caps_words_and_zero_rate_hold_shift() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let plan = generate_plan("Use the API or NASA data.\n", cfg(0.0, 1.0), &mut rng).unwrap();
+    assert_eq!(presses(&plan, KEY_CAPSLOCK), 0);
+
+    let mut rng = StdRng::seed_from_u64(2);
+    let plan = generate_plan(TEXT, cfg(0.0, 0.0), &mut rng).unwrap();
+    assert_eq!(presses(&plan, KEY_CAPSLOCK), 0);
+}
+
+#[test]
+fn trace_decodes_caps_lock_typing() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let plan = generate_plan(TEXT, cfg(0.5, 1.0), &mut rng).unwrap();
+    assert!(presses(&plan, KEY_CAPSLOCK) > 0);
+
+    let typed: String = plan_console_trace(&plan.actions)
+        .iter()
+        .filter_map(|event| event.line.strip_prefix("Typing \""))
+        .collect();
+    let first_line = typed.split("\\n").next().unwrap();
+    assert!(first_line.starts_with("IMP"), "{typed}");
+    assert!(!first_line.chars().any(char::is_lowercase), "{typed}");
+}
@@ -0,0 +1,109 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::careful::{careful_tokens, CarefulKind};
+use drafter::estimate::DurationEstimator;
+use drafter::model::Plan;
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::{self, EditorBehavior};
+
+const TEXT: &str = "Mail alice.smith@example.org or read https://docs.example.com/guide?page=2, \
+then call parse_config() from src/main.rs. Words around them still get typos.";
+
+/// The text on screen after each edit.
+fn texts(plan: &Plan) -> Vec<String> {
+    let mut texts = Vec::new();
+    sim::replay(plan, EditorBehavior::default(), |_, editor, _| {
+        texts.push(editor.as_string())
+    })
+    .unwrap();
+    texts
+}
+
+#[test]
+fn urls_emails_and_identifiers_are_found_without_sentence_punctuation() {
+    let text = "See (www.rust-lang.org), mail bob@mail.co.uk, and use getValue() or \
+snake_case in config.toml; not e.g. 3.14, and/or YouTube.";
+    let chars: Vec<char> = text.chars().collect();
+    let found: Vec<(String, CarefulKind)> = careful_tokens(&chars)
+        .into_iter()
+        .map(|token| (chars[token.range].iter().collect(), token.kind))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("www.rust-lang.org".to_string(), CarefulKind::Url),
+            ("bob@mail.co.uk".to_string(), CarefulKind::Email),
+            ("getValue()".to_string(), CarefulKind::Identifier),
+            ("snake_case".to_string(), CarefulKind::Identifier),
+            ("config.toml".to_string(), CarefulKind::Identifier),
+        ]
+    );
+}
+
+#[test]
+fn careful_tokens_are_typed_without_mistakes_in_one_go() {
+    let tokens = [
+        "alice.smith@example.org",
+        "https://docs.example.com/guide?page=2",
+        "parse_config()",
+        "src/main.rs",
+    ];
+    for seed in 0..8 {
+        let cfg = PlannerConfig {
+            error_rate_per_word: 0.6,
+            punctuation_error_rate: 0.5,
+            immediate_fix_rate: 0.3,
+            ..Default::default()
+        };
+        let plan = generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap();
+        let mut texts = texts(&plan);
+        texts.dedup();
+        for token in tokens {
+            // Typed one character after the other, and left alone from then on.
+            let done = texts.iter().position(|text| text.ends_with(token)).unwrap();
+            let start = done + 1 - token.len();
+            for (len, text) in (1..=token.len()).zip(&texts[start..=done]) {
+                assert!(text.ends_with(&token[..len]), "seed {seed}: {text:?}");
+            }
+            assert!(
+                texts[done..].iter().all(|text| text.contains(token)),
+                "seed {seed}: {token:?} was edited"
+            );
+        }
+        assert_eq!(texts.last().unwrap(), TEXT);
+    }
+}
+
+#[test]
+fn careful_tokens_are_slower_and_can_be_turned_off() {
+    let text = "https://docs.example.com/guide/getting-started/index.html";
+    let wait = |careful_tokens: bool| {
+        let cfg = PlannerConfig {
+            error_rate_per_word: 0.0,
+            careful_tokens,
+            ..Default::default()
+        };
+        let plans = (0..10).map(|seed| {
+            generate_plan(text, cfg.clone(), &mut StdRng::seed_from_u64(seed)).unwrap()
+        });
+        plans
+            .map(|plan| sim::stats(&plan).total_wait_ms)
+            .sum::<u64>() as f64
+            / 10.0
+    };
+    let (careful, careless) = (wait(true), wait(false));
+    assert!(careful > careless * 1.2, "{careful} vs {careless}");
+
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        ..Default::default()
+    };
+    let estimate = DurationEstimator::new(text, &cfg).unwrap().estimate(&cfg);
+    let ratio = estimate.expected_ms / careful;
+    assert!(
+        (0.9..=1.1).contains(&ratio),
+        "estimated {}",
+        estimate.expected_ms
+    );
+}
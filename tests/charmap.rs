@@ -0,0 +1,70 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::charmap::CharMap;
+use drafter::keyboard::{KEY_E, KEY_LEFT};
+use drafter::model::{Action, KeyState};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+#[test]
+fn replacements_are_applied_before_planning() {
+    let charmap = CharMap::from_json_str(r#"{ "—": "--", "→": "->" }"#).unwrap();
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.3,
+        charmap,
+        ..Default::default()
+    };
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let plan = generate_plan("Wait — A → B, then C.\n", cfg, &mut rng).unwrap();
+
+    assert_eq!(
+        simulate_typed_text(&plan).unwrap(),
+        "Wait -- A -> B, then C.\n"
+    );
+}
+
+#[test]
+fn key_overrides_type_the_given_strokes() {
+    let charmap = CharMap::from_json_str(r#"{ "é": { "keys": [{ "keycode": 18 }] } }"#).unwrap();
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        charmap,
+        ..Default::default()
+    };
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let plan = generate_plan("é", cfg, &mut rng).unwrap();
+
+    let pressed: Vec<u32> = plan
+        .actions
+        .iter()
+        .filter_map(|a| match a {
+            Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            } => Some(*keycode),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(pressed, vec![KEY_E]);
+}
+
+#[test]
+fn rejects_invalid_charmaps() {
+    assert!(CharMap::from_json_str(r#"{ "ab": "x" }"#).is_err());
+    assert!(CharMap::from_json_str(r#"{ "—": "a—b" }"#).is_err());
+    assert!(CharMap::from_json_str(r#"{ "é": { "keys": [] } }"#).is_err());
+
+    let charmap = CharMap::from_json_str(&format!(
+        r#"{{ "é": {{ "keys": [{{ "keycode": {KEY_LEFT} }}] }} }}"#
+    ))
+    .unwrap();
+    let cfg = PlannerConfig {
+        charmap,
+        ..Default::default()
+    };
+    let err = generate_plan("é", cfg, &mut StdRng::seed_from_u64(1)).unwrap_err();
+    assert!(err.to_string().contains("not a typing key"), "{err}");
+}
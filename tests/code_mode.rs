@@ -0,0 +1,150 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::editor::{CodeEditor, EditorModel};
+use drafter::estimate::DurationEstimator;
+use drafter::keyboard::KEY_DELETE;
+use drafter::model::{Action, KeyState, Plan};
+use drafter::planner::{generate_plan, PlannerConfig, TypingMode};
+use drafter::sim::{self, EditorBehavior, SimEdit};
+
+const CODE: &str = "fn main() {
+    let values = vec![1, 2, 3];
+    for v in &values {
+        if *v > 1 {
+            println!(\"{}\", v);
+        }
+    }
+    // Sad :(
+    let total = values.iter().sum::<i32>();
+}
+";
+
+const CODE_EDITOR: CodeEditor = CodeEditor {
+    auto_indent: true,
+    auto_close_brackets: true,
+};
+
+fn code_config() -> PlannerConfig {
+    PlannerConfig {
+        mode: TypingMode::Code,
+        code_editor: CODE_EDITOR,
+        ..Default::default()
+    }
+}
+
+fn simulate(plan: &Plan, code_editor: CodeEditor) -> String {
+    let behavior = EditorBehavior {
+        code_editor,
+        ..Default::default()
+    };
+    sim::simulate_typed_text_with(plan, behavior).unwrap()
+}
+
+#[test]
+fn code_editor_indents_new_lines_and_closes_brackets() {
+    let mut editor = EditorModel::new();
+    for c in "    if (x) {\n".chars() {
+        editor.type_char(c, CODE_EDITOR);
+    }
+    assert_eq!(editor.as_string(), "    if (x) {\n    }");
+    assert_eq!(editor.cursor(), 17);
+
+    // Typing the closing bracket moves over the one the editor added.
+    for c in "y();\n}".chars() {
+        editor.type_char(c, CODE_EDITOR);
+    }
+    assert_eq!(editor.as_string(), "    if (x) {\n    y();\n    }");
+
+    // Not before a word.
+    let mut editor = EditorModel::new();
+    for c in "word".chars() {
+        editor.type_char(c, CODE_EDITOR);
+    }
+    editor.move_left(false);
+    editor.move_left(false);
+    assert_eq!(editor.type_char('(', CODE_EDITOR), 0);
+    assert_eq!(editor.as_string(), "wo(rd");
+}
+
+#[test]
+fn code_plans_rely_on_auto_indent_and_closed_brackets() {
+    for seed in 0..8 {
+        let cfg = PlannerConfig {
+            error_rate_per_word: 0.3,
+            immediate_fix_rate: 0.3,
+            ..code_config()
+        };
+        let plan = generate_plan(CODE, cfg, &mut StdRng::seed_from_u64(seed)).unwrap();
+        assert_eq!(simulate(&plan, CODE_EDITOR), CODE, "seed {seed}");
+        assert_ne!(simulate(&plan, CodeEditor::default()), CODE, "seed {seed}");
+
+        // The closing bracket added for the unclosed `:(` is deleted.
+        let deletes = plan
+            .actions
+            .iter()
+            .filter(|action| {
+                matches!(
+                    action,
+                    Action::Key {
+                        keycode: KEY_DELETE,
+                        state: KeyState::Pressed
+                    }
+                )
+            })
+            .count();
+        assert_eq!(deletes, 1, "seed {seed}");
+    }
+
+    // Without the editor's help, the plan types everything itself.
+    let cfg = PlannerConfig {
+        code_editor: CodeEditor::default(),
+        ..code_config()
+    };
+    let plan = generate_plan(CODE, cfg, &mut StdRng::seed_from_u64(0)).unwrap();
+    assert_eq!(simulate(&plan, CodeEditor::default()), CODE);
+}
+
+#[test]
+fn code_is_typed_without_doubled_spaces() {
+    let text = "let mut first = second + third * fourth - fifth / sixth; ".repeat(20);
+    let doubled = |mode: TypingMode, seed: u64| {
+        let cfg = PlannerConfig {
+            mode,
+            error_rate_per_word: 0.5,
+            word_variant_share: 1.0,
+            ..Default::default()
+        };
+        let plan = generate_plan(&text, cfg, &mut StdRng::seed_from_u64(seed)).unwrap();
+        let mut doubled = false;
+        sim::replay(&plan, EditorBehavior::default(), |_, editor, edit| {
+            doubled |= edit == SimEdit::Appended(' ') && editor.as_string().ends_with("  ");
+        })
+        .unwrap();
+        doubled
+    };
+    assert!((0..4).any(|seed| doubled(TypingMode::Prose, seed)));
+    assert!((0..4).all(|seed| !doubled(TypingMode::Code, seed)));
+}
+
+#[test]
+fn code_estimates_are_close_to_generated_plans() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        ..code_config()
+    };
+    let estimate = DurationEstimator::new(CODE, &cfg).unwrap().estimate(&cfg);
+    let actual = (0..20)
+        .map(|seed| {
+            let plan = generate_plan(CODE, cfg.clone(), &mut StdRng::seed_from_u64(seed)).unwrap();
+            sim::stats(&plan).total_wait_ms
+        })
+        .sum::<u64>() as f64
+        / 20.0;
+    let ratio = estimate.expected_ms / actual;
+    assert!(
+        (0.94..=1.06).contains(&ratio),
+        "estimated {:.0} ms, plans take {actual:.0} ms",
+        estimate.expected_ms
+    );
+}
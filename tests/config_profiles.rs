@@ -0,0 +1,202 @@
+use drafter::config::{Config, Setting};
+use drafter::word_nav::{CustomRules, WordChars};
+use drafter::word_nav_profile::{CustomWordNav, WordNavProfile};
+
+#[test]
+fn parses_profiles_into_flag_settings() {
+    let config = Config::parse(
+        r#"
+        [profiles.thesis]
+        wpm-min = 60
+        llm = true
+        llm_provider = "anthropic"
+        llm-temperature = 0.5
+        llm-exclude = ["code", "quote"]
+
+        [profiles.fast]
+        wpm-max = 120
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.profiles.keys().collect::<Vec<_>>(),
+        ["fast", "thesis"]
+    );
+    let thesis = config.profile("thesis").unwrap();
+    assert_eq!(thesis.settings["wpm-min"], Setting::Value("60".to_string()));
+    assert_eq!(thesis.settings["llm"], Setting::Bool(true));
+    assert_eq!(
+        thesis.settings["llm-provider"],
+        Setting::Value("anthropic".to_string())
+    );
+    assert_eq!(
+        thesis.settings["llm-temperature"],
+        Setting::Value("0.5".to_string())
+    );
+    assert_eq!(
+        thesis.settings["llm-exclude"],
+        Setting::List(vec!["code".to_string(), "quote".to_string()])
+    );
+}
+
+#[test]
+fn rejects_malformed_configs() {
+    let err = Config::parse("wpm-min = 60").unwrap_err();
+    assert!(err.to_string().contains("must be a table"), "{err}");
+
+    let err = Config::parse("[settings]\nwpm-min = 60").unwrap_err();
+    assert!(
+        err.to_string().contains("unknown table [settings]"),
+        "{err}"
+    );
+
+    let err = Config::parse("[profiles.thesis.llm]\nprovider = \"mock\"").unwrap_err();
+    assert!(
+        format!("{err:#}").contains("profiles.thesis.llm"),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn names_the_defined_profiles_when_one_is_missing() {
+    let config = Config::parse("[profiles.thesis]\n[profiles.fast]").unwrap();
+    let err = config.profile("draft").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "no profile \"draft\" (defined: fast, thesis)"
+    );
+
+    let err = Config::default().profile("draft").unwrap_err();
+    assert!(err.to_string().contains("defines none"), "{err}");
+}
+
+#[test]
+fn writes_settings_into_profiles_and_keeps_the_rest() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_profiles");
+    let _ = std::fs::remove_dir_all(&dir);
+    let path = dir.join("drafter").join("config.toml");
+
+    Config::write_setting(&path, "editor", "profile", "gtk").unwrap();
+    std::fs::write(
+        &path,
+        format!(
+            "# Typing profiles\n{}\n[profiles.fast]\nwpm-max = 120\n",
+            std::fs::read_to_string(&path).unwrap()
+        ),
+    )
+    .unwrap();
+    Config::write_setting(&path, "editor", "profile", "qt").unwrap();
+    Config::write_setting(&path, "fast", "profile", "vscode").unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("# Typing profiles\n"), "{text}");
+    let config = Config::parse(&text).unwrap();
+    assert_eq!(
+        config.profile("editor").unwrap().settings["profile"],
+        Setting::Value("qt".to_string())
+    );
+    let fast = &config.profile("fast").unwrap().settings;
+    assert_eq!(fast["wpm-max"], Setting::Value("120".to_string()));
+    assert_eq!(fast["profile"], Setting::Value("vscode".to_string()));
+
+    std::fs::write(&path, "[other]\n").unwrap();
+    assert!(Config::write_setting(&path, "editor", "profile", "qt").is_err());
+}
+
+#[test]
+fn parses_word_nav_profiles() {
+    let config = Config::parse(
+        r#"
+        [word-nav.notes]
+        word-chars = ["apostrophes", "hyphens"]
+        punctuation_runs = false
+        cross-newlines = true
+        min-jump = 6
+
+        [word-nav.plain]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.word_nav("notes").unwrap(),
+        WordNavProfile::Custom(CustomWordNav {
+            name: "notes".to_string(),
+            rules: CustomRules {
+                word_chars: WordChars {
+                    apostrophes: true,
+                    hyphens: true,
+                    ..Default::default()
+                },
+                punctuation_runs: false,
+            },
+            crosses_newlines: true,
+            min_jump: 6,
+        })
+    );
+    assert_eq!(
+        config.word_nav("plain").unwrap(),
+        WordNavProfile::Custom(CustomWordNav {
+            name: "plain".to_string(),
+            ..Default::default()
+        })
+    );
+    let err = config.word_nav("code").unwrap_err();
+    assert!(err.to_string().contains("defined: notes, plain"), "{err}");
+
+    for (table, message) in [
+        (
+            "word-chars = [\"slashes\"]",
+            "unknown word-chars class \"slashes\"",
+        ),
+        ("min-jump = 0", "positive integer"),
+        ("cross-newlines = \"yes\"", "true or false"),
+        ("right-stop = \"end\"", "unknown key right-stop"),
+    ] {
+        let err = Config::parse(&format!("[word-nav.bad]\n{table}")).unwrap_err();
+        assert!(format!("{err:#}").contains(message), "{table}: {err:#}");
+    }
+}
+
+#[test]
+fn maps_applications_to_word_nav_profiles() {
+    let config = Config::parse(
+        r#"
+        [word-nav-apps]
+        Obsidian = "notes"
+        gedit = "Compatible"
+
+        [word-nav.notes]
+        "#,
+    )
+    .unwrap();
+    let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(
+        config.app_word_nav(&ids(&["obsidian", "obsidian"])),
+        Some(("obsidian", "notes"))
+    );
+    // Entries of the config file win over the built-in ones.
+    assert_eq!(
+        config.app_word_nav(&ids(&["gedit", "Gedit"])),
+        Some(("gedit", "compatible"))
+    );
+    assert_eq!(
+        config.app_word_nav(&ids(&["kate", "kate"])),
+        Some(("kate", "qt"))
+    );
+    assert_eq!(
+        config.app_word_nav(&ids(&["org.gnome.TextEditor"])),
+        Some(("org.gnome.texteditor", "gtk"))
+    );
+    assert_eq!(config.app_word_nav(&ids(&["xterm", "XTerm"])), None);
+
+    for (apps, message) in [
+        ("code = \"emacs\"", "neither a built-in profile"),
+        ("code = 1", "must be a profile name"),
+    ] {
+        let err = Config::parse(&format!("[word-nav-apps]\n{apps}")).unwrap_err();
+        assert!(format!("{err:#}").contains(message), "{apps}: {err:#}");
+    }
+}
@@ -0,0 +1,57 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "Step one — then two → three.\n┌─┐\n└─┘\n";
+
+#[test]
+fn custom_keymap_covers_characters_outside_the_layout() {
+    for error_rate in [0.0, 0.4] {
+        let cfg = PlannerConfig {
+            error_rate_per_word: error_rate,
+            custom_keymap: true,
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let plan = generate_plan(TEXT, cfg, &mut rng).expect("plan generation");
+
+        assert_eq!(plan.config.layout, "us+custom");
+        assert!(plan.config.keymap.contains("emdash"));
+        assert_eq!(simulate_typed_text(&plan).expect("simulation"), TEXT);
+    }
+}
+
+#[test]
+fn custom_keymap_is_opt_in() {
+    let err = generate_plan(
+        TEXT,
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .expect_err("em dash is not on the us layout");
+    assert!(err.to_string().contains("U+2014"), "{err}");
+}
+
+#[test]
+fn custom_keymap_is_skipped_when_layout_suffices() {
+    let cfg = PlannerConfig {
+        custom_keymap: true,
+        ..Default::default()
+    };
+    let plan = generate_plan("plain ascii", cfg, &mut StdRng::seed_from_u64(1)).unwrap();
+    assert_eq!(plan.config.layout, "us");
+}
+
+#[test]
+fn custom_keymap_reports_capacity() {
+    let text: String = ('\u{2500}'..='\u{2540}').collect();
+    let cfg = PlannerConfig {
+        custom_keymap: true,
+        ..Default::default()
+    };
+    let err = generate_plan(&text, cfg, &mut StdRng::seed_from_u64(1)).expect_err("too many");
+    assert!(err.to_string().contains("at most"), "{err}");
+}
@@ -0,0 +1,58 @@
+use drafter::diff::{word_edits, WordEdit};
+
+/// Replace each edit's target span with its draft text.
+fn apply_to_target(target: &str, edits: &[WordEdit]) -> String {
+    let chars: Vec<char> = target.chars().collect();
+    let mut out = String::new();
+    let mut pos = 0usize;
+    for edit in edits {
+        let len = edit.target.chars().count();
+        let span: String = chars[edit.start..edit.start + len].iter().collect();
+        assert_eq!(span, edit.target, "edit does not match the target text");
+        out.extend(&chars[pos..edit.start]);
+        out.push_str(&edit.draft);
+        pos = edit.start + len;
+    }
+    out.extend(&chars[pos..]);
+    out
+}
+
+#[test]
+fn edits_turn_the_target_back_into_the_draft() {
+    let cases = [
+        (
+            "So basically the meeting went on way too long and people got tired.",
+            "The meeting dragged on, and by the end everyone looked tired.",
+        ),
+        ("the cat sat", "the big cat sat"),
+        ("the big cat sat", "the cat sat"),
+        ("Hello there", "Goodbye"),
+        ("One. Two. Three.", "One, two, three."),
+    ];
+
+    for (draft, target) in cases {
+        let edits = word_edits(draft, target);
+        assert!(!edits.is_empty());
+        for edit in &edits {
+            assert!(!edit.target.is_empty() && !edit.draft.is_empty());
+        }
+        for pair in edits.windows(2) {
+            assert!(pair[0].start + pair[0].target.chars().count() <= pair[1].start);
+        }
+        assert_eq!(apply_to_target(target, &edits), draft);
+    }
+}
+
+#[test]
+fn insertions_take_in_the_preceding_word() {
+    let edits = word_edits("the big cat sat", "the cat sat");
+    assert_eq!(
+        edits,
+        vec![WordEdit {
+            start: 0,
+            target: "the ".to_string(),
+            draft: "the big ".to_string(),
+        }]
+    );
+    assert!(word_edits("same text", "same text").is_empty());
+}
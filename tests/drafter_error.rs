@@ -0,0 +1,70 @@
+use drafter::error::DrafterError;
+use drafter::planner::{generate_plan, PlannerConfig};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+#[test]
+fn unsupported_characters_are_reported_with_their_position() {
+    let err = generate_plan(
+        "fine\nthen 漢",
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .unwrap_err();
+    match err {
+        DrafterError::UnsupportedCharacter {
+            ch, line, column, ..
+        } => assert_eq!((ch, line, column), ('漢', 2, 6)),
+        other => panic!("expected an unsupported character, got: {other:#}"),
+    }
+}
+
+#[test]
+fn rejected_tabs_are_their_own_error() {
+    let err = generate_plan(
+        "a\tb",
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, DrafterError::TabRejected { line: 1, column: 2 }),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn typed_errors_keep_their_message() {
+    let err = DrafterError::SeatNotFound {
+        requested: "seat9".to_string(),
+        available: vec!["seat0".to_string(), "seat1".to_string()],
+    };
+    assert_eq!(
+        err.to_string(),
+        "requested seat \"seat9\" not found; available seats: seat0, seat1"
+    );
+    assert_eq!(DrafterError::Aborted.to_string(), "aborted");
+}
+
+#[test]
+fn typed_errors_survive_anyhow_context() {
+    use anyhow::Context;
+
+    let err = Err::<(), _>(anyhow::Error::from(DrafterError::Aborted))
+        .context("while playing")
+        .unwrap_err();
+    assert!(matches!(DrafterError::from(err), DrafterError::Aborted));
+
+    let other = DrafterError::from(anyhow::anyhow!("something else"));
+    assert!(matches!(other, DrafterError::Other(_)));
+    assert_eq!(other.to_string(), "something else");
+}
+
+#[cfg(not(feature = "llm"))]
+#[test]
+fn llm_clients_report_that_llm_support_is_disabled() {
+    use drafter::llm::anthropic::AnthropicParagraphRephraseClient;
+
+    let err = AnthropicParagraphRephraseClient::new("key").unwrap_err();
+    assert!(matches!(err, DrafterError::LlmDisabled), "{err:#}");
+}
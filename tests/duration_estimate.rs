@@ -0,0 +1,96 @@
+use drafter::estimate::DurationEstimator;
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const TEXT: &str = "Typing plans take a while. The planner adds pauses after punctuation, \
+thinks between sentences, and makes mistakes it fixes right away or later on.\n\n\
+A second paragraph, with Some Capitals and numbers like 42, keeps going for a bit longer so \
+the averages settle. It ends here!\n\n\
+The third paragraph is short, but it has questions? And more words: enough of them to make a \
+few typos along the way, then review everything at the end.";
+
+/// Mean duration of plans generated with `cfg`, in ms.
+fn mean_plan_ms(cfg: &PlannerConfig, seeds: u64) -> f64 {
+    let total: u64 = (0..seeds)
+        .map(|seed| {
+            let plan = generate_plan(TEXT, cfg.clone(), &mut StdRng::seed_from_u64(seed)).unwrap();
+            sim::stats(&plan).total_wait_ms
+        })
+        .sum();
+    total as f64 / seeds as f64
+}
+
+#[test]
+fn estimates_are_close_to_generated_plans() {
+    let estimator = DurationEstimator::new(TEXT, &PlannerConfig::default()).unwrap();
+    for (wpm_min, wpm_max, error_rate, fix_rate) in [
+        (40.0, 60.0, 0.0, 0.35),
+        (40.0, 60.0, 0.05, 0.35),
+        (70.0, 90.0, 0.1, 0.8),
+        (25.0, 35.0, 0.15, 0.2),
+        (100.0, 140.0, 0.05, 0.35),
+    ] {
+        let cfg = PlannerConfig {
+            wpm_min,
+            wpm_max,
+            error_rate_per_word: error_rate,
+            immediate_fix_rate: fix_rate,
+            ..Default::default()
+        };
+        let estimate = estimator.estimate(&cfg);
+        assert!(estimate.fastest_ms <= estimate.expected_ms);
+        assert!(estimate.expected_ms <= estimate.slowest_ms);
+
+        let actual = mean_plan_ms(&cfg, 20);
+        let ratio = estimate.expected_ms / actual;
+        assert!(
+            (0.94..=1.06).contains(&ratio),
+            "{wpm_min}-{wpm_max} WPM, error rate {error_rate}, fix rate {fix_rate}: \
+             estimated {:.0} ms, plans take {actual:.0} ms",
+            estimate.expected_ms
+        );
+    }
+}
+
+#[test]
+fn estimates_count_punctuation_mistakes_and_confusions() {
+    let estimator = DurationEstimator::new(TEXT, &PlannerConfig::default()).unwrap();
+    let cfg = PlannerConfig {
+        punctuation_error_rate: 0.5,
+        missing_period_rate: 1.0,
+        confusion_rate: 0.5,
+        ..Default::default()
+    };
+    let estimate = estimator.estimate(&cfg);
+    assert!(estimate.expected_ms > estimator.estimate(&PlannerConfig::default()).expected_ms);
+
+    let actual = mean_plan_ms(&cfg, 20);
+    let ratio = estimate.expected_ms / actual;
+    assert!(
+        (0.94..=1.06).contains(&ratio),
+        "estimated {:.0} ms, plans take {actual:.0} ms",
+        estimate.expected_ms
+    );
+}
+
+#[test]
+fn estimates_count_session_breaks_and_rereads() {
+    let estimator = DurationEstimator::new(TEXT, &PlannerConfig::default()).unwrap();
+    let cfg = PlannerConfig {
+        session_break_rate: 1.0,
+        session_break_ms_min: 20_000,
+        session_break_ms_max: 60_000,
+        reread_after_ms: 30_000,
+        ..Default::default()
+    };
+    let estimate = estimator.estimate(&cfg);
+    let actual = mean_plan_ms(&cfg, 40);
+    let ratio = estimate.expected_ms / actual;
+    assert!(
+        (0.94..=1.06).contains(&ratio),
+        "estimated {:.0} ms, plans take {actual:.0} ms",
+        estimate.expected_ms
+    );
+}
@@ -0,0 +1,107 @@
+use drafter::editor::{CursorPosition, EditorModel};
+use drafter::keyboard::{KEY_BACKSPACE, KEY_DOWN, KEY_END, KEY_HOME, KEY_LEFT, KEY_RIGHT, KEY_UP};
+
+fn editor_with(text: &str) -> EditorModel {
+    let mut editor = EditorModel::new();
+    for c in text.chars() {
+        editor.insert_char(c);
+    }
+    editor
+}
+
+fn type_text(editor: &mut EditorModel, text: &str) {
+    for c in text.chars() {
+        editor.insert_char(c);
+    }
+}
+
+#[test]
+fn shift_home_and_end_select_to_the_line_edges() {
+    let mut editor = editor_with("first line\nsecond line\nthird");
+    editor.press_edit_key(KEY_UP, false, false);
+    assert_eq!(
+        editor.cursor_position(),
+        CursorPosition { line: 2, column: 6 }
+    );
+
+    editor.press_edit_key(KEY_HOME, true, false);
+    assert_eq!(editor.selection(), Some(11..16));
+    type_text(&mut editor, "2nd");
+    assert_eq!(editor.as_string(), "first line\n2ndd line\nthird");
+
+    editor.press_edit_key(KEY_END, true, false);
+    assert_eq!(
+        editor.press_edit_key(KEY_BACKSPACE, false, false),
+        Some("d line".chars().collect())
+    );
+    editor.press_edit_key(KEY_END, true, true);
+    editor.press_edit_key(KEY_BACKSPACE, false, false);
+    assert_eq!(editor.as_string(), "first line\n2nd");
+    assert!(editor.at_end());
+
+    editor.press_edit_key(KEY_HOME, true, true);
+    assert_eq!(editor.selection(), Some(0..14));
+}
+
+#[test]
+fn shift_ctrl_arrows_select_words_and_plain_arrows_collapse() {
+    let mut editor = editor_with("one two three");
+    editor.press_edit_key(KEY_LEFT, true, true);
+    editor.press_edit_key(KEY_LEFT, true, true);
+    assert_eq!(editor.selection(), Some(4..13));
+
+    editor.press_edit_key(KEY_RIGHT, true, true);
+    assert_eq!(editor.selection(), Some(7..13));
+
+    editor.press_edit_key(KEY_LEFT, false, false);
+    assert_eq!((editor.cursor(), editor.selection()), (7, None));
+}
+
+#[test]
+fn up_and_down_keep_the_column_where_lines_allow() {
+    let mut editor = editor_with("a long line\nshort\nanother long line");
+    editor.press_edit_key(KEY_UP, false, false);
+    assert_eq!(
+        editor.cursor_position(),
+        CursorPosition { line: 2, column: 6 }
+    );
+    editor.press_edit_key(KEY_UP, false, false);
+    assert_eq!(
+        editor.cursor_position(),
+        CursorPosition { line: 1, column: 6 }
+    );
+    editor.press_edit_key(KEY_UP, false, false);
+    assert_eq!(editor.cursor(), 0);
+
+    editor.press_edit_key(KEY_DOWN, false, false);
+    editor.press_edit_key(KEY_DOWN, false, false);
+    editor.press_edit_key(KEY_DOWN, false, false);
+    assert!(editor.at_end());
+}
+
+#[test]
+fn character_ids_follow_characters_through_edits() {
+    let mut editor = editor_with("abc");
+    editor.press_edit_key(KEY_LEFT, false, false);
+    editor.press_edit_key(KEY_BACKSPACE, false, false);
+    let id = editor.insert_char('x');
+    assert_eq!(editor.as_string(), "axc");
+    assert_eq!(editor.char_ids(), &[0, id, 2]);
+}
+
+#[test]
+fn autocorrect_fixes_a_typo_once_whitespace_follows_it() {
+    let mut editor = EditorModel::with_undo();
+    type_text(&mut editor, "Teh");
+    assert!(!editor.autocorrect());
+    type_text(&mut editor, " ");
+    assert!(editor.autocorrect());
+    assert_eq!(editor.as_string(), "The ");
+    assert!(editor.at_end());
+
+    type_text(&mut editor, "fine ");
+    assert!(!editor.autocorrect());
+    assert!(editor.undo());
+    assert!(editor.undo());
+    assert_eq!(editor.as_string(), "Teh ");
+}
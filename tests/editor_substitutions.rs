@@ -0,0 +1,68 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::{KEY_DOT, KEY_MINUS};
+use drafter::model::{Action, KeyState};
+use drafter::planner::{generate_plan, EditorSubstitutions, PlannerConfig, SubstitutionStrategy};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "Wait\u{2014}really\u{2026}\n";
+
+fn cfg(substitutions: EditorSubstitutions) -> PlannerConfig {
+    PlannerConfig {
+        error_rate_per_word: 0.0,
+        substitutions,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn plain_editor_rejects_em_dash_and_ellipsis() {
+    let err = generate_plan(
+        TEXT,
+        cfg(EditorSubstitutions::default()),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("U+2014"), "{err}");
+}
+
+#[test]
+fn google_docs_types_ascii_spellings() {
+    let plan = generate_plan(
+        TEXT,
+        cfg(EditorSubstitutions::GOOGLE_DOCS),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .unwrap();
+
+    let pressed: Vec<u32> = plan
+        .actions
+        .iter()
+        .filter_map(|a| match a {
+            Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            } if *keycode == KEY_MINUS || *keycode == KEY_DOT => Some(*keycode),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        pressed,
+        vec![KEY_MINUS, KEY_MINUS, KEY_DOT, KEY_DOT, KEY_DOT]
+    );
+
+    // The simulator does not model the editor's substitution.
+    assert_eq!(simulate_typed_text(&plan).unwrap(), "Wait--really...\n");
+}
+
+#[test]
+fn strategies_are_per_character() {
+    let substitutions = EditorSubstitutions {
+        em_dash: SubstitutionStrategy::Ascii,
+        ellipsis: SubstitutionStrategy::Reject,
+        autocorrect: false,
+    };
+    let err = generate_plan(TEXT, cfg(substitutions), &mut StdRng::seed_from_u64(1)).unwrap_err();
+    assert!(err.to_string().contains("U+2026"), "{err}");
+}
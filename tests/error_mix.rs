@@ -0,0 +1,105 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::KEY_BACKSPACE;
+use drafter::model::{Action, KeyState};
+use drafter::planner::{generate_plan, ErrorMix, PlannerConfig};
+use drafter::sim::{self, EditorBehavior};
+
+const TEXT: &str = "Several quiet gardens surround every modern library where students \
+gather before lectures begin across campus during spring afternoons.";
+
+fn config(mix: ErrorMix) -> PlannerConfig {
+    PlannerConfig {
+        error_rate_per_word: 1.0,
+        immediate_fix_rate: 1.0,
+        error_mix: mix,
+        ..Default::default()
+    }
+}
+
+/// Each mistyped word next to the word it stands for: the last word typed before the text first
+/// shrinks after growing.
+fn wrong_words(cfg: PlannerConfig, seed: u64) -> Vec<(String, String)> {
+    let plan = generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap();
+    let words: Vec<&str> = TEXT.split_whitespace().collect();
+    let mut found = Vec::new();
+    let mut previous = String::new();
+    let mut growing = true;
+    sim::replay(&plan, EditorBehavior::default(), |_, editor, _| {
+        let text = editor.as_string();
+        if text.len() < previous.len() && growing {
+            let typed: Vec<&str> = previous.split_whitespace().collect();
+            let index = typed.len() - 1;
+            let word = words[index].trim_end_matches('.');
+            found.push((typed[index].to_string(), word.to_string()));
+        }
+        growing = text.len() >= previous.len();
+        previous = text;
+    })
+    .unwrap();
+    found
+}
+
+#[test]
+fn transpositions_only_swap_neighboring_letters() {
+    let mix = ErrorMix {
+        transposition: true,
+        ..ErrorMix::NONE
+    };
+    for seed in 0..4 {
+        let found = wrong_words(config(mix), seed);
+        assert!(!found.is_empty(), "seed {seed}");
+        for (wrong, word) in found {
+            let (wrong, word): (Vec<char>, Vec<char>) =
+                (wrong.chars().collect(), word.chars().collect());
+            let diff: Vec<usize> = (0..word.len()).filter(|&i| wrong[i] != word[i]).collect();
+            assert_eq!(diff.len(), 2, "{wrong:?} for {word:?}");
+            assert_eq!(diff[1], diff[0] + 1);
+            assert_eq!(wrong[diff[0]], word[diff[1]]);
+            assert_eq!(wrong[diff[1]], word[diff[0]]);
+        }
+    }
+}
+
+#[test]
+fn adjacent_substitutions_only_change_one_letter() {
+    let mix = ErrorMix {
+        adjacent_substitution: true,
+        ..ErrorMix::NONE
+    };
+    for seed in 0..4 {
+        let found = wrong_words(config(mix), seed);
+        assert!(!found.is_empty(), "seed {seed}");
+        for (wrong, word) in found {
+            assert_eq!(wrong.chars().count(), word.chars().count());
+            let diff = wrong
+                .chars()
+                .zip(word.chars())
+                .filter(|(a, b)| a != b)
+                .count();
+            assert_eq!(diff, 1, "{wrong:?} for {word:?}");
+        }
+    }
+}
+
+#[test]
+fn no_error_kinds_means_no_corrections() {
+    let cfg = PlannerConfig {
+        punctuation_error_rate: 1.0,
+        missing_period_rate: 1.0,
+        confusion_rate: 1.0,
+        ..config(ErrorMix::NONE)
+    };
+    for seed in 0..4 {
+        let plan = generate_plan(TEXT, cfg.clone(), &mut StdRng::seed_from_u64(seed)).unwrap();
+        assert!(!plan.actions.iter().any(|action| matches!(
+            action,
+            Action::Key {
+                keycode: KEY_BACKSPACE,
+                state: KeyState::Pressed
+            }
+        )));
+        assert_eq!(sim::simulate_typed_text(&plan).unwrap(), TEXT);
+    }
+}
@@ -0,0 +1,75 @@
+use drafter::geometry::{Geometry, KeyboardGeometry};
+use drafter::keyboard::{
+    KEY_102ND, KEY_A, KEY_APOSTROPHE, KEY_BACKSLASH, KEY_C, KEY_D, KEY_E, KEY_F, KEY_R,
+    KEY_RIGHTBRACE, KEY_S, KEY_X, KEY_Z,
+};
+use drafter::keymap::LayoutSpec;
+use drafter::layout::KeyboardLayout;
+
+#[test]
+fn ansi_neighbors_follow_row_stagger() {
+    let geometry = KeyboardGeometry::ansi();
+    assert_eq!(
+        geometry.neighbors(KEY_D),
+        vec![KEY_E, KEY_R, KEY_S, KEY_F, KEY_X, KEY_C]
+    );
+}
+
+#[test]
+fn iso_geometry_moves_backslash_and_adds_102nd() {
+    let geometry = KeyboardGeometry::iso();
+    assert_eq!(geometry.neighbors(KEY_102ND), vec![KEY_A, KEY_Z]);
+    assert!(geometry.neighbors(KEY_BACKSLASH).contains(&KEY_APOSTROPHE));
+    assert!(geometry.neighbors(KEY_BACKSLASH).contains(&KEY_RIGHTBRACE));
+
+    let ansi = KeyboardGeometry::ansi();
+    assert_eq!(ansi.position(KEY_102ND), None);
+    assert!(!ansi.neighbors(KEY_BACKSLASH).contains(&KEY_APOSTROPHE));
+}
+
+#[test]
+fn german_less_than_needs_iso() {
+    let iso = KeyboardLayout::from_spec(&LayoutSpec::new("de")).unwrap();
+    let strokes = iso.strokes_for_output_char('<').unwrap();
+    assert_eq!(strokes[0].keycode, KEY_102ND);
+    assert!(iso.adjacent_chars('y').contains(&'<'));
+
+    let ansi = KeyboardLayout::from_spec(&LayoutSpec {
+        geometry: Geometry::Ansi,
+        ..LayoutSpec::new("de")
+    })
+    .unwrap();
+    assert!(ansi.strokes_for_output_char('<').is_none());
+    assert!(ansi.strokes_for_output_char('y').is_some());
+    assert_eq!(ansi.geometry().geometry(), Geometry::Ansi);
+}
+
+#[test]
+fn us_adjacency_matches_qwerty() {
+    let layout = KeyboardLayout::us_qwerty().unwrap();
+    let mut adjacent = layout.adjacent_chars('d');
+    adjacent.sort_unstable();
+    assert_eq!(adjacent, vec!['c', 'e', 'f', 'r', 's', 'x']);
+
+    let mut adjacent = layout.adjacent_chars('G');
+    adjacent.sort_unstable();
+    assert_eq!(adjacent, vec!['B', 'F', 'H', 'T', 'V', 'Y']);
+
+    // Letters do not reach the number row; digits reach the top letter row.
+    assert!(!layout.adjacent_chars('q').contains(&'1'));
+    assert!(layout.adjacent_chars('1').contains(&'q'));
+}
+
+#[test]
+fn adjacency_follows_the_active_layout() {
+    // On a German layout the key right of `t` types `z`, and `ö` sits next to `l`.
+    let layout = KeyboardLayout::from_spec(&LayoutSpec::new("de")).unwrap();
+    assert!(layout.adjacent_chars('t').contains(&'z'));
+    assert!(layout.adjacent_chars('l').contains(&'ö'));
+
+    let layout = KeyboardLayout::from_spec(&LayoutSpec::new("fr")).unwrap();
+    // AZERTY: `a` is where QWERTY has `q`, so `z` is next to it and `q` below it.
+    assert!(layout.adjacent_chars('a').contains(&'z'));
+    assert!(layout.adjacent_chars('a').contains(&'q'));
+    assert!(!layout.adjacent_chars('a').contains(&'s'));
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::KEY_BACKSPACE;
+use drafter::model::{Action, KeyState, Plan};
+use drafter::plan_edit::{anonymize, scale_waits, verify_neutral};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::playback::{play_plan_to, CancellationToken, Clock, KeySink, PlaybackOptions};
+use drafter::sim::{self, simulate_typed_text};
+
+const TEXT: &str = "Holding a key down repeats it, which is how long mistakes get deleted.";
+
+fn plan(hold_backspace_from: usize, seed: u64) -> Plan {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.6,
+        hold_backspace_from,
+        ..Default::default()
+    };
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+fn repeats(plan: &Plan) -> Vec<&Action> {
+    plan.actions
+        .iter()
+        .filter(|action| matches!(action, Action::KeyRepeat { .. }))
+        .collect()
+}
+
+#[test]
+fn a_repeat_expands_into_evenly_spaced_taps() {
+    let repeat = Action::KeyRepeat {
+        keycode: KEY_BACKSPACE,
+        count: 3,
+        interval_ms: 33,
+    };
+    let press = Action::Key {
+        keycode: KEY_BACKSPACE,
+        state: KeyState::Pressed,
+    };
+    let release = Action::Key {
+        keycode: KEY_BACKSPACE,
+        state: KeyState::Released,
+    };
+    let wait = Action::Wait { ms: 33 };
+    assert_eq!(
+        repeat.expand(),
+        [
+            press.clone(),
+            release.clone(),
+            wait.clone(),
+            press.clone(),
+            release.clone(),
+            wait,
+            press,
+            release,
+        ]
+    );
+    assert_eq!(repeat.duration_ms(), 66);
+    assert_eq!(Action::Wait { ms: 5 }.expand(), [Action::Wait { ms: 5 }]);
+}
+
+#[test]
+fn held_backspace_deletes_mistakes_of_the_given_length() {
+    let mut held = 0;
+    for seed in 1..=6 {
+        let plan = plan(2, seed);
+        assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT, "seed {seed}");
+        verify_neutral(&plan).unwrap();
+        for repeat in repeats(&plan) {
+            let Action::KeyRepeat { keycode, count, .. } = *repeat else {
+                unreachable!()
+            };
+            assert_eq!(keycode, KEY_BACKSPACE);
+            assert!(count >= 2);
+            held += 1;
+        }
+    }
+    assert!(held > 0);
+}
+
+#[test]
+fn backspace_is_not_held_by_default() {
+    for seed in 1..=6 {
+        assert!(repeats(&plan(PlannerConfig::default().hold_backspace_from, seed)).is_empty());
+    }
+}
+
+#[derive(Default)]
+struct KeyRecorder(Vec<Action>);
+
+impl KeySink for KeyRecorder {
+    fn send_key(&mut self, keycode: u32, state: KeyState) -> Result<()> {
+        self.0.push(Action::Key { keycode, state });
+        Ok(())
+    }
+
+    fn send_modifiers(&mut self, _: u32, _: u32, _: u32, _: u32) -> Result<()> {
+        Ok(())
+    }
+
+    fn reset(&mut self) {}
+}
+
+#[derive(Default)]
+struct VirtualClock(u64);
+
+impl Clock for VirtualClock {
+    fn sleep(&mut self, ms: u64, cancel: &CancellationToken) -> bool {
+        self.0 += ms;
+        cancel.is_cancelled()
+    }
+}
+
+#[test]
+fn playback_taps_the_held_key_in_virtual_time() {
+    let plan = plan(2, 3);
+    assert!(!repeats(&plan).is_empty());
+    let mut sink = KeyRecorder::default();
+    let mut clock = VirtualClock::default();
+    play_plan_to(
+        &plan,
+        &PlaybackOptions::default(),
+        &mut sink,
+        &mut clock,
+        &CancellationToken::new(),
+        &mut (),
+    )
+    .unwrap();
+
+    let keys: Vec<Action> = plan
+        .actions
+        .iter()
+        .flat_map(Action::expand)
+        .filter(|action| matches!(action, Action::Key { .. }))
+        .collect();
+    assert_eq!(sink.0, keys);
+    assert_eq!(clock.0, sim::stats(&plan).total_wait_ms);
+}
+
+#[test]
+fn editing_keeps_held_keys() {
+    let plan = plan(2, 4);
+    let intervals = |plan: &Plan| -> Vec<u64> {
+        repeats(plan)
+            .into_iter()
+            .map(|repeat| match repeat {
+                Action::KeyRepeat { interval_ms, .. } => *interval_ms,
+                _ => unreachable!(),
+            })
+            .collect()
+    };
+    let scaled = scale_waits(&plan, 2.0).unwrap();
+    assert_eq!(
+        intervals(&scaled),
+        intervals(&plan).iter().map(|ms| ms * 2).collect::<Vec<_>>()
+    );
+
+    // Backspace is an editing key, so anonymizing keeps it.
+    assert_eq!(repeats(&anonymize(&plan, false).unwrap()), repeats(&plan));
+}
@@ -0,0 +1,89 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::geometry::Geometry;
+use drafter::heatmap::{heatmap_svg, key_usage};
+use drafter::keyboard::{KEY_A, KEY_B, KEY_LEFTSHIFT, KEY_RIGHTSHIFT, KEY_SPACE};
+use drafter::layout::KeyboardLayout;
+use drafter::model::{Action, KeyState, Plan, PlanConfig};
+use drafter::planner::{generate_plan, PlannerConfig};
+
+fn tap(keycode: u32, hold_ms: u64) -> [Action; 3] {
+    [
+        Action::Key {
+            keycode,
+            state: KeyState::Pressed,
+        },
+        Action::Wait { ms: hold_ms },
+        Action::Key {
+            keycode,
+            state: KeyState::Released,
+        },
+    ]
+}
+
+#[test]
+fn counts_presses_and_hold_times_per_key() {
+    let mut actions = Vec::new();
+    actions.extend(tap(KEY_A, 40));
+    actions.push(Action::Wait { ms: 100 });
+    actions.extend(tap(KEY_A, 60));
+    actions.extend(tap(KEY_B, 30));
+    let plan = Plan {
+        version: 1,
+        config: PlanConfig {
+            layout: "us".to_string(),
+            keymap_format: 1,
+            keymap: String::new(),
+            wpm_target: 0.0,
+            word_nav_profile: None,
+            paragraph_breaks: Default::default(),
+            strip_trailing_whitespace: false,
+            strip_final_newline: false,
+        },
+        actions,
+        annotations: Vec::new(),
+    };
+
+    let usage = key_usage(&plan).unwrap();
+    let summary: Vec<(&str, usize, u64)> = usage
+        .iter()
+        .map(|key| (key.label.as_str(), key.presses, key.total_hold_ms))
+        .collect();
+    assert_eq!(summary, [("a", 2, 100), ("b", 1, 30)]);
+    assert!((usage[0].mean_hold_ms - 50.0).abs() < 1e-9);
+    assert!((usage[0].share - 2.0 / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn heatmap_shades_every_pressed_key() {
+    let text = "The heatmap of a plan follows the letters of its text.";
+    let plan = generate_plan(
+        text,
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(3),
+    )
+    .unwrap();
+    let usage = key_usage(&plan).unwrap();
+    assert_eq!(usage[0].keycode, KEY_SPACE);
+    assert!(usage
+        .iter()
+        .any(|key| [KEY_LEFTSHIFT, KEY_RIGHTSHIFT].contains(&key.keycode)));
+    let shares: f64 = usage.iter().map(|key| key.share).sum();
+    assert!((shares - 1.0).abs() < 1e-9);
+
+    let layout = KeyboardLayout::for_plan_config(&plan.config).unwrap();
+    for geometry in [Geometry::Ansi, Geometry::Iso] {
+        let svg = heatmap_svg(&usage, &layout, geometry);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        for key in &usage {
+            let title = format!(
+                "<title>{} ({}): {}</title>",
+                key.label, key.keycode, key.presses
+            );
+            assert!(svg.contains(&title), "{title} missing");
+        }
+        // The most pressed key has the hottest color.
+        assert!(svg.contains("fill=\"#d7301f\""));
+    }
+}
@@ -0,0 +1,122 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::{keystroke_for_output_char, KEY_LEFT, KEY_RIGHTALT};
+use drafter::keymap::LayoutSpec;
+use drafter::layout::KeyboardLayout;
+use drafter::model::{Action, KeyState};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+use drafter::trace::{plan_console_trace, trace_events_for_plan};
+
+#[test]
+fn us_layout_matches_builtin_keystroke_table() {
+    let layout = KeyboardLayout::us_qwerty().expect("us layout should compile");
+
+    let mut candidates = vec!['\n', ' ', '’', '”'];
+    candidates.extend((33u8..=126u8).map(char::from));
+
+    for c in candidates {
+        let expected = keystroke_for_output_char(c).expect("builtin table covers ASCII");
+        assert_eq!(
+            layout.strokes_for_output_char(c),
+            Some(&[expected][..]),
+            "stroke mismatch for {c:?}"
+        );
+    }
+
+    assert!(layout.strokes_for_output_char('\t').is_none());
+    assert!(layout.strokes_for_output_char('é').is_none());
+}
+
+#[test]
+fn german_layout_types_accents_with_dead_keys() {
+    let layout = KeyboardLayout::from_spec(&LayoutSpec::new("de")).expect("de layout");
+
+    let strokes = layout.strokes_for_output_char('é').expect("é via dead key");
+    assert_eq!(strokes.len(), 2);
+
+    let strokes = layout
+        .strokes_for_output_char('ä')
+        .expect("ä is a direct key");
+    assert_eq!(strokes.len(), 1);
+
+    let strokes = layout.strokes_for_output_char('@').expect("@ via AltGr");
+    assert_eq!(strokes.len(), 1);
+    assert!(strokes[0].altgr);
+}
+
+#[test]
+fn compose_key_sequences_use_right_alt() {
+    let spec = LayoutSpec {
+        options: Some("compose:ralt".to_string()),
+        ..LayoutSpec::default()
+    };
+    let layout = KeyboardLayout::from_spec(&spec).expect("us + compose layout");
+
+    let strokes = layout.strokes_for_output_char('é').expect("é via Compose");
+    assert_eq!(strokes.len(), 3);
+    assert_eq!(strokes[0].keycode, KEY_RIGHTALT);
+}
+
+#[test]
+fn dead_key_plan_roundtrips_through_simulator() {
+    let final_text = "Café déjà vu, naïve Grüße @ 10 €.\nÀ bientôt!\n";
+
+    for error_rate in [0.0, 0.4] {
+        let cfg = PlannerConfig {
+            error_rate_per_word: error_rate,
+            layout: LayoutSpec::new("de"),
+            ..Default::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let plan = generate_plan(final_text, cfg, &mut rng).expect("plan generation");
+
+        assert_eq!(plan.config.layout, "de");
+        let typed = simulate_typed_text(&plan).expect("simulation");
+        assert_eq!(typed, final_text);
+    }
+}
+
+#[test]
+fn rejects_characters_the_layout_cannot_type() {
+    let err = generate_plan(
+        "Café",
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .expect_err("us layout has no é");
+    assert!(err.to_string().contains("U+00E9"), "{err}");
+}
+
+#[test]
+fn german_plans_trace_with_their_keymap() {
+    let cfg = PlannerConfig {
+        layout: LayoutSpec::new("de"),
+        ..Default::default()
+    };
+    let mut plan = generate_plan("x", cfg, &mut StdRng::seed_from_u64(1)).unwrap();
+    let layout = KeyboardLayout::for_plan_config(&plan.config).unwrap();
+
+    // On German keyboards Z and Y are swapped and ü has its own key.
+    let mut keycodes: Vec<u32> = "zürich"
+        .chars()
+        .map(|c| layout.strokes_for_output_char(c).expect("direct key")[0].keycode)
+        .collect();
+    keycodes.push(KEY_LEFT);
+    plan.actions = keycodes
+        .into_iter()
+        .flat_map(|keycode| {
+            [KeyState::Pressed, KeyState::Released].map(|state| Action::Key { keycode, state })
+        })
+        .collect();
+
+    let lines: Vec<String> = trace_events_for_plan(&plan)
+        .unwrap()
+        .into_iter()
+        .map(|event| event.line)
+        .collect();
+    assert_eq!(lines, vec!["Typing \"zürich\"...".to_string()]);
+    assert_ne!(plan_console_trace(&plan.actions)[0].line, lines[0]);
+}
@@ -0,0 +1,244 @@
+use std::collections::HashSet;
+
+use drafter::llm::cache::{cache_key, CacheSecret, CachedItems, LlmCache, LLM_CACHE_VERSION};
+#[cfg(feature = "llm")]
+use drafter::llm::cache::{is_encrypted, ENCRYPTED_MAGIC};
+use drafter::llm::{HumanMistake, MistakeKind, PhraseAlternative, Redactor, RephrasePrompts};
+
+const MODEL: &str = "mock-model";
+
+fn key(paragraph: &str) -> String {
+    cache_key(
+        MODEL,
+        "subtle",
+        &RephrasePrompts::default(),
+        &Redactor::default(),
+        false,
+        paragraph,
+    )
+}
+
+fn alternative(original: &str, alternative: &str) -> PhraseAlternative {
+    PhraseAlternative {
+        original: original.to_string(),
+        alternative: alternative.to_string(),
+        revise: None,
+    }
+}
+
+/// A cache with alternatives fetched (limit 4) for each of `paragraphs`.
+fn cache_for(paragraphs: &[&str]) -> LlmCache {
+    let mut cache = LlmCache::default();
+    for paragraph in paragraphs {
+        let (original, _) = paragraph.split_once(' ').unwrap();
+        cache.entry(key(paragraph), paragraph).alternatives = Some(CachedItems {
+            max_items: 4,
+            items: vec![alternative(original, "Alt")],
+            reviewed: false,
+        });
+    }
+    cache
+}
+
+#[test]
+fn upgrades_version_1_files_to_paragraph_keys() {
+    let v1 = r#"{
+        "version": 1,
+        "model": "mock-model",
+        "max_suggestions": 3,
+        "rewrite_strength": "subtle",
+        "paragraphs": ["First one here.", "Second one here."],
+        "alternatives_by_paragraph": [[{"original": "First", "alternative": "Initial"}], []],
+        "mistakes_by_paragraph": [[], [{"original": "here", "mistake": "hear", "kind": "homophone"}]],
+        "drafts_by_paragraph": ["First one.", "Second one."]
+    }"#;
+    let cache = LlmCache::from_json(v1, 2).unwrap();
+    assert_eq!(cache.version, LLM_CACHE_VERSION);
+    assert_eq!(cache.entries.len(), 2);
+
+    let first = key("First one here.");
+    let second = key("Second one here.");
+    assert_eq!(
+        cache.alternatives(&first, 3),
+        Some(vec![alternative("First", "Initial")])
+    );
+    assert_eq!(cache.alternatives(&first, 4), None);
+    assert_eq!(
+        cache.mistakes(&second, 2),
+        Some(vec![HumanMistake {
+            original: "here".to_string(),
+            mistake: "hear".to_string(),
+            kind: MistakeKind::Homophone,
+        }])
+    );
+    assert_eq!(cache.draft(&second), Some("Second one."));
+
+    // The upgraded cache is written and read back as version 2.
+    let json = cache.to_json().unwrap();
+    assert!(json.contains(&format!("\"version\": {LLM_CACHE_VERSION}")));
+    assert_eq!(LlmCache::from_json(&json, 2).unwrap(), cache);
+}
+
+#[test]
+fn rejects_unknown_versions() {
+    let err = LlmCache::from_json(r#"{"version": 9, "entries": {}}"#, 4).unwrap_err();
+    assert!(
+        err.to_string().contains("unsupported LLM cache version 9"),
+        "{err}"
+    );
+}
+
+#[test]
+fn reuses_unchanged_paragraphs_and_misses_edited_ones() {
+    let cache = cache_for(&["Alpha stays the same.", "Beta is the old wording."]);
+
+    let unchanged = key("Alpha stays the same.");
+    let edited = key("Beta is the new wording.");
+    assert!(cache.alternatives(&unchanged, 4).is_some());
+    assert!(cache.alternatives(&edited, 4).is_none());
+    // Another limit, model or strength is another request.
+    assert!(cache.alternatives(&unchanged, 2).is_none());
+    let other_model = cache_key(
+        "other-model",
+        "subtle",
+        &RephrasePrompts::default(),
+        &Redactor::default(),
+        false,
+        "Alpha stays the same.",
+    );
+    assert!(cache.alternatives(&other_model, 4).is_none());
+    assert_ne!(
+        cache_key(
+            MODEL,
+            "dramatic",
+            &RephrasePrompts::default(),
+            &Redactor::default(),
+            false,
+            "Alpha stays the same."
+        ),
+        unchanged
+    );
+}
+
+#[test]
+fn misses_when_the_redaction_changes() {
+    let paragraph = "Mail Ada at ada@example.com by 5 May.";
+    let cache = cache_for(&[paragraph]);
+    let redacted = |redactor: &Redactor| {
+        cache_key(
+            MODEL,
+            "subtle",
+            &RephrasePrompts::default(),
+            redactor,
+            false,
+            paragraph,
+        )
+    };
+    let emails = Redactor {
+        emails: true,
+        ..Redactor::default()
+    };
+    let ada = Redactor {
+        emails: true,
+        terms: vec!["Ada".to_string()],
+        ..Redactor::default()
+    };
+
+    // Masking nothing keeps the key of a cache written without redaction.
+    assert!(cache
+        .alternatives(&redacted(&Redactor::default()), 4)
+        .is_some());
+    assert!(cache.alternatives(&redacted(&emails), 4).is_none());
+    assert_ne!(redacted(&emails), redacted(&ada));
+    assert_ne!(
+        redacted(&emails),
+        redacted(&Redactor {
+            numbers: true,
+            ..Redactor::default()
+        })
+    );
+    // The order and repeats of terms do not change what is masked.
+    assert_eq!(
+        redacted(&Redactor {
+            terms: vec!["Bob".to_string(), "Ada".to_string(), "Ada".to_string()],
+            ..Redactor::default()
+        }),
+        redacted(&Redactor {
+            terms: vec!["Ada".to_string(), "Bob".to_string()],
+            ..Redactor::default()
+        })
+    );
+}
+
+#[test]
+fn prunes_entries_of_paragraphs_no_longer_in_the_draft() {
+    let mut cache = cache_for(&["Alpha stays the same.", "Beta is the old wording."]);
+    let edited = "Beta is the new wording.";
+    cache.entry(key(edited), edited).draft = Some("Beta new.".to_string());
+
+    let current = [key("Alpha stays the same."), key(edited)];
+    let keep: HashSet<&str> = current.iter().map(String::as_str).collect();
+    assert_eq!(cache.prune(&keep), 1);
+    assert_eq!(cache.entries.len(), 2);
+    assert!(!cache.to_json().unwrap().contains("old wording"));
+    assert_eq!(cache.prune(&keep), 0);
+}
+
+#[cfg(feature = "llm")]
+#[test]
+fn encrypted_caches_decrypt_with_the_same_secret() {
+    let json = cache_for(&["Alpha stays the same."]).to_json().unwrap();
+    let secret = CacheSecret::new("correct horse battery staple");
+    let file = secret.encrypt(&json).unwrap();
+    assert!(is_encrypted(&file));
+    assert!(!is_encrypted(json.as_bytes()));
+    assert!(!file.windows(5).any(|window| window == b"Alpha"));
+    assert_eq!(secret.decrypt(&file).unwrap(), json);
+
+    // Every file gets its own salt and nonce.
+    let again = secret.encrypt(&json).unwrap();
+    let header = ENCRYPTED_MAGIC.len();
+    assert_ne!(file[header..header + 16], again[header..header + 16]);
+}
+
+#[cfg(not(feature = "llm"))]
+#[test]
+fn encrypting_a_cache_needs_the_llm_feature() {
+    let secret = CacheSecret::new("correct horse battery staple");
+    let err = secret
+        .encrypt(r#"{"version": 2, "entries": {}}"#)
+        .unwrap_err();
+    assert!(err.to_string().contains("--features llm"), "{err}");
+}
+
+#[cfg(feature = "llm")]
+#[test]
+fn encrypted_caches_reject_a_wrong_secret_or_a_damaged_file() {
+    let secret = CacheSecret::new("correct horse battery staple");
+    let file = secret.encrypt(r#"{"version": 2, "entries": {}}"#).unwrap();
+
+    let err = CacheSecret::new("wrong").decrypt(&file).unwrap_err();
+    assert!(err.to_string().contains("wrong key"), "{err}");
+
+    let mut tampered = file.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(secret.decrypt(&tampered).is_err());
+    // The salt is authenticated too.
+    let mut salted = file.clone();
+    salted[ENCRYPTED_MAGIC.len()] ^= 1;
+    assert!(secret.decrypt(&salted).is_err());
+
+    let err = secret
+        .decrypt(&file[..ENCRYPTED_MAGIC.len() + 20])
+        .unwrap_err();
+    assert!(err.to_string().contains("truncated"), "{err}");
+    assert!(secret.decrypt(&file[..file.len() - 1]).is_err());
+
+    let mut bad_magic = file;
+    bad_magic[0] = b'X';
+    let err = secret.decrypt(&bad_magic).unwrap_err();
+    assert!(
+        err.to_string().contains("not an encrypted LLM cache"),
+        "{err}"
+    );
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "llm")]
+
+use std::path::Path;
+use std::process::Command;
+
+use drafter::llm::cache::is_encrypted;
+
+const DRAFT: &str = "We need a quick way to show how big the problem is.\n\nMaybe their team can start very soon, perhaps tomorrow.\n";
+
+/// `drafter plan` of `draft.txt` in `dir` with `--llm-cache cache.json`, without any API key in
+/// the environment.
+fn plan(dir: &Path, provider: &str, output: &str, cache_key: Option<&str>) {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_drafter"));
+    command
+        .current_dir(dir)
+        .args([
+            "plan",
+            "--input",
+            "draft.txt",
+            "--seed",
+            "7",
+            "--output",
+            output,
+        ])
+        .args([
+            "--llm",
+            "--llm-provider",
+            provider,
+            "--llm-model",
+            "test-model",
+        ])
+        .args(["--llm-cache", "cache.json"])
+        .env_remove("DRAFTER_LLM_CACHE_KEY");
+    for key in ["OPENROUTER_API_KEY", "OPENAI_API_KEY", "ANTHROPIC_API_KEY"] {
+        command.env_remove(key);
+    }
+    if let Some(secret) = cache_key {
+        command.env("DRAFTER_LLM_CACHE_KEY", secret);
+    }
+    let output = command.output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(!stderr.contains("Falling back"), "{stderr}");
+}
+
+#[test]
+fn encrypting_a_fully_cached_draft_needs_no_api_key() {
+    let dir = std::env::temp_dir().join(format!("drafter-llm-cache-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("draft.txt"), DRAFT).unwrap();
+
+    // The mock provider fills a plaintext cache for every paragraph.
+    plan(&dir, "mock", "plaintext.json", None);
+    let cache = std::fs::read(dir.join("cache.json")).unwrap();
+    assert!(!is_encrypted(&cache));
+
+    // A remote provider without credentials plans from the cache and encrypts it.
+    plan(&dir, "openrouter", "encrypted.json", Some("correct horse"));
+    assert!(is_encrypted(
+        &std::fs::read(dir.join("cache.json")).unwrap()
+    ));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("encrypted.json")).unwrap(),
+        std::fs::read_to_string(dir.join("plaintext.json")).unwrap()
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,54 @@
+use drafter::llm::ParagraphExclusions;
+use regex::Regex;
+
+fn paragraphs(text: &[&str]) -> Vec<String> {
+    text.iter().map(|paragraph| paragraph.to_string()).collect()
+}
+
+#[test]
+fn excludes_code_blocks_and_quotations() {
+    let paragraphs = paragraphs(&[
+        "Run the script first.",
+        "```sh\nmake build",
+        "make test\n```",
+        "    let x = 1;\n    let y = 2;",
+        "> Quoted from the spec.\n> Second line.",
+        "“The plan is final.”",
+        "“Yes,” she said, “go ahead.”",
+    ]);
+    let exclusions = ParagraphExclusions {
+        code_blocks: true,
+        quotations: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        exclusions.excluded(&paragraphs),
+        vec![false, true, true, true, true, true, false]
+    );
+    assert_eq!(
+        ParagraphExclusions::default().excluded(&paragraphs),
+        vec![false; 7]
+    );
+}
+
+#[test]
+fn excludes_paragraphs_by_index_and_pattern() {
+    let paragraphs = paragraphs(&[
+        "Dear Dana,",
+        "The report is attached.",
+        "CONFIDENTIAL: salary figures follow.",
+        "Best regards",
+    ]);
+    let exclusions = ParagraphExclusions {
+        indices: vec![0],
+        patterns: vec![Regex::new("^CONFIDENTIAL").unwrap()],
+        ..Default::default()
+    };
+
+    assert!(!exclusions.is_empty());
+    assert_eq!(
+        exclusions.excluded(&paragraphs),
+        vec![true, false, true, false]
+    );
+}
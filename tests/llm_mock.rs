@@ -0,0 +1,103 @@
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::llm::mock::MockRephraseProvider;
+use drafter::llm::{
+    validate_human_mistakes, validate_paragraph_draft, validate_phrase_alternatives,
+    ParagraphDraftOptions, ParagraphMistakeOptions, ParagraphRephraseOptions,
+};
+use drafter::planner::{
+    generate_plan_with_draft_provider, generate_plan_with_rephrase_provider, PlannerConfig,
+};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "We often need a quick way to show how big the problem is.\n\nMaybe their team can start very soon, perhaps tomorrow.\n";
+
+/// Poll a future that never waits on I/O to completion.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn mock_suggestions_are_deterministic_and_valid() {
+    let mock = MockRephraseProvider;
+    let paragraph = "We often need a quick way to show how big the problem is.";
+
+    let alternatives = mock.rephrase_paragraph(paragraph, &ParagraphRephraseOptions::default());
+    let pairs: Vec<_> = alternatives
+        .iter()
+        .map(|a| (a.original.as_str(), a.alternative.as_str()))
+        .collect();
+    assert_eq!(
+        pairs,
+        [
+            ("often", "frequently"),
+            ("quick", "fast"),
+            ("show", "demonstrate"),
+            ("big", "large")
+        ]
+    );
+    validate_phrase_alternatives(paragraph, &alternatives).unwrap();
+
+    let options = ParagraphRephraseOptions {
+        max_suggestions: 2,
+        ..Default::default()
+    };
+    assert_eq!(mock.rephrase_paragraph(paragraph, &options).len(), 2);
+
+    let mistakes = mock.paragraph_mistakes(
+        "Their problem is there.",
+        &ParagraphMistakeOptions { max_mistakes: 3 },
+    );
+    validate_human_mistakes("Their problem is there.", &mistakes).unwrap();
+    assert_eq!(mistakes.len(), 3);
+    assert_eq!(mistakes[0].mistake, "There");
+
+    let draft = mock.draft_paragraph(paragraph);
+    assert_eq!(
+        draft,
+        "We frequently need a fast way to demonstrate how large the problem is."
+    );
+    validate_paragraph_draft(paragraph, &draft).unwrap();
+    assert_eq!(mock.draft_paragraph("Nothing to swap."), "Nothing to swap");
+}
+
+#[test]
+fn plans_round_trip_through_the_mock_provider() {
+    for seed in 0..4 {
+        let cfg = PlannerConfig {
+            error_rate_per_word: 0.1,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let plan = block_on(generate_plan_with_rephrase_provider(
+            TEXT,
+            cfg.clone(),
+            &MockRephraseProvider,
+            ParagraphRephraseOptions::default(),
+            &mut rng,
+        ))
+        .expect("plan generation should succeed");
+        assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT);
+
+        let plan = block_on(generate_plan_with_draft_provider(
+            TEXT,
+            cfg,
+            &MockRephraseProvider,
+            ParagraphDraftOptions::default(),
+            &mut rng,
+        ))
+        .expect("plan generation should succeed");
+        assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT);
+    }
+}
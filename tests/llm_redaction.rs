@@ -0,0 +1,46 @@
+use drafter::llm::Redactor;
+
+#[test]
+fn masks_emails_numbers_and_terms() {
+    let redactor = Redactor {
+        emails: true,
+        numbers: true,
+        terms: vec!["Dana".to_string()],
+    };
+    let paragraph = "Dana (dana.k@example.org) called 555-0100 twice. Ask Dana, not Danae.";
+
+    let redaction = redactor.redact(paragraph);
+    assert_eq!(
+        redaction.text(),
+        "[NAME_1] ([EMAIL_1]) called [NUMBER_1] twice. Ask [NAME_1], not Danae."
+    );
+    assert_eq!(redaction.unmask(redaction.text()), paragraph);
+}
+
+#[test]
+fn flags_suggestions_touching_masked_text() {
+    let redactor = Redactor {
+        numbers: true,
+        ..Default::default()
+    };
+    let redaction = redactor.redact("The invoice total was 1,250.00 last month.");
+    assert_eq!(
+        redaction.text(),
+        "The invoice total was [NUMBER_1] last month."
+    );
+
+    assert!(!redaction.touches_masked("invoice total", "bill"));
+    assert!(redaction.touches_masked("was [NUMBER_1]", "came to [NUMBER_1]"));
+    assert!(redaction.touches_masked("last month", "last month ([NUMBER_1])"));
+    assert!(redaction.touches_masked("1]", "one]"));
+}
+
+#[test]
+fn empty_redactor_sends_paragraph_unchanged() {
+    let redactor = Redactor::default();
+    assert!(redactor.is_empty());
+
+    let redaction = redactor.redact("Call me at 555-0100.");
+    assert!(!redaction.is_masked());
+    assert_eq!(redaction.text(), "Call me at 555-0100.");
+}
@@ -0,0 +1,120 @@
+use std::io::Cursor;
+
+use drafter::llm::cache::{CachedItems, LlmCacheEntry};
+use drafter::llm::review::Reviewer;
+use drafter::llm::{HumanMistake, MistakeKind, PhraseAlternative};
+
+const PARAGRAPH: &str = "We need a quick way to show how big the problem is.";
+
+fn alternative(original: &str, alternative: &str) -> PhraseAlternative {
+    PhraseAlternative {
+        original: original.to_string(),
+        alternative: alternative.to_string(),
+        revise: None,
+    }
+}
+
+fn entry() -> LlmCacheEntry {
+    LlmCacheEntry {
+        paragraph: PARAGRAPH.to_string(),
+        alternatives: Some(CachedItems {
+            max_items: 4,
+            items: vec![
+                alternative("quick way", "fast way"),
+                alternative("show", "demonstrate"),
+                alternative("big", "large"),
+            ],
+            reviewed: false,
+        }),
+        mistakes: Some(CachedItems {
+            max_items: 2,
+            items: vec![HumanMistake {
+                original: "problem".to_string(),
+                mistake: "problme".to_string(),
+                kind: MistakeKind::Misspelling,
+            }],
+            reviewed: false,
+        }),
+        ..Default::default()
+    }
+}
+
+/// Reviews `entry` with alternatives (limit 4) and mistakes (limit 2) in use, answering with
+/// `answers`; returns whether it changed and the prompts shown.
+fn review(entry: &mut LlmCacheEntry, answers: &str) -> (bool, String) {
+    let mut output = Vec::new();
+    let mut reviewer = Reviewer::new(Cursor::new(answers.to_string()), &mut output);
+    let changed = reviewer.review_entry(entry, Some(4), Some(2)).unwrap();
+    (changed, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn accepts_rejects_and_edits_suggestions() {
+    let mut entry = entry();
+    let (changed, output) = review(&mut entry, "y\nn\ne\nhuge\nn\n");
+    assert!(changed);
+
+    let alternatives = entry.alternatives.unwrap();
+    assert!(alternatives.reviewed);
+    assert_eq!(
+        alternatives.items,
+        [
+            alternative("quick way", "fast way"),
+            alternative("big", "huge")
+        ]
+    );
+    let mistakes = entry.mistakes.unwrap();
+    assert!(mistakes.reviewed);
+    assert!(mistakes.items.is_empty());
+
+    assert!(output.contains("[quick way] to show"), "{output}");
+    assert!(output.contains("Misspelling mistake: \"problem\" -> \"problme\""));
+}
+
+#[test]
+fn asks_again_after_an_invalid_edit_or_answer() {
+    let mut entry = entry();
+    // An empty replacement is not a valid alternative; "maybe" is not an answer.
+    let (_, output) = review(&mut entry, "maybe\ne\n\ne\nlarge-ish\ny\ny\ny\n");
+    assert!(output.contains("Rejected edit"), "{output}");
+    assert_eq!(
+        entry.alternatives.unwrap().items[0],
+        alternative("quick way", "large-ish")
+    );
+}
+
+#[test]
+fn accept_all_keeps_the_rest_without_asking() {
+    let mut entry = entry();
+    let (_, output) = review(&mut entry, "n\na\n");
+    assert_eq!(entry.alternatives.unwrap().items.len(), 2);
+    assert_eq!(entry.mistakes.unwrap().items.len(), 1);
+    assert_eq!(output.matches("Accept?").count(), 2);
+}
+
+#[test]
+fn skips_reviewed_items_and_tasks_not_in_use() {
+    let mut entry = entry();
+    review(&mut entry, "a\n");
+    let reviewed = entry.clone();
+    // Nothing left to ask, so no input is read.
+    let (changed, output) = review(&mut entry, "");
+    assert!(!changed);
+    assert!(output.is_empty());
+    assert_eq!(entry, reviewed);
+
+    // Items fetched with another limit are not the ones planned with.
+    let mut entry = self::entry();
+    let mut reviewer = Reviewer::new(Cursor::new(String::new()), Vec::new());
+    assert!(!reviewer.review_entry(&mut entry, Some(3), None).unwrap());
+}
+
+#[test]
+fn fails_when_the_answers_run_out() {
+    let mut entry = entry();
+    let mut reviewer = Reviewer::new(Cursor::new("y\n".to_string()), Vec::new());
+    let err = reviewer
+        .review_entry(&mut entry, Some(4), Some(2))
+        .unwrap_err();
+    assert!(err.to_string().contains("input ended"), "{err}");
+}
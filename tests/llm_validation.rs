@@ -1,4 +1,7 @@
-use drafter::llm::{validate_phrase_alternatives, PhraseAlternative};
+use drafter::llm::{
+    changed_words, validate_human_mistakes, validate_phrase_alternatives, HumanMistake,
+    MistakeKind, PhraseAlternative, SuggestionQuality,
+};
 
 #[test]
 fn validate_accepts_unique_non_overlapping_alternatives() {
@@ -7,10 +10,12 @@ fn validate_accepts_unique_non_overlapping_alternatives() {
         PhraseAlternative {
             original: "dragged on, and by the end everyone".to_string(),
             alternative: "ran long. Everyone".to_string(),
+            revise: None,
         },
         PhraseAlternative {
             original: "looked tired".to_string(),
             alternative: "looked tired by the end".to_string(),
+            revise: None,
         },
     ];
 
@@ -23,6 +28,7 @@ fn validate_rejects_non_unique_original() {
     let items = vec![PhraseAlternative {
         original: "word".to_string(),
         alternative: "term".to_string(),
+        revise: None,
     }];
 
     let err = validate_phrase_alternatives(paragraph, &items).unwrap_err();
@@ -39,10 +45,12 @@ fn validate_rejects_overlapping_originals() {
         PhraseAlternative {
             original: "abc".to_string(),
             alternative: "abx".to_string(),
+            revise: None,
         },
         PhraseAlternative {
             original: "bcd".to_string(),
             alternative: "bxd".to_string(),
+            revise: None,
         },
     ];
 
@@ -59,6 +67,7 @@ fn validate_rejects_whitespace_at_span_edges() {
     let items = vec![PhraseAlternative {
         original: "hello ".to_string(),
         alternative: "hi".to_string(),
+        revise: None,
     }];
 
     let err = validate_phrase_alternatives(paragraph, &items).unwrap_err();
@@ -74,6 +83,7 @@ fn validate_rejects_unsupported_chars() {
     let items = vec![PhraseAlternative {
         original: "world".to_string(),
         alternative: "wo\trld".to_string(),
+        revise: None,
     }];
 
     let err = validate_phrase_alternatives(paragraph, &items).unwrap_err();
@@ -82,3 +92,82 @@ fn validate_rejects_unsupported_chars() {
         "unexpected error: {err:?}"
     );
 }
+
+#[test]
+fn validate_mistakes_accepts_words_and_informal_spans() {
+    let paragraph = "I will definitely go there with numerous friends.";
+    let items = vec![
+        HumanMistake {
+            original: "definitely".to_string(),
+            mistake: "definately".to_string(),
+            kind: MistakeKind::Misspelling,
+        },
+        HumanMistake {
+            original: "there".to_string(),
+            mistake: "their".to_string(),
+            kind: MistakeKind::Homophone,
+        },
+        HumanMistake {
+            original: "numerous friends".to_string(),
+            mistake: "a bunch of friends".to_string(),
+            kind: MistakeKind::Informal,
+        },
+    ];
+
+    validate_human_mistakes(paragraph, &items).expect("should validate");
+}
+
+#[test]
+fn validate_mistakes_rejects_partial_words_and_distant_misspellings() {
+    let paragraph = "I will definitely go there.";
+
+    let partial = vec![HumanMistake {
+        original: "finitely".to_string(),
+        mistake: "finately".to_string(),
+        kind: MistakeKind::Misspelling,
+    }];
+    let err = validate_human_mistakes(paragraph, &partial).unwrap_err();
+    assert!(
+        err.to_string().contains("word boundaries"),
+        "unexpected error: {err:?}"
+    );
+
+    let distant = vec![HumanMistake {
+        original: "definitely".to_string(),
+        mistake: "certainly".to_string(),
+        kind: MistakeKind::Misspelling,
+    }];
+    let err = validate_human_mistakes(paragraph, &distant).unwrap_err();
+    assert!(
+        err.to_string().contains("at most 2"),
+        "unexpected error: {err:?}"
+    );
+}
+
+#[test]
+fn quality_filter_discards_trivial_alternatives() {
+    let alternative = |original: &str, alternative: &str| PhraseAlternative {
+        original: original.to_string(),
+        alternative: alternative.to_string(),
+        revise: None,
+    };
+    assert_eq!(changed_words("however it works", "however, it works"), 0);
+    assert_eq!(changed_words("the plan", "The plan"), 0);
+    assert_eq!(changed_words("looked tired", "seemed very tired"), 2);
+
+    let mut items = vec![
+        alternative("however it", "however, it"),
+        alternative("the meeting", "The meeting"),
+        alternative("looked", "seemed"),
+        alternative("ran", "run"),
+    ];
+    assert_eq!(SuggestionQuality::default().retain(&mut items), 2);
+    assert_eq!(items.len(), 2);
+
+    let strict = SuggestionQuality {
+        min_edit_distance: 2,
+        min_changed_words: 1,
+    };
+    assert_eq!(strict.retain(&mut items), 1);
+    assert_eq!(items, [alternative("looked", "seemed")]);
+}
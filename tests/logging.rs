@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::Layer;
+
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::playback::{ConsoleObserver, PlaybackObserver};
+use drafter::trace::{CursorPosition, TraceDetail, TraceLine, TraceLineKind};
+
+/// Fields of every event, by name, with the message under `message`.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<HashMap<String, String>>>>);
+
+#[derive(Default)]
+struct Fields(HashMap<String, String>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}"));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.0.lock().unwrap().push(fields.0);
+    }
+}
+
+#[test]
+fn planner_logs_the_generated_plan_with_fields() {
+    let recorder = Recorder::default();
+    let subscriber = tracing_subscriber::registry().with(recorder.clone());
+    let text = "Logged while planning.\n";
+    let plan = tracing::subscriber::with_default(subscriber, || {
+        generate_plan(
+            text,
+            PlannerConfig::default(),
+            &mut StdRng::seed_from_u64(3),
+        )
+        .unwrap()
+    });
+
+    let events = recorder.0.lock().unwrap();
+    let generated = events
+        .iter()
+        .find(|fields| fields["message"] == "generated plan")
+        .expect("generated plan event");
+    assert_eq!(generated["actions"], plan.actions.len().to_string());
+    assert_eq!(generated["chars"], text.chars().count().to_string());
+    assert!(events.iter().any(|fields| fields["message"] == "planning"));
+}
+
+#[test]
+fn trace_lines_log_their_text_only_at_trace_level() {
+    let line = TraceLine {
+        action_index: 42,
+        offset_ms: 1_500,
+        cursor: CursorPosition { line: 3, column: 7 },
+        kind: TraceLineKind::Replace,
+        line: "Replace \"scret\" with \"secret\"".to_string(),
+    };
+
+    let recorder = Recorder::default();
+    let subscriber =
+        tracing_subscriber::registry().with(recorder.clone().with_filter(LevelFilter::INFO));
+    tracing::subscriber::with_default(subscriber, || {
+        ConsoleObserver::new(TraceDetail::Plain).on_trace_event(&line);
+    });
+
+    let events = recorder.0.lock().unwrap();
+    let logged = events
+        .iter()
+        .find(|fields| fields["message"] == "trace line")
+        .expect("trace line event");
+    assert_eq!(logged["action_index"], "42");
+    assert_eq!(logged["cursor_line"], "3");
+    assert_eq!(logged["cursor_column"], "7");
+    assert!(
+        events
+            .iter()
+            .all(|fields| fields.values().all(|value| !value.contains("secret"))),
+        "{events:?}"
+    );
+}
@@ -0,0 +1,39 @@
+use drafter::playback::{check_max_duration, parse_duration_ms};
+
+#[test]
+fn parses_durations_in_each_unit() {
+    assert_eq!(parse_duration_ms("1500ms"), Ok(1_500));
+    assert_eq!(parse_duration_ms("90s"), Ok(90_000));
+    assert_eq!(parse_duration_ms("1.5s"), Ok(1_500));
+    assert_eq!(parse_duration_ms("45m"), Ok(2_700_000));
+    assert_eq!(parse_duration_ms("2h"), Ok(7_200_000));
+    assert_eq!(parse_duration_ms("0.5h"), Ok(1_800_000));
+}
+
+#[test]
+fn rejects_zero_negative_and_malformed_durations() {
+    for arg in [
+        "0s", "0ms", "-5m", "-0.5h", "45", "m", "", "1.5x", "ten m", "NaNs", "infh", "5 m",
+    ] {
+        let err = parse_duration_ms(arg).unwrap_err();
+        assert!(err.contains("expected a duration"), "{arg:?}: {err}");
+    }
+}
+
+#[test]
+fn refuses_only_plans_longer_than_the_limit() {
+    assert!(check_max_duration(u64::MAX, None).is_ok());
+    assert!(check_max_duration(59_999, Some(60_000)).is_ok());
+    assert!(check_max_duration(60_000, Some(60_000)).is_ok());
+
+    let err = check_max_duration(60_001, Some(60_000)).unwrap_err();
+    assert!(
+        err.to_string().contains("longer than --max-duration"),
+        "{err}"
+    );
+    let err = check_max_duration(5_400_000, Some(2_700_000)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "refusing to play: the plan takes ~90.0 min, longer than --max-duration (45.0 min)"
+    );
+}
@@ -0,0 +1,57 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::layout::KeyboardLayout;
+use drafter::normalize::normalize_text;
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+#[test]
+fn replaces_untypeable_characters_and_reports_them() {
+    let layout = KeyboardLayout::us_qwerty().unwrap();
+    let text = "Wait\u{2014}what\u{2026}\nIt\u{2019}s\u{00A0}a \u{201E}test\u{201C} \u{2013} ok\u{200B}.\u{2014}\n";
+
+    let normalized = normalize_text(text, |c| layout.is_supported_char(c));
+
+    assert_eq!(
+        normalized.text,
+        "Wait--what...\nIt\u{2019}s a \"test\u{201C} - ok.--\n"
+    );
+
+    let summary: Vec<(char, usize, usize, usize)> = normalized
+        .substitutions
+        .iter()
+        .map(|s| (s.from, s.count, s.first_line, s.first_column))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ('\u{2014}', 2, 1, 5),
+            ('\u{2026}', 1, 1, 10),
+            ('\u{00A0}', 1, 2, 5),
+            ('\u{201E}', 1, 2, 8),
+            ('\u{2013}', 1, 2, 15),
+            ('\u{200B}', 1, 2, 19),
+        ]
+    );
+    assert_eq!(
+        normalized.substitutions[0].to_string(),
+        "replaced 2x U+2014 '—' with \"--\" (first at line 1, column 5)"
+    );
+
+    let plan = generate_plan(
+        &normalized.text,
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(3),
+    )
+    .unwrap();
+    let typed = simulate_typed_text(&plan).unwrap();
+    assert_eq!(typed, "Wait--what...\nIt's a \"test\" - ok.--\n");
+}
+
+#[test]
+fn keeps_characters_the_layout_can_type() {
+    let normalized = normalize_text("a\u{2014}b\u{2013}c", |c| c == '\u{2014}');
+    assert_eq!(normalized.text, "a\u{2014}b-c");
+    assert_eq!(normalized.substitutions.len(), 1);
+}
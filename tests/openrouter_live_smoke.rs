@@ -59,7 +59,7 @@ async fn request_two_results(
 
     let content = response
         .choices
-        .get(0)
+        .first()
         .and_then(|c| c.message.content.as_deref())
         .context("missing choices[0].message.content")?;
 
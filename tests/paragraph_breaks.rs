@@ -0,0 +1,89 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::model::{ParagraphBreaks, Plan};
+use drafter::planner::{generate_plan, split_paragraphs_with, PlannerConfig};
+use drafter::sim::{simulate_typed_text, verify_typed_text};
+
+const TEXT: &str =
+    "\nFirst paragraph,\nwrapped once.\n\n\n\nSecond paragraph.\n\nThird and last paragraph.\n\n";
+
+fn plan(error_rate_per_word: f64, paragraph_breaks: ParagraphBreaks, seed: u64) -> Plan {
+    let cfg = PlannerConfig::builder()
+        .error_rate_per_word(error_rate_per_word)
+        .paragraph_breaks(paragraph_breaks)
+        .build()
+        .unwrap();
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+#[test]
+fn breaks_between_paragraphs_are_normalized() {
+    assert_eq!(ParagraphBreaks::AsWritten.apply(TEXT), TEXT);
+    assert_eq!(
+        ParagraphBreaks::Single.apply(TEXT),
+        "\nFirst paragraph,\nwrapped once.\nSecond paragraph.\nThird and last paragraph.\n\n"
+    );
+    assert_eq!(
+        ParagraphBreaks::Double.apply(TEXT),
+        "\nFirst paragraph,\nwrapped once.\n\nSecond paragraph.\n\nThird and last paragraph.\n\n"
+    );
+    assert_eq!(ParagraphBreaks::Single.apply("\n\n\n"), "\n\n\n");
+    assert_eq!(ParagraphBreaks::Double.apply("no breaks"), "no breaks");
+}
+
+#[test]
+fn plans_type_the_normalized_breaks_and_verify_against_the_draft() {
+    for breaks in [
+        ParagraphBreaks::AsWritten,
+        ParagraphBreaks::Single,
+        ParagraphBreaks::Double,
+    ] {
+        for (error_rate, seed) in [(0.0, 1), (0.4, 2)] {
+            let plan = plan(error_rate, breaks, seed);
+            assert_eq!(plan.config.paragraph_breaks, breaks);
+            assert_eq!(simulate_typed_text(&plan).unwrap(), breaks.apply(TEXT));
+            verify_typed_text(&plan, TEXT).unwrap();
+        }
+    }
+}
+
+#[test]
+fn single_breaks_make_every_line_a_paragraph() {
+    let plan = plan(0.3, ParagraphBreaks::Single, 3);
+    let typed = simulate_typed_text(&plan).unwrap();
+    let annotated: Vec<String> = plan
+        .annotations
+        .iter()
+        .map(|annotation| {
+            let range = annotation.source();
+            typed.chars().skip(range.start).take(range.len()).collect()
+        })
+        .collect();
+    assert_eq!(
+        annotated,
+        [
+            "First paragraph,",
+            "wrapped once.",
+            "Second paragraph.",
+            "Third and last paragraph."
+        ]
+    );
+    assert_eq!(
+        annotated,
+        split_paragraphs_with(TEXT, ParagraphBreaks::Single)
+    );
+}
+
+#[test]
+fn the_policy_is_saved_only_when_not_as_written() {
+    let as_written = plan(0.0, ParagraphBreaks::AsWritten, 4);
+    let json = serde_json::to_string(&as_written).unwrap();
+    assert!(!json.contains("paragraph_breaks"));
+
+    let double = plan(0.0, ParagraphBreaks::Double, 4);
+    let json = serde_json::to_string(&double).unwrap();
+    assert!(json.contains("\"paragraph_breaks\":\"double\""));
+    let read: Plan = serde_json::from_str(&json).unwrap();
+    assert_eq!(read.config.paragraph_breaks, ParagraphBreaks::Double);
+}
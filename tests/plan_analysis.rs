@@ -0,0 +1,183 @@
+use drafter::analysis::{analyze, AnalysisOptions, DELAY_BUCKET_BOUNDS_MS};
+use drafter::keyboard::{keystroke_for_output_char, KEY_BACKSPACE, KEY_LEFT, KEY_RIGHT};
+use drafter::model::{Action, KeyState, Plan, PlanConfig};
+use drafter::planner::{generate_plan, split_paragraphs, PlannerConfig};
+use drafter::sim;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const TEXT: &str = "The quick brown fox jumps over the lazy dog.\n\nA second, shorter paragraph.";
+
+fn press(keycode: u32) -> Action {
+    Action::Key {
+        keycode,
+        state: KeyState::Pressed,
+    }
+}
+
+/// Presses for `text`, `ms` apart.
+fn type_text(text: &str, ms: u64) -> Vec<Action> {
+    text.chars()
+        .flat_map(|c| {
+            let stroke = keystroke_for_output_char(c).expect("test text must be typable");
+            [Action::Wait { ms }, press(stroke.keycode)]
+        })
+        .collect()
+}
+
+fn repeat(keycode: u32, times: usize) -> Vec<Action> {
+    (0..times)
+        .flat_map(|_| [Action::Wait { ms: 20 }, press(keycode)])
+        .collect()
+}
+
+fn dummy_plan(actions: Vec<Action>) -> Plan {
+    Plan {
+        version: 1,
+        config: PlanConfig {
+            layout: "us".to_string(),
+            keymap_format: 1,
+            keymap: String::new(),
+            wpm_target: 0.0,
+            word_nav_profile: None,
+            paragraph_breaks: Default::default(),
+            strip_trailing_whitespace: false,
+            strip_final_newline: false,
+        },
+        actions,
+        annotations: Vec::new(),
+    }
+}
+
+#[test]
+fn error_free_plan_has_no_corrections() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        ..Default::default()
+    };
+    let plan = generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(1)).unwrap();
+    let analysis = analyze(&plan, &AnalysisOptions::default()).unwrap();
+
+    assert_eq!(analysis.total_ms, sim::stats(&plan).total_wait_ms);
+    assert_eq!(analysis.typed_chars, TEXT.chars().count());
+    assert_eq!(analysis.deleted_chars, 0);
+    assert_eq!(analysis.corrections, Default::default());
+    assert_eq!(analysis.navigation, Default::default());
+
+    let chars: Vec<usize> = analysis.paragraphs.iter().map(|p| p.chars).collect();
+    let expected: Vec<usize> = split_paragraphs(TEXT)
+        .iter()
+        .map(|p| p.chars().count())
+        .collect();
+    assert_eq!(chars, expected);
+    let paragraph_ms: u64 = analysis.paragraphs.iter().map(|p| p.ms).sum();
+    assert!(paragraph_ms <= analysis.total_ms);
+    assert!(analysis.paragraphs.iter().all(|p| p.ms > 0 && p.wpm > 0.0));
+
+    let typed: usize = analysis.wpm_windows.iter().map(|w| w.typed_chars).sum();
+    assert_eq!(typed, analysis.typed_chars);
+    assert_eq!(
+        analysis.delay_histogram.len(),
+        DELAY_BUCKET_BOUNDS_MS.len() + 1
+    );
+    let gaps: usize = analysis.delay_histogram.iter().map(|b| b.count).sum();
+    assert_eq!(gaps, analysis.typed_chars - 1);
+}
+
+#[test]
+fn classifies_immediate_and_delayed_corrections() {
+    let mut actions = type_text("teh", 100);
+    actions.extend(repeat(KEY_BACKSPACE, 3));
+    actions.extend(type_text("the cay sat.", 100));
+    // Back to the end of "cay", and fix it.
+    actions.extend(repeat(KEY_LEFT, 5));
+    actions.extend(repeat(KEY_BACKSPACE, 3));
+    actions.extend(type_text("cat", 100));
+    actions.extend(repeat(KEY_RIGHT, 5));
+    actions.push(Action::Wait { ms: 1500 });
+    actions.extend(type_text(" done.", 100));
+
+    let plan = dummy_plan(actions);
+    assert_eq!(
+        sim::simulate_typed_text(&plan).unwrap(),
+        "the cat sat. done."
+    );
+    let analysis = analyze(&plan, &AnalysisOptions::default()).unwrap();
+
+    let corrections = &analysis.corrections;
+    assert_eq!((corrections.immediate, corrections.delayed), (1, 1));
+    assert_eq!(corrections.transposition, 1);
+    assert_eq!(corrections.substitution, 1);
+    assert_eq!(
+        corrections.word + corrections.phrase + corrections.spacing,
+        0
+    );
+    assert_eq!(analysis.deleted_chars, 6);
+
+    assert_eq!(analysis.navigation.arrow_presses, 10);
+    assert_eq!(analysis.navigation.chars_moved, 10);
+    assert_eq!(analysis.navigation.word_jumps, 0);
+    assert_eq!(analysis.navigation.line_jumps, 0);
+
+    let longest = &analysis.longest_pauses[0];
+    assert_eq!(longest.ms, 1600);
+    assert_eq!(longest.context, "the cat sat.");
+}
+
+#[test]
+fn reports_speed_per_window() {
+    // 100 characters, one every 120 ms: 12 s at 100 WPM.
+    let text = "word ".repeat(20);
+    let plan = dummy_plan(type_text(&text, 120));
+    let options = AnalysisOptions {
+        wpm_window_ms: 6_000,
+        longest_pauses: 0,
+        ..Default::default()
+    };
+    let analysis = analyze(&plan, &options).unwrap();
+
+    assert_eq!(analysis.total_ms, 12_000);
+    assert!(analysis.longest_pauses.is_empty());
+    let windows: Vec<(u64, u64, usize)> = analysis
+        .wpm_windows
+        .iter()
+        .map(|w| (w.start_ms, w.end_ms, w.typed_chars))
+        .collect();
+    assert_eq!(windows, [(0, 6_000, 49), (6_000, 12_000, 51)]);
+    assert_eq!(analysis.paragraphs.len(), 1);
+    assert!((analysis.paragraphs[0].wpm - 100.0).abs() < 1e-9);
+}
+
+#[test]
+fn reports_rolling_speed_and_pauses_over_a_threshold() {
+    let mut actions = type_text("one ", 120);
+    actions.push(Action::Wait { ms: 1_500 });
+    actions.extend(type_text("two\nthree", 120));
+    let plan = dummy_plan(actions);
+    let options = AnalysisOptions {
+        wpm_window_ms: 2_000,
+        rolling_wpm_step_ms: Some(1_000),
+        pause_threshold_ms: Some(1_000),
+        ..Default::default()
+    };
+    let analysis = analyze(&plan, &options).unwrap();
+
+    assert_eq!(analysis.total_ms, 3_060);
+    let samples: Vec<(u64, usize)> = analysis
+        .rolling_wpm
+        .iter()
+        .map(|sample| (sample.at_ms, sample.typed_chars))
+        .collect();
+    assert_eq!(samples, [(1_000, 4), (2_000, 4), (3_000, 8), (3_060, 9)]);
+    assert!((analysis.rolling_wpm[0].wpm - 48.0).abs() < 1e-9);
+
+    assert_eq!(analysis.pauses.len(), 1);
+    let pause = &analysis.pauses[0];
+    assert_eq!((pause.at_ms, pause.ms), (480, 1_620));
+    assert_eq!((pause.cursor.line, pause.cursor.column), (1, 5));
+    assert_eq!(pause.context, "one ");
+    assert_eq!(analysis.longest_pauses[0].cursor, pause.cursor);
+
+    let default = analyze(&plan, &AnalysisOptions::default()).unwrap();
+    assert!(default.rolling_wpm.is_empty() && default.pauses.is_empty());
+}
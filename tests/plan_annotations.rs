@@ -0,0 +1,133 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::model::{Action, AnnotationKind, Plan};
+use drafter::plan_edit::{anonymize, concat, scale_waits, separator, trim, PlanCut};
+use drafter::planner::{generate_plan, split_paragraphs, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "First paragraph, with a few words.\n\nSecond one follows it.\nStill the second.\n\n\nThird and last paragraph.\n";
+
+fn plan(error_rate_per_word: f64, seed: u64) -> Plan {
+    let cfg = PlannerConfig {
+        error_rate_per_word,
+        ..Default::default()
+    };
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+fn source(text: &str, range: std::ops::Range<usize>) -> String {
+    text.chars().skip(range.start).take(range.len()).collect()
+}
+
+#[test]
+fn plans_annotate_every_paragraph_in_order() {
+    for (error_rate, seed) in [(0.0, 1), (0.3, 2), (0.3, 3)] {
+        let plan = plan(error_rate, seed);
+        let paragraphs: Vec<String> = plan
+            .annotations
+            .iter()
+            .map(|annotation| {
+                assert_eq!(annotation.kind, AnnotationKind::Paragraph);
+                source(TEXT, annotation.source())
+            })
+            .collect();
+        assert_eq!(paragraphs, split_paragraphs(TEXT), "seed {seed}");
+
+        for pair in plan.annotations.windows(2) {
+            assert!(pair[0].action_end <= pair[1].action_start);
+            assert!(pair[0].source_end < pair[1].source_start);
+        }
+        let last = plan.annotations.last().unwrap();
+        assert!(last.action_end <= plan.actions.len());
+        assert!(plan
+            .annotations_at(last.action_start)
+            .eq(std::iter::once(last)));
+    }
+}
+
+#[test]
+fn paragraphs_are_typed_by_their_actions() {
+    let plan = plan(0.0, 4);
+    for annotation in &plan.annotations {
+        let head = trim(&plan, None, Some(PlanCut::Action(annotation.action_end))).unwrap();
+        assert_eq!(
+            simulate_typed_text(&head).unwrap(),
+            source(TEXT, 0..annotation.source_end)
+        );
+    }
+}
+
+#[test]
+fn trimming_resumes_at_a_paragraph() {
+    let plan = plan(0.0, 5);
+    let second = plan.annotations[1].clone();
+    let rest = trim(&plan, Some(PlanCut::Action(second.action_start)), None).unwrap();
+    assert_eq!(
+        simulate_typed_text(&rest).unwrap(),
+        source(TEXT, second.source_start..TEXT.chars().count())
+    );
+    assert_eq!(rest.annotations.len(), 2);
+    for (kept, original) in rest.annotations.iter().zip(&plan.annotations[1..]) {
+        assert_eq!(kept.source(), original.source());
+        assert_eq!(
+            rest.actions[kept.actions()],
+            plan.actions[original.actions()]
+        );
+    }
+
+    let head = trim(&plan, None, Some(PlanCut::Action(second.action_end - 1))).unwrap();
+    assert_eq!(head.annotations, plan.annotations[..1]);
+}
+
+#[test]
+fn scaling_waits_keeps_annotations_on_their_actions() {
+    let plan = plan(0.3, 6);
+    // Drops most waits.
+    let scaled = scale_waits(&plan, 0.001).unwrap();
+    assert!(scaled.actions.len() < plan.actions.len());
+    let keys = |actions: &[Action]| -> Vec<Action> {
+        actions
+            .iter()
+            .filter(|action| !matches!(action, Action::Wait { .. }))
+            .cloned()
+            .collect()
+    };
+    for (scaled_annotation, annotation) in scaled.annotations.iter().zip(&plan.annotations) {
+        assert_eq!(scaled_annotation.source(), annotation.source());
+        assert_eq!(
+            keys(&scaled.actions[scaled_annotation.actions()]),
+            keys(&plan.actions[annotation.actions()])
+        );
+    }
+    assert_eq!(scaled.annotations.len(), plan.annotations.len());
+}
+
+#[test]
+fn concatenated_annotations_refer_to_the_joined_text() {
+    let first = plan(0.3, 7);
+    let second = plan(0.3, 8);
+    let joined = concat(&[first.clone(), separator(&first, 1, 0), second.clone()]).unwrap();
+    let text = simulate_typed_text(&joined).unwrap();
+    assert_eq!(text, format!("{TEXT}\n{TEXT}"));
+
+    assert_eq!(joined.annotations.len(), 6);
+    for annotation in &joined.annotations {
+        assert!(split_paragraphs(TEXT).contains(&source(&text, annotation.source())));
+    }
+    let shifted = &joined.annotations[3];
+    assert_eq!(
+        shifted.action_start,
+        second.annotations[0].action_start + joined.actions.len() - second.actions.len()
+    );
+}
+
+#[test]
+fn anonymizing_drops_annotations_unless_whitespace_is_kept() {
+    let plan = plan(0.3, 9);
+    assert!(anonymize(&plan, false).unwrap().annotations.is_empty());
+    assert_eq!(
+        anonymize(&plan, true).unwrap().annotations,
+        plan.annotations
+    );
+}
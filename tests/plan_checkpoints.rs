@@ -0,0 +1,115 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::model::{Action, Plan};
+use drafter::plan_edit::{anonymize, concat, cut_index, separator, trim, verify_neutral, PlanCut};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "Short opening line. Then a second sentence follows!\n\nA NEW PARAGRAPH, shouted. Is it over? Almost.\nThe end.";
+
+fn plan(error_rate_per_word: f64, seed: u64) -> Plan {
+    let cfg = PlannerConfig {
+        error_rate_per_word,
+        caps_lock_rate: 1.0,
+        ..Default::default()
+    };
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+/// `(action index, chars)` of each checkpoint.
+fn checkpoints(plan: &Plan) -> Vec<(usize, usize)> {
+    plan.actions
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, action)| match action {
+            Action::Checkpoint { chars } => Some((idx, *chars)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn prefix(chars: usize) -> String {
+    TEXT.chars().take(chars).collect()
+}
+
+#[test]
+fn checkpoints_hold_a_prefix_of_the_text_with_a_neutral_keyboard() {
+    for (error_rate, seed) in [(0.0, 1), (0.3, 2), (0.6, 3), (0.6, 4)] {
+        let plan = plan(error_rate, seed);
+        let checkpoints = checkpoints(&plan);
+        // Pending typos leave out the boundaries they span; the end always has one.
+        assert!(checkpoints.len() >= if error_rate == 0.0 { 5 } else { 1 });
+        assert_eq!(
+            checkpoints.last().unwrap().1,
+            TEXT.chars().count(),
+            "seed {seed}"
+        );
+        assert!(checkpoints.windows(2).all(|pair| pair[0].1 < pair[1].1));
+
+        for (idx, chars) in checkpoints {
+            let head = Plan {
+                actions: plan.actions[..idx].to_vec(),
+                ..plan.clone()
+            };
+            verify_neutral(&head).unwrap_or_else(|err| panic!("seed {seed}, action {idx}: {err}"));
+            assert_eq!(simulate_typed_text(&head).unwrap(), prefix(chars));
+        }
+    }
+}
+
+#[test]
+fn resuming_at_a_checkpoint_types_the_rest_of_the_text() {
+    for seed in [5, 6, 7] {
+        let plan = plan(0.4, seed);
+        let checkpoints = checkpoints(&plan);
+        let (idx, chars) = checkpoints[checkpoints.len() / 2];
+
+        let cut = PlanCut::Checkpoint(chars + 3);
+        assert_eq!(cut_index(&plan, cut), idx);
+        let rest = trim(&plan, Some(cut), None).unwrap();
+        // Only the neutral modifiers every cut sends go before the checkpoint.
+        assert!(rest.actions.ends_with(&plan.actions[idx..]), "seed {seed}");
+        assert!(
+            rest.actions[..rest.actions.len() - (plan.actions.len() - idx)]
+                .iter()
+                .all(|action| matches!(action, Action::Modifiers { .. }))
+        );
+        assert_eq!(
+            simulate_typed_text(&rest).unwrap(),
+            TEXT.chars().skip(chars).collect::<String>()
+        );
+    }
+    assert_eq!(cut_index(&plan(0.4, 5), PlanCut::Checkpoint(0)), 0);
+}
+
+#[test]
+fn concatenated_checkpoints_count_the_chars_typed_before() {
+    let first = plan(0.3, 8);
+    let second = plan(0.3, 9);
+    let joined = concat(&[first.clone(), separator(&first, 2, 0), second.clone()]).unwrap();
+    let offset = TEXT.chars().count() + 2;
+
+    let joined_checkpoints = checkpoints(&joined);
+    let (first_checkpoints, second_checkpoints) = (checkpoints(&first), checkpoints(&second));
+    assert_eq!(
+        joined_checkpoints.len(),
+        first_checkpoints.len() + second_checkpoints.len()
+    );
+    for ((_, joined), (_, original)) in joined_checkpoints[first_checkpoints.len()..]
+        .iter()
+        .zip(&second_checkpoints)
+    {
+        assert_eq!(*joined, original + offset);
+    }
+}
+
+#[test]
+fn anonymizing_drops_checkpoints_unless_whitespace_is_kept() {
+    let plan = plan(0.3, 10);
+    assert!(checkpoints(&anonymize(&plan, false).unwrap()).is_empty());
+    assert_eq!(
+        checkpoints(&anonymize(&plan, true).unwrap()),
+        checkpoints(&plan)
+    );
+}
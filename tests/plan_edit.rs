@@ -0,0 +1,263 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::{KEY_A, KEY_CAPSLOCK, KEY_LEFTSHIFT, KEY_RIGHTSHIFT};
+use drafter::model::{Action, KeyState, Plan};
+use drafter::plan_edit::{
+    anonymize, concat, cut_index, optimize, scale_waits, separator, trim, verify_neutral, PlanCut,
+    PLACEHOLDER_KEYCODES,
+};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::{self, simulate_typed_text};
+
+const TEXT: &str = "IMPORTANT NOTICE: READ THIS FIRST!\nThe rest is Normal text, mostly.\n";
+
+fn plan() -> Plan {
+    let cfg = PlannerConfig {
+        caps_lock_rate: 1.0,
+        ..Default::default()
+    };
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(5)).unwrap()
+}
+
+fn position(plan: &Plan, keycodes: &[u32], state: KeyState) -> usize {
+    plan.actions
+        .iter()
+        .position(|action| {
+            matches!(action, Action::Key { keycode, state: s } if keycodes.contains(keycode) && *s == state)
+        })
+        .expect("plan presses the key")
+}
+
+/// Splits `plan` before action `idx` and joins the halves again.
+fn split_and_join(plan: &Plan, idx: usize) -> Plan {
+    let head = trim(plan, None, Some(PlanCut::Action(idx))).unwrap();
+    let tail = trim(plan, Some(PlanCut::Action(idx)), None).unwrap();
+    verify_neutral(&head).unwrap();
+    verify_neutral(&tail).unwrap();
+    concat(&[head, tail]).unwrap()
+}
+
+#[test]
+fn splitting_while_keys_are_held_keeps_the_text() {
+    let plan = plan();
+    let caps_press = position(&plan, &[KEY_CAPSLOCK], KeyState::Pressed);
+    let shift_press = position(&plan, &[KEY_LEFTSHIFT, KEY_RIGHTSHIFT], KeyState::Pressed);
+
+    // CapsLock held, CapsLock on, and Shift held.
+    for idx in [caps_press + 1, caps_press + 3, shift_press + 1] {
+        let joined = split_and_join(&plan, idx);
+        verify_neutral(&joined).unwrap();
+        assert_eq!(
+            simulate_typed_text(&joined).unwrap(),
+            TEXT,
+            "split at {idx}"
+        );
+    }
+}
+
+#[test]
+fn cuts_by_time_before_the_first_action_starting_then() {
+    let plan = plan();
+    assert_eq!(cut_index(&plan, PlanCut::Ms(0)), 0);
+    assert_eq!(cut_index(&plan, PlanCut::Ms(u64::MAX)), plan.actions.len());
+
+    let total_ms = sim::stats(&plan).total_wait_ms;
+    let idx = cut_index(&plan, PlanCut::Ms(total_ms / 2));
+    let head = trim(&plan, None, Some(PlanCut::Action(idx))).unwrap();
+    assert!(sim::stats(&head).total_wait_ms >= total_ms / 2);
+    assert_eq!(
+        trim(&plan, None, Some(PlanCut::Ms(total_ms / 2)))
+            .unwrap()
+            .actions
+            .len(),
+        head.actions.len()
+    );
+
+    assert!(trim(&plan, Some(PlanCut::Action(10)), Some(PlanCut::Action(10))).is_err());
+}
+
+#[test]
+fn scales_waits_and_the_wpm_target() {
+    let plan = plan();
+    let faster = scale_waits(&plan, 0.5).unwrap();
+    let (before, after) = (
+        sim::stats(&plan).total_wait_ms as f64,
+        sim::stats(&faster).total_wait_ms as f64,
+    );
+    assert!((after - before / 2.0).abs() <= plan.actions.len() as f64);
+    assert!((faster.config.wpm_target - plan.config.wpm_target * 2.0).abs() < 1e-9);
+    assert_eq!(simulate_typed_text(&faster).unwrap(), TEXT);
+
+    assert!(scale_waits(&plan, 0.0).is_err());
+    assert!(scale_waits(&plan, f64::NAN).is_err());
+}
+
+/// Each key event of `plan` with its start time and the modifier state sent before it.
+fn key_timeline(plan: &Plan) -> Vec<(u64, Action, Option<Action>)> {
+    let (mut now_ms, mut modifiers) = (0, None);
+    let mut timeline = Vec::new();
+    for action in plan.actions.iter().flat_map(Action::expand) {
+        match action {
+            Action::Wait { ms } => now_ms += ms,
+            Action::Modifiers { .. } => modifiers = Some(action),
+            Action::Key { .. } => timeline.push((now_ms, action, modifiers.clone())),
+            Action::Checkpoint { .. } | Action::KeyRepeat { .. } => {}
+        }
+    }
+    timeline
+}
+
+#[test]
+fn optimizing_drops_redundant_actions_but_plays_the_same_keys() {
+    let mut plan = plan();
+    // Redundant actions as plans edited by hand or put together may have them.
+    let modifiers = plan
+        .actions
+        .iter()
+        .position(|action| matches!(action, Action::Modifiers { .. }))
+        .unwrap();
+    let repeated = plan.actions[modifiers].clone();
+    plan.actions.insert(modifiers + 1, repeated.clone());
+    plan.actions.insert(modifiers + 1, Action::Wait { ms: 7 });
+    plan.actions.insert(modifiers + 1, Action::Wait { ms: 0 });
+    plan.actions.insert(
+        modifiers,
+        Action::Modifiers {
+            mods_depressed: 1,
+            mods_latched: 0,
+            mods_locked: 0,
+            group: 0,
+        },
+    );
+
+    let optimized = optimize(&plan);
+    assert!(optimized.actions.len() <= plan.actions.len() - 4);
+    assert_eq!(key_timeline(&optimized), key_timeline(&plan));
+    assert_eq!(
+        sim::stats(&optimized).total_wait_ms,
+        sim::stats(&plan).total_wait_ms
+    );
+    assert_eq!(simulate_typed_text(&optimized).unwrap(), TEXT);
+    verify_neutral(&optimized).unwrap();
+    assert!(optimized.actions.windows(2).all(|pair| !matches!(
+        pair,
+        [Action::Wait { .. }, Action::Wait { .. }]
+            | [Action::Modifiers { .. }, Action::Modifiers { .. }]
+    )));
+    assert_eq!(optimize(&optimized), optimized);
+}
+
+#[test]
+fn optimizing_keeps_checkpoints_and_annotations_on_their_actions() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.3,
+        ..Default::default()
+    };
+    let text = "One paragraph. Two sentences.\n\nAnother paragraph, with a typo or two.";
+    let plan = generate_plan(text, cfg, &mut StdRng::seed_from_u64(9)).unwrap();
+    let optimized = optimize(&plan);
+
+    let checkpoints = |plan: &Plan| -> Vec<Action> {
+        plan.actions
+            .iter()
+            .filter(|action| matches!(action, Action::Checkpoint { .. }))
+            .cloned()
+            .collect()
+    };
+    assert_eq!(checkpoints(&optimized), checkpoints(&plan));
+    assert_eq!(optimized.annotations.len(), plan.annotations.len());
+    for (kept, original) in optimized.annotations.iter().zip(&plan.annotations) {
+        assert_eq!(kept.source(), original.source());
+        let keys = |actions: &[Action]| -> Vec<Action> {
+            actions
+                .iter()
+                .filter(|action| matches!(action, Action::Key { .. }))
+                .cloned()
+                .collect()
+        };
+        assert_eq!(
+            keys(&optimized.actions[kept.actions()]),
+            keys(&plan.actions[original.actions()])
+        );
+    }
+}
+
+#[test]
+fn rejects_unbalanced_and_mismatched_plans() {
+    let plan = plan();
+    let mut held = plan.clone();
+    held.actions.push(Action::Key {
+        keycode: KEY_A,
+        state: KeyState::Pressed,
+    });
+    assert!(verify_neutral(&held)
+        .unwrap_err()
+        .to_string()
+        .contains("held"));
+    assert!(concat(&[plan.clone(), held]).is_err());
+
+    let mut other_layout = plan.clone();
+    other_layout.config.layout = "de".to_string();
+    let err = concat(&[plan, other_layout]).unwrap_err();
+    assert!(err.to_string().contains("different keymap"), "{err}");
+}
+
+#[test]
+fn anonymizing_keeps_timing_and_structure_but_not_text() {
+    let plan = plan();
+    let anonymized = anonymize(&plan, true).unwrap();
+    verify_neutral(&anonymized).unwrap();
+    assert_eq!(
+        sim::stats(&anonymized).total_wait_ms,
+        sim::stats(&plan).total_wait_ms
+    );
+    assert_eq!(anonymized.actions.len(), plan.actions.len());
+    for (before, after) in plan.actions.iter().zip(&anonymized.actions) {
+        match (before, after) {
+            (
+                Action::Key { state, .. },
+                Action::Key {
+                    keycode,
+                    state: after_state,
+                },
+            ) if PLACEHOLDER_KEYCODES.contains(keycode) => assert_eq!(state, after_state),
+            _ => assert_eq!(format!("{before:?}"), format!("{after:?}")),
+        }
+    }
+
+    let text = simulate_typed_text(&anonymized).unwrap();
+    let masked: String = TEXT
+        .chars()
+        .map(|c| if c.is_whitespace() { c } else { 'x' })
+        .collect();
+    assert_eq!(text.to_lowercase(), masked);
+
+    let text = simulate_typed_text(&anonymize(&plan, false).unwrap()).unwrap();
+    assert!(text.chars().all(|c| c.eq_ignore_ascii_case(&'x')), "{text}");
+}
+
+#[test]
+fn separators_join_texts_with_enters_and_a_pause() {
+    let first = plan();
+    let second = generate_plan(
+        "Second part.",
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(6),
+    )
+    .unwrap();
+    let gap = separator(&first, 1, 30_000);
+    verify_neutral(&gap).unwrap();
+    assert_eq!(sim::stats(&gap).total_wait_ms, 30_000 + 60 + 350);
+
+    let joined = concat(&[first.clone(), gap, second.clone()]).unwrap();
+    assert_eq!(
+        simulate_typed_text(&joined).unwrap(),
+        format!("{TEXT}\nSecond part.")
+    );
+    assert_eq!(
+        sim::stats(&joined).total_wait_ms,
+        sim::stats(&first).total_wait_ms + 30_410 + sim::stats(&second).total_wait_ms
+    );
+    assert!(separator(&first, 0, 0).actions.is_empty());
+}
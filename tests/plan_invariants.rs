@@ -0,0 +1,70 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::{KEY_A, KEY_CAPSLOCK, KEY_LEFTSHIFT};
+use drafter::model::{Action, KeyState, Plan};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::testing::{
+    assert_plan_invariants, check_balanced_keys, check_no_stuck_modifiers, check_plan_invariants,
+    check_waits_within,
+};
+
+const TEXT: &str = "Dear team,\n\nThe QUARTERLY REPORT is ready; please review it by Friday.\n";
+
+fn plan(seed: u64) -> Plan {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.4,
+        caps_lock_rate: 0.5,
+        ..Default::default()
+    };
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+fn key(keycode: u32, state: KeyState) -> Action {
+    Action::Key { keycode, state }
+}
+
+#[test]
+fn generated_plans_keep_the_invariants() {
+    for seed in 0..8 {
+        let plan = plan(seed);
+        assert_plan_invariants(&plan, TEXT);
+        check_waits_within(&plan, 1..=u64::MAX).unwrap();
+    }
+}
+
+#[test]
+fn reports_the_first_violation() {
+    let plan = plan(1);
+
+    let mut held = plan.clone();
+    held.actions.push(key(KEY_A, KeyState::Pressed));
+    let err = check_balanced_keys(&held).unwrap_err();
+    assert!(err.to_string().contains("[30] held"), "{err}");
+    check_no_stuck_modifiers(&held).unwrap();
+
+    let mut released = plan.clone();
+    released.actions.push(key(KEY_A, KeyState::Released));
+    let err = check_balanced_keys(&released).unwrap_err();
+    assert!(err.to_string().contains("released while not held"), "{err}");
+
+    let mut shift = plan.clone();
+    shift.actions.push(key(KEY_LEFTSHIFT, KeyState::Pressed));
+    check_balanced_keys(&shift).unwrap();
+    let err = check_no_stuck_modifiers(&shift).unwrap_err();
+    assert!(err.to_string().contains("modifier keycodes"), "{err}");
+
+    let mut caps = plan.clone();
+    caps.actions.extend([
+        key(KEY_CAPSLOCK, KeyState::Pressed),
+        key(KEY_CAPSLOCK, KeyState::Released),
+    ]);
+    let err = check_no_stuck_modifiers(&caps).unwrap_err();
+    assert!(err.to_string().contains("CapsLock on"), "{err}");
+
+    let err = check_plan_invariants(&plan, "Dear team,\n").unwrap_err();
+    assert!(err.to_string().contains("after the end"), "{err}");
+
+    let err = check_waits_within(&plan, 0..=10).unwrap_err();
+    assert!(err.to_string().contains("outside 0..=10 ms"), "{err}");
+}
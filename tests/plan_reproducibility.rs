@@ -0,0 +1,95 @@
+use drafter::llm::{HumanMistake, MistakeKind, PhraseAlternative};
+use drafter::model::Action;
+use drafter::plan::{canonical_hash, first_difference};
+use drafter::planner::{generate_plan, generate_plan_with_human_mistakes, PlannerConfig};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const TEXT: &str = "Plans are generated from a seed. The SAME SEED must give the same plan,\n\
+every time, with typos, corrections and all.";
+
+fn plans_for_seed(seed: u64) -> [drafter::model::Plan; 2] {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.2,
+        ..Default::default()
+    };
+    let alternatives = vec![vec![PhraseAlternative {
+        original: "with typos".to_string(),
+        alternative: "including typos".to_string(),
+        revise: None,
+    }]];
+    let mistakes = vec![vec![HumanMistake {
+        original: "every".to_string(),
+        mistake: "evry".to_string(),
+        kind: MistakeKind::Misspelling,
+    }]];
+    [0, 1].map(|_| {
+        generate_plan_with_human_mistakes(
+            TEXT,
+            cfg.clone(),
+            &alternatives,
+            &mistakes,
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .unwrap()
+    })
+}
+
+#[test]
+fn same_seed_gives_the_same_plan() {
+    for seed in 0..10 {
+        let [first, second] = plans_for_seed(seed);
+        assert_eq!(first_difference(&first, &second), None, "seed {seed}");
+        assert_eq!(
+            serde_json::to_string_pretty(&first).unwrap(),
+            serde_json::to_string_pretty(&second).unwrap()
+        );
+    }
+}
+
+#[test]
+fn hash_ignores_formatting_but_not_contents() {
+    let plan = generate_plan(
+        TEXT,
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .unwrap();
+    let hash = canonical_hash(&plan).unwrap();
+    assert_eq!(hash.len(), 64);
+
+    let pretty = serde_json::to_string_pretty(&plan).unwrap();
+    let reloaded = drafter::plan::load(&pretty).unwrap();
+    assert_eq!(canonical_hash(&reloaded).unwrap(), hash);
+
+    let mut slower = plan.clone();
+    let idx = slower
+        .actions
+        .iter()
+        .position(|action| matches!(action, Action::Wait { .. }))
+        .unwrap();
+    slower.actions[idx] = Action::Wait { ms: 10_000 };
+    assert_ne!(canonical_hash(&slower).unwrap(), hash);
+    let difference = first_difference(&plan, &slower).unwrap();
+    assert!(
+        difference.starts_with(&format!("action {idx} ")),
+        "{difference}"
+    );
+
+    let other_seed = generate_plan(
+        TEXT,
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(2),
+    )
+    .unwrap();
+    assert_eq!(
+        first_difference(&plan, &other_seed).as_deref(),
+        Some("different config.wpm_target")
+    );
+
+    let mut shorter = plan.clone();
+    shorter.actions.pop();
+    assert!(first_difference(&plan, &shorter)
+        .unwrap()
+        .contains("actions instead of"));
+}
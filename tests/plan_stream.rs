@@ -0,0 +1,116 @@
+use std::io::Cursor;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::model::{Action, Plan};
+use drafter::plan::{read, write_stream, PlanInput, PlanStream, PLAN_VERSION};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim;
+
+fn plan() -> Plan {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.3,
+        ..Default::default()
+    };
+    generate_plan(
+        "A plan read one line at a time.\n\nIts second paragraph.",
+        cfg,
+        &mut StdRng::seed_from_u64(3),
+    )
+    .unwrap()
+}
+
+fn stream_bytes(plan: &Plan) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_stream(plan, &mut out).unwrap();
+    out
+}
+
+fn open(bytes: &[u8]) -> PlanStream<Cursor<&[u8]>> {
+    match read(Cursor::new(bytes)).unwrap() {
+        PlanInput::Stream(stream) => stream,
+        PlanInput::Plan(_) => panic!("read a whole plan instead of a stream"),
+    }
+}
+
+#[test]
+fn streams_hold_a_header_and_one_action_per_line() {
+    let plan = plan();
+    let bytes = stream_bytes(&plan);
+    let text = String::from_utf8(bytes.clone()).unwrap();
+    assert_eq!(text.lines().count(), plan.actions.len() + 1);
+
+    let stream = open(&bytes);
+    assert_eq!(stream.header.version, PLAN_VERSION);
+    assert_eq!(stream.header.config, plan.config);
+    assert_eq!(stream.header.action_count, plan.actions.len());
+    assert_eq!(stream.header.total_wait_ms, sim::stats(&plan).total_wait_ms);
+    assert_eq!(stream.header.annotations, plan.annotations);
+    let actions: Vec<Action> = stream.actions.collect::<Result<_, _>>().unwrap();
+    assert_eq!(actions, plan.actions);
+}
+
+#[test]
+fn whole_plans_are_read_as_before() {
+    let plan = plan();
+    for json in [
+        serde_json::to_string_pretty(&plan).unwrap(),
+        serde_json::to_string(&plan).unwrap(),
+    ] {
+        match read(Cursor::new(json.as_bytes())).unwrap() {
+            PlanInput::Plan(read) => assert_eq!(read, plan),
+            PlanInput::Stream(_) => panic!("read a whole plan as a stream"),
+        }
+    }
+}
+
+#[test]
+fn truncated_or_overlong_streams_fail_at_the_end() {
+    let plan = plan();
+    let bytes = stream_bytes(&plan);
+    let text = String::from_utf8(bytes).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+
+    let truncated = lines[..lines.len() - 2].join("\n");
+    let results: Vec<_> = open(truncated.as_bytes()).actions.collect();
+    assert_eq!(results.len(), plan.actions.len() - 1);
+    let err = results.last().unwrap().as_ref().unwrap_err();
+    assert!(format!("{err:#}").contains("2 actions missing"), "{err:#}");
+
+    let overlong = format!("{text}{}\n", lines[1]);
+    let results: Vec<_> = open(overlong.as_bytes()).actions.collect();
+    assert_eq!(results.len(), plan.actions.len() + 1);
+    assert!(results.last().unwrap().is_err());
+}
+
+#[test]
+fn a_bad_action_line_is_reported_by_number() {
+    let plan = plan();
+    let text = String::from_utf8(stream_bytes(&plan)).unwrap();
+    let mut lines: Vec<&str> = text.lines().collect();
+    lines[4] = "{\"type\":\"teleport\"}";
+
+    let results: Vec<_> = open(lines.join("\n").as_bytes()).actions.collect();
+    assert_eq!(results.len(), 4);
+    let err = results[3].as_ref().unwrap_err();
+    assert!(format!("{err:#}").contains("line 5"), "{err:#}");
+}
+
+#[test]
+fn streams_in_another_format_version_are_rejected() {
+    let plan = Plan {
+        version: PLAN_VERSION + 1,
+        ..plan()
+    };
+    let err = read(Cursor::new(stream_bytes(&plan).as_slice()))
+        .err()
+        .unwrap();
+    assert!(format!("{err:#}").contains("upgrade drafter"), "{err:#}");
+
+    let plan = Plan { version: 2, ..plan };
+    let err = read(Cursor::new(stream_bytes(&plan).as_slice()))
+        .err()
+        .unwrap();
+    assert!(format!("{err:#}").contains("re-plan"), "{err:#}");
+}
@@ -0,0 +1,178 @@
+use drafter::keymap::{keymap_for_layout, LayoutSpec};
+use drafter::model::ParagraphBreaks;
+use drafter::plan::{load, PLAN_JSON_SCHEMA, PLAN_VERSION};
+use drafter::planner::{generate_plan, PlannerConfig, WhitespacePolicy};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::Value;
+
+#[test]
+fn loads_current_plans_unchanged() {
+    let plan = generate_plan(
+        "Hello there.",
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(3),
+    )
+    .unwrap();
+    assert_eq!(plan.version, PLAN_VERSION);
+
+    let json = serde_json::to_string(&plan).unwrap();
+    let loaded = load(&json).unwrap();
+    assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+}
+
+#[test]
+fn migrates_version_1_plans_without_a_keymap() {
+    let json = r#"{
+        "version": 1,
+        "config": {"layout": "de", "keymap_format": 1, "keymap": "", "wpm_target": 60.0},
+        "actions": [
+            {"type": "key", "keycode": 30, "state": "pressed"},
+            {"type": "wait", "ms": 80},
+            {"type": "key", "keycode": 30, "state": "released"}
+        ]
+    }"#;
+    let plan = load(json).unwrap();
+
+    assert_eq!(plan.version, PLAN_VERSION);
+    assert_eq!(plan.config.layout, "de");
+    assert_eq!(
+        plan.config.keymap,
+        keymap_for_layout(&LayoutSpec::new("de")).unwrap().keymap
+    );
+    assert_eq!(plan.config.wpm_target, 60.0);
+    assert_eq!(plan.actions.len(), 3);
+}
+
+#[test]
+fn migrates_version_2_plans_unchanged() {
+    let json = r#"{
+        "version": 2,
+        "config": {"layout": "us", "keymap_format": 1, "keymap": "xkb_keymap {};", "wpm_target": 60.0},
+        "actions": [{"type": "wait", "ms": 80}]
+    }"#;
+    let plan = load(json).unwrap();
+
+    assert_eq!(plan.version, PLAN_VERSION);
+    assert_eq!(plan.config.keymap, "xkb_keymap {};");
+    assert_eq!(plan.actions.len(), 1);
+}
+
+#[test]
+fn migrates_version_3_plans_with_checkpoints() {
+    let json = r#"{
+        "version": 3,
+        "config": {"layout": "us", "keymap_format": 1, "keymap": "xkb_keymap {};", "wpm_target": 60.0},
+        "actions": [{"type": "wait", "ms": 80}, {"type": "checkpoint", "chars": 0}]
+    }"#;
+    let plan = load(json).unwrap();
+
+    assert_eq!(plan.version, PLAN_VERSION);
+    assert_eq!(plan.actions.len(), 2);
+}
+
+#[test]
+fn rejects_newer_and_unversioned_plans() {
+    let newer = format!(
+        r#"{{"version": {}, "config": {{}}, "actions": []}}"#,
+        PLAN_VERSION + 1
+    );
+    let err = load(&newer).unwrap_err();
+    assert!(err.to_string().contains("upgrade drafter"), "{err}");
+
+    let err = load(r#"{"config": {}, "actions": []}"#).unwrap_err();
+    assert!(err.to_string().contains("no \"version\""), "{err}");
+
+    let err = load(r#"{"version": 0, "config": {}, "actions": []}"#).unwrap_err();
+    assert!(err.to_string().contains("unsupported"), "{err}");
+}
+
+/// Sorted keys of a JSON object.
+fn keys(value: &Value) -> Vec<&str> {
+    let mut keys: Vec<&str> = value
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    keys.sort_unstable();
+    keys
+}
+
+/// Sorted `required` properties of a schema.
+fn required(schema: &Value) -> Vec<&str> {
+    let mut required: Vec<&str> = schema["required"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|key| key.as_str().unwrap())
+        .collect();
+    required.sort_unstable();
+    required
+}
+
+#[test]
+fn schema_describes_the_serialized_plan() {
+    let schema: Value = serde_json::from_str(PLAN_JSON_SCHEMA).unwrap();
+    assert_eq!(schema["properties"]["version"]["const"], PLAN_VERSION);
+
+    let cfg = PlannerConfig {
+        caps_lock_rate: 1.0,
+        error_rate_per_word: 1.0,
+        hold_backspace_from: 1,
+        paragraph_breaks: ParagraphBreaks::Double,
+        trailing_whitespace: WhitespacePolicy::Strip,
+        final_newline: WhitespacePolicy::Strip,
+        ..Default::default()
+    };
+    let plan = generate_plan(
+        "LOUD words, then quiet.",
+        cfg,
+        &mut StdRng::seed_from_u64(4),
+    )
+    .unwrap();
+    let plan = serde_json::to_value(&plan).unwrap();
+    let defs = &schema["$defs"];
+    // `annotations` is optional: plans that record none leave it out.
+    assert_eq!(keys(&plan), keys(&schema["properties"]));
+    assert!(required(&schema)
+        .iter()
+        .all(|key| keys(&plan).contains(key)));
+    for annotation in plan["annotations"].as_array().unwrap() {
+        assert_eq!(keys(annotation), required(&defs["Annotation"]));
+    }
+    // `word_nav_profile` is optional: plans without Ctrl+Left/Right navigation leave it out, and
+    // `paragraph_breaks` and the whitespace flags are left out at their defaults.
+    assert_eq!(
+        keys(&plan["config"]),
+        keys(&defs["PlanConfig"]["properties"])
+    );
+    assert!(required(&defs["PlanConfig"])
+        .iter()
+        .all(|key| keys(&plan["config"]).contains(key)));
+
+    let variants: Vec<&Value> = defs["Action"]["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|variant| {
+            let name = variant["$ref"]
+                .as_str()
+                .unwrap()
+                .trim_start_matches("#/$defs/");
+            &defs[name]
+        })
+        .collect();
+    let mut seen = Vec::new();
+    for action in plan["actions"].as_array().unwrap() {
+        let variant = variants
+            .iter()
+            .find(|variant| variant["properties"]["type"]["const"] == action["type"])
+            .unwrap_or_else(|| panic!("no schema for {action}"));
+        assert_eq!(keys(action), required(variant));
+        seen.push(action["type"].as_str().unwrap());
+    }
+    seen.sort_unstable();
+    seen.dedup();
+    assert_eq!(seen.len(), variants.len(), "plan uses {seen:?}");
+}
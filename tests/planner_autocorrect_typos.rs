@@ -0,0 +1,67 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::editor::CodeEditor;
+use drafter::keyboard::KEY_BACKSPACE;
+use drafter::model::{Action, KeyState, Plan};
+use drafter::planner::{generate_plan, EditorSubstitutions, PlannerConfig};
+use drafter::sim::{simulate_typed_text, simulate_typed_text_with, EditorBehavior};
+
+const TEXT: &str = "The cat and the dog are friends.\nTHE END, and that is just weird";
+
+fn plan(substitutions: EditorSubstitutions, seed: u64) -> Plan {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        autocorrect_typo_rate: 1.0,
+        substitutions,
+        ..Default::default()
+    };
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+const AUTOCORRECT: EditorBehavior = EditorBehavior {
+    undo: false,
+    autocorrect: true,
+    code_editor: CodeEditor {
+        auto_indent: false,
+        auto_close_brackets: false,
+    },
+};
+
+#[test]
+fn typos_are_left_for_the_editor_to_autocorrect() {
+    for seed in [1, 2, 3] {
+        let plan = plan(EditorSubstitutions::GOOGLE_DOCS, seed);
+        assert!(
+            !plan.actions.iter().any(|action| matches!(
+                action,
+                Action::Key {
+                    keycode: KEY_BACKSPACE,
+                    state: KeyState::Pressed,
+                }
+            )),
+            "seed {seed}"
+        );
+
+        let typed = simulate_typed_text(&plan).unwrap();
+        assert!(
+            typed
+                .split([' ', '\n'])
+                .all(|word| !["the", "and", "that", "just"].contains(&word.to_lowercase().as_str())),
+            "{typed}"
+        );
+        assert!(typed.contains(", adn taht is jsut weird"), "{typed}");
+        // Only words followed by a space are left to the editor.
+        assert!(typed.contains("are friends."), "{typed}");
+        assert!(typed.ends_with(" weird"), "{typed}");
+
+        assert_eq!(simulate_typed_text_with(&plan, AUTOCORRECT).unwrap(), TEXT);
+    }
+}
+
+#[test]
+fn editors_without_autocorrect_get_the_words_right() {
+    let plan = plan(EditorSubstitutions::default(), 4);
+    assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT);
+    assert_eq!(simulate_typed_text_with(&plan, AUTOCORRECT).unwrap(), TEXT);
+}
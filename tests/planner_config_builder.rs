@@ -0,0 +1,99 @@
+use drafter::error::DrafterError;
+use drafter::planner::{generate_plan, KeyRhythm, PlannerConfig, TabPolicy};
+use drafter::word_nav_profile::WordNavProfile;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+#[test]
+fn builder_sets_fields_over_the_defaults() {
+    let cfg = PlannerConfig::builder()
+        .wpm(70.0, 90.0)
+        .error_rate_per_word(0.0)
+        .word_nav_profile(WordNavProfile::Gtk)
+        .tab_policy(TabPolicy::Spaces(4))
+        .review_pause_ms(500, 800)
+        .build()
+        .unwrap();
+    assert_eq!((cfg.wpm_min, cfg.wpm_max), (70.0, 90.0));
+    assert_eq!(cfg.error_rate_per_word, 0.0);
+    assert_eq!(cfg.word_nav_profile, WordNavProfile::Gtk);
+    assert_eq!(cfg.tab_policy, TabPolicy::Spaces(4));
+    assert_eq!(
+        (cfg.review_pause_ms_min, cfg.review_pause_ms_max),
+        (500, 800)
+    );
+    assert_eq!(cfg.caps_lock_rate, PlannerConfig::default().caps_lock_rate);
+
+    assert!(generate_plan("hello", cfg, &mut StdRng::seed_from_u64(1)).is_ok());
+}
+
+#[test]
+fn builder_reports_the_first_invalid_setting() {
+    let err = PlannerConfig::builder()
+        .wpm(60.0, 40.0)
+        .caps_lock_rate(2.0)
+        .build()
+        .unwrap_err();
+    assert!(matches!(&err, DrafterError::InvalidConfig(_)), "{err:#}");
+    assert_eq!(err.to_string(), "wpm_min must be <= wpm_max");
+
+    let err = PlannerConfig::builder()
+        .immediate_fix_rate(-0.1)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "immediate_fix_rate must be between 0.0 and 1.0"
+    );
+
+    let err = PlannerConfig::builder()
+        .punctuation_error_rate(0.1)
+        .missing_period_rate(1.5)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "missing_period_rate must be between 0.0 and 1.0"
+    );
+
+    let err = PlannerConfig::builder()
+        .rhythm(KeyRhythm {
+            hold_min_ms: 80,
+            hold_max_ms: 40,
+            ..Default::default()
+        })
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "hold_min_ms must be > 0 and <= hold_max_ms"
+    );
+
+    let err = PlannerConfig::builder()
+        .review_pause_ms(900, 100)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "review_pause_ms_min must be <= review_pause_ms_max"
+    );
+}
+
+#[test]
+fn builder_rejects_non_finite_speeds() {
+    let err = PlannerConfig::builder()
+        .wpm(f64::NAN, 60.0)
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "wpm_min must be finite");
+}
+
+#[test]
+fn struct_literals_are_still_checked_at_planning_time() {
+    let cfg = PlannerConfig {
+        llm_mistake_rate: 1.5,
+        ..Default::default()
+    };
+    let err = generate_plan("hello", cfg, &mut StdRng::seed_from_u64(1)).unwrap_err();
+    assert!(matches!(err, DrafterError::InvalidConfig(_)), "{err:#}");
+}
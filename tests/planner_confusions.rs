@@ -0,0 +1,58 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::model::Plan;
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "Their dog is bigger than yours.\nIts bark is loud, and it's here.";
+
+fn plan(confusion_rate: f64, seed: u64) -> Plan {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        confusion_rate,
+        ..Default::default()
+    };
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+/// The text on screen after each action.
+fn texts(plan: &Plan) -> Vec<String> {
+    (1..=plan.actions.len())
+        .map(|end| {
+            simulate_typed_text(&Plan {
+                actions: plan.actions[..end].to_vec(),
+                ..plan.clone()
+            })
+            .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn confused_words_are_typed_and_fixed_at_a_later_review() {
+    for seed in [1, 2] {
+        let plan = plan(1.0, seed);
+        let texts = texts(&plan);
+        // In the case of the word they stand for, and left alone at least until the sentence
+        // is done.
+        assert!(
+            texts.iter().any(|text| {
+                (text.starts_with("There dog") || text.starts_with("They're dog"))
+                    && text.contains("yours.")
+            }),
+            "seed {seed}"
+        );
+        assert!(texts.iter().any(|text| text.contains("bigger then")));
+        assert!(texts.iter().any(|text| text.contains("\nIt's bark")));
+        assert_eq!(texts.last().unwrap(), TEXT);
+    }
+}
+
+#[test]
+fn confusions_are_off_by_default() {
+    let texts = texts(&plan(0.0, 4));
+    for (typed, text) in texts.iter().zip(texts.iter().skip(1)) {
+        assert!(text.starts_with(typed.as_str()), "{typed:?} then {text:?}");
+    }
+}
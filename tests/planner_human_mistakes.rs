@@ -0,0 +1,100 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::{KEY_L, KEY_W};
+use drafter::llm::{HumanMistake, MistakeKind};
+use drafter::model::{Action, KeyState, Plan};
+use drafter::planner::{generate_plan_with_human_mistakes, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+fn presses(plan: &Plan, keycode: u32) -> usize {
+    plan.actions
+        .iter()
+        .filter(|a| {
+            matches!(
+                a,
+                Action::Key { keycode: k, state: KeyState::Pressed } if *k == keycode
+            )
+        })
+        .count()
+}
+
+fn mistake(original: &str, mistake: &str, kind: MistakeKind) -> HumanMistake {
+    HumanMistake {
+        original: original.to_string(),
+        mistake: mistake.to_string(),
+        kind,
+    }
+}
+
+#[test]
+fn types_llm_word_mistakes_instead_of_mechanical_typos() {
+    // No `w` in the text: any W press comes from the misspelling.
+    let final_text = "The quick fox ran home.";
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        llm_mistake_rate: 1.0,
+        ..Default::default()
+    };
+    let mistakes = vec![vec![mistake("quick", "qwick", MistakeKind::Misspelling)]];
+
+    let mut rng = StdRng::seed_from_u64(11);
+    let plan =
+        generate_plan_with_human_mistakes(final_text, cfg, &[Vec::new()], &mistakes, &mut rng)
+            .expect("plan generation should succeed");
+
+    assert_eq!(simulate_typed_text(&plan).unwrap(), final_text);
+    assert_eq!(presses(&plan, KEY_W), 1);
+}
+
+#[test]
+fn types_informal_phrasing_and_revises_it() {
+    // No `l` in the text: any L press comes from the informal phrasing.
+    let final_text = "There were numerous issues.\n\nWe fixed them.";
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        llm_mistake_rate: 1.0,
+        ..Default::default()
+    };
+    let mistakes = vec![
+        vec![mistake("numerous", "a lot of", MistakeKind::Informal)],
+        Vec::new(),
+    ];
+
+    let mut rng = StdRng::seed_from_u64(5);
+    let plan = generate_plan_with_human_mistakes(
+        final_text,
+        cfg,
+        &[Vec::new(), Vec::new()],
+        &mistakes,
+        &mut rng,
+    )
+    .expect("plan generation should succeed");
+
+    assert_eq!(simulate_typed_text(&plan).unwrap(), final_text);
+    assert_eq!(presses(&plan, KEY_L), 1);
+}
+
+#[test]
+fn rejects_invalid_mistakes() {
+    let cfg = PlannerConfig::default();
+    let mistakes = vec![vec![mistake(
+        "quick fox",
+        "qiuck fox",
+        MistakeKind::Misspelling,
+    )]];
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let err = generate_plan_with_human_mistakes(
+        "The quick fox.",
+        cfg,
+        &[Vec::new()],
+        &mistakes,
+        &mut rng,
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("single word"),
+        "unexpected error: {err:?}"
+    );
+}
@@ -0,0 +1,79 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::KEY_Z;
+use drafter::model::{Action, KeyState};
+use drafter::planner::{generate_plan_with_paragraph_drafts, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+#[test]
+fn types_first_drafts_and_edits_them_into_the_final_text() {
+    let final_text = "The meeting dragged on, and by the end everyone looked tired.\n\nWe agreed to meet again next week.\n";
+    // Only the drafts contain a `z`.
+    let drafts = vec![
+        "So the meeting went on way too long and people got lazy and tired.".to_string(),
+        "We said we'd meet again sometime next week, zero rush.".to_string(),
+    ];
+
+    for seed in 0..8 {
+        let cfg = PlannerConfig {
+            error_rate_per_word: 0.1,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let plan = generate_plan_with_paragraph_drafts(final_text, cfg, &drafts, &mut rng)
+            .expect("plan generation should succeed");
+
+        assert_eq!(simulate_typed_text(&plan).unwrap(), final_text);
+        let z_presses = plan
+            .actions
+            .iter()
+            .filter(|a| {
+                matches!(
+                    a,
+                    Action::Key {
+                        keycode: KEY_Z,
+                        state: KeyState::Pressed
+                    }
+                )
+            })
+            .count();
+        assert!(z_presses >= 2, "expected the drafts to be typed");
+    }
+}
+
+#[test]
+fn rejects_drafts_that_span_paragraphs() {
+    let drafts = vec!["One.\n\nTwo.".to_string()];
+    let mut rng = StdRng::seed_from_u64(1);
+    let err = generate_plan_with_paragraph_drafts(
+        "One and two.",
+        PlannerConfig::default(),
+        &drafts,
+        &mut rng,
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("single paragraph"),
+        "unexpected error: {err:?}"
+    );
+}
+
+#[test]
+fn types_paragraphs_without_a_separate_draft_as_is() {
+    let final_text = "Keep this paragraph verbatim.\n\nWe agreed to meet again next week.\n";
+    let drafts = vec![
+        "Keep this paragraph verbatim.".to_string(),
+        "We said we'd meet again sometime next week, zero rush.".to_string(),
+    ];
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let plan = generate_plan_with_paragraph_drafts(
+        final_text,
+        PlannerConfig::default(),
+        &drafts,
+        &mut rng,
+    )
+    .expect("plan generation should succeed");
+    assert_eq!(simulate_typed_text(&plan).unwrap(), final_text);
+}
@@ -1,12 +1,55 @@
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 
+use drafter::error::DrafterError;
 use drafter::keyboard::{KEY_BACKSPACE, KEY_LEFT, KEY_Z};
-use drafter::llm::PhraseAlternative;
+use drafter::llm::{
+    ParagraphRephraseOptions, ParagraphRephraseProvider, PhraseAlternative, RevisionTiming,
+};
 use drafter::model::Action;
-use drafter::planner::{generate_plan_with_phrase_alternatives, PlannerConfig};
+use drafter::planner::{
+    generate_plan_with_phrase_alternatives, generate_plan_with_rephrase_provider, PlannerConfig,
+};
 use drafter::sim::simulate_typed_text;
 
+/// Replaces the first word of each paragraph with its uppercase form.
+struct FirstWordProvider;
+
+impl ParagraphRephraseProvider for FirstWordProvider {
+    async fn rephrase_paragraphs(
+        &self,
+        paragraphs: &[String],
+        _options: ParagraphRephraseOptions,
+    ) -> Result<Vec<Vec<PhraseAlternative>>, DrafterError> {
+        Ok(paragraphs
+            .iter()
+            .map(|paragraph| {
+                let word = paragraph.split_whitespace().next().unwrap_or_default();
+                vec![PhraseAlternative {
+                    original: word.to_string(),
+                    alternative: word.to_uppercase(),
+                    revise: None,
+                }]
+            })
+            .collect())
+    }
+}
+
+/// Poll a future that never waits on I/O to completion.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
 #[test]
 fn generates_plan_with_llm_phrase_alternative_edits() {
     let final_text = "HelloWorld";
@@ -22,6 +65,7 @@ fn generates_plan_with_llm_phrase_alternative_edits() {
     let alternatives_by_paragraph = vec![vec![PhraseAlternative {
         original: "Hello".to_string(),
         alternative: "zzz".to_string(),
+        revise: None,
     }]];
 
     let mut rng = StdRng::seed_from_u64(7);
@@ -64,3 +108,74 @@ fn generates_plan_with_llm_phrase_alternative_edits() {
         "expected at least one backspace for corrections"
     );
 }
+
+#[test]
+fn generates_plan_with_a_custom_rephrase_provider() {
+    let final_text = "Quickly typed words.\n\nAnother short paragraph.\n";
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.1,
+        ..Default::default()
+    };
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let plan = block_on(generate_plan_with_rephrase_provider(
+        final_text,
+        cfg,
+        &FirstWordProvider,
+        ParagraphRephraseOptions::default(),
+        &mut rng,
+    ))
+    .expect("plan generation should succeed");
+
+    assert_eq!(simulate_typed_text(&plan).unwrap(), final_text);
+    assert!(plan.actions.iter().any(|a| matches!(
+        a,
+        Action::Key { keycode, .. } if *keycode == KEY_BACKSPACE
+    )));
+}
+
+#[test]
+fn honors_llm_revision_hints() {
+    let final_text =
+        "The first draft was short. It needed more work.\n\nA second paragraph follows here.\n";
+    let alternative = |original: &str, alternative: &str, revise| PhraseAlternative {
+        original: original.to_string(),
+        alternative: alternative.to_string(),
+        revise: Some(revise),
+    };
+    let alternatives_by_paragraph = vec![
+        vec![
+            alternative(
+                "first draft",
+                "initial version",
+                RevisionTiming::EndOfParagraph,
+            ),
+            alternative("was short", "was brief", RevisionTiming::Immediately),
+            alternative("more work", "work", RevisionTiming::FinalReview),
+        ],
+        vec![alternative(
+            "follows here",
+            "comes next",
+            RevisionTiming::FinalReview,
+        )],
+    ];
+
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let plan = generate_plan_with_phrase_alternatives(
+            final_text,
+            PlannerConfig {
+                error_rate_per_word: 0.2,
+                ..Default::default()
+            },
+            &alternatives_by_paragraph,
+            &mut rng,
+        )
+        .expect("plan generation should succeed");
+        assert_eq!(
+            simulate_typed_text(&plan).unwrap(),
+            final_text,
+            "seed {seed}"
+        );
+    }
+}
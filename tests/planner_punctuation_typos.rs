@@ -0,0 +1,80 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::model::Plan;
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "First, a clause; then the rest.\nSecond paragraph ends here.";
+
+fn plan(cfg: PlannerConfig, seed: u64) -> Plan {
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+/// The text on screen after each action.
+fn texts(plan: &Plan) -> Vec<String> {
+    (1..=plan.actions.len())
+        .map(|end| {
+            simulate_typed_text(&Plan {
+                actions: plan.actions[..end].to_vec(),
+                ..plan.clone()
+            })
+            .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn punctuation_is_mistyped_as_an_adjacent_key_and_fixed() {
+    for (immediate_fix_rate, seed) in [(1.0, 1), (0.0, 2)] {
+        let cfg = PlannerConfig {
+            error_rate_per_word: 0.0,
+            punctuation_error_rate: 1.0,
+            immediate_fix_rate,
+            ..Default::default()
+        };
+        let plan = plan(cfg, seed);
+        let texts = texts(&plan);
+        assert!(
+            texts.iter().any(|text| text.starts_with("First.")),
+            "seed {seed}"
+        );
+        // The keys around `;` on US QWERTY.
+        assert!(texts.iter().any(|text| text
+            .strip_prefix("First, a clause")
+            .is_some_and(|rest| rest.starts_with(['\'', '[', '/', '.']))));
+        assert_eq!(texts.last().unwrap(), TEXT);
+    }
+}
+
+#[test]
+fn a_paragraph_final_period_is_left_out_and_added_later() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        missing_period_rate: 1.0,
+        ..Default::default()
+    };
+    let plan = plan(cfg, 3);
+    let texts = texts(&plan);
+    assert!(texts
+        .iter()
+        .any(|text| text.starts_with("First, a clause; then the rest\nS")));
+    // The period of the last paragraph waits for the final review.
+    assert!(texts
+        .iter()
+        .any(|text| text.ends_with("Second paragraph ends here")));
+    assert_eq!(texts.last().unwrap(), TEXT);
+}
+
+#[test]
+fn punctuation_typos_are_off_by_default() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        ..Default::default()
+    };
+    let texts = texts(&plan(cfg, 4));
+    for (typed, text) in texts.iter().zip(texts.iter().skip(1)) {
+        // Typing only ever appends to the text.
+        assert!(text.starts_with(typed.as_str()), "{typed:?} then {text:?}");
+    }
+}
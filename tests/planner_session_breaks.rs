@@ -0,0 +1,80 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::{KEY_DOWN, KEY_HOME, KEY_UP};
+use drafter::model::{Action, KeyState, Plan};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "First paragraph, on one line.\n\nSecond paragraph,\nwrapped by hand.\n\nThird.";
+
+fn plan(cfg: PlannerConfig, seed: u64) -> Plan {
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+fn presses(plan: &Plan) -> Vec<u32> {
+    plan.actions
+        .iter()
+        .filter_map(|action| match *action {
+            Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            } => Some(keycode),
+            _ => None,
+        })
+        .collect()
+}
+
+fn breaks(plan: &Plan, min_ms: u64) -> usize {
+    plan.actions
+        .iter()
+        .filter(|action| matches!(action, Action::Wait { ms } if *ms >= min_ms))
+        .count()
+}
+
+#[test]
+fn long_breaks_are_followed_by_a_reread() {
+    for (error_rate_per_word, seed) in [(0.0, 1), (0.3, 2)] {
+        let cfg = PlannerConfig::builder()
+            .error_rate_per_word(error_rate_per_word)
+            .session_break_rate(1.0)
+            .session_break_ms(60_000, 90_000)
+            .build()
+            .unwrap();
+        let plan = plan(cfg, seed);
+        // One break before each paragraph but the first.
+        assert_eq!(breaks(&plan, 60_000), 2);
+        let presses = presses(&plan);
+        let ups = presses.iter().filter(|&&key| key == KEY_UP).count();
+        assert!(ups >= 4, "seed {seed}");
+        assert_eq!(presses.iter().filter(|&&key| key == KEY_DOWN).count(), ups);
+        assert!(presses.iter().filter(|&&key| key == KEY_HOME).count() >= 2);
+        assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT);
+    }
+}
+
+#[test]
+fn short_breaks_are_not_reread() {
+    let cfg = PlannerConfig::builder()
+        .error_rate_per_word(0.0)
+        .session_break_rate(1.0)
+        .session_break_ms(10_000, 20_000)
+        .reread_after_ms(30_000)
+        .build()
+        .unwrap();
+    let plan = plan(cfg, 3);
+    assert_eq!(breaks(&plan, 10_000), 2);
+    assert!(!presses(&plan).contains(&KEY_UP));
+    assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT);
+}
+
+#[test]
+fn session_breaks_are_off_by_default() {
+    let plan = plan(PlannerConfig::default(), 4);
+    assert_eq!(breaks(&plan, 10_000), 0);
+    assert!(!presses(&plan).contains(&KEY_UP));
+    assert!(PlannerConfig::builder()
+        .session_break_ms(2, 1)
+        .build()
+        .is_err());
+}
@@ -1,7 +1,27 @@
 use std::ffi::OsString;
+#[cfg(feature = "x11")]
+use std::future::Future;
+#[cfg(feature = "x11")]
+use std::pin::pin;
+#[cfg(feature = "x11")]
+use std::sync::Arc;
 use std::sync::{Mutex, OnceLock};
+#[cfg(feature = "x11")]
+use std::task::{Context, Poll, Wake};
+#[cfg(feature = "x11")]
+use std::thread::Thread;
 
-use drafter::playback::{preflight_backend, resolve_backend, PlaybackBackend};
+use drafter::error::DrafterError;
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::playback::doctor::{diagnose, CheckStatus, DoctorReport};
+use drafter::playback::{
+    play_plan_async, preflight_backend, resolve_backend, CancellationToken, PlaybackBackend,
+    PlaybackOptions, SeatInfo,
+};
+#[cfg(feature = "x11")]
+use drafter::playback::{play_plan_with, PlaybackObserver};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 fn env_lock() -> &'static Mutex<()> {
     static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
@@ -164,6 +184,45 @@ fn explicit_wayland_is_rejected_or_accepted() {
     }
 }
 
+#[cfg(all(feature = "wayland", feature = "x11"))]
+#[test]
+fn auto_types_into_xwayland_apps_through_x11() {
+    use drafter::playback::{preflight_backend_for, resolve_backend_for};
+
+    let _guard = env_lock().lock().unwrap();
+    let _restore = EnvRestore::snapshot();
+
+    unset("WAYLAND_SOCKET");
+    set("WAYLAND_DISPLAY", "wayland-1");
+    set("DISPLAY", ":0");
+    assert_eq!(
+        resolve_backend_for(PlaybackBackend::Auto, true).unwrap(),
+        PlaybackBackend::X11
+    );
+    assert_eq!(
+        resolve_backend_for(PlaybackBackend::Auto, false).unwrap(),
+        PlaybackBackend::Wayland
+    );
+    // An explicit backend wins.
+    assert_eq!(
+        resolve_backend_for(PlaybackBackend::Wayland, true).unwrap(),
+        PlaybackBackend::Wayland
+    );
+    // The seat is Wayland-only, and the XWayland app is reached through X11.
+    let err = preflight_backend_for(PlaybackBackend::Auto, Some("seat0"), true).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("--seat is Wayland-only"),
+        "{err:#}"
+    );
+
+    // Without Xwayland, the virtual keyboard is the only way in.
+    unset("DISPLAY");
+    assert_eq!(
+        resolve_backend_for(PlaybackBackend::Auto, true).unwrap(),
+        PlaybackBackend::Wayland
+    );
+}
+
 #[test]
 fn seat_must_not_be_empty() {
     let err = preflight_backend(PlaybackBackend::Wayland, Some("   ")).unwrap_err();
@@ -200,3 +259,294 @@ fn seat_is_rejected_on_x11() {
         );
     }
 }
+
+#[cfg(feature = "x11")]
+struct ThreadWaker(Thread);
+
+#[cfg(feature = "x11")]
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls `future` on the current thread until it completes.
+#[cfg(feature = "x11")]
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
+
+#[test]
+fn cancellation_tokens_share_their_state() {
+    let token = CancellationToken::new();
+    let clone = token.clone();
+    assert!(!token.is_cancelled());
+    clone.cancel();
+    assert!(token.is_cancelled());
+}
+
+#[cfg(feature = "x11")]
+#[test]
+fn async_playback_reports_errors_from_its_thread() {
+    let _guard = env_lock().lock().unwrap();
+    let _restore = EnvRestore::snapshot();
+
+    unset("WAYLAND_DISPLAY");
+    unset("WAYLAND_SOCKET");
+    // No X server listens on this display.
+    set("DISPLAY", ":4242");
+
+    let plan = generate_plan(
+        "hi",
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .unwrap();
+    let options = PlaybackOptions {
+        backend: PlaybackBackend::X11,
+        ..Default::default()
+    };
+    let mut task = play_plan_async(plan, options, CancellationToken::new()).unwrap();
+    assert_eq!(block_on(task.next_event()), None);
+    let err = block_on(task).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("failed to connect to X11"),
+        "expected a connection error, got: {err:#}"
+    );
+}
+
+#[test]
+fn async_playback_checks_the_seat_before_starting() {
+    let err = play_plan_async(
+        generate_plan(
+            "hi",
+            PlannerConfig::default(),
+            &mut StdRng::seed_from_u64(1),
+        )
+        .unwrap(),
+        PlaybackOptions {
+            backend: PlaybackBackend::Wayland,
+            seat_name: Some(" ".to_string()),
+            ..Default::default()
+        },
+        CancellationToken::new(),
+    )
+    .unwrap_err();
+    assert!(format!("{err:#}").contains("--seat must not be empty"));
+}
+
+#[test]
+fn no_display_session_is_a_backend_unavailable_error() {
+    let _guard = env_lock().lock().unwrap();
+    let _restore = EnvRestore::snapshot();
+
+    unset("WAYLAND_DISPLAY");
+    unset("WAYLAND_SOCKET");
+    unset("DISPLAY");
+
+    let err = resolve_backend(PlaybackBackend::Auto).unwrap_err();
+    assert!(
+        matches!(err, DrafterError::BackendUnavailable(_)),
+        "{err:#}"
+    );
+}
+
+#[cfg(feature = "x11")]
+#[derive(Default)]
+struct RecordingObserver {
+    errors: Vec<String>,
+    completed: bool,
+}
+
+#[cfg(feature = "x11")]
+impl PlaybackObserver for RecordingObserver {
+    fn on_error(&mut self, err: &DrafterError) {
+        self.errors.push(format!("{err:#}"));
+    }
+
+    fn on_complete(&mut self, _trace_summary: Option<&str>) {
+        self.completed = true;
+    }
+}
+
+#[cfg(feature = "x11")]
+#[test]
+fn observers_are_told_why_playback_failed() {
+    let _guard = env_lock().lock().unwrap();
+    let _restore = EnvRestore::snapshot();
+
+    unset("WAYLAND_DISPLAY");
+    unset("WAYLAND_SOCKET");
+    // No X server listens on this display.
+    set("DISPLAY", ":4242");
+
+    let plan = generate_plan(
+        "hi",
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .unwrap();
+    let options = PlaybackOptions {
+        backend: PlaybackBackend::X11,
+        ..Default::default()
+    };
+    let mut observer = RecordingObserver::default();
+    let err =
+        play_plan_with(&plan, &options, &CancellationToken::new(), &mut observer).unwrap_err();
+    assert_eq!(observer.errors, vec![format!("{err:#}")]);
+    assert!(observer.errors[0].contains("failed to connect to X11"));
+    assert!(!observer.completed);
+}
+
+fn statuses(report: &DoctorReport) -> Vec<(&str, CheckStatus)> {
+    report
+        .checks
+        .iter()
+        .map(|check| (check.name, check.status))
+        .collect()
+}
+
+#[test]
+fn doctor_fails_outside_a_graphical_session() {
+    let _guard = env_lock().lock().unwrap();
+    let _restore = EnvRestore::snapshot();
+
+    unset("WAYLAND_DISPLAY");
+    unset("WAYLAND_SOCKET");
+    unset("DISPLAY");
+
+    let report = diagnose(&PlaybackOptions::default(), None);
+    assert_eq!(
+        statuses(&report),
+        [
+            ("session", CheckStatus::Fail),
+            ("backend", CheckStatus::Fail)
+        ]
+    );
+    assert!(report.checks[0].hint.is_some());
+    assert_eq!(report.failures(), 2);
+    assert!(!report.passed());
+}
+
+#[cfg(feature = "x11")]
+#[test]
+fn doctor_stops_at_the_first_check_that_others_depend_on() {
+    let _guard = env_lock().lock().unwrap();
+    let _restore = EnvRestore::snapshot();
+
+    unset("WAYLAND_DISPLAY");
+    unset("WAYLAND_SOCKET");
+    // No X server listens on this display.
+    set("DISPLAY", ":4242");
+
+    let report = diagnose(&PlaybackOptions::default(), None);
+    assert_eq!(
+        statuses(&report),
+        [
+            ("session", CheckStatus::Pass),
+            ("backend", CheckStatus::Pass),
+            ("x11 connection", CheckStatus::Fail)
+        ]
+    );
+    assert!(report.checks[2].detail.contains("failed to connect to X11"));
+
+    // Seats are Wayland-only, as in `play`.
+    let options = PlaybackOptions {
+        seat_name: Some("seat0".to_string()),
+        ..Default::default()
+    };
+    let report = diagnose(&options, None);
+    assert_eq!(report.checks[1].status, CheckStatus::Fail);
+    assert!(report.checks[1].detail.contains("--seat"));
+}
+
+#[test]
+fn seats_list_their_input_devices() {
+    let seat = SeatInfo {
+        name: Some("seat0".to_string()),
+        keyboard: true,
+        pointer: false,
+        touch: true,
+    };
+    assert_eq!(seat.capabilities(), ["keyboard", "touch"]);
+}
+
+#[cfg(feature = "wayland")]
+#[test]
+fn listing_seats_needs_a_compositor() {
+    use drafter::playback::wayland_seats;
+
+    let _guard = env_lock().lock().unwrap();
+    let _restore = EnvRestore::snapshot();
+
+    unset("WAYLAND_SOCKET");
+    // No compositor listens on this socket.
+    set("WAYLAND_DISPLAY", "/nonexistent/drafter-wayland-0");
+
+    let err = wayland_seats().unwrap_err();
+    assert!(
+        format!("{err:#}").contains("failed to connect to Wayland"),
+        "{err:#}"
+    );
+}
+
+#[cfg(all(feature = "wayland", feature = "x11"))]
+#[test]
+fn a_backend_chain_skips_backends_that_cannot_play() {
+    use drafter::playback::{preflight_backend_chain, BackendChoice};
+
+    let _guard = env_lock().lock().unwrap();
+    let _restore = EnvRestore::snapshot();
+
+    // Neither a compositor nor an X server listens on these.
+    set("WAYLAND_DISPLAY", "drafter-test-no-such-socket");
+    unset("WAYLAND_SOCKET");
+    set("DISPLAY", ":4242");
+
+    // A single backend is only resolved, as without a list.
+    assert_eq!(
+        preflight_backend_chain(&[PlaybackBackend::X11], None, false).unwrap(),
+        BackendChoice {
+            backend: PlaybackBackend::X11,
+            skipped: Vec::new(),
+        }
+    );
+
+    let err = preflight_backend_chain(
+        &[PlaybackBackend::Wayland, PlaybackBackend::X11],
+        None,
+        false,
+    )
+    .unwrap_err();
+    assert!(matches!(err, DrafterError::BackendUnavailable(_)), "{err}");
+    let message = err.to_string();
+    assert!(
+        message.contains("wayland: failed to connect to Wayland"),
+        "{message}"
+    );
+    assert!(
+        message.contains("x11: failed to connect to X11"),
+        "{message}"
+    );
+
+    // X11 takes no seat, so it is skipped before probing.
+    let err = preflight_backend_chain(
+        &[PlaybackBackend::X11, PlaybackBackend::Wayland],
+        Some("seat0"),
+        false,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("x11: "), "{err}");
+    assert!(
+        preflight_backend_chain(&[], None, false).is_err(),
+        "an empty chain has nothing to play with"
+    );
+}
@@ -0,0 +1,449 @@
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::error::DrafterError;
+use drafter::keyboard::KEY_BACKSPACE;
+use drafter::model::{Action, KeyState, Plan};
+use drafter::plan::{read, write_stream, PlanInput, PlanStream};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::playback::{
+    play_plan_to, play_stream_to, CancellationToken, Clock, KeySink, PlaybackObserver,
+    PlaybackOptions, PlaybackProgress, SystemClock, FAST_FORWARD_WAIT_MS,
+};
+use drafter::sim;
+use drafter::trace::{console_trace_for_plan, TraceLevel, TraceLine};
+
+fn plan() -> Plan {
+    generate_plan(
+        "Fake keys, virtual time.\nNo display needed.",
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(11),
+    )
+    .unwrap()
+}
+
+/// Records what it is sent, failing from the `fail_at`-th send on.
+#[derive(Default)]
+struct FakeSink {
+    sent: Vec<Action>,
+    resets: usize,
+    fail_at: Option<usize>,
+}
+
+impl FakeSink {
+    fn send(&mut self, action: Action) -> Result<()> {
+        if self.fail_at == Some(self.sent.len()) {
+            return Err(anyhow!("device unplugged"));
+        }
+        self.sent.push(action);
+        Ok(())
+    }
+}
+
+impl KeySink for FakeSink {
+    fn send_key(&mut self, keycode: u32, state: KeyState) -> Result<()> {
+        self.send(Action::Key { keycode, state })
+    }
+
+    fn send_modifiers(
+        &mut self,
+        mods_depressed: u32,
+        mods_latched: u32,
+        mods_locked: u32,
+        group: u32,
+    ) -> Result<()> {
+        self.send(Action::Modifiers {
+            mods_depressed,
+            mods_latched,
+            mods_locked,
+            group,
+        })
+    }
+
+    fn reset(&mut self) {
+        self.resets += 1;
+    }
+}
+
+/// Advances instantly, cancelling the token once `cancel_at_ms` have passed.
+#[derive(Default)]
+struct VirtualClock {
+    now_ms: u64,
+    cancel_at_ms: Option<u64>,
+}
+
+impl Clock for VirtualClock {
+    fn sleep(&mut self, ms: u64, cancel: &CancellationToken) -> bool {
+        self.now_ms += ms;
+        if self.cancel_at_ms.is_some_and(|at| self.now_ms >= at) {
+            cancel.cancel();
+        }
+        cancel.is_cancelled()
+    }
+}
+
+#[derive(Default)]
+struct Recorder {
+    countdown: Vec<u64>,
+    actions: usize,
+    last_progress: Option<PlaybackProgress>,
+    trace: Vec<TraceLine>,
+    repeat_taps: Vec<(usize, u32)>,
+    error: Option<String>,
+    completed: bool,
+}
+
+impl PlaybackObserver for Recorder {
+    fn on_countdown(&mut self, remaining_secs: u64, _total_secs: u64) {
+        self.countdown.push(remaining_secs);
+    }
+
+    fn on_action(&mut self, _: usize, _: &Action, progress: &PlaybackProgress) {
+        self.actions += 1;
+        self.last_progress = Some(progress.clone());
+    }
+
+    fn on_trace_event(&mut self, line: &TraceLine) {
+        self.trace.push(line.clone());
+    }
+
+    fn on_repeat_tap(&mut self, action_index: usize, taps: u32) {
+        self.repeat_taps.push((action_index, taps));
+    }
+
+    fn on_error(&mut self, err: &DrafterError) {
+        self.error = Some(format!("{err:#}"));
+    }
+
+    fn on_complete(&mut self, _trace_summary: Option<&str>) {
+        self.completed = true;
+    }
+}
+
+fn keys_and_modifiers(plan: &Plan) -> Vec<Action> {
+    plan.actions
+        .iter()
+        .filter(|action| matches!(action, Action::Key { .. } | Action::Modifiers { .. }))
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn plays_every_key_in_virtual_time_after_the_countdown() {
+    let plan = plan();
+    let options = PlaybackOptions {
+        countdown_secs: 3,
+        trace: Some(TraceLevel::Verbose),
+        ..Default::default()
+    };
+    let mut sink = FakeSink::default();
+    let mut clock = VirtualClock::default();
+    let mut recorder = Recorder::default();
+    play_plan_to(
+        &plan,
+        &options,
+        &mut sink,
+        &mut clock,
+        &CancellationToken::new(),
+        &mut recorder,
+    )
+    .unwrap();
+
+    assert_eq!(sink.sent, keys_and_modifiers(&plan));
+    assert_eq!(sink.resets, 0);
+    assert_eq!(clock.now_ms, 3000 + sim::stats(&plan).total_wait_ms);
+
+    assert_eq!(recorder.countdown, [3, 2, 1]);
+    assert_eq!(recorder.actions, plan.actions.len());
+    assert_eq!(
+        recorder.trace,
+        console_trace_for_plan(&plan, TraceLevel::Verbose)
+            .unwrap()
+            .lines
+    );
+    assert!(recorder.completed);
+    assert!(recorder.error.is_none());
+}
+
+#[test]
+fn fast_forward_plays_the_same_keys_with_tiny_waits() {
+    let plan = plan();
+    let options = PlaybackOptions {
+        countdown_secs: 2,
+        fast_forward: true,
+        ..Default::default()
+    };
+    let mut sink = FakeSink::default();
+    let mut clock = VirtualClock::default();
+    let mut recorder = Recorder::default();
+    play_plan_to(
+        &plan,
+        &options,
+        &mut sink,
+        &mut clock,
+        &CancellationToken::new(),
+        &mut recorder,
+    )
+    .unwrap();
+
+    assert_eq!(sink.sent, keys_and_modifiers(&plan));
+    let waits = plan
+        .actions
+        .iter()
+        .filter(|action| matches!(action, Action::Wait { .. }))
+        .count() as u64;
+    // The countdown still leaves time to focus the editor.
+    let played_ms = clock.now_ms - 2000;
+    assert!(played_ms <= waits * FAST_FORWARD_WAIT_MS);
+    assert!(played_ms < sim::stats(&plan).total_wait_ms / 10);
+    let progress = recorder.last_progress.unwrap();
+    assert_eq!(progress.total_ms, played_ms);
+    assert!(recorder.completed);
+}
+
+#[test]
+fn max_wait_cuts_only_the_longer_waits() {
+    let plan = plan();
+    let options = PlaybackOptions {
+        max_wait_ms: Some(150),
+        ..Default::default()
+    };
+    let mut sink = FakeSink::default();
+    let mut clock = VirtualClock::default();
+    let mut recorder = Recorder::default();
+    play_plan_to(
+        &plan,
+        &options,
+        &mut sink,
+        &mut clock,
+        &CancellationToken::new(),
+        &mut recorder,
+    )
+    .unwrap();
+
+    assert_eq!(sink.sent, keys_and_modifiers(&plan));
+    let expected_ms: u64 = plan
+        .actions
+        .iter()
+        .map(|action| match action {
+            Action::Wait { ms } => (*ms).min(150),
+            _ => 0,
+        })
+        .sum();
+    assert_eq!(clock.now_ms, expected_ms);
+    assert_eq!(options.played_ms(&plan), expected_ms);
+    assert!(expected_ms < sim::stats(&plan).total_wait_ms);
+    assert_eq!(recorder.last_progress.unwrap().total_ms, expected_ms);
+}
+
+#[test]
+fn skipping_ends_the_wait_in_progress() {
+    let cancel = CancellationToken::new();
+    let skipper = cancel.clone();
+    let started = Instant::now();
+    let sleeper = std::thread::spawn(move || SystemClock.sleep(60_000, &cancel));
+    std::thread::sleep(Duration::from_millis(50));
+    skipper.skip_wait();
+    assert!(!sleeper.join().unwrap(), "skipping does not cancel");
+    assert!(started.elapsed() < Duration::from_secs(30));
+    assert!(!skipper.is_cancelled());
+}
+
+#[test]
+fn cancelling_stops_sending_and_resets_the_sink() {
+    let plan = plan();
+    let total_ms = sim::stats(&plan).total_wait_ms;
+    let mut sink = FakeSink::default();
+    let mut clock = VirtualClock {
+        cancel_at_ms: Some(total_ms / 2),
+        ..Default::default()
+    };
+    let mut recorder = Recorder::default();
+    let err = play_plan_to(
+        &plan,
+        &PlaybackOptions::default(),
+        &mut sink,
+        &mut clock,
+        &CancellationToken::new(),
+        &mut recorder,
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, DrafterError::Aborted), "{err}");
+    assert_eq!(sink.resets, 1);
+    assert!(!sink.sent.is_empty());
+    assert!(sink.sent.len() < keys_and_modifiers(&plan).len());
+    let progress = recorder.last_progress.unwrap();
+    assert!(progress.played_ms < total_ms / 2);
+    assert_eq!(recorder.error.as_deref(), Some("aborted"));
+    assert!(!recorder.completed);
+}
+
+#[test]
+fn cancelling_a_key_repeat_reports_the_taps_sent() {
+    let mut plan = plan();
+    plan.actions = vec![
+        Action::Wait { ms: 50 },
+        Action::KeyRepeat {
+            keycode: KEY_BACKSPACE,
+            count: 5,
+            interval_ms: 100,
+        },
+    ];
+    let mut sink = FakeSink::default();
+    let mut clock = VirtualClock {
+        cancel_at_ms: Some(300),
+        ..Default::default()
+    };
+    let mut recorder = Recorder::default();
+    let err = play_plan_to(
+        &plan,
+        &PlaybackOptions::default(),
+        &mut sink,
+        &mut clock,
+        &CancellationToken::new(),
+        &mut recorder,
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, DrafterError::Aborted), "{err}");
+    // Taps at 50, 150 and 250 ms; the wait for the fourth is cancelled.
+    assert_eq!(recorder.repeat_taps, [(1, 1), (1, 2), (1, 3)]);
+    assert_eq!(sink.sent.len(), 6);
+}
+
+#[test]
+fn a_failed_send_resets_the_sink_and_reports_the_action() {
+    let plan = plan();
+    let mut sink = FakeSink {
+        fail_at: Some(5),
+        ..Default::default()
+    };
+    let mut recorder = Recorder::default();
+    let err = play_plan_to(
+        &plan,
+        &PlaybackOptions::default(),
+        &mut sink,
+        &mut VirtualClock::default(),
+        &CancellationToken::new(),
+        &mut recorder,
+    )
+    .unwrap_err();
+
+    let message = format!("{err:#}");
+    assert!(message.contains("failed to play action"), "{message}");
+    assert!(message.contains("device unplugged"), "{message}");
+    assert_eq!(sink.sent.len(), 5);
+    assert_eq!(sink.resets, 1);
+    assert_eq!(recorder.error, Some(message));
+}
+
+#[test]
+fn a_cancelled_countdown_sends_nothing() {
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let mut sink = FakeSink::default();
+    let err = play_plan_to(
+        &plan(),
+        &PlaybackOptions {
+            countdown_secs: 5,
+            ..Default::default()
+        },
+        &mut sink,
+        &mut VirtualClock::default(),
+        &cancel,
+        &mut (),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, DrafterError::Aborted));
+    assert!(sink.sent.is_empty());
+    assert_eq!(sink.resets, 0);
+}
+
+fn stream(bytes: &[u8]) -> PlanStream<Cursor<&[u8]>> {
+    match read(Cursor::new(bytes)).unwrap() {
+        PlanInput::Stream(stream) => stream,
+        PlanInput::Plan(_) => panic!("not a stream"),
+    }
+}
+
+#[test]
+fn a_stream_plays_like_the_plan_it_was_written_from() {
+    let plan = plan();
+    let mut bytes = Vec::new();
+    write_stream(&plan, &mut bytes).unwrap();
+    let mut sink = FakeSink::default();
+    let mut clock = VirtualClock::default();
+    let mut recorder = Recorder::default();
+    play_stream_to(
+        &mut stream(&bytes),
+        &PlaybackOptions::default(),
+        &mut sink,
+        &mut clock,
+        &CancellationToken::new(),
+        &mut recorder,
+    )
+    .unwrap();
+
+    assert_eq!(sink.sent, keys_and_modifiers(&plan));
+    assert_eq!(clock.now_ms, sim::stats(&plan).total_wait_ms);
+    assert_eq!(recorder.actions, plan.actions.len());
+    let progress = recorder.last_progress.unwrap();
+    assert_eq!(progress.total_actions, plan.actions.len());
+    assert_eq!(progress.total_ms, clock.now_ms);
+    assert!(recorder.completed);
+}
+
+#[test]
+fn a_truncated_stream_resets_the_sink_where_it_ends() {
+    let plan = plan();
+    let mut bytes = Vec::new();
+    write_stream(&plan, &mut bytes).unwrap();
+    // Cut half way through the action lines; the header takes most of the bytes.
+    let header_end = bytes.iter().position(|&byte| byte == b'\n').unwrap();
+    bytes.truncate(header_end + (bytes.len() - header_end) / 2);
+    let mut sink = FakeSink::default();
+    let mut recorder = Recorder::default();
+    let err = play_stream_to(
+        &mut stream(&bytes),
+        &PlaybackOptions::default(),
+        &mut sink,
+        &mut VirtualClock::default(),
+        &CancellationToken::new(),
+        &mut recorder,
+    )
+    .unwrap_err();
+
+    let message = format!("{err:#}");
+    assert!(message.contains("failed to read action"), "{message}");
+    assert!(!sink.sent.is_empty());
+    assert_eq!(sink.resets, 1);
+    assert!(!recorder.completed);
+}
+
+#[test]
+fn streams_cannot_be_traced() {
+    let mut bytes = Vec::new();
+    write_stream(&plan(), &mut bytes).unwrap();
+    let mut sink = FakeSink::default();
+    let err = play_stream_to(
+        &mut stream(&bytes),
+        &PlaybackOptions {
+            trace: Some(TraceLevel::Normal),
+            ..Default::default()
+        },
+        &mut sink,
+        &mut VirtualClock::default(),
+        &CancellationToken::new(),
+        &mut (),
+    )
+    .unwrap_err();
+
+    assert!(format!("{err}").contains("console trace"), "{err}");
+    assert!(sink.sent.is_empty());
+}
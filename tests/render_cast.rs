@@ -0,0 +1,236 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::Value;
+
+use drafter::plan_edit::{trim, PlanCut};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::playback::CancellationToken;
+use drafter::render::{
+    asciinema_cast, html_report, play_in_terminal, CastOptions, TerminalPreview,
+};
+use drafter::sim::{self, simulate_typed_text};
+
+const TEXT: &str = "Rendering a plan shows every correction as it happens.\n\nThe second paragraph is typed after a pause, with a few more mistakes.";
+
+/// The screen of a `width` x `height` terminal after `output`, supporting what casts use:
+/// text, CR/LF, cursor positioning, clearing and reverse video (ignored).
+fn terminal_screen(output: &str, width: usize, height: usize) -> Vec<String> {
+    let mut screen = vec![vec![' '; width]; height];
+    let (mut row, mut col) = (0usize, 0usize);
+    let mut chars = output.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                assert_eq!(chars.next(), Some('['));
+                let mut params = String::new();
+                let command = loop {
+                    let c = chars.next().expect("unterminated escape sequence");
+                    if c.is_ascii_alphabetic() {
+                        break c;
+                    }
+                    params.push(c);
+                };
+                match (command, params.as_str()) {
+                    ('H', "") => (row, col) = (0, 0),
+                    ('H', params) => {
+                        let (r, c) = params.split_once(';').unwrap();
+                        (row, col) = (
+                            r.parse::<usize>().unwrap() - 1,
+                            c.parse::<usize>().unwrap() - 1,
+                        );
+                    }
+                    ('J', "2") => screen = vec![vec![' '; width]; height],
+                    ('m', "7" | "27") => {}
+                    other => panic!("unexpected escape sequence {other:?}"),
+                }
+            }
+            '\r' => col = 0,
+            '\n' => {
+                if row + 1 == height {
+                    screen.remove(0);
+                    screen.push(vec![' '; width]);
+                } else {
+                    row += 1;
+                }
+            }
+            c => {
+                if col == width {
+                    col = 0;
+                    if row + 1 == height {
+                        screen.remove(0);
+                        screen.push(vec![' '; width]);
+                    } else {
+                        row += 1;
+                    }
+                }
+                screen[row][col] = c;
+                col += 1;
+            }
+        }
+    }
+    screen
+        .into_iter()
+        .map(|line| line.into_iter().collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn cast_replays_the_plan_with_its_timing() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.3,
+        ..Default::default()
+    };
+    let plan = generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(11)).unwrap();
+    let options = CastOptions {
+        width: 40,
+        height: 10,
+        title: Some("preview".to_string()),
+    };
+    let cast = asciinema_cast(&plan, &options).unwrap();
+
+    let mut lines = cast.lines();
+    let header: Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert_eq!(header["version"], 2);
+    assert_eq!(
+        (header["width"].as_u64(), header["height"].as_u64()),
+        (Some(40), Some(10))
+    );
+    assert_eq!(header["title"], "preview");
+
+    let mut output = String::new();
+    let mut last_time = 0.0;
+    let mut redraws = 0;
+    for line in lines {
+        let event: Value = serde_json::from_str(line).unwrap();
+        let time = event[0].as_f64().unwrap();
+        assert!(time >= last_time, "{time} before {last_time}");
+        last_time = time;
+        assert_eq!(event[1], "o");
+        let data = event[2].as_str().unwrap();
+        redraws += usize::from(data.starts_with("\x1b[H"));
+        output.push_str(data);
+    }
+    assert!(redraws > 0, "corrections redraw the screen");
+    assert!(last_time <= sim::stats(&plan).total_wait_ms as f64 / 1000.0);
+
+    // The final screen shows the text wrapped at 40 columns.
+    let mut expected: Vec<String> = Vec::new();
+    for line in TEXT.split('\n') {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            expected.push(String::new());
+        }
+        expected.extend(
+            chars
+                .chunks(40)
+                .map(|row| row.iter().collect::<String>().trim_end().to_string()),
+        );
+    }
+    expected.resize(10, String::new());
+    assert_eq!(terminal_screen(&output, 40, 10), expected);
+
+    // On a shorter terminal, the text scrolls and the end stays in view.
+    let short = CastOptions {
+        height: 3,
+        ..options
+    };
+    let output: String = asciinema_cast(&plan, &short)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let event: Value = serde_json::from_str(line).unwrap();
+            event[2].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(terminal_screen(&output, 40, 3), expected[2..5]);
+}
+
+#[test]
+fn terminal_preview_writes_the_cast_screen() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.3,
+        ..Default::default()
+    };
+    let plan = generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(11)).unwrap();
+    let options = TerminalPreview {
+        width: 40,
+        height: 10,
+        speed: 50.0,
+        max_wait_ms: Some(200),
+    };
+    let mut written = Vec::new();
+    let started = std::time::Instant::now();
+    let finished =
+        play_in_terminal(&plan, &options, &mut written, &CancellationToken::new()).unwrap();
+    assert!(finished);
+    assert!(started.elapsed().as_millis() < sim::stats(&plan).total_wait_ms as u128 / 10);
+
+    let cast = CastOptions {
+        width: 40,
+        height: 10,
+        title: None,
+    };
+    let expected: String = asciinema_cast(&plan, &cast)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let event: Value = serde_json::from_str(line).unwrap();
+            event[2].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(
+        String::from_utf8(written).unwrap(),
+        format!("\x1b[H\x1b[2J{expected}")
+    );
+
+    // A cancelled preview stops before the first key.
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let mut written = Vec::new();
+    assert!(!play_in_terminal(&plan, &options, &mut written, &cancel).unwrap());
+    assert_eq!(written, b"\x1b[H\x1b[2J");
+}
+
+#[test]
+fn html_report_shows_the_text_at_any_time() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.3,
+        ..Default::default()
+    };
+    let plan = generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(11)).unwrap();
+    let html = html_report(&plan, "Draft <1>").unwrap();
+    assert!(html.contains("<title>Draft &lt;1&gt;</title>"));
+    assert!(html.contains("<polyline points="));
+
+    let start = html.find("id=\"plan-data\">").unwrap() + "id=\"plan-data\">".len();
+    let end = start + html[start..].find("</script>").unwrap();
+    let data: Value = serde_json::from_str(&html[start..end]).unwrap();
+    let total_ms = sim::stats(&plan).total_wait_ms;
+    assert_eq!(data["total_ms"], total_ms);
+    assert!(!data["corrections"].as_array().unwrap().is_empty());
+
+    // What the player shows at a time is what the plan typed until then.
+    let text_at = |at_ms: u64| -> String {
+        data["chars"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|typed| {
+                typed[1].as_u64().unwrap() <= at_ms
+                    && typed[2].as_u64().is_none_or(|deleted| deleted > at_ms)
+            })
+            .map(|typed| typed[0].as_str().unwrap())
+            .collect()
+    };
+    assert_eq!(text_at(total_ms), TEXT);
+    for at_ms in (0..total_ms).step_by(997) {
+        let head = trim(&plan, None, Some(PlanCut::Ms(at_ms + 1))).unwrap();
+        assert_eq!(
+            text_at(at_ms),
+            simulate_typed_text(&head).unwrap(),
+            "at {at_ms} ms"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::keystroke_for_output_char;
+use drafter::model::{Action, KeyState, Plan};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::run_state::{self, RunState};
+use drafter::sim;
+
+const TEXT: &str = "The first paragraph sets the scene.\n\nThe second one follows it up.";
+
+fn plan(error_rate_per_word: f64, seed: u64) -> Plan {
+    let cfg = PlannerConfig {
+        error_rate_per_word,
+        ..Default::default()
+    };
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+fn state_dir(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("drafter-run-state-{}-{name}", std::process::id()))
+}
+
+#[test]
+fn stashed_runs_load_back() {
+    let dir = state_dir("roundtrip");
+    let state = RunState {
+        plans: vec![plan(0.3, 0), plan(0.3, 1)],
+        action_index: 120,
+        repeat_taps: 0,
+    };
+    state.save(&dir).unwrap();
+    assert!(dir.join("plan.json").exists());
+    assert!(dir.join("plan-2.json").exists());
+    assert_eq!(RunState::load(&dir).unwrap(), state);
+
+    run_state::clear(&dir).unwrap();
+    assert!(RunState::load(&dir).is_err());
+}
+
+#[test]
+fn saving_and_clearing_leave_other_files_alone() {
+    let dir = state_dir("other-files");
+    std::fs::create_dir_all(&dir).unwrap();
+    let draft = dir.join("draft.txt");
+    let plan_notes = dir.join("plan-notes.json");
+    std::fs::write(&draft, "keep me").unwrap();
+    std::fs::write(&plan_notes, "{}").unwrap();
+
+    let state = RunState {
+        plans: vec![plan(0.3, 3), plan(0.3, 4)],
+        action_index: 10,
+        repeat_taps: 0,
+    };
+    state.save(&dir).unwrap();
+    // Saving a shorter run replaces the old plan files.
+    RunState {
+        plans: vec![plan(0.3, 5)],
+        ..state
+    }
+    .save(&dir)
+    .unwrap();
+    assert!(!dir.join("plan-2.json").exists());
+    assert_eq!(std::fs::read_to_string(&draft).unwrap(), "keep me");
+
+    run_state::clear(&dir).unwrap();
+    assert!(!dir.join("plan.json").exists());
+    assert!(!dir.join("progress.json").exists());
+    assert_eq!(std::fs::read_to_string(&draft).unwrap(), "keep me");
+    assert!(plan_notes.exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn resumed_plans_type_the_rest_of_the_text() {
+    let plan = plan(0.0, 2);
+    let (action_index, chars) = plan
+        .actions
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(idx, action)| match action {
+            Action::Checkpoint { chars } if *chars > 0 && *chars < TEXT.len() => {
+                Some((idx, *chars))
+            }
+            _ => None,
+        })
+        .expect("a checkpoint inside the text");
+
+    let state = RunState {
+        plans: vec![plan.clone(), plan],
+        action_index,
+        repeat_taps: 0,
+    };
+    let (typed, cursor_back) = state.typed_so_far().unwrap();
+    assert_eq!(typed, TEXT[..chars]);
+    assert_eq!(cursor_back, 0);
+
+    let remaining = state.remaining().unwrap();
+    assert_eq!(remaining.len(), 2);
+    let rest = sim::simulate_typed_text(&remaining[0]).unwrap();
+    assert_eq!(format!("{typed}{rest}"), TEXT);
+
+    // A plan played to its end is not played again.
+    let state = RunState {
+        action_index: state.plans[0].actions.len(),
+        ..state
+    };
+    assert_eq!(state.remaining().unwrap().len(), 1);
+}
+
+/// What the editor holds once `state`'s run stopped (after `repeat_taps` taps of a key repeat,
+/// releasing the keys it held) and its first remaining plan was played.
+fn resumed_text(state: &RunState) -> String {
+    let plan = &state.plans[0];
+    let mut actions = plan.actions[..state.action_index].to_vec();
+    if let Some(Action::KeyRepeat {
+        keycode,
+        interval_ms,
+        ..
+    }) = plan.actions.get(state.action_index)
+    {
+        actions.push(Action::KeyRepeat {
+            keycode: *keycode,
+            count: state.repeat_taps,
+            interval_ms: *interval_ms,
+        });
+    }
+    let mut held = Vec::new();
+    for action in &actions {
+        match action {
+            Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            } => held.push(*keycode),
+            Action::Key {
+                keycode,
+                state: KeyState::Released,
+            } => held.retain(|k| k != keycode),
+            _ => {}
+        }
+    }
+    actions.extend(held.into_iter().rev().map(|keycode| Action::Key {
+        keycode,
+        state: KeyState::Released,
+    }));
+    actions.extend(state.remaining().unwrap().remove(0).actions);
+    sim::simulate_typed_text(&Plan {
+        actions,
+        ..plan.clone()
+    })
+    .unwrap()
+}
+
+#[test]
+fn resuming_inside_a_key_hold_does_not_type_the_key_again() {
+    let text = "Hello world.";
+    let plan = generate_plan(
+        text,
+        PlannerConfig {
+            error_rate_per_word: 0.0,
+            ..Default::default()
+        },
+        &mut StdRng::seed_from_u64(4),
+    )
+    .unwrap();
+    let e = keystroke_for_output_char('e').unwrap().keycode;
+    let press = plan
+        .actions
+        .iter()
+        .position(|action| {
+            *action
+                == Action::Key {
+                    keycode: e,
+                    state: KeyState::Pressed,
+                }
+        })
+        .unwrap();
+    let shift = plan.actions[..press]
+        .iter()
+        .position(|action| {
+            matches!(
+                action,
+                Action::Key {
+                    state: KeyState::Pressed,
+                    ..
+                }
+            )
+        })
+        .unwrap();
+
+    // Inside the hold of `e`, and inside the hold of Shift for `H`.
+    for action_index in [press + 1, shift + 1, shift + 2] {
+        let state = RunState {
+            plans: vec![plan.clone()],
+            action_index,
+            repeat_taps: 0,
+        };
+        assert_eq!(resumed_text(&state), text, "stopped at {action_index}");
+    }
+}
+
+#[test]
+fn resuming_a_key_repeat_plays_only_the_taps_left() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.4,
+        hold_backspace_from: 2,
+        ..Default::default()
+    };
+    let (plan, action_index, count) = (0..50)
+        .find_map(|seed| {
+            let plan = generate_plan(TEXT, cfg.clone(), &mut StdRng::seed_from_u64(seed)).unwrap();
+            let (idx, count) =
+                plan.actions
+                    .iter()
+                    .enumerate()
+                    .find_map(|(idx, action)| match action {
+                        Action::KeyRepeat { count, .. } if *count >= 3 => Some((idx, *count)),
+                        _ => None,
+                    })?;
+            Some((plan, idx, count))
+        })
+        .expect("a plan holding Backspace for 3 or more taps");
+
+    for repeat_taps in [0, 1, count - 1] {
+        let state = RunState {
+            plans: vec![plan.clone()],
+            action_index,
+            repeat_taps,
+        };
+        assert_eq!(resumed_text(&state), TEXT, "after {repeat_taps} taps");
+        let remaining = state.remaining().unwrap();
+        let left = remaining[0].actions.iter().find_map(|action| match action {
+            Action::KeyRepeat { count, .. } => Some(*count),
+            _ => None,
+        });
+        assert_eq!(left, Some(count - repeat_taps));
+    }
+}
@@ -0,0 +1,65 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::{KEY_LEFTSHIFT, KEY_RIGHTSHIFT};
+use drafter::model::{Action, KeyState, Plan};
+use drafter::planner::{generate_plan, PlannerConfig, ShiftProfile};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "Alice met Kate and Bob in Paris. Dinner: 8 PM!\n";
+
+fn shift_presses(plan: &Plan) -> Vec<u32> {
+    plan.actions
+        .iter()
+        .filter_map(|a| match a {
+            Action::Key {
+                keycode,
+                state: KeyState::Pressed,
+            } if *keycode == KEY_LEFTSHIFT || *keycode == KEY_RIGHTSHIFT => Some(*keycode),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn hand_aware_shift_uses_the_opposite_hand() {
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.0,
+        shift_profile: ShiftProfile::HandAware,
+        ..Default::default()
+    };
+    let plan = generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(1)).unwrap();
+
+    // A, K, B, P, D, `:`, then one Shift held through `PM!`.
+    assert_eq!(
+        shift_presses(&plan),
+        vec![
+            KEY_RIGHTSHIFT,
+            KEY_LEFTSHIFT,
+            KEY_RIGHTSHIFT,
+            KEY_LEFTSHIFT,
+            KEY_RIGHTSHIFT,
+            KEY_LEFTSHIFT,
+            KEY_LEFTSHIFT,
+        ]
+    );
+    assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT);
+}
+
+#[test]
+fn left_only_profile_is_the_default_and_keeps_left_shift() {
+    assert_eq!(
+        PlannerConfig::default().shift_profile,
+        ShiftProfile::LeftOnly
+    );
+    let cfg = PlannerConfig {
+        error_rate_per_word: 0.3,
+        ..Default::default()
+    };
+    let plan = generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(1)).unwrap();
+
+    let presses = shift_presses(&plan);
+    assert!(!presses.is_empty());
+    assert!(presses.iter().all(|k| *k == KEY_LEFTSHIFT));
+    assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT);
+}
@@ -24,8 +24,13 @@ fn dummy_plan(actions: Vec<Action>) -> Plan {
             keymap_format: 1,
             keymap: String::new(),
             wpm_target: 0.0,
+            word_nav_profile: None,
+            paragraph_breaks: Default::default(),
+            strip_trailing_whitespace: false,
+            strip_final_newline: false,
         },
         actions,
+        annotations: Vec::new(),
     }
 }
 
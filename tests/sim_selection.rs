@@ -0,0 +1,93 @@
+use drafter::keyboard::{
+    keystroke_for_output_char, KEY_BACKSPACE, KEY_DELETE, KEY_LEFT, KEY_LEFTCTRL, KEY_LEFTSHIFT,
+    KEY_RIGHT,
+};
+use drafter::model::{Action, KeyState, Plan, PlanConfig};
+use drafter::sim::simulate_typed_text;
+
+fn key_presses_for_text(text: &str) -> Vec<Action> {
+    text.chars()
+        .map(|c| {
+            let stroke = keystroke_for_output_char(c).expect("test text must be typable");
+            Action::Key {
+                keycode: stroke.keycode,
+                state: KeyState::Pressed,
+            }
+        })
+        .collect()
+}
+
+fn key(keycode: u32, state: KeyState) -> Action {
+    Action::Key { keycode, state }
+}
+
+/// `keys` pressed while holding `modifiers`.
+fn chord(modifiers: &[u32], keys: &[u32]) -> Vec<Action> {
+    let mut actions: Vec<Action> = modifiers
+        .iter()
+        .map(|m| key(*m, KeyState::Pressed))
+        .collect();
+    actions.extend(keys.iter().map(|k| key(*k, KeyState::Pressed)));
+    actions.extend(modifiers.iter().map(|m| key(*m, KeyState::Released)));
+    actions
+}
+
+fn simulate(actions: Vec<Action>) -> String {
+    let plan = Plan {
+        version: 1,
+        config: PlanConfig {
+            layout: "us".to_string(),
+            keymap_format: 1,
+            keymap: String::new(),
+            wpm_target: 0.0,
+            word_nav_profile: None,
+            paragraph_breaks: Default::default(),
+            strip_trailing_whitespace: false,
+            strip_final_newline: false,
+        },
+        actions,
+        annotations: Vec::new(),
+    };
+    simulate_typed_text(&plan).expect("plan simulation should succeed")
+}
+
+#[test]
+fn ctrl_backspace_and_ctrl_delete_delete_by_word() {
+    let mut actions = key_presses_for_text("one two three");
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_BACKSPACE]));
+    assert_eq!(simulate(actions.clone()), "one two ");
+
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_LEFT, KEY_LEFT, KEY_DELETE]));
+    assert_eq!(simulate(actions), " two ");
+}
+
+#[test]
+fn shift_arrows_select_and_typing_replaces_the_selection() {
+    let mut actions = key_presses_for_text("hello wrold");
+    actions.extend(chord(&[KEY_LEFTSHIFT, KEY_LEFTCTRL], &[KEY_LEFT]));
+    actions.extend(key_presses_for_text("world"));
+    assert_eq!(simulate(actions), "hello world");
+
+    let mut actions = key_presses_for_text("abcdef");
+    actions.extend(chord(
+        &[KEY_LEFTSHIFT],
+        &[KEY_LEFT, KEY_LEFT, KEY_LEFT, KEY_RIGHT],
+    ));
+    actions.push(key(KEY_BACKSPACE, KeyState::Pressed));
+    assert_eq!(simulate(actions), "abcd");
+}
+
+#[test]
+fn plain_arrows_collapse_the_selection() {
+    let mut actions = key_presses_for_text("abcdef");
+    actions.extend(chord(&[KEY_LEFTSHIFT], &[KEY_LEFT, KEY_LEFT]));
+    actions.push(key(KEY_LEFT, KeyState::Pressed));
+    actions.extend(key_presses_for_text("x"));
+    assert_eq!(simulate(actions), "abcdxef");
+
+    let mut actions = key_presses_for_text("abcdef");
+    actions.extend(chord(&[KEY_LEFTSHIFT], &[KEY_LEFT, KEY_LEFT]));
+    actions.push(key(KEY_RIGHT, KeyState::Pressed));
+    actions.extend(key_presses_for_text("x"));
+    assert_eq!(simulate(actions), "abcdefx");
+}
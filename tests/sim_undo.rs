@@ -0,0 +1,115 @@
+use drafter::keyboard::{
+    keystroke_for_output_char, KEY_BACKSPACE, KEY_LEFT, KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_Y, KEY_Z,
+};
+use drafter::model::{Action, KeyState, Plan, PlanConfig};
+use drafter::sim::{simulate_typed_text, simulate_typed_text_with, EditorBehavior};
+
+fn key_presses_for_text(text: &str) -> Vec<Action> {
+    text.chars()
+        .map(|c| {
+            let stroke = keystroke_for_output_char(c).expect("test text must be typable");
+            key(stroke.keycode, KeyState::Pressed)
+        })
+        .collect()
+}
+
+fn key(keycode: u32, state: KeyState) -> Action {
+    Action::Key { keycode, state }
+}
+
+/// `keys` pressed while holding `modifiers`.
+fn chord(modifiers: &[u32], keys: &[u32]) -> Vec<Action> {
+    let mut actions: Vec<Action> = modifiers
+        .iter()
+        .map(|m| key(*m, KeyState::Pressed))
+        .collect();
+    actions.extend(keys.iter().map(|k| key(*k, KeyState::Pressed)));
+    actions.extend(modifiers.iter().map(|m| key(*m, KeyState::Released)));
+    actions
+}
+
+fn plan(actions: Vec<Action>) -> Plan {
+    Plan {
+        version: 1,
+        config: PlanConfig {
+            layout: "us".to_string(),
+            keymap_format: 1,
+            keymap: String::new(),
+            wpm_target: 0.0,
+            word_nav_profile: None,
+            paragraph_breaks: Default::default(),
+            strip_trailing_whitespace: false,
+            strip_final_newline: false,
+        },
+        actions,
+        annotations: Vec::new(),
+    }
+}
+
+fn simulate(actions: Vec<Action>) -> String {
+    simulate_typed_text_with(
+        &plan(actions),
+        EditorBehavior {
+            undo: true,
+            ..Default::default()
+        },
+    )
+    .expect("plan simulation should succeed")
+}
+
+#[test]
+fn undoes_typing_a_word_at_a_time_and_redoes_it() {
+    let mut actions = key_presses_for_text("one two");
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_Z]));
+    assert_eq!(simulate(actions.clone()), "one ");
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_Z, KEY_Z]));
+    assert_eq!(simulate(actions.clone()), "");
+
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_Y]));
+    assert_eq!(simulate(actions.clone()), "one ");
+    actions.extend(chord(&[KEY_LEFTCTRL, KEY_LEFTSHIFT], &[KEY_Z]));
+    assert_eq!(simulate(actions.clone()), "one two");
+
+    // Typing after an undo drops what could be redone.
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_Z]));
+    actions.extend(key_presses_for_text("three"));
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_Y]));
+    assert_eq!(simulate(actions), "one three");
+}
+
+#[test]
+fn groups_backspaces_and_breaks_groups_at_cursor_moves() {
+    let mut actions = key_presses_for_text("hello world");
+    actions.extend([KEY_BACKSPACE; 3].map(|k| key(k, KeyState::Pressed)));
+    assert_eq!(simulate(actions.clone()), "hello wo");
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_Z]));
+    assert_eq!(simulate(actions.clone()), "hello world");
+
+    actions.push(key(KEY_LEFT, KeyState::Pressed));
+    actions.extend(key_presses_for_text("x"));
+    assert_eq!(simulate(actions.clone()), "hello worlxd");
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_Z]));
+    assert_eq!(simulate(actions.clone()), "hello world");
+
+    // The cursor is back where the undone edit started.
+    actions.extend(key_presses_for_text("-"));
+    assert_eq!(simulate(actions), "hello worl-d");
+}
+
+#[test]
+fn undoes_typing_over_a_selection_in_one_step() {
+    let mut actions = key_presses_for_text("a cat");
+    actions.extend(chord(&[KEY_LEFTSHIFT], &[KEY_LEFT, KEY_LEFT, KEY_LEFT]));
+    actions.extend(key_presses_for_text("dog"));
+    assert_eq!(simulate(actions.clone()), "a dog");
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_Z]));
+    assert_eq!(simulate(actions), "a cat");
+}
+
+#[test]
+fn undo_needs_the_editor_behavior() {
+    let mut actions = key_presses_for_text("one");
+    actions.extend(chord(&[KEY_LEFTCTRL], &[KEY_Z]));
+    let err = simulate_typed_text(&plan(actions)).unwrap_err();
+    assert!(err.to_string().contains("Ctrl"), "{err}");
+}
@@ -0,0 +1,39 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::verify_typed_text;
+
+const TEXT: &str = "First line of the draft.\nSecond line, with a few words.";
+
+#[test]
+fn verifies_the_typed_text_and_points_at_the_first_difference() {
+    let plan = generate_plan(
+        TEXT,
+        PlannerConfig::default(),
+        &mut StdRng::seed_from_u64(4),
+    )
+    .unwrap();
+    verify_typed_text(&plan, TEXT).unwrap();
+
+    let stale = TEXT.replace("few", "couple of");
+    let err = verify_typed_text(&plan, &stale).unwrap_err().to_string();
+    assert_eq!(
+        err,
+        "typed text differs at line 2, column 21: expected \"couple of words.\", the plan \
+         types \"few words.\""
+    );
+
+    let err = verify_typed_text(&plan, &format!("{TEXT}\n")).unwrap_err();
+    assert!(
+        err.to_string().ends_with("the plan stops before \"\\n\""),
+        "{err}"
+    );
+    let err = verify_typed_text(&plan, "First line").unwrap_err();
+    assert!(
+        err.to_string().starts_with(
+            "typed text differs at line 1, column 11: the plan types \" of the draft."
+        ),
+        "{err}"
+    );
+}
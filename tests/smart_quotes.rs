@@ -0,0 +1,54 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keymap::LayoutSpec;
+use drafter::layout::{KeyboardLayout, SmartQuotes};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "“Don’t,” she said. ‘Fine.’\n";
+
+fn cfg(smart_quotes: SmartQuotes, error_rate: f64) -> PlannerConfig {
+    PlannerConfig {
+        error_rate_per_word: error_rate,
+        smart_quotes,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn substitute_types_ascii_quotes() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let plan = generate_plan(TEXT, cfg(SmartQuotes::Substitute, 0.0), &mut rng).unwrap();
+    assert_eq!(plan.config.layout, "us");
+    assert_eq!(
+        simulate_typed_text(&plan).unwrap(),
+        "\"Don't,\" she said. 'Fine.'\n"
+    );
+}
+
+#[test]
+fn literal_quotes_match_the_draft_exactly() {
+    for seed in 0..10 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let plan = generate_plan(TEXT, cfg(SmartQuotes::Literal, 0.3), &mut rng).unwrap();
+        assert_eq!(plan.config.layout, "us+custom", "seed {seed}");
+        assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT, "seed {seed}");
+    }
+}
+
+#[test]
+fn literal_quotes_without_quotes_keep_the_base_keymap() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let plan = generate_plan("Plain text.\n", cfg(SmartQuotes::Literal, 0.0), &mut rng).unwrap();
+    assert_eq!(plan.config.layout, "us");
+}
+
+#[test]
+fn literal_layout_rejects_quotes_it_cannot_type() {
+    let mut layout = KeyboardLayout::from_spec(&LayoutSpec::default()).unwrap();
+    assert!(layout.is_supported_char('’'));
+    layout.set_smart_quotes(SmartQuotes::Literal);
+    assert!(!layout.is_supported_char('’'));
+    assert!(layout.is_supported_char('\''));
+}
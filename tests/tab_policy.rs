@@ -0,0 +1,59 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::keyboard::KEY_TAB;
+use drafter::model::Action;
+use drafter::planner::{generate_plan, PlannerConfig, TabPolicy};
+use drafter::sim::simulate_typed_text;
+
+const TEXT: &str = "fn main() {\n\tprintln!(\"hi\");\n\tif ready {\n\t\tgo();\n\t}\n}\n";
+
+fn cfg(error_rate: f64, tab_policy: TabPolicy) -> PlannerConfig {
+    PlannerConfig {
+        error_rate_per_word: error_rate,
+        tab_policy,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn tabs_are_rejected_by_default() {
+    let err = generate_plan(
+        TEXT,
+        cfg(0.0, TabPolicy::Reject),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .expect_err("tabs should be rejected");
+    assert!(err.to_string().contains("line 2, column 1"), "{err}");
+}
+
+#[test]
+fn literal_tabs_press_the_tab_key() {
+    for error_rate in [0.0, 0.4] {
+        let mut rng = StdRng::seed_from_u64(5);
+        let plan = generate_plan(TEXT, cfg(error_rate, TabPolicy::Literal), &mut rng).unwrap();
+
+        let tab_presses = plan
+            .actions
+            .iter()
+            .filter(|a| matches!(a, Action::Key { keycode, .. } if *keycode == KEY_TAB))
+            .count();
+        assert!(tab_presses >= 2 * 5);
+        assert_eq!(simulate_typed_text(&plan).unwrap(), TEXT);
+    }
+}
+
+#[test]
+fn space_policy_expands_tabs() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let plan = generate_plan(TEXT, cfg(0.4, TabPolicy::Spaces(2)), &mut rng).unwrap();
+
+    assert!(!plan
+        .actions
+        .iter()
+        .any(|a| matches!(a, Action::Key { keycode, .. } if *keycode == KEY_TAB)));
+    assert_eq!(
+        simulate_typed_text(&plan).unwrap(),
+        TEXT.replace('\t', "  ")
+    );
+}
@@ -0,0 +1,122 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::model::{Action, KeyState, Plan};
+use drafter::planner::{generate_plan, KeyRhythm, PlannerConfig};
+use drafter::timing_calibration::{fit, read_keystrokes, KeystrokeLog};
+
+/// Letters and spaces only, which the planner types without punctuation or thinking pauses.
+const TEXT: &str = "the quick brown fox jumps over the lazy dog while five wizards box quickly \
+                    and a jovial mage packs my box with liquor jugs near the old barn door ";
+
+fn plan(cfg: PlannerConfig, seed: u64) -> Plan {
+    generate_plan(&TEXT.repeat(6), cfg, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+/// `(press, release)` times of each key of `plan`.
+fn keystrokes(plan: &Plan) -> Vec<(u64, u64)> {
+    let mut now = 0;
+    let mut pressed = Vec::new();
+    let mut out = Vec::new();
+    for action in &plan.actions {
+        match action {
+            Action::Wait { ms } => now += ms,
+            Action::Key {
+                state: KeyState::Pressed,
+                ..
+            } => pressed.push(now),
+            Action::Key {
+                state: KeyState::Released,
+                ..
+            } => out.push((pressed.pop().unwrap(), now)),
+            _ => {}
+        }
+    }
+    out.sort();
+    out
+}
+
+#[test]
+fn fitting_a_plan_recovers_its_timing() {
+    let cfg = PlannerConfig {
+        wpm_min: 70.0,
+        wpm_max: 70.0,
+        error_rate_per_word: 0.0,
+        rhythm: KeyRhythm {
+            delay_spread: 0.2,
+            hold_min_ms: 60,
+            hold_max_ms: 120,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut csv = String::from("PARTICIPANT_ID\tPRESS_TIME\tRELEASE_TIME\tLETTER\n");
+    for (press, release) in keystrokes(&plan(cfg, 1)) {
+        csv.push_str(&format!("7\t{press}\t{release}\tx\n"));
+    }
+
+    let log = read_keystrokes(csv.as_bytes()).unwrap();
+    let fit = fit(&log).unwrap();
+    assert!((64.0..=76.0).contains(&fit.wpm_min), "{fit:?}");
+    assert!((64.0..=76.0).contains(&fit.wpm_max), "{fit:?}");
+    assert!((0.15..=0.25).contains(&fit.rhythm.delay_spread), "{fit:?}");
+    assert!((60..=66).contains(&fit.rhythm.hold_min_ms), "{fit:?}");
+    assert!((114..=120).contains(&fit.rhythm.hold_max_ms), "{fit:?}");
+    assert_eq!(fit.pauses, 0);
+
+    // A plan with the fitted timing types like the original.
+    let refitted = plan(
+        PlannerConfig {
+            wpm_min: fit.wpm_min,
+            wpm_max: fit.wpm_max,
+            rhythm: fit.rhythm,
+            error_rate_per_word: 0.0,
+            ..Default::default()
+        },
+        2,
+    );
+    let keys = keystrokes(&refitted);
+    let log = KeystrokeLog {
+        latencies_ms: keys
+            .windows(2)
+            .map(|pair| (pair[1].0 - pair[0].0) as f64)
+            .collect(),
+        holds_ms: keys
+            .iter()
+            .map(|(press, release)| (release - press) as f64)
+            .collect(),
+    };
+    let refit = drafter::timing_calibration::fit(&log).unwrap();
+    assert!((refit.wpm_min - fit.wpm_min).abs() < 6.0, "{refit:?}");
+}
+
+#[test]
+fn latency_columns_are_read_and_pauses_left_out() {
+    let mut csv = String::from("Latency (ms),Hold ms\n");
+    for i in 0..120 {
+        csv.push_str(&format!("{},{}\n", 150 + i % 40, 80 + i % 10));
+    }
+    csv.push_str("5400,\n");
+    let log = read_keystrokes(csv.as_bytes()).unwrap();
+    assert_eq!(log.latencies_ms.len(), 121);
+    assert_eq!(log.holds_ms.len(), 120);
+
+    let fit = fit(&log).unwrap();
+    assert_eq!(fit.keystrokes, 120);
+    assert_eq!(fit.pauses, 1);
+    assert!(fit.rhythm.delay_min_ms < fit.rhythm.delay_max_ms);
+    assert_eq!(fit.settings()[0].0, "wpm-min");
+}
+
+#[test]
+fn unusable_csvs_are_rejected() {
+    let err = read_keystrokes("key,letter\n30,a\n".as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("no latency column"), "{err}");
+
+    let err = read_keystrokes("latency_ms\n120\nfast\n".as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("line 3"), "{err}");
+
+    let log = read_keystrokes("latency_ms\n120\n140\n".as_bytes()).unwrap();
+    let err = fit(&log).unwrap_err();
+    assert!(err.to_string().contains("too few keystrokes"), "{err}");
+}
@@ -1,6 +1,11 @@
-use drafter::keyboard::{keystroke_for_output_char, KEY_BACKSPACE, KEY_LEFT, KEY_RIGHT};
+use drafter::keyboard::{
+    keystroke_for_output_char, KEY_BACKSPACE, KEY_LEFT, KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_RIGHT,
+};
 use drafter::model::{Action, KeyState};
-use drafter::trace::plan_console_trace;
+use drafter::trace::{
+    console_trace, plan_console_trace, trace_records, trace_records_csv, trace_records_subtitles,
+    SubtitleFormat, TraceEventKind, TraceLevel, TraceLineKind, TraceRecord,
+};
 
 fn actions_for_text(text: &str) -> Vec<Action> {
     text.chars()
@@ -78,3 +83,265 @@ fn does_not_log_typing_run_at_end_of_plan() {
     let events = trace_events(&actions);
     assert!(events.is_empty());
 }
+
+#[test]
+fn records_kind_time_cursor_and_final_text_range() {
+    let mut actions = vec![Action::Wait { ms: 400 }];
+    actions.extend(actions_for_text("hi\nhello wurld."));
+    let typing_len = actions.len();
+    actions.push(Action::Wait { ms: 250 });
+    actions.push(Action::Key {
+        keycode: KEY_LEFT,
+        state: KeyState::Pressed,
+    });
+    for _ in 0..5 {
+        actions.push(Action::Key {
+            keycode: KEY_BACKSPACE,
+            state: KeyState::Pressed,
+        });
+    }
+    actions.extend(actions_for_text("world"));
+    actions.push(Action::Key {
+        keycode: KEY_RIGHT,
+        state: KeyState::Pressed,
+    });
+
+    let events = plan_console_trace(&actions);
+    assert_eq!(events[1].annotation(), "0:00.7 L2:7");
+    let records = trace_records(&events);
+    assert_eq!(
+        records,
+        vec![
+            TraceRecord {
+                action_index: 1,
+                offset_ms: 400,
+                cursor_line: 1,
+                cursor_column: 1,
+                kind: TraceEventKind::Typing,
+                text_start: Some(0),
+                text_end: Some(15),
+                line: "Typing \"hi\\nhello wurld.\"...".to_string(),
+            },
+            TraceRecord {
+                action_index: typing_len + 1,
+                offset_ms: 650,
+                cursor_line: 2,
+                cursor_column: 7,
+                kind: TraceEventKind::Replace,
+                text_start: Some(9),
+                text_end: Some(14),
+                line: "Replace \"wurld\" with \"world\"...".to_string(),
+            },
+        ]
+    );
+
+    let csv = trace_records_csv(&records);
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("action_index,offset_ms,cursor_line,cursor_column,kind,text_start,text_end,line")
+    );
+    assert_eq!(
+        lines.next(),
+        Some("1,400,1,1,typing,0,15,\"Typing \"\"hi\\nhello wurld.\"\"...\"")
+    );
+}
+
+#[test]
+fn logs_replacing_a_selection_and_ctrl_backspace() {
+    let mut actions = actions_for_text("hello wrold");
+    let typing_len = actions.len();
+    actions.extend([
+        Action::Key {
+            keycode: KEY_LEFTSHIFT,
+            state: KeyState::Pressed,
+        },
+        Action::Key {
+            keycode: KEY_LEFTCTRL,
+            state: KeyState::Pressed,
+        },
+        Action::Key {
+            keycode: KEY_LEFT,
+            state: KeyState::Pressed,
+        },
+        Action::Key {
+            keycode: KEY_LEFTCTRL,
+            state: KeyState::Released,
+        },
+        Action::Key {
+            keycode: KEY_LEFTSHIFT,
+            state: KeyState::Released,
+        },
+    ]);
+    actions.extend(actions_for_text("world again"));
+    let retyped_len = actions.len();
+    actions.extend([
+        Action::Key {
+            keycode: KEY_LEFTCTRL,
+            state: KeyState::Pressed,
+        },
+        Action::Key {
+            keycode: KEY_BACKSPACE,
+            state: KeyState::Pressed,
+        },
+        Action::Key {
+            keycode: KEY_LEFTCTRL,
+            state: KeyState::Released,
+        },
+    ]);
+    actions.extend(actions_for_text("now."));
+    actions.push(Action::Key {
+        keycode: KEY_LEFT,
+        state: KeyState::Pressed,
+    });
+
+    assert_eq!(
+        trace_events(&actions),
+        vec![
+            (0, "Typing \"hello wrold\"...".to_string()),
+            (
+                typing_len + 2,
+                "Replace \"wrold\" with \"world\"...".to_string()
+            ),
+            (typing_len + 10, "Typing \" again\"...".to_string()),
+            (
+                retyped_len + 1,
+                "Replace \"again\" with \"now\"...".to_string()
+            ),
+            (retyped_len + 6, "Typing \".\"...".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn subtitles_show_each_line_until_the_next_one() {
+    let record = |offset_ms: u64, kind: TraceEventKind, line: &str| TraceRecord {
+        action_index: 0,
+        offset_ms,
+        cursor_line: 1,
+        cursor_column: 1,
+        kind,
+        text_start: None,
+        text_end: None,
+        line: line.to_string(),
+    };
+    let records = [
+        record(400, TraceEventKind::Typing, "Typing \"skipped\"..."),
+        record(400, TraceEventKind::Typing, "Typing \"a <b>\"..."),
+        record(
+            3_661_250,
+            TraceEventKind::Replace,
+            "Replace \"wurld\" with \"world\"...",
+        ),
+    ];
+
+    assert_eq!(
+        trace_records_subtitles(&records, 3_700_000, SubtitleFormat::Srt),
+        "1\n00:00:00,400 --> 01:01:01,250\nTyping \"a <b>\"...\n\n\
+         2\n01:01:01,250 --> 01:01:05,250\nReplace \"wurld\" with \"world\"...\n\n"
+    );
+    let vtt = trace_records_subtitles(&records, 3_662_000, SubtitleFormat::Vtt);
+    assert!(
+        vtt.starts_with("WEBVTT\n\n1\n00:00:00.400 --> 01:01:01.250\nTyping \"a &lt;b&gt;\"...")
+    );
+    assert!(vtt.contains("01:01:01.250 --> 01:01:02.000\n"));
+}
+
+/// Types `ab cd`, pauses 1.5 s, moves back with Left, Ctrl+Left and Right, then types `x`.
+fn navigating_actions() -> Vec<Action> {
+    let key = |keycode| Action::Key {
+        keycode,
+        state: KeyState::Pressed,
+    };
+    let mut actions = actions_for_text("ab cd");
+    actions.push(Action::Wait { ms: 600 });
+    actions.push(Action::Wait { ms: 900 });
+    actions.extend([key(KEY_LEFT), key(KEY_LEFT), key(KEY_LEFT)]);
+    actions.push(key(KEY_LEFTCTRL));
+    actions.push(key(KEY_LEFT));
+    actions.push(Action::Key {
+        keycode: KEY_LEFTCTRL,
+        state: KeyState::Released,
+    });
+    actions.push(key(KEY_RIGHT));
+    actions.extend(actions_for_text("x"));
+    actions
+}
+
+#[test]
+fn trace_levels_add_navigation_pauses_and_actions() {
+    let actions = navigating_actions();
+
+    let quiet = console_trace(&actions, TraceLevel::Quiet);
+    assert!(quiet.lines.is_empty());
+    assert_eq!(
+        quiet.summary.as_deref(),
+        Some("Typed 6 characters in 0:01.5 with 0 corrections.")
+    );
+
+    let normal = console_trace(&actions, TraceLevel::Normal);
+    assert_eq!(normal.summary, None);
+    let events: Vec<_> = plan_console_trace(&actions)
+        .into_iter()
+        .map(|e| e.line)
+        .collect();
+    let lines: Vec<_> = normal.lines.iter().map(|l| l.line.clone()).collect();
+    assert_eq!(lines, events);
+
+    let verbose = console_trace(&actions, TraceLevel::Verbose);
+    let lines: Vec<_> = verbose
+        .lines
+        .iter()
+        .map(|l| (l.action_index, l.kind, l.line.as_str()))
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            (0, TraceLineKind::Typing, "Typing \"ab cd\"..."),
+            (5, TraceLineKind::Pause, "Pause 1.5 s"),
+            (7, TraceLineKind::Navigation, "Move Left x3 to L1:3"),
+            (11, TraceLineKind::Navigation, "Move Ctrl+Left to L1:1"),
+            (13, TraceLineKind::Navigation, "Move Right to L1:2"),
+        ]
+    );
+    assert_eq!(verbose.lines[2].cursor.column, 6);
+    assert_eq!(verbose.lines[2].offset_ms, 1_500);
+    assert!(verbose.summary.is_some());
+
+    let debug = console_trace(&actions, TraceLevel::Debug);
+    assert_eq!(debug.lines.len(), lines.len() + actions.len());
+    assert_eq!(debug.lines[1].line, "#0 key 30 pressed");
+    assert_eq!(debug.lines[7].line, "#5 wait 600 ms");
+    assert_eq!(debug.lines[6].kind, TraceLineKind::Pause);
+}
+
+#[test]
+fn timeline_shows_planned_times_up_to_the_limit() {
+    let trace = console_trace(&navigating_actions(), TraceLevel::Verbose);
+
+    assert_eq!(
+        trace.timeline(false, Some(2), 1_500),
+        "   0:00.0  Typing \"ab cd\"...\n   \
+         0:00.0  Pause 1.5 s\n\
+         ... 3 more lines, until 0:01.5\n"
+    );
+    let one_left = trace.timeline(true, Some(4), 1_500);
+    assert!(
+        one_left.contains("   0:01.5  L1:3      Move Ctrl+Left to L1:1\n"),
+        "{one_left}"
+    );
+    assert!(
+        one_left.ends_with("\n... 1 more line, until 0:01.5\n"),
+        "{one_left}"
+    );
+
+    // Without a limit, or with one past the end, the summary follows the last line.
+    let full = trace.timeline(false, None, 1_500);
+    assert_eq!(full.lines().count(), trace.lines.len() + 1);
+    assert!(full.ends_with(&format!("{}\n", trace.summary.clone().unwrap())));
+    assert_eq!(trace.timeline(false, Some(99), 1_500), full);
+    assert_eq!(
+        trace.timeline(false, Some(0), 1_500),
+        "... 5 more lines, until 0:01.5\n"
+    );
+}
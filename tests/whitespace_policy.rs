@@ -0,0 +1,89 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::error::DrafterError;
+use drafter::model::{find_trailing_whitespace, strip_trailing_whitespace, Plan};
+use drafter::planner::{generate_plan, PlannerConfig, TabPolicy, WhitespacePolicy};
+use drafter::sim::{simulate_typed_text, verify_typed_text};
+
+const TEXT: &str = "Spaces after this line.  \nA tab\there\t\nClean line.\n\n";
+
+fn plan(
+    trailing_whitespace: WhitespacePolicy,
+    final_newline: WhitespacePolicy,
+    error_rate_per_word: f64,
+    seed: u64,
+) -> Result<Plan, DrafterError> {
+    let cfg = PlannerConfig::builder()
+        .error_rate_per_word(error_rate_per_word)
+        .tab_policy(TabPolicy::Literal)
+        .trailing_whitespace(trailing_whitespace)
+        .final_newline(final_newline)
+        .build()
+        .unwrap();
+    generate_plan(TEXT, cfg, &mut StdRng::seed_from_u64(seed))
+}
+
+#[test]
+fn trailing_whitespace_is_found_and_stripped_per_line() {
+    assert_eq!(find_trailing_whitespace(TEXT), [(1, 24), (2, 11)]);
+    assert_eq!(
+        strip_trailing_whitespace(TEXT),
+        "Spaces after this line.\nA tab\there\nClean line.\n\n"
+    );
+    assert_eq!(
+        find_trailing_whitespace("crlf\r\nend \u{a0}"),
+        [(1, 5), (2, 4)]
+    );
+    assert!(find_trailing_whitespace("clean\n\n").is_empty());
+}
+
+#[test]
+fn stripped_whitespace_is_left_out_and_verifies_against_the_draft() {
+    use WhitespacePolicy::{Preserve, Strip};
+
+    for (trailing, final_newline, typed) in [
+        (Preserve, Preserve, TEXT),
+        (
+            Strip,
+            Preserve,
+            "Spaces after this line.\nA tab\there\nClean line.\n\n",
+        ),
+        (
+            Preserve,
+            Strip,
+            "Spaces after this line.  \nA tab\there\t\nClean line.",
+        ),
+        (
+            Strip,
+            Strip,
+            "Spaces after this line.\nA tab\there\nClean line.",
+        ),
+    ] {
+        for (error_rate, seed) in [(0.0, 1), (0.4, 2)] {
+            let plan = plan(trailing, final_newline, error_rate, seed).unwrap();
+            assert_eq!(simulate_typed_text(&plan).unwrap(), typed);
+            assert_eq!(plan.config.typed_text(TEXT), typed);
+            verify_typed_text(&plan, TEXT).unwrap();
+        }
+    }
+}
+
+#[test]
+fn rejected_whitespace_is_reported() {
+    use WhitespacePolicy::{Preserve, Reject};
+
+    let err = plan(Reject, Preserve, 0.0, 3).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            DrafterError::TrailingWhitespace {
+                line: 1,
+                column: 24
+            }
+        ),
+        "{err:#}"
+    );
+    let err = plan(Preserve, Reject, 0.0, 3).unwrap_err();
+    assert!(matches!(err, DrafterError::FinalNewline), "{err:#}");
+}
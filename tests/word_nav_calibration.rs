@@ -0,0 +1,97 @@
+use drafter::keyboard::KEY_HOME;
+use drafter::sim::simulate_typed_text;
+use drafter::testing::{check_balanced_keys, check_no_stuck_modifiers};
+use drafter::word_nav_calibration::{
+    calibrate, calibrate_document, calibration_plan, expected_document, marker_positions,
+    ProbeDirection, ProbePlanBuilder, ProbeTiming, CANDIDATES, DEFAULT_STEPS, PROBE_TEXT,
+};
+use drafter::word_nav_profile::WordNavProfile;
+
+fn documents(left: &WordNavProfile, right: &WordNavProfile) -> (String, String) {
+    (
+        expected_document(left, ProbeDirection::Left, DEFAULT_STEPS),
+        expected_document(right, ProbeDirection::Right, DEFAULT_STEPS),
+    )
+}
+
+#[test]
+fn recognizes_every_candidate_from_its_markers() {
+    for profile in CANDIDATES {
+        let (left, right) = documents(&profile, &profile);
+        let calibration = calibrate(&left, &right, DEFAULT_STEPS).unwrap();
+        assert_eq!(calibration.recommended(), profile);
+        assert!(calibration.matches[0].is_exact());
+        assert!(
+            !calibration.matches[1].is_exact(),
+            "{profile:?} and {:?} are indistinguishable",
+            calibration.matches[1].profile
+        );
+
+        // The probes run into both ends of the text.
+        let len = PROBE_TEXT.chars().count();
+        assert_eq!(calibration.left.last(), Some(&Some(0)), "{profile:?}");
+        assert_eq!(calibration.right.last(), Some(&Some(len)), "{profile:?}");
+    }
+}
+
+#[test]
+fn falls_back_to_compatible_when_no_profile_matches() {
+    let (left, right) = documents(&WordNavProfile::Gtk, &WordNavProfile::Qt);
+    let calibration = calibrate(&left, &right, DEFAULT_STEPS).unwrap();
+    assert_eq!(calibration.recommended(), WordNavProfile::Compatible);
+    assert!(calibration.matches.iter().all(|m| !m.is_exact()));
+}
+
+#[test]
+fn rejects_documents_that_are_not_the_probe_text() {
+    let (left, right) = documents(&WordNavProfile::Chrome, &WordNavProfile::Chrome);
+    let corrected = right.replace("don't", "don’t");
+    let err = calibrate(&left, &corrected, DEFAULT_STEPS).unwrap_err();
+    assert!(err.to_string().contains("Ctrl+Right probe"), "{err}");
+}
+
+#[test]
+fn reads_marker_positions_without_counting_markers() {
+    assert_eq!(
+        marker_positions("ab<2>c<1>d<9>", 3),
+        [Some(3), Some(2), None]
+    );
+}
+
+#[test]
+fn probes_leave_the_model_document() {
+    for direction in [ProbeDirection::Left, ProbeDirection::Right] {
+        let mut b = ProbePlanBuilder::new(ProbeTiming::default()).unwrap();
+        b.type_string(PROBE_TEXT).unwrap();
+        if direction == ProbeDirection::Right {
+            b.ctrl_tap(KEY_HOME);
+        }
+        b.probe(direction, DEFAULT_STEPS).unwrap();
+        assert_eq!(
+            simulate_typed_text(&b.into_plan()).unwrap(),
+            expected_document(&WordNavProfile::Chrome, direction, DEFAULT_STEPS)
+        );
+    }
+
+    // The calibration plan probes both lines of one document, without leaving the keys it may
+    // press: no select-all, copy or paste.
+    let plan = calibration_plan(DEFAULT_STEPS, ProbeTiming::default()).unwrap();
+    check_balanced_keys(&plan).unwrap();
+    check_no_stuck_modifiers(&plan).unwrap();
+    let document = simulate_typed_text(&plan).unwrap();
+    let (left, right) = documents(&WordNavProfile::Chrome, &WordNavProfile::Chrome);
+    assert_eq!(document, format!("{left}\n{right}"));
+    let calibration = calibrate_document(&document, DEFAULT_STEPS).unwrap();
+    assert_eq!(calibration.recommended(), WordNavProfile::Chrome);
+}
+
+#[test]
+fn reads_the_saved_document_of_both_probes() {
+    let (left, right) = documents(&WordNavProfile::Qt, &WordNavProfile::Qt);
+    let saved = format!("{left}\r\n{right}\r\n");
+    let calibration = calibrate_document(&saved, DEFAULT_STEPS).unwrap();
+    assert_eq!(calibration.recommended(), WordNavProfile::Qt);
+
+    let err = calibrate_document(&left, DEFAULT_STEPS).unwrap_err();
+    assert!(err.to_string().contains("1 lines instead of 2"), "{err}");
+}
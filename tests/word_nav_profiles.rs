@@ -1,6 +1,17 @@
-use drafter::planner::PlannerConfig;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use drafter::analysis::{analyze, AnalysisOptions};
+use drafter::editor::is_word_char;
+use drafter::keyboard::{KEY_END, KEY_HOME, KEY_LEFTCTRL};
+use drafter::model::{Action, KeyState};
+use drafter::planner::{generate_plan, PlannerConfig};
+use drafter::sim::simulate_typed_text;
+use drafter::word_nav::{
+    ctrl_right, toolkit_ctrl_left, toolkit_ctrl_right, CustomRules, Toolkit, WordChars,
+};
 use drafter::word_nav_profile::{
-    compatible_ctrl_jump_is_safe, compatible_ctrl_span_is_safe, WordNavProfile,
+    compatible_ctrl_jump_is_safe, compatible_ctrl_span_is_safe, CustomWordNav, WordNavProfile,
 };
 
 fn chars(s: &str) -> Vec<char> {
@@ -65,3 +76,202 @@ fn compatible_safe_span_rejects_risky_characters() {
         );
     }
 }
+
+/// Every Ctrl+Right stop from the start and every Ctrl+Left stop from the end.
+fn stops(text: &str, toolkit: Toolkit) -> (Vec<usize>, Vec<usize>) {
+    let buf = chars(text);
+    let (mut right, mut left) = (vec![], vec![]);
+    let mut cursor = 0;
+    while cursor < buf.len() {
+        cursor = toolkit_ctrl_right(&buf, cursor, toolkit);
+        right.push(cursor);
+    }
+    while cursor > 0 {
+        cursor = toolkit_ctrl_left(&buf, cursor, toolkit);
+        left.push(cursor);
+    }
+    (right, left)
+}
+
+#[test]
+fn toolkits_stop_at_their_own_word_boundaries() {
+    let text = "don't stop-now, ok";
+    assert_eq!(
+        stops(text, Toolkit::Gtk),
+        (vec![5, 10, 14, 18], vec![16, 11, 6, 0])
+    );
+    assert_eq!(
+        stops(text, Toolkit::Qt),
+        (vec![6, 10, 11, 14, 16, 18], vec![16, 14, 11, 10, 6, 0])
+    );
+    assert_eq!(
+        stops(text, Toolkit::VsCode),
+        (
+            vec![3, 4, 5, 10, 11, 14, 15, 18],
+            vec![16, 14, 11, 10, 6, 4, 3, 0]
+        )
+    );
+
+    // Qt stops at every punctuation character, LibreOffice only at the start of a run.
+    assert_eq!(stops("Wait?! ok", Toolkit::Qt).0, vec![4, 5, 7, 9]);
+    assert_eq!(stops("Wait?! ok", Toolkit::LibreOffice).0, vec![4, 7, 9]);
+}
+
+#[test]
+fn toolkit_profiles_only_allow_jumps_landing_on_the_model_stop() {
+    let buf = chars("don't stop-now, ok");
+    let model = ctrl_right(&buf, 0, is_word_char);
+    assert_eq!(model, 5);
+    assert!(WordNavProfile::Gtk.allows_ctrl_jump(&buf, 0, model));
+    assert!(!WordNavProfile::Qt.allows_ctrl_jump(&buf, 0, model));
+    assert!(!WordNavProfile::VsCode.allows_ctrl_jump(&buf, 0, model));
+    assert!(WordNavProfile::Qt.allows_ctrl_jump(&buf, 14, 11));
+
+    let buf = chars("first\nsecond");
+    assert!(!WordNavProfile::Gtk.allows_ctrl_jump(&buf, 5, 12));
+}
+
+#[test]
+fn plans_for_every_profile_type_the_text() {
+    let text = "Revisions happen mid-sentence, don't they? Editors disagree on where words end.";
+    for profile in [
+        WordNavProfile::Chrome,
+        WordNavProfile::Compatible,
+        WordNavProfile::CompatibleHomeEnd,
+        WordNavProfile::Gtk,
+        WordNavProfile::Qt,
+        WordNavProfile::LibreOffice,
+        WordNavProfile::VsCode,
+        WordNavProfile::Custom(CustomWordNav::default()),
+    ] {
+        let cfg = PlannerConfig {
+            word_nav_profile: profile.clone(),
+            error_rate_per_word: 0.2,
+            immediate_fix_rate: 0.0,
+            ..Default::default()
+        };
+        let plan = generate_plan(text, cfg, &mut StdRng::seed_from_u64(11)).unwrap();
+        assert_eq!(simulate_typed_text(&plan).unwrap(), text, "{profile:?}");
+        assert_eq!(
+            plan.config.word_nav_profile.as_deref(),
+            Some(profile.name()),
+            "{profile:?}"
+        );
+    }
+}
+
+#[test]
+fn custom_profiles_stop_by_their_own_rules() {
+    let text = "don't stop-now, ok";
+    let hyphenated = CustomRules {
+        word_chars: WordChars {
+            apostrophes: true,
+            hyphens: true,
+            ..Default::default()
+        },
+        punctuation_runs: false,
+    };
+    assert_eq!(
+        stops(text, Toolkit::Custom(hyphenated)),
+        (vec![5, 14, 18], vec![16, 6, 0])
+    );
+    assert_eq!(
+        stops(text, Toolkit::Custom(CustomRules::default())),
+        (vec![3, 5, 10, 14, 18], vec![16, 11, 6, 4, 0])
+    );
+
+    let buf = chars("first line\nsecond");
+    let custom = CustomWordNav {
+        rules: CustomRules {
+            word_chars: WordChars {
+                apostrophes: true,
+                ..Default::default()
+            },
+            punctuation_runs: true,
+        },
+        ..Default::default()
+    };
+    assert!(!WordNavProfile::Custom(custom.clone()).allows_ctrl_jump(&buf, 11, 6));
+    let crossing = CustomWordNav {
+        crosses_newlines: true,
+        ..custom
+    };
+    assert!(WordNavProfile::Custom(crossing).allows_ctrl_jump(&buf, 11, 6));
+}
+
+#[test]
+fn custom_min_jump_limits_ctrl_navigation() {
+    let text = "Revisions happen mid-sentence, don't they? Editors disagree on where words end.";
+    let ctrl_presses = |min_jump| {
+        let cfg = PlannerConfig {
+            word_nav_profile: WordNavProfile::Custom(CustomWordNav {
+                min_jump,
+                ..Default::default()
+            }),
+            error_rate_per_word: 0.3,
+            immediate_fix_rate: 0.0,
+            ..Default::default()
+        };
+        let plan = generate_plan(text, cfg, &mut StdRng::seed_from_u64(3)).unwrap();
+        assert_eq!(simulate_typed_text(&plan).unwrap(), text);
+        plan.actions
+            .iter()
+            .filter(|action| {
+                matches!(
+                    action,
+                    Action::Key {
+                        keycode: KEY_LEFTCTRL,
+                        state: KeyState::Pressed
+                    }
+                )
+            })
+            .count()
+    };
+    assert!(ctrl_presses(1) > 0);
+    assert_eq!(ctrl_presses(usize::MAX), 0);
+}
+
+#[test]
+fn home_end_profile_jumps_to_nearby_line_edges() {
+    let buf = chars("ab cd ef gh\nij kl mn op");
+    let profile = WordNavProfile::CompatibleHomeEnd;
+    // Home: targets near the start of the line or on earlier lines.
+    assert_eq!(profile.line_edge_jump(&buf, 23, 13), Some(12));
+    assert_eq!(profile.line_edge_jump(&buf, 23, 20), None);
+    assert_eq!(profile.line_edge_jump(&buf, 23, 5), Some(12));
+    assert_eq!(profile.line_edge_jump(&buf, 12, 5), None);
+    // End: targets near the end of the line or on later lines.
+    assert_eq!(profile.line_edge_jump(&buf, 0, 9), Some(11));
+    assert_eq!(profile.line_edge_jump(&buf, 0, 15), Some(11));
+    assert_eq!(profile.line_edge_jump(&buf, 0, 3), None);
+
+    assert_eq!(
+        WordNavProfile::Compatible.line_edge_jump(&buf, 23, 13),
+        None
+    );
+    let long = chars(&format!("{}\nab", "word ".repeat(20)));
+    assert_eq!(profile.line_edge_jump(&long, 100, 2), None);
+}
+
+#[test]
+fn home_end_profile_plans_with_home_and_end() {
+    let text = "Short lines here.\nEach one is brief,\nso Home and End\nare safe to press.";
+    let cfg = PlannerConfig {
+        word_nav_profile: WordNavProfile::CompatibleHomeEnd,
+        error_rate_per_word: 0.4,
+        immediate_fix_rate: 0.0,
+        ..Default::default()
+    };
+    let plan = generate_plan(text, cfg, &mut StdRng::seed_from_u64(5)).unwrap();
+    assert_eq!(simulate_typed_text(&plan).unwrap(), text);
+    assert!(plan.actions.iter().any(|action| matches!(
+        action,
+        Action::Key {
+            keycode: KEY_HOME | KEY_END,
+            state: KeyState::Pressed
+        }
+    )));
+
+    let analysis = analyze(&plan, &AnalysisOptions::default()).unwrap();
+    assert!(analysis.navigation.line_jumps > 0);
+}